@@ -33,6 +33,7 @@ if ($Verbose) {
 $picky_url = "http://127.0.0.1:12345"
 $picky_realm = "WaykDen"
 $picky_authority = "${picky_realm} Authority"
+$picky_api_key = "secret-api-key"
 
 $picky_provisioner_public_key = "-----BEGIN PUBLIC KEY-----
 MIIBIjANBgkqhkiG9w0BAQEFAAOCAQ8AMIIBCgKCAQEA6CbtUnGihZ7UIYfc/m94
@@ -112,11 +113,11 @@ Describe 'picky-server REST API tests' {
             & 'cargo' 'build' '--manifest-path' $location '--quiet'
 
             $pickyCmd = {
-                param($script_root, $picky_realm, $picky_provisioner_public_key, $picky_backend, $SavePickyCertificatesString, $location)
+                param($script_root, $picky_realm, $picky_provisioner_public_key, $picky_api_key, $picky_backend, $SavePickyCertificatesString, $location)
                 . "$script_root/Private/RunPicky.ps1"
-                RunPicky $picky_realm $picky_provisioner_public_key $picky_backend $SavePickyCertificatesString $location
+                RunPicky $picky_realm $picky_provisioner_public_key $picky_api_key $picky_backend $SavePickyCertificatesString $location
             }
-            Start-Job -Name "picky-server" -ScriptBlock $pickyCmd -ArgumentList $PSScriptRoot, $picky_realm, $picky_provisioner_public_key, $picky_backend, $SavePickyCertificatesString, $location
+            Start-Job -Name "picky-server" -ScriptBlock $pickyCmd -ArgumentList $PSScriptRoot, $picky_realm, $picky_provisioner_public_key, $picky_api_key, $picky_backend, $SavePickyCertificatesString, $location
         } else {
             & 'docker' 'stop' 'picky-server'
             & 'docker' 'rm' 'picky-server'
@@ -124,6 +125,7 @@ Describe 'picky-server REST API tests' {
                 '--mount' "source=pickyvolume,target=$currentPath/database/" `
                 '-e' "PICKY_REALM=$picky_realm" `
                 '-e' "PICKY_PROVISIONER_PUBLIC_KEY=$picky_provisioner_public_key" `
+                '-e' "PICKY_API_KEY=$picky_api_key" `
                 '-e' "PICKY_BACKEND=$picky_backend" `
                 '-e' "PICKY_DATABASE_URL=$picky_database_url" `
                 '-e' "PICKY_SAVE_CERTIFICATE=$SavePickyCertificatesString" `
@@ -190,6 +192,7 @@ Describe 'picky-server REST API tests' {
 
         $headers = @{
             "Authorization" = "Bearer $bearerToken"
+            "Picky-Api-Key" = $picky_api_key
             "Accept" = 'application/x-pem-file'
         }
 
@@ -213,6 +216,7 @@ Describe 'picky-server REST API tests' {
 
         $headers = @{
             "Authorization" = "Bearer $bearerToken"
+            "Picky-Api-Key" = $picky_api_key
         }
 
         $response = Invoke-WebRequest `
@@ -230,6 +234,7 @@ Describe 'picky-server REST API tests' {
 
         $headers = @{
             "Authorization" = "Bearer $bearerToken"
+            "Picky-Api-Key" = $picky_api_key
         }
 
         $response = Invoke-WebRequest `
@@ -247,6 +252,7 @@ Describe 'picky-server REST API tests' {
 
         $headers = @{
             "Authorization" = "Bearer $bearerToken"
+            "Picky-Api-Key" = $picky_api_key
         }
 
         $response = try {
@@ -259,7 +265,7 @@ Describe 'picky-server REST API tests' {
             $_.Exception.Response
         }
 
-        $response.StatusCode | Should -Be 400
+        $response.StatusCode | Should -Be 415
     }
 
     function SignCertAndGetDer {
@@ -268,6 +274,7 @@ Describe 'picky-server REST API tests' {
 
         $headers = @{
             "Authorization" = "Bearer $bearerToken"
+            "Picky-Api-Key" = $picky_api_key
         }
         $response = Invoke-WebRequest `
             -Uri $picky_url/sign/ -Method POST `
@@ -386,6 +393,7 @@ Describe 'picky-server REST API tests' {
         $csr_base64 = [Convert]::ToBase64String($csr_der)
         $headers = @{
             "Authorization" = "Bearer $bearerToken"
+            "Picky-Api-Key" = $picky_api_key
             "Accept" = "application/x-pem-file"
         }
         $response = Invoke-WebRequest `
@@ -397,6 +405,7 @@ Describe 'picky-server REST API tests' {
         $signed_cert_base64 = [Convert]::ToBase64String($signed_cert_der)
 
         $postCert = Invoke-RestMethod -Uri $picky_url/cert/ -Method POST `
+            -Headers @{ "Picky-Api-Key" = $picky_api_key } `
             -ContentType 'application/pkix-cert-base64' `
             -Body $signed_cert_base64
         $postCert | Should -Not -Be $null
@@ -407,6 +416,7 @@ Describe 'picky-server REST API tests' {
         $csr_base64 = [Convert]::ToBase64String($csr_der)
         $headers = @{
             "Authorization" = "Bearer $bearerToken"
+            "Picky-Api-Key" = $picky_api_key
             "Accept" = "application/pkix-cert-base64"
         }
         $signed_cert_base64 = Invoke-RestMethod -Uri $picky_url/sign/ -Method POST `
@@ -420,11 +430,55 @@ Describe 'picky-server REST API tests' {
             certificate = $signed_cert_pem
         } | ConvertTo-Json
         $postCert = Invoke-RestMethod -Uri $picky_url/cert/ -Method POST `
+            -Headers @{ "Picky-Api-Key" = $picky_api_key } `
             -ContentType 'application/json' `
             -Body $json
         $postCert | Should -Not -Be $null
     }
 
+    function SignCertAndGetBase64 {
+        $csr_der = GenerateCsrDer "CN=test.${picky_realm}"
+        $csr_base64 = [Convert]::ToBase64String($csr_der)
+        $headers = @{
+            "Authorization" = "Bearer $bearerToken"
+            "Picky-Api-Key" = $picky_api_key
+            "Accept" = "application/pkix-cert-base64"
+        }
+        return Invoke-RestMethod -Uri $picky_url/sign/ -Method POST `
+            -ContentType 'application/pkcs10-base64' `
+            -Headers $headers `
+            -Body $csr_base64
+    }
+
+    function PostCertAndGetResponse($headers) {
+        $signed_cert_base64 = SignCertAndGetBase64
+        try {
+            return Invoke-WebRequest -Uri $picky_url/cert/ -Method POST `
+                -ContentType 'application/pkix-cert-base64' `
+                -Headers $headers `
+                -Body $signed_cert_base64
+        } catch {
+            return $_.Exception.Response
+        }
+    }
+
+    It 'register certificate with API key in Authorization header' {
+        $response = PostCertAndGetResponse @{ "Authorization" = "Bearer $picky_api_key" }
+        $response.StatusCode | Should -Be 200
+    }
+
+    It 'register certificate without API key returns unauthorized' {
+        $response = PostCertAndGetResponse @{}
+        $response.StatusCode | Should -Be 401
+        $response.Headers.WwwAuthenticate | Should -Not -BeNullOrEmpty
+    }
+
+    It 'register certificate with wrong API key returns unauthorized' {
+        $response = PostCertAndGetResponse @{ "Picky-Api-Key" = "wrong-api-key" }
+        $response.StatusCode | Should -Be 401
+        $response.Headers.WwwAuthenticate | Should -Not -BeNullOrEmpty
+    }
+
     It 'register certificate not signed by a picky server CA' {
         $cert_pem = "-----BEGIN CERTIFICATE-----
 MIIDPzCCAiegAwIBAgIBATANBgkqhkiG9w0BAQUFADA7MQswCQYDVQQGEwJOTDER
@@ -449,6 +503,7 @@ Od8i323fM5dQS1qQpBjBc/5fPw==
 
         {
             Invoke-RestMethod -Uri $picky_url/cert/ -Method POST `
+                -Headers @{ "Picky-Api-Key" = $picky_api_key } `
                 -ContentType 'application/x-pem-file' `
                 -Body $cert_pem
         } | Should -Throw
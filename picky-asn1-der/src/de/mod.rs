@@ -80,9 +80,17 @@ impl<'de> Deserializer<'de> {
                 if self.raw_der {
                     self.raw_der = false;
                     let peeked = self.reader.peek_buffer()?;
+                    if peeked.len() < 2 {
+                        debug_log!("TRUNCATED DATA (couldn't read raw der header)");
+                        return Err(Asn1DerError::TruncatedData);
+                    }
                     let msg_len = Length::deserialized(&mut Cursor::new(&peeked.buffer()[1..]))?;
                     let header_len = Length::encoded_len(msg_len) + 1;
-                    (Tag::from(peeked.buffer()[0]), header_len + msg_len)
+                    let len = header_len.checked_add(msg_len).ok_or_else(|| {
+                        debug_log!("TRUNCATED DATA (raw der length overflow)");
+                        Asn1DerError::TruncatedData
+                    })?;
+                    (Tag::from(peeked.buffer()[0]), len)
                 } else {
                     let tag = Tag::from(self.reader.read_one()?);
                     let len = Length::deserialized(&mut self.reader)?;
@@ -462,7 +470,8 @@ impl<'de, 'a> serde::de::Deserializer<'de> for &'a mut Deserializer<'de> {
             }
         }
         self.h_next_object()?;
-        visitor.visit_byte_buf(self.buf.to_vec())
+        // hand over the internal buffer instead of copying it: `h_next_object` will allocate a new one if required
+        visitor.visit_byte_buf(std::mem::take(&mut self.buf))
     }
 
     fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
@@ -599,7 +608,12 @@ impl<'de, 'a> serde::de::Deserializer<'de> for &'a mut Deserializer<'de> {
             return Err(Asn1DerError::TruncatedData);
         }
         let payload_len = Length::deserialized(&mut Cursor::new(&peeked.buffer()[1..]))?;
-        let len = 1 + payload_len + Length::encoded_len(payload_len);
+        let len = payload_len
+            .checked_add(1 + Length::encoded_len(payload_len))
+            .ok_or_else(|| {
+                debug_log!("TRUNCATED DATA (choice length overflow)");
+                Asn1DerError::TruncatedData
+            })?;
         visitor.visit_seq(Sequence::deserialize_lazy(&mut self, len))
     }
 
@@ -618,6 +632,10 @@ impl<'de, 'a> serde::de::Deserializer<'de> for &'a mut Deserializer<'de> {
 
         // Read len and copy payload into `self.buf`
         let len = Length::deserialized(&mut self.reader)?;
+        if len > self.max_len {
+            debug_log!("TRUNCATED DATA (invalid len: found {}, max is {})", len, self.max_len);
+            return Err(Asn1DerError::TruncatedData);
+        }
         self.buf.resize(len, 0);
         self.reader.read_exact(&mut self.buf)?;
 
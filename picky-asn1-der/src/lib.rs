@@ -31,6 +31,9 @@
 //!
 //! Everything sequence-like combined out of this types is also supported out of the box.
 //!
+//! When serde types are overkill (skipping unmodeled elements, extracting the raw bytes of a
+//! nested structure…), `DerReader` and `DerWriter` give access to raw TLVs.
+//!
 //! ```rust
 //! use serde::{Serialize, Deserialize};
 //!
@@ -76,11 +79,13 @@ mod de;
 mod misc;
 mod raw_der;
 mod ser;
+mod tlv;
 
 pub use crate::{
     de::{from_bytes, from_reader, from_reader_with_max_len, Deserializer},
     raw_der::Asn1RawDer,
     ser::{to_byte_buf, to_bytes, to_vec, to_writer, Serializer},
+    tlv::{DerReader, DerWriter},
 };
 
 use std::{
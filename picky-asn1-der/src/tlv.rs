@@ -0,0 +1,190 @@
+use crate::{misc::Length, Asn1DerError, Result};
+use picky_asn1::tag::Tag;
+use std::io::Write;
+
+/// Low-level reader walking DER-encoded TLVs (tag, length, value) without serde.
+///
+/// Only the tag and length of each TLV are decoded: contents are handed over as raw slices
+/// borrowed from the input. Positions are offsets in the input the outermost reader was
+/// created from, nested readers included.
+///
+/// # Example
+/// ```
+/// use picky_asn1::tag::Tag;
+/// use picky_asn1_der::DerReader;
+///
+/// let der = [
+///     0x30, 0x08,
+///         0x0C, 0x03, 0x41, 0x62, 0x63,
+///         0x02, 0x01, 0x05,
+/// ];
+///
+/// let mut reader = DerReader::new(&der);
+/// let mut sequence = reader.enter_sequence().expect("sequence");
+/// assert!(reader.is_empty());
+///
+/// assert_eq!(sequence.peek_tag().expect("tag"), Tag::UTF8_STRING);
+/// assert_eq!(sequence.position(), 2);
+/// let (tag, tlv, contents) = sequence.read_tlv().expect("utf8 string");
+/// assert_eq!(tag, Tag::UTF8_STRING);
+/// assert_eq!(tlv, &der[2..7]);
+/// assert_eq!(contents, b"Abc");
+///
+/// assert_eq!(sequence.position(), 7);
+/// let (tag, _, contents) = sequence.read_tlv().expect("integer");
+/// assert_eq!(tag, Tag::INTEGER);
+/// assert_eq!(contents, [0x05]);
+/// assert!(sequence.is_empty());
+/// ```
+#[derive(Debug, Clone)]
+pub struct DerReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> DerReader<'a> {
+    /// Creates a reader over `data`, which may contain several concatenated TLVs
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    /// Offset of the next TLV
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Bytes that are left to read
+    pub fn remaining(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Returns `true` if there is nothing left to read
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Returns the tag of the next TLV without consuming it
+    pub fn peek_tag(&self) -> Result<Tag> {
+        match self.data.first() {
+            // high tag number form isn't supported
+            Some(tag) if tag & 0x1F == 0x1F => Err(Asn1DerError::UnsupportedValue),
+            Some(tag) => Ok(Tag::from(*tag)),
+            None => Err(Asn1DerError::TruncatedData),
+        }
+    }
+
+    /// Reads the next TLV
+    ///
+    /// Returns its tag, the whole TLV (header included) and its contents.
+    pub fn read_tlv(&mut self) -> Result<(Tag, &'a [u8], &'a [u8])> {
+        let tag = self.peek_tag()?;
+
+        let mut header = &self.data[1..];
+        if header.first() == Some(&0x80) {
+            // indefinite length is forbidden in DER
+            return Err(Asn1DerError::InvalidData);
+        }
+        let len = Length::deserialized(&mut header)?;
+        let header_len = self.data.len() - header.len();
+        let tlv_len = header_len.checked_add(len).ok_or(Asn1DerError::TruncatedData)?;
+        if tlv_len > self.data.len() {
+            return Err(Asn1DerError::TruncatedData);
+        }
+
+        let (tlv, rest) = self.data.split_at(tlv_len);
+        self.data = rest;
+        self.pos += tlv_len;
+
+        Ok((tag, tlv, &tlv[header_len..]))
+    }
+
+    /// Reads the next TLV, which must be a SEQUENCE, and returns a reader over its contents
+    pub fn enter_sequence(&mut self) -> Result<DerReader<'a>> {
+        if self.peek_tag()? != Tag::SEQUENCE {
+            return Err(Asn1DerError::InvalidData);
+        }
+
+        let (_, _, contents) = self.read_tlv()?;
+        Ok(DerReader {
+            data: contents,
+            pos: self.pos - contents.len(),
+        })
+    }
+}
+
+/// Low-level writer emitting DER-encoded TLVs into a buffer or any `io::Write` sink.
+///
+/// # Example
+/// ```
+/// use picky_asn1::tag::Tag;
+/// use picky_asn1_der::DerWriter;
+///
+/// let mut writer = DerWriter::new(Vec::new());
+/// writer
+///     .write_constructed(Tag::SEQUENCE, |sequence| {
+///         sequence.write_tlv(Tag::UTF8_STRING, b"Abc")?;
+///         sequence.write_tlv(Tag::INTEGER, &[0x05])?;
+///         Ok(())
+///     })
+///     .expect("sequence");
+/// assert_eq!(writer.written(), 10);
+///
+/// assert_eq!(
+///     writer.into_inner(),
+///     [
+///         0x30, 0x08,
+///             0x0C, 0x03, 0x41, 0x62, 0x63,
+///             0x02, 0x01, 0x05,
+///     ]
+/// );
+/// ```
+#[derive(Debug)]
+pub struct DerWriter<W: Write> {
+    writer: W,
+    written: usize,
+}
+
+impl<W: Write> DerWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer, written: 0 }
+    }
+
+    /// Amount of bytes written so far
+    pub fn written(&self) -> usize {
+        self.written
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Writes a TLV with the given tag and contents, returns the amount of bytes written
+    pub fn write_tlv(&mut self, tag: Tag, contents: &[u8]) -> Result<usize> {
+        self.writer.write_all(&[tag.number()])?;
+        let header_len = 1 + Length::serialize(contents.len(), &mut self.writer)?;
+        self.writer.write_all(contents)?;
+
+        let written = header_len + contents.len();
+        self.written += written;
+        Ok(written)
+    }
+
+    /// Writes already DER-encoded data as is (e.g. a TLV returned by `DerReader::read_tlv`)
+    pub fn write_raw(&mut self, der: &[u8]) -> Result<usize> {
+        self.writer.write_all(der)?;
+        self.written += der.len();
+        Ok(der.len())
+    }
+
+    /// Writes a constructed TLV (e.g. a SEQUENCE) whose contents are emitted by `f`
+    ///
+    /// Contents are buffered since their length must be known before writing the header.
+    pub fn write_constructed<F>(&mut self, tag: Tag, f: F) -> Result<usize>
+    where
+        F: FnOnce(&mut DerWriter<Vec<u8>>) -> Result<()>,
+    {
+        let mut contents = DerWriter::new(Vec::new());
+        f(&mut contents)?;
+        self.write_tlv(tag, &contents.into_inner())
+    }
+}
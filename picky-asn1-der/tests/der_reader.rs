@@ -0,0 +1,134 @@
+use picky_asn1::tag::Tag;
+use picky_asn1_der::{Asn1DerError, DerReader, DerWriter};
+
+const CERT_PEM: &str = include_str!("../../test_assets/intermediate_ca.crt");
+
+fn cert_der() -> Vec<u8> {
+    let base64: String = CERT_PEM.lines().filter(|line| !line.starts_with("-----")).collect();
+    base64::decode(&base64).expect("certificate base64")
+}
+
+#[test]
+fn locate_tbs_and_signature() {
+    let der = cert_der();
+    let mut reader = DerReader::new(&der);
+
+    let mut certificate = reader.enter_sequence().expect("certificate");
+    assert!(reader.is_empty());
+    assert_eq!(reader.position(), der.len());
+
+    // tbsCertificate
+    assert_eq!(certificate.position(), 4);
+    assert_eq!(certificate.peek_tag().expect("tbs tag"), Tag::SEQUENCE);
+    let (_, tbs, tbs_contents) = certificate.read_tlv().expect("tbs certificate");
+    assert_eq!(tbs, &der[4..559]);
+    assert_eq!(tbs_contents, &der[8..559]);
+
+    // signatureAlgorithm
+    assert_eq!(certificate.position(), 559);
+    let mut signature_algorithm = certificate.enter_sequence().expect("signature algorithm");
+    assert_eq!(signature_algorithm.position(), 561);
+    let (tag, _, _) = signature_algorithm.read_tlv().expect("algorithm oid");
+    assert_eq!(tag, Tag::OID);
+    let (tag, _, contents) = signature_algorithm.read_tlv().expect("algorithm parameters");
+    assert_eq!(tag, Tag::NULL);
+    assert!(contents.is_empty());
+    assert!(signature_algorithm.is_empty());
+
+    // signatureValue
+    assert_eq!(certificate.position(), 574);
+    let (tag, signature, signature_contents) = certificate.read_tlv().expect("signature value");
+    assert_eq!(tag, Tag::BIT_STRING);
+    assert_eq!(signature, &der[574..]);
+    assert_eq!(signature_contents.len(), 257);
+    assert_eq!(signature_contents[0], 0, "no unused bits");
+    assert!(certificate.is_empty());
+}
+
+#[test]
+fn walk_tbs_fields() {
+    let der = cert_der();
+    let mut reader = DerReader::new(&der);
+    let mut certificate = reader.enter_sequence().expect("certificate");
+    let mut tbs = certificate.enter_sequence().expect("tbs certificate");
+
+    let mut tags = Vec::new();
+    while !tbs.is_empty() {
+        let (tag, _, _) = tbs.read_tlv().expect("tbs field");
+        tags.push(tag);
+    }
+
+    assert_eq!(
+        tags,
+        [
+            Tag::APP_0,    // version
+            Tag::INTEGER,  // serialNumber
+            Tag::SEQUENCE, // signature
+            Tag::SEQUENCE, // issuer
+            Tag::SEQUENCE, // validity
+            Tag::SEQUENCE, // subject
+            Tag::SEQUENCE, // subjectPublicKeyInfo
+            Tag::APP_3,    // extensions
+        ]
+    );
+}
+
+#[test]
+fn rewrite_certificate() {
+    let der = cert_der();
+    let mut reader = DerReader::new(&der);
+    let mut certificate = reader.enter_sequence().expect("certificate");
+
+    let mut writer = DerWriter::new(Vec::new());
+    writer
+        .write_constructed(Tag::SEQUENCE, |sequence| {
+            while !certificate.is_empty() {
+                let (_, tlv, _) = certificate.read_tlv()?;
+                sequence.write_raw(tlv)?;
+            }
+            Ok(())
+        })
+        .expect("certificate");
+
+    assert_eq!(writer.written(), der.len());
+    assert_eq!(writer.into_inner(), der);
+}
+
+#[test]
+fn split_concatenated_objects() {
+    let mut writer = DerWriter::new(Vec::new());
+    writer.write_tlv(Tag::INTEGER, &[0x01]).expect("first");
+    writer.write_tlv(Tag::OCTET_STRING, &[0xAB; 200]).expect("second");
+    let der = writer.into_inner();
+    assert_eq!(&der[3..6], [0x04, 0x81, 0xC8]);
+
+    let mut reader = DerReader::new(&der);
+    let (tag, _, contents) = reader.read_tlv().expect("first");
+    assert_eq!((tag, contents), (Tag::INTEGER, &[0x01][..]));
+    assert_eq!(reader.position(), 3);
+    let (tag, _, contents) = reader.read_tlv().expect("second");
+    assert_eq!((tag, contents), (Tag::OCTET_STRING, &[0xAB; 200][..]));
+    assert!(reader.is_empty());
+}
+
+#[test]
+fn malformed_input() {
+    let reader = DerReader::new(&[]);
+    assert!(matches!(reader.peek_tag(), Err(Asn1DerError::TruncatedData)));
+
+    let mut reader = DerReader::new(&[0x04, 0x03, 0x01]);
+    assert!(matches!(reader.read_tlv(), Err(Asn1DerError::TruncatedData)));
+    assert_eq!(reader.position(), 0, "reader is left untouched on error");
+
+    let mut reader = DerReader::new(&[0x04]);
+    assert!(matches!(reader.read_tlv(), Err(Asn1DerError::TruncatedData)));
+
+    let mut reader = DerReader::new(&[0x30, 0x80, 0x00, 0x00]);
+    assert!(matches!(reader.read_tlv(), Err(Asn1DerError::InvalidData)));
+
+    let reader = DerReader::new(&[0x1F, 0x01, 0x00]);
+    assert!(matches!(reader.peek_tag(), Err(Asn1DerError::UnsupportedValue)));
+
+    let mut reader = DerReader::new(&[0x02, 0x01, 0x00]);
+    assert!(matches!(reader.enter_sequence(), Err(Asn1DerError::InvalidData)));
+}
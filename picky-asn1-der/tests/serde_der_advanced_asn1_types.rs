@@ -52,6 +52,25 @@ fn bit_string() {
     check(&bit_string_buffer, bit_string);
 }
 
+#[test]
+fn malformed_bit_string() {
+    // empty content (missing unused bits header)
+    picky_asn1_der::from_bytes::<BitStringAsn1>(&[0x03, 0x00]).unwrap_err();
+    // unused bits without payload
+    picky_asn1_der::from_bytes::<BitStringAsn1>(&[0x03, 0x01, 0x07]).unwrap_err();
+    // more than 7 unused bits
+    picky_asn1_der::from_bytes::<BitStringAsn1>(&[0x03, 0x02, 0x08, 0xff]).unwrap_err();
+}
+
+#[test]
+fn raw_der_length_overflow() {
+    let buffer = [0x30, 0x88, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff];
+    match picky_asn1_der::from_bytes::<picky_asn1_der::Asn1RawDer>(&buffer) {
+        Err(picky_asn1_der::Asn1DerError::TruncatedData) => {}
+        result => panic!("length overflow => invalid result: {:?}", result),
+    }
+}
+
 #[test]
 fn encapsulated_types() {
     {
@@ -0,0 +1,27 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use picky_asn1_x509::Certificate;
+
+const RSA_4096_CERT_PEM: &str = include_str!("../../test_assets/rsa_4096_self_signed.crt");
+
+fn cert_der() -> Vec<u8> {
+    let base64_body = RSA_4096_CERT_PEM
+        .lines()
+        .filter(|line| !line.starts_with("-----"))
+        .collect::<String>();
+    base64::decode(&base64_body).expect("couldn't decode certificate base64")
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let der = cert_der();
+    let cert: Certificate = picky_asn1_der::from_bytes(&der).expect("couldn't parse certificate");
+
+    c.bench_function("parse 4096-bit key certificate", |b| {
+        b.iter(|| picky_asn1_der::from_bytes::<Certificate>(black_box(&der)).unwrap())
+    });
+    c.bench_function("encode 4096-bit key certificate", |b| {
+        b.iter(|| picky_asn1_der::to_vec(black_box(&cert)).unwrap())
+    });
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,43 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use picky_asn1::wrapper::{BitStringAsn1, IntegerAsn1, ObjectIdentifierAsn1, OctetStringAsn1};
+use picky_asn1_der::Asn1RawDer;
+use picky_asn1_x509::{
+    AlgorithmIdentifier, Certificate, CertificationRequest, Extensions, GeneralNames, Name, PrivateKeyInfo,
+    SubjectPublicKeyInfo, Time, Validity,
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+fn check<T: DeserializeOwned + Serialize>(data: &[u8]) -> Option<T> {
+    let value = picky_asn1_der::from_bytes::<T>(data).ok()?;
+    let _ = picky_asn1_der::to_vec(&value);
+    Some(value)
+}
+
+fuzz_target!(|data: &[u8]| {
+    // primitives
+    let _ = check::<Asn1RawDer>(data);
+    let _ = check::<IntegerAsn1>(data);
+    let _ = check::<OctetStringAsn1>(data);
+    let _ = check::<ObjectIdentifierAsn1>(data);
+    if let Some(bit_string) = check::<BitStringAsn1>(data) {
+        let _ = bit_string.0.payload_view();
+        let _ = bit_string.0.get_num_bits();
+    }
+
+    // x509 structures
+    let _ = check::<Time>(data);
+    let _ = check::<Validity>(data);
+    let _ = check::<Name>(data);
+    let _ = check::<GeneralNames>(data);
+    let _ = check::<AlgorithmIdentifier>(data);
+    let _ = check::<Extensions>(data);
+    let _ = check::<SubjectPublicKeyInfo>(data);
+    let _ = check::<PrivateKeyInfo>(data);
+    if let Some(csr) = check::<CertificationRequest>(data) {
+        let _ = csr.signature.0.payload_view();
+    }
+    if let Some(cert) = check::<Certificate>(data) {
+        let _ = cert.signature_value.0.payload_view();
+    }
+});
@@ -2,9 +2,12 @@ use crate::oids;
 use oid::ObjectIdentifier;
 use picky_asn1::{
     tag::{Tag, TagPeeker},
-    wrapper::{IntegerAsn1, ObjectIdentifierAsn1, OctetStringAsn1},
+    wrapper::{
+        ApplicationTag0, ApplicationTag1, ApplicationTag2, ApplicationTag3, IntegerAsn1, ObjectIdentifierAsn1,
+        OctetStringAsn1,
+    },
 };
-use serde::{de, ser};
+use serde::{de, ser, Deserialize, Serialize};
 use std::fmt;
 
 #[derive(Debug, PartialEq, Clone)]
@@ -75,6 +78,13 @@ impl AlgorithmIdentifier {
         }
     }
 
+    pub fn new_rsassa_pss(params: RsassaPssParams) -> Self {
+        Self {
+            algorithm: oids::rsassa_pss().into(),
+            parameters: AlgorithmIdentifierParameters::RsassaPss(Box::new(params)),
+        }
+    }
+
     pub fn new_rsa_encryption() -> Self {
         Self {
             algorithm: oids::rsa_encryption().into(),
@@ -82,6 +92,13 @@ impl AlgorithmIdentifier {
         }
     }
 
+    pub fn new_ecdsa_with_sha512() -> Self {
+        Self {
+            algorithm: oids::ecdsa_with_sha512().into(),
+            parameters: AlgorithmIdentifierParameters::None,
+        }
+    }
+
     pub fn new_ecdsa_with_sha384() -> Self {
         Self {
             algorithm: oids::ecdsa_with_sha384().into(),
@@ -151,6 +168,9 @@ impl ser::Serialize for AlgorithmIdentifier {
             AlgorithmIdentifierParameters::AES(aes_params) => {
                 seq.serialize_element(aes_params)?;
             }
+            AlgorithmIdentifierParameters::RsassaPss(pss_params) => {
+                seq.serialize_element(pss_params)?;
+            }
         }
         seq.end()
     }
@@ -177,8 +197,12 @@ impl<'de> de::Deserialize<'de> for AlgorithmIdentifier {
                 let oid: ObjectIdentifierAsn1 = seq_next_element!(seq, AlgorithmIdentifier, "algorithm oid");
 
                 let args = match Into::<String>::into(&oid.0).as_str() {
-                    oids::RSA_ENCRYPTION
-                    | oids::SHA1_WITH_RSA_ENCRYPTION
+                    // rsaEncryption parameters must be NULL, but some encoders omit them
+                    oids::RSA_ENCRYPTION => match seq.next_element::<()>()? {
+                        Some(()) => AlgorithmIdentifierParameters::Null,
+                        None => AlgorithmIdentifierParameters::None,
+                    },
+                    oids::SHA1_WITH_RSA_ENCRYPTION
                     | oids::SHA224_WITH_RSA_ENCRYPTION
                     | oids::SHA256_WITH_RSA_ENCRYPTION
                     | oids::SHA384_WITH_RSA_ENCRYPTION
@@ -186,7 +210,14 @@ impl<'de> de::Deserialize<'de> for AlgorithmIdentifier {
                         seq_next_element!(seq, AlgorithmIdentifier, "algorithm identifier parameters (null)");
                         AlgorithmIdentifierParameters::Null
                     }
-                    oids::ECDSA_WITH_SHA384 | oids::ECDSA_WITH_SHA256 => AlgorithmIdentifierParameters::None,
+                    oids::RSASSA_PSS => AlgorithmIdentifierParameters::RsassaPss(seq_next_element!(
+                        seq,
+                        AlgorithmIdentifier,
+                        "RSASSA-PSS parameters"
+                    )),
+                    oids::ECDSA_WITH_SHA256 | oids::ECDSA_WITH_SHA384 | oids::ECDSA_WITH_SHA512 => {
+                        AlgorithmIdentifierParameters::None
+                    }
                     oids::EC_PUBLIC_KEY => AlgorithmIdentifierParameters::EC(seq_next_element!(
                         seq,
                         AlgorithmIdentifier,
@@ -196,6 +227,11 @@ impl<'de> de::Deserialize<'de> for AlgorithmIdentifier {
                     x if x.starts_with("2.16.840.1.101.3.4.1.") => AlgorithmIdentifierParameters::AES(
                         seq_next_element!(seq, AlgorithmIdentifier, "aes algorithm identifier"),
                     ),
+                    // SHA-1 parameters are either NULL or absent
+                    oids::SHA1 => match seq.next_element::<()>()? {
+                        Some(()) => AlgorithmIdentifierParameters::Null,
+                        None => AlgorithmIdentifierParameters::None,
+                    },
                     // SHA
                     x if x.starts_with("2.16.840.1.101.3.4.2.") => {
                         seq_next_element!(seq, AlgorithmIdentifier, "sha algorithm identifier");
@@ -227,6 +263,155 @@ pub enum AlgorithmIdentifierParameters {
     Null,
     AES(AESParameters),
     EC(ECParameters),
+    RsassaPss(Box<RsassaPssParams>),
+}
+
+/// [RFC 4055 #3.1](https://tools.ietf.org/html/rfc4055#section-3.1)
+///
+/// ```not_rust
+/// RSASSA-PSS-params  ::=  SEQUENCE  {
+///     hashAlgorithm      [0] HashAlgorithm DEFAULT sha1Identifier,
+///     maskGenAlgorithm   [1] MaskGenAlgorithm DEFAULT mgf1SHA1Identifier,
+///     saltLength         [2] INTEGER DEFAULT 20,
+///     trailerField       [3] INTEGER DEFAULT 1  }
+/// ```
+///
+/// Fields holding their default value are omitted when encoding.
+#[derive(Debug, PartialEq, Clone)]
+pub struct RsassaPssParams {
+    pub hash_algorithm: AlgorithmIdentifier,
+    pub mask_gen_algorithm: MaskGenAlgorithm,
+    pub salt_length: u32,
+    pub trailer_field: u8,
+}
+
+impl RsassaPssParams {
+    /// Uses `variant` for both the message digest and MGF1
+    pub fn new(variant: SHAVariant, salt_length: u32) -> Self {
+        Self {
+            hash_algorithm: AlgorithmIdentifier::new_sha(variant),
+            mask_gen_algorithm: MaskGenAlgorithm::new_mgf1(variant),
+            salt_length,
+            trailer_field: 1,
+        }
+    }
+}
+
+impl Default for RsassaPssParams {
+    fn default() -> Self {
+        Self::new(SHAVariant::SHA1, 20)
+    }
+}
+
+impl ser::Serialize for RsassaPssParams {
+    fn serialize<S>(&self, serializer: S) -> Result<<S as ser::Serializer>::Ok, <S as ser::Serializer>::Error>
+    where
+        S: ser::Serializer,
+    {
+        use ser::SerializeSeq;
+
+        let default = Self::default();
+        let mut seq = serializer.serialize_seq(Some(4))?;
+        if self.hash_algorithm != default.hash_algorithm {
+            seq.serialize_element(&ApplicationTag0(self.hash_algorithm.clone()))?;
+        }
+        if self.mask_gen_algorithm != default.mask_gen_algorithm {
+            seq.serialize_element(&ApplicationTag1(self.mask_gen_algorithm.clone()))?;
+        }
+        if self.salt_length != default.salt_length {
+            seq.serialize_element(&ApplicationTag2(self.salt_length))?;
+        }
+        if self.trailer_field != default.trailer_field {
+            seq.serialize_element(&ApplicationTag3(self.trailer_field))?;
+        }
+        seq.end()
+    }
+}
+
+impl<'de> de::Deserialize<'de> for RsassaPssParams {
+    fn deserialize<D>(deserializer: D) -> Result<Self, <D as de::Deserializer<'de>>::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        struct Visitor;
+
+        impl<'de> de::Visitor<'de> for Visitor {
+            type Value = RsassaPssParams;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a valid DER-encoded RSASSA-PSS-params")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: de::SeqAccess<'de>,
+            {
+                let mut params = RsassaPssParams::default();
+
+                let mut next_tag = seq.next_element::<TagPeeker>()?.map(|peeker| peeker.next_tag);
+
+                if next_tag == Some(Tag::APP_0) {
+                    let ApplicationTag0(hash_algorithm) = seq_next_element!(seq, RsassaPssParams, "hash algorithm");
+                    params.hash_algorithm = hash_algorithm;
+                    next_tag = seq.next_element::<TagPeeker>()?.map(|peeker| peeker.next_tag);
+                }
+
+                if next_tag == Some(Tag::APP_1) {
+                    let ApplicationTag1(mask_gen_algorithm) =
+                        seq_next_element!(seq, RsassaPssParams, "mask generation algorithm");
+                    params.mask_gen_algorithm = mask_gen_algorithm;
+                    next_tag = seq.next_element::<TagPeeker>()?.map(|peeker| peeker.next_tag);
+                }
+
+                if next_tag == Some(Tag::APP_2) {
+                    let ApplicationTag2(salt_length) = seq_next_element!(seq, RsassaPssParams, "salt length");
+                    params.salt_length = salt_length;
+                    next_tag = seq.next_element::<TagPeeker>()?.map(|peeker| peeker.next_tag);
+                }
+
+                match next_tag {
+                    Some(Tag::APP_3) => {
+                        let ApplicationTag3(trailer_field) = seq_next_element!(seq, RsassaPssParams, "trailer field");
+                        params.trailer_field = trailer_field;
+                    }
+                    Some(_) => {
+                        return Err(serde_invalid_value!(
+                            RsassaPssParams,
+                            "unexpected trailing element",
+                            "trailer field"
+                        ))
+                    }
+                    None => {}
+                }
+
+                Ok(params)
+            }
+        }
+
+        deserializer.deserialize_seq(Visitor)
+    }
+}
+
+/// [RFC 4055 #2.2](https://tools.ietf.org/html/rfc4055#section-2.2)
+///
+/// ```not_rust
+/// MaskGenAlgorithm  ::=  AlgorithmIdentifier
+/// ```
+///
+/// Only MGF1 is defined, its parameter is the hash algorithm used by the mask generation function.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct MaskGenAlgorithm {
+    pub algorithm: ObjectIdentifierAsn1,
+    pub hash_algorithm: AlgorithmIdentifier,
+}
+
+impl MaskGenAlgorithm {
+    pub fn new_mgf1(variant: SHAVariant) -> Self {
+        Self {
+            algorithm: oids::mgf1().into(),
+            hash_algorithm: AlgorithmIdentifier::new_sha(variant),
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -441,6 +626,7 @@ impl<'de> de::Deserialize<'de> for AESParameters {
 #[derive(Clone, Copy, PartialEq, Debug)]
 #[allow(non_camel_case_types)] // 'SHA2_512_224' is clearer than 'SHA2512224' or 'Sha2512224' imo
 pub enum SHAVariant {
+    SHA1,
     SHA2_224,
     SHA2_256,
     SHA2_384,
@@ -458,6 +644,7 @@ pub enum SHAVariant {
 impl From<SHAVariant> for ObjectIdentifierAsn1 {
     fn from(variant: SHAVariant) -> Self {
         match variant {
+            SHAVariant::SHA1 => oids::sha1().into(),
             SHAVariant::SHA2_224 => oids::sha224().into(),
             SHAVariant::SHA2_256 => oids::sha256().into(),
             SHAVariant::SHA2_384 => oids::sha384().into(),
@@ -519,6 +706,30 @@ mod tests {
         check_serde!(sha: AlgorithmIdentifier in expected);
     }
 
+    #[test]
+    fn sha1() {
+        let expected = [48, 9, 6, 5, 43, 14, 3, 2, 26, 5, 0];
+        let sha = AlgorithmIdentifier::new_sha(SHAVariant::SHA1);
+        check_serde!(sha: AlgorithmIdentifier in expected);
+
+        let absent_params = [48, 7, 6, 5, 43, 14, 3, 2, 26];
+        let sha: AlgorithmIdentifier = picky_asn1_der::from_bytes(&absent_params).expect("deserialization");
+        assert_eq!(sha.parameters(), &AlgorithmIdentifierParameters::None);
+        assert!(sha.is_a(oids::sha1()));
+    }
+
+    #[test]
+    fn rsa_encryption() {
+        let expected = [48, 13, 6, 9, 42, 134, 72, 134, 247, 13, 1, 1, 1, 5, 0];
+        let rsa = AlgorithmIdentifier::new_rsa_encryption();
+        check_serde!(rsa: AlgorithmIdentifier in expected);
+
+        let absent_params = [48, 11, 6, 9, 42, 134, 72, 134, 247, 13, 1, 1, 1];
+        let rsa: AlgorithmIdentifier = picky_asn1_der::from_bytes(&absent_params).expect("deserialization");
+        assert_eq!(rsa.parameters(), &AlgorithmIdentifierParameters::None);
+        assert!(rsa.is_a(oids::rsa_encryption()));
+    }
+
     #[test]
     fn ec_params() {
         let expected = [
@@ -528,4 +739,30 @@ mod tests {
             AlgorithmIdentifier::new_elliptic_curve(ECParameters::NamedCurve(oids::ecdsa_with_sha256().into()));
         check_serde!(ec_params: AlgorithmIdentifier in expected);
     }
+
+    #[test]
+    fn rsassa_pss_params() {
+        // as encoded by OpenSSL
+        let expected = [
+            48, 65, 6, 9, 42, 134, 72, 134, 247, 13, 1, 1, 10, 48, 52, 160, 15, 48, 13, 6, 9, 96, 134, 72, 1, 101, 3,
+            4, 2, 1, 5, 0, 161, 28, 48, 26, 6, 9, 42, 134, 72, 134, 247, 13, 1, 1, 8, 48, 13, 6, 9, 96, 134, 72, 1,
+            101, 3, 4, 2, 1, 5, 0, 162, 3, 2, 1, 32,
+        ];
+        let pss = AlgorithmIdentifier::new_rsassa_pss(RsassaPssParams::new(SHAVariant::SHA2_256, 32));
+        check_serde!(pss: AlgorithmIdentifier in expected);
+    }
+
+    #[test]
+    fn rsassa_pss_default_params() {
+        let expected = [48, 13, 6, 9, 42, 134, 72, 134, 247, 13, 1, 1, 10, 48, 0];
+        let pss = AlgorithmIdentifier::new_rsassa_pss(RsassaPssParams::default());
+        check_serde!(pss: AlgorithmIdentifier in expected);
+
+        let params = RsassaPssParams {
+            trailer_field: 2,
+            ..RsassaPssParams::default()
+        };
+        let expected = [48, 5, 163, 3, 2, 1, 2];
+        check_serde!(params: RsassaPssParams in expected);
+    }
 }
@@ -1,9 +1,11 @@
 use crate::{oids, DirectoryString};
 use picky_asn1::wrapper::{IA5StringAsn1, ObjectIdentifierAsn1};
+use picky_asn1_der::Asn1RawDer;
 use serde::{de, ser};
 use std::fmt;
 
 #[derive(Debug, PartialEq, Clone)]
+#[non_exhaustive]
 pub enum AttributeTypeAndValueParameters {
     CommonName(DirectoryString),
     Surname(DirectoryString),
@@ -15,6 +17,8 @@ pub enum AttributeTypeAndValueParameters {
     OrganizationName(DirectoryString),
     OrganizationalUnitName(DirectoryString),
     EmailAddress(IA5StringAsn1),
+    /// Raw DER-encoded value (unsupported attribute types are preserved)
+    Custom(Asn1RawDer),
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -134,6 +138,9 @@ impl ser::Serialize for AttributeTypeAndValue {
             AttributeTypeAndValueParameters::EmailAddress(name) => {
                 seq.serialize_element(name)?;
             }
+            AttributeTypeAndValueParameters::Custom(der) => {
+                seq.serialize_element(der)?;
+            }
         }
         seq.end()
     }
@@ -205,13 +212,11 @@ impl<'de> de::Deserialize<'de> for AttributeTypeAndValue {
                             AttributeTypeAndValue,
                             "at email address"
                         )),
-                        _ => {
-                            return Err(serde_invalid_value!(
-                                AttributeTypeAndValue,
-                                "unsupported type (unknown oid)",
-                                "a supported type"
-                            ));
-                        }
+                        _ => AttributeTypeAndValueParameters::Custom(seq_next_element!(
+                            seq,
+                            AttributeTypeAndValue,
+                            "at value"
+                        )),
                     };
 
                 Ok(AttributeTypeAndValue { ty, value })
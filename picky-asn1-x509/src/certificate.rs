@@ -278,4 +278,27 @@ mod tests {
             "b45ae4a5b3ded252f6b9d5a6950feb3ebcc7fdff"
         );
     }
+
+    #[test]
+    fn rsa_4096_certificate_round_trip() {
+        use crate::PublicKey;
+
+        let pem = include_str!("../../test_assets/rsa_4096_self_signed.crt");
+        let encoded = base64::decode(
+            pem.lines()
+                .filter(|line| !line.starts_with("-----"))
+                .collect::<String>(),
+        )
+        .expect("invalid base64");
+
+        let cert: Certificate = picky_asn1_der::from_bytes(&encoded).expect("4096-bit key certificate");
+        match &cert.tbs_certificate.subject_public_key_info.subject_public_key {
+            PublicKey::RSA(rsa) => assert_eq!((rsa.0).modulus.as_unsigned_bytes_be().len(), 512),
+            _ => panic!("expected RSA public key"),
+        }
+        assert_eq!(cert.signature_value.0.payload_view().len(), 512);
+
+        let reencoded = picky_asn1_der::to_vec(&cert).expect("serialization failed");
+        pretty_assertions::assert_eq!(reencoded, encoded);
+    }
 }
@@ -0,0 +1,423 @@
+use crate::{AlgorithmIdentifier, Extension, ExtensionView, Extensions, Name, Time, Version};
+use picky_asn1::{
+    tag::{Tag, TagPeeker},
+    wrapper::{ApplicationTag0, Asn1SequenceOf, BitStringAsn1, IntegerAsn1},
+};
+use picky_asn1_der::Asn1RawDer;
+use serde::{de, ser, Deserialize, Serialize};
+use std::fmt;
+
+/// [RFC 5280 #5.1](https://tools.ietf.org/html/rfc5280#section-5.1)
+///
+/// ```not_rust
+/// CertificateList  ::=  SEQUENCE  {
+///      tbsCertList          TBSCertList,
+///      signatureAlgorithm   AlgorithmIdentifier,
+///      signatureValue       BIT STRING  }
+/// ```
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CertificateList {
+    pub tbs_cert_list: TBSCertList,
+    pub signature_algorithm: AlgorithmIdentifier,
+    pub signature_value: BitStringAsn1,
+}
+
+impl CertificateList {
+    fn h_find_extension(&self, oid: &oid::ObjectIdentifier) -> Option<&Extension> {
+        self.extensions().iter().find(|ext| ext.extn_id() == oid)
+    }
+
+    pub fn crl_number(&self) -> Option<&IntegerAsn1> {
+        let ext = self.h_find_extension(&crate::oids::crl_number())?;
+        match ext.extn_value() {
+            ExtensionView::CrlNumber(number) => Some(number),
+            _ => None,
+        }
+    }
+
+    pub fn revoked_certificates(&self) -> &[RevokedCertificate] {
+        match &self.tbs_cert_list.revoked_certificates {
+            Some(revoked) => &(revoked.0),
+            None => &[],
+        }
+    }
+
+    pub fn extensions(&self) -> &[Extension] {
+        match &self.tbs_cert_list.crl_extensions {
+            Some(extensions) => &(extensions.0).0,
+            None => &[],
+        }
+    }
+}
+
+/// [RFC 5280 #5.1](https://tools.ietf.org/html/rfc5280#section-5.1)
+///
+/// ```not_rust
+/// TBSCertList  ::=  SEQUENCE  {
+///      version                 Version OPTIONAL,
+///                                   -- if present, MUST be v2
+///      signature               AlgorithmIdentifier,
+///      issuer                  Name,
+///      thisUpdate              Time,
+///      nextUpdate              Time OPTIONAL,
+///      revokedCertificates     SEQUENCE OF SEQUENCE  {
+///           userCertificate         CertificateSerialNumber,
+///           revocationDate          Time,
+///           crlEntryExtensions      Extensions OPTIONAL
+///                                    -- if present, version MUST be v2
+///                                }  OPTIONAL,
+///      crlExtensions           [0]  EXPLICIT Extensions OPTIONAL
+///                                    -- if present, version MUST be v2
+///                                }
+/// ```
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct TBSCertList {
+    pub version: Option<Version>,
+    pub signature: AlgorithmIdentifier,
+    pub issuer: Name,
+    pub this_update: Time,
+    pub next_update: Option<Time>,
+    pub revoked_certificates: Option<Asn1SequenceOf<RevokedCertificate>>,
+    pub crl_extensions: Option<ApplicationTag0<Extensions>>,
+}
+
+impl<'de> de::Deserialize<'de> for TBSCertList {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        struct Visitor;
+
+        impl<'de> de::Visitor<'de> for Visitor {
+            type Value = TBSCertList;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("struct TBSCertList")
+            }
+
+            fn visit_seq<V>(self, mut seq: V) -> Result<Self::Value, V::Error>
+            where
+                V: de::SeqAccess<'de>,
+            {
+                let tag_peeker: TagPeeker = seq_next_element!(seq, TBSCertList, "version or signature tag");
+                let version = if tag_peeker.next_tag == Tag::INTEGER {
+                    // Version deserializer only accepts V3 (certificates), CRLs are always V2
+                    let version: u8 = seq_next_element!(seq, TBSCertList, "version");
+                    match Version::from_u8(version) {
+                        Some(Version::V2) => Some(Version::V2),
+                        _ => {
+                            return Err(serde_invalid_value!(
+                                TBSCertList,
+                                "unsupported CRL version",
+                                "a v2 CRL"
+                            ))
+                        }
+                    }
+                } else {
+                    None
+                };
+
+                let signature = seq_next_element!(seq, TBSCertList, "signature");
+                let issuer = seq_next_element!(seq, TBSCertList, "issuer");
+                let this_update = seq_next_element!(seq, TBSCertList, "this update");
+
+                let mut next_tag = seq.next_element::<TagPeeker>()?.map(|peeker| peeker.next_tag);
+
+                let next_update = match next_tag {
+                    Some(Tag::UTC_TIME) | Some(Tag::GENERALIZED_TIME) => {
+                        let next_update = seq_next_element!(seq, TBSCertList, "next update");
+                        next_tag = seq.next_element::<TagPeeker>()?.map(|peeker| peeker.next_tag);
+                        Some(next_update)
+                    }
+                    _ => None,
+                };
+
+                let revoked_certificates = match next_tag {
+                    Some(Tag::SEQUENCE) => {
+                        let revoked_certificates = seq_next_element!(seq, TBSCertList, "revoked certificates");
+                        next_tag = seq.next_element::<TagPeeker>()?.map(|peeker| peeker.next_tag);
+                        Some(revoked_certificates)
+                    }
+                    _ => None,
+                };
+
+                let crl_extensions = match next_tag {
+                    Some(Tag::APP_0) => Some(seq_next_element!(seq, TBSCertList, "CRL extensions")),
+                    Some(_) => {
+                        return Err(serde_invalid_value!(
+                            TBSCertList,
+                            "unexpected trailing element",
+                            "CRL extensions"
+                        ))
+                    }
+                    None => None,
+                };
+
+                Ok(TBSCertList {
+                    version,
+                    signature,
+                    issuer,
+                    this_update,
+                    next_update,
+                    revoked_certificates,
+                    crl_extensions,
+                })
+            }
+        }
+
+        deserializer.deserialize_seq(Visitor)
+    }
+}
+
+/// [RFC 5280 #5.1](https://tools.ietf.org/html/rfc5280#section-5.1)
+///
+/// ```not_rust
+/// SEQUENCE  {
+///      userCertificate         CertificateSerialNumber,
+///      revocationDate          Time,
+///      crlEntryExtensions      Extensions OPTIONAL
+///                               -- if present, version MUST be v2
+/// }
+/// ```
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct RevokedCertificate {
+    pub user_certificate: IntegerAsn1,
+    pub revocation_date: Time,
+    pub crl_entry_extensions: Option<Extensions>,
+}
+
+impl RevokedCertificate {
+    pub fn reason_code(&self) -> Option<CrlReason> {
+        let extensions = &self.crl_entry_extensions.as_ref()?.0;
+        let ext = extensions
+            .iter()
+            .find(|ext| ext.extn_id() == &crate::oids::crl_reason_code())?;
+        match ext.extn_value() {
+            ExtensionView::CrlReason(reason) => Some(*reason),
+            _ => None,
+        }
+    }
+}
+
+impl<'de> de::Deserialize<'de> for RevokedCertificate {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        struct Visitor;
+
+        impl<'de> de::Visitor<'de> for Visitor {
+            type Value = RevokedCertificate;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("struct RevokedCertificate")
+            }
+
+            fn visit_seq<V>(self, mut seq: V) -> Result<Self::Value, V::Error>
+            where
+                V: de::SeqAccess<'de>,
+            {
+                Ok(RevokedCertificate {
+                    user_certificate: seq_next_element!(seq, RevokedCertificate, "user certificate"),
+                    revocation_date: seq_next_element!(seq, RevokedCertificate, "revocation date"),
+                    crl_entry_extensions: seq.next_element()?,
+                })
+            }
+        }
+
+        deserializer.deserialize_seq(Visitor)
+    }
+}
+
+/// [RFC 5280 #5.3.1](https://tools.ietf.org/html/rfc5280#section-5.3.1)
+///
+/// ```not_rust
+/// CRLReason ::= ENUMERATED {
+///      unspecified             (0),
+///      keyCompromise           (1),
+///      cACompromise            (2),
+///      affiliationChanged      (3),
+///      superseded              (4),
+///      cessationOfOperation    (5),
+///      certificateHold         (6),
+///           -- value 7 is not used
+///      removeFromCRL           (8),
+///      privilegeWithdrawn      (9),
+///      aACompromise           (10) }
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[repr(u8)]
+pub enum CrlReason {
+    Unspecified = 0,
+    KeyCompromise = 1,
+    CaCompromise = 2,
+    AffiliationChanged = 3,
+    Superseded = 4,
+    CessationOfOperation = 5,
+    CertificateHold = 6,
+    RemoveFromCrl = 8,
+    PrivilegeWithdrawn = 9,
+    AaCompromise = 10,
+}
+
+impl CrlReason {
+    const ENUMERATED_TAG: u8 = 0x0A;
+
+    pub fn from_u8(v: u8) -> Option<Self> {
+        match v {
+            0 => Some(Self::Unspecified),
+            1 => Some(Self::KeyCompromise),
+            2 => Some(Self::CaCompromise),
+            3 => Some(Self::AffiliationChanged),
+            4 => Some(Self::Superseded),
+            5 => Some(Self::CessationOfOperation),
+            6 => Some(Self::CertificateHold),
+            8 => Some(Self::RemoveFromCrl),
+            9 => Some(Self::PrivilegeWithdrawn),
+            10 => Some(Self::AaCompromise),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for CrlReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Self::Unspecified => "Unspecified",
+            Self::KeyCompromise => "Key Compromise",
+            Self::CaCompromise => "CA Compromise",
+            Self::AffiliationChanged => "Affiliation Changed",
+            Self::Superseded => "Superseded",
+            Self::CessationOfOperation => "Cessation Of Operation",
+            Self::CertificateHold => "Certificate Hold",
+            Self::RemoveFromCrl => "Remove From CRL",
+            Self::PrivilegeWithdrawn => "Privilege Withdrawn",
+            Self::AaCompromise => "AA Compromise",
+        };
+        f.write_str(name)
+    }
+}
+
+// ENUMERATED isn't supported by picky-asn1: (de)serialize raw DER instead.
+
+impl ser::Serialize for CrlReason {
+    fn serialize<S>(&self, serializer: S) -> Result<<S as ser::Serializer>::Ok, <S as ser::Serializer>::Error>
+    where
+        S: ser::Serializer,
+    {
+        Asn1RawDer(vec![Self::ENUMERATED_TAG, 0x01, *self as u8]).serialize(serializer)
+    }
+}
+
+impl<'de> de::Deserialize<'de> for CrlReason {
+    fn deserialize<D>(deserializer: D) -> Result<Self, <D as de::Deserializer<'de>>::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let raw = Asn1RawDer::deserialize(deserializer)?;
+        match raw.0.as_slice() {
+            [Self::ENUMERATED_TAG, 0x01, value] => CrlReason::from_u8(*value)
+                .ok_or_else(|| serde_invalid_value!(CrlReason, "unknown reason code", "a valid CRLReason")),
+            _ => Err(serde_invalid_value!(
+                CrlReason,
+                "not an ENUMERATED",
+                "a DER-encoded CRLReason"
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DirectoryName, KeyIdentifier};
+    use picky_asn1::{bit_string::BitString, date::UTCTime};
+
+    #[test]
+    fn crl_reason() {
+        let encoded = [0x0A, 0x01, 0x01];
+        let reason = CrlReason::KeyCompromise;
+        check_serde!(reason: CrlReason in encoded);
+
+        let unknown: picky_asn1_der::Result<CrlReason> = picky_asn1_der::from_bytes(&[0x0A, 0x01, 0x07]);
+        assert!(unknown.is_err());
+    }
+
+    #[test]
+    fn openssl_crl() {
+        // generated using `openssl ca -gencrl` after revoking two certificates
+        let encoded = base64::decode(
+            "MIIBzDCBtQIBATANBgkqhkiG9w0BAQsFADAZMRcwFQYDVQQDDA5UZXN0IEF1dGhvcml0eRcNMjYxMDE3MDI0MDMwWhcNMjYx\
+             MDI0MDI0MDMwWjA2MCACAQEXDTI2MTAxNzAyNDAzMFowDDAKBgNVHRUEAwoBATASAgECFw0yNjEwMTcwMjQwMzBaoDAwLjAf\
+             BgNVHSMEGDAWgBRORJDZEQ5ha+iG3sZTQOOft0kJfDALBgNVHRQEBAICEAAwDQYJKoZIhvcNAQELBQADggEBAGccmcHENSkb\
+             DMMNQPw4cC0qUH3SOTz08lCHr+wrfKz39mdKLPgHkdJpRNIE73mQtnGarRltw/j0rgq2bExjjnxtpd6TZfQSkmI34hYCxqkX\
+             RUUoqal9iVW3BeooTRDgXIcNczE4TBmilktdhqf2H26d1zFBVgic03fG44aB1cxIcUtWWakPKaSe0/7IEoW9BNaoNFeESicb\
+             SOXkS6nlie5XeU1OqR9RZuj0HSQSSJDHed79GPNNhI/03/TJvcFKoNlLmVtei6U3FT/4xzYJDjSv+ZksP//nG8MPZVJ/QP1+\
+             irGHJaI0TNEsrqRPZcfajK4NF3ObMtWvotR5uL71gw4=",
+        )
+        .expect("invalid base64");
+
+        let revoked_certificates = vec![
+            RevokedCertificate {
+                user_certificate: vec![0x01].into(),
+                revocation_date: UTCTime::new(2026, 10, 17, 2, 40, 30).unwrap().into(),
+                crl_entry_extensions: Some(Extensions(vec![Extension::new_crl_reason(CrlReason::KeyCompromise)])),
+            },
+            RevokedCertificate {
+                user_certificate: vec![0x02].into(),
+                revocation_date: UTCTime::new(2026, 10, 17, 2, 40, 30).unwrap().into(),
+                crl_entry_extensions: None,
+            },
+        ];
+        type RevokedCertificates = Asn1SequenceOf<RevokedCertificate>;
+        let revoked_certificates: RevokedCertificates = Asn1SequenceOf(revoked_certificates);
+        check_serde!(revoked_certificates: RevokedCertificates in encoded[82..138]);
+
+        let crl_extensions = Extensions(vec![
+            Extension::new_authority_key_identifier(KeyIdentifier::from(encoded[155..175].to_vec()), None, None),
+            Extension::new_crl_number(vec![0x10, 0x00]),
+        ]);
+
+        let tbs_cert_list = TBSCertList {
+            version: Some(Version::V2),
+            signature: AlgorithmIdentifier::new_sha256_with_rsa_encryption(),
+            issuer: DirectoryName::new_common_name("Test Authority"),
+            this_update: UTCTime::new(2026, 10, 17, 2, 40, 30).unwrap().into(),
+            next_update: Some(UTCTime::new(2026, 10, 24, 2, 40, 30).unwrap().into()),
+            revoked_certificates: Some(revoked_certificates),
+            crl_extensions: Some(crl_extensions.into()),
+        };
+        check_serde!(tbs_cert_list: TBSCertList in encoded[4..188]);
+
+        let crl = CertificateList {
+            tbs_cert_list,
+            signature_algorithm: AlgorithmIdentifier::new_sha256_with_rsa_encryption(),
+            signature_value: BitString::with_bytes(&encoded[208..464]).into(),
+        };
+        check_serde!(crl: CertificateList in encoded);
+
+        assert_eq!(crl.crl_number().unwrap().as_unsigned_bytes_be(), &[0x10, 0x00]);
+        let revoked = crl.revoked_certificates();
+        assert_eq!(revoked.len(), 2);
+        assert_eq!(revoked[0].reason_code(), Some(CrlReason::KeyCompromise));
+        assert_eq!(revoked[1].reason_code(), None);
+    }
+
+    #[test]
+    fn minimal_crl() {
+        // v1 CRL without nextUpdate, revoked certificates nor extensions
+        let tbs_cert_list = TBSCertList {
+            version: None,
+            signature: AlgorithmIdentifier::new_sha256_with_rsa_encryption(),
+            issuer: DirectoryName::new_common_name("Test Authority"),
+            this_update: UTCTime::new(2026, 10, 17, 2, 40, 30).unwrap().into(),
+            next_update: None,
+            revoked_certificates: None,
+            crl_extensions: None,
+        };
+
+        let encoded = picky_asn1_der::to_vec(&tbs_cert_list).unwrap();
+        let decoded: TBSCertList = picky_asn1_der::from_bytes(&encoded).unwrap();
+        assert_eq!(decoded, tbs_cert_list);
+    }
+}
@@ -1,6 +1,11 @@
-use crate::{AlgorithmIdentifier, Name, SubjectPublicKeyInfo};
-use picky_asn1::wrapper::{ApplicationTag0, BitStringAsn1, HeaderOnly, Implicit};
-use serde::{Deserialize, Serialize};
+use crate::{oids, AlgorithmIdentifier, Extension, Extensions, Name, SubjectPublicKeyInfo};
+use picky_asn1::{
+    tag::Tag,
+    wrapper::{Asn1SetOf, BitStringAsn1, ObjectIdentifierAsn1},
+};
+use picky_asn1_der::Asn1RawDer;
+use serde::{de, ser, Deserialize, Serialize};
+use std::fmt;
 
 /// [RFC 2986 #4](https://tools.ietf.org/html/rfc2986#section-4)
 ///
@@ -17,19 +22,151 @@ pub struct CertificationRequestInfo {
     pub version: u8,
     pub subject: Name,
     pub subject_public_key_info: SubjectPublicKeyInfo,
-    /// Not yet supported
-    pub attributes: Implicit<Option<HeaderOnly<ApplicationTag0<()>>>>,
+    pub attributes: Attributes,
 }
 
 impl CertificationRequestInfo {
     pub fn new(subject: Name, subject_public_key_info: SubjectPublicKeyInfo) -> Self {
+        Self::new_with_attributes(subject, subject_public_key_info, Attributes::default())
+    }
+
+    pub fn new_with_attributes(
+        subject: Name,
+        subject_public_key_info: SubjectPublicKeyInfo,
+        attributes: Attributes,
+    ) -> Self {
         // It shall be 0 for this version of the standard.
         Self {
             version: 0,
             subject,
             subject_public_key_info,
-            attributes: Implicit(Some(HeaderOnly::<ApplicationTag0<()>>::default())),
+            attributes,
+        }
+    }
+}
+
+/// [RFC 2986 #4](https://tools.ietf.org/html/rfc2986#section-4)
+///
+/// ```not_rust
+/// Attributes { ATTRIBUTE:IOSet } ::= SET OF Attribute{{ IOSet }}
+/// ```
+///
+/// Implicitly tagged with `[0]` in `CertificationRequestInfo`.
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Attributes(pub Vec<Attribute>);
+
+impl ser::Serialize for Attributes {
+    fn serialize<S>(&self, serializer: S) -> Result<<S as ser::Serializer>::Ok, <S as ser::Serializer>::Error>
+    where
+        S: ser::Serializer,
+    {
+        // attributes are implicitly tagged: SET OF tag is replaced by the context-specific tag
+        let mut der = picky_asn1_der::to_vec(&self.0).map_err(ser::Error::custom)?;
+        der[0] = Tag::APP_0.number();
+        Asn1RawDer(der).serialize(serializer)
+    }
+}
+
+impl<'de> de::Deserialize<'de> for Attributes {
+    fn deserialize<D>(deserializer: D) -> Result<Self, <D as de::Deserializer<'de>>::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let mut der = Asn1RawDer::deserialize(deserializer)?.0;
+        if der.first() != Some(&Tag::APP_0.number()) {
+            return Err(serde_invalid_value!(
+                Attributes,
+                "invalid attributes tag",
+                "context-specific tag 0"
+            ));
+        }
+        // restore SET OF tag to decode the attributes
+        der[0] = Tag::SET.number();
+        picky_asn1_der::from_bytes(&der)
+            .map(Self)
+            .map_err(|_| serde_invalid_value!(Attributes, "invalid attributes", "valid attributes"))
+    }
+}
+
+/// [RFC 2986 #4](https://tools.ietf.org/html/rfc2986#section-4)
+///
+/// ```not_rust
+/// Attribute { ATTRIBUTE:IOSet } ::= SEQUENCE {
+///      type   ATTRIBUTE.&id({IOSet}),
+///      values SET SIZE(1..MAX) OF ATTRIBUTE.&Type({IOSet}{@type})
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Attribute {
+    pub ty: ObjectIdentifierAsn1,
+    pub value: AttributeValue,
+}
+
+impl Attribute {
+    /// [RFC 2985 #5.4.2](https://tools.ietf.org/html/rfc2985#section-5.4.2) extensionRequest attribute
+    pub fn new_extension_request(extensions: Vec<Extension>) -> Self {
+        Self {
+            ty: oids::extension_request().into(),
+            value: AttributeValue::Extensions(Asn1SetOf(vec![Extensions(extensions)])),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AttributeValue {
+    /// `extensionRequest` values
+    Extensions(Asn1SetOf<Extensions>),
+    /// Raw DER-encoded SET of values (unsupported attribute types are preserved)
+    Custom(Asn1RawDer),
+}
+
+impl ser::Serialize for Attribute {
+    fn serialize<S>(&self, serializer: S) -> Result<<S as ser::Serializer>::Ok, <S as ser::Serializer>::Error>
+    where
+        S: ser::Serializer,
+    {
+        use ser::SerializeSeq;
+        let mut seq = serializer.serialize_seq(Some(2))?;
+        seq.serialize_element(&self.ty)?;
+        match &self.value {
+            AttributeValue::Extensions(extensions) => seq.serialize_element(extensions)?,
+            AttributeValue::Custom(der) => seq.serialize_element(der)?,
+        }
+        seq.end()
+    }
+}
+
+impl<'de> de::Deserialize<'de> for Attribute {
+    fn deserialize<D>(deserializer: D) -> Result<Self, <D as de::Deserializer<'de>>::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        struct Visitor;
+
+        impl<'de> de::Visitor<'de> for Visitor {
+            type Value = Attribute;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a valid DER-encoded attribute")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: de::SeqAccess<'de>,
+            {
+                let ty: ObjectIdentifierAsn1 = seq_next_element!(seq, Attribute, "type");
+                let value = match Into::<String>::into(&ty.0).as_str() {
+                    oids::EXTENSION_REQUEST => {
+                        AttributeValue::Extensions(seq_next_element!(seq, Attribute, "extension request values"))
+                    }
+                    _ => AttributeValue::Custom(seq_next_element!(seq, Attribute, "values")),
+                };
+
+                Ok(Attribute { ty, value })
+            }
         }
+
+        deserializer.deserialize_seq(Visitor)
     }
 }
 
@@ -52,7 +189,7 @@ pub struct CertificationRequest {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::DirectoryName;
+    use crate::{DirectoryName, ExtensionView};
     use picky_asn1::{bit_string::BitString, restricted_string::PrintableString, wrapper::IntegerAsn1};
     use std::str::FromStr;
 
@@ -93,4 +230,44 @@ mod tests {
 
         check_serde!(csr: CertificationRequest in encoded);
     }
+
+    #[test]
+    fn deserialize_csr_with_extension_request() {
+        let encoded = base64::decode(
+            "MIICpTCCAY0CAQAwHTEbMBkGA1UEAwwSdGVzdC5jb250b3NvLmxvY2FsMIIBIjAN\
+            BgkqhkiG9w0BAQEFAAOCAQ8AMIIBCgKCAQEAjwszt/5a++z57tQ2AFnLSx2dbmrv\
+            X5h3EAKtOlJDmLrOMyjjsvqumDB75pNCRxVxruokfQoVDeu43p+7F5A0+c4wfpSZ\
+            ttKtbrTiGHCsm4T06C88HrZQqTdMBiiP8SWdTEXO3n5A5lAheBka2GEAt7CVK4Ga\
+            a8L2ln5XNmBw+P8K8o3GXvb6fTldwBY2N0clnMI4xIJYiCEUbmdh+eiePYKfOtNK\
+            a43jCymFIiBanIexHEw3/r7HanJNa9UP529dczgcBP092YE4d2rAKmoc2ni1sNne\
+            u8eOtglHPCU27O/lvu9jWmtGEEuEd5j05gtTB+Sky8OvdD/lFEwIDhcb4QIDAQAB\
+            oEMwQQYJKoZIhvcNAQkOMTQwMjAwBgNVHREEKTAnghJ0ZXN0LmNvbnRvc28ubG9j\
+            YWyCEXd3dy5jb250b3NvLmxvY2FsMA0GCSqGSIb3DQEBCwUAA4IBAQB0oNb4jsMK\
+            Da3Ssb6k1+lglpviUbajqNVGyk/8+qVYSCQ+InZmkdpdwSqAEntx+8m9u9rxNgdb\
+            i6avnKDSmnSQPWGMULXadfe0c9P3Mnj5u1H4vRI6Tu88kj6fkQjYGvXPf4+z2fnz\
+            Jn1LKGRSPngw0Er0k6LYXjGiD+DVLuCiWRf1JL8dbT4mYENIN07T8OvHFklWJqI9\
+            idC3gYHj8h+wYzPaGcqlTKY4psAkFcXt+vmCMfK8Ec5UFk+lCkjxx9Oebpp9jVcy\
+            82FdZjA2zzp8q14RoLMfcpITabCUvsd5CmIa01Is7XtpNZkpuq4Rhqjnq1aBYd02\
+            pagxF004PHG5",
+        )
+        .expect("invalid base64");
+
+        let csr: CertificationRequest = picky_asn1_der::from_bytes(&encoded).expect("couldn't deserialize csr");
+        let attributes = &csr.certification_request_info.attributes.0;
+        assert_eq!(attributes.len(), 1);
+        assert_eq!(attributes[0].ty, oids::extension_request());
+        match &attributes[0].value {
+            AttributeValue::Extensions(Asn1SetOf(values)) => {
+                assert_eq!(values.len(), 1);
+                assert_eq!(values[0].0.len(), 1);
+                match values[0].0[0].extn_value() {
+                    ExtensionView::SubjectAltName(san) => assert_eq!(san.0.len(), 2),
+                    unexpected => panic!("unexpected extension: {:?}", unexpected),
+                }
+            }
+            unexpected => panic!("unexpected attribute value: {:?}", unexpected),
+        }
+
+        check_serde!(csr: CertificationRequest in encoded);
+    }
 }
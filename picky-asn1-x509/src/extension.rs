@@ -1,4 +1,4 @@
-use crate::{oids, GeneralName, GeneralNames};
+use crate::{oids, CrlReason, GeneralName, GeneralNames};
 use core::slice::{Iter, IterMut};
 use picky_asn1::{
     bit_string::BitString,
@@ -14,6 +14,151 @@ use std::fmt;
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct Extensions(pub Vec<Extension>);
 
+/// Violation of the RFC 5280 rules on an extensions set
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExtensionsError {
+    /// an extension appears more than once
+    Duplicate { oid: String },
+    /// a critical extension isn't recognized
+    UnknownCritical { oid: String },
+}
+
+impl fmt::Display for ExtensionsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExtensionsError::Duplicate { oid } => write!(f, "extension {} appears more than once", oid),
+            ExtensionsError::UnknownCritical { oid } => write!(f, "critical extension {} isn't recognized", oid),
+        }
+    }
+}
+
+impl std::error::Error for ExtensionsError {}
+
+impl Extensions {
+    pub fn iter(&self) -> Iter<'_, Extension> {
+        self.0.iter()
+    }
+
+    /// Finds the extension identified by `oid`
+    pub fn find<C: PartialEq<oid::ObjectIdentifier>>(&self, oid: C) -> Option<&Extension> {
+        self.0.iter().find(|ext| oid.eq(&ext.extn_id.0))
+    }
+
+    fn position(&self, oid: &ObjectIdentifierAsn1) -> Option<usize> {
+        self.0.iter().position(|ext| &ext.extn_id == oid)
+    }
+
+    /// Adds an extension, failing if an extension with the same identifier is already present
+    pub fn insert(&mut self, extension: Extension) -> Result<(), ExtensionsError> {
+        if self.position(&extension.extn_id).is_some() {
+            return Err(ExtensionsError::Duplicate {
+                oid: (&extension.extn_id.0).into(),
+            });
+        }
+        self.0.push(extension);
+        Ok(())
+    }
+
+    /// Adds an extension, returning the extension with the same identifier it replaces if any
+    pub fn replace(&mut self, extension: Extension) -> Option<Extension> {
+        match self.position(&extension.extn_id) {
+            Some(idx) => Some(std::mem::replace(&mut self.0[idx], extension)),
+            None => {
+                self.0.push(extension);
+                None
+            }
+        }
+    }
+
+    /// Checks that no extension appears more than once and that all critical extensions are recognized
+    pub fn validate(&self) -> Result<(), ExtensionsError> {
+        for (idx, ext) in self.0.iter().enumerate() {
+            if self.0[..idx].iter().any(|previous| previous.extn_id == ext.extn_id) {
+                return Err(ExtensionsError::Duplicate {
+                    oid: (&ext.extn_id.0).into(),
+                });
+            }
+
+            if ext.critical() && matches!(ext.extn_value, ExtensionValue::Unknown(_)) {
+                return Err(ExtensionsError::UnknownCritical {
+                    oid: (&ext.extn_id.0).into(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    pub fn authority_key_identifier(&self) -> Option<&AuthorityKeyIdentifier> {
+        match self.find(oids::authority_key_identifier())?.extn_value() {
+            ExtensionView::AuthorityKeyIdentifier(aki) => Some(aki),
+            _ => None,
+        }
+    }
+
+    pub fn subject_key_identifier(&self) -> Option<&[u8]> {
+        match self.find(oids::subject_key_identifier())?.extn_value() {
+            ExtensionView::SubjectKeyIdentifier(ski) => Some(&ski.0),
+            _ => None,
+        }
+    }
+
+    pub fn key_usage(&self) -> Option<&KeyUsage> {
+        match self.find(oids::key_usage())?.extn_value() {
+            ExtensionView::KeyUsage(key_usage) => Some(key_usage),
+            _ => None,
+        }
+    }
+
+    pub fn basic_constraints(&self) -> Option<&BasicConstraints> {
+        match self.find(oids::basic_constraints())?.extn_value() {
+            ExtensionView::BasicConstraints(basic_constraints) => Some(basic_constraints),
+            _ => None,
+        }
+    }
+
+    pub fn extended_key_usage(&self) -> Option<&ExtendedKeyUsage> {
+        match self.find(oids::extended_key_usage())?.extn_value() {
+            ExtensionView::ExtendedKeyUsage(eku) => Some(eku),
+            _ => None,
+        }
+    }
+
+    pub fn subject_alt_name(&self) -> Option<GeneralNames> {
+        match self.find(oids::subject_alternative_name())?.extn_value() {
+            ExtensionView::SubjectAltName(san) => Some(san),
+            _ => None,
+        }
+    }
+
+    pub fn issuer_alt_name(&self) -> Option<GeneralNames> {
+        match self.find(oids::issuer_alternative_name())?.extn_value() {
+            ExtensionView::IssuerAltName(ian) => Some(ian),
+            _ => None,
+        }
+    }
+
+    pub fn authority_info_access(&self) -> Option<&AuthorityInfoAccess> {
+        match self.find(oids::authority_info_access())?.extn_value() {
+            ExtensionView::AuthorityInfoAccess(aia) => Some(aia),
+            _ => None,
+        }
+    }
+
+    pub fn crl_number(&self) -> Option<&IntegerAsn1> {
+        match self.find(oids::crl_number())?.extn_value() {
+            ExtensionView::CrlNumber(number) => Some(number),
+            _ => None,
+        }
+    }
+
+    pub fn crl_reason(&self) -> Option<&CrlReason> {
+        match self.find(oids::crl_reason_code())?.extn_value() {
+            ExtensionView::CrlReason(reason) => Some(reason),
+            _ => None,
+        }
+    }
+}
+
 /// [RFC 5280 #4.1.2.9](https://tools.ietf.org/html/rfc5280#section-4.1.2.9)
 #[derive(Debug, PartialEq, Clone)]
 pub struct Extension {
@@ -32,7 +177,34 @@ impl Extension {
     }
 
     pub fn extn_value(&self) -> ExtensionView<'_> {
-        ExtensionView::from(&self.extn_value)
+        match &self.extn_value {
+            ExtensionValue::AuthorityKeyIdentifier(OctetStringAsn1Container(val)) => {
+                ExtensionView::AuthorityKeyIdentifier(val)
+            }
+            ExtensionValue::SubjectKeyIdentifier(OctetStringAsn1Container(val)) => {
+                ExtensionView::SubjectKeyIdentifier(val)
+            }
+            ExtensionValue::KeyUsage(OctetStringAsn1Container(val)) => ExtensionView::KeyUsage(val),
+            ExtensionValue::SubjectAltName(OctetStringAsn1Container(val)) => ExtensionView::SubjectAltName(val.clone()),
+            ExtensionValue::IssuerAltName(OctetStringAsn1Container(val)) => ExtensionView::IssuerAltName(val.clone()),
+            ExtensionValue::BasicConstraints(OctetStringAsn1Container(val)) => ExtensionView::BasicConstraints(val),
+            ExtensionValue::ExtendedKeyUsage(OctetStringAsn1Container(val)) => ExtensionView::ExtendedKeyUsage(val),
+            ExtensionValue::AuthorityInfoAccess(OctetStringAsn1Container(val)) => {
+                ExtensionView::AuthorityInfoAccess(val)
+            }
+            ExtensionValue::CrlNumber(OctetStringAsn1Container(val)) => ExtensionView::CrlNumber(val),
+            ExtensionValue::CrlReason(OctetStringAsn1Container(val)) => ExtensionView::CrlReason(val),
+            ExtensionValue::Unknown(raw_value) => ExtensionView::Unknown {
+                oid: &self.extn_id,
+                critical: self.critical.0,
+                raw_value: &raw_value.0,
+            },
+        }
+    }
+
+    /// Returns `true` if this extension isn't recognized, its value being kept as raw DER
+    pub fn is_unknown(&self) -> bool {
+        matches!(self.extn_value, ExtensionValue::Unknown(_))
     }
 
     pub fn into_critical(mut self) -> Self {
@@ -164,6 +336,53 @@ impl Extension {
             extn_value: ExtensionValue::IssuerAltName(name.into()),
         }
     }
+
+    /// Conforming CAs MUST mark this extension as non-critical.
+    ///
+    /// Default is non-critical.
+    pub fn new_authority_info_access<A: Into<AuthorityInfoAccess>>(aia: A) -> Self {
+        Self {
+            extn_id: oids::authority_info_access().into(),
+            critical: false.into(),
+            extn_value: ExtensionValue::AuthorityInfoAccess(aia.into().into()),
+        }
+    }
+
+    /// CRL issuers conforming to RFC 5280 MUST include this extension in all CRLs
+    /// and MUST mark it as non-critical.
+    ///
+    /// Default is non-critical.
+    pub fn new_crl_number<N: Into<IntegerAsn1>>(number: N) -> Self {
+        Self {
+            extn_id: oids::crl_number().into(),
+            critical: false.into(),
+            extn_value: ExtensionValue::CrlNumber(number.into().into()),
+        }
+    }
+
+    /// CRL entry extension. When present, conforming CRL issuers MUST mark it as non-critical.
+    ///
+    /// Default is non-critical.
+    pub fn new_crl_reason(reason: CrlReason) -> Self {
+        Self {
+            extn_id: oids::crl_reason_code().into(),
+            critical: false.into(),
+            extn_value: ExtensionValue::CrlReason(reason.into()),
+        }
+    }
+
+    /// Extension this crate has no model for (vendor extensions…), `raw_value` being the DER
+    /// encoding of its value (contents of the `extnValue` OCTET STRING), written as is.
+    ///
+    /// `oid` shouldn't identify an extension supported by the other constructors,
+    /// as such an extension would be parsed back as its typed counterpart.
+    pub fn new_unknown<V: Into<Vec<u8>>>(oid: oid::ObjectIdentifier, critical: bool, raw_value: V) -> Self {
+        Self {
+            extn_id: oid.into(),
+            critical: critical.into(),
+            extn_value: ExtensionValue::Unknown(OctetStringAsn1(raw_value.into())),
+        }
+    }
 }
 
 impl ser::Serialize for Extension {
@@ -227,7 +446,13 @@ impl<'de> de::Deserialize<'de> for Extension {
                     oids::EXTENDED_KEY_USAGE => {
                         ExtensionValue::ExtendedKeyUsage(seq_next_element!(seq, Extension, "ExtendedKeyUsage"))
                     }
-                    _ => ExtensionValue::Generic(seq_next_element!(seq, Extension, "Generic")),
+                    oids::AUTHORITY_INFO_ACCESS => {
+                        ExtensionValue::AuthorityInfoAccess(seq_next_element!(seq, Extension, "AuthorityInfoAccess"))
+                    }
+                    oids::CRL_NUMBER => ExtensionValue::CrlNumber(seq_next_element!(seq, Extension, "CrlNumber")),
+                    oids::CRL_REASON_CODE => ExtensionValue::CrlReason(seq_next_element!(seq, Extension, "CrlReason")),
+                    // unknown extensions are kept as raw DER so that they are written back byte for byte
+                    _ => ExtensionValue::Unknown(seq_next_element!(seq, Extension, "Unknown")),
                 };
 
                 Ok(Extension {
@@ -243,6 +468,8 @@ impl<'de> de::Deserialize<'de> for Extension {
 }
 
 /// A view on an Extension's value designed to be easier to match on
+///
+/// Extensions this crate has no model for are viewed as `Unknown`.
 #[derive(Debug, PartialEq, Clone)]
 pub enum ExtensionView<'a> {
     AuthorityKeyIdentifier(&'a AuthorityKeyIdentifier),
@@ -252,22 +479,15 @@ pub enum ExtensionView<'a> {
     IssuerAltName(super::name::GeneralNames),
     BasicConstraints(&'a BasicConstraints),
     ExtendedKeyUsage(&'a ExtendedKeyUsage),
-    Generic(&'a OctetStringAsn1),
-}
-
-impl<'a> From<&'a ExtensionValue> for ExtensionView<'a> {
-    fn from(value: &'a ExtensionValue) -> Self {
-        match value {
-            ExtensionValue::AuthorityKeyIdentifier(OctetStringAsn1Container(val)) => Self::AuthorityKeyIdentifier(val),
-            ExtensionValue::SubjectKeyIdentifier(OctetStringAsn1Container(val)) => Self::SubjectKeyIdentifier(val),
-            ExtensionValue::KeyUsage(OctetStringAsn1Container(val)) => Self::KeyUsage(val),
-            ExtensionValue::SubjectAltName(OctetStringAsn1Container(val)) => Self::SubjectAltName(val.clone()),
-            ExtensionValue::IssuerAltName(OctetStringAsn1Container(val)) => Self::IssuerAltName(val.clone()),
-            ExtensionValue::BasicConstraints(OctetStringAsn1Container(val)) => Self::BasicConstraints(val),
-            ExtensionValue::ExtendedKeyUsage(OctetStringAsn1Container(val)) => Self::ExtendedKeyUsage(val),
-            ExtensionValue::Generic(val) => Self::Generic(val),
-        }
-    }
+    AuthorityInfoAccess(&'a AuthorityInfoAccess),
+    CrlNumber(&'a IntegerAsn1),
+    CrlReason(&'a CrlReason),
+    Unknown {
+        oid: &'a ObjectIdentifierAsn1,
+        critical: bool,
+        /// DER encoding of the extension value, as found in the `extnValue` OCTET STRING
+        raw_value: &'a [u8],
+    },
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -287,7 +507,11 @@ enum ExtensionValue {
     //CRLDistributionPoints(…),
     //InhibitAnyPolicy(…),
     //FreshestCRL(…),
-    Generic(OctetStringAsn1),
+    AuthorityInfoAccess(OctetStringAsn1Container<AuthorityInfoAccess>),
+    CrlNumber(OctetStringAsn1Container<IntegerAsn1>),
+    CrlReason(OctetStringAsn1Container<CrlReason>),
+    /// Raw contents of the `extnValue` OCTET STRING, which don't have to be a single TLV
+    Unknown(OctetStringAsn1),
 }
 
 impl ser::Serialize for ExtensionValue {
@@ -303,7 +527,10 @@ impl ser::Serialize for ExtensionValue {
             ExtensionValue::IssuerAltName(ian) => ian.serialize(serializer),
             ExtensionValue::BasicConstraints(basic_constraints) => basic_constraints.serialize(serializer),
             ExtensionValue::ExtendedKeyUsage(eku) => eku.serialize(serializer),
-            ExtensionValue::Generic(octet_string) => octet_string.serialize(serializer),
+            ExtensionValue::AuthorityInfoAccess(aia) => aia.serialize(serializer),
+            ExtensionValue::CrlNumber(number) => number.serialize(serializer),
+            ExtensionValue::CrlReason(reason) => reason.serialize(serializer),
+            ExtensionValue::Unknown(octet_string) => octet_string.serialize(serializer),
         }
     }
 }
@@ -378,26 +605,46 @@ pub struct KeyUsage(BitStringAsn1);
 
 impl Default for KeyUsage {
     fn default() -> Self {
-        Self::new(9)
+        Self(BitString::from_named_bits(&[]).into())
     }
 }
 
-macro_rules! bit_string_get_set {
-    ($getter:ident , $setter:ident , $idx:literal) => {
-        pub fn $getter(&self) -> bool {
-            self.0.is_set($idx)
+/// Declares the named bits of a named bit list once.
+///
+/// For each bit, generates a getter, a setter and a chainable setter consuming `self`,
+/// as well as a `Display` implementation printing set bits the way OpenSSL does.
+/// Setters go through `BitString::set_named_bit` so that DER minimal encoding is preserved.
+macro_rules! named_bit_list {
+    ( $ty:ident { $( $getter:ident , $setter:ident , $with:ident , $idx:literal , $display:literal ; )+ } ) => {
+        impl $ty {
+            $(
+                pub fn $getter(&self) -> bool {
+                    self.0.named_bit($idx)
+                }
+
+                pub fn $setter(&mut self, val: bool) {
+                    self.0.set_named_bit($idx, val);
+                }
+
+                pub fn $with(mut self, val: bool) -> Self {
+                    self.$setter(val);
+                    self
+                }
+            )+
         }
 
-        pub fn $setter(&mut self, val: bool) {
-            if self.0.get_num_bits() <= $idx {
-                self.0.set_num_bits($idx + 1)
+        impl fmt::Display for $ty {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                let mut names = Vec::new();
+                $(
+                    if self.$getter() {
+                        names.push($display);
+                    }
+                )+
+                write!(f, "{}", names.join(", "))
             }
-            self.0.set($idx, val);
         }
     };
-    ( $( $getter:ident , $setter:ident , $idx:literal ; )+ ) => {
-        $( bit_string_get_set! { $getter, $setter, $idx } )+
-    };
 }
 
 impl KeyUsage {
@@ -405,20 +652,41 @@ impl KeyUsage {
         Self(BitString::with_len(num_bits).into())
     }
 
+    /// Builds a `KeyUsage` from named bits (`digitalSignature` first) with trailing zero bits dropped.
+    pub fn from_named_bits(bits: &[bool]) -> Self {
+        Self(BitString::from_named_bits(bits).into())
+    }
+
     pub fn as_bytes(&self) -> &[u8] {
         self.0.payload_view()
     }
+}
+
+named_bit_list! {
+    KeyUsage {
+        digital_signature, set_digital_signature, with_digital_signature, 0, "Digital Signature";
+        content_commitment, set_content_commitment, with_content_commitment, 1, "Non Repudiation";
+        key_encipherment, set_key_encipherment, with_key_encipherment, 2, "Key Encipherment";
+        data_encipherment, set_data_encipherment, with_data_encipherment, 3, "Data Encipherment";
+        key_agreement, set_key_agreement, with_key_agreement, 4, "Key Agreement";
+        key_cert_sign, set_key_cert_sign, with_key_cert_sign, 5, "Certificate Sign";
+        crl_sign, set_crl_sign, with_crl_sign, 6, "CRL Sign";
+        encipher_only, set_encipher_only, with_encipher_only, 7, "Encipher Only";
+        decipher_only, set_decipher_only, with_decipher_only, 8, "Decipher Only";
+    }
+}
+
+impl core::ops::BitOr for KeyUsage {
+    type Output = KeyUsage;
 
-    bit_string_get_set! {
-        digital_signature, set_digital_signature, 0;
-        content_commitment, set_content_commitment, 1;
-        key_encipherment, set_key_encipherment, 2;
-        data_encipherment, set_data_encipherment, 3;
-        key_agreement, set_key_agreement, 4;
-        key_cert_sign, set_key_cert_sign, 5;
-        crl_sign, set_crl_sign, 6;
-        encipher_only, set_encipher_only, 7;
-        decipher_only, set_decipher_only, 8;
+    /// Union of both key usages.
+    fn bitor(mut self, rhs: KeyUsage) -> KeyUsage {
+        for idx in 0..rhs.0.get_num_bits() {
+            if rhs.0.named_bit(idx) {
+                self.0.set_named_bit(idx, true);
+            }
+        }
+        self
     }
 }
 
@@ -490,6 +758,27 @@ impl<OID: Into<ObjectIdentifierAsn1>> From<Vec<OID>> for ExtendedKeyUsage {
     }
 }
 
+impl Default for ExtendedKeyUsage {
+    fn default() -> Self {
+        Self(Vec::new().into())
+    }
+}
+
+/// Declares a getter and a chainable setter for each well-known key purpose.
+macro_rules! key_purposes {
+    ( $( $getter:ident , $with:ident , $oid:ident ; )+ ) => {
+        $(
+            pub fn $getter(&self) -> bool {
+                self.contains(oids::$oid())
+            }
+
+            pub fn $with(self) -> Self {
+                self.with_purpose(oids::$oid())
+            }
+        )+
+    };
+}
+
 impl ExtendedKeyUsage {
     pub fn new<OID: Into<ObjectIdentifierAsn1>>(purpose_oids: Vec<OID>) -> Self {
         Self(
@@ -512,6 +801,105 @@ impl ExtendedKeyUsage {
     pub fn contains<C: PartialEq<oid::ObjectIdentifier>>(&self, item: C) -> bool {
         (self.0).0.iter().any(|id| item.eq(&id.0))
     }
+
+    pub fn len(&self) -> usize {
+        (self.0).0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        (self.0).0.is_empty()
+    }
+
+    /// Adds a key purpose (arbitrary OIDs are accepted for private purposes). Duplicates are ignored.
+    pub fn push<OID: Into<ObjectIdentifierAsn1>>(&mut self, purpose_oid: OID) {
+        let purpose_oid = purpose_oid.into();
+        if !(self.0).0.contains(&purpose_oid) {
+            (self.0).0.push(purpose_oid);
+        }
+    }
+
+    pub fn with_purpose<OID: Into<ObjectIdentifierAsn1>>(mut self, purpose_oid: OID) -> Self {
+        self.push(purpose_oid);
+        self
+    }
+
+    key_purposes! {
+        server_auth, with_server_auth, kp_server_auth;
+        client_auth, with_client_auth, kp_client_auth;
+        code_signing, with_code_signing, kp_code_signing;
+        email_protection, with_email_protection, kp_email_protection;
+        time_stamping, with_time_stamping, kp_time_stamping;
+        ocsp_signing, with_ocsp_signing, kp_ocsp_signing;
+        any_extended_key_usage, with_any_extended_key_usage, kp_any_extended_key_usage;
+    }
+}
+
+/// [RFC 5280 #4.2.2.1](https://tools.ietf.org/html/rfc5280#section-4.2.2.1)
+///
+/// ```not_rust
+/// AuthorityInfoAccessSyntax  ::=
+///         SEQUENCE SIZE (1..MAX) OF AccessDescription
+/// ```
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct AuthorityInfoAccess(Asn1SequenceOf<AccessDescription>);
+
+impl From<Vec<AccessDescription>> for AuthorityInfoAccess {
+    fn from(descriptions: Vec<AccessDescription>) -> Self {
+        Self(descriptions.into())
+    }
+}
+
+impl AuthorityInfoAccess {
+    pub fn iter(&self) -> Iter<'_, AccessDescription> {
+        (self.0).0.iter()
+    }
+
+    pub fn push(&mut self, description: AccessDescription) {
+        (self.0).0.push(description);
+    }
+
+    /// Locations where certificates of the issuer can be fetched from
+    pub fn ca_issuers(&self) -> impl Iterator<Item = &GeneralName> {
+        self.locations(oids::ad_ca_issuers())
+    }
+
+    /// Locations of the OCSP responders of the issuer
+    pub fn ocsp(&self) -> impl Iterator<Item = &GeneralName> {
+        self.locations(oids::ad_ocsp())
+    }
+
+    fn locations(&self, access_method: oid::ObjectIdentifier) -> impl Iterator<Item = &GeneralName> {
+        self.iter()
+            .filter(move |description| description.access_method.0 == access_method)
+            .map(|description| &description.access_location)
+    }
+}
+
+/// ```not_rust
+/// AccessDescription  ::=  SEQUENCE {
+///         accessMethod          OBJECT IDENTIFIER,
+///         accessLocation        GeneralName  }
+/// ```
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct AccessDescription {
+    pub access_method: ObjectIdentifierAsn1,
+    pub access_location: GeneralName,
+}
+
+impl AccessDescription {
+    pub fn new_ca_issuers<L: Into<GeneralName>>(location: L) -> Self {
+        Self {
+            access_method: oids::ad_ca_issuers().into(),
+            access_location: location.into(),
+        }
+    }
+
+    pub fn new_ocsp<L: Into<GeneralName>>(location: L) -> Self {
+        Self {
+            access_method: oids::ad_ocsp().into(),
+            access_location: location.into(),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -519,6 +907,179 @@ mod tests {
     use super::*;
     use crate::GeneralName;
     use picky_asn1::restricted_string::IA5String;
+    use std::convert::TryInto;
+
+    #[test]
+    fn extensions_lookup() {
+        let san: GeneralNames = vec![GeneralName::DNSName(
+            IA5String::from_string("example.com".into()).unwrap().into(),
+        )]
+        .into();
+        let mut extensions = Extensions(Vec::new());
+        extensions
+            .insert(Extension::new_basic_constraints(true, 0).into_critical())
+            .unwrap();
+        extensions
+            .insert(Extension::new_key_usage(KeyUsage::default().with_key_cert_sign(true)))
+            .unwrap();
+        extensions
+            .insert(Extension::new_extended_key_usage(vec![oids::kp_server_auth()]))
+            .unwrap();
+        extensions.insert(Extension::new_subject_alt_name(san.clone())).unwrap();
+        extensions.insert(Extension::new_issuer_alt_name(san.clone())).unwrap();
+        extensions
+            .insert(Extension::new_subject_key_identifier(vec![1, 2, 3]))
+            .unwrap();
+        extensions
+            .insert(Extension::new_authority_key_identifier(
+                KeyIdentifier::from(vec![4, 5, 6]),
+                None,
+                None,
+            ))
+            .unwrap();
+        extensions.insert(Extension::new_crl_number(vec![7])).unwrap();
+        extensions
+            .insert(Extension::new_crl_reason(CrlReason::KeyCompromise))
+            .unwrap();
+        assert_eq!(extensions.validate(), Ok(()));
+
+        assert_eq!(extensions.basic_constraints().unwrap().pathlen(), Some(0));
+        assert!(extensions.key_usage().unwrap().key_cert_sign());
+        assert!(extensions.extended_key_usage().unwrap().server_auth());
+        assert_eq!(extensions.subject_alt_name(), Some(san.clone()));
+        assert_eq!(extensions.issuer_alt_name(), Some(san));
+        assert_eq!(extensions.subject_key_identifier(), Some(&[1, 2, 3][..]));
+        assert_eq!(
+            extensions.authority_key_identifier().unwrap().key_identifier(),
+            Some(&[4, 5, 6][..])
+        );
+        assert_eq!(extensions.crl_number().unwrap().as_unsigned_bytes_be(), &[7]);
+        assert_eq!(extensions.crl_reason(), Some(&CrlReason::KeyCompromise));
+        assert!(extensions.find(oids::basic_constraints()).unwrap().critical());
+        assert!(extensions.find(oids::kp_server_auth()).is_none());
+
+        let err = extensions
+            .insert(Extension::new_basic_constraints(false, None))
+            .unwrap_err();
+        assert_eq!(err.to_string(), "extension 2.5.29.19 appears more than once");
+
+        let previous = extensions.replace(Extension::new_basic_constraints(false, None));
+        assert!(previous.unwrap().critical());
+        assert_eq!(extensions.basic_constraints().unwrap().ca(), Some(false));
+        assert_eq!(extensions.0.len(), 9);
+    }
+
+    #[test]
+    fn extensions_validation() {
+        let mut extensions = Extensions(vec![
+            Extension::new_key_usage(KeyUsage::default().with_digital_signature(true)),
+            Extension::new_basic_constraints(None, None),
+            Extension::new_key_usage(KeyUsage::default().with_crl_sign(true)),
+        ]);
+        assert_eq!(
+            extensions.validate(),
+            Err(ExtensionsError::Duplicate {
+                oid: "2.5.29.15".to_owned()
+            })
+        );
+        // the first occurrence is returned
+        assert!(extensions.key_usage().unwrap().digital_signature());
+
+        extensions.0.pop();
+        assert_eq!(extensions.validate(), Ok(()));
+
+        let unknown: Extension = picky_asn1_der::from_bytes(&[
+            0x30, 0x0C, // extension
+            0x06, 0x03, 0x2A, 0x03, 0x04, // oid 1.2.3.4
+            0x01, 0x01, 0xFF, // critical
+            0x04, 0x02, 0x05, 0x00, // value
+        ])
+        .unwrap();
+        extensions.0.push(unknown.clone());
+        assert_eq!(
+            extensions.validate().unwrap_err().to_string(),
+            "critical extension 1.2.3.4 isn't recognized"
+        );
+
+        extensions.replace(unknown.into_non_critical());
+        assert_eq!(extensions.validate(), Ok(()));
+    }
+
+    #[test]
+    fn unknown_extension() {
+        // Microsoft certificate template extension
+        let encoded = [
+            0x30, 0x1E, // extension
+            0x06, 0x09, 0x2B, 0x06, 0x01, 0x04, 0x01, 0x82, 0x37, 0x15, 0x07, // oid 1.3.6.1.4.1.311.21.7
+            0x04, 0x11, // value
+            0x30, 0x0F, 0x06, 0x07, 0x2B, 0x06, 0x01, 0x04, 0x01, 0x82, 0x37, 0x02, 0x01, 0x64, 0x02, 0x01, 0x00,
+        ];
+        let template_oid: oid::ObjectIdentifier = "1.3.6.1.4.1.311.21.7".try_into().unwrap();
+
+        let extension: Extension = picky_asn1_der::from_bytes(&encoded).expect("unknown extension");
+        assert!(extension.is_unknown());
+        assert_eq!(
+            extension.extn_value(),
+            ExtensionView::Unknown {
+                oid: &template_oid.clone().into(),
+                critical: false,
+                raw_value: &encoded[15..],
+            }
+        );
+        assert_eq!(picky_asn1_der::to_vec(&extension).unwrap(), encoded);
+
+        let built = Extension::new_unknown(template_oid, false, &encoded[15..]);
+        assert_eq!(built, extension);
+        assert_eq!(picky_asn1_der::to_vec(&built).unwrap(), encoded);
+
+        // values that aren't a single TLV are kept as well
+        let opaque = Extension::new_unknown("1.2.3.4".try_into().unwrap(), true, vec![0xDE, 0xAD]);
+        let opaque_der = picky_asn1_der::to_vec(&opaque).unwrap();
+        assert_eq!(picky_asn1_der::from_bytes::<Extension>(&opaque_der).unwrap(), opaque);
+
+        // typed getters skip unknown extensions
+        let extensions = Extensions(vec![
+            extension,
+            Extension::new_basic_constraints(true, None),
+            opaque.into_non_critical(),
+        ]);
+        assert_eq!(extensions.basic_constraints().unwrap().ca(), Some(true));
+        assert!(extensions.key_usage().is_none());
+        assert_eq!(extensions.iter().filter(|ext| ext.is_unknown()).count(), 2);
+    }
+
+    #[test]
+    fn authority_info_access() {
+        // same encoding as OpenSSL with `authorityInfoAccess = caIssuers;URI:http://ca.example/ca.cer, OCSP;URI:http://ocsp.example`
+        let encoded = [
+            0x30, 0x47, // AuthorityInfoAccessSyntax
+            0x30, 0x24, // access description
+            0x06, 0x08, 0x2B, 0x06, 0x01, 0x05, 0x05, 0x07, 0x30, 0x02, // oid 1.3.6.1.5.5.7.48.2 (caIssuers)
+            0x86, 0x18, // uri
+            0x68, 0x74, 0x74, 0x70, 0x3A, 0x2F, 0x2F, 0x63, 0x61, 0x2E, 0x65, 0x78, 0x61, 0x6D, 0x70, 0x6C, 0x65, 0x2F,
+            0x63, 0x61, 0x2E, 0x63, 0x65, 0x72, 0x30, 0x1F, // access description
+            0x06, 0x08, 0x2B, 0x06, 0x01, 0x05, 0x05, 0x07, 0x30, 0x01, // oid 1.3.6.1.5.5.7.48.1 (ocsp)
+            0x86, 0x13, // uri
+            0x68, 0x74, 0x74, 0x70, 0x3A, 0x2F, 0x2F, 0x6F, 0x63, 0x73, 0x70, 0x2E, 0x65, 0x78, 0x61, 0x6D, 0x70, 0x6C,
+            0x65,
+        ];
+        let uri = |uri: &str| GeneralName::URI(IA5String::from_string(uri.into()).unwrap().into());
+        let aia = AuthorityInfoAccess::from(vec![
+            AccessDescription::new_ca_issuers(uri("http://ca.example/ca.cer")),
+            AccessDescription::new_ocsp(uri("http://ocsp.example")),
+        ]);
+        check_serde!(aia: AuthorityInfoAccess in encoded);
+        assert_eq!(aia.ca_issuers().collect::<Vec<_>>(), [&uri("http://ca.example/ca.cer")]);
+        assert_eq!(aia.ocsp().collect::<Vec<_>>(), [&uri("http://ocsp.example")]);
+
+        let extension = Extension::new_authority_info_access(aia.clone());
+        assert!(!extension.critical());
+        let extension_der = picky_asn1_der::to_vec(&extension).unwrap();
+        assert_eq!(&extension_der[extension_der.len() - encoded.len()..], &encoded[..]);
+        let extensions = Extensions(vec![picky_asn1_der::from_bytes(&extension_der).unwrap()]);
+        assert_eq!(extensions.authority_info_access(), Some(&aia));
+        assert_eq!(extensions.validate(), Ok(()));
+    }
 
     #[test]
     fn key_usage() {
@@ -530,6 +1091,155 @@ mod tests {
         check_serde!(key_usage: KeyUsage in encoded);
     }
 
+    #[test]
+    fn key_usage_digital_signature_only() {
+        // same encoding as OpenSSL with `keyUsage=digitalSignature`
+        let encoded: [u8; 4] = [0x03, 0x02, 0x07, 0x80];
+        let mut key_usage = KeyUsage::default();
+        key_usage.set_digital_signature(true);
+        check_serde!(key_usage: KeyUsage in encoded);
+        assert_eq!(KeyUsage::from_named_bits(&[true, false, false]), key_usage);
+    }
+
+    #[test]
+    fn key_usage_key_cert_sign_and_crl_sign() {
+        // same encoding as OpenSSL with `keyUsage=keyCertSign,cRLSign`
+        let encoded: [u8; 4] = [0x03, 0x02, 0x01, 0x06];
+        let mut key_usage = KeyUsage::default();
+        key_usage.set_key_cert_sign(true);
+        key_usage.set_crl_sign(true);
+        check_serde!(key_usage: KeyUsage in encoded);
+        assert!(!key_usage.digital_signature());
+        assert!(!key_usage.decipher_only());
+    }
+
+    #[test]
+    fn key_usage_all_bits_set() {
+        // same encoding as OpenSSL with all nine key usages
+        let encoded: [u8; 5] = [0x03, 0x03, 0x07, 0xFF, 0x80];
+        let key_usage = KeyUsage::from_named_bits(&[true; 9]);
+        check_serde!(key_usage: KeyUsage in encoded);
+
+        let mut key_usage = key_usage;
+        key_usage.set_encipher_only(false);
+        key_usage.set_decipher_only(false);
+        assert_eq!(key_usage.as_bytes(), &[0xFE]);
+        assert!(key_usage.crl_sign());
+        assert!(!key_usage.decipher_only());
+    }
+
+    #[test]
+    fn key_usage_extension_common_combinations() {
+        // encodings emitted by OpenSSL for the same critical key usage extensions
+        let leaf = KeyUsage::default()
+            .with_digital_signature(true)
+            .with_key_encipherment(true);
+        let encoded = [
+            0x30, 0x0E, 0x06, 0x03, 0x55, 0x1D, 0x0F, 0x01, 0x01, 0xFF, 0x04, 0x04, 0x03, 0x02, 0x05, 0xA0,
+        ];
+        let extension = Extension::new_key_usage(leaf);
+        check_serde!(extension: Extension in encoded);
+
+        let ca = KeyUsage::default().with_key_cert_sign(true) | KeyUsage::default().with_crl_sign(true);
+        let encoded = [
+            0x30, 0x0E, 0x06, 0x03, 0x55, 0x1D, 0x0F, 0x01, 0x01, 0xFF, 0x04, 0x04, 0x03, 0x02, 0x01, 0x06,
+        ];
+        let extension = Extension::new_key_usage(ca);
+        check_serde!(extension: Extension in encoded);
+
+        let intermediate = KeyUsage::default()
+            .with_digital_signature(true)
+            .with_key_cert_sign(true)
+            .with_crl_sign(true);
+        let encoded = [
+            0x30, 0x0E, 0x06, 0x03, 0x55, 0x1D, 0x0F, 0x01, 0x01, 0xFF, 0x04, 0x04, 0x03, 0x02, 0x01, 0x86,
+        ];
+        let extension = Extension::new_key_usage(intermediate);
+        check_serde!(extension: Extension in encoded);
+    }
+
+    #[test]
+    fn key_usage_short_encoding() {
+        // only 1 bit encoded
+        let key_usage: KeyUsage = picky_asn1_der::from_bytes(&[0x03, 0x02, 0x07, 0x80]).expect("key usage");
+        assert!(key_usage.digital_signature());
+        assert!(!key_usage.content_commitment());
+        assert!(!key_usage.crl_sign());
+        assert!(!key_usage.decipher_only());
+
+        // no bit at all
+        let key_usage: KeyUsage = picky_asn1_der::from_bytes(&[0x03, 0x01, 0x00]).expect("key usage");
+        assert!(!key_usage.digital_signature());
+        assert!(!key_usage.decipher_only());
+        assert_eq!(key_usage.to_string(), "");
+    }
+
+    #[test]
+    fn key_usage_display() {
+        let key_usage = KeyUsage::default()
+            .with_digital_signature(true)
+            .with_key_cert_sign(true);
+        assert_eq!(key_usage.to_string(), "Digital Signature, Certificate Sign");
+
+        let key_usage = KeyUsage::from_named_bits(&[true; 9]);
+        assert_eq!(
+            key_usage.to_string(),
+            "Digital Signature, Non Repudiation, Key Encipherment, Data Encipherment, Key Agreement, \
+             Certificate Sign, CRL Sign, Encipher Only, Decipher Only"
+        );
+    }
+
+    #[test]
+    fn extended_key_usage_standard_purposes() {
+        let eku = ExtendedKeyUsage::default().with_server_auth().with_client_auth();
+        let encoded = [
+            0x30, 0x14, 0x06, 0x08, 0x2B, 0x06, 0x01, 0x05, 0x05, 0x07, 0x03, 0x01, 0x06, 0x08, 0x2B, 0x06, 0x01, 0x05,
+            0x05, 0x07, 0x03, 0x02,
+        ];
+        check_serde!(eku: ExtendedKeyUsage in encoded);
+        assert!(eku.server_auth());
+        assert!(eku.client_auth());
+        assert!(!eku.code_signing());
+        assert!(!eku.any_extended_key_usage());
+        assert_eq!(
+            eku,
+            ExtendedKeyUsage::new(vec![oids::kp_server_auth(), oids::kp_client_auth()])
+        );
+
+        let all = ExtendedKeyUsage::default()
+            .with_server_auth()
+            .with_client_auth()
+            .with_code_signing()
+            .with_email_protection()
+            .with_time_stamping()
+            .with_ocsp_signing()
+            .with_any_extended_key_usage()
+            .with_server_auth();
+        assert_eq!(all.len(), 7);
+        let encoded = picky_asn1_der::to_vec(&all).unwrap();
+        let decoded: ExtendedKeyUsage = picky_asn1_der::from_bytes(&encoded).unwrap();
+        assert_eq!(decoded, all);
+        assert!(decoded.ocsp_signing());
+        assert!(decoded.time_stamping());
+        assert!(decoded.email_protection());
+    }
+
+    #[test]
+    fn extended_key_usage_private_purpose() {
+        let private_purpose: oid::ObjectIdentifier = "1.3.6.1.4.1.311.10.3.4".try_into().unwrap();
+        let eku = ExtendedKeyUsage::default()
+            .with_client_auth()
+            .with_purpose(private_purpose.clone());
+        let encoded = picky_asn1_der::to_vec(&eku).unwrap();
+        let decoded: ExtendedKeyUsage = picky_asn1_der::from_bytes(&encoded).unwrap();
+        assert!(decoded.contains(private_purpose.clone()));
+        assert!(decoded.client_auth());
+        assert_eq!(
+            decoded.iter().map(|oid| (&oid.0).into()).collect::<Vec<String>>(),
+            vec!["1.3.6.1.5.5.7.3.2".to_owned(), "1.3.6.1.4.1.311.10.3.4".to_owned()]
+        );
+    }
+
     #[test]
     fn eku_ku_bc_san_extensions() {
         let cert_der = base64::decode(
@@ -4,11 +4,14 @@ mod macros;
 pub mod algorithm_identifier;
 pub mod attribute_type_and_value;
 pub mod certificate;
+pub mod certificate_list;
 pub mod certification_request;
 pub mod directory_string;
 pub mod extension;
 pub mod name;
+pub mod ocsp;
 pub mod oids;
+pub mod pkcs7;
 pub mod private_key_info;
 pub mod subject_public_key_info;
 pub mod validity;
@@ -17,10 +20,13 @@ pub mod version;
 pub use algorithm_identifier::*;
 pub use attribute_type_and_value::*;
 pub use certificate::*;
+pub use certificate_list::*;
 pub use certification_request::*;
 pub use directory_string::*;
 pub use extension::*;
 pub use name::*;
+pub use ocsp::*;
+pub use pkcs7::*;
 pub use private_key_info::*;
 pub use subject_public_key_info::*;
 pub use validity::*;
@@ -2,11 +2,13 @@ use crate::{AttributeTypeAndValue, AttributeTypeAndValueParameters, DirectoryStr
 use picky_asn1::{
     tag::{Tag, TagPeeker},
     wrapper::{
-        ApplicationTag1, ApplicationTag2, ApplicationTag4, ApplicationTag5, ApplicationTag6, ApplicationTag7,
-        ApplicationTag8, Asn1SequenceOf, Asn1SetOf, ContextTag0, ContextTag1, ContextTag2, ContextTag4, ContextTag5,
-        ContextTag6, ContextTag7, ContextTag8, IA5StringAsn1, Implicit, ObjectIdentifierAsn1, OctetStringAsn1,
+        ApplicationTag0, ApplicationTag1, ApplicationTag2, ApplicationTag4, ApplicationTag5, ApplicationTag6,
+        ApplicationTag7, ApplicationTag8, Asn1SequenceOf, Asn1SetOf, ContextTag0, ContextTag1, ContextTag2,
+        ContextTag4, ContextTag5, ContextTag6, ContextTag7, ContextTag8, IA5StringAsn1, Implicit, ObjectIdentifierAsn1,
+        OctetStringAsn1,
     },
 };
+use picky_asn1_der::Asn1RawDer;
 use serde::{de, ser, Deserialize, Serialize};
 use std::fmt;
 
@@ -72,14 +74,38 @@ impl Name {
 
     /// Find the first common name contained in this `Name`
     pub fn find_common_name(&self) -> Option<&DirectoryString> {
-        for relative_distinguished_name in &((self.0).0) {
-            for attr_ty_val in &relative_distinguished_name.0 {
-                if let AttributeTypeAndValueParameters::CommonName(dir_string) = &attr_ty_val.value {
-                    return Some(dir_string);
-                }
+        self.find_attr(NameAttr::CommonName)
+    }
+
+    /// Find the first value of the given attribute type contained in this `Name`
+    pub fn find_attr(&self, attr: NameAttr) -> Option<&DirectoryString> {
+        self.attributes().find_map(|attr_ty_val| match (&attr, &attr_ty_val.value) {
+            (NameAttr::CommonName, AttributeTypeAndValueParameters::CommonName(value))
+            | (NameAttr::Surname, AttributeTypeAndValueParameters::Surname(value))
+            | (NameAttr::SerialNumber, AttributeTypeAndValueParameters::SerialNumber(value))
+            | (NameAttr::CountryName, AttributeTypeAndValueParameters::CountryName(value))
+            | (NameAttr::LocalityName, AttributeTypeAndValueParameters::LocalityName(value))
+            | (NameAttr::StateOrProvinceName, AttributeTypeAndValueParameters::StateOrProvinceName(value))
+            | (NameAttr::StreetName, AttributeTypeAndValueParameters::StreetName(value))
+            | (NameAttr::OrganizationName, AttributeTypeAndValueParameters::OrganizationName(value))
+            | (NameAttr::OrganizationalUnitName, AttributeTypeAndValueParameters::OrganizationalUnitName(value)) => {
+                Some(value)
             }
-        }
-        None
+            _ => None,
+        })
+    }
+
+    /// Find the first emailAddress attribute contained in this `Name`
+    pub fn find_email(&self) -> Option<&IA5StringAsn1> {
+        self.attributes().find_map(|attr_ty_val| match &attr_ty_val.value {
+            AttributeTypeAndValueParameters::EmailAddress(value) => Some(value),
+            _ => None,
+        })
+    }
+
+    /// Iterates over the attributes of every relative distinguished name, in encoding order
+    pub fn attributes(&self) -> impl Iterator<Item = &AttributeTypeAndValue> {
+        (self.0).0.iter().flat_map(|rdn| rdn.0.iter())
     }
 
     pub fn add_attr<S: Into<DirectoryString>>(&mut self, attr: NameAttr, value: S) {
@@ -116,13 +142,15 @@ pub struct NamePrettyFormatter<'a>(pub &'a Name);
 
 impl fmt::Display for NamePrettyFormatter<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let mut first = true;
-        for name in &((self.0).0).0 {
-            for attr in &name.0 {
-                if first {
-                    first = false;
-                } else {
-                    write!(f, ",")?;
+        for (rdn_idx, name) in ((self.0).0).0.iter().enumerate() {
+            if rdn_idx != 0 {
+                write!(f, ",")?;
+            }
+
+            // attributes of a multi-valued RDN are separated by a plus sign
+            for (attr_idx, attr) in name.0.iter().enumerate() {
+                if attr_idx != 0 {
+                    write!(f, "+")?;
                 }
 
                 match &attr.value {
@@ -156,6 +184,12 @@ impl fmt::Display for NamePrettyFormatter<'_> {
                     AttributeTypeAndValueParameters::EmailAddress(name) => {
                         write!(f, "EMAIL={}", String::from_utf8_lossy(name.as_bytes()))?;
                     }
+                    AttributeTypeAndValueParameters::Custom(der) => {
+                        write!(f, "{}=#", Into::<String>::into(&attr.ty.0))?;
+                        for byte in &der.0 {
+                            write!(f, "{:02X}", byte)?;
+                        }
+                    }
                 }
             }
         }
@@ -186,10 +220,11 @@ pub type GeneralNames = Asn1SequenceOf<GeneralName>;
 /// ```
 #[derive(Debug, PartialEq, Clone)]
 pub enum GeneralName {
-    //OtherName(OtherName),
+    OtherName(OtherName),
     RFC822Name(IA5StringAsn1),
     DNSName(IA5StringAsn1),
-    //X400Address(ORAddress),
+    /// ORAddress is not supported, raw DER (including the choice tag) is preserved instead
+    X400Address(Asn1RawDer),
     DirectoryName(Name),
     EDIPartyName(EDIPartyName),
     URI(IA5StringAsn1),
@@ -198,6 +233,17 @@ pub enum GeneralName {
 }
 
 impl GeneralName {
+    pub fn new_other_name<OID, V>(type_id: OID, value: V) -> Self
+    where
+        OID: Into<ObjectIdentifierAsn1>,
+        V: Into<Vec<u8>>,
+    {
+        Self::OtherName(OtherName {
+            type_id: type_id.into(),
+            value: ApplicationTag0(Asn1RawDer(value.into())),
+        })
+    }
+
     pub fn new_edi_party_name<PN, NA>(party_name: PN, name_assigner: Option<NA>) -> Self
     where
         PN: Into<DirectoryString>,
@@ -222,9 +268,17 @@ impl ser::Serialize for GeneralName {
         S: ser::Serializer,
     {
         match &self {
+            GeneralName::OtherName(name) => {
+                // OtherName is implicitly tagged: SEQUENCE tag is replaced by the constructed choice tag
+                let mut der = picky_asn1_der::to_vec(name).map_err(ser::Error::custom)?;
+                der[0] = Tag::APP_0.number();
+                Asn1RawDer(der).serialize(serializer)
+            }
             GeneralName::RFC822Name(name) => ContextTag1(name).serialize(serializer),
             GeneralName::DNSName(name) => ContextTag2(name).serialize(serializer),
-            GeneralName::DirectoryName(name) => ContextTag4(name).serialize(serializer),
+            GeneralName::X400Address(raw) => raw.serialize(serializer),
+            // Name is a CHOICE and therefore always explicitly tagged
+            GeneralName::DirectoryName(name) => ApplicationTag4(name).serialize(serializer),
             GeneralName::EDIPartyName(name) => ContextTag5(name).serialize(serializer),
             GeneralName::URI(name) => ContextTag6(name).serialize(serializer),
             GeneralName::IpAddress(name) => ContextTag7(name).serialize(serializer),
@@ -253,11 +307,14 @@ impl<'de> de::Deserialize<'de> for GeneralName {
             {
                 let tag_peeker: TagPeeker = seq_next_element!(seq, DirectoryString, "choice tag");
                 match tag_peeker.next_tag {
-                    Tag::CTX_0 | Tag::APP_0 => Err(serde_invalid_value!(
-                        GeneralName,
-                        "OtherName not supported",
-                        "a supported choice"
-                    )),
+                    Tag::CTX_0 | Tag::APP_0 => {
+                        let mut der = seq_next_element!(seq, Asn1RawDer, GeneralName, "OtherName").0;
+                        // OtherName is implicitly tagged: restore SEQUENCE tag to decode it
+                        der[0] = Tag::SEQUENCE.number();
+                        let other_name = picky_asn1_der::from_bytes(&der)
+                            .map_err(|_| serde_invalid_value!(GeneralName, "invalid OtherName", "a valid OtherName"))?;
+                        Ok(GeneralName::OtherName(other_name))
+                    }
                     Tag::CTX_1 => Ok(GeneralName::RFC822Name(
                         seq_next_element!(seq, ContextTag1<IA5StringAsn1>, GeneralName, "RFC822Name").0,
                     )),
@@ -270,11 +327,12 @@ impl<'de> de::Deserialize<'de> for GeneralName {
                     Tag::APP_2 => Ok(GeneralName::DNSName(
                         seq_next_element!(seq, ApplicationTag2<IA5StringAsn1>, GeneralName, "DNSName").0,
                     )),
-                    Tag::CTX_3 | Tag::APP_3 => Err(serde_invalid_value!(
+                    Tag::CTX_3 | Tag::APP_3 => Ok(GeneralName::X400Address(seq_next_element!(
+                        seq,
+                        Asn1RawDer,
                         GeneralName,
-                        "X400Address not supported",
-                        "a supported choice"
-                    )),
+                        "X400Address"
+                    ))),
                     Tag::CTX_4 => Ok(GeneralName::DirectoryName(
                         seq_next_element!(seq, ContextTag4<Name>, GeneralName, "DirectoryName").0,
                     )),
@@ -317,8 +375,10 @@ impl<'de> de::Deserialize<'de> for GeneralName {
         deserializer.deserialize_enum(
             "GeneralName",
             &[
+                "OtherName",
                 "RFC822Name",
                 "DNSName",
+                "X400Address",
                 "DirectoryName",
                 "EDIPartyName",
                 "URI",
@@ -330,10 +390,20 @@ impl<'de> de::Deserialize<'de> for GeneralName {
     }
 }
 
-// OtherName ::= SEQUENCE {
-//      type-id    OBJECT IDENTIFIER,
-//      value      [0] EXPLICIT ANY DEFINED BY type-id }
-//pub struct OtherName { ... }
+/// [RFC 5280 #4.2.1.6](https://tools.ietf.org/html/rfc5280#section-4.2.1.6)
+///
+/// ```not_rust
+/// OtherName ::= SEQUENCE {
+///      type-id    OBJECT IDENTIFIER,
+///      value      [0] EXPLICIT ANY DEFINED BY type-id }
+/// ```
+///
+/// Value is kept as raw DER since its type depends on `type_id` (e.g. Microsoft UPN is an UTF8String).
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct OtherName {
+    pub type_id: ObjectIdentifierAsn1,
+    pub value: ApplicationTag0<Asn1RawDer>,
+}
 
 /// [RFC 5280 #4.2.1.6](https://tools.ietf.org/html/rfc5280#section-4.2.1.6)
 ///
@@ -352,7 +422,7 @@ pub struct EDIPartyName {
 mod tests {
     use super::*;
     use picky_asn1::restricted_string::IA5String;
-    use std::str::FromStr;
+    use std::{convert::TryInto, str::FromStr};
 
     #[test]
     fn common_name() {
@@ -404,6 +474,51 @@ mod tests {
         check_serde!(expected: Name in encoded);
     }
 
+    #[test]
+    fn multi_valued_rdn_and_unknown_attribute() {
+        // same encoding as OpenSSL with `-multivalue-rdn -subj "/C=CA/O=Acme/CN=x+OU=Engineering/DC=example"`
+        #[rustfmt::skip]
+        let encoded = [
+            0x30, 0x55, // sequence
+            0x31, 0x0B, // set 1 (country)
+            0x30, 0x09,
+            0x06, 0x03, 0x55, 0x04, 0x06,
+            0x13, 0x02, b'C', b'A', // printable string
+            0x31, 0x0D, // set 2 (organization)
+            0x30, 0x0B,
+            0x06, 0x03, 0x55, 0x04, 0x0A,
+            0x0C, 0x04, b'A', b'c', b'm', b'e',
+            0x31, 0x1E, // set 3 (common name + organizational unit)
+            0x30, 0x08,
+            0x06, 0x03, 0x55, 0x04, 0x03,
+            0x0C, 0x01, b'x',
+            0x30, 0x12,
+            0x06, 0x03, 0x55, 0x04, 0x0B,
+            0x0C, 0x0B, b'E', b'n', b'g', b'i', b'n', b'e', b'e', b'r', b'i', b'n', b'g',
+            0x31, 0x17, // set 4 (domain component, not supported)
+            0x30, 0x15,
+            0x06, 0x0A, 0x09, 0x92, 0x26, 0x89, 0x93, 0xF2, 0x2C, 0x64, 0x01, 0x19,
+            0x16, 0x07, b'e', b'x', b'a', b'm', b'p', b'l', b'e', // IA5String
+        ];
+
+        let name: Name = picky_asn1_der::from_bytes(&encoded).expect("couldn't decode name");
+        assert_eq!(picky_asn1_der::to_vec(&name).unwrap(), encoded);
+
+        assert_eq!(((name.0).0).len(), 4);
+        assert_eq!(((name.0).0)[2].0.len(), 2);
+        assert_eq!(name.find_common_name().unwrap().to_string(), "x");
+        assert_eq!(
+            name.find_attr(NameAttr::OrganizationalUnitName).unwrap().to_string(),
+            "Engineering"
+        );
+        assert_eq!(name.find_attr(NameAttr::CountryName).unwrap().to_string(), "CA");
+        assert_eq!(name.find_attr(NameAttr::LocalityName), None);
+        assert_eq!(
+            name.to_string(),
+            "C=CA,O=Acme,CN=x+OU=Engineering,0.9.2342.19200300.100.1.25=#16076578616D706C65"
+        );
+    }
+
     #[test]
     fn general_name_dns() {
         #[rustfmt::skip]
@@ -414,4 +529,68 @@ mod tests {
         let expected = GeneralName::DNSName(IA5String::from_string("devel.example.com".into()).unwrap().into());
         check_serde!(expected: GeneralName in encoded);
     }
+
+    #[test]
+    fn general_names_dns_ip_email() {
+        // same encoding as OpenSSL with
+        // `subjectAltName=DNS:example.com,IP:192.168.1.1,IP:2001:db8::1,email:admin@example.com`
+        #[rustfmt::skip]
+        let encoded = [
+            0x30, 0x38, // sequence
+            0x82, 0x0B, // dNSName
+            b'e', b'x', b'a', b'm', b'p', b'l', b'e', b'.', b'c', b'o', b'm',
+            0x87, 0x04, // iPAddress (v4)
+            0xC0, 0xA8, 0x01, 0x01,
+            0x87, 0x10, // iPAddress (v6)
+            0x20, 0x01, 0x0D, 0xB8, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+            0x81, 0x11, // rfc822Name
+            b'a', b'd', b'm', b'i', b'n', b'@', b'e', b'x', b'a', b'm', b'p', b'l', b'e', b'.', b'c', b'o', b'm',
+        ];
+        let expected: GeneralNames = vec![
+            GeneralName::DNSName(IA5String::from_string("example.com".into()).unwrap().into()),
+            GeneralName::IpAddress(vec![192, 168, 1, 1].into()),
+            GeneralName::IpAddress(vec![0x20, 0x01, 0x0D, 0xB8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1].into()),
+            GeneralName::RFC822Name(IA5String::from_string("admin@example.com".into()).unwrap().into()),
+        ]
+        .into();
+        check_serde!(expected: GeneralNames in encoded);
+    }
+
+    #[test]
+    fn general_names_other_name_uri_directory_name() {
+        // same encoding as OpenSSL with
+        // `subjectAltName=otherName:1.3.6.1.4.1.311.20.2.3;UTF8:user@example.com,URI:https://example.com/,dirName:dir_sect`
+        #[rustfmt::skip]
+        let encoded = [
+            0x30, 0x4C, // sequence
+            0xA0, 0x20, // otherName
+            0x06, 0x0A, 0x2B, 0x06, 0x01, 0x04, 0x01, 0x82, 0x37, 0x14, 0x02, 0x03, // type-id (Microsoft UPN)
+            0xA0, 0x12, // value
+            0x0C, 0x10,
+            b'u', b's', b'e', b'r', b'@', b'e', b'x', b'a', b'm', b'p', b'l', b'e', b'.', b'c', b'o', b'm',
+            0x86, 0x14, // uniformResourceIdentifier
+            b'h', b't', b't', b'p', b's', b':', b'/', b'/', b'e', b'x', b'a', b'm', b'p', b'l', b'e', b'.', b'c', b'o',
+            b'm', b'/',
+            0xA4, 0x12, // directoryName
+            0x30, 0x10, 0x31, 0x0E, 0x30, 0x0C, 0x06, 0x03, 0x55, 0x04, 0x03, 0x0C, 0x05,
+            b'p', b'i', b'c', b'k', b'y',
+        ];
+        let upn_oid: oid::ObjectIdentifier = "1.3.6.1.4.1.311.20.2.3".try_into().unwrap();
+        let upn_value = picky_asn1_der::to_vec(&DirectoryString::from("user@example.com")).unwrap();
+        let expected: GeneralNames = vec![
+            GeneralName::new_other_name(upn_oid, upn_value),
+            GeneralName::URI(IA5String::from_string("https://example.com/".into()).unwrap().into()),
+            GeneralName::DirectoryName(Name::new_common_name("picky")),
+        ]
+        .into();
+        check_serde!(expected: GeneralNames in encoded);
+    }
+
+    #[test]
+    fn general_name_x400_address_is_preserved() {
+        let encoded = [0x30, 0x05, 0xA3, 0x03, 0x04, 0x01, 0xFF];
+        let expected: GeneralNames =
+            vec![GeneralName::X400Address(Asn1RawDer(vec![0xA3, 0x03, 0x04, 0x01, 0xFF]))].into();
+        check_serde!(expected: GeneralNames in encoded);
+    }
 }
@@ -0,0 +1,955 @@
+use crate::{AlgorithmIdentifier, Certificate, CrlReason, Extension, Extensions, GeneralName, Name};
+use picky_asn1::{
+    tag::{Tag, TagPeeker},
+    wrapper::{
+        ApplicationTag0, ApplicationTag1, ApplicationTag2, Asn1SequenceOf, BitStringAsn1, ContextTag0, ContextTag2,
+        GeneralizedTimeAsn1, IntegerAsn1, ObjectIdentifierAsn1, OctetStringAsn1, OctetStringAsn1Container,
+    },
+};
+use picky_asn1_der::Asn1RawDer;
+use serde::{de, ser, Deserialize, Serialize};
+use std::fmt;
+
+/// [RFC 6960 #4.1.1](https://tools.ietf.org/html/rfc6960#section-4.1.1)
+///
+/// ```not_rust
+/// OCSPRequest ::= SEQUENCE {
+///     tbsRequest                  TBSRequest,
+///     optionalSignature   [0]     EXPLICIT Signature OPTIONAL }
+/// ```
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct OcspRequest {
+    pub tbs_request: TBSRequest,
+    pub optional_signature: Option<ApplicationTag0<OcspSignature>>,
+}
+
+impl OcspRequest {
+    pub fn requests(&self) -> &[SingleRequest] {
+        &(self.tbs_request.request_list.0)
+    }
+
+    pub fn extensions(&self) -> &[Extension] {
+        match &self.tbs_request.request_extensions {
+            Some(extensions) => &(extensions.0).0,
+            None => &[],
+        }
+    }
+
+    /// Nonce extension as sent by the client, if any (RFC 6960 #4.4.1)
+    pub fn nonce(&self) -> Option<&Extension> {
+        self.extensions()
+            .iter()
+            .find(|ext| ext.extn_id() == &crate::oids::ocsp_nonce())
+    }
+}
+
+impl<'de> de::Deserialize<'de> for OcspRequest {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        struct Visitor;
+
+        impl<'de> de::Visitor<'de> for Visitor {
+            type Value = OcspRequest;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("struct OcspRequest")
+            }
+
+            fn visit_seq<V>(self, mut seq: V) -> Result<Self::Value, V::Error>
+            where
+                V: de::SeqAccess<'de>,
+            {
+                Ok(OcspRequest {
+                    tbs_request: seq_next_element!(seq, OcspRequest, "tbs request"),
+                    optional_signature: seq.next_element()?,
+                })
+            }
+        }
+
+        deserializer.deserialize_seq(Visitor)
+    }
+}
+
+/// [RFC 6960 #4.1.1](https://tools.ietf.org/html/rfc6960#section-4.1.1)
+///
+/// ```not_rust
+/// TBSRequest ::= SEQUENCE {
+///     version             [0]     EXPLICIT Version DEFAULT v1,
+///     requestorName       [1]     EXPLICIT GeneralName OPTIONAL,
+///     requestList                 SEQUENCE OF Request,
+///     requestExtensions   [2]     EXPLICIT Extensions OPTIONAL }
+/// ```
+///
+/// Only v1 exists: version is accepted when explicitly encoded but never serialized (DER omits DEFAULT values).
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct TBSRequest {
+    pub requestor_name: Option<ApplicationTag1<GeneralName>>,
+    pub request_list: Asn1SequenceOf<SingleRequest>,
+    pub request_extensions: Option<ApplicationTag2<Extensions>>,
+}
+
+impl<'de> de::Deserialize<'de> for TBSRequest {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        struct Visitor;
+
+        impl<'de> de::Visitor<'de> for Visitor {
+            type Value = TBSRequest;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("struct TBSRequest")
+            }
+
+            fn visit_seq<V>(self, mut seq: V) -> Result<Self::Value, V::Error>
+            where
+                V: de::SeqAccess<'de>,
+            {
+                let mut tag_peeker: TagPeeker = seq_next_element!(seq, TBSRequest, "version or request list tag");
+
+                if tag_peeker.next_tag == Tag::APP_0 {
+                    let version: ApplicationTag0<u8> = seq_next_element!(seq, TBSRequest, "version");
+                    if version.0 != 0 {
+                        return Err(serde_invalid_value!(
+                            TBSRequest,
+                            "unsupported OCSP request version",
+                            "a v1 OCSP request"
+                        ));
+                    }
+                    tag_peeker = seq_next_element!(seq, TBSRequest, "requestor name or request list tag");
+                }
+
+                let requestor_name = if tag_peeker.next_tag == Tag::APP_1 {
+                    Some(seq_next_element!(seq, TBSRequest, "requestor name"))
+                } else {
+                    None
+                };
+
+                Ok(TBSRequest {
+                    requestor_name,
+                    request_list: seq_next_element!(seq, TBSRequest, "request list"),
+                    request_extensions: seq.next_element()?,
+                })
+            }
+        }
+
+        deserializer.deserialize_seq(Visitor)
+    }
+}
+
+/// [RFC 6960 #4.1.1](https://tools.ietf.org/html/rfc6960#section-4.1.1)
+///
+/// ```not_rust
+/// Request ::= SEQUENCE {
+///     reqCert                     CertID,
+///     singleRequestExtensions     [0] EXPLICIT Extensions OPTIONAL }
+/// ```
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct SingleRequest {
+    pub req_cert: CertId,
+    pub single_request_extensions: Option<ApplicationTag0<Extensions>>,
+}
+
+impl<'de> de::Deserialize<'de> for SingleRequest {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        struct Visitor;
+
+        impl<'de> de::Visitor<'de> for Visitor {
+            type Value = SingleRequest;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("struct SingleRequest")
+            }
+
+            fn visit_seq<V>(self, mut seq: V) -> Result<Self::Value, V::Error>
+            where
+                V: de::SeqAccess<'de>,
+            {
+                Ok(SingleRequest {
+                    req_cert: seq_next_element!(seq, SingleRequest, "request certificate id"),
+                    single_request_extensions: seq.next_element()?,
+                })
+            }
+        }
+
+        deserializer.deserialize_seq(Visitor)
+    }
+}
+
+/// [RFC 6960 #4.1.1](https://tools.ietf.org/html/rfc6960#section-4.1.1)
+///
+/// ```not_rust
+/// CertID ::= SEQUENCE {
+///     hashAlgorithm       AlgorithmIdentifier,
+///     issuerNameHash      OCTET STRING, -- Hash of issuer's DN
+///     issuerKeyHash       OCTET STRING, -- Hash of issuer's public key
+///     serialNumber        CertificateSerialNumber }
+/// ```
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CertId {
+    pub hash_algorithm: AlgorithmIdentifier,
+    pub issuer_name_hash: OctetStringAsn1,
+    pub issuer_key_hash: OctetStringAsn1,
+    pub serial_number: IntegerAsn1,
+}
+
+/// [RFC 6960 #4.1.1](https://tools.ietf.org/html/rfc6960#section-4.1.1)
+///
+/// ```not_rust
+/// Signature ::= SEQUENCE {
+///     signatureAlgorithm      AlgorithmIdentifier,
+///     signature               BIT STRING,
+///     certs               [0] EXPLICIT SEQUENCE OF Certificate OPTIONAL }
+/// ```
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct OcspSignature {
+    pub signature_algorithm: AlgorithmIdentifier,
+    pub signature: BitStringAsn1,
+    pub certs: Option<ApplicationTag0<Asn1SequenceOf<Certificate>>>,
+}
+
+impl<'de> de::Deserialize<'de> for OcspSignature {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        struct Visitor;
+
+        impl<'de> de::Visitor<'de> for Visitor {
+            type Value = OcspSignature;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("struct OcspSignature")
+            }
+
+            fn visit_seq<V>(self, mut seq: V) -> Result<Self::Value, V::Error>
+            where
+                V: de::SeqAccess<'de>,
+            {
+                Ok(OcspSignature {
+                    signature_algorithm: seq_next_element!(seq, OcspSignature, "signature algorithm"),
+                    signature: seq_next_element!(seq, OcspSignature, "signature"),
+                    certs: seq.next_element()?,
+                })
+            }
+        }
+
+        deserializer.deserialize_seq(Visitor)
+    }
+}
+
+/// [RFC 6960 #4.2.1](https://tools.ietf.org/html/rfc6960#section-4.2.1)
+///
+/// ```not_rust
+/// OCSPResponse ::= SEQUENCE {
+///     responseStatus         OCSPResponseStatus,
+///     responseBytes          [0] EXPLICIT ResponseBytes OPTIONAL }
+/// ```
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct OcspResponse {
+    pub response_status: OcspResponseStatus,
+    pub response_bytes: Option<ApplicationTag0<ResponseBytes>>,
+}
+
+impl OcspResponse {
+    /// Unsuccessful response (no response bytes are sent along error statuses)
+    pub fn new_error(status: OcspResponseStatus) -> Self {
+        Self {
+            response_status: status,
+            response_bytes: None,
+        }
+    }
+
+    pub fn new_basic(basic_response: BasicOcspResponse) -> Self {
+        Self {
+            response_status: OcspResponseStatus::Successful,
+            response_bytes: Some(ApplicationTag0(ResponseBytes {
+                response_type: crate::oids::ocsp_basic().into(),
+                response: basic_response.into(),
+            })),
+        }
+    }
+
+    pub fn basic_response(&self) -> Option<&BasicOcspResponse> {
+        self.response_bytes.as_ref().map(|bytes| &(bytes.0).response.0)
+    }
+}
+
+impl<'de> de::Deserialize<'de> for OcspResponse {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        struct Visitor;
+
+        impl<'de> de::Visitor<'de> for Visitor {
+            type Value = OcspResponse;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("struct OcspResponse")
+            }
+
+            fn visit_seq<V>(self, mut seq: V) -> Result<Self::Value, V::Error>
+            where
+                V: de::SeqAccess<'de>,
+            {
+                Ok(OcspResponse {
+                    response_status: seq_next_element!(seq, OcspResponse, "response status"),
+                    response_bytes: seq.next_element()?,
+                })
+            }
+        }
+
+        deserializer.deserialize_seq(Visitor)
+    }
+}
+
+/// [RFC 6960 #4.2.1](https://tools.ietf.org/html/rfc6960#section-4.2.1)
+///
+/// ```not_rust
+/// OCSPResponseStatus ::= ENUMERATED {
+///     successful            (0),  -- Response has valid confirmations
+///     malformedRequest      (1),  -- Illegal confirmation request
+///     internalError         (2),  -- Internal error in issuer
+///     tryLater              (3),  -- Try again later
+///                                 -- (4) is not used
+///     sigRequired           (5),  -- Must sign the request
+///     unauthorized          (6)   -- Request unauthorized
+/// }
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[repr(u8)]
+pub enum OcspResponseStatus {
+    Successful = 0,
+    MalformedRequest = 1,
+    InternalError = 2,
+    TryLater = 3,
+    SigRequired = 5,
+    Unauthorized = 6,
+}
+
+impl OcspResponseStatus {
+    const ENUMERATED_TAG: u8 = 0x0A;
+
+    pub fn from_u8(v: u8) -> Option<Self> {
+        match v {
+            0 => Some(Self::Successful),
+            1 => Some(Self::MalformedRequest),
+            2 => Some(Self::InternalError),
+            3 => Some(Self::TryLater),
+            5 => Some(Self::SigRequired),
+            6 => Some(Self::Unauthorized),
+            _ => None,
+        }
+    }
+}
+
+// ENUMERATED isn't supported by picky-asn1: (de)serialize raw DER instead.
+
+impl ser::Serialize for OcspResponseStatus {
+    fn serialize<S>(&self, serializer: S) -> Result<<S as ser::Serializer>::Ok, <S as ser::Serializer>::Error>
+    where
+        S: ser::Serializer,
+    {
+        Asn1RawDer(vec![Self::ENUMERATED_TAG, 0x01, *self as u8]).serialize(serializer)
+    }
+}
+
+impl<'de> de::Deserialize<'de> for OcspResponseStatus {
+    fn deserialize<D>(deserializer: D) -> Result<Self, <D as de::Deserializer<'de>>::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let raw = Asn1RawDer::deserialize(deserializer)?;
+        match raw.0.as_slice() {
+            [Self::ENUMERATED_TAG, 0x01, value] => OcspResponseStatus::from_u8(*value).ok_or_else(|| {
+                serde_invalid_value!(
+                    OcspResponseStatus,
+                    "unknown response status",
+                    "a valid OCSPResponseStatus"
+                )
+            }),
+            _ => Err(serde_invalid_value!(
+                OcspResponseStatus,
+                "not an ENUMERATED",
+                "a DER-encoded OCSPResponseStatus"
+            )),
+        }
+    }
+}
+
+/// [RFC 6960 #4.2.1](https://tools.ietf.org/html/rfc6960#section-4.2.1)
+///
+/// ```not_rust
+/// ResponseBytes ::= SEQUENCE {
+///     responseType   OBJECT IDENTIFIER,
+///     response       OCTET STRING }
+/// ```
+///
+/// Only `id-pkix-ocsp-basic` responses are supported.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ResponseBytes {
+    pub response_type: ObjectIdentifierAsn1,
+    pub response: OctetStringAsn1Container<BasicOcspResponse>,
+}
+
+/// [RFC 6960 #4.2.1](https://tools.ietf.org/html/rfc6960#section-4.2.1)
+///
+/// ```not_rust
+/// BasicOCSPResponse ::= SEQUENCE {
+///    tbsResponseData      ResponseData,
+///    signatureAlgorithm   AlgorithmIdentifier,
+///    signature            BIT STRING,
+///    certs            [0] EXPLICIT SEQUENCE OF Certificate OPTIONAL }
+/// ```
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct BasicOcspResponse {
+    pub tbs_response_data: ResponseData,
+    pub signature_algorithm: AlgorithmIdentifier,
+    pub signature: BitStringAsn1,
+    pub certs: Option<ApplicationTag0<Asn1SequenceOf<Certificate>>>,
+}
+
+impl<'de> de::Deserialize<'de> for BasicOcspResponse {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        struct Visitor;
+
+        impl<'de> de::Visitor<'de> for Visitor {
+            type Value = BasicOcspResponse;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("struct BasicOcspResponse")
+            }
+
+            fn visit_seq<V>(self, mut seq: V) -> Result<Self::Value, V::Error>
+            where
+                V: de::SeqAccess<'de>,
+            {
+                Ok(BasicOcspResponse {
+                    tbs_response_data: seq_next_element!(seq, BasicOcspResponse, "tbs response data"),
+                    signature_algorithm: seq_next_element!(seq, BasicOcspResponse, "signature algorithm"),
+                    signature: seq_next_element!(seq, BasicOcspResponse, "signature"),
+                    certs: seq.next_element()?,
+                })
+            }
+        }
+
+        deserializer.deserialize_seq(Visitor)
+    }
+}
+
+/// [RFC 6960 #4.2.1](https://tools.ietf.org/html/rfc6960#section-4.2.1)
+///
+/// ```not_rust
+/// ResponseData ::= SEQUENCE {
+///    version              [0] EXPLICIT Version DEFAULT v1,
+///    responderID              ResponderID,
+///    producedAt               GeneralizedTime,
+///    responses                SEQUENCE OF SingleResponse,
+///    responseExtensions   [1] EXPLICIT Extensions OPTIONAL }
+/// ```
+///
+/// Only v1 exists: version is accepted when explicitly encoded but never serialized (DER omits DEFAULT values).
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct ResponseData {
+    pub responder_id: ResponderId,
+    pub produced_at: GeneralizedTimeAsn1,
+    pub responses: Asn1SequenceOf<SingleResponse>,
+    pub response_extensions: Option<ApplicationTag1<Extensions>>,
+}
+
+impl ResponseData {
+    pub fn extensions(&self) -> &[Extension] {
+        match &self.response_extensions {
+            Some(extensions) => &(extensions.0).0,
+            None => &[],
+        }
+    }
+}
+
+impl<'de> de::Deserialize<'de> for ResponseData {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        struct Visitor;
+
+        impl<'de> de::Visitor<'de> for Visitor {
+            type Value = ResponseData;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("struct ResponseData")
+            }
+
+            fn visit_seq<V>(self, mut seq: V) -> Result<Self::Value, V::Error>
+            where
+                V: de::SeqAccess<'de>,
+            {
+                let tag_peeker: TagPeeker = seq_next_element!(seq, ResponseData, "version or responder id tag");
+                if tag_peeker.next_tag == Tag::APP_0 {
+                    let version: ApplicationTag0<u8> = seq_next_element!(seq, ResponseData, "version");
+                    if version.0 != 0 {
+                        return Err(serde_invalid_value!(
+                            ResponseData,
+                            "unsupported OCSP response version",
+                            "a v1 OCSP response"
+                        ));
+                    }
+                }
+
+                Ok(ResponseData {
+                    responder_id: seq_next_element!(seq, ResponseData, "responder id"),
+                    produced_at: seq_next_element!(seq, ResponseData, "produced at"),
+                    responses: seq_next_element!(seq, ResponseData, "responses"),
+                    response_extensions: seq.next_element()?,
+                })
+            }
+        }
+
+        deserializer.deserialize_seq(Visitor)
+    }
+}
+
+/// [RFC 6960 #4.2.1](https://tools.ietf.org/html/rfc6960#section-4.2.1)
+///
+/// ```not_rust
+/// ResponderID ::= CHOICE {
+///    byName   [1] Name,
+///    byKey    [2] KeyHash }
+///
+/// KeyHash ::= OCTET STRING -- SHA-1 hash of responder's public key
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub enum ResponderId {
+    ByName(Name),
+    ByKey(OctetStringAsn1),
+}
+
+impl ser::Serialize for ResponderId {
+    fn serialize<S>(&self, serializer: S) -> Result<<S as ser::Serializer>::Ok, <S as ser::Serializer>::Error>
+    where
+        S: ser::Serializer,
+    {
+        // OCSP ASN.1 module uses explicit tagging by default
+        match &self {
+            ResponderId::ByName(name) => ApplicationTag1(name).serialize(serializer),
+            ResponderId::ByKey(key_hash) => ApplicationTag2(key_hash).serialize(serializer),
+        }
+    }
+}
+
+impl<'de> de::Deserialize<'de> for ResponderId {
+    fn deserialize<D>(deserializer: D) -> Result<Self, <D as de::Deserializer<'de>>::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        struct Visitor;
+
+        impl<'de> de::Visitor<'de> for Visitor {
+            type Value = ResponderId;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a valid DER-encoded ResponderID")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: de::SeqAccess<'de>,
+            {
+                let tag_peeker: TagPeeker = seq_next_element!(seq, ResponderId, "choice tag");
+                match tag_peeker.next_tag {
+                    Tag::APP_1 => Ok(ResponderId::ByName(
+                        seq_next_element!(seq, ApplicationTag1<Name>, ResponderId, "ByName").0,
+                    )),
+                    Tag::APP_2 => Ok(ResponderId::ByKey(
+                        seq_next_element!(seq, ApplicationTag2<OctetStringAsn1>, ResponderId, "ByKey").0,
+                    )),
+                    _ => Err(serde_invalid_value!(
+                        ResponderId,
+                        "unknown choice value",
+                        "a supported ResponderID choice"
+                    )),
+                }
+            }
+        }
+
+        deserializer.deserialize_enum("ResponderId", &["ByName", "ByKey"], Visitor)
+    }
+}
+
+/// [RFC 6960 #4.2.1](https://tools.ietf.org/html/rfc6960#section-4.2.1)
+///
+/// ```not_rust
+/// SingleResponse ::= SEQUENCE {
+///    certID                       CertID,
+///    certStatus                   CertStatus,
+///    thisUpdate                   GeneralizedTime,
+///    nextUpdate         [0]       EXPLICIT GeneralizedTime OPTIONAL,
+///    singleExtensions   [1]       EXPLICIT Extensions OPTIONAL }
+/// ```
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct SingleResponse {
+    pub cert_id: CertId,
+    pub cert_status: CertStatus,
+    pub this_update: GeneralizedTimeAsn1,
+    pub next_update: Option<ApplicationTag0<GeneralizedTimeAsn1>>,
+    pub single_extensions: Option<ApplicationTag1<Extensions>>,
+}
+
+impl<'de> de::Deserialize<'de> for SingleResponse {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        struct Visitor;
+
+        impl<'de> de::Visitor<'de> for Visitor {
+            type Value = SingleResponse;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("struct SingleResponse")
+            }
+
+            fn visit_seq<V>(self, mut seq: V) -> Result<Self::Value, V::Error>
+            where
+                V: de::SeqAccess<'de>,
+            {
+                let cert_id = seq_next_element!(seq, SingleResponse, "cert id");
+                let cert_status = seq_next_element!(seq, SingleResponse, "cert status");
+                let this_update = seq_next_element!(seq, SingleResponse, "this update");
+
+                let mut next_tag = seq.next_element::<TagPeeker>()?.map(|peeker| peeker.next_tag);
+
+                let next_update = match next_tag {
+                    Some(Tag::APP_0) => {
+                        let next_update = seq_next_element!(seq, SingleResponse, "next update");
+                        next_tag = seq.next_element::<TagPeeker>()?.map(|peeker| peeker.next_tag);
+                        Some(next_update)
+                    }
+                    _ => None,
+                };
+
+                let single_extensions = match next_tag {
+                    Some(Tag::APP_1) => Some(seq_next_element!(seq, SingleResponse, "single extensions")),
+                    Some(_) => {
+                        return Err(serde_invalid_value!(
+                            SingleResponse,
+                            "unexpected trailing element",
+                            "single extensions"
+                        ))
+                    }
+                    None => None,
+                };
+
+                Ok(SingleResponse {
+                    cert_id,
+                    cert_status,
+                    this_update,
+                    next_update,
+                    single_extensions,
+                })
+            }
+        }
+
+        deserializer.deserialize_seq(Visitor)
+    }
+}
+
+/// [RFC 6960 #4.2.1](https://tools.ietf.org/html/rfc6960#section-4.2.1)
+///
+/// ```not_rust
+/// CertStatus ::= CHOICE {
+///     good        [0]     IMPLICIT NULL,
+///     revoked     [1]     IMPLICIT RevokedInfo,
+///     unknown     [2]     IMPLICIT UnknownInfo }
+///
+/// UnknownInfo ::= NULL
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub enum CertStatus {
+    Good,
+    Revoked(RevokedInfo),
+    Unknown,
+}
+
+impl ser::Serialize for CertStatus {
+    fn serialize<S>(&self, serializer: S) -> Result<<S as ser::Serializer>::Ok, <S as ser::Serializer>::Error>
+    where
+        S: ser::Serializer,
+    {
+        match &self {
+            CertStatus::Good => ContextTag0(()).serialize(serializer),
+            CertStatus::Revoked(info) => {
+                // RevokedInfo is implicitly tagged: SEQUENCE tag is replaced by the constructed choice tag
+                let mut der = picky_asn1_der::to_vec(info).map_err(ser::Error::custom)?;
+                der[0] = Tag::APP_1.number();
+                Asn1RawDer(der).serialize(serializer)
+            }
+            CertStatus::Unknown => ContextTag2(()).serialize(serializer),
+        }
+    }
+}
+
+impl<'de> de::Deserialize<'de> for CertStatus {
+    fn deserialize<D>(deserializer: D) -> Result<Self, <D as de::Deserializer<'de>>::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        struct Visitor;
+
+        impl<'de> de::Visitor<'de> for Visitor {
+            type Value = CertStatus;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a valid DER-encoded CertStatus")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: de::SeqAccess<'de>,
+            {
+                let tag_peeker: TagPeeker = seq_next_element!(seq, CertStatus, "choice tag");
+                match tag_peeker.next_tag {
+                    Tag::CTX_0 => {
+                        seq_next_element!(seq, ContextTag0<()>, CertStatus, "Good");
+                        Ok(CertStatus::Good)
+                    }
+                    Tag::APP_1 => {
+                        let mut der = seq_next_element!(seq, Asn1RawDer, CertStatus, "Revoked").0;
+                        // RevokedInfo is implicitly tagged: restore SEQUENCE tag to decode it
+                        der[0] = Tag::SEQUENCE.number();
+                        let info = picky_asn1_der::from_bytes(&der).map_err(|_| {
+                            serde_invalid_value!(CertStatus, "invalid RevokedInfo", "a valid RevokedInfo")
+                        })?;
+                        Ok(CertStatus::Revoked(info))
+                    }
+                    Tag::CTX_2 => {
+                        seq_next_element!(seq, ContextTag2<()>, CertStatus, "Unknown");
+                        Ok(CertStatus::Unknown)
+                    }
+                    _ => Err(serde_invalid_value!(
+                        CertStatus,
+                        "unknown choice value",
+                        "a supported CertStatus choice"
+                    )),
+                }
+            }
+        }
+
+        deserializer.deserialize_enum("CertStatus", &["Good", "Revoked", "Unknown"], Visitor)
+    }
+}
+
+/// [RFC 6960 #4.2.1](https://tools.ietf.org/html/rfc6960#section-4.2.1)
+///
+/// ```not_rust
+/// RevokedInfo ::= SEQUENCE {
+///     revocationTime              GeneralizedTime,
+///     revocationReason    [0]     EXPLICIT CRLReason OPTIONAL }
+/// ```
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct RevokedInfo {
+    pub revocation_time: GeneralizedTimeAsn1,
+    pub revocation_reason: Option<ApplicationTag0<CrlReason>>,
+}
+
+impl<'de> de::Deserialize<'de> for RevokedInfo {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        struct Visitor;
+
+        impl<'de> de::Visitor<'de> for Visitor {
+            type Value = RevokedInfo;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("struct RevokedInfo")
+            }
+
+            fn visit_seq<V>(self, mut seq: V) -> Result<Self::Value, V::Error>
+            where
+                V: de::SeqAccess<'de>,
+            {
+                Ok(RevokedInfo {
+                    revocation_time: seq_next_element!(seq, RevokedInfo, "revocation time"),
+                    revocation_reason: seq.next_element()?,
+                })
+            }
+        }
+
+        deserializer.deserialize_seq(Visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DirectoryName, SHAVariant};
+    use picky_asn1::{bit_string::BitString, date::GeneralizedTime};
+
+    fn openssl_cert_id() -> CertId {
+        CertId {
+            hash_algorithm: AlgorithmIdentifier::new_sha(SHAVariant::SHA1),
+            issuer_name_hash: vec![
+                0x7E, 0x3B, 0x6E, 0xC0, 0xF0, 0xFF, 0x51, 0xD1, 0x1A, 0x3E, 0x1A, 0x4C, 0x86, 0xBE, 0x04, 0x13, 0x3A,
+                0xFB, 0x19, 0xCC,
+            ]
+            .into(),
+            issuer_key_hash: vec![
+                0x4E, 0x44, 0x90, 0xD9, 0x11, 0x0E, 0x61, 0x6B, 0xE8, 0x86, 0xDE, 0xC6, 0x53, 0x40, 0xE3, 0x9F, 0xB7,
+                0x49, 0x09, 0x7C,
+            ]
+            .into(),
+            serial_number: vec![0x01].into(),
+        }
+    }
+
+    #[test]
+    fn openssl_request_with_nonce() {
+        // generated using `openssl ocsp -issuer ca.crt -cert a.crt -reqout req.der`
+        let encoded = base64::decode(
+            "MGcwZTA+MDwwOjAJBgUrDgMCGgUABBR+O27A8P9R0Ro+GkyGvgQTOvsZzAQUTkSQ2REOYWvoht7GU0Djn7dJCXwCAQGiIzAhMB8G\
+             CSsGAQUFBzABAgQSBBB1gKlC1oenb3VRg//il6pH",
+        )
+        .expect("invalid base64");
+
+        let cert_id = openssl_cert_id();
+        check_serde!(cert_id: CertId in encoded[8..68]);
+
+        let request: OcspRequest = picky_asn1_der::from_bytes(&encoded).expect("deserialization");
+        assert_eq!(request.requests().len(), 1);
+        assert_eq!(request.requests()[0].req_cert, cert_id);
+        assert_eq!(request.nonce().unwrap().extn_id(), &crate::oids::ocsp_nonce());
+
+        let reencoded = picky_asn1_der::to_vec(&request).expect("serialization");
+        assert_eq!(reencoded, encoded);
+    }
+
+    #[test]
+    fn minimal_request() {
+        let request = OcspRequest {
+            tbs_request: TBSRequest {
+                requestor_name: None,
+                request_list: vec![SingleRequest {
+                    req_cert: openssl_cert_id(),
+                    single_request_extensions: None,
+                }]
+                .into(),
+                request_extensions: None,
+            },
+            optional_signature: None,
+        };
+        let encoded = base64::decode(
+            "MEIwQDA+MDwwOjAJBgUrDgMCGgUABBR+O27A8P9R0Ro+GkyGvgQTOvsZzAQUTkSQ2REOYWvoht7GU0Djn7dJCXwCAQE=",
+        )
+        .expect("invalid base64");
+        check_serde!(request: OcspRequest in encoded);
+    }
+
+    #[test]
+    fn cert_status() {
+        let encoded = [0x80, 0x00];
+        let good = CertStatus::Good;
+        check_serde!(good: CertStatus in encoded);
+
+        let encoded = [0x82, 0x00];
+        let unknown = CertStatus::Unknown;
+        check_serde!(unknown: CertStatus in encoded);
+
+        #[rustfmt::skip]
+        let encoded = [
+            0xA1, 0x16,
+                0x18, 0x0F, 0x32, 0x30, 0x32, 0x36, 0x31, 0x30, 0x31, 0x37,
+                            0x30, 0x32, 0x34, 0x30, 0x33, 0x30, 0x5A,
+                0xA0, 0x03, 0x0A, 0x01, 0x01,
+        ];
+        let revoked = CertStatus::Revoked(RevokedInfo {
+            revocation_time: GeneralizedTime::new(2026, 10, 17, 2, 40, 30).unwrap().into(),
+            revocation_reason: Some(CrlReason::KeyCompromise.into()),
+        });
+        check_serde!(revoked: CertStatus in encoded);
+    }
+
+    #[test]
+    fn response_status() {
+        let encoded = [0x0A, 0x01, 0x06];
+        let status = OcspResponseStatus::Unauthorized;
+        check_serde!(status: OcspResponseStatus in encoded);
+
+        let encoded = [0x30, 0x03, 0x0A, 0x01, 0x01];
+        let error_response = OcspResponse::new_error(OcspResponseStatus::MalformedRequest);
+        check_serde!(error_response: OcspResponse in encoded);
+    }
+
+    #[test]
+    fn openssl_response() {
+        // generated using `openssl ocsp -index index.txt -rsigner ca.crt -rkey ca.key -CA ca.crt`
+        // in response to the request from `openssl_request_with_nonce` test
+        let encoded = base64::decode(
+            "MIIFIwoBAKCCBRwwggUYBgkrBgEFBQcwAQEEggUJMIIFBTCBz6EbMBkxFzAVBgNVBAMMDlRlc3QgQXV0aG9yaXR5GA8yMDI2MTAx\
+             NzAyNTExMFowejB4MDowCQYFKw4DAhoFAAQUfjtuwPD/UdEaPhpMhr4EEzr7GcwEFE5EkNkRDmFr6IbexlNA45+3SQl8AgEBoRYY\
+             DzIwMjYxMDE3MDI0MDMwWqADCgEBGA8yMDI2MTAxNzAyNTExMFqgERgPMjAyNjEwMjQwMjUxMTBaoSMwITAfBgkrBgEFBQcwAQIE\
+             EgQQdYCpQtaHp291UYP/4peqRzANBgkqhkiG9w0BAQsFAAOCAQEAjWRECvWt6ZnEnCeW3J3/hs6Y2FyeACCrohpBhkb8eEnqGnLj\
+             7Citpc9AWlbIWjhMQhNSdswEkF/XldPG1Pyy7AHOsVc//54l9jB1ZJH6V2vbt3337s6xmBqmoniGRDuhKLEpnWPeWvosWWB/Ssj2\
+             L6JXu4WeDgV6axU5/Hb2yKZXL3JTgArR4bwqTiV0zNQ/YGkc1pvpz1Wel1TW3Nju/LVTVn/zI3hRY3GT7vp5hZRlHc7Myjs06hja\
+             dczQT02vkJ+JfdOzQg2JGnDqZziKSV1siJSi1ZYKcSZrVRLMVXImPeQWgRnf6jh8G2e0kpXpxa+JJ0pwkkBbWeEM/8mUaqCCAxsw\
+             ggMXMIIDEzCCAfugAwIBAgIUcLDshzJQDTVjOGXBoQ3cPa4eoAEwDQYJKoZIhvcNAQELBQAwGTEXMBUGA1UEAwwOVGVzdCBBdXRo\
+             b3JpdHkwHhcNMjYxMDE3MDI0MDMwWhcNMzYxMDE0MDI0MDMwWjAZMRcwFQYDVQQDDA5UZXN0IEF1dGhvcml0eTCCASIwDQYJKoZI\
+             hvcNAQEBBQADggEPADCCAQoCggEBAMLOq2OdJp/vh36aRW47EaRhwXO6RZC9iv7HQQJrs4WBaTtPhOt93yqhiFHGZsQAC3S4PyUh\
+             FDtkWjCrurAEnxYBUjbG+l0qx3dAqJPvCsIaQSXculUeZ07muNJRMx7Z8U5hzCEMY9WcXGLKcS+pOdIRdunMv39GirznGlAoOYD3\
+             zvPtR2w6EmAgLl/buFVFkfmjzS3iVvJItwG1ahbludRGTGFaHL3u5eZa8e3dQOLXlUKqSp5VpywJe1wNEXMG4Io8nD3LkqJkBO7X\
+             S5bQfgoHHxZ43bp0dUpypnMhxOmhc4AOR8SjyVwwbVhXJxPHX77xxg7Fqmnr38MI3UuEI5UCAwEAAaNTMFEwHQYDVR0OBBYEFE5E\
+             kNkRDmFr6IbexlNA45+3SQl8MB8GA1UdIwQYMBaAFE5EkNkRDmFr6IbexlNA45+3SQl8MA8GA1UdEwEB/wQFMAMBAf8wDQYJKoZI\
+             hvcNAQELBQADggEBAGLpvBvwyrNrR3kX92crT0uQw281zpLLseiOrys8Qs21hY2X1BHHRAbrOS9lijAmKpnbasb+vl0dBIe66eqv\
+             0xG6MH3iplyLp79CqhjqqmVevwEJ+HXFcUDP/ud0+n07Z2iR2ti/GcgU8rc7ycen7r7A6z6WqDSGCC8UTdh34fA//V8b/jERx0lh\
+             6pMiKHAcPwr6ROlk3ESXpgU5sqFxKNm8mIGpOeLHyxBsnyxWuD1C+JniFSqW0a41ORQvs7W+//1nc8bBvTd8tb/TNvtF+SsJY4VZ\
+             GmGIvvthE0y81tbpkfxpfZlCZC8mIhmyIoRar8gxOyp76bsCaLQiZ9O9qPU=",
+        )
+        .expect("invalid base64");
+
+        let single_response = SingleResponse {
+            cert_id: openssl_cert_id(),
+            cert_status: CertStatus::Revoked(RevokedInfo {
+                revocation_time: GeneralizedTime::new(2026, 10, 17, 2, 40, 30).unwrap().into(),
+                revocation_reason: Some(CrlReason::KeyCompromise.into()),
+            }),
+            this_update: GeneralizedTime::new(2026, 10, 17, 2, 51, 10).unwrap().into(),
+            next_update: Some(ApplicationTag0(
+                GeneralizedTime::new(2026, 10, 24, 2, 51, 10).unwrap().into(),
+            )),
+            single_extensions: None,
+        };
+        check_serde!(single_response: SingleResponse in encoded[85..207]);
+
+        let nonce: Extension = picky_asn1_der::from_bytes(&encoded[211..244]).expect("nonce extension");
+        let tbs_response_data = ResponseData {
+            responder_id: ResponderId::ByName(DirectoryName::new_common_name("Test Authority")),
+            produced_at: GeneralizedTime::new(2026, 10, 17, 2, 51, 10).unwrap().into(),
+            responses: vec![single_response].into(),
+            response_extensions: Some(Extensions(vec![nonce]).into()),
+        };
+        check_serde!(tbs_response_data: ResponseData in encoded[34..244]);
+
+        let certificate: Certificate = picky_asn1_der::from_bytes(&encoded[528..]).expect("responder certificate");
+        let basic_response = BasicOcspResponse {
+            tbs_response_data,
+            signature_algorithm: AlgorithmIdentifier::new_sha256_with_rsa_encryption(),
+            signature: BitString::with_bytes(&encoded[264..520]).into(),
+            certs: Some(ApplicationTag0(vec![certificate].into())),
+        };
+        check_serde!(basic_response: BasicOcspResponse in encoded[30..1319]);
+
+        let response = OcspResponse::new_basic(basic_response);
+        check_serde!(response: OcspResponse in encoded);
+    }
+}
@@ -44,15 +44,26 @@ define_oid! {
     EC_PUBLIC_KEY => ec_public_key => "1.2.840.10045.2.1",
     ECDSA_WITH_SHA256 => ecdsa_with_sha256 => "1.2.840.10045.4.3.2",
     ECDSA_WITH_SHA384 => ecdsa_with_sha384 => "1.2.840.10045.4.3.3",
+    ECDSA_WITH_SHA512 => ecdsa_with_sha512 => "1.2.840.10045.4.3.4",
+    SECP256R1 => secp256r1 => "1.2.840.10045.3.1.7",
 
     // RSADSI
     RSA_ENCRYPTION => rsa_encryption => "1.2.840.113549.1.1.1",
+    MGF1 => mgf1 => "1.2.840.113549.1.1.8",
+    RSASSA_PSS => rsassa_pss => "1.2.840.113549.1.1.10",
     SHA1_WITH_RSA_ENCRYPTION => sha1_with_rsa_encryption => "1.2.840.113549.1.1.5",
     SHA256_WITH_RSA_ENCRYPTION => sha256_with_rsa_encryption => "1.2.840.113549.1.1.11",
     SHA384_WITH_RSA_ENCRYPTION => sha384_with_rsa_encryption => "1.2.840.113549.1.1.12",
     SHA512_WITH_RSA_ENCRYPTION => sha512_with_rsa_encryption => "1.2.840.113549.1.1.13",
     SHA224_WITH_RSA_ENCRYPTION => sha224_with_rsa_encryption => "1.2.840.113549.1.1.14",
+    PKCS7_DATA => pkcs7_data => "1.2.840.113549.1.7.1",
+    PKCS7_SIGNED_DATA => pkcs7_signed_data => "1.2.840.113549.1.7.2",
     EMAIL_ADDRESS => email_address => "1.2.840.113549.1.9.1", // deprecated
+    EXTENSION_REQUEST => extension_request => "1.2.840.113549.1.9.14",
+    PBKDF2 => pbkdf2 => "1.2.840.113549.1.5.12",
+    PBES2 => pbes2 => "1.2.840.113549.1.5.13",
+    HMAC_WITH_SHA1 => hmac_with_sha1 => "1.2.840.113549.2.7",
+    HMAC_WITH_SHA256 => hmac_with_sha256 => "1.2.840.113549.2.9",
 
     // NIST
     DSA_WITH_SHA224 => dsa_with_sha224 => "2.16.840.1.101.3.4.3.1",
@@ -65,6 +76,17 @@ define_oid! {
     ID_RSASSA_PKCS1_V1_5_WITH_SHA3_384 => id_rsassa_pkcs1_v1_5_with_sha3_384 => "2.16.840.1.101.3.4.3.15",
     ID_RSASSA_PKCS1_V1_5_WITH_SHA3_512 => id_rsassa_pkcs1_v1_5_with_sha3_512 => "2.16.840.1.101.3.4.3.16",
 
+    // OIW
+    SHA1 => sha1 => "1.3.14.3.2.26",
+
+    // OCSP
+    AD_OCSP => ad_ocsp => "1.3.6.1.5.5.7.48.1",
+    OCSP_BASIC => ocsp_basic => "1.3.6.1.5.5.7.48.1.1",
+    OCSP_NONCE => ocsp_nonce => "1.3.6.1.5.5.7.48.1.2",
+
+    // access methods
+    AD_CA_ISSUERS => ad_ca_issuers => "1.3.6.1.5.5.7.48.2",
+
     // Certicom Object Identifiers
     SECP384R1 => secp384r1 => "1.3.132.0.34",
 
@@ -97,8 +119,11 @@ define_oid! {
     SUBJECT_ALTERNATIVE_NAME => subject_alternative_name => "2.5.29.17",
     ISSUER_ALTERNATIVE_NAME => issuer_alternative_name => "2.5.29.18",
     BASIC_CONSTRAINTS => basic_constraints => "2.5.29.19",
+    CRL_NUMBER => crl_number => "2.5.29.20",
+    CRL_REASON_CODE => crl_reason_code => "2.5.29.21",
     AUTHORITY_KEY_IDENTIFIER => authority_key_identifier => "2.5.29.35",
     EXTENDED_KEY_USAGE => extended_key_usage => "2.5.29.37",
+    AUTHORITY_INFO_ACCESS => authority_info_access => "1.3.6.1.5.5.7.1.1",
 
     // aes
     // aes-128
@@ -0,0 +1,268 @@
+use crate::{oids, AlgorithmIdentifier, Certificate, CertificateList};
+use picky_asn1::{
+    tag::Tag,
+    wrapper::{ApplicationTag0, Asn1SetOf, ObjectIdentifierAsn1},
+};
+use picky_asn1_der::Asn1RawDer;
+use serde::{de, ser, Serialize};
+use std::fmt;
+
+/// [RFC 2315 #7](https://tools.ietf.org/html/rfc2315#section-7)
+///
+/// ```not_rust
+/// ContentInfo ::= SEQUENCE {
+///     contentType ContentType,
+///     content [0] EXPLICIT ANY DEFINED BY contentType OPTIONAL }
+/// ```
+///
+/// Only the signed-data content type is supported.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct ContentInfo {
+    pub content_type: ObjectIdentifierAsn1,
+    pub content: ApplicationTag0<SignedData>,
+}
+
+impl ContentInfo {
+    pub fn new_signed_data(signed_data: SignedData) -> Self {
+        Self {
+            content_type: oids::pkcs7_signed_data().into(),
+            content: ApplicationTag0(signed_data),
+        }
+    }
+
+    pub fn signed_data(&self) -> &SignedData {
+        &self.content.0
+    }
+}
+
+impl<'de> de::Deserialize<'de> for ContentInfo {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        struct Visitor;
+
+        impl<'de> de::Visitor<'de> for Visitor {
+            type Value = ContentInfo;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a valid DER-encoded ContentInfo")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: de::SeqAccess<'de>,
+            {
+                let content_type: ObjectIdentifierAsn1 = seq_next_element!(seq, ContentInfo, "content type");
+                if Into::<String>::into(&content_type.0) != oids::PKCS7_SIGNED_DATA {
+                    return Err(serde_invalid_value!(
+                        ContentInfo,
+                        "unsupported content type",
+                        "signed data content type"
+                    ));
+                }
+
+                Ok(ContentInfo {
+                    content_type,
+                    content: seq_next_element!(seq, ContentInfo, "signed data"),
+                })
+            }
+        }
+
+        deserializer.deserialize_seq(Visitor)
+    }
+}
+
+/// [RFC 2315 #9.1](https://tools.ietf.org/html/rfc2315#section-9.1)
+///
+/// ```not_rust
+/// SignedData ::= SEQUENCE {
+///     version Version,
+///     digestAlgorithms DigestAlgorithmIdentifiers,
+///     contentInfo ContentInfo,
+///     certificates
+///         [0] IMPLICIT ExtendedCertificatesAndCertificates OPTIONAL,
+///     crls
+///         [1] IMPLICIT CertificateRevocationLists OPTIONAL,
+///     signerInfos SignerInfos }
+/// ```
+///
+/// Signer infos are kept as raw DER.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SignedData {
+    pub version: u8,
+    pub digest_algorithms: Asn1SetOf<AlgorithmIdentifier>,
+    pub content_info: EncapsulatedContentInfo,
+    pub certificates: Vec<Certificate>,
+    pub crls: Vec<CertificateList>,
+    pub signer_infos: Asn1SetOf<Asn1RawDer>,
+}
+
+impl SignedData {
+    /// Degenerate "certs-only" signed data, without content nor signers, used to convey certificates
+    pub fn certs_only(certificates: Vec<Certificate>) -> Self {
+        Self {
+            version: 1,
+            digest_algorithms: Asn1SetOf(Vec::new()),
+            content_info: EncapsulatedContentInfo {
+                content_type: oids::pkcs7_data().into(),
+                content: None,
+            },
+            certificates,
+            crls: Vec::new(),
+            signer_infos: Asn1SetOf(Vec::new()),
+        }
+    }
+}
+
+/// SET OF elements whose tag is replaced by an implicit context-specific tag
+fn implicit_set_of<T, E>(tag: Tag, elements: &[T]) -> Result<Asn1RawDer, E>
+where
+    T: Serialize,
+    E: ser::Error,
+{
+    let mut der = picky_asn1_der::to_vec(&Asn1SetOf(elements.iter().collect())).map_err(ser::Error::custom)?;
+    der[0] = tag.number();
+    Ok(Asn1RawDer(der))
+}
+
+impl ser::Serialize for SignedData {
+    fn serialize<S>(&self, serializer: S) -> Result<<S as ser::Serializer>::Ok, <S as ser::Serializer>::Error>
+    where
+        S: ser::Serializer,
+    {
+        use ser::SerializeSeq;
+        let mut seq = serializer.serialize_seq(Some(6))?;
+        seq.serialize_element(&self.version)?;
+        seq.serialize_element(&self.digest_algorithms)?;
+        seq.serialize_element(&self.content_info)?;
+        if !self.certificates.is_empty() {
+            seq.serialize_element(&implicit_set_of(Tag::APP_0, &self.certificates)?)?;
+        }
+        if !self.crls.is_empty() {
+            seq.serialize_element(&implicit_set_of(Tag::APP_1, &self.crls)?)?;
+        }
+        seq.serialize_element(&self.signer_infos)?;
+        seq.end()
+    }
+}
+
+impl<'de> de::Deserialize<'de> for SignedData {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        struct Visitor;
+
+        impl<'de> de::Visitor<'de> for Visitor {
+            type Value = SignedData;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a valid DER-encoded SignedData")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: de::SeqAccess<'de>,
+            {
+                let version = seq_next_element!(seq, SignedData, "version");
+                let digest_algorithms = seq_next_element!(seq, SignedData, "digest algorithms");
+                let content_info = seq_next_element!(seq, SignedData, "content info");
+
+                let mut certificates = Vec::new();
+                let mut crls = Vec::new();
+                let signer_infos = loop {
+                    let mut der = seq_next_element!(seq, Asn1RawDer, SignedData, "signer infos").0;
+                    match Tag::from(der[0]) {
+                        Tag::APP_0 => {
+                            // certificates are implicitly tagged: restore SET tag to decode them
+                            der[0] = Tag::SET.number();
+                            let decoded: Asn1SetOf<Certificate> = picky_asn1_der::from_bytes(&der).map_err(|_| {
+                                serde_invalid_value!(SignedData, "invalid certificates", "a valid SET OF Certificate")
+                            })?;
+                            certificates = decoded.0;
+                        }
+                        Tag::APP_1 => {
+                            der[0] = Tag::SET.number();
+                            let decoded: Asn1SetOf<CertificateList> =
+                                picky_asn1_der::from_bytes(&der).map_err(|_| {
+                                    serde_invalid_value!(SignedData, "invalid crls", "a valid SET OF CertificateList")
+                                })?;
+                            crls = decoded.0;
+                        }
+                        _ => {
+                            break picky_asn1_der::from_bytes(&der).map_err(|_| {
+                                serde_invalid_value!(SignedData, "invalid signer infos", "a valid SET OF SignerInfo")
+                            })?
+                        }
+                    }
+                };
+
+                Ok(SignedData {
+                    version,
+                    digest_algorithms,
+                    content_info,
+                    certificates,
+                    crls,
+                    signer_infos,
+                })
+            }
+        }
+
+        deserializer.deserialize_seq(Visitor)
+    }
+}
+
+/// ContentInfo carried by signed data, without content in degenerate "certs-only" signed data
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct EncapsulatedContentInfo {
+    pub content_type: ObjectIdentifierAsn1,
+    pub content: Option<ApplicationTag0<Asn1RawDer>>,
+}
+
+impl<'de> de::Deserialize<'de> for EncapsulatedContentInfo {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        struct Visitor;
+
+        impl<'de> de::Visitor<'de> for Visitor {
+            type Value = EncapsulatedContentInfo;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a valid DER-encoded ContentInfo")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: de::SeqAccess<'de>,
+            {
+                Ok(EncapsulatedContentInfo {
+                    content_type: seq_next_element!(seq, EncapsulatedContentInfo, "content type"),
+                    content: seq.next_element()?,
+                })
+            }
+        }
+
+        deserializer.deserialize_seq(Visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn certs_only() {
+        // generated using `openssl crl2pkcs7 -nocrl -certfile test_authority.crt -outform DER`
+        let encoded = include_bytes!("../../test_assets/test_authority.p7b");
+
+        let certificate: Certificate = picky_asn1_der::from_bytes(&encoded[45..836]).expect("certificate");
+        let signed_data = SignedData::certs_only(vec![certificate]);
+        check_serde!(signed_data: SignedData in encoded[19..838]);
+
+        let content_info = ContentInfo::new_signed_data(signed_data);
+        check_serde!(content_info: ContentInfo in encoded);
+    }
+}
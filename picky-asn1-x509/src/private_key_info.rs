@@ -1,5 +1,13 @@
-use crate::{oids, AlgorithmIdentifier};
-use picky_asn1::wrapper::{Asn1SequenceOf, IntegerAsn1, OctetStringAsn1Container};
+use crate::{oids, AlgorithmIdentifier, ECParameters};
+use oid::ObjectIdentifier;
+use picky_asn1::{
+    bit_string::BitString,
+    tag::{Tag, TagPeeker},
+    wrapper::{
+        ApplicationTag0, ApplicationTag1, Asn1SequenceOf, BitStringAsn1, IntegerAsn1, OctetStringAsn1,
+        OctetStringAsn1Container,
+    },
+};
 use serde::{de, ser, Deserialize, Serialize};
 use std::fmt;
 
@@ -68,6 +76,28 @@ impl PrivateKeyInfo {
             private_key,
         }
     }
+
+    /// Wraps an elliptic curve private key on the given named curve.
+    ///
+    /// The curve is only stored in the private key algorithm identifier, as recommended by
+    /// [RFC 5915 #3](https://tools.ietf.org/html/rfc5915#section-3) for keys carried in PKCS#8.
+    pub fn new_ec_encryption(curve: ObjectIdentifier, secret: Vec<u8>, public_point: Option<BitString>) -> Self {
+        let private_key = PrivateKeyValue::EC(
+            ECPrivateKey {
+                version: 1,
+                private_key: secret.into(),
+                parameters: None,
+                public_key: public_point.map(|point| BitStringAsn1::from(point).into()),
+            }
+            .into(),
+        );
+
+        Self {
+            version: 0,
+            private_key_algorithm: AlgorithmIdentifier::new_elliptic_curve(curve),
+            private_key,
+        }
+    }
 }
 
 impl<'de> de::Deserialize<'de> for PrivateKeyInfo {
@@ -101,6 +131,8 @@ impl<'de> de::Deserialize<'de> for PrivateKeyInfo {
                     seq_next_element!(seq, PrivateKeyInfo, "private key algorithm");
                 let private_key = if private_key_algorithm.is_a(oids::rsa_encryption()) {
                     PrivateKeyValue::RSA(seq_next_element!(seq, PrivateKeyInfo, "rsa oid"))
+                } else if private_key_algorithm.is_a(oids::ec_public_key()) {
+                    PrivateKeyValue::EC(seq_next_element!(seq, PrivateKeyInfo, "ec private key"))
                 } else {
                     return Err(serde_invalid_value!(
                         PrivateKeyInfo,
@@ -124,6 +156,7 @@ impl<'de> de::Deserialize<'de> for PrivateKeyInfo {
 #[derive(Debug, PartialEq, Clone)]
 pub enum PrivateKeyValue {
     RSA(OctetStringAsn1Container<RSAPrivateKey>),
+    EC(OctetStringAsn1Container<ECPrivateKey>),
 }
 
 impl ser::Serialize for PrivateKeyValue {
@@ -133,6 +166,7 @@ impl ser::Serialize for PrivateKeyValue {
     {
         match self {
             PrivateKeyValue::RSA(rsa) => rsa.serialize(serializer),
+            PrivateKeyValue::EC(ec) => ec.serialize(serializer),
         }
     }
 }
@@ -167,6 +201,89 @@ impl RSAPrivateKey {
     }
 }
 
+/// [RFC 5915 #3](https://tools.ietf.org/html/rfc5915#section-3)
+///
+/// ```not_rust
+/// ECPrivateKey ::= SEQUENCE {
+///   version        INTEGER { ecPrivkeyVer1(1) } (ecPrivkeyVer1),
+///   privateKey     OCTET STRING,
+///   parameters [0] ECParameters {{ NamedCurve }} OPTIONAL,
+///   publicKey  [1] BIT STRING OPTIONAL
+/// }
+/// ```
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ECPrivateKey {
+    pub version: u8,
+    pub private_key: OctetStringAsn1,
+    pub parameters: Option<ApplicationTag0<ECParameters>>,
+    pub public_key: Option<ApplicationTag1<BitStringAsn1>>,
+}
+
+impl<'de> de::Deserialize<'de> for ECPrivateKey {
+    fn deserialize<D>(deserializer: D) -> Result<Self, <D as de::Deserializer<'de>>::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        struct Visitor;
+
+        impl<'de> de::Visitor<'de> for Visitor {
+            type Value = ECPrivateKey;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a valid DER-encoded ECPrivateKey")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: de::SeqAccess<'de>,
+            {
+                let version = seq_next_element!(seq, ECPrivateKey, "version");
+                if version != 1 {
+                    return Err(serde_invalid_value!(
+                        ECPrivateKey,
+                        "unsupported version (valid version number: 1)",
+                        "a supported ECPrivateKey"
+                    ));
+                }
+
+                let private_key = seq_next_element!(seq, ECPrivateKey, "private key");
+
+                let mut next_tag = seq.next_element::<TagPeeker>()?.map(|peeker| peeker.next_tag);
+
+                let parameters = match next_tag {
+                    Some(Tag::APP_0) => {
+                        let parameters = seq_next_element!(seq, ECPrivateKey, "parameters");
+                        next_tag = seq.next_element::<TagPeeker>()?.map(|peeker| peeker.next_tag);
+                        Some(parameters)
+                    }
+                    _ => None,
+                };
+
+                let public_key = match next_tag {
+                    Some(Tag::APP_1) => Some(seq_next_element!(seq, ECPrivateKey, "public key")),
+                    Some(_) => {
+                        return Err(serde_invalid_value!(
+                            ECPrivateKey,
+                            "unexpected trailing element",
+                            "public key"
+                        ))
+                    }
+                    None => None,
+                };
+
+                Ok(ECPrivateKey {
+                    version,
+                    private_key,
+                    parameters,
+                    public_key,
+                })
+            }
+        }
+
+        deserializer.deserialize_seq(Visitor)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -198,4 +315,37 @@ mod tests {
         let private_key = PrivateKeyInfo::new_rsa_encryption(modulus, public_exponent, private_exponent, primes);
         check_serde!(private_key: PrivateKeyInfo in encoded);
     }
+
+    #[test]
+    fn pkcs_8_ec_private_key() {
+        let encoded = base64::decode(
+            "MIGHAgEAMBMGByqGSM49AgEGCCqGSM49AwEHBG0wawIBAQQgO1ex7kvDg/sD/X62\
+             /u+5TJrlfXmw69oJ87ORG00B7GihRANCAARQj0thd+B1Pq0rpu40APbecQrPXlve\
+             SsInCCwMW34IRg1RktszH/YwXLOo28trygNwn7BazeHa026UlQUH/ePG",
+        )
+        .expect("invalid base64");
+
+        let secret = encoded[36..68].to_vec();
+        let point = BitString::with_bytes(&encoded[73..138]);
+        let private_key = PrivateKeyInfo::new_ec_encryption(oids::secp256r1(), secret, Some(point));
+        check_serde!(private_key: PrivateKeyInfo in encoded);
+    }
+
+    #[test]
+    fn ec_private_key_with_parameters() {
+        let encoded = base64::decode(
+            "MHcCAQEEIDtXse5Lw4P7A/1+tv7vuUya5X15sOvaCfOzkRtNAexooAoGCCqGSM49\
+             AwEHoUQDQgAEUI9LYXfgdT6tK6buNAD23nEKz15b3krCJwgsDFt+CEYNUZLbMx/2\
+             MFyzqNvLa8oDcJ+wWs3h2tNulJUFB/3jxg==",
+        )
+        .expect("invalid base64");
+
+        let private_key = ECPrivateKey {
+            version: 1,
+            private_key: encoded[7..39].to_vec().into(),
+            parameters: Some(ECParameters::from(oids::secp256r1()).into()),
+            public_key: Some(BitStringAsn1::from(BitString::with_bytes(&encoded[56..121])).into()),
+        };
+        check_serde!(private_key: ECPrivateKey in encoded);
+    }
 }
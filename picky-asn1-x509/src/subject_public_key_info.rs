@@ -1,4 +1,6 @@
 use crate::{oids, AlgorithmIdentifier};
+use oid::ObjectIdentifier;
+use picky_asn1::bit_string::BitString;
 use picky_asn1::wrapper::{BitStringAsn1, BitStringAsn1Container, IntegerAsn1, OctetStringAsn1};
 use serde::{de, ser, Deserialize, Serialize};
 use std::fmt;
@@ -39,6 +41,13 @@ impl SubjectPublicKeyInfo {
             ),
         }
     }
+
+    pub fn new_ec_key(curve: ObjectIdentifier, point: BitString) -> Self {
+        Self {
+            algorithm: AlgorithmIdentifier::new_elliptic_curve(curve),
+            subject_public_key: PublicKey::EC(point.into()),
+        }
+    }
 }
 
 impl ser::Serialize for SubjectPublicKeyInfo {
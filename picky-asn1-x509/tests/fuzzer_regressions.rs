@@ -0,0 +1,84 @@
+//! This test run problematic artifacts found by fuzzing (see `fuzz/fuzz_targets/der.rs`)
+
+use picky_asn1::wrapper::{BitStringAsn1, IntegerAsn1, ObjectIdentifierAsn1, OctetStringAsn1};
+use picky_asn1_der::Asn1RawDer;
+use picky_asn1_x509::{
+    AlgorithmIdentifier, Certificate, CertificationRequest, Extensions, GeneralNames, Name, PrivateKeyInfo,
+    SubjectPublicKeyInfo, Time, Validity,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{fs, io::Read, path::PathBuf, time::Instant};
+
+fn read_vector() -> Vec<(PathBuf, Vec<u8>)> {
+    let dir = PathBuf::from("tests/artifacts_vector");
+    let mut vector = Vec::new();
+    for entry in fs::read_dir(dir).expect("couldn't read dir") {
+        let entry = entry.expect("couldn't read entry");
+        let path = entry.path();
+        if !path.is_dir() {
+            let mut file = fs::File::open(&path).expect("couldn't open file");
+            let mut test = Vec::new();
+            file.read_to_end(&mut test).expect("couldn't read file");
+            vector.push((path, test));
+        }
+    }
+    vector
+}
+
+fn check<T: DeserializeOwned + Serialize>(data: &[u8]) -> Option<T> {
+    let value = picky_asn1_der::from_bytes::<T>(data).ok()?;
+    let _ = picky_asn1_der::to_vec(&value);
+    Some(value)
+}
+
+fn fuzz_target(data: &[u8]) {
+    println!("Primitives...");
+    let _ = check::<Asn1RawDer>(data);
+    let _ = check::<IntegerAsn1>(data);
+    let _ = check::<OctetStringAsn1>(data);
+    let _ = check::<ObjectIdentifierAsn1>(data);
+    if let Some(bit_string) = check::<BitStringAsn1>(data) {
+        let _ = bit_string.0.payload_view();
+        let _ = bit_string.0.get_num_bits();
+    }
+
+    println!("X509...");
+    let _ = check::<Time>(data);
+    let _ = check::<Validity>(data);
+    let _ = check::<Name>(data);
+    let _ = check::<GeneralNames>(data);
+    let _ = check::<AlgorithmIdentifier>(data);
+    let _ = check::<Extensions>(data);
+    let _ = check::<SubjectPublicKeyInfo>(data);
+    let _ = check::<PrivateKeyInfo>(data);
+    if let Some(csr) = check::<CertificationRequest>(data) {
+        let _ = csr.signature.0.payload_view();
+    }
+    if let Some(cert) = check::<Certificate>(data) {
+        let _ = cert.signature_value.0.payload_view();
+    }
+
+    println!("Done!");
+}
+
+#[test]
+fn fuzz_artifacts() {
+    let mut success = true;
+
+    for (path, test) in read_vector() {
+        println!("==> Test: {}", path.display());
+
+        let now = Instant::now();
+
+        fuzz_target(&test);
+
+        let duration = Instant::now().duration_since(now);
+        println!("Duration: {:?}", duration);
+        if duration.as_secs() > 1 {
+            println!("/!!!!\\ WAY TOO SLOW");
+            success = false;
+        }
+    }
+
+    assert!(success);
+}
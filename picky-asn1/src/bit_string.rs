@@ -146,7 +146,7 @@ impl BitString {
     /// assert_eq!(b.is_set(7), true);
     /// ```
     pub fn is_set(&self, i: usize) -> bool {
-        if i > self.get_num_bits() {
+        if i >= self.get_num_bits() {
             return false;
         }
 
@@ -158,7 +158,7 @@ impl BitString {
 
     /// Set bit `i` to `val`.
     pub fn set(&mut self, i: usize, val: bool) {
-        if i > self.get_num_bits() {
+        if i >= self.get_num_bits() {
             return;
         }
 
@@ -173,6 +173,76 @@ impl BitString {
         }
     }
 
+    /// Construct a `BitString` from a named bit list (e.g. `KeyUsage`).
+    ///
+    /// DER requires trailing zero bits of a named bit list to be removed, hence the
+    /// resulting `BitString` only spans up to the last bit set.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use picky_asn1::bit_string::BitString;
+    ///
+    /// let b = BitString::from_named_bits(&[false, false, false, false, false, true, true, false, false]);
+    /// assert_eq!(b.get_num_bits(), 7);
+    /// assert_eq!(b.payload_view(), &[0x06]);
+    ///
+    /// let empty = BitString::from_named_bits(&[false, false]);
+    /// assert_eq!(empty.get_num_bits(), 0);
+    /// ```
+    pub fn from_named_bits(bits: &[bool]) -> BitString {
+        let num_bits = bits.iter().rposition(|&bit| bit).map(|idx| idx + 1).unwrap_or(0);
+        let mut bit_string = Self::with_len(num_bits);
+        for (i, &bit) in bits[..num_bits].iter().enumerate() {
+            bit_string.set(i, bit);
+        }
+        bit_string
+    }
+
+    /// Check if named bit `i` is set.
+    ///
+    /// Since trailing zero bits are dropped in named bit lists, bits beyond the
+    /// encoded length are simply considered unset.
+    pub fn named_bit(&self, i: usize) -> bool {
+        self.is_set(i)
+    }
+
+    /// Set named bit `i` to `val`, keeping the encoding of a named bit list valid.
+    ///
+    /// The `BitString` grows as needed when setting a bit beyond its length, and
+    /// trailing zero bits are dropped when clearing a bit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use picky_asn1::bit_string::BitString;
+    ///
+    /// let mut b = BitString::from_named_bits(&[]);
+    /// b.set_named_bit(0, true);
+    /// b.set_named_bit(8, true);
+    /// assert_eq!(b.get_num_bits(), 9);
+    ///
+    /// b.set_named_bit(8, false);
+    /// assert_eq!(b.get_num_bits(), 1);
+    /// assert_eq!(b.payload_view(), &[0x80]);
+    /// ```
+    pub fn set_named_bit(&mut self, i: usize, val: bool) {
+        if val {
+            if i >= self.get_num_bits() {
+                self.set_num_bits(i + 1);
+            }
+            self.set(i, true);
+        } else if i < self.get_num_bits() {
+            self.set(i, false);
+            let num_bits = (0..self.get_num_bits())
+                .rev()
+                .find(|&idx| self.is_set(idx))
+                .map(|idx| idx + 1)
+                .unwrap_or(0);
+            self.set_num_bits(num_bits);
+        }
+    }
+
     pub fn get_num_unused_bits(&self) -> u8 {
         self.data[0]
     }
@@ -273,7 +343,16 @@ impl<'de> de::Deserialize<'de> for BitString {
             where
                 E: de::Error,
             {
-                Ok(BitString { data: v })
+                // first byte is the number of unused bits (must be 0 if there is no payload)
+                match v.first() {
+                    Some(&unused_bits) if unused_bits < 8 && (unused_bits == 0 || v.len() > 1) => {
+                        Ok(BitString { data: v })
+                    }
+                    _ => Err(E::invalid_value(
+                        de::Unexpected::Other("invalid bit string header"),
+                        &"a valid buffer representing a bit string",
+                    )),
+                }
             }
         }
 
@@ -9,7 +9,7 @@ pub fn encode_to_canonical_address(data: &[u8]) -> String {
     multibase::encode(CANONICAL_BASE, hash.as_bytes())
 }
 
-const ALTERNATIVE_HASHES: [Hash; 1] = [Hash::Sha1];
+const ALTERNATIVE_HASHES: [Hash; 2] = [Hash::Sha1, Hash::Sha2_512];
 pub fn encode_to_alternative_addresses(data: &[u8]) -> Result<Vec<String>, String> {
     let mut addresses = Vec::with_capacity(ALTERNATIVE_HASHES.len());
 
@@ -27,6 +27,67 @@ pub fn convert_to_canonical_base(multibase_multihash_address: &str) -> Result<(S
     Ok((multibase::encode(CANONICAL_BASE, multi.as_bytes()), multi.algorithm()))
 }
 
+/// Hash algorithms an address can be given as a bare hex digest for, with their multihash code and digest size
+const HEX_DIGEST_HASHES: [(Hash, u8, usize); 3] = [
+    (Hash::Sha1, 0x11, 20),
+    (Hash::Sha2_256, 0x12, 32),
+    (Hash::Sha2_512, 0x13, 64),
+];
+
+/// Multihash address, in the canonical base, of a hex-encoded digest
+pub fn to_multihash(hash: Hash, hex_digest: &str) -> Result<String, String> {
+    let (_, code, size) = HEX_DIGEST_HASHES
+        .iter()
+        .find(|(supported, _, _)| *supported == hash)
+        .ok_or_else(|| format!("unsupported hash algorithm: {:?}", hash))?;
+    let digest = hex::decode(hex_digest).map_err(|e| format!("invalid hex digest: {}", e))?;
+    if digest.len() != *size {
+        return Err(format!(
+            "{:?} digests are {} bytes long, got {}",
+            hash,
+            size,
+            digest.len()
+        ));
+    }
+
+    // codes and sizes are below 0x80, so their varint encoding is a single byte
+    let mut raw_multi = vec![*code, *size as u8];
+    raw_multi.extend_from_slice(&digest);
+    let multi = Multihash::from_bytes(raw_multi).map_err(|e| e.to_string())?;
+    Ok(multibase::encode(CANONICAL_BASE, multi.as_bytes()))
+}
+
+/// Addresses, in the canonical base, a client-provided address may stand for, in lookup order:
+/// the multibase-encoded multihash it is, then the hex digest of the algorithm its length matches.
+pub fn address_candidates(address: &str) -> Result<Vec<(String, Hash)>, String> {
+    let mut candidates = Vec::with_capacity(2);
+    if let Ok(candidate) = convert_to_canonical_base(address) {
+        candidates.push(candidate);
+    }
+
+    if !address.is_empty() && address.bytes().all(|b| b.is_ascii_hexdigit()) {
+        match HEX_DIGEST_HASHES.iter().find(|(_, _, size)| size * 2 == address.len()) {
+            Some((hash, _, _)) => candidates.push((to_multihash(*hash, address)?, *hash)),
+            None if candidates.is_empty() => {
+                return Err(format!(
+                    "hex digest of {} characters doesn't match any of SHA-1, SHA-256 or SHA-512",
+                    address.len()
+                ))
+            }
+            None => {}
+        }
+    }
+
+    if candidates.is_empty() {
+        Err(format!(
+            "{} is neither a multihash nor a hex-encoded SHA-1, SHA-256 or SHA-512 digest",
+            address
+        ))
+    } else {
+        Ok(candidates)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -34,6 +95,9 @@ mod tests {
     fn alternate_to_canonical_table(alternate: &str) -> Option<&'static str> {
         match alternate {
             "uERSIwvEfss45KstbKYbmQCEcRpAHPg" => Some("uEiCcvAfD-ZFyWDajqipYHKICkZiqQgudmbwOEx2fPiy-Rw"),
+            "uE0D61Yp2-SfTtbvbYGzPGXACJfFXJj-1FePEGU-hIgrTTR1gvzWgfeDhXIIpx-vHJFdUJc1YGk7plf86VHWr_eDX" => {
+                Some("uEiCcvAfD-ZFyWDajqipYHKICkZiqQgudmbwOEx2fPiy-Rw")
+            }
             _ => None,
         }
     }
@@ -62,4 +126,45 @@ mod tests {
             assert_eq!(canonical, "uEiCcvAfD-ZFyWDajqipYHKICkZiqQgudmbwOEx2fPiy-Rw");
         }
     }
+
+    #[test]
+    fn hex_digests() {
+        let sha256_hex = "9cbc07c3f991725836a3aa2a581ca2029198aa420b9d99bc0e131d9f3e2cbe47";
+        assert_eq!(
+            to_multihash(Hash::Sha2_256, sha256_hex).expect("sha256"),
+            "uEiCcvAfD-ZFyWDajqipYHKICkZiqQgudmbwOEx2fPiy-Rw"
+        );
+        assert_eq!(
+            address_candidates(sha256_hex).expect("candidates"),
+            vec![(
+                "uEiCcvAfD-ZFyWDajqipYHKICkZiqQgudmbwOEx2fPiy-Rw".to_owned(),
+                Hash::Sha2_256
+            )]
+        );
+
+        let sha1_hex = "88c2f11fb2ce392acb5b2986e640211c4690073e";
+        assert_eq!(
+            address_candidates(sha1_hex).expect("candidates"),
+            vec![("uERSIwvEfss45KstbKYbmQCEcRpAHPg".to_owned(), Hash::Sha1)]
+        );
+
+        // multihashes are taken as is
+        assert_eq!(
+            address_candidates("uEiCcvAfD-ZFyWDajqipYHKICkZiqQgudmbwOEx2fPiy-Rw").expect("candidates"),
+            vec![(
+                "uEiCcvAfD-ZFyWDajqipYHKICkZiqQgudmbwOEx2fPiy-Rw".to_owned(),
+                Hash::Sha2_256
+            )]
+        );
+
+        assert_eq!(
+            address_candidates("9cbc07c3").unwrap_err(),
+            "hex digest of 8 characters doesn't match any of SHA-1, SHA-256 or SHA-512"
+        );
+        assert!(address_candidates("not an address").is_err());
+        assert_eq!(
+            to_multihash(Hash::Sha1, sha256_hex).unwrap_err(),
+            "Sha1 digests are 20 bytes long, got 32"
+        );
+    }
 }
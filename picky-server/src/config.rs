@@ -1,41 +1,175 @@
-use crate::utils::PathOr;
-use clap::App;
+use crate::{policy::IssuancePolicy, utils::PathOr};
+use clap::{App, ArgMatches};
 use log::LevelFilter;
 use picky::{
     hash::HashAlgorithm,
     key::{PrivateKey, PublicKey},
     pem::Pem,
     signature::SignatureAlgorithm,
-    x509::Cert,
+    x509::{Cert, KeyIdGenMethod},
 };
 use serde::{Deserialize, Serialize};
 use std::{
+    collections::HashSet,
     env,
+    ffi::OsString,
+    fmt,
+    panic::{self, AssertUnwindSafe},
     path::{Path, PathBuf},
+    str::FromStr,
 };
 
 const YAML_CONF_PATH: &str = "picky_server_conf.yaml";
 
+/// Smallest RSA key accepted for generated CAs
+pub const MIN_CA_RSA_KEY_SIZE: usize = 2048;
+
+/// Settings only read on startup, which a configuration reload refuses to change
+const RESTART_ONLY_SETTINGS: &[&str] = &[
+    "backend",
+    "file_backend_path",
+    "database_url",
+    "database_name",
+    "redis_key_prefix",
+    "redis_leaf_ttl",
+    "memory_snapshot_path",
+    "mongo_connect_timeout_secs",
+    "mongo_operation_timeout_secs",
+    "mongo_max_pool_size",
+    "mongo_connect_attempts",
+    "mongo_retry_delay_ms",
+    "mongo_collection_prefix",
+    "mongo_realm_prefix",
+    "cache_size",
+    "cache_ttl_secs",
+    "listen_address",
+    "tls",
+    "tls_bootstrap",
+    "tls_bootstrap_dns_name",
+    "insecure_http",
+    "shutdown_drain_timeout_secs",
+    "expiry_sweep_interval_secs",
+    "expired_retention_days",
+    "request_log_level",
+    "request_log_excluded_paths",
+    "cors_allowed_origins",
+    "webhook_urls",
+    "webhook_secret",
+    "webhook_max_attempts",
+    "webhook_retry_delay_ms",
+];
+
 const PICKY_REALM_ENV: &str = "PICKY_REALM";
+const PICKY_SIGNING_ALGORITHM_ENV: &str = "PICKY_SIGNING_ALGORITHM";
+const PICKY_ALLOW_INSECURE_ALGORITHMS_ENV: &str = "PICKY_ALLOW_INSECURE_ALGORITHMS";
+const PICKY_KEY_ID_METHOD_ENV: &str = "PICKY_KEY_ID_METHOD";
 const PICKY_SAVE_CERTIFICATE_ENV: &str = "PICKY_SAVE_CERTIFICATE";
 const PICKY_BACKEND_ENV: &str = "PICKY_BACKEND";
 const PICKY_FILE_BACKEND_PATH_ENV: &str = "PICKY_FILE_BACKEND_PATH";
 const PICKY_DATABASE_URL_ENV: &str = "PICKY_DATABASE_URL";
 const PICKY_DATABASE_NAME_ENV: &str = "PICKY_DATABASE_NAME";
+const PICKY_REDIS_KEY_PREFIX_ENV: &str = "PICKY_REDIS_KEY_PREFIX";
+const PICKY_REDIS_LEAF_TTL_ENV: &str = "PICKY_REDIS_LEAF_TTL";
+const PICKY_MEMORY_SNAPSHOT_PATH_ENV: &str = "PICKY_MEMORY_SNAPSHOT_PATH";
+const PICKY_MONGO_CONNECT_TIMEOUT_ENV: &str = "PICKY_MONGO_CONNECT_TIMEOUT";
+const PICKY_MONGO_OPERATION_TIMEOUT_ENV: &str = "PICKY_MONGO_OPERATION_TIMEOUT";
+const PICKY_MONGO_MAX_POOL_SIZE_ENV: &str = "PICKY_MONGO_MAX_POOL_SIZE";
+const PICKY_MONGO_CONNECT_ATTEMPTS_ENV: &str = "PICKY_MONGO_CONNECT_ATTEMPTS";
+const PICKY_MONGO_RETRY_DELAY_MS_ENV: &str = "PICKY_MONGO_RETRY_DELAY_MS";
+const PICKY_MONGO_COLLECTION_PREFIX_ENV: &str = "PICKY_MONGO_COLLECTION_PREFIX";
+const PICKY_MONGO_REALM_PREFIX_ENV: &str = "PICKY_MONGO_REALM_PREFIX";
+const PICKY_CACHE_SIZE_ENV: &str = "PICKY_CACHE_SIZE";
+const PICKY_CACHE_TTL_ENV: &str = "PICKY_CACHE_TTL";
 
 const PICKY_ROOT_CERT_ENV: &str = "PICKY_ROOT_CERT";
 const PICKY_ROOT_CERT_PATH_ENV: &str = "PICKY_ROOT_CERT_PATH";
 const PICKY_ROOT_KEY_ENV: &str = "PICKY_ROOT_KEY";
 const PICKY_ROOT_KEY_PATH_ENV: &str = "PICKY_ROOT_KEY_PATH";
+const PICKY_ROOT_CERT_FILE_ENV: &str = "PICKY_ROOT_CERT_FILE";
+const PICKY_ROOT_KEY_FILE_ENV: &str = "PICKY_ROOT_KEY_FILE";
+
+const PICKY_ROOT_VALIDITY_ENV: &str = "PICKY_ROOT_VALIDITY";
+const PICKY_ROOT_PATHLEN_ENV: &str = "PICKY_ROOT_PATHLEN";
+const PICKY_INTERMEDIATE_VALIDITY_ENV: &str = "PICKY_INTERMEDIATE_VALIDITY";
+
+const PICKY_KEY_TYPE_ENV: &str = "PICKY_KEY_TYPE";
+const PICKY_ROOT_KEY_SIZE_ENV: &str = "PICKY_ROOT_KEY_SIZE";
+const PICKY_INTERMEDIATE_KEY_SIZE_ENV: &str = "PICKY_INTERMEDIATE_KEY_SIZE";
+
+const PICKY_INTERMEDIATES_ENV: &str = "PICKY_INTERMEDIATES";
 
 const PICKY_INTERMEDIATE_CERT_ENV: &str = "PICKY_INTERMEDIATE_CERT";
 const PICKY_INTERMEDIATE_CERT_PATH_ENV: &str = "PICKY_INTERMEDIATE_CERT_PATH";
 const PICKY_INTERMEDIATE_KEY_ENV: &str = "PICKY_INTERMEDIATE_KEY";
 const PICKY_INTERMEDIATE_KEY_PATH_ENV: &str = "PICKY_INTERMEDIATE_KEY_PATH";
+const PICKY_INTERMEDIATE_CERT_FILE_ENV: &str = "PICKY_INTERMEDIATE_CERT_FILE";
+const PICKY_INTERMEDIATE_KEY_FILE_ENV: &str = "PICKY_INTERMEDIATE_KEY_FILE";
+
+const PICKY_OCSP_RESPONDER_CERT_ENV: &str = "PICKY_OCSP_RESPONDER_CERT";
+const PICKY_OCSP_RESPONDER_CERT_PATH_ENV: &str = "PICKY_OCSP_RESPONDER_CERT_PATH";
+const PICKY_OCSP_RESPONDER_KEY_ENV: &str = "PICKY_OCSP_RESPONDER_KEY";
+const PICKY_OCSP_RESPONDER_KEY_PATH_ENV: &str = "PICKY_OCSP_RESPONDER_KEY_PATH";
+const PICKY_OCSP_RESPONDER_CERT_FILE_ENV: &str = "PICKY_OCSP_RESPONDER_CERT_FILE";
+const PICKY_OCSP_RESPONDER_KEY_FILE_ENV: &str = "PICKY_OCSP_RESPONDER_KEY_FILE";
 
 const PICKY_PROVISIONER_PUBLIC_KEY_ENV: &str = "PICKY_PROVISIONER_PUBLIC_KEY";
 const PICKY_PROVISIONER_PUBLIC_KEY_PATH_ENV: &str = "PICKY_PROVISIONER_PUBLIC_KEY_PATH";
 
+const PICKY_PROVISIONER_JWKS_PATH_ENV: &str = "PICKY_PROVISIONER_JWKS_PATH";
+const PICKY_PROVISIONER_AUDIENCE_ENV: &str = "PICKY_PROVISIONER_AUDIENCE";
+
+const PICKY_AUTH_MODE_ENV: &str = "PICKY_AUTH_MODE";
+const PICKY_API_KEY_ENV: &str = "PICKY_API_KEY";
+const PICKY_PROTECTED_ROUTES_ENV: &str = "PICKY_PROTECTED_ROUTES";
+
+const PICKY_ISSUANCE_ALLOWED_NAMES_ENV: &str = "PICKY_ISSUANCE_ALLOWED_NAMES";
+const PICKY_ISSUANCE_ALLOW_IP_ADDRESSES_ENV: &str = "PICKY_ISSUANCE_ALLOW_IP_ADDRESSES";
+const PICKY_ISSUANCE_REQUIRE_CN_IN_SAN_ENV: &str = "PICKY_ISSUANCE_REQUIRE_CN_IN_SAN";
+const PICKY_SYNTHESIZE_SAN_FROM_CN_ENV: &str = "PICKY_SYNTHESIZE_SAN_FROM_CN";
+const PICKY_REUSE_EXISTING_CERTS_ENV: &str = "PICKY_REUSE_EXISTING_CERTS";
+
+const PICKY_CSR_MIN_RSA_KEY_SIZE_ENV: &str = "PICKY_CSR_MIN_RSA_KEY_SIZE";
+const PICKY_CSR_MIN_RSA_PUBLIC_EXPONENT_ENV: &str = "PICKY_CSR_MIN_RSA_PUBLIC_EXPONENT";
+const PICKY_CSR_ALLOWED_HASH_ALGORITHMS_ENV: &str = "PICKY_CSR_ALLOWED_HASH_ALGORITHMS";
+
+const PICKY_LISTEN_ADDRESS_ENV: &str = "PICKY_LISTEN_ADDRESS";
+const PICKY_EXTERNAL_URL_ENV: &str = "PICKY_EXTERNAL_URL";
+const PICKY_INSECURE_HTTP_ENV: &str = "PICKY_INSECURE_HTTP";
+const PICKY_TLS_CERTIFICATE_CHAIN_ENV: &str = "PICKY_TLS_CERTIFICATE_CHAIN";
+const PICKY_TLS_CERTIFICATE_CHAIN_PATH_ENV: &str = "PICKY_TLS_CERTIFICATE_CHAIN_PATH";
+const PICKY_TLS_PRIVATE_KEY_ENV: &str = "PICKY_TLS_PRIVATE_KEY";
+const PICKY_TLS_PRIVATE_KEY_PATH_ENV: &str = "PICKY_TLS_PRIVATE_KEY_PATH";
+const PICKY_TLS_BOOTSTRAP_ENV: &str = "PICKY_TLS_BOOTSTRAP";
+const PICKY_TLS_BOOTSTRAP_DNS_NAME_ENV: &str = "PICKY_TLS_BOOTSTRAP_DNS_NAME";
+
+const PICKY_SHUTDOWN_DRAIN_TIMEOUT_ENV: &str = "PICKY_SHUTDOWN_DRAIN_TIMEOUT";
+
+const PICKY_METRICS_ENABLED_ENV: &str = "PICKY_METRICS_ENABLED";
+
+const PICKY_EXPIRY_SWEEP_INTERVAL_ENV: &str = "PICKY_EXPIRY_SWEEP_INTERVAL";
+const PICKY_EXPIRED_RETENTION_DAYS_ENV: &str = "PICKY_EXPIRED_RETENTION_DAYS";
+
+const PICKY_MAX_CHAIN_DEPTH_ENV: &str = "PICKY_MAX_CHAIN_DEPTH";
+
+const PICKY_RATE_LIMIT_PER_MINUTE_ENV: &str = "PICKY_RATE_LIMIT_PER_MINUTE";
+const PICKY_RATE_LIMIT_BURST_ENV: &str = "PICKY_RATE_LIMIT_BURST";
+
+const PICKY_MAX_BODY_SIZE_ENV: &str = "PICKY_MAX_BODY_SIZE";
+
+const PICKY_LOG_LEVEL_ENV: &str = "PICKY_LOG_LEVEL";
+const PICKY_LOG_FORMAT_ENV: &str = "PICKY_LOG_FORMAT";
+const PICKY_LOG_FILE_ENV: &str = "PICKY_LOG_FILE";
+
+const PICKY_REQUEST_LOG_LEVEL_ENV: &str = "PICKY_REQUEST_LOG_LEVEL";
+const PICKY_REQUEST_LOG_EXCLUDED_PATHS_ENV: &str = "PICKY_REQUEST_LOG_EXCLUDED_PATHS";
+
+const PICKY_CORS_ALLOWED_ORIGINS_ENV: &str = "PICKY_CORS_ALLOWED_ORIGINS";
+
+const PICKY_WEBHOOK_URLS_ENV: &str = "PICKY_WEBHOOK_URLS";
+const PICKY_WEBHOOK_SECRET_ENV: &str = "PICKY_WEBHOOK_SECRET";
+const PICKY_WEBHOOK_MAX_ATTEMPTS_ENV: &str = "PICKY_WEBHOOK_MAX_ATTEMPTS";
+const PICKY_WEBHOOK_RETRY_DELAY_MS_ENV: &str = "PICKY_WEBHOOK_RETRY_DELAY_MS";
+
 fn default_picky_realm() -> String {
     String::from("Picky")
 }
@@ -48,22 +182,203 @@ fn default_database_name() -> String {
     String::from("picky")
 }
 
+fn default_redis_key_prefix() -> String {
+    String::from("picky:")
+}
+
+const fn default_mongo_connect_timeout_secs() -> u64 {
+    15
+}
+
+const fn default_mongo_operation_timeout_secs() -> u64 {
+    30
+}
+
+const fn default_mongo_connect_attempts() -> u32 {
+    5
+}
+
+const fn default_mongo_retry_delay_ms() -> u64 {
+    500
+}
+
+const fn default_cache_size() -> usize {
+    256
+}
+
+const fn default_cache_ttl_secs() -> u64 {
+    300
+}
+
 fn default_file_backend_path() -> PathBuf {
     Path::new("database/").to_owned()
 }
 
+fn default_listen_address() -> String {
+    String::from("0.0.0.0:12345")
+}
+
+fn default_tls_bootstrap_dns_name() -> String {
+    String::from("localhost")
+}
+
+const fn default_shutdown_drain_timeout_secs() -> u64 {
+    30
+}
+
 const fn default_save_certificate() -> bool {
     false
 }
 
+const fn default_synthesize_san_from_cn() -> bool {
+    true
+}
+
+const fn default_metrics_enabled() -> bool {
+    true
+}
+
 const fn default_log_level() -> LevelFilter {
     LevelFilter::Info
 }
 
+const fn default_rate_limit_per_minute() -> u32 {
+    60
+}
+
+const fn default_rate_limit_burst() -> u32 {
+    20
+}
+
+const fn default_max_chain_depth() -> usize {
+    8
+}
+
+const fn default_max_body_size() -> usize {
+    128 * 1024
+}
+
+const fn default_request_log_level() -> LevelFilter {
+    LevelFilter::Info
+}
+
+fn default_request_log_excluded_paths() -> Vec<String> {
+    vec![String::from("/health")]
+}
+
+const fn default_webhook_max_attempts() -> u32 {
+    5
+}
+
+const fn default_webhook_retry_delay_ms() -> u64 {
+    1000
+}
+
+const fn default_root_key_size() -> usize {
+    4096
+}
+
+const fn default_intermediate_key_size() -> usize {
+    2048
+}
+
+fn default_root_validity() -> chrono::Duration {
+    chrono::Duration::days(3650)
+}
+
+fn default_intermediate_validity() -> chrono::Duration {
+    chrono::Duration::days(1825)
+}
+
 const fn default_signing_algorithm() -> SignatureAlgorithm {
     SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_256)
 }
 
+const fn default_csr_min_rsa_key_size() -> usize {
+    2048
+}
+
+const fn default_csr_min_rsa_public_exponent() -> u64 {
+    65537
+}
+
+fn default_csr_allowed_hash_algorithms() -> Vec<HashAlgorithm> {
+    vec![
+        HashAlgorithm::SHA2_224,
+        HashAlgorithm::SHA2_256,
+        HashAlgorithm::SHA2_384,
+        HashAlgorithm::SHA2_512,
+        HashAlgorithm::SHA3_384,
+        HashAlgorithm::SHA3_512,
+    ]
+}
+
+fn parse_hash_algorithm(s: &str) -> Option<HashAlgorithm> {
+    match s.to_uppercase().replace('-', "_").as_str() {
+        "SHA1" => Some(HashAlgorithm::SHA1),
+        "SHA2_224" | "SHA224" => Some(HashAlgorithm::SHA2_224),
+        "SHA2_256" | "SHA256" => Some(HashAlgorithm::SHA2_256),
+        "SHA2_384" | "SHA384" => Some(HashAlgorithm::SHA2_384),
+        "SHA2_512" | "SHA512" => Some(HashAlgorithm::SHA2_512),
+        "SHA3_384" => Some(HashAlgorithm::SHA3_384),
+        "SHA3_512" => Some(HashAlgorithm::SHA3_512),
+        _ => None,
+    }
+}
+
+fn parse_hash_algorithms(s: &str) -> Vec<HashAlgorithm> {
+    s.split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| parse_hash_algorithm(name).unwrap_or_else(|| panic!("unsupported hash algorithm: {}", name)))
+        .collect()
+}
+
+/// Signing algorithms accepted in the configuration
+const SIGNING_ALGORITHMS: &[SignatureAlgorithm] = &[
+    SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA1),
+    SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_224),
+    SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_256),
+    SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_384),
+    SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_512),
+    SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA3_384),
+    SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA3_512),
+    SignatureAlgorithm::RsaPssSha256,
+    SignatureAlgorithm::RsaPssSha384,
+    SignatureAlgorithm::RsaPssSha512,
+];
+
+fn parse_signing_algorithm(s: &str) -> Result<SignatureAlgorithm, String> {
+    s.trim()
+        .parse()
+        .ok()
+        .filter(|algorithm| SIGNING_ALGORITHMS.contains(algorithm))
+        .ok_or_else(|| {
+            let names: Vec<String> = SIGNING_ALGORITHMS.iter().map(ToString::to_string).collect();
+            format!(
+                "unsupported signing algorithm '{}', expected one of: {}",
+                s,
+                names.join(", ")
+            )
+        })
+}
+
+fn parse_key_id_method(s: &str) -> Result<KeyIdGenMethod, String> {
+    s.trim().parse().map_err(|_| {
+        let names: Vec<String> = KeyIdGenMethod::all().map(|method| method.to_string()).collect();
+        format!(
+            "unsupported key identifier method '{}', expected one of: {}",
+            s,
+            names.join(", ")
+        )
+    })
+}
+
+/// Signing algorithms relying on a broken hash function
+fn is_insecure_signing_algorithm(algorithm: SignatureAlgorithm) -> bool {
+    matches!(algorithm, SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA1))
+}
+
 fn parse_level_filter(s: &str) -> LevelFilter {
     match s.to_lowercase().as_str() {
         "error" => LevelFilter::Error,
@@ -75,12 +390,108 @@ fn parse_level_filter(s: &str) -> LevelFilter {
     }
 }
 
+const DURATION_UNITS: &[(char, i64)] = &[
+    ('y', 365 * 86_400),
+    ('w', 7 * 86_400),
+    ('d', 86_400),
+    ('h', 3_600),
+    ('m', 60),
+    ('s', 1),
+];
+
+/// Parses durations such as "20y", "90d" or "1y30d" (units: y = 365 days, w, d, h, m, s)
+pub fn parse_duration(s: &str) -> Result<chrono::Duration, String> {
+    let mut total_secs: i64 = 0;
+    let mut number = String::new();
+
+    for c in s.trim().chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+
+        let unit_secs = DURATION_UNITS
+            .iter()
+            .find(|(unit, _)| *unit == c.to_ascii_lowercase())
+            .map(|(_, secs)| *secs)
+            .ok_or_else(|| format!("invalid duration '{}': unknown unit '{}'", s, c))?;
+        let value = number
+            .parse::<i64>()
+            .map_err(|_| format!("invalid duration '{}': expected a number before '{}'", s, c))?;
+        total_secs = value
+            .checked_mul(unit_secs)
+            .and_then(|secs| total_secs.checked_add(secs))
+            .ok_or_else(|| format!("invalid duration '{}': too big", s))?;
+        number.clear();
+    }
+
+    if !number.is_empty() {
+        return Err(format!("invalid duration '{}': missing unit after {}", s, number));
+    }
+    if total_secs == 0 {
+        return Err(format!("invalid duration '{}': must be positive", s));
+    }
+
+    Ok(chrono::Duration::seconds(total_secs))
+}
+
+/// Formats a duration using the largest unit dividing it
+pub fn format_duration(duration: chrono::Duration) -> String {
+    let secs = duration.num_seconds();
+    DURATION_UNITS
+        .iter()
+        .find(|(_, unit_secs)| secs % unit_secs == 0)
+        .map(|(unit, unit_secs)| format!("{}{}", secs / unit_secs, unit))
+        .unwrap_or_else(|| format!("{}s", secs))
+}
+
+mod duration_str {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(duration: &chrono::Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&super::format_duration(*duration))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<chrono::Duration, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        super::parse_duration(&s).map_err(de::Error::custom)
+    }
+}
+
+/// Type of the keys generated for CAs
+#[derive(PartialEq, Clone, Copy, Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyType {
+    #[default]
+    Rsa,
+}
+
+impl FromStr for KeyType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "rsa" => Ok(KeyType::Rsa),
+            "ec-p256" | "ec-p384" => Err(format!("{} keys aren't supported for CAs yet", s)),
+            _ => Err(format!("unknown key type: {}", s)),
+        }
+    }
+}
+
 #[derive(PartialEq, Clone, Serialize, Deserialize, Debug)]
 #[serde(rename_all = "lowercase")]
 pub enum BackendType {
     MongoDb,
     Memory,
     File,
+    /// Requires picky-server to be built with the `postgres` feature
+    Postgres,
+    /// Requires picky-server to be built with the `redis` feature
+    Redis,
+    /// Requires picky-server to be built with the `sqlite` feature
+    Sqlite,
+    /// Requires picky-server to be built with the `mysql` feature
+    Mysql,
 }
 
 impl Default for BackendType {
@@ -95,6 +506,100 @@ impl From<&str> for BackendType {
             "mongodb" => Self::MongoDb,
             "memory" => Self::Memory,
             "file" => Self::File,
+            "postgres" | "postgresql" => Self::Postgres,
+            "redis" => Self::Redis,
+            "sqlite" | "sqlite3" => Self::Sqlite,
+            "mysql" | "mariadb" => Self::Mysql,
+            _ => Self::default(),
+        }
+    }
+}
+
+/// Permission granted to an API key
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiKeyScope {
+    /// Signing and registering certificates
+    Issue,
+    /// Revoking certificates
+    Revoke,
+    /// Fetching and listing certificates, chains, CRLs and health
+    Read,
+    /// Every other scope, plus CA management, configuration reload and the audit log
+    Admin,
+}
+
+impl fmt::Display for ApiKeyScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ApiKeyScope::Issue => "issue",
+            ApiKeyScope::Revoke => "revoke",
+            ApiKeyScope::Read => "read",
+            ApiKeyScope::Admin => "admin",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// API key identified by its name, its value never being logged
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct NamedApiKey {
+    pub name: String,
+    pub key: String,
+    pub scopes: Vec<ApiKeyScope>,
+}
+
+impl NamedApiKey {
+    pub fn allows(&self, scope: ApiKeyScope) -> bool {
+        self.scopes.contains(&ApiKeyScope::Admin) || self.scopes.contains(&scope)
+    }
+}
+
+/// Name the `api_key` setting goes by among the named API keys
+pub const DEFAULT_API_KEY_NAME: &str = "default";
+
+/// How log records are written
+#[derive(PartialEq, Clone, Copy, Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human-readable lines
+    #[default]
+    Text,
+    /// One JSON object per line, with the request ID, route and error kind as separate fields
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            _ => Err(format!("unknown log format: {}", s)),
+        }
+    }
+}
+
+/// Credentials required by picky
+#[derive(PartialEq, Clone, Copy, Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthMode {
+    /// API key on protected routes, which is also enough to get a certificate signed
+    ApiKey,
+    /// Provisioner token to get a certificate signed, API key is never checked
+    Jwt,
+    /// API key on protected routes (when configured) and provisioner token to get a certificate signed
+    #[default]
+    Both,
+}
+
+impl From<&str> for AuthMode {
+    fn from(s: &str) -> Self {
+        match s.to_lowercase().as_str() {
+            "api_key" | "api-key" | "apikey" => Self::ApiKey,
+            "jwt" => Self::Jwt,
+            "both" => Self::Both,
             _ => Self::default(),
         }
     }
@@ -106,6 +611,37 @@ pub struct CertKeyPair {
     pub key: PathOr<PrivateKey>,
 }
 
+/// TLS identity served by the HTTPS listener, each part being PEM-formatted text or a path to a PEM file
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TlsConfig {
+    /// Server certificate followed by its issuers
+    pub certificate_chain: PathOr<String>,
+    pub private_key: PathOr<String>,
+}
+
+/// One-off maintenance command run instead of the server
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    /// Writes a backup of the storage to `out`
+    Export { out: PathBuf, include_keys: bool },
+    /// Restores the backup read from `input` into the storage
+    Import { input: PathBuf },
+    /// Generates or imports the root and intermediate CAs, as done on server startup
+    Init,
+    /// Prints the PEM chain of the realm's CA
+    ShowChain,
+    /// Issues a certificate for the CSR read from `csr`, written to `out` or to stdout
+    Sign { csr: PathBuf, out: Option<PathBuf> },
+    /// Writes the PEM chain of a CA, followed by its private key encrypted to the passphrase read from
+    /// `passphrase_file` when `include_key` is set, to `out` or to stdout
+    ExportCa {
+        ca: Option<String>,
+        include_key: bool,
+        passphrase_file: Option<PathBuf>,
+        out: Option<PathBuf>,
+    },
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct Config {
     #[serde(default = "default_picky_realm")]
@@ -114,8 +650,19 @@ pub struct Config {
     pub save_certificate: bool,
     #[serde(default = "default_log_level")]
     pub log_level: LevelFilter,
+    #[serde(default)]
+    pub log_format: LogFormat,
+    /// Logs are appended to this file instead of being written to stdout, the file being reopened on SIGHUP
+    #[serde(default)]
+    pub log_file: Option<PathBuf>,
     #[serde(default = "default_signing_algorithm")]
     pub signing_algorithm: SignatureAlgorithm,
+    /// Allows signing with an algorithm relying on a broken hash function, such as SHA-1
+    #[serde(default)]
+    pub allow_insecure_algorithms: bool,
+    /// How subject and authority key identifiers of generated certificates are computed
+    #[serde(default)]
+    pub key_id_method: KeyIdGenMethod,
 
     #[serde(default)]
     pub backend: BackendType,
@@ -125,13 +672,174 @@ pub struct Config {
     pub database_url: String,
     #[serde(default = "default_database_name")]
     pub database_name: String,
+    /// Prepended to every key written by the Redis backend
+    #[serde(default = "default_redis_key_prefix")]
+    pub redis_key_prefix: String,
+    /// Lets Redis evict leaf certificates, and their lookup entries, once their notAfter date is past
+    #[serde(default)]
+    pub redis_leaf_ttl: bool,
+    /// File the memory backend is loaded from on startup and saved to after every change
+    #[serde(default)]
+    pub memory_snapshot_path: Option<PathBuf>,
+    /// How long establishing a connection to a MongoDB server may take
+    #[serde(default = "default_mongo_connect_timeout_secs")]
+    pub mongo_connect_timeout_secs: u64,
+    /// How long a MongoDB operation waits for a reachable server before failing
+    #[serde(default = "default_mongo_operation_timeout_secs")]
+    pub mongo_operation_timeout_secs: u64,
+    /// Maximum number of connections per MongoDB server, the driver default when unset
+    #[serde(default)]
+    pub mongo_max_pool_size: Option<u32>,
+    /// Number of attempts at reaching MongoDB on startup before giving up
+    #[serde(default = "default_mongo_connect_attempts")]
+    pub mongo_connect_attempts: u32,
+    /// Delay before the first startup retry, doubled for every following retry
+    #[serde(default = "default_mongo_retry_delay_ms")]
+    pub mongo_retry_delay_ms: u64,
+    /// Prepended to the MongoDB collection names, so that several instances can share a database
+    #[serde(default)]
+    pub mongo_collection_prefix: String,
+    /// Prefixes the MongoDB collection names with the realm when no prefix is configured
+    #[serde(default)]
+    pub mongo_realm_prefix: bool,
+    /// Number of certificates, keys and lookups of each kind cached in front of the backend, 0 disabling the cache
+    #[serde(default = "default_cache_size")]
+    pub cache_size: usize,
+    /// How long a cached read is served before being fetched again from the backend
+    #[serde(default = "default_cache_ttl_secs")]
+    pub cache_ttl_secs: u64,
+
+    #[serde(default = "default_listen_address")]
+    pub listen_address: String,
+    /// Base URL clients reach this server at, used to build the URLs embedded in issued certificates
+    #[serde(default)]
+    pub external_url: Option<String>,
+    #[serde(default)]
+    pub tls: Option<TlsConfig>,
+    /// Serves a server certificate issued by the intermediate CA when no TLS identity is configured
+    #[serde(default)]
+    pub tls_bootstrap: bool,
+    #[serde(default = "default_tls_bootstrap_dns_name")]
+    pub tls_bootstrap_dns_name: String,
+    /// Listens on plain HTTP when no TLS identity is available
+    #[serde(default)]
+    pub insecure_http: bool,
+    /// Time given to in-flight requests to complete on shutdown
+    #[serde(default = "default_shutdown_drain_timeout_secs")]
+    pub shutdown_drain_timeout_secs: u64,
+
+    #[serde(default)]
+    pub key_type: KeyType,
+    #[serde(default = "default_root_key_size")]
+    pub root_key_size: usize,
+    #[serde(default = "default_intermediate_key_size")]
+    pub intermediate_key_size: usize,
 
     #[serde(default)]
     pub root: Option<CertKeyPair>,
+    /// Validity of a generated root CA
+    #[serde(default = "default_root_validity", with = "duration_str")]
+    pub root_validity: chrono::Duration,
+    /// Path length constraint of a generated root CA, unlimited when unset
+    #[serde(default)]
+    pub root_pathlen: Option<u8>,
     #[serde(default)]
     pub intermediate: Option<CertKeyPair>,
+    /// Validity of a generated intermediate CA, which must end before the root CA expires
+    #[serde(default = "default_intermediate_validity", with = "duration_str")]
+    pub intermediate_validity: chrono::Duration,
+    /// Names of additional intermediate CAs generated under the root, next to "{realm} Authority"
+    #[serde(default)]
+    pub intermediates: Vec<String>,
+    #[serde(default)]
+    pub ocsp_responder: Option<CertKeyPair>,
     #[serde(default)]
     pub provisioner_public_key: Option<PathOr<PublicKey>>,
+    #[serde(default)]
+    pub provisioner_jwks: Option<PathBuf>,
+    #[serde(default)]
+    pub provisioner_audience: Option<String>,
+
+    #[serde(default)]
+    pub auth_mode: AuthMode,
+    #[serde(default)]
+    pub api_key: Option<String>,
+    /// API keys limited to some scopes, next to `api_key` which has them all
+    #[serde(default)]
+    pub api_keys: Vec<NamedApiKey>,
+    #[serde(default)]
+    pub protected_routes: Option<Vec<String>>,
+
+    #[serde(default)]
+    pub issuance_policy: Option<IssuancePolicy>,
+    #[serde(default = "default_synthesize_san_from_cn")]
+    pub synthesize_san_from_cn: bool,
+    /// Answer a CSR matching a stored, valid and unrevoked certificate (same subject and public key) with that certificate
+    #[serde(default)]
+    pub reuse_existing_certs: bool,
+
+    #[serde(default = "default_csr_min_rsa_key_size")]
+    pub csr_min_rsa_key_size: usize,
+    #[serde(default = "default_csr_min_rsa_public_exponent")]
+    pub csr_min_rsa_public_exponent: u64,
+    #[serde(default = "default_csr_allowed_hash_algorithms")]
+    pub csr_allowed_hash_algorithms: Vec<HashAlgorithm>,
+
+    #[serde(default = "default_metrics_enabled")]
+    pub metrics_enabled: bool,
+
+    /// Interval between sweeps flagging expired certificates, 0 disables the sweep
+    #[serde(default)]
+    pub expiry_sweep_interval_secs: u64,
+    /// Days after which expired leaf certificates are deleted by the sweep, never when unset
+    #[serde(default)]
+    pub expired_retention_days: Option<u32>,
+
+    /// Maximum number of certificates walked when building a CA chain from stored certificates
+    #[serde(default = "default_max_chain_depth")]
+    pub max_chain_depth: usize,
+
+    /// Sustained rate of requests per client on issuance endpoints, 0 disables rate limiting
+    #[serde(default = "default_rate_limit_per_minute")]
+    pub rate_limit_per_minute: u32,
+    #[serde(default = "default_rate_limit_burst")]
+    pub rate_limit_burst: u32,
+
+    /// Largest request body accepted, in bytes
+    #[serde(default = "default_max_body_size")]
+    pub max_body_size: usize,
+
+    #[serde(default = "default_request_log_level")]
+    pub request_log_level: LevelFilter,
+    #[serde(default = "default_request_log_excluded_paths")]
+    pub request_log_excluded_paths: Vec<String>,
+
+    /// Origins allowed to call the API from a browser (`*` for any), CORS is disabled when empty
+    #[serde(default)]
+    pub cors_allowed_origins: Vec<String>,
+
+    /// URLs a signed JSON payload is POSTed to whenever a certificate is issued, registered or revoked
+    #[serde(default)]
+    pub webhook_urls: Vec<String>,
+    /// Shared secret keying the HMAC-SHA256 signature of webhook payloads, required when webhooks are set
+    #[serde(default)]
+    pub webhook_secret: Option<String>,
+    /// Number of attempts at delivering a payload to a webhook before giving up
+    #[serde(default = "default_webhook_max_attempts")]
+    pub webhook_max_attempts: u32,
+    /// Delay before the first delivery retry, doubled for every following retry
+    #[serde(default = "default_webhook_retry_delay_ms")]
+    pub webhook_retry_delay_ms: u64,
+
+    /// Set from the command line only
+    #[serde(skip)]
+    pub command: Option<Command>,
+    /// Configuration file given on the command line, read again on reload
+    #[serde(skip)]
+    pub config_path: Option<PathBuf>,
+    /// Command line arguments, applied again on reload
+    #[serde(skip)]
+    pub cli_args: Vec<OsString>,
 }
 
 impl Default for Config {
@@ -140,118 +848,681 @@ impl Default for Config {
             realm: default_picky_realm(),
             save_certificate: default_save_certificate(),
             log_level: default_log_level(),
+            log_format: LogFormat::default(),
+            log_file: None,
             signing_algorithm: default_signing_algorithm(),
+            allow_insecure_algorithms: false,
+            key_id_method: KeyIdGenMethod::default(),
             backend: BackendType::default(),
             file_backend_path: default_file_backend_path(),
             database_url: default_database_url(),
             database_name: default_database_name(),
+            redis_key_prefix: default_redis_key_prefix(),
+            redis_leaf_ttl: false,
+            memory_snapshot_path: None,
+            mongo_connect_timeout_secs: default_mongo_connect_timeout_secs(),
+            mongo_operation_timeout_secs: default_mongo_operation_timeout_secs(),
+            mongo_max_pool_size: None,
+            mongo_connect_attempts: default_mongo_connect_attempts(),
+            mongo_retry_delay_ms: default_mongo_retry_delay_ms(),
+            mongo_collection_prefix: String::new(),
+            mongo_realm_prefix: false,
+            cache_size: default_cache_size(),
+            cache_ttl_secs: default_cache_ttl_secs(),
+            listen_address: default_listen_address(),
+            external_url: None,
+            tls: None,
+            tls_bootstrap: false,
+            tls_bootstrap_dns_name: default_tls_bootstrap_dns_name(),
+            insecure_http: false,
+            shutdown_drain_timeout_secs: default_shutdown_drain_timeout_secs(),
+            key_type: KeyType::default(),
+            root_key_size: default_root_key_size(),
+            intermediate_key_size: default_intermediate_key_size(),
             root: None,
+            root_validity: default_root_validity(),
+            root_pathlen: None,
             intermediate: None,
+            intermediate_validity: default_intermediate_validity(),
+            intermediates: Vec::new(),
+            ocsp_responder: None,
             provisioner_public_key: None,
+            provisioner_jwks: None,
+            provisioner_audience: None,
+            auth_mode: AuthMode::default(),
+            api_key: None,
+            api_keys: Vec::new(),
+            protected_routes: None,
+            issuance_policy: None,
+            synthesize_san_from_cn: default_synthesize_san_from_cn(),
+            reuse_existing_certs: false,
+            csr_min_rsa_key_size: default_csr_min_rsa_key_size(),
+            csr_min_rsa_public_exponent: default_csr_min_rsa_public_exponent(),
+            csr_allowed_hash_algorithms: default_csr_allowed_hash_algorithms(),
+            metrics_enabled: default_metrics_enabled(),
+            expiry_sweep_interval_secs: 0,
+            expired_retention_days: None,
+            max_chain_depth: default_max_chain_depth(),
+            rate_limit_per_minute: default_rate_limit_per_minute(),
+            rate_limit_burst: default_rate_limit_burst(),
+            max_body_size: default_max_body_size(),
+            request_log_level: default_request_log_level(),
+            request_log_excluded_paths: default_request_log_excluded_paths(),
+            cors_allowed_origins: Vec::new(),
+            webhook_urls: Vec::new(),
+            webhook_secret: None,
+            webhook_max_attempts: default_webhook_max_attempts(),
+            webhook_retry_delay_ms: default_webhook_retry_delay_ms(),
+            command: None,
+            config_path: None,
+            cli_args: Vec::new(),
         }
     }
 }
 
 impl Config {
+    /// Layers the configuration: command line over environment over configuration file over defaults
     pub fn startup_init() -> Self {
-        let mut config = if let Ok(yaml_conf) = std::fs::read_to_string(YAML_CONF_PATH) {
-            serde_yaml::from_str(&yaml_conf).expect("yaml conf")
+        let cli_args: Vec<OsString> = env::args_os().collect();
+        let yaml = clap::load_yaml!("cli.yml");
+        let matches = App::from_yaml(yaml).get_matches_from(cli_args.iter());
+
+        let config_path = matches.value_of("config").map(PathBuf::from);
+        let mut config = if config_path.is_some() || Path::new(YAML_CONF_PATH).exists() {
+            let (config, unknown_keys) = Config::init_file(config_path.as_deref()).expect("config file");
+            if !unknown_keys.is_empty() {
+                // logging isn't initialized yet
+                eprintln!("unknown configuration keys ignored: {}", unknown_keys.join(", "));
+            }
+            config
         } else {
             Config::default()
         };
+        config.config_path = config_path;
+        config.cli_args = cli_args;
 
         config.inject_env();
-        config.inject_cli();
+        config.inject_cli(&matches);
 
         config
     }
 
-    pub fn init_yaml() -> Result<Self, String> {
-        let yaml_conf =
-            std::fs::read_to_string(YAML_CONF_PATH).map_err(|e| format!("couldn't read yaml config: {}", e))?;
-        Ok(serde_yaml::from_str(&yaml_conf).map_err(|e| format!("invalid yaml conf: {}", e))?)
-    }
+    /// Layers the configuration again the way `startup_init` did, reading the same configuration file
+    /// and the current environment, along with the unknown keys found in the file
+    pub fn reread(&self) -> Result<(Self, Vec<String>), String> {
+        let (mut config, unknown_keys) = if self.config_path.is_some() || Path::new(YAML_CONF_PATH).exists() {
+            Config::init_file(self.config_path.as_deref())?
+        } else {
+            (Config::default(), Vec::new())
+        };
+        config.config_path = self.config_path.clone();
+        config.cli_args = self.cli_args.clone();
 
-    fn inject_cli(&mut self) {
-        let yaml = clap::load_yaml!("cli.yml");
-        let app = App::from_yaml(yaml);
-        let matches = app.get_matches();
+        // a bad environment variable must fail the reload, not bring the server down
+        panic::catch_unwind(AssertUnwindSafe(|| config.inject_env())).map_err(|panic| {
+            let message = panic
+                .downcast_ref::<String>()
+                .cloned()
+                .or_else(|| panic.downcast_ref::<&str>().map(|s| (*s).to_owned()))
+                .unwrap_or_default();
+            format!("invalid environment: {}", message)
+        })?;
 
-        if let Some(v) = matches.value_of("realm") {
-            self.realm = v.to_string();
+        if !self.cli_args.is_empty() {
+            let yaml = clap::load_yaml!("cli.yml");
+            let matches = App::from_yaml(yaml)
+                .get_matches_from_safe(self.cli_args.iter())
+                .map_err(|e| e.to_string())?;
+            config.inject_cli(&matches);
         }
 
-        if matches.is_present("save-certificate") {
-            self.save_certificate = true;
-        }
+        Ok((config, unknown_keys))
+    }
 
-        if let Some(v) = matches.value_of("log-level") {
-            self.log_level = parse_level_filter(v);
-        }
+    /// Names of the settings whose value differs in `other`
+    pub fn changed_settings(&self, other: &Config) -> Vec<String> {
+        let (old, new) = match (serde_json::to_value(self), serde_json::to_value(other)) {
+            (Ok(serde_json::Value::Object(old)), Ok(serde_json::Value::Object(new))) => (old, new),
+            _ => return Vec::new(),
+        };
+        old.iter()
+            .filter(|(name, value)| new.get(*name) != Some(*value))
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
 
-        if let Some(v) = matches.value_of("backend") {
-            self.backend = BackendType::from(v);
+    /// Checks that going from this configuration to `other` doesn't need a restart
+    pub fn check_reloadable(&self, other: &Config) -> Result<(), String> {
+        let refused: Vec<String> = self
+            .changed_settings(other)
+            .into_iter()
+            .filter(|name| RESTART_ONLY_SETTINGS.contains(&name.as_str()))
+            .collect();
+        if refused.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("changing {} requires a restart", refused.join(", ")))
         }
+    }
 
-        if let Some(v) = matches.value_of("db-url") {
-            self.database_url = v.to_string();
-        }
+    /// Checks the settings against each other and against the files they name, so that a bad
+    /// configuration is refused on startup rather than failing requests later on.
+    ///
+    /// Every problem found is reported, not only the first one.
+    pub fn validate(&self) -> Result<(), ConfigErrors> {
+        let mut errors = Vec::new();
 
-        if let Some(v) = matches.value_of("db-name") {
-            self.database_name = v.to_string();
+        if let Err(e) = self.check_signing_algorithm() {
+            errors.push(e);
         }
 
-        if matches.is_present("dump-config") {
-            let yaml_conf = serde_yaml::to_string(&self).expect("conf to yaml");
-            if let Err(e) = std::fs::write(YAML_CONF_PATH, yaml_conf) {
-                println!("failed to write yaml config: {}", e);
+        self.validate_backend(&mut errors);
+
+        if let Some(path) = &self.log_file {
+            if let Err(e) = check_writable_parent(path) {
+                errors.push(format!("log_file: {}", e));
             }
         }
 
-        if matches.is_present("show-config") {
-            println!("{:#?}", self);
+        if self.root.is_none() && self.root_key_size < MIN_CA_RSA_KEY_SIZE {
+            errors.push(format!(
+                "root_key_size: RSA keys must be at least {} bits (got {})",
+                MIN_CA_RSA_KEY_SIZE, self.root_key_size
+            ));
         }
-    }
-
-    fn inject_env(&mut self) {
-        if let Ok(val) = env::var(PICKY_REALM_ENV) {
-            self.realm = val;
+        if self.intermediate.is_none() && self.intermediate_key_size < MIN_CA_RSA_KEY_SIZE {
+            errors.push(format!(
+                "intermediate_key_size: RSA keys must be at least {} bits (got {})",
+                MIN_CA_RSA_KEY_SIZE, self.intermediate_key_size
+            ));
         }
 
-        if let Ok(val) = env::var(PICKY_SAVE_CERTIFICATE_ENV) {
-            self.save_certificate = val.parse::<bool>().expect("save certificate env variable");
+        if self.max_body_size == 0 {
+            errors.push("max_body_size must be positive".to_owned());
         }
 
-        if let Ok(val) = env::var(PICKY_BACKEND_ENV) {
-            self.backend = BackendType::from(val.as_str());
+        if self.root_validity <= chrono::Duration::zero() {
+            errors.push("root_validity must be positive".to_owned());
+        }
+        if self.intermediate_validity <= chrono::Duration::zero() {
+            errors.push("intermediate_validity must be positive".to_owned());
+        }
+        if self.root.is_none() && self.intermediate_validity >= self.root_validity {
+            errors.push("intermediate_validity must be shorter than root_validity".to_owned());
+        }
+        if self.root.is_none() && self.root_pathlen == Some(0) {
+            errors.push("root_pathlen must be at least 1 to allow intermediate CAs".to_owned());
         }
 
-        self.file_backend_path = env::var(PICKY_FILE_BACKEND_PATH_ENV)
-            .map(PathBuf::from)
-            .unwrap_or_else(|_| default_file_backend_path());
-
-        if let Ok(val) = env::var(PICKY_DATABASE_URL_ENV) {
-            self.database_url = val;
+        for (setting, pair) in &[
+            ("root", &self.root),
+            ("intermediate", &self.intermediate),
+            ("ocsp_responder", &self.ocsp_responder),
+        ] {
+            if let Some(pair) = pair {
+                if let Err(e) = check_cert_key_pair(pair) {
+                    errors.push(format!("{}: {}", setting, e));
+                }
+            }
         }
 
-        if let Ok(val) = env::var(PICKY_DATABASE_NAME_ENV) {
-            self.database_name = val;
+        if let Some(tls) = &self.tls {
+            for (setting, pem) in &[
+                ("tls.certificate_chain", &tls.certificate_chain),
+                ("tls.private_key", &tls.private_key),
+            ] {
+                let pem_str = match pem {
+                    PathOr::Path(path) => std::fs::read_to_string(path)
+                        .map_err(|e| format!("{}: couldn't read {}: {}", setting, path.display(), e)),
+                    PathOr::Some(pem_str) => Ok(pem_str.clone()),
+                };
+                match pem_str {
+                    Ok(pem_str) => {
+                        if let Err(e) = pem_str.parse::<Pem>() {
+                            errors.push(format!("{}: invalid pem: {}", setting, e));
+                        }
+                    }
+                    Err(e) => errors.push(e),
+                }
+            }
         }
 
-        if !inject_cert_key_pair(&mut self.root, PICKY_ROOT_CERT_ENV, PICKY_ROOT_KEY_ENV) {
-            inject_cert_key_pair_path(&mut self.root, PICKY_ROOT_CERT_PATH_ENV, PICKY_ROOT_KEY_PATH_ENV);
+        if let Some(PathOr::Path(path)) = &self.provisioner_public_key {
+            if let Err(e) = read_pem_file(path).and_then(|pem| PublicKey::from_pem(&pem).map_err(|e| e.to_string())) {
+                errors.push(format!("provisioner_public_key: {}", e));
+            }
+        }
+        if let Some(path) = &self.provisioner_jwks {
+            if let Err(e) = std::fs::metadata(path) {
+                errors.push(format!("provisioner_jwks: couldn't read {}: {}", path.display(), e));
+            }
         }
 
-        if !inject_cert_key_pair(
-            &mut self.intermediate,
-            PICKY_INTERMEDIATE_CERT_ENV,
-            PICKY_INTERMEDIATE_KEY_ENV,
-        ) {
-            inject_cert_key_pair_path(
-                &mut self.intermediate,
-                PICKY_INTERMEDIATE_CERT_PATH_ENV,
-                PICKY_INTERMEDIATE_KEY_PATH_ENV,
-            );
+        match &self.api_key {
+            Some(api_key) if api_key.trim().is_empty() => errors.push("api_key is empty".to_owned()),
+            Some(_) => {}
+            None if !self.api_keys.is_empty() => {}
+            None if self.auth_mode == AuthMode::ApiKey => {
+                errors.push("auth_mode api_key requires an api_key".to_owned())
+            }
+            None if self.protected_routes.is_some() => {
+                errors.push("protected_routes are set without an api_key".to_owned())
+            }
+            None => {}
+        }
+
+        let mut names = HashSet::new();
+        if self.api_key.is_some() {
+            names.insert(DEFAULT_API_KEY_NAME);
+        }
+        for api_key in &self.api_keys {
+            if api_key.name.trim().is_empty() {
+                errors.push("api_keys: a key has no name".to_owned());
+            } else if !names.insert(api_key.name.as_str()) {
+                errors.push(format!("api_keys: {} is used by several keys", api_key.name));
+            }
+            if api_key.key.trim().is_empty() {
+                errors.push(format!("api_keys: {} key is empty", api_key.name));
+            }
+            if api_key.scopes.is_empty() {
+                errors.push(format!("api_keys: {} has no scope", api_key.name));
+            }
+        }
+
+        if let Some(url) = &self.external_url {
+            if let Err(e) = check_external_url(url) {
+                errors.push(format!("external_url: {}", e));
+            }
+        }
+
+        for origin in &self.cors_allowed_origins {
+            if let Err(e) = check_cors_origin(origin) {
+                errors.push(format!("cors_allowed_origins: {}", e));
+            }
+        }
+
+        for url in &self.webhook_urls {
+            if let Err(e) = check_webhook_url(url) {
+                errors.push(format!("webhook_urls: {}", e));
+            }
+        }
+        match &self.webhook_secret {
+            Some(secret) if secret.is_empty() => errors.push("webhook_secret is empty".to_owned()),
+            Some(_) => {}
+            None if !self.webhook_urls.is_empty() => {
+                errors.push("webhook_urls are set without a webhook_secret".to_owned())
+            }
+            None => {}
+        }
+        if self.webhook_max_attempts == 0 {
+            errors.push("webhook_max_attempts must be positive".to_owned());
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigErrors(errors))
+        }
+    }
+
+    fn validate_backend(&self, errors: &mut Vec<String>) {
+        let (feature, schemes): (Option<&str>, &[&str]) = match self.backend {
+            BackendType::MongoDb => (None, &["mongodb://", "mongodb+srv://"]),
+            BackendType::Memory | BackendType::File => (None, &[]),
+            BackendType::Postgres => (Some("postgres"), &["postgres://", "postgresql://"]),
+            BackendType::Redis => (Some("redis"), &["redis://", "rediss://", "redis+unix://"]),
+            BackendType::Sqlite => (Some("sqlite"), &[]),
+            BackendType::Mysql => (Some("mysql"), &["mysql://"]),
+        };
+
+        let feature_missing = (matches!(feature, Some("postgres")) && !cfg!(feature = "postgres"))
+            || (matches!(feature, Some("redis")) && !cfg!(feature = "redis"))
+            || (matches!(feature, Some("sqlite")) && !cfg!(feature = "sqlite"))
+            || (matches!(feature, Some("mysql")) && !cfg!(feature = "mysql"));
+        if feature_missing {
+            errors.push(format!(
+                "backend {:?} requires picky-server to be built with the {} feature",
+                self.backend,
+                feature.unwrap_or_default()
+            ));
+        }
+
+        if !schemes.is_empty() && !schemes.iter().any(|scheme| self.database_url.starts_with(scheme)) {
+            errors.push(format!(
+                "database_url '{}' isn't a {:?} URL (expected {})",
+                self.database_url,
+                self.backend,
+                schemes.join(" or ")
+            ));
+        }
+
+        let path = match self.backend {
+            BackendType::File => Some(("file_backend_path", self.file_backend_path.clone())),
+            BackendType::Sqlite => match self.database_url.strip_prefix("sqlite://") {
+                Some(path) => Path::new(path).parent().map(|dir| ("database_url", dir.to_owned())),
+                None => Some(("file_backend_path", self.file_backend_path.clone())),
+            },
+            BackendType::Memory => self
+                .memory_snapshot_path
+                .as_ref()
+                .and_then(|path| path.parent())
+                .map(|dir| ("memory_snapshot_path", dir.to_owned())),
+            _ => None,
+        };
+        if let Some((setting, dir)) = path {
+            if let Err(e) = check_writable_dir(&dir) {
+                errors.push(format!("{}: {}", setting, e));
+            }
+        }
+    }
+
+    /// Refuses a signing algorithm relying on a broken hash function unless insecure algorithms are allowed
+    pub fn check_signing_algorithm(&self) -> Result<(), String> {
+        if is_insecure_signing_algorithm(self.signing_algorithm) && !self.allow_insecure_algorithms {
+            return Err(format!(
+                "signing algorithm {} is insecure, set allow_insecure_algorithms to use it anyway",
+                self.signing_algorithm
+            ));
+        }
+        Ok(())
+    }
+
+    /// Reads the given configuration file, or `picky_server_conf.yaml`, along with the unknown keys it contains.
+    /// Files with a `.toml` extension are read as TOML, others as YAML.
+    pub fn init_file(path: Option<&Path>) -> Result<(Self, Vec<String>), String> {
+        let path = path.unwrap_or_else(|| Path::new(YAML_CONF_PATH));
+        let conf = std::fs::read_to_string(path).map_err(|e| format!("couldn't read {}: {}", path.display(), e))?;
+
+        let mut unknown_keys = Vec::new();
+        let config = if path.extension().map(|ext| ext == "toml").unwrap_or(false) {
+            let mut deserializer = toml::Deserializer::new(&conf);
+            serde_ignored::deserialize(&mut deserializer, |key| unknown_keys.push(unknown_key(&key)))
+                .map_err(|e| format!("invalid toml conf: {}", e))?
+        } else {
+            let value: serde_yaml::Value =
+                serde_yaml::from_str(&conf).map_err(|e| format!("invalid yaml conf: {}", e))?;
+            serde_ignored::deserialize(value, |key| unknown_keys.push(unknown_key(&key)))
+                .map_err(|e| format!("invalid yaml conf: {}", e))?
+        };
+
+        Ok((config, unknown_keys))
+    }
+
+    fn inject_cli(&mut self, matches: &ArgMatches) {
+        if let Some(v) = matches.value_of("realm") {
+            self.realm = v.to_string();
+        }
+
+        if matches.is_present("save-certificate") {
+            self.save_certificate = true;
+        }
+
+        if matches.is_present("insecure-http") {
+            self.insecure_http = true;
+        }
+
+        if let Some(v) = matches.value_of("signing-algorithm") {
+            self.signing_algorithm = parse_signing_algorithm(v).unwrap_or_else(|e| panic!("{}", e));
+        }
+
+        if let Some(v) = matches.value_of("key-id-method") {
+            self.key_id_method = parse_key_id_method(v).unwrap_or_else(|e| panic!("{}", e));
+        }
+
+        if matches.is_present("allow-insecure-algorithms") {
+            self.allow_insecure_algorithms = true;
+        }
+
+        if let Some(v) = matches.value_of("log-level") {
+            self.log_level = parse_level_filter(v);
+        }
+
+        if let (Some(cert), Some(key)) = (matches.value_of("root-cert-file"), matches.value_of("root-key-file")) {
+            self.root = Some(CertKeyPair {
+                cert: PathOr::Path(cert.into()),
+                key: PathOr::Path(key.into()),
+            });
+        }
+
+        if let (Some(cert), Some(key)) = (
+            matches.value_of("intermediate-cert-file"),
+            matches.value_of("intermediate-key-file"),
+        ) {
+            self.intermediate = Some(CertKeyPair {
+                cert: PathOr::Path(cert.into()),
+                key: PathOr::Path(key.into()),
+            });
+        }
+
+        if let Some(v) = matches.value_of("backend") {
+            self.backend = BackendType::from(v);
+        }
+
+        if let Some(v) = matches.value_of("db-url") {
+            self.database_url = v.to_string();
+        }
+
+        if let Some(v) = matches.value_of("db-name") {
+            self.database_name = v.to_string();
+        }
+
+        if let Some(v) = matches.value_of("csr-min-rsa-key-size") {
+            self.csr_min_rsa_key_size = v.parse().expect("csr min rsa key size");
+        }
+
+        if let Some(v) = matches.value_of("csr-min-rsa-public-exponent") {
+            self.csr_min_rsa_public_exponent = v.parse().expect("csr min rsa public exponent");
+        }
+
+        if let Some(v) = matches.value_of("csr-allowed-hash-algorithms") {
+            self.csr_allowed_hash_algorithms = parse_hash_algorithms(v);
+        }
+
+        self.command = match matches.subcommand() {
+            ("export", Some(export)) => Some(Command::Export {
+                out: PathBuf::from(export.value_of("out").expect("export output path")),
+                include_keys: export.is_present("include-keys"),
+            }),
+            ("import", Some(import)) => Some(Command::Import {
+                input: PathBuf::from(import.value_of("in").expect("import input path")),
+            }),
+            ("init", Some(_)) => Some(Command::Init),
+            ("show-chain", Some(_)) => Some(Command::ShowChain),
+            ("sign", Some(sign)) => Some(Command::Sign {
+                csr: PathBuf::from(sign.value_of("csr").expect("sign csr path")),
+                out: sign.value_of("out").map(PathBuf::from),
+            }),
+            ("export-ca", Some(export_ca)) => Some(Command::ExportCa {
+                ca: export_ca.value_of("ca").map(str::to_owned),
+                include_key: export_ca.is_present("include-key"),
+                passphrase_file: export_ca.value_of("passphrase-file").map(PathBuf::from),
+                out: export_ca.value_of("out").map(PathBuf::from),
+            }),
+            _ => None,
+        };
+
+        if matches.is_present("dump-config") {
+            let yaml_conf = serde_yaml::to_string(&self).expect("conf to yaml");
+            if let Err(e) = std::fs::write(YAML_CONF_PATH, yaml_conf) {
+                println!("failed to write yaml config: {}", e);
+            }
+        }
+
+        if matches.is_present("show-config") {
+            println!("{:#?}", self);
+        }
+    }
+
+    fn inject_env(&mut self) {
+        if let Ok(val) = env::var(PICKY_REALM_ENV) {
+            self.realm = val;
+        }
+
+        if let Ok(val) = env::var(PICKY_SIGNING_ALGORITHM_ENV) {
+            self.signing_algorithm = parse_signing_algorithm(&val).unwrap_or_else(|e| panic!("{}", e));
+        }
+
+        if let Ok(val) = env::var(PICKY_KEY_ID_METHOD_ENV) {
+            self.key_id_method = parse_key_id_method(&val).unwrap_or_else(|e| panic!("{}", e));
+        }
+
+        if let Ok(val) = env::var(PICKY_ALLOW_INSECURE_ALGORITHMS_ENV) {
+            self.allow_insecure_algorithms = val.parse::<bool>().expect("allow insecure algorithms env variable");
+        }
+
+        if let Ok(val) = env::var(PICKY_SAVE_CERTIFICATE_ENV) {
+            self.save_certificate = val.parse::<bool>().expect("save certificate env variable");
+        }
+
+        if let Ok(val) = env::var(PICKY_BACKEND_ENV) {
+            self.backend = BackendType::from(val.as_str());
+        }
+
+        if let Ok(val) = env::var(PICKY_FILE_BACKEND_PATH_ENV) {
+            self.file_backend_path = PathBuf::from(val);
+        }
+
+        if let Ok(val) = env::var(PICKY_DATABASE_URL_ENV) {
+            self.database_url = val;
+        }
+
+        if let Ok(val) = env::var(PICKY_DATABASE_NAME_ENV) {
+            self.database_name = val;
+        }
+
+        if let Ok(val) = env::var(PICKY_REDIS_KEY_PREFIX_ENV) {
+            self.redis_key_prefix = val;
+        }
+
+        if let Ok(val) = env::var(PICKY_REDIS_LEAF_TTL_ENV) {
+            self.redis_leaf_ttl = val.parse::<bool>().expect("redis leaf ttl env variable");
+        }
+
+        if let Ok(val) = env::var(PICKY_MEMORY_SNAPSHOT_PATH_ENV) {
+            self.memory_snapshot_path = Some(PathBuf::from(val));
+        }
+
+        if let Ok(val) = env::var(PICKY_MONGO_CONNECT_TIMEOUT_ENV) {
+            self.mongo_connect_timeout_secs = val.parse().expect("mongo connect timeout env variable");
+        }
+
+        if let Ok(val) = env::var(PICKY_MONGO_OPERATION_TIMEOUT_ENV) {
+            self.mongo_operation_timeout_secs = val.parse().expect("mongo operation timeout env variable");
+        }
+
+        if let Ok(val) = env::var(PICKY_MONGO_MAX_POOL_SIZE_ENV) {
+            self.mongo_max_pool_size = Some(val.parse().expect("mongo max pool size env variable"));
+        }
+
+        if let Ok(val) = env::var(PICKY_MONGO_CONNECT_ATTEMPTS_ENV) {
+            self.mongo_connect_attempts = val.parse().expect("mongo connect attempts env variable");
+        }
+
+        if let Ok(val) = env::var(PICKY_MONGO_RETRY_DELAY_MS_ENV) {
+            self.mongo_retry_delay_ms = val.parse().expect("mongo retry delay env variable");
+        }
+
+        if let Ok(val) = env::var(PICKY_MONGO_COLLECTION_PREFIX_ENV) {
+            self.mongo_collection_prefix = val;
+        }
+
+        if let Ok(val) = env::var(PICKY_MONGO_REALM_PREFIX_ENV) {
+            self.mongo_realm_prefix = val.parse::<bool>().expect("mongo realm prefix env variable");
+        }
+
+        if let Ok(val) = env::var(PICKY_CACHE_SIZE_ENV) {
+            self.cache_size = val.parse().expect("cache size env variable");
+        }
+
+        if let Ok(val) = env::var(PICKY_CACHE_TTL_ENV) {
+            self.cache_ttl_secs = val.parse().expect("cache ttl env variable");
+        }
+
+        if let Ok(val) = env::var(PICKY_LISTEN_ADDRESS_ENV) {
+            self.listen_address = val;
+        }
+
+        if let Ok(val) = env::var(PICKY_EXTERNAL_URL_ENV) {
+            self.external_url = Some(val);
+        }
+
+        if let Ok(val) = env::var(PICKY_INSECURE_HTTP_ENV) {
+            self.insecure_http = val.parse::<bool>().expect("insecure http env variable");
+        }
+
+        if let Ok(val) = env::var(PICKY_SHUTDOWN_DRAIN_TIMEOUT_ENV) {
+            self.shutdown_drain_timeout_secs = val.parse().expect("shutdown drain timeout env variable");
+        }
+
+        if let (Ok(certificate_chain), Ok(private_key)) = (
+            env::var(PICKY_TLS_CERTIFICATE_CHAIN_ENV),
+            env::var(PICKY_TLS_PRIVATE_KEY_ENV),
+        ) {
+            self.tls = Some(TlsConfig {
+                certificate_chain: PathOr::Some(certificate_chain),
+                private_key: PathOr::Some(private_key),
+            });
+        } else if let (Ok(certificate_chain_path), Ok(private_key_path)) = (
+            env::var(PICKY_TLS_CERTIFICATE_CHAIN_PATH_ENV),
+            env::var(PICKY_TLS_PRIVATE_KEY_PATH_ENV),
+        ) {
+            self.tls = Some(TlsConfig {
+                certificate_chain: PathOr::Path(certificate_chain_path.into()),
+                private_key: PathOr::Path(private_key_path.into()),
+            });
+        }
+
+        if let Ok(val) = env::var(PICKY_TLS_BOOTSTRAP_ENV) {
+            self.tls_bootstrap = val.parse::<bool>().expect("tls bootstrap env variable");
+        }
+
+        if let Ok(val) = env::var(PICKY_TLS_BOOTSTRAP_DNS_NAME_ENV) {
+            self.tls_bootstrap_dns_name = val;
+        }
+
+        if let Ok(val) = env::var(PICKY_KEY_TYPE_ENV) {
+            self.key_type = val.parse().expect("key type env variable");
+        }
+
+        if let Ok(val) = env::var(PICKY_ROOT_KEY_SIZE_ENV) {
+            self.root_key_size = val.parse().expect("root key size env variable");
+        }
+
+        if let Ok(val) = env::var(PICKY_INTERMEDIATE_KEY_SIZE_ENV) {
+            self.intermediate_key_size = val.parse().expect("intermediate key size env variable");
+        }
+
+        if let Ok(val) = env::var(PICKY_INTERMEDIATES_ENV) {
+            self.intermediates = val
+                .split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(str::to_owned)
+                .collect();
+        }
+
+        if let Ok(val) = env::var(PICKY_ROOT_VALIDITY_ENV) {
+            self.root_validity = parse_duration(&val).expect("root validity env variable");
+        }
+
+        if let Ok(val) = env::var(PICKY_ROOT_PATHLEN_ENV) {
+            self.root_pathlen = Some(val.parse().expect("root pathlen env variable"));
+        }
+
+        if let Ok(val) = env::var(PICKY_INTERMEDIATE_VALIDITY_ENV) {
+            self.intermediate_validity = parse_duration(&val).expect("intermediate validity env variable");
+        }
+
+        for (pair, vars) in [
+            (&mut self.root, &ROOT_CERT_KEY_PAIR_ENV),
+            (&mut self.intermediate, &INTERMEDIATE_CERT_KEY_PAIR_ENV),
+            (&mut self.ocsp_responder, &OCSP_RESPONDER_CERT_KEY_PAIR_ENV),
+        ] {
+            match cert_key_pair_from_env(vars, |name| env::var(name).ok()) {
+                Ok(Some(from_env)) => *pair = Some(from_env),
+                Ok(None) => {}
+                Err(e) => panic!("{}", e),
+            }
         }
 
         if let Ok(pem_str) = env::var(PICKY_PROVISIONER_PUBLIC_KEY_ENV) {
@@ -263,41 +1534,783 @@ impl Config {
         } else if let Ok(val) = env::var(PICKY_PROVISIONER_PUBLIC_KEY_PATH_ENV) {
             self.provisioner_public_key = Some(PathOr::Path(val.into()));
         }
+
+        if let Ok(val) = env::var(PICKY_PROVISIONER_JWKS_PATH_ENV) {
+            self.provisioner_jwks = Some(PathBuf::from(val));
+        }
+
+        if let Ok(val) = env::var(PICKY_PROVISIONER_AUDIENCE_ENV) {
+            self.provisioner_audience = Some(val);
+        }
+
+        if let Ok(val) = env::var(PICKY_AUTH_MODE_ENV) {
+            self.auth_mode = AuthMode::from(val.as_str());
+        }
+
+        if let Ok(val) = env::var(PICKY_API_KEY_ENV) {
+            self.api_key = Some(val);
+        }
+
+        if let Ok(val) = env::var(PICKY_PROTECTED_ROUTES_ENV) {
+            self.protected_routes = Some(
+                val.split(',')
+                    .map(str::trim)
+                    .filter(|route| !route.is_empty())
+                    .map(str::to_owned)
+                    .collect(),
+            );
+        }
+
+        if let Ok(val) = env::var(PICKY_ISSUANCE_ALLOWED_NAMES_ENV) {
+            self.issuance_policy
+                .get_or_insert_with(IssuancePolicy::default)
+                .allowed_names = val
+                .split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(str::to_owned)
+                .collect();
+        }
+
+        if let Ok(val) = env::var(PICKY_ISSUANCE_ALLOW_IP_ADDRESSES_ENV) {
+            self.issuance_policy
+                .get_or_insert_with(IssuancePolicy::default)
+                .allow_ip_addresses = val.parse().unwrap_or(false);
+        }
+
+        if let Ok(val) = env::var(PICKY_ISSUANCE_REQUIRE_CN_IN_SAN_ENV) {
+            self.issuance_policy
+                .get_or_insert_with(IssuancePolicy::default)
+                .require_cn_in_san = val.parse().unwrap_or(false);
+        }
+
+        if let Ok(val) = env::var(PICKY_SYNTHESIZE_SAN_FROM_CN_ENV) {
+            self.synthesize_san_from_cn = val.parse::<bool>().expect("synthesize SAN from CN env variable");
+        }
+
+        if let Ok(val) = env::var(PICKY_REUSE_EXISTING_CERTS_ENV) {
+            self.reuse_existing_certs = val.parse::<bool>().expect("reuse existing certs env variable");
+        }
+
+        if let Ok(val) = env::var(PICKY_CSR_MIN_RSA_KEY_SIZE_ENV) {
+            self.csr_min_rsa_key_size = val.parse().expect("csr min rsa key size env variable");
+        }
+
+        if let Ok(val) = env::var(PICKY_CSR_MIN_RSA_PUBLIC_EXPONENT_ENV) {
+            self.csr_min_rsa_public_exponent = val.parse().expect("csr min rsa public exponent env variable");
+        }
+
+        if let Ok(val) = env::var(PICKY_CSR_ALLOWED_HASH_ALGORITHMS_ENV) {
+            self.csr_allowed_hash_algorithms = parse_hash_algorithms(&val);
+        }
+
+        if let Ok(val) = env::var(PICKY_METRICS_ENABLED_ENV) {
+            self.metrics_enabled = val.parse::<bool>().expect("metrics enabled env variable");
+        }
+
+        if let Ok(val) = env::var(PICKY_EXPIRY_SWEEP_INTERVAL_ENV) {
+            self.expiry_sweep_interval_secs = val.parse().expect("expiry sweep interval env variable");
+        }
+
+        if let Ok(val) = env::var(PICKY_EXPIRED_RETENTION_DAYS_ENV) {
+            self.expired_retention_days = Some(val.parse().expect("expired retention days env variable"));
+        }
+
+        if let Ok(val) = env::var(PICKY_MAX_CHAIN_DEPTH_ENV) {
+            self.max_chain_depth = val.parse().expect("max chain depth env variable");
+        }
+
+        if let Ok(val) = env::var(PICKY_RATE_LIMIT_PER_MINUTE_ENV) {
+            self.rate_limit_per_minute = val.parse().expect("rate limit per minute env variable");
+        }
+
+        if let Ok(val) = env::var(PICKY_RATE_LIMIT_BURST_ENV) {
+            self.rate_limit_burst = val.parse().expect("rate limit burst env variable");
+        }
+
+        if let Ok(val) = env::var(PICKY_MAX_BODY_SIZE_ENV) {
+            self.max_body_size = val.parse().expect("max body size env variable");
+        }
+
+        if let Ok(val) = env::var(PICKY_LOG_LEVEL_ENV) {
+            self.log_level = parse_level_filter(&val);
+        }
+
+        if let Ok(val) = env::var(PICKY_LOG_FORMAT_ENV) {
+            self.log_format = val.parse().expect("log format env variable");
+        }
+
+        if let Ok(val) = env::var(PICKY_LOG_FILE_ENV) {
+            self.log_file = Some(PathBuf::from(val));
+        }
+
+        if let Ok(val) = env::var(PICKY_REQUEST_LOG_LEVEL_ENV) {
+            self.request_log_level = parse_level_filter(&val);
+        }
+
+        if let Ok(val) = env::var(PICKY_REQUEST_LOG_EXCLUDED_PATHS_ENV) {
+            self.request_log_excluded_paths = val
+                .split(',')
+                .map(str::trim)
+                .filter(|path| !path.is_empty())
+                .map(str::to_owned)
+                .collect();
+        }
+
+        if let Ok(val) = env::var(PICKY_CORS_ALLOWED_ORIGINS_ENV) {
+            self.cors_allowed_origins = val
+                .split(',')
+                .map(str::trim)
+                .filter(|origin| !origin.is_empty())
+                .map(str::to_owned)
+                .collect();
+        }
+
+        if let Ok(val) = env::var(PICKY_WEBHOOK_URLS_ENV) {
+            self.webhook_urls = val
+                .split(',')
+                .map(str::trim)
+                .filter(|url| !url.is_empty())
+                .map(str::to_owned)
+                .collect();
+        }
+
+        if let Ok(val) = env::var(PICKY_WEBHOOK_SECRET_ENV) {
+            self.webhook_secret = Some(val);
+        }
+
+        if let Ok(val) = env::var(PICKY_WEBHOOK_MAX_ATTEMPTS_ENV) {
+            self.webhook_max_attempts = val.parse().expect("webhook max attempts env variable");
+        }
+
+        if let Ok(val) = env::var(PICKY_WEBHOOK_RETRY_DELAY_MS_ENV) {
+            self.webhook_retry_delay_ms = val.parse().expect("webhook retry delay env variable");
+        }
     }
 }
 
-fn inject_cert_key_pair(pair: &mut Option<CertKeyPair>, cert_pem_env: &str, key_pem_env: &str) -> bool {
-    if let Ok(cert_pem_str) = env::var(cert_pem_env) {
-        if let Ok(key_pem_str) = env::var(key_pem_env) {
-            *pair = Some(CertKeyPair {
-                cert: PathOr::Some({
-                    let pem = cert_pem_str.parse::<Pem>().expect("cert pem");
-                    Cert::from_pem(&pem).expect("cert")
-                }),
-                key: PathOr::Some({
-                    let pem = key_pem_str.parse::<Pem>().expect("key pem");
-                    PrivateKey::from_pem(&pem).expect("key")
-                }),
-            });
+/// Problems found by [`Config::validate`]
+#[derive(Debug)]
+pub struct ConfigErrors(pub Vec<String>);
+
+impl fmt::Display for ConfigErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid configuration:")?;
+        for error in &self.0 {
+            write!(f, "\n  - {}", error)?;
+        }
+        Ok(())
+    }
+}
 
-            return true;
+/// Dotted path of an unknown configuration key, optional and newtype levels left out
+fn unknown_key(path: &serde_ignored::Path<'_>) -> String {
+    use serde_ignored::Path;
+
+    let (parent, segment) = match path {
+        Path::Root => return String::new(),
+        Path::Seq { parent, index } => (parent, index.to_string()),
+        Path::Map { parent, key } => (parent, key.clone()),
+        Path::Some { parent } | Path::NewtypeStruct { parent } | Path::NewtypeVariant { parent } => {
+            return unknown_key(parent)
         }
+    };
+    match unknown_key(parent) {
+        parent if parent.is_empty() => segment,
+        parent => format!("{}.{}", parent, segment),
     }
+}
 
-    false
+fn read_pem_file(path: &Path) -> Result<Pem<'static>, String> {
+    std::fs::read_to_string(path)
+        .map_err(|e| format!("couldn't read {}: {}", path.display(), e))?
+        .parse::<Pem>()
+        .map_err(|e| format!("invalid pem in {}: {}", path.display(), e))
 }
 
-fn inject_cert_key_pair_path(pair: &mut Option<CertKeyPair>, cert_path_env: &str, key_path_env: &str) -> bool {
-    if let Ok(cert_path) = env::var(cert_path_env) {
-        if let Ok(key_path) = env::var(key_path_env) {
-            *pair = Some(CertKeyPair {
-                cert: PathOr::Path(cert_path.into()),
-                key: PathOr::Path(key_path.into()),
-            });
+/// Checks that a certificate and key can be loaded and that the key is the certificate's one
+fn check_cert_key_pair(pair: &CertKeyPair) -> Result<(), String> {
+    let cert = match &pair.cert {
+        PathOr::Path(path) => Cert::from_pem(&read_pem_file(path)?)
+            .map_err(|e| format!("invalid certificate in {}: {}", path.display(), e))?,
+        PathOr::Some(cert) => cert.clone(),
+    };
+    let key = match &pair.key {
+        PathOr::Path(path) => PrivateKey::from_pem(&read_pem_file(path)?)
+            .map_err(|e| format!("invalid private key in {}: {}", path.display(), e))?,
+        PathOr::Some(key) => key.clone(),
+    };
+
+    // signing proves the key matches without depending on how the public key is encoded
+    let proof_algorithm = SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_256);
+    let message = b"picky configuration check";
+    proof_algorithm
+        .sign(message, &key)
+        .and_then(|signature| proof_algorithm.verify(cert.public_key(), message, &signature))
+        .map_err(|_| "private key doesn't match the certificate".to_owned())
+}
 
-            return true;
+/// Checks that `dir`, or the closest ancestor that would hold it once created, is a writable directory
+fn check_writable_dir(dir: &Path) -> Result<(), String> {
+    let existing = dir
+        .ancestors()
+        .find(|ancestor| ancestor.as_os_str().is_empty() || ancestor.exists())
+        .filter(|ancestor| !ancestor.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    let metadata = std::fs::metadata(existing).map_err(|e| format!("couldn't access {}: {}", existing.display(), e))?;
+    if !metadata.is_dir() {
+        return Err(format!("{} isn't a directory", existing.display()));
+    }
+    if metadata.permissions().readonly() {
+        return Err(format!("{} isn't writable", existing.display()));
+    }
+    Ok(())
+}
+
+fn check_writable_parent(file: &Path) -> Result<(), String> {
+    if file.is_dir() {
+        return Err(format!("{} is a directory", file.display()));
+    }
+    check_writable_dir(file.parent().unwrap_or_else(|| Path::new(".")))
+}
+
+/// Browsers send origins as `<scheme>://<host>[:<port>]`, anything else would never match
+fn check_cors_origin(origin: &str) -> Result<(), String> {
+    if origin == "*" {
+        return Ok(());
+    }
+
+    let host = origin
+        .strip_prefix("https://")
+        .or_else(|| origin.strip_prefix("http://"))
+        .ok_or_else(|| format!("{} isn't an http(s) origin", origin))?;
+    if host.is_empty() || host.contains('/') {
+        return Err(format!(
+            "{} isn't an origin, expected <scheme>://<host>[:<port>]",
+            origin
+        ));
+    }
+
+    Ok(())
+}
+
+/// URLs derived from the external URL are embedded in certificates and must be fetchable as is
+fn check_external_url(url: &str) -> Result<(), String> {
+    let rest = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))
+        .ok_or_else(|| format!("{} isn't an http(s) URL", url))?;
+    if rest.is_empty() || rest.starts_with('/') {
+        return Err(format!("{} has no host", url));
+    }
+    if rest.contains(|c: char| c == '?' || c == '#' || c.is_whitespace()) {
+        return Err(format!("{} must not have a query, a fragment or whitespace", url));
+    }
+
+    Ok(())
+}
+
+/// Unlike the external URL, webhook URLs may have a query
+fn check_webhook_url(url: &str) -> Result<(), String> {
+    let rest = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))
+        .ok_or_else(|| format!("{} isn't an http(s) URL", url))?;
+    if rest.is_empty() || rest.starts_with('/') {
+        return Err(format!("{} has no host", url));
+    }
+
+    Ok(())
+}
+
+/// Environment variables providing a certificate and its key, either inline as PEM or as PEM file paths
+struct CertKeyPairEnv {
+    cert: &'static str,
+    cert_paths: [&'static str; 2],
+    key: &'static str,
+    key_paths: [&'static str; 2],
+}
+
+const ROOT_CERT_KEY_PAIR_ENV: CertKeyPairEnv = CertKeyPairEnv {
+    cert: PICKY_ROOT_CERT_ENV,
+    cert_paths: [PICKY_ROOT_CERT_FILE_ENV, PICKY_ROOT_CERT_PATH_ENV],
+    key: PICKY_ROOT_KEY_ENV,
+    key_paths: [PICKY_ROOT_KEY_FILE_ENV, PICKY_ROOT_KEY_PATH_ENV],
+};
+
+const INTERMEDIATE_CERT_KEY_PAIR_ENV: CertKeyPairEnv = CertKeyPairEnv {
+    cert: PICKY_INTERMEDIATE_CERT_ENV,
+    cert_paths: [PICKY_INTERMEDIATE_CERT_FILE_ENV, PICKY_INTERMEDIATE_CERT_PATH_ENV],
+    key: PICKY_INTERMEDIATE_KEY_ENV,
+    key_paths: [PICKY_INTERMEDIATE_KEY_FILE_ENV, PICKY_INTERMEDIATE_KEY_PATH_ENV],
+};
+
+const OCSP_RESPONDER_CERT_KEY_PAIR_ENV: CertKeyPairEnv = CertKeyPairEnv {
+    cert: PICKY_OCSP_RESPONDER_CERT_ENV,
+    cert_paths: [PICKY_OCSP_RESPONDER_CERT_FILE_ENV, PICKY_OCSP_RESPONDER_CERT_PATH_ENV],
+    key: PICKY_OCSP_RESPONDER_KEY_ENV,
+    key_paths: [PICKY_OCSP_RESPONDER_KEY_FILE_ENV, PICKY_OCSP_RESPONDER_KEY_PATH_ENV],
+};
+
+/// Where a PEM value set in the environment comes from
+enum PemSource {
+    Inline(String),
+    Path(PathBuf),
+}
+
+/// Finds the single source of a PEM value among its inline and path variables.
+///
+/// Setting it both inline and as a file, or through two path variables naming different files,
+/// is ambiguous and refused rather than silently resolved.
+fn pem_source_from_env(
+    inline_var: &str,
+    path_vars: &[&str],
+    lookup: &impl Fn(&str) -> Option<String>,
+) -> Result<Option<PemSource>, String> {
+    let mut path: Option<(&str, String)> = None;
+    for var in path_vars {
+        if let Some(value) = lookup(var) {
+            match &path {
+                Some((other_var, other_value)) if *other_value != value => {
+                    return Err(format!(
+                        "{} ({}) and {} ({}) name different files",
+                        other_var, other_value, var, value
+                    ));
+                }
+                Some(_) => {}
+                None => path = Some((var, value)),
+            }
         }
     }
 
-    false
+    match (lookup(inline_var), path) {
+        (Some(_), Some((path_var, _))) => Err(format!("both {} and {} are set", inline_var, path_var)),
+        (Some(pem), None) => Ok(Some(PemSource::Inline(pem))),
+        (None, Some((_, path))) => Ok(Some(PemSource::Path(path.into()))),
+        (None, None) => Ok(None),
+    }
+}
+
+/// Certificate and key set in the environment, if any
+fn cert_key_pair_from_env(
+    vars: &CertKeyPairEnv,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Result<Option<CertKeyPair>, String> {
+    let cert = pem_source_from_env(vars.cert, &vars.cert_paths, &lookup)?;
+    let key = pem_source_from_env(vars.key, &vars.key_paths, &lookup)?;
+
+    let (cert, key) = match (cert, key) {
+        (Some(cert), Some(key)) => (cert, key),
+        (None, None) => return Ok(None),
+        (Some(_), None) => return Err(format!("{} or {} is set without a key", vars.cert, vars.cert_paths[0])),
+        (None, Some(_)) => {
+            return Err(format!(
+                "{} or {} is set without a certificate",
+                vars.key, vars.key_paths[0]
+            ))
+        }
+    };
+
+    let cert = match cert {
+        PemSource::Inline(pem_str) => {
+            let pem = pem_str
+                .parse::<Pem>()
+                .map_err(|e| format!("couldn't parse {} pem: {}", vars.cert, e))?;
+            PathOr::Some(Cert::from_pem(&pem).map_err(|e| format!("couldn't parse {}: {}", vars.cert, e))?)
+        }
+        PemSource::Path(path) => PathOr::Path(path),
+    };
+
+    let key = match key {
+        PemSource::Inline(pem_str) => {
+            let pem = pem_str
+                .parse::<Pem>()
+                .map_err(|e| format!("couldn't parse {} pem: {}", vars.key, e))?;
+            PathOr::Some(PrivateKey::from_pem(&pem).map_err(|e| format!("couldn't parse {}: {}", vars.key, e))?)
+        }
+        PemSource::Path(path) => PathOr::Path(path),
+    };
+
+    Ok(Some(CertKeyPair { cert, key }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("20y").unwrap(), chrono::Duration::days(7300));
+        assert_eq!(parse_duration("90d").unwrap(), chrono::Duration::days(90));
+        assert_eq!(parse_duration("1y30d").unwrap(), chrono::Duration::days(395));
+        assert_eq!(parse_duration(" 12H ").unwrap(), chrono::Duration::hours(12));
+        assert!(parse_duration("20").is_err());
+        assert!(parse_duration("d").is_err());
+        assert!(parse_duration("5 years").is_err());
+        assert!(parse_duration("0d").is_err());
+
+        assert_eq!(format_duration(chrono::Duration::days(3650)), "10y");
+        assert_eq!(format_duration(chrono::Duration::days(1826)), "1826d");
+        assert_eq!(format_duration(chrono::Duration::seconds(90)), "90s");
+    }
+
+    #[test]
+    fn signing_algorithms() {
+        for (name, hash) in &[
+            ("rsa-sha256", HashAlgorithm::SHA2_256),
+            ("rsa-sha384", HashAlgorithm::SHA2_384),
+            ("rsa-sha512", HashAlgorithm::SHA2_512),
+            ("RSA-SHA3-512", HashAlgorithm::SHA3_512),
+        ] {
+            assert_eq!(
+                parse_signing_algorithm(name).unwrap(),
+                SignatureAlgorithm::RsaPkcs1v15(*hash)
+            );
+        }
+
+        assert_eq!(
+            parse_signing_algorithm("rsa-pss-sha256").unwrap(),
+            SignatureAlgorithm::RsaPssSha256
+        );
+
+        let err = parse_signing_algorithm("rsa-sha265").unwrap_err();
+        assert!(err.contains("'rsa-sha265'"), "{}", err);
+        assert!(err.contains("rsa-sha256, rsa-sha384"), "{}", err);
+        assert!(err.contains("rsa-pss-sha512"), "{}", err);
+
+        // the authority keys are RSA keys
+        let err = parse_signing_algorithm("ecdsa-sha256").unwrap_err();
+        assert!(err.contains("'ecdsa-sha256'"), "{}", err);
+
+        let mut config = Config::default();
+        assert!(config.check_signing_algorithm().is_ok());
+        config.signing_algorithm = parse_signing_algorithm("rsa-sha1").unwrap();
+        assert!(config.check_signing_algorithm().is_err());
+        config.allow_insecure_algorithms = true;
+        assert!(config.check_signing_algorithm().is_ok());
+    }
+
+    #[test]
+    fn key_id_methods() {
+        assert_eq!(
+            parse_key_id_method("spk-value-sha1").unwrap(),
+            KeyIdGenMethod::SPKValueHashedLeftmost160(HashAlgorithm::SHA1)
+        );
+        assert_eq!(
+            parse_key_id_method(" spki-der-sha256").unwrap(),
+            KeyIdGenMethod::SPKFullDER(HashAlgorithm::SHA2_256)
+        );
+        assert_eq!(
+            Config::default().key_id_method,
+            parse_key_id_method("spki-der-sha256").unwrap()
+        );
+
+        let err = parse_key_id_method("spk-sha1").unwrap_err();
+        assert!(err.contains("'spk-sha1'"), "{}", err);
+        assert!(err.contains("spk-value-sha1, spk-value-sha224"), "{}", err);
+    }
+
+    #[test]
+    fn reload_refuses_restart_only_settings() {
+        let old = Config::default();
+        let mut new = old.clone();
+        new.api_key = Some("rotated".to_owned());
+        new.rate_limit_per_minute = 10;
+        assert_eq!(old.changed_settings(&new), vec!["api_key", "rate_limit_per_minute"]);
+        assert!(old.check_reloadable(&new).is_ok());
+
+        new.backend = BackendType::Memory;
+        new.listen_address = "0.0.0.0:443".to_owned();
+        assert_eq!(
+            old.check_reloadable(&new).unwrap_err(),
+            "changing backend, listen_address requires a restart"
+        );
+    }
+
+    fn validation_errors(config: &Config) -> Vec<String> {
+        config.validate().err().map(|errors| errors.0).unwrap_or_default()
+    }
+
+    #[test]
+    fn valid_configuration() {
+        let dir = std::env::temp_dir().join(format!("picky_valid_conf_{}", std::process::id()));
+        let mut config = Config::default();
+        assert!(validation_errors(&config).is_empty());
+
+        config.backend = BackendType::File;
+        config.file_backend_path = dir.join("not/created/yet");
+        config.api_key = Some("secret".to_owned());
+        config.auth_mode = AuthMode::ApiKey;
+        config.protected_routes = Some(vec!["/sign".to_owned()]);
+        config.cors_allowed_origins = vec!["https://dashboard.example.com:8443".to_owned(), "*".to_owned()];
+        config.external_url = Some("http://pki.example.com/picky/".to_owned());
+        config.webhook_urls = vec!["https://inventory.example.com/hooks/picky?source=pki".to_owned()];
+        config.webhook_secret = Some("hook-secret".to_owned());
+        assert_eq!(validation_errors(&config), Vec::<String>::new());
+    }
+
+    #[test]
+    fn invalid_configurations() {
+        let file = std::env::temp_dir().join(format!("picky_invalid_conf_{}", std::process::id()));
+        std::fs::write(&file, "not a directory").unwrap();
+
+        type Case = (fn(&mut Config), &'static str);
+
+        let cases: Vec<Case> = vec![
+            (
+                |config| config.database_url = "127.0.0.1:27017".to_owned(),
+                "database_url '127.0.0.1:27017' isn't a MongoDb URL",
+            ),
+            (|config| config.api_key = Some(" ".to_owned()), "api_key is empty"),
+            (
+                |config| config.auth_mode = AuthMode::ApiKey,
+                "auth_mode api_key requires an api_key",
+            ),
+            (
+                |config| config.protected_routes = Some(vec!["/sign".to_owned()]),
+                "protected_routes are set without an api_key",
+            ),
+            (
+                |config| config.intermediate_validity = config.root_validity,
+                "intermediate_validity must be shorter than root_validity",
+            ),
+            (
+                |config| config.root_pathlen = Some(0),
+                "root_pathlen must be at least 1 to allow intermediate CAs",
+            ),
+            (
+                |config| {
+                    let key = NamedApiKey {
+                        name: "ci".to_owned(),
+                        key: "ci-secret".to_owned(),
+                        scopes: vec![ApiKeyScope::Issue],
+                    };
+                    config.api_keys = vec![key.clone(), key];
+                },
+                "api_keys: ci is used by several keys",
+            ),
+            (
+                |config| config.cors_allowed_origins = vec!["https://dashboard.example.com/".to_owned()],
+                "cors_allowed_origins: https://dashboard.example.com/ isn't an origin",
+            ),
+            (
+                |config| config.cors_allowed_origins = vec!["dashboard.example.com".to_owned()],
+                "cors_allowed_origins: dashboard.example.com isn't an http(s) origin",
+            ),
+            (
+                |config| config.external_url = Some("pki.example.com".to_owned()),
+                "external_url: pki.example.com isn't an http(s) URL",
+            ),
+            (
+                |config| config.external_url = Some("http://pki.example.com/?realm=picky".to_owned()),
+                "external_url: http://pki.example.com/?realm=picky must not have a query",
+            ),
+            (
+                |config| config.webhook_urls = vec!["https://inventory.example.com/hooks".to_owned()],
+                "webhook_urls are set without a webhook_secret",
+            ),
+            (
+                |config| config.webhook_urls = vec!["inventory.example.com".to_owned()],
+                "webhook_urls: inventory.example.com isn't an http(s) URL",
+            ),
+            (
+                |config| config.webhook_max_attempts = 0,
+                "webhook_max_attempts must be positive",
+            ),
+            (|config| config.max_body_size = 0, "max_body_size must be positive"),
+            (
+                |config| config.root_key_size = 1024,
+                "root_key_size: RSA keys must be at least 2048 bits (got 1024)",
+            ),
+            (
+                |config| {
+                    config.tls = Some(TlsConfig {
+                        certificate_chain: PathOr::Path("/nonexistent/picky/chain.pem".into()),
+                        private_key: PathOr::Some("not a pem".to_owned()),
+                    })
+                },
+                "tls.certificate_chain: couldn't read /nonexistent/picky/chain.pem",
+            ),
+        ];
+        for (configure, expected) in cases {
+            let mut config = Config::default();
+            configure(&mut config);
+            let errors = validation_errors(&config);
+            assert!(
+                errors.iter().any(|error| error.starts_with(expected)),
+                "expected '{}' in {:?}",
+                expected,
+                errors
+            );
+        }
+
+        let config = Config {
+            backend: BackendType::File,
+            file_backend_path: file.join("database"),
+            ..Default::default()
+        };
+        let errors = validation_errors(&config);
+        assert_eq!(
+            errors,
+            vec![format!("file_backend_path: {} isn't a directory", file.display())]
+        );
+
+        let key = |pem: &str| PrivateKey::from_pem(&pem.parse::<Pem>().unwrap()).unwrap();
+        let root_key = key(crate::test_files::RSA_2048_PK_1);
+        let root = crate::picky_controller::Picky::generate_root(
+            "Picky Root CA",
+            &root_key,
+            config.signing_algorithm,
+            config.key_id_method,
+            chrono::Duration::days(365),
+        )
+        .unwrap();
+        let config = Config {
+            root: Some(CertKeyPair {
+                cert: PathOr::Some(root),
+                key: PathOr::Some(key(crate::test_files::RSA_2048_PK_2)),
+            }),
+            intermediate: Some(CertKeyPair {
+                cert: PathOr::Path(file.clone()),
+                key: PathOr::Some(root_key),
+            }),
+            api_key: Some(String::new()),
+            ..Default::default()
+        };
+        let errors = validation_errors(&config);
+        assert_eq!(errors.len(), 3, "{:?}", errors);
+        assert_eq!(errors[0], "root: private key doesn't match the certificate");
+        assert!(errors[1].starts_with(&format!("intermediate: invalid pem in {}", file.display())));
+        assert_eq!(errors[2], "api_key is empty");
+
+        let report = ConfigErrors(errors).to_string();
+        assert!(report.starts_with("invalid configuration:\n  - root: "), "{}", report);
+
+        std::fs::remove_file(&file).unwrap();
+    }
+
+    fn lookup<'a>(vars: &'a [(&'a str, &'a str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        move |name| {
+            vars.iter()
+                .find(|(var, _)| *var == name)
+                .map(|(_, value)| (*value).to_owned())
+        }
+    }
+
+    #[test]
+    fn cert_key_pair_files_from_env() {
+        let pair = cert_key_pair_from_env(
+            &ROOT_CERT_KEY_PAIR_ENV,
+            lookup(&[
+                (PICKY_ROOT_CERT_FILE_ENV, "/run/secrets/root.crt"),
+                (PICKY_ROOT_KEY_PATH_ENV, "/run/secrets/root.key"),
+            ]),
+        )
+        .unwrap()
+        .unwrap();
+        assert!(matches!(&pair.cert, PathOr::Path(path) if path == Path::new("/run/secrets/root.crt")));
+        assert!(matches!(&pair.key, PathOr::Path(path) if path == Path::new("/run/secrets/root.key")));
+
+        // the same file named twice is no conflict
+        let pair = cert_key_pair_from_env(
+            &INTERMEDIATE_CERT_KEY_PAIR_ENV,
+            lookup(&[
+                (PICKY_INTERMEDIATE_CERT_FILE_ENV, "/run/secrets/int.crt"),
+                (PICKY_INTERMEDIATE_CERT_PATH_ENV, "/run/secrets/int.crt"),
+                (PICKY_INTERMEDIATE_KEY_FILE_ENV, "/run/secrets/int.key"),
+            ]),
+        )
+        .unwrap()
+        .unwrap();
+        assert!(matches!(&pair.cert, PathOr::Path(path) if path == Path::new("/run/secrets/int.crt")));
+
+        assert!(cert_key_pair_from_env(&ROOT_CERT_KEY_PAIR_ENV, lookup(&[]))
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn inconsistent_cert_key_pair_env_is_refused() {
+        let err = cert_key_pair_from_env(
+            &ROOT_CERT_KEY_PAIR_ENV,
+            lookup(&[
+                (PICKY_ROOT_CERT_ENV, "-----BEGIN CERTIFICATE-----"),
+                (PICKY_ROOT_CERT_FILE_ENV, "/run/secrets/root.crt"),
+                (PICKY_ROOT_KEY_FILE_ENV, "/run/secrets/root.key"),
+            ]),
+        )
+        .err()
+        .unwrap();
+        assert_eq!(err, "both PICKY_ROOT_CERT and PICKY_ROOT_CERT_FILE are set");
+
+        let err = cert_key_pair_from_env(
+            &ROOT_CERT_KEY_PAIR_ENV,
+            lookup(&[
+                (PICKY_ROOT_CERT_FILE_ENV, "/run/secrets/root.crt"),
+                (PICKY_ROOT_CERT_PATH_ENV, "/etc/picky/root.crt"),
+                (PICKY_ROOT_KEY_FILE_ENV, "/run/secrets/root.key"),
+            ]),
+        )
+        .err()
+        .unwrap();
+        assert!(err.contains("name different files"), "{}", err);
+
+        let err = cert_key_pair_from_env(
+            &ROOT_CERT_KEY_PAIR_ENV,
+            lookup(&[(PICKY_ROOT_CERT_FILE_ENV, "/run/secrets/root.crt")]),
+        )
+        .err()
+        .unwrap();
+        assert!(err.contains("without a key"), "{}", err);
+    }
+
+    #[test]
+    fn log_settings_from_env() {
+        assert_eq!("JSON".parse::<LogFormat>().unwrap(), LogFormat::Json);
+        assert_eq!("text".parse::<LogFormat>().unwrap(), LogFormat::Text);
+        assert!("logfmt".parse::<LogFormat>().is_err());
+
+        // only read by this test
+        env::set_var(PICKY_LOG_LEVEL_ENV, "debug");
+        env::set_var(PICKY_LOG_FORMAT_ENV, "json");
+        env::set_var(PICKY_LOG_FILE_ENV, "/var/log/picky.log");
+        let mut config = Config::default();
+        config.inject_env();
+        env::remove_var(PICKY_LOG_LEVEL_ENV);
+        env::remove_var(PICKY_LOG_FORMAT_ENV);
+        env::remove_var(PICKY_LOG_FILE_ENV);
+
+        assert_eq!(config.log_level, LevelFilter::Debug);
+        assert_eq!(config.log_format, LogFormat::Json);
+        assert_eq!(config.log_file, Some(PathBuf::from("/var/log/picky.log")));
+    }
+
+    #[test]
+    fn toml_file_layered_under_env() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../test_assets/picky_server_conf.toml");
+        let (mut config, unknown_keys) = Config::init_file(Some(&path)).expect("couldn't load config file");
+        assert_eq!(
+            unknown_keys,
+            vec!["rate_limit_brust", "issuance_policy.require_cn_in_sann"]
+        );
+
+        // only read by this test
+        env::set_var(PICKY_API_KEY_ENV, "from-env");
+        config.inject_env();
+        env::remove_var(PICKY_API_KEY_ENV);
+
+        assert_eq!(config.api_key.as_deref(), Some("from-env"));
+        assert_eq!(config.realm, "Fixture");
+        assert_eq!(config.backend, BackendType::File);
+        assert_eq!(config.file_backend_path, PathBuf::from("/var/lib/picky"));
+        assert_eq!(config.rate_limit_per_minute, 120);
+        assert_eq!(config.rate_limit_burst, default_rate_limit_burst());
+        assert_eq!(
+            config.issuance_policy,
+            Some(IssuancePolicy {
+                allowed_names: vec![".fixture.local".to_owned()],
+                ..IssuancePolicy::default()
+            })
+        );
+    }
 }
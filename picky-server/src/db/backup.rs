@@ -0,0 +1,467 @@
+use crate::db::{
+    AuditEntry, CertificateEntry, ConflictPolicy, IssuanceMetadata, ListFilter, PickyStorage, RevocationEntry,
+    StorageError,
+};
+use picky::x509::{certificate::CertType, Cert};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Format version written in exported bundles, bundles from a newer version are refused on import
+pub const BACKUP_BUNDLE_VERSION: u32 = 1;
+
+/// Backend-independent dump of a storage, used to back it up or to migrate to another backend
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackupBundle {
+    pub version: u32,
+    /// Export date as a unix timestamp
+    pub exported_at: i64,
+    pub certificates: Vec<BackupCertificate>,
+    /// Private keys, only exported when explicitly asked for since bundles are not encrypted
+    #[serde(default)]
+    pub keys: Vec<BackupKey>,
+    pub names: Vec<BackupName>,
+    pub key_identifiers: Vec<BackupKeyIdentifier>,
+    pub revocations: Vec<RevocationEntry>,
+    #[serde(default)]
+    pub audit: Vec<AuditEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackupCertificate {
+    pub addressing_hash: String,
+    /// DER-encoded certificate
+    #[serde(with = "base64_der")]
+    pub cert: Vec<u8>,
+    /// Set when flagged by the expiry sweep
+    #[serde(default)]
+    pub expired: bool,
+    pub metadata: Option<IssuanceMetadata>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackupKey {
+    pub addressing_hash: String,
+    /// PKCS#8 DER-encoded private key
+    #[serde(with = "base64_der")]
+    pub key: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackupName {
+    pub name: String,
+    /// Certificates stored under this name, oldest first
+    pub addressing_hashes: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackupKeyIdentifier {
+    pub key_identifier: String,
+    pub addressing_hash: String,
+}
+
+mod base64_der {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(der: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&base64::encode(der))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        base64::decode(&s).map_err(de::Error::custom)
+    }
+}
+
+/// Number of entries restored by `import`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ImportReport {
+    pub certificates: usize,
+    pub keys: usize,
+    pub revocations: usize,
+    pub audit: usize,
+}
+
+/// Dumps every stored certificate along with its names, key identifier, metadata, expiry status and revocation.
+/// Names and key identifiers are recovered from the certificates themselves (subject common name, DNS names and
+/// subject key identifier), which is how the server stores them.
+pub async fn export<S: PickyStorage + ?Sized>(storage: &S, include_keys: bool) -> Result<BackupBundle, StorageError> {
+    let listed = storage.list(&ListFilter::default(), 0, usize::MAX).await?;
+
+    let mut certificates = Vec::with_capacity(listed.len());
+    let mut keys = Vec::new();
+    let mut names = BTreeMap::new();
+    let mut key_identifiers = Vec::new();
+    let mut revocations = BTreeMap::new();
+
+    for listed in listed {
+        let hash = listed.addressing_hash;
+
+        if include_keys {
+            if let Ok(key) = storage.get_key_by_addressing_hash(&hash).await {
+                keys.push(BackupKey {
+                    addressing_hash: hash.clone(),
+                    key,
+                });
+            }
+        }
+
+        if let Some(revocation) = storage.get_revocation_by_addressing_hash(&hash).await? {
+            revocations.insert(revocation.addressing_hash.clone(), revocation);
+        }
+
+        match Cert::from_der(&listed.cert) {
+            Ok(cert) => {
+                for name in name_candidates(&cert) {
+                    if names.contains_key(&name) {
+                        continue;
+                    }
+                    let history = storage.get_addressing_hashes_by_name(&name).await?;
+                    if !history.is_empty() {
+                        names.insert(name, history);
+                    }
+                }
+
+                if let Ok(ski) = cert.subject_key_identifier() {
+                    let key_identifier = hex::encode(ski);
+                    if let Ok(bound) = storage.get_addressing_hash_by_key_identifier(&key_identifier).await {
+                        if bound == hash {
+                            key_identifiers.push(BackupKeyIdentifier {
+                                key_identifier,
+                                addressing_hash: hash.clone(),
+                            });
+                        }
+                    }
+                }
+
+                // certificates revoked without being stored are only reachable through their issuer
+                if matches!(cert.ty(), CertType::Root | CertType::Intermediate) {
+                    if let Some(issuer_name) = cert.subject_name().common_name().map(ToString::to_string) {
+                        for revocation in storage.get_revocations_by_issuer(&issuer_name).await? {
+                            revocations.insert(revocation.addressing_hash.clone(), revocation);
+                        }
+                    }
+                }
+            }
+            Err(e) => log::warn!("exporting undecodable certificate {} without its names: {}", hash, e),
+        }
+
+        certificates.push(BackupCertificate {
+            addressing_hash: hash.clone(),
+            metadata: storage.get_metadata(&hash).await?,
+            expired: listed.expiry.map(|expiry| expiry.expired).unwrap_or(false),
+            cert: listed.cert,
+        });
+    }
+
+    Ok(BackupBundle {
+        version: BACKUP_BUNDLE_VERSION,
+        exported_at: chrono::Utc::now().timestamp(),
+        certificates,
+        keys,
+        names: names
+            .into_iter()
+            .map(|(name, addressing_hashes)| BackupName {
+                name,
+                addressing_hashes,
+            })
+            .collect(),
+        key_identifiers,
+        revocations: revocations.into_values().collect(),
+        audit: storage.list_audit(0, usize::MAX).await?,
+    })
+}
+
+/// Names the server may have stored a certificate under
+fn name_candidates(cert: &Cert) -> Vec<String> {
+    let mut candidates = Vec::new();
+    if let Some(common_name) = cert.subject_name().common_name() {
+        candidates.push(common_name.to_string());
+    }
+    if let Ok(san) = cert.subject_alt_name() {
+        candidates.extend(san.dns_names().into_iter().map(ToString::to_string));
+    }
+    candidates
+}
+
+/// Restores a bundle produced by `export`, overwriting certificates that are already stored.
+/// Certificates are stored in the order of their name history so that each name ends up bound to the same
+/// certificate as in the exported storage.
+pub async fn import<S: PickyStorage + ?Sized>(storage: &S, bundle: BackupBundle) -> Result<ImportReport, StorageError> {
+    if bundle.version > BACKUP_BUNDLE_VERSION {
+        return Err(StorageError::UnsupportedBackup {
+            version: bundle.version,
+        });
+    }
+
+    let certificates: BTreeMap<&str, &BackupCertificate> = bundle
+        .certificates
+        .iter()
+        .map(|certificate| (certificate.addressing_hash.as_str(), certificate))
+        .collect();
+    let keys: BTreeMap<&str, &[u8]> = bundle
+        .keys
+        .iter()
+        .map(|key| (key.addressing_hash.as_str(), key.key.as_slice()))
+        .collect();
+    let key_identifiers: BTreeMap<&str, &str> = bundle
+        .key_identifiers
+        .iter()
+        .map(|kid| (kid.addressing_hash.as_str(), kid.key_identifier.as_str()))
+        .collect();
+
+    let mut report = ImportReport::default();
+    let mut stored = BTreeSet::new();
+
+    let entry = |name: String, certificate: &BackupCertificate| CertificateEntry {
+        name,
+        cert: certificate.cert.clone(),
+        key_identifier: key_identifiers
+            .get(certificate.addressing_hash.as_str())
+            .map(|kid| (*kid).to_owned())
+            .unwrap_or_else(|| certificate.addressing_hash.clone()),
+        key: keys.get(certificate.addressing_hash.as_str()).map(|key| key.to_vec()),
+    };
+
+    for name in &bundle.names {
+        for hash in &name.addressing_hashes {
+            let certificate = match certificates.get(hash.as_str()) {
+                Some(certificate) => certificate,
+                None => {
+                    log::warn!("skipping name {} pointing to {} missing from backup", name.name, hash);
+                    continue;
+                }
+            };
+            storage
+                .store(entry(name.name.clone(), certificate), ConflictPolicy::Overwrite)
+                .await?;
+            stored.insert(hash.as_str());
+        }
+    }
+
+    for certificate in &bundle.certificates {
+        let hash = certificate.addressing_hash.as_str();
+        if !stored.contains(hash) {
+            // not reachable by name in the exported storage, the addressing hash stands for its name
+            storage
+                .store(entry(hash.to_owned(), certificate), ConflictPolicy::Overwrite)
+                .await?;
+            stored.insert(hash);
+        }
+
+        if let Some(metadata) = &certificate.metadata {
+            storage.set_metadata(hash, metadata.clone()).await?;
+        }
+        if certificate.expired {
+            storage.set_expired(hash).await?;
+        }
+
+        report.certificates += 1;
+        if keys.contains_key(hash) {
+            report.keys += 1;
+        }
+    }
+
+    for revocation in bundle.revocations {
+        storage.revoke(revocation).await?;
+        report.revocations += 1;
+    }
+
+    for audit in bundle.audit {
+        storage.append_audit(audit).await?;
+        report.audit += 1;
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        addressing::encode_to_canonical_address,
+        config::Config,
+        db::{file::FileStorage, memory::MemoryStorage},
+        picky_controller::Picky,
+    };
+    use picky::{
+        hash::HashAlgorithm,
+        key::PrivateKey,
+        pem::parse_pem,
+        signature::SignatureAlgorithm,
+        x509::{csr::Csr, date::UTCDate, name::DirectoryName, KeyIdGenMethod},
+    };
+    use tokio_test::block_on;
+
+    const SIGNATURE_ALGORITHM: SignatureAlgorithm = SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_256);
+
+    fn private_key(pem: &str) -> PrivateKey {
+        PrivateKey::from_pem(&parse_pem(pem).unwrap()).unwrap()
+    }
+
+    fn store(storage: &dyn PickyStorage, name: &str, cert: &Cert, key: Option<&PrivateKey>) -> String {
+        let cert = cert.to_der().unwrap();
+        let hash = encode_to_canonical_address(&cert);
+        block_on(storage.store(
+            CertificateEntry {
+                name: name.to_owned(),
+                key_identifier: hex::encode(Cert::from_der(&cert).unwrap().subject_key_identifier().unwrap()),
+                cert,
+                key: key.map(|key| key.to_pkcs8().unwrap()),
+            },
+            ConflictPolicy::ErrorIfExists,
+        ))
+        .unwrap();
+        hash
+    }
+
+    /// Root and intermediate CAs with their keys, plus a revoked leaf
+    fn populated_storage() -> MemoryStorage {
+        let root_key = private_key(crate::test_files::RSA_2048_PK_1);
+        let intermediate_key = private_key(crate::test_files::RSA_2048_PK_2);
+        let leaf_key = private_key(crate::test_files::RSA_2048_PK_3);
+
+        let root = Picky::generate_root(
+            "Backup Root",
+            &root_key,
+            SIGNATURE_ALGORITHM,
+            KeyIdGenMethod::default(),
+            chrono::Duration::days(10),
+        )
+        .unwrap();
+        let intermediate = Picky::generate_intermediate(
+            "Backup Intermediate",
+            intermediate_key.to_public_key(),
+            &root,
+            &root_key,
+            SIGNATURE_ALGORITHM,
+            KeyIdGenMethod::default(),
+            chrono::Duration::days(5),
+        )
+        .unwrap();
+        let csr = Csr::generate(
+            DirectoryName::new_common_name("leaf.backup"),
+            &leaf_key,
+            SIGNATURE_ALGORITHM,
+        )
+        .unwrap();
+        let leaf = Picky::generate_leaf_from_csr(
+            csr,
+            &intermediate,
+            &intermediate_key,
+            SIGNATURE_ALGORITHM,
+            KeyIdGenMethod::default(),
+            None,
+            chrono::Duration::days(1),
+        )
+        .unwrap();
+
+        let storage = MemoryStorage::new();
+        store(&storage, "Backup Root", &root, Some(&root_key));
+        store(&storage, "Backup Intermediate", &intermediate, Some(&intermediate_key));
+        let leaf_hash = store(&storage, "leaf.backup", &leaf, None);
+        block_on(async {
+            storage
+                .revoke(RevocationEntry {
+                    addressing_hash: leaf_hash.clone(),
+                    issuer_name: "Backup Intermediate".to_owned(),
+                    serial_number: hex::encode(leaf.serial_number().as_signed_bytes_be()),
+                    revocation_date: 1_600_000_000,
+                    reason: Some(1),
+                })
+                .await
+                .unwrap();
+            storage.set_expired(&leaf_hash).await.unwrap();
+        });
+        storage
+    }
+
+    #[test]
+    fn keys_are_only_exported_on_request() {
+        let storage = populated_storage();
+
+        let bundle = block_on(storage.export(false)).unwrap();
+        assert_eq!(bundle.version, BACKUP_BUNDLE_VERSION);
+        assert_eq!(bundle.certificates.len(), 3);
+        assert!(bundle.keys.is_empty());
+
+        let bundle = block_on(storage.export(true)).unwrap();
+        assert_eq!(bundle.keys.len(), 2);
+    }
+
+    #[test]
+    fn newer_bundles_are_refused() {
+        let mut bundle = block_on(populated_storage().export(false)).unwrap();
+        bundle.version = BACKUP_BUNDLE_VERSION + 1;
+        match block_on(MemoryStorage::new().import(bundle)) {
+            Err(StorageError::UnsupportedBackup { version }) => assert_eq!(version, BACKUP_BUNDLE_VERSION + 1),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn memory_export_restores_into_file_storage() {
+        let source = populated_storage();
+        let bundle = block_on(source.export(true)).unwrap();
+        let json = serde_json::to_string(&bundle).unwrap();
+        let bundle: BackupBundle = serde_json::from_str(&json).unwrap();
+
+        let config = Config {
+            file_backend_path: std::env::temp_dir().join(format!("picky_file_backup_restore_{}", std::process::id())),
+            ..Default::default()
+        };
+        let _ = std::fs::remove_dir_all(&config.file_backend_path);
+
+        block_on(async {
+            let target = FileStorage::new(&config).await;
+            let report = target.import(bundle.clone()).await.unwrap();
+            assert_eq!(
+                report,
+                ImportReport {
+                    certificates: 3,
+                    keys: 2,
+                    revocations: 1,
+                    audit: 0,
+                }
+            );
+
+            // everything exported comes back, the export date aside
+            let mut restored = target.export(true).await.unwrap();
+            restored.exported_at = bundle.exported_at;
+            assert_eq!(restored, bundle);
+
+            // the restored intermediate CA keeps issuing
+            let intermediate_hash = target.get_addressing_hash_by_name("Backup Intermediate").await.unwrap();
+            let intermediate =
+                Cert::from_der(&target.get_cert_by_addressing_hash(&intermediate_hash).await.unwrap()).unwrap();
+            let intermediate_key =
+                PrivateKey::from_pkcs8(&target.get_key_by_addressing_hash(&intermediate_hash).await.unwrap()).unwrap();
+            let leaf_key = private_key(crate::test_files::RSA_2048_PK_4);
+            let csr = Csr::generate(
+                DirectoryName::new_common_name("restored.backup"),
+                &leaf_key,
+                SIGNATURE_ALGORITHM,
+            )
+            .unwrap();
+            let leaf = Picky::generate_leaf_from_csr(
+                csr,
+                &intermediate,
+                &intermediate_key,
+                SIGNATURE_ALGORITHM,
+                KeyIdGenMethod::default(),
+                None,
+                chrono::Duration::days(1),
+            )
+            .unwrap();
+            let root_hash = target.get_addressing_hash_by_name("Backup Root").await.unwrap();
+            let root = Cert::from_der(&target.get_cert_by_addressing_hash(&root_hash).await.unwrap()).unwrap();
+            let chain = [intermediate, root];
+            leaf.verifier()
+                .chain(chain.iter())
+                .exact_date(&UTCDate::now())
+                .verify()
+                .expect("couldn't verify chain to the restored CAs");
+        });
+
+        let _ = std::fs::remove_dir_all(&config.file_backend_path);
+    }
+}
@@ -0,0 +1,443 @@
+use crate::{
+    addressing::encode_to_canonical_address,
+    db::{
+        AuditEntry, BoxedPickyStorage, CertificateEntry, CertificateExpiry, ConflictPolicy, IssuanceMetadata,
+        ListFilter, ListedCertificate, PickyStorage, RevocationEntry, StorageError, StoreOutcome,
+    },
+};
+use futures::{future::BoxFuture, FutureExt};
+use std::{
+    collections::HashMap,
+    sync::{Mutex, MutexGuard, PoisonError},
+    time::{Duration, Instant},
+};
+
+struct CacheEntry<V> {
+    value: V,
+    inserted_at: Instant,
+    last_used: u64,
+}
+
+/// Map evicting its least recently used entry once full, entries older than `ttl` being never served
+struct LruCache<V> {
+    capacity: usize,
+    ttl: Duration,
+    clock: u64,
+    entries: HashMap<String, CacheEntry<V>>,
+}
+
+impl<V: Clone + PartialEq> LruCache<V> {
+    fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            capacity,
+            ttl,
+            clock: 0,
+            entries: HashMap::new(),
+        }
+    }
+
+    fn get(&mut self, key: &str) -> Option<V> {
+        self.clock += 1;
+        let entry = self.entries.get_mut(key)?;
+        if entry.inserted_at.elapsed() >= self.ttl {
+            self.entries.remove(key);
+            return None;
+        }
+        entry.last_used = self.clock;
+        Some(entry.value.clone())
+    }
+
+    fn insert(&mut self, key: String, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if !self.entries.contains_key(&key) && self.entries.len() >= self.capacity {
+            let least_recently_used = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone());
+            if let Some(least_recently_used) = least_recently_used {
+                self.entries.remove(&least_recently_used);
+            }
+        }
+
+        self.clock += 1;
+        self.entries.insert(
+            key,
+            CacheEntry {
+                value,
+                inserted_at: Instant::now(),
+                last_used: self.clock,
+            },
+        );
+    }
+
+    fn remove(&mut self, key: &str) {
+        self.entries.remove(key);
+    }
+
+    fn remove_value(&mut self, value: &V) {
+        self.entries.retain(|_, entry| entry.value != *value);
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+struct Caches {
+    /// Bumped by every invalidation so that values fetched before it aren't cached afterwards
+    generation: u64,
+    certs: LruCache<Vec<u8>>,
+    keys: LruCache<Vec<u8>>,
+    names: LruCache<String>,
+    key_identifiers: LruCache<String>,
+}
+
+impl Caches {
+    /// Forgets a certificate along with every name and key identifier resolving to it
+    fn invalidate_hash(&mut self, hash: &str) {
+        self.generation += 1;
+        self.certs.remove(hash);
+        self.keys.remove(hash);
+        self.names.remove_value(&hash.to_owned());
+        self.key_identifiers.remove_value(&hash.to_owned());
+    }
+
+    fn clear(&mut self) {
+        self.generation += 1;
+        self.certs.clear();
+        self.keys.clear();
+        self.names.clear();
+        self.key_identifiers.clear();
+    }
+}
+
+/// Read-through cache in front of another storage.
+///
+/// Certificates, private keys and the hashes names and key identifiers resolve to are cached,
+/// and invalidated when written through this storage. Private keys are stored unencrypted by every backend,
+/// so they are cached as read.
+pub struct CachingStorage {
+    inner: BoxedPickyStorage,
+    caches: Mutex<Caches>,
+}
+
+impl CachingStorage {
+    /// Caches up to `capacity` values of each kind for at most `ttl`
+    pub fn new(inner: BoxedPickyStorage, capacity: usize, ttl: Duration) -> Self {
+        Self {
+            inner,
+            caches: Mutex::new(Caches {
+                generation: 0,
+                certs: LruCache::new(capacity, ttl),
+                keys: LruCache::new(capacity, ttl),
+                names: LruCache::new(capacity, ttl),
+                key_identifiers: LruCache::new(capacity, ttl),
+            }),
+        }
+    }
+
+    fn caches(&self) -> MutexGuard<'_, Caches> {
+        // cached values are only read or replaced as a whole, they stay consistent after a panic
+        self.caches.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    async fn read_through<'a, V: Clone + PartialEq>(
+        &'a self,
+        cache: fn(&mut Caches) -> &mut LruCache<V>,
+        key: &str,
+        fetch: impl FnOnce() -> BoxFuture<'a, Result<V, StorageError>>,
+    ) -> Result<V, StorageError> {
+        let generation = {
+            let mut caches = self.caches();
+            if let Some(value) = cache(&mut caches).get(key) {
+                return Ok(value);
+            }
+            caches.generation
+        };
+
+        let value = fetch().await?;
+
+        let mut caches = self.caches();
+        if caches.generation == generation {
+            cache(&mut caches).insert(key.to_owned(), value.clone());
+        }
+        Ok(value)
+    }
+}
+
+impl PickyStorage for CachingStorage {
+    fn ping(&self) -> BoxFuture<'_, Result<(), StorageError>> {
+        self.inner.ping()
+    }
+
+    fn close(&self) -> BoxFuture<'_, Result<(), StorageError>> {
+        self.inner.close()
+    }
+
+    fn clear_cache(&self) {
+        self.caches().clear();
+        self.inner.clear_cache();
+    }
+
+    fn store(
+        &self,
+        entry: CertificateEntry,
+        policy: ConflictPolicy,
+    ) -> BoxFuture<'_, Result<StoreOutcome, StorageError>> {
+        async move {
+            let addressing_hash = encode_to_canonical_address(&entry.cert);
+            let name = entry.name.clone();
+            let key_identifier = entry.key_identifier.clone();
+
+            let result = self.inner.store(entry, policy).await;
+
+            let mut caches = self.caches();
+            caches.invalidate_hash(&addressing_hash);
+            caches.names.remove(&name);
+            caches.key_identifiers.remove(&key_identifier);
+
+            result
+        }
+        .boxed()
+    }
+
+    fn get_cert_by_addressing_hash<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<Vec<u8>, StorageError>> {
+        self.read_through(
+            |caches| &mut caches.certs,
+            hash,
+            move || self.inner.get_cert_by_addressing_hash(hash),
+        )
+        .boxed()
+    }
+
+    fn get_key_by_addressing_hash<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<Vec<u8>, StorageError>> {
+        self.read_through(
+            |caches| &mut caches.keys,
+            hash,
+            move || self.inner.get_key_by_addressing_hash(hash),
+        )
+        .boxed()
+    }
+
+    fn get_addressing_hash_by_name<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<String, StorageError>> {
+        self.read_through(
+            |caches| &mut caches.names,
+            name,
+            move || self.inner.get_addressing_hash_by_name(name),
+        )
+        .boxed()
+    }
+
+    fn get_addressing_hashes_by_name<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<Vec<String>, StorageError>> {
+        self.inner.get_addressing_hashes_by_name(name)
+    }
+
+    fn get_addressing_hash_by_key_identifier<'a>(
+        &'a self,
+        key_identifier: &'a str,
+    ) -> BoxFuture<'a, Result<String, StorageError>> {
+        self.read_through(
+            |caches| &mut caches.key_identifiers,
+            key_identifier,
+            move || self.inner.get_addressing_hash_by_key_identifier(key_identifier),
+        )
+        .boxed()
+    }
+
+    fn lookup_addressing_hash<'a>(&'a self, lookup_key: &'a str) -> BoxFuture<'a, Result<String, StorageError>> {
+        self.inner.lookup_addressing_hash(lookup_key)
+    }
+
+    fn get_revocations_by_issuer<'a>(
+        &'a self,
+        issuer_name: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<RevocationEntry>, StorageError>> {
+        self.inner.get_revocations_by_issuer(issuer_name)
+    }
+
+    fn revoke(&self, entry: RevocationEntry) -> BoxFuture<'_, Result<(), StorageError>> {
+        async move {
+            let addressing_hash = entry.addressing_hash.clone();
+            let result = self.inner.revoke(entry).await;
+            self.caches().invalidate_hash(&addressing_hash);
+            result
+        }
+        .boxed()
+    }
+
+    fn list<'a>(
+        &'a self,
+        filter: &'a ListFilter,
+        offset: usize,
+        limit: usize,
+    ) -> BoxFuture<'a, Result<Vec<ListedCertificate>, StorageError>> {
+        self.inner.list(filter, offset, limit)
+    }
+
+    fn count(&self) -> BoxFuture<'_, Result<usize, StorageError>> {
+        self.inner.count()
+    }
+
+    fn get_revocation_by_addressing_hash<'a>(
+        &'a self,
+        hash: &'a str,
+    ) -> BoxFuture<'a, Result<Option<RevocationEntry>, StorageError>> {
+        self.inner.get_revocation_by_addressing_hash(hash)
+    }
+
+    fn get_expiry<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<Option<CertificateExpiry>, StorageError>> {
+        self.inner.get_expiry(hash)
+    }
+
+    fn set_expired<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<(), StorageError>> {
+        self.inner.set_expired(hash)
+    }
+
+    fn get_metadata<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<Option<IssuanceMetadata>, StorageError>> {
+        self.inner.get_metadata(hash)
+    }
+
+    fn set_metadata<'a>(
+        &'a self,
+        hash: &'a str,
+        metadata: IssuanceMetadata,
+    ) -> BoxFuture<'a, Result<(), StorageError>> {
+        self.inner.set_metadata(hash, metadata)
+    }
+
+    fn find_expiring_before(&self, not_after: i64) -> BoxFuture<'_, Result<Vec<String>, StorageError>> {
+        self.inner.find_expiring_before(not_after)
+    }
+
+    fn delete<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<(), StorageError>> {
+        async move {
+            let result = self.inner.delete(hash).await;
+            self.caches().invalidate_hash(hash);
+            result
+        }
+        .boxed()
+    }
+
+    fn append_audit(&self, entry: AuditEntry) -> BoxFuture<'_, Result<(), StorageError>> {
+        self.inner.append_audit(entry)
+    }
+
+    fn list_audit(&self, since: i64, limit: usize) -> BoxFuture<'_, Result<Vec<AuditEntry>, StorageError>> {
+        self.inner.list_audit(since, limit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::tests::SpyStorage;
+    use picky::pem::parse_pem;
+    use std::sync::atomic::Ordering;
+    use tokio_test::block_on;
+
+    const TTL: Duration = Duration::from_secs(60);
+
+    fn entry(name: &str, pem: &str, key_identifier: &str) -> CertificateEntry {
+        CertificateEntry {
+            name: name.to_owned(),
+            cert: parse_pem(pem).unwrap().data().to_vec(),
+            key_identifier: key_identifier.to_owned(),
+            key: Some(vec![1, 2, 3]),
+        }
+    }
+
+    #[test]
+    fn least_recently_used_entry_is_evicted() {
+        let mut cache = LruCache::new(2, TTL);
+        cache.insert("a".to_owned(), 1);
+        cache.insert("b".to_owned(), 2);
+        assert_eq!(cache.get("a"), Some(1));
+        cache.insert("c".to_owned(), 3);
+        assert_eq!(cache.get("b"), None);
+        assert_eq!(cache.get("a"), Some(1));
+        assert_eq!(cache.get("c"), Some(3));
+
+        let mut cache = LruCache::new(2, Duration::from_millis(0));
+        cache.insert("a".to_owned(), 1);
+        assert_eq!(cache.get("a"), None);
+        assert!(cache.entries.is_empty());
+
+        let mut disabled = LruCache::new(0, TTL);
+        disabled.insert("a".to_owned(), 1);
+        assert_eq!(disabled.get("a"), None);
+    }
+
+    #[test]
+    fn reads_are_served_from_cache_until_invalidated() {
+        let (spy, reads) = SpyStorage::new();
+        let storage = CachingStorage::new(Box::new(spy), 16, TTL);
+        let authority = entry(
+            "authority",
+            include_str!("../../../test_assets/intermediate_ca.crt"),
+            "authority_kid",
+        );
+        let authority_hash = encode_to_canonical_address(&authority.cert);
+
+        block_on(async {
+            storage
+                .store(authority.clone(), ConflictPolicy::ErrorIfExists)
+                .await
+                .unwrap();
+
+            let read_all = || async {
+                let hash = storage.get_addressing_hash_by_name("authority").await.unwrap();
+                assert_eq!(
+                    storage
+                        .get_addressing_hash_by_key_identifier("authority_kid")
+                        .await
+                        .unwrap(),
+                    hash
+                );
+                storage.get_cert_by_addressing_hash(&hash).await.unwrap();
+                storage.get_key_by_addressing_hash(&hash).await.unwrap()
+            };
+            read_all().await;
+            assert_eq!(reads.swap(0, Ordering::SeqCst), 4);
+            read_all().await;
+            assert_eq!(reads.load(Ordering::SeqCst), 0);
+
+            // a new certificate under the same name is seen right away
+            let rotated = entry(
+                "authority",
+                include_str!("../../../test_assets/root_ca.crt"),
+                "rotated_kid",
+            );
+            let rotated_hash = encode_to_canonical_address(&rotated.cert);
+            storage.store(rotated, ConflictPolicy::ErrorIfExists).await.unwrap();
+            assert_eq!(
+                storage.get_addressing_hash_by_name("authority").await.unwrap(),
+                rotated_hash
+            );
+
+            // and the previous one again once the new one is deleted
+            storage.delete(&rotated_hash).await.unwrap();
+            assert_eq!(
+                storage.get_addressing_hash_by_name("authority").await.unwrap(),
+                authority_hash
+            );
+            assert!(storage.get_cert_by_addressing_hash(&rotated_hash).await.is_err());
+
+            // failed reads aren't cached
+            reads.store(0, Ordering::SeqCst);
+            for _ in 0..2 {
+                assert!(storage.get_addressing_hash_by_name("unknown").await.is_err());
+            }
+            assert_eq!(reads.load(Ordering::SeqCst), 2);
+
+            storage.get_key_by_addressing_hash(&authority_hash).await.unwrap();
+            reads.store(0, Ordering::SeqCst);
+            storage.clear_cache();
+            storage.get_key_by_addressing_hash(&authority_hash).await.unwrap();
+            assert_eq!(reads.load(Ordering::SeqCst), 1);
+        });
+    }
+}
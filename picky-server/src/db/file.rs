@@ -1,13 +1,23 @@
 use crate::{
     addressing::{encode_to_alternative_addresses, encode_to_canonical_address},
     config::Config,
-    db::{config::DatabaseConfig, CertificateEntry, PickyStorage, StorageError, SCHEMA_LAST_VERSION},
+    db::{
+        config::DatabaseConfig, resolve_conflict, AuditEntry, CertificateEntry, CertificateExpiry, ConflictPolicy,
+        IssuanceMetadata, ListFilter, ListedCertificate, PickyStorage, RevocationEntry, StorageError, StoreOutcome,
+        SCHEMA_LAST_VERSION,
+    },
 };
+use chrono::Utc;
 use futures::{future::BoxFuture, FutureExt};
+use serde::{Deserialize, Serialize};
 use std::{
-    fs::File,
+    fs::{File, OpenOptions},
     io::{Read, Write},
     path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
 };
 use thiserror::Error;
 
@@ -23,9 +33,76 @@ impl From<String> for FileStorageError {
     }
 }
 
+/// Distinguishes temporary files written concurrently by this process
+static TMP_FILE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+#[cfg(test)]
+thread_local! {
+    /// Number of files written by the current thread before a write fails once, to inject faults in tests
+    static WRITES_BEFORE_FAILURE: std::cell::Cell<Option<usize>> = const { std::cell::Cell::new(None) };
+}
+
+/// Writes `data` to `path` through a temporary file which is synced to disk and then renamed over `path`,
+/// so that readers and crashes never see a partially written file.
+/// Private files are only readable by their owner on unix.
+pub(crate) fn write_atomically(tmp_folder_path: &Path, path: &Path, data: &[u8], private: bool) -> std::io::Result<()> {
+    #[cfg(test)]
+    {
+        let fail = WRITES_BEFORE_FAILURE.with(|countdown| match countdown.get() {
+            Some(0) => {
+                countdown.set(None);
+                true
+            }
+            Some(remaining) => {
+                countdown.set(Some(remaining - 1));
+                false
+            }
+            None => false,
+        });
+        if fail {
+            return Err(std::io::Error::other("injected failure"));
+        }
+    }
+
+    let file_name = path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+    let tmp_path = tmp_folder_path.join(format!(
+        "{}-{}-{}",
+        std::process::id(),
+        TMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed),
+        file_name
+    ));
+
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        if private {
+            options.mode(0o600);
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = private;
+
+    let result = options
+        .open(&tmp_path)
+        .and_then(|mut file| {
+            file.write_all(data)?;
+            file.sync_all()
+        })
+        .and_then(|_| std::fs::rename(&tmp_path, path));
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp_path);
+    }
+    result
+}
+
 #[derive(Clone)]
 struct FileRepo<T> {
     folder_path: PathBuf,
+    tmp_folder_path: PathBuf,
+    /// Files only readable by their owner, for private keys
+    private: bool,
     _pd: std::marker::PhantomData<T>,
 }
 
@@ -39,10 +116,17 @@ where
             .map_err(|e| format!("couldn't create folder '{}': {}", folder_path.to_string_lossy(), e))?;
         Ok(Self {
             folder_path,
+            tmp_folder_path: db_folder_path.join(REPO_TMP),
+            private: false,
             _pd: std::marker::PhantomData,
         })
     }
 
+    fn with_private_files(mut self) -> Self {
+        self.private = true;
+        self
+    }
+
     async fn get_collection(&self) -> Result<Vec<String>, FileStorageError> {
         // This isn't an efficient way to proceed.
         // Implementing a lazy wrapper would be a better approach should this be used in production.
@@ -66,16 +150,69 @@ where
     }
 
     async fn insert(&self, key: &str, value: &T) -> Result<(), FileStorageError> {
-        let mut file = File::create(self.folder_path.join(key)).map_err(|e| {
+        write_atomically(
+            &self.tmp_folder_path,
+            &self.folder_path.join(key),
+            value.as_ref(),
+            self.private,
+        )
+        .map_err(|e| {
             format!(
-                "couldn't open file ({}{}): {}",
+                "couldn't write file ({}{}): {}",
                 self.folder_path.to_string_lossy(),
                 key,
                 e
             )
         })?;
-        file.write_all(value.as_ref())
-            .map_err(|e| format!("Error writing data to {}: {}", key, e))?;
+        Ok(())
+    }
+
+    fn remove(&self, key: &str) -> Result<(), FileStorageError> {
+        match std::fs::remove_file(self.folder_path.join(key)) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(format!(
+                "couldn't remove file ({}{}): {}",
+                self.folder_path.to_string_lossy(),
+                key,
+                e
+            )
+            .into()),
+        }
+    }
+
+    /// Removes every entry whose content is `value`
+    async fn remove_values(&self, value: &str) -> Result<(), FileStorageError> {
+        for file in self.get_collection().await? {
+            let file_path = self.folder_path.join(&file);
+            let content = tokio::fs::read(&file_path)
+                .await
+                .map_err(|e| format!("error reading file '{}': {}", file_path.to_string_lossy(), e))?;
+            if content == value.as_bytes() {
+                self.remove(&file)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Flushes every file of the repository (and the folder entries on unix) to disk
+    fn sync(&self) -> Result<(), FileStorageError> {
+        let entries =
+            std::fs::read_dir(&self.folder_path).map_err(|e| format!("repository folder not found: {}", e))?;
+        for entry in entries {
+            let path = entry.map_err(|e| format!("couldn't read entry: {}", e))?.path();
+            File::open(&path)
+                .and_then(|file| file.sync_all())
+                .map_err(|e| format!("couldn't sync file '{}': {}", path.to_string_lossy(), e))?;
+        }
+
+        #[cfg(unix)]
+        {
+            File::open(&self.folder_path)
+                .and_then(|folder| folder.sync_all())
+                .map_err(|e| format!("couldn't sync folder '{}': {}", self.folder_path.to_string_lossy(), e))?;
+        }
+
         Ok(())
     }
 }
@@ -85,23 +222,80 @@ const REPO_CERTIFICATE_OLD: &str = "CertificateStore/";
 const REPO_CERTIFICATE: &str = "certificate_store/";
 const REPO_KEY: &str = "key_store/";
 const REPO_CERT_NAME: &str = "name_store/";
+const REPO_CERT_NAME_HISTORY: &str = "name_history_store/";
 const REPO_KEY_IDENTIFIER: &str = "key_identifier_store/";
 const REPO_HASH_LOOKUP_TABLE: &str = "hash_lookup_store/";
+const REPO_REVOCATION: &str = "revocation_store/";
+const REPO_EXPIRY: &str = "expiry_store/";
+const REPO_METADATA: &str = "metadata_store/";
+/// Temporary files, renamed into their repository once fully written
+const REPO_TMP: &str = "tmp/";
+/// Entries being stored, replayed on startup when a store was interrupted
+const REPO_PENDING: &str = "pending_store/";
+/// Files found corrupted on startup
+const REPO_CORRUPTED: &str = "corrupted/";
 const TXT_EXT: &str = ".txt";
 const DER_EXT: &str = ".der";
+const JSON_EXT: &str = ".json";
 
 const CONFIG_FILE_NAME: &str = "config.json";
+/// Audit log, one JSON entry per line
+const AUDIT_LOG_FILE_NAME: &str = "audit_log.jsonl";
+/// Written then removed in the temporary folder by health checks
+const HEALTH_PROBE_FILE_NAME: &str = "health_probe";
+
+/// Entry written to the pending repository before its files are stored
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PendingEntry {
+    name: String,
+    cert: Vec<u8>,
+    key_identifier: String,
+    key: Option<Vec<u8>>,
+}
+
+impl From<CertificateEntry> for PendingEntry {
+    fn from(entry: CertificateEntry) -> Self {
+        Self {
+            name: entry.name,
+            cert: entry.cert,
+            key_identifier: entry.key_identifier,
+            key: entry.key,
+        }
+    }
+}
+
+/// What was repaired while opening the file backend
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct RecoveryReport {
+    /// Temporary files left over by interrupted writes
+    pub removed_tmp_files: usize,
+    /// Addressing hashes of partially written entries which were stored again
+    pub replayed: Vec<String>,
+    /// Files which couldn't be recovered, moved to the corrupted folder
+    pub quarantined: Vec<String>,
+}
 
 pub struct FileStorage {
     name: FileRepo<String>,
+    name_history: FileRepo<String>,
     cert: FileRepo<Vec<u8>>,
     keys: FileRepo<Vec<u8>>,
+    expiry: FileRepo<String>,
+    metadata: FileRepo<String>,
     key_identifiers: FileRepo<String>,
     hash_lookup: FileRepo<String>,
+    revocations: FileRepo<String>,
+    pending: FileRepo<Vec<u8>>,
+    corrupted_folder_path: PathBuf,
+    audit_log_path: PathBuf,
+    audit_lock: Mutex<()>,
+    /// Held by operations writing several files so that repositories never go out of sync
+    write_lock: tokio::sync::Mutex<()>,
 }
 
 impl FileStorage {
-    pub fn new(config: &Config) -> Self {
+    /// Opens the file backend, repairing entries left partially written by a previous run
+    pub async fn new(config: &Config) -> Self {
         std::fs::create_dir_all(&config.file_backend_path).expect("create file backend directory");
 
         let config_path = config.file_backend_path.join(CONFIG_FILE_NAME);
@@ -126,15 +320,221 @@ impl FileStorage {
             std::fs::write(config_path, json).expect("write json config");
         }
 
-        FileStorage {
+        let storage = FileStorage {
             name: FileRepo::new(&config.file_backend_path, REPO_CERT_NAME).expect("couldn't initialize name repo"),
+            name_history: FileRepo::new(&config.file_backend_path, REPO_CERT_NAME_HISTORY)
+                .expect("couldn't initialize name history repo"),
             cert: FileRepo::new(&config.file_backend_path, REPO_CERTIFICATE).expect("couldn't initialize cert repo"),
-            keys: FileRepo::new(&config.file_backend_path, REPO_KEY).expect("couldn't initialize keys repo"),
+            keys: FileRepo::new(&config.file_backend_path, REPO_KEY)
+                .expect("couldn't initialize keys repo")
+                .with_private_files(),
+            expiry: FileRepo::new(&config.file_backend_path, REPO_EXPIRY).expect("couldn't initialize expiry repo"),
+            metadata: FileRepo::new(&config.file_backend_path, REPO_METADATA)
+                .expect("couldn't initialize metadata repo"),
             key_identifiers: FileRepo::new(&config.file_backend_path, REPO_KEY_IDENTIFIER)
                 .expect("couldn't initialize key identifiers repo"),
             hash_lookup: FileRepo::new(&config.file_backend_path, REPO_HASH_LOOKUP_TABLE)
                 .expect("couldn't initialize hash lookup table repo"),
+            revocations: FileRepo::new(&config.file_backend_path, REPO_REVOCATION)
+                .expect("couldn't initialize revocations repo"),
+            pending: FileRepo::new(&config.file_backend_path, REPO_PENDING)
+                .expect("couldn't initialize pending repo")
+                .with_private_files(),
+            corrupted_folder_path: config.file_backend_path.join(REPO_CORRUPTED),
+            audit_log_path: config.file_backend_path.join(AUDIT_LOG_FILE_NAME),
+            audit_lock: Mutex::new(()),
+            write_lock: tokio::sync::Mutex::new(()),
+        };
+
+        let report = storage.recover().await.expect("couldn't recover file backend");
+        if report.removed_tmp_files > 0 {
+            log::warn!(
+                "removed {} temporary files left by interrupted writes",
+                report.removed_tmp_files
+            );
+        }
+        for hash in &report.replayed {
+            log::warn!("stored partially written entry {} again", hash);
+        }
+        for file in &report.quarantined {
+            log::error!("moved corrupted file {} to the {} folder", file, REPO_CORRUPTED);
+        }
+
+        storage
+    }
+
+    /// Removes leftover temporary files, replays interrupted stores, checks certificates match their
+    /// addressing hash and restricts private key permissions
+    async fn recover(&self) -> Result<RecoveryReport, FileStorageError> {
+        let mut report = RecoveryReport::default();
+
+        let tmp_folder_path = &self.cert.tmp_folder_path;
+        std::fs::create_dir_all(tmp_folder_path)
+            .map_err(|e| format!("couldn't create folder '{}': {}", tmp_folder_path.to_string_lossy(), e))?;
+        let tmp_files = std::fs::read_dir(tmp_folder_path).map_err(|e| format!("temporary folder not found: {}", e))?;
+        for tmp_file in tmp_files {
+            let path = tmp_file.map_err(|e| format!("couldn't read entry: {}", e))?.path();
+            std::fs::remove_file(&path)
+                .map_err(|e| format!("couldn't remove file '{}': {}", path.to_string_lossy(), e))?;
+            report.removed_tmp_files += 1;
+        }
+
+        for file in self.pending.get_collection().await? {
+            let file_path = self.pending.folder_path.join(&file);
+            let json = tokio::fs::read(&file_path)
+                .await
+                .map_err(|e| format!("error reading file '{}': {}", file_path.to_string_lossy(), e))?;
+            match serde_json::from_slice::<PendingEntry>(&json) {
+                Ok(entry) => {
+                    self.h_store(&entry).await?;
+                    self.pending.remove(&file)?;
+                    report.replayed.push(encode_to_canonical_address(&entry.cert));
+                }
+                Err(e) => {
+                    log::error!("couldn't decode pending entry '{}': {}", file, e);
+                    report.quarantined.push(self.h_quarantine(&self.pending, &file)?);
+                }
+            }
+        }
+
+        for file in self.cert.get_collection().await? {
+            let file_path = self.cert.folder_path.join(&file);
+            let cert = tokio::fs::read(&file_path)
+                .await
+                .map_err(|e| format!("error reading file '{}': {}", file_path.to_string_lossy(), e))?;
+            if encode_to_canonical_address(&cert) != file.trim_end_matches(DER_EXT) {
+                report.quarantined.push(self.h_quarantine(&self.cert, &file)?);
+            }
+        }
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            for file in self.keys.get_collection().await? {
+                let file_path = self.keys.folder_path.join(&file);
+                std::fs::set_permissions(&file_path, std::fs::Permissions::from_mode(0o600)).map_err(|e| {
+                    format!(
+                        "couldn't restrict permissions of '{}': {}",
+                        file_path.to_string_lossy(),
+                        e
+                    )
+                })?;
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Moves a file out of its repository, returning its new path
+    fn h_quarantine<T>(&self, repo: &FileRepo<T>, file: &str) -> Result<String, FileStorageError> {
+        std::fs::create_dir_all(&self.corrupted_folder_path).map_err(|e| {
+            format!(
+                "couldn't create folder '{}': {}",
+                self.corrupted_folder_path.to_string_lossy(),
+                e
+            )
+        })?;
+        let quarantined_path = self.corrupted_folder_path.join(file);
+        std::fs::rename(repo.folder_path.join(file), &quarantined_path)
+            .map_err(|e| format!("couldn't move corrupted file '{}': {}", file, e))?;
+        Ok(quarantined_path.to_string_lossy().into_owned())
+    }
+
+    /// Removes every file of an entry, the caller holding the write lock
+    async fn h_delete(&self, hash: &str) -> Result<(), FileStorageError> {
+        let cert_file = format!("{}{}", hash, DER_EXT);
+        if !self.cert.folder_path.join(&cert_file).exists() {
+            return Err(FileStorageError::Other {
+                description: "Cert file not found".to_owned(),
+            });
+        }
+
+        self.key_identifiers.remove_values(hash).await?;
+        self.hash_lookup.remove_values(hash).await?;
+
+        for name_file in self.name_history.get_collection().await? {
+            let mut history = self.h_get_name_history(&name_file).await?.unwrap_or_default();
+            if !history.iter().any(|value| value == hash) {
+                continue;
+            }
+
+            history.retain(|value| value != hash);
+            if history.is_empty() {
+                self.name_history.remove(&name_file)?;
+            } else {
+                self.name_history.insert(&name_file, &history.join("\n")).await?;
+            }
+        }
+
+        for name_file in self.name.get_collection().await? {
+            let file_path = self.name.folder_path.join(&name_file);
+            let value = tokio::fs::read_to_string(&file_path)
+                .await
+                .map_err(|e| format!("error reading file '{}': {}", file_path.to_string_lossy(), e))?;
+            if value != hash {
+                continue;
+            }
+
+            match self
+                .h_get_name_history(&name_file)
+                .await?
+                .and_then(|history| history.last().cloned())
+            {
+                Some(previous) => self.name.insert(&name_file, &previous).await?,
+                None => self.name.remove(&name_file)?,
+            }
+        }
+
+        self.keys.remove(&cert_file)?;
+        self.expiry.remove(&format!("{}{}", hash, JSON_EXT))?;
+        self.metadata.remove(&format!("{}{}", hash, JSON_EXT))?;
+        // removed last so that an interrupted deletion can be retried
+        self.cert.remove(&cert_file)?;
+
+        Ok(())
+    }
+
+    /// Writes the files of an entry, the caller holding the write lock.
+    /// The certificate and its key are written before the mappings referring to them, the name last.
+    async fn h_store(&self, entry: &PendingEntry) -> Result<(), FileStorageError> {
+        let addressing_hash = encode_to_canonical_address(&entry.cert);
+
+        let alternative_addresses =
+            encode_to_alternative_addresses(&entry.cert).map_err(|e| FileStorageError::Other {
+                description: format!("couldn't encode alternative addresses: {}", e),
+            })?;
+        let expiry = CertificateExpiry::from_der(&entry.cert)?;
+        let metadata = IssuanceMetadata::from_der(&entry.cert, Some(Utc::now().timestamp()))?;
+
+        self.cert
+            .insert(&format!("{}{}", addressing_hash, DER_EXT), &entry.cert)
+            .await?;
+        if let Some(key) = &entry.key {
+            self.keys
+                .insert(&format!("{}{}", addressing_hash, DER_EXT), key)
+                .await?;
+        }
+        self.h_set_expiry(&addressing_hash, &expiry).await?;
+        self.h_set_metadata(&addressing_hash, &metadata).await?;
+        self.key_identifiers
+            .insert(&format!("{}{}", entry.key_identifier, TXT_EXT), &addressing_hash)
+            .await?;
+
+        for alternative_address in alternative_addresses.into_iter() {
+            self.hash_lookup
+                .insert(&format!("{}{}", alternative_address, TXT_EXT), &addressing_hash)
+                .await?;
         }
+
+        let name_file = format!("{}{}", entry.name.replace(" ", "_"), TXT_EXT);
+        let mut name_history = self.h_get_name_history(&name_file).await?.unwrap_or_default();
+        if !name_history.contains(&addressing_hash) {
+            name_history.push(addressing_hash.clone());
+            self.name_history.insert(&name_file, &name_history.join("\n")).await?;
+        }
+        self.name.insert(&name_file, &addressing_hash).await?;
+
+        Ok(())
     }
 
     async fn h_get<'a>(
@@ -171,50 +571,189 @@ impl FileStorage {
             Ok(found_item)
         }
     }
+
+    async fn h_get_name_history(&self, file_name: &str) -> Result<Option<Vec<String>>, FileStorageError> {
+        let file_path = self.name_history.folder_path.join(file_name);
+        if !file_path.exists() {
+            return Ok(None);
+        }
+
+        let history = tokio::fs::read_to_string(&file_path)
+            .await
+            .map_err(|e| FileStorageError::Other {
+                description: format!("error reading file '{}': {}", file_path.to_string_lossy(), e),
+            })?;
+        Ok(Some(history.lines().map(str::to_owned).collect()))
+    }
+
+    async fn h_get_expiry(&self, hash: &str) -> Result<Option<CertificateExpiry>, FileStorageError> {
+        let file_path = self.expiry.folder_path.join(format!("{}{}", hash, JSON_EXT));
+        if !file_path.exists() {
+            return Ok(None);
+        }
+
+        let json = tokio::fs::read_to_string(&file_path)
+            .await
+            .map_err(|e| FileStorageError::Other {
+                description: format!("error reading file '{}': {}", file_path.to_string_lossy(), e),
+            })?;
+        let expiry = serde_json::from_str(&json).map_err(|e| FileStorageError::Other {
+            description: format!("couldn't decode expiry '{}': {}", file_path.to_string_lossy(), e),
+        })?;
+        Ok(Some(expiry))
+    }
+
+    async fn h_set_expiry(&self, hash: &str, expiry: &CertificateExpiry) -> Result<(), FileStorageError> {
+        let json = serde_json::to_string_pretty(expiry).map_err(|e| FileStorageError::Other {
+            description: format!("couldn't encode expiry: {}", e),
+        })?;
+        self.expiry.insert(&format!("{}{}", hash, JSON_EXT), &json).await
+    }
+
+    async fn h_get_metadata(&self, file_name: &str) -> Result<Option<IssuanceMetadata>, FileStorageError> {
+        let file_path = self.metadata.folder_path.join(file_name);
+        if !file_path.exists() {
+            return Ok(None);
+        }
+
+        let json = tokio::fs::read_to_string(&file_path)
+            .await
+            .map_err(|e| FileStorageError::Other {
+                description: format!("error reading file '{}': {}", file_path.to_string_lossy(), e),
+            })?;
+        let metadata = serde_json::from_str(&json).map_err(|e| FileStorageError::Other {
+            description: format!("couldn't decode metadata '{}': {}", file_path.to_string_lossy(), e),
+        })?;
+        Ok(Some(metadata))
+    }
+
+    async fn h_set_metadata(&self, hash: &str, metadata: &IssuanceMetadata) -> Result<(), FileStorageError> {
+        let json = serde_json::to_string_pretty(metadata).map_err(|e| FileStorageError::Other {
+            description: format!("couldn't encode metadata: {}", e),
+        })?;
+        self.metadata.insert(&format!("{}{}", hash, JSON_EXT), &json).await
+    }
+
+    /// Addressing hashes of the certificates whose metadata matches, ordered by the given key.
+    /// Every metadata file is read, like listing.
+    async fn h_find_by_metadata(
+        &self,
+        filter: impl Fn(&IssuanceMetadata) -> bool,
+        order_key: impl Fn(&IssuanceMetadata) -> i64,
+    ) -> Result<Vec<String>, FileStorageError> {
+        let mut found = Vec::new();
+        for file in self.metadata.get_collection().await? {
+            if let Some(metadata) = self.h_get_metadata(&file).await? {
+                if filter(&metadata) {
+                    found.push((order_key(&metadata), file.trim_end_matches(JSON_EXT).to_owned()));
+                }
+            }
+        }
+        found.sort();
+        Ok(found.into_iter().map(|(_, hash)| hash).collect())
+    }
 }
 
 impl PickyStorage for FileStorage {
-    fn health(&self) -> BoxFuture<'_, Result<(), StorageError>> {
-        async { Ok(()) }.boxed()
-    }
+    fn ping(&self) -> BoxFuture<'_, Result<(), StorageError>> {
+        async move {
+            for folder_path in &[
+                &self.name.folder_path,
+                &self.cert.folder_path,
+                &self.revocations.folder_path,
+            ] {
+                let _ = tokio::fs::read_dir(folder_path)
+                    .await
+                    .map_err(|e| FileStorageError::Other {
+                        description: format!("couldn't open folder '{}': {}", folder_path.to_string_lossy(), e),
+                    })?;
+            }
 
-    fn store(&self, entry: CertificateEntry) -> BoxFuture<'_, Result<(), StorageError>> {
-        let name = entry.name;
-        let cert = entry.cert;
-        let key_identifier = entry.key_identifier;
-        let key = entry.key;
+            // a full or read-only disk only shows up when writing
+            let tmp_folder_path = &self.cert.tmp_folder_path;
+            let probe_path = tmp_folder_path.join(HEALTH_PROBE_FILE_NAME);
+            write_atomically(tmp_folder_path, &probe_path, b"ok", false).map_err(|e| FileStorageError::Other {
+                description: format!(
+                    "couldn't write to folder '{}': {}",
+                    tmp_folder_path.to_string_lossy(),
+                    e
+                ),
+            })?;
+            let _ = std::fs::remove_file(&probe_path);
 
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn close(&self) -> BoxFuture<'_, Result<(), StorageError>> {
         async move {
-            let addressing_hash = encode_to_canonical_address(&cert);
+            self.name.sync()?;
+            self.name_history.sync()?;
+            self.cert.sync()?;
+            self.keys.sync()?;
+            self.expiry.sync()?;
+            self.metadata.sync()?;
+            self.key_identifiers.sync()?;
+            self.hash_lookup.sync()?;
+            self.revocations.sync()?;
+            if self.audit_log_path.exists() {
+                File::open(&self.audit_log_path)
+                    .and_then(|file| file.sync_all())
+                    .map_err(|e| FileStorageError::Other {
+                        description: format!("couldn't sync audit log: {}", e),
+                    })?;
+            }
+            Ok(())
+        }
+        .boxed()
+    }
 
-            let alternative_addresses =
-                encode_to_alternative_addresses(&cert).map_err(|e| FileStorageError::Other {
-                    description: format!("couldn't encode alternative addresses: {}", e),
-                })?;
+    fn store(
+        &self,
+        entry: CertificateEntry,
+        policy: ConflictPolicy,
+    ) -> BoxFuture<'_, Result<StoreOutcome, StorageError>> {
+        let entry = PendingEntry::from(entry);
 
-            self.name
-                .insert(&format!("{}{}", name.replace(" ", "_"), TXT_EXT), &addressing_hash)
-                .await?;
-            self.cert
-                .insert(&format!("{}{}", addressing_hash, DER_EXT), &cert.to_vec())
-                .await?;
-            self.key_identifiers
-                .insert(&format!("{}{}", key_identifier, TXT_EXT), &addressing_hash)
-                .await?;
+        async move {
+            let _guard = self.write_lock.lock().await;
 
-            for alternative_address in alternative_addresses.into_iter() {
-                self.hash_lookup
-                    .insert(&format!("{}{}", alternative_address, TXT_EXT), &addressing_hash)
-                    .await?;
+            let addressing_hash = encode_to_canonical_address(&entry.cert);
+            let cert_path = self.cert.folder_path.join(format!("{}{}", addressing_hash, DER_EXT));
+            let already_stored = cert_path.exists();
+            let (outcome, write) = resolve_conflict(policy, &addressing_hash, already_stored)?;
+            if !write {
+                return Ok(outcome);
             }
 
-            if let Some(key) = key {
-                self.keys
-                    .insert(&format!("{}{}", addressing_hash, DER_EXT), &key.to_vec())
-                    .await?;
+            // recorded first so that an interrupted store is replayed on next startup
+            let pending_file = format!("{}{}", addressing_hash, JSON_EXT);
+            let json = serde_json::to_vec(&entry).map_err(|e| FileStorageError::Other {
+                description: format!("couldn't encode pending entry: {}", e),
+            })?;
+            self.pending.insert(&pending_file, &json).await?;
+
+            if let Err(e) = self.h_store(&entry).await {
+                let rollback = if already_stored || !cert_path.exists() {
+                    Ok(())
+                } else {
+                    self.h_delete(&addressing_hash).await
+                };
+                match rollback {
+                    Ok(()) => self.pending.remove(&pending_file)?,
+                    // left pending so that the entry is completed on next startup
+                    Err(rollback_error) => log::error!(
+                        "couldn't roll back partially stored certificate {}: {}",
+                        addressing_hash,
+                        rollback_error
+                    ),
+                }
+                return Err(e.into());
             }
+            self.pending.remove(&pending_file)?;
 
-            Ok(())
+            Ok(outcome)
         }
         .boxed()
     }
@@ -257,6 +796,22 @@ impl PickyStorage for FileStorage {
         .boxed()
     }
 
+    fn get_addressing_hashes_by_name<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<Vec<String>, StorageError>> {
+        let name_file = format!("{}{}", name, TXT_EXT).replace(" ", "_");
+        async move {
+            if let Some(history) = self.h_get_name_history(&name_file).await? {
+                return Ok(history);
+            }
+
+            // certificates stored before name history was tracked
+            match self.get_addressing_hash_by_name(name).await {
+                Ok(hash) => Ok(vec![hash]),
+                Err(_) => Ok(Vec::new()),
+            }
+        }
+        .boxed()
+    }
+
     fn get_addressing_hash_by_key_identifier(
         &self,
         key_identifier: &str,
@@ -303,4 +858,548 @@ impl PickyStorage for FileStorage {
         }
         .boxed()
     }
+
+    fn get_revocations_by_issuer<'a>(
+        &'a self,
+        issuer_name: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<RevocationEntry>, StorageError>> {
+        async move {
+            let mut revocations = Vec::new();
+            for file in self.revocations.get_collection().await? {
+                if !file.ends_with(JSON_EXT) {
+                    continue;
+                }
+
+                let file_path = self.revocations.folder_path.join(file);
+                let json = tokio::fs::read_to_string(&file_path)
+                    .await
+                    .map_err(|e| FileStorageError::Other {
+                        description: format!("error reading file '{}': {}", file_path.to_string_lossy(), e),
+                    })?;
+                let entry: RevocationEntry = serde_json::from_str(&json).map_err(|e| FileStorageError::Other {
+                    description: format!("couldn't decode revocation '{}': {}", file_path.to_string_lossy(), e),
+                })?;
+
+                if entry.issuer_name == issuer_name {
+                    revocations.push(entry);
+                }
+            }
+            Ok(revocations)
+        }
+        .boxed()
+    }
+
+    fn revoke(&self, entry: RevocationEntry) -> BoxFuture<'_, Result<(), StorageError>> {
+        async move {
+            let json = serde_json::to_string_pretty(&entry).map_err(|e| FileStorageError::Other {
+                description: format!("couldn't encode revocation: {}", e),
+            })?;
+            self.revocations
+                .insert(&format!("{}{}", entry.addressing_hash, JSON_EXT), &json)
+                .await?;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn list<'a>(
+        &'a self,
+        filter: &'a ListFilter,
+        offset: usize,
+        limit: usize,
+    ) -> BoxFuture<'a, Result<Vec<ListedCertificate>, StorageError>> {
+        async move {
+            let mut files = self
+                .cert
+                .get_collection()
+                .await?
+                .into_iter()
+                .filter(|file| file.ends_with(DER_EXT))
+                .collect::<Vec<_>>();
+            files.sort();
+
+            let mut skipped = 0;
+            if *filter == ListFilter::default() {
+                // without filter, skipped certificates don't need to be read
+                files = files.split_off(offset.min(files.len()));
+                skipped = offset;
+            }
+
+            let mut certs = Vec::new();
+            for file in files {
+                if certs.len() >= limit {
+                    break;
+                }
+
+                let file_path = self.cert.folder_path.join(&file);
+                let cert = tokio::fs::read(&file_path).await.map_err(|e| FileStorageError::Other {
+                    description: format!("error reading file '{}': {}", file_path.to_string_lossy(), e),
+                })?;
+                let addressing_hash = file.trim_end_matches(DER_EXT);
+                let expiry = self.h_get_expiry(addressing_hash).await?;
+                if !filter.matches(&cert, expiry.as_ref()) {
+                    continue;
+                }
+
+                if skipped < offset {
+                    skipped += 1;
+                    continue;
+                }
+
+                certs.push(ListedCertificate {
+                    addressing_hash: addressing_hash.to_owned(),
+                    cert,
+                    expiry,
+                });
+            }
+            Ok(certs)
+        }
+        .boxed()
+    }
+
+    fn count(&self) -> BoxFuture<'_, Result<usize, StorageError>> {
+        async move {
+            Ok(self
+                .cert
+                .get_collection()
+                .await?
+                .iter()
+                .filter(|file| file.ends_with(DER_EXT))
+                .count())
+        }
+        .boxed()
+    }
+
+    fn get_revocation_by_addressing_hash<'a>(
+        &'a self,
+        hash: &'a str,
+    ) -> BoxFuture<'a, Result<Option<RevocationEntry>, StorageError>> {
+        let file_name = format!("{}{}", hash, JSON_EXT);
+        async move {
+            let file = match self
+                .revocations
+                .get_collection()
+                .await?
+                .into_iter()
+                .find(|filename| filename.eq(&file_name))
+            {
+                Some(file) => file,
+                None => return Ok(None),
+            };
+
+            let file_path = self.revocations.folder_path.join(file);
+            let json = tokio::fs::read_to_string(&file_path)
+                .await
+                .map_err(|e| FileStorageError::Other {
+                    description: format!("error reading file '{}': {}", file_path.to_string_lossy(), e),
+                })?;
+            let entry = serde_json::from_str(&json).map_err(|e| FileStorageError::Other {
+                description: format!("couldn't decode revocation '{}': {}", file_path.to_string_lossy(), e),
+            })?;
+            Ok(Some(entry))
+        }
+        .boxed()
+    }
+
+    fn get_expiry<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<Option<CertificateExpiry>, StorageError>> {
+        async move { Ok(self.h_get_expiry(hash).await?) }.boxed()
+    }
+
+    fn set_expired<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<(), StorageError>> {
+        async move {
+            let mut expiry = match self.h_get_expiry(hash).await? {
+                Some(expiry) => expiry,
+                None => CertificateExpiry::from_der(&self.h_get(hash, &self.cert, "Cert").await?)
+                    .map_err(FileStorageError::from)?,
+            };
+            expiry.expired = true;
+            self.h_set_expiry(hash, &expiry).await?;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn get_metadata<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<Option<IssuanceMetadata>, StorageError>> {
+        async move { Ok(self.h_get_metadata(&format!("{}{}", hash, JSON_EXT)).await?) }.boxed()
+    }
+
+    fn set_metadata<'a>(
+        &'a self,
+        hash: &'a str,
+        metadata: IssuanceMetadata,
+    ) -> BoxFuture<'a, Result<(), StorageError>> {
+        async move { Ok(self.h_set_metadata(hash, &metadata).await?) }.boxed()
+    }
+
+    fn find_expiring_before(&self, not_after: i64) -> BoxFuture<'_, Result<Vec<String>, StorageError>> {
+        async move {
+            Ok(self
+                .h_find_by_metadata(|metadata| metadata.not_after < not_after, |metadata| metadata.not_after)
+                .await?)
+        }
+        .boxed()
+    }
+
+    fn delete<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<(), StorageError>> {
+        async move {
+            let _guard = self.write_lock.lock().await;
+            Ok(self.h_delete(hash).await?)
+        }
+        .boxed()
+    }
+
+    fn append_audit(&self, entry: AuditEntry) -> BoxFuture<'_, Result<(), StorageError>> {
+        async move {
+            let mut line = serde_json::to_string(&entry).map_err(|e| FileStorageError::Other {
+                description: format!("couldn't encode audit entry: {}", e),
+            })?;
+            line.push('\n');
+
+            let _guard = self.audit_lock.lock().expect("couldn't lock audit log (poisoned)");
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.audit_log_path)
+                .and_then(|mut file| file.write_all(line.as_bytes()))
+                .map_err(|e| FileStorageError::Other {
+                    description: format!(
+                        "couldn't append to audit log '{}': {}",
+                        self.audit_log_path.to_string_lossy(),
+                        e
+                    ),
+                })?;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn list_audit(&self, since: i64, limit: usize) -> BoxFuture<'_, Result<Vec<AuditEntry>, StorageError>> {
+        async move {
+            if !self.audit_log_path.exists() {
+                return Ok(Vec::new());
+            }
+
+            let log = tokio::fs::read_to_string(&self.audit_log_path)
+                .await
+                .map_err(|e| FileStorageError::Other {
+                    description: format!("error reading file '{}': {}", self.audit_log_path.to_string_lossy(), e),
+                })?;
+
+            let mut entries = Vec::new();
+            for line in log.lines().filter(|line| !line.is_empty()) {
+                if entries.len() >= limit {
+                    break;
+                }
+
+                let entry: AuditEntry = serde_json::from_str(line).map_err(|e| FileStorageError::Other {
+                    description: format!("couldn't decode audit entry: {}", e),
+                })?;
+                if entry.timestamp >= since {
+                    entries.push(entry);
+                }
+            }
+            Ok(entries)
+        }
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::HealthStatus;
+    use picky::pem::parse_pem;
+    use std::sync::Arc;
+    use tokio_test::block_on;
+
+    fn scratch_config(name: &str) -> Config {
+        let path = std::env::temp_dir().join(format!("picky_file_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        Config {
+            file_backend_path: path,
+            ..Default::default()
+        }
+    }
+
+    fn der(pem: &str) -> Vec<u8> {
+        parse_pem(pem).expect("couldn't parse pem").data().to_vec()
+    }
+
+    fn entry(name: &str, cert: Vec<u8>, key_identifier: &str) -> CertificateEntry {
+        CertificateEntry {
+            name: name.to_owned(),
+            cert,
+            key_identifier: key_identifier.to_owned(),
+            key: None,
+        }
+    }
+
+    #[test]
+    fn file_storage() {
+        let config = scratch_config("storage");
+        block_on(async {
+            let storage = FileStorage::new(&config).await;
+            crate::db::tests::check_storage(&storage).await;
+            crate::db::tests::check_pagination(&storage).await;
+            crate::db::tests::check_conflicts(&storage).await;
+            crate::db::tests::check_metadata(&storage).await;
+        });
+        std::fs::remove_dir_all(&config.file_backend_path).unwrap();
+    }
+
+    #[test]
+    fn partial_entries_are_replayed_or_quarantined() {
+        let config = scratch_config("partial");
+        let authority = der(include_str!("../../../test_assets/test_authority.crt"));
+        let root = der(include_str!("../../../test_assets/root_ca.crt"));
+        let authority_hash = encode_to_canonical_address(&authority);
+        let root_hash = encode_to_canonical_address(&root);
+
+        block_on(async {
+            drop(FileStorage::new(&config).await);
+
+            // store interrupted after its intent was recorded and while writing the certificate
+            let pending = PendingEntry::from(entry("Test Authority", authority.clone(), "authority_kid"));
+            std::fs::write(
+                config
+                    .file_backend_path
+                    .join(REPO_PENDING)
+                    .join(format!("{}{}", authority_hash, JSON_EXT)),
+                serde_json::to_vec(&pending).unwrap(),
+            )
+            .unwrap();
+            let cert_folder_path = config.file_backend_path.join(REPO_CERTIFICATE);
+            std::fs::write(
+                cert_folder_path.join(format!("{}{}", authority_hash, DER_EXT)),
+                &authority[..authority.len() / 2],
+            )
+            .unwrap();
+
+            // truncated certificate without any intent to replay
+            std::fs::write(
+                cert_folder_path.join(format!("{}{}", root_hash, DER_EXT)),
+                &root[..root.len() / 2],
+            )
+            .unwrap();
+            std::fs::write(config.file_backend_path.join(REPO_TMP).join("leftover"), b"junk").unwrap();
+
+            let storage = FileStorage::new(&config).await;
+            let report = storage.recover().await.unwrap();
+            assert_eq!(report, RecoveryReport::default());
+
+            assert_eq!(
+                storage.get_cert_by_addressing_hash(&authority_hash).await.unwrap(),
+                authority
+            );
+            assert_eq!(
+                storage.get_addressing_hash_by_name("Test Authority").await.unwrap(),
+                authority_hash
+            );
+            assert_eq!(
+                storage
+                    .get_addressing_hash_by_key_identifier("authority_kid")
+                    .await
+                    .unwrap(),
+                authority_hash
+            );
+            assert!(storage.pending.get_collection().await.unwrap().is_empty());
+
+            assert!(storage.get_cert_by_addressing_hash(&root_hash).await.is_err());
+            assert!(config
+                .file_backend_path
+                .join(REPO_CORRUPTED)
+                .join(format!("{}{}", root_hash, DER_EXT))
+                .exists());
+            assert_eq!(
+                std::fs::read_dir(config.file_backend_path.join(REPO_TMP))
+                    .unwrap()
+                    .count(),
+                0
+            );
+        });
+        std::fs::remove_dir_all(&config.file_backend_path).unwrap();
+    }
+
+    #[test]
+    fn recovery_report() {
+        let config = scratch_config("report");
+        let authority = der(include_str!("../../../test_assets/test_authority.crt"));
+        let authority_hash = encode_to_canonical_address(&authority);
+
+        block_on(async {
+            let storage = FileStorage::new(&config).await;
+            let pending = PendingEntry::from(entry("Test Authority", authority.clone(), "authority_kid"));
+            storage
+                .pending
+                .insert(
+                    &format!("{}{}", authority_hash, JSON_EXT),
+                    &serde_json::to_vec(&pending).unwrap(),
+                )
+                .await
+                .unwrap();
+            storage.pending.insert("garbage.json", &b"{".to_vec()).await.unwrap();
+            std::fs::write(config.file_backend_path.join(REPO_TMP).join("leftover"), b"junk").unwrap();
+
+            let report = storage.recover().await.unwrap();
+            assert_eq!(report.removed_tmp_files, 1);
+            assert_eq!(report.replayed, vec![authority_hash]);
+            assert_eq!(report.quarantined.len(), 1);
+            assert!(report.quarantined[0].ends_with("garbage.json"));
+        });
+        std::fs::remove_dir_all(&config.file_backend_path).unwrap();
+    }
+
+    #[test]
+    fn concurrent_stores_keep_name_history() {
+        let config = scratch_config("concurrent");
+        let intermediate = der(include_str!("../../../test_assets/intermediate_ca.crt"));
+        let root = der(include_str!("../../../test_assets/root_ca.crt"));
+        let storage = Arc::new(block_on(FileStorage::new(&config)));
+
+        let handles = vec![(intermediate.clone(), "intermediate_kid"), (root.clone(), "root_kid")]
+            .into_iter()
+            .map(|(cert, key_identifier)| {
+                let storage = Arc::clone(&storage);
+                std::thread::spawn(move || {
+                    for _ in 0..20 {
+                        block_on(storage.store(
+                            entry("Shared Name", cert.clone(), key_identifier),
+                            ConflictPolicy::Overwrite,
+                        ))
+                        .unwrap();
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let mut hashes = block_on(storage.get_addressing_hashes_by_name("Shared Name")).unwrap();
+        hashes.sort();
+        let mut expected = vec![
+            encode_to_canonical_address(&intermediate),
+            encode_to_canonical_address(&root),
+        ];
+        expected.sort();
+        assert_eq!(hashes, expected);
+        assert!(block_on(storage.pending.get_collection()).unwrap().is_empty());
+
+        std::fs::remove_dir_all(&config.file_backend_path).unwrap();
+    }
+
+    #[test]
+    fn failed_store_leaves_no_partial_entry() {
+        let root = der(include_str!("../../../test_assets/root_ca.crt"));
+        let intermediate = der(include_str!("../../../test_assets/intermediate_ca.crt"));
+        let root_hash = encode_to_canonical_address(&root);
+        let intermediate_hash = encode_to_canonical_address(&intermediate);
+
+        let mut failed_stores = 0;
+        for writes_before_failure in 0.. {
+            let config = scratch_config(&format!("fault_{}", writes_before_failure));
+            let stored = block_on(async {
+                let storage = FileStorage::new(&config).await;
+                storage
+                    .store(
+                        entry("Shared Name", root.clone(), "root_kid"),
+                        ConflictPolicy::ErrorIfExists,
+                    )
+                    .await
+                    .unwrap();
+
+                let mut intermediate_entry = entry("Shared Name", intermediate.clone(), "intermediate_kid");
+                intermediate_entry.key = Some(vec![1, 2, 3]);
+                WRITES_BEFORE_FAILURE.with(|countdown| countdown.set(Some(writes_before_failure)));
+                let result = storage.store(intermediate_entry, ConflictPolicy::ErrorIfExists).await;
+                WRITES_BEFORE_FAILURE.with(|countdown| countdown.set(None));
+                if result.is_ok() {
+                    return true;
+                }
+
+                assert!(storage.get_cert_by_addressing_hash(&intermediate_hash).await.is_err());
+                assert!(storage.get_key_by_addressing_hash(&intermediate_hash).await.is_err());
+                assert!(storage.get_expiry(&intermediate_hash).await.unwrap().is_none());
+                assert!(storage.get_metadata(&intermediate_hash).await.unwrap().is_none());
+                assert_eq!(
+                    storage.get_addressing_hash_by_name("Shared Name").await.unwrap(),
+                    root_hash
+                );
+                assert_eq!(
+                    storage.get_addressing_hashes_by_name("Shared Name").await.unwrap(),
+                    vec![root_hash.clone()]
+                );
+                assert!(storage
+                    .get_addressing_hash_by_key_identifier("intermediate_kid")
+                    .await
+                    .is_err());
+                for alternative_address in encode_to_alternative_addresses(&intermediate).unwrap() {
+                    assert!(storage.lookup_addressing_hash(&alternative_address).await.is_err());
+                }
+                assert!(storage.pending.get_collection().await.unwrap().is_empty());
+                false
+            });
+            std::fs::remove_dir_all(&config.file_backend_path).unwrap();
+
+            if stored {
+                break;
+            }
+            failed_stores += 1;
+        }
+
+        // pending entry, certificate, key, expiry, metadata, key identifier, alternative addresses, name history
+        // and name
+        assert!(failed_stores >= 9);
+    }
+
+    #[test]
+    fn unwritable_folder_is_degraded() {
+        let config = scratch_config("health");
+        block_on(async {
+            let storage = FileStorage::new(&config).await;
+            assert_eq!(storage.health().await, HealthStatus::Ok);
+            assert!(!storage.cert.tmp_folder_path.join(HEALTH_PROBE_FILE_NAME).exists());
+
+            std::fs::remove_dir_all(&storage.cert.tmp_folder_path).unwrap();
+            match storage.health().await {
+                HealthStatus::Degraded { message } => assert!(message.contains("couldn't write"), "{}", message),
+                HealthStatus::Ok => panic!("file storage can't be healthy without its temporary folder"),
+            }
+        });
+        std::fs::remove_dir_all(&config.file_backend_path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn private_keys_are_owner_only() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let config = scratch_config("permissions");
+        let authority = der(include_str!("../../../test_assets/test_authority.crt"));
+        let key_path = config.file_backend_path.join(REPO_KEY).join(format!(
+            "{}{}",
+            encode_to_canonical_address(&authority),
+            DER_EXT
+        ));
+
+        block_on(async {
+            let storage = FileStorage::new(&config).await;
+            let mut authority_entry = entry("Test Authority", authority, "authority_kid");
+            authority_entry.key = Some(vec![1, 2, 3]);
+            storage
+                .store(authority_entry, ConflictPolicy::ErrorIfExists)
+                .await
+                .unwrap();
+            let mode = std::fs::metadata(&key_path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+
+            // keys written by older versions are restricted on startup
+            std::fs::set_permissions(&key_path, std::fs::Permissions::from_mode(0o644)).unwrap();
+            drop(storage);
+            drop(FileStorage::new(&config).await);
+            let mode = std::fs::metadata(&key_path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        });
+        std::fs::remove_dir_all(&config.file_backend_path).unwrap();
+    }
 }
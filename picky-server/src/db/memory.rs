@@ -1,12 +1,18 @@
 use crate::{
     addressing::{encode_to_alternative_addresses, encode_to_canonical_address},
-    db::{CertificateEntry, PickyStorage, StorageError},
+    db::{
+        file::write_atomically, resolve_conflict, AuditEntry, CertificateEntry, CertificateExpiry, ConflictPolicy,
+        IssuanceMetadata, ListFilter, ListedCertificate, PickyStorage, RevocationEntry, StorageError, StoreOutcome,
+    },
 };
+use chrono::Utc;
 use futures::{future::BoxFuture, FutureExt};
+use serde::{Deserialize, Serialize};
 use std::{
     collections::HashMap,
     hash::Hash,
-    sync::{RwLock, RwLockReadGuard},
+    path::{Path, PathBuf},
+    sync::{Mutex, RwLock, RwLockReadGuard},
 };
 use thiserror::Error;
 
@@ -22,11 +28,28 @@ impl From<String> for MemoryStorageError {
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug)]
 struct MemoryRepository<T> {
     repo: RwLock<HashMap<String, T>>,
 }
 
+// derived Default would require T: Default
+impl<T> Default for MemoryRepository<T> {
+    fn default() -> Self {
+        Self {
+            repo: RwLock::new(HashMap::new()),
+        }
+    }
+}
+
+impl<T> From<HashMap<String, T>> for MemoryRepository<T> {
+    fn from(repo: HashMap<String, T>) -> Self {
+        Self {
+            repo: RwLock::new(repo),
+        }
+    }
+}
+
 impl<'a, T> MemoryRepository<T>
 where
     T: Eq + Clone + Hash,
@@ -46,29 +69,187 @@ where
             log::info!("Key was updated because it was already stored");
         }
     }
+
+    fn remove(&self, key: &str) -> Option<T> {
+        self.repo
+            .write()
+            .expect("couldn't get write lock on repo (poisoned)")
+            .remove(key)
+    }
+
+    fn retain(&self, f: impl FnMut(&String, &mut T) -> bool) {
+        self.repo
+            .write()
+            .expect("couldn't get write lock on repo (poisoned)")
+            .retain(f);
+    }
+}
+
+impl<T> MemoryRepository<Vec<T>>
+where
+    T: Eq + Clone + Hash,
+{
+    fn push(&self, key: String, value: T) {
+        let mut repo = self.repo.write().expect("couldn't get write lock on repo (poisoned)");
+        let values = repo.entry(key).or_default();
+        if !values.contains(&value) {
+            values.push(value);
+        }
+    }
+}
+
+/// Content of every repository, saved so that the memory backend survives restarts
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Snapshot {
+    name: HashMap<String, String>,
+    name_history: HashMap<String, Vec<String>>,
+    cert: HashMap<String, Vec<u8>>,
+    keys: HashMap<String, Vec<u8>>,
+    expiry: HashMap<String, CertificateExpiry>,
+    /// Missing from snapshots saved before metadata was tracked
+    #[serde(default)]
+    metadata: HashMap<String, IssuanceMetadata>,
+    key_identifiers: HashMap<String, String>,
+    hash_lookup: HashMap<String, String>,
+    revocations: HashMap<String, RevocationEntry>,
+    audit: Vec<AuditEntry>,
 }
 
 #[derive(Debug, Default)]
 pub struct MemoryStorage {
     name: MemoryRepository<String>,
+    name_history: MemoryRepository<Vec<String>>,
     cert: MemoryRepository<Vec<u8>>,
     keys: MemoryRepository<Vec<u8>>,
+    expiry: MemoryRepository<CertificateExpiry>,
+    metadata: MemoryRepository<IssuanceMetadata>,
     key_identifiers: MemoryRepository<String>,
     hash_lookup: MemoryRepository<String>,
+    revocations: MemoryRepository<RevocationEntry>,
+    audit: RwLock<Vec<AuditEntry>>,
+    /// File the repositories are saved to after every change
+    snapshot_path: Option<PathBuf>,
+    snapshot_lock: Mutex<()>,
 }
 
 impl MemoryStorage {
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// Loads the snapshot at `path` when it exists, then saves every change to it
+    pub fn with_snapshot(path: &Path) -> Result<Self, MemoryStorageError> {
+        let snapshot = if path.exists() {
+            let json = std::fs::read(path)
+                .map_err(|e| format!("couldn't read memory snapshot '{}': {}", path.to_string_lossy(), e))?;
+            serde_json::from_slice(&json).map_err(|e| {
+                format!(
+                    "memory snapshot '{}' is corrupted, refusing to start with an empty storage: {}",
+                    path.to_string_lossy(),
+                    e
+                )
+            })?
+        } else {
+            Snapshot::default()
+        };
+
+        Ok(Self {
+            name: snapshot.name.into(),
+            name_history: snapshot.name_history.into(),
+            cert: snapshot.cert.into(),
+            keys: snapshot.keys.into(),
+            expiry: snapshot.expiry.into(),
+            metadata: snapshot.metadata.into(),
+            key_identifiers: snapshot.key_identifiers.into(),
+            hash_lookup: snapshot.hash_lookup.into(),
+            revocations: snapshot.revocations.into(),
+            audit: RwLock::new(snapshot.audit),
+            snapshot_path: Some(path.to_owned()),
+            snapshot_lock: Mutex::new(()),
+        })
+    }
+
+    /// Atomically replaces the snapshot file, if any, with the current content of the repositories
+    fn h_save_snapshot(&self) -> Result<(), MemoryStorageError> {
+        let path = match &self.snapshot_path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        let _guard = self
+            .snapshot_lock
+            .lock()
+            .expect("couldn't get snapshot lock (poisoned)");
+        let snapshot = Snapshot {
+            name: self.name.get_collection().clone(),
+            name_history: self.name_history.get_collection().clone(),
+            cert: self.cert.get_collection().clone(),
+            keys: self.keys.get_collection().clone(),
+            expiry: self.expiry.get_collection().clone(),
+            metadata: self.metadata.get_collection().clone(),
+            key_identifiers: self.key_identifiers.get_collection().clone(),
+            hash_lookup: self.hash_lookup.get_collection().clone(),
+            revocations: self.revocations.get_collection().clone(),
+            audit: self
+                .audit
+                .read()
+                .expect("couldn't get read lock on audit log (poisoned)")
+                .clone(),
+        };
+        let json = serde_json::to_vec(&snapshot).map_err(|e| format!("couldn't encode memory snapshot: {}", e))?;
+
+        let folder_path = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        write_atomically(folder_path, path, &json, true)
+            .map_err(|e| format!("couldn't write memory snapshot '{}': {}", path.to_string_lossy(), e))?;
+        Ok(())
+    }
+
+    /// Addressing hashes of the certificates whose metadata matches, ordered by the given key
+    fn h_find_by_metadata(
+        &self,
+        filter: impl Fn(&IssuanceMetadata) -> bool,
+        order_key: impl Fn(&IssuanceMetadata) -> i64,
+    ) -> Vec<String> {
+        let metadata = self.metadata.get_collection();
+        let mut found = metadata
+            .iter()
+            .filter(|(_, metadata)| filter(metadata))
+            .map(|(hash, metadata)| (order_key(metadata), hash.clone()))
+            .collect::<Vec<_>>();
+        found.sort();
+        found.into_iter().map(|(_, hash)| hash).collect()
+    }
+
+    /// Drops the metadata of a certificate, as if it was stored before metadata was tracked
+    #[cfg(test)]
+    pub(crate) fn forget_metadata(&self, hash: &str) {
+        self.metadata.remove(hash);
+    }
 }
 
 impl PickyStorage for MemoryStorage {
-    fn health(&self) -> BoxFuture<'_, Result<(), StorageError>> {
-        async { Ok(()) }.boxed()
+    fn ping(&self) -> BoxFuture<'_, Result<(), StorageError>> {
+        async move {
+            self.cert.repo.read().map(drop).map_err(|_| MemoryStorageError::Other {
+                description: "certificate repository lock is poisoned".to_owned(),
+            })?;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn close(&self) -> BoxFuture<'_, Result<(), StorageError>> {
+        async move { Ok(self.h_save_snapshot()?) }.boxed()
     }
 
-    fn store(&self, entry: CertificateEntry) -> BoxFuture<'_, Result<(), StorageError>> {
+    fn store(
+        &self,
+        entry: CertificateEntry,
+        policy: ConflictPolicy,
+    ) -> BoxFuture<'_, Result<StoreOutcome, StorageError>> {
         let name = entry.name;
         let cert = entry.cert;
         let key_identifier = entry.key_identifier;
@@ -76,14 +257,25 @@ impl PickyStorage for MemoryStorage {
 
         async move {
             let addressing_hash = encode_to_canonical_address(&cert);
+            let already_stored = self.cert.get_collection().contains_key(&addressing_hash);
+            let (outcome, write) = resolve_conflict(policy, &addressing_hash, already_stored)?;
+            if !write {
+                return Ok(outcome);
+            }
 
             let alternative_addresses =
                 encode_to_alternative_addresses(&cert).map_err(|e| MemoryStorageError::Other {
                     description: format!("couldn't encode alternative addresses: {}", e),
                 })?;
+            let expiry = CertificateExpiry::from_der(&cert).map_err(MemoryStorageError::from)?;
+            let metadata =
+                IssuanceMetadata::from_der(&cert, Some(Utc::now().timestamp())).map_err(MemoryStorageError::from)?;
 
-            self.name.insert(name, addressing_hash.clone());
+            self.name.insert(name.clone(), addressing_hash.clone());
+            self.name_history.push(name, addressing_hash.clone());
             self.cert.insert(addressing_hash.clone(), cert);
+            self.expiry.insert(addressing_hash.clone(), expiry);
+            self.metadata.insert(addressing_hash.clone(), metadata);
             self.key_identifiers.insert(key_identifier, addressing_hash.clone());
 
             for alternative_address in alternative_addresses.into_iter() {
@@ -94,7 +286,9 @@ impl PickyStorage for MemoryStorage {
                 self.keys.insert(addressing_hash, key);
             }
 
-            Ok(())
+            self.h_save_snapshot()?;
+
+            Ok(outcome)
         }
         .boxed()
     }
@@ -141,6 +335,17 @@ impl PickyStorage for MemoryStorage {
         .boxed()
     }
 
+    fn get_addressing_hashes_by_name<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<Vec<String>, StorageError>> {
+        async move {
+            if let Some(hashes) = self.name_history.get_collection().get(name) {
+                return Ok(hashes.clone());
+            }
+
+            Ok(self.name.get_collection().get(name).cloned().into_iter().collect())
+        }
+        .boxed()
+    }
+
     fn get_addressing_hash_by_key_identifier<'a>(
         &'a self,
         key_identifier: &'a str,
@@ -171,4 +376,239 @@ impl PickyStorage for MemoryStorage {
         }
         .boxed()
     }
+
+    fn get_revocations_by_issuer<'a>(
+        &'a self,
+        issuer_name: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<RevocationEntry>, StorageError>> {
+        async move {
+            Ok(self
+                .revocations
+                .get_collection()
+                .values()
+                .filter(|entry| entry.issuer_name == issuer_name)
+                .cloned()
+                .collect())
+        }
+        .boxed()
+    }
+
+    fn revoke(&self, entry: RevocationEntry) -> BoxFuture<'_, Result<(), StorageError>> {
+        async move {
+            self.revocations.insert(entry.addressing_hash.clone(), entry);
+            self.h_save_snapshot()?;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn list<'a>(
+        &'a self,
+        filter: &'a ListFilter,
+        offset: usize,
+        limit: usize,
+    ) -> BoxFuture<'a, Result<Vec<ListedCertificate>, StorageError>> {
+        async move {
+            let certs = self.cert.get_collection();
+            let expiry = self.expiry.get_collection();
+            let mut hashes = certs.keys().collect::<Vec<_>>();
+            hashes.sort();
+
+            Ok(hashes
+                .into_iter()
+                .filter(|hash| filter.matches(&certs[*hash], expiry.get(*hash)))
+                .skip(offset)
+                .take(limit)
+                .map(|hash| ListedCertificate {
+                    addressing_hash: hash.clone(),
+                    cert: certs[hash].clone(),
+                    expiry: expiry.get(hash).copied(),
+                })
+                .collect())
+        }
+        .boxed()
+    }
+
+    fn count(&self) -> BoxFuture<'_, Result<usize, StorageError>> {
+        async move { Ok(self.cert.get_collection().len()) }.boxed()
+    }
+
+    fn get_revocation_by_addressing_hash<'a>(
+        &'a self,
+        hash: &'a str,
+    ) -> BoxFuture<'a, Result<Option<RevocationEntry>, StorageError>> {
+        async move { Ok(self.revocations.get_collection().get(hash).cloned()) }.boxed()
+    }
+
+    fn get_expiry<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<Option<CertificateExpiry>, StorageError>> {
+        async move { Ok(self.expiry.get_collection().get(hash).copied()) }.boxed()
+    }
+
+    fn set_expired<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<(), StorageError>> {
+        async move {
+            let stored = self.expiry.get_collection().get(hash).copied();
+            let mut expiry = match stored {
+                Some(expiry) => expiry,
+                None => CertificateExpiry::from_der(&self.get_cert_by_addressing_hash(hash).await?)
+                    .map_err(MemoryStorageError::from)?,
+            };
+            expiry.expired = true;
+            self.expiry.insert(hash.to_owned(), expiry);
+            self.h_save_snapshot()?;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn get_metadata<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<Option<IssuanceMetadata>, StorageError>> {
+        async move { Ok(self.metadata.get_collection().get(hash).cloned()) }.boxed()
+    }
+
+    fn set_metadata<'a>(
+        &'a self,
+        hash: &'a str,
+        metadata: IssuanceMetadata,
+    ) -> BoxFuture<'a, Result<(), StorageError>> {
+        async move {
+            self.metadata.insert(hash.to_owned(), metadata);
+            self.h_save_snapshot()?;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn find_expiring_before(&self, not_after: i64) -> BoxFuture<'_, Result<Vec<String>, StorageError>> {
+        async move {
+            Ok(self.h_find_by_metadata(
+                |metadata| metadata.not_after < not_after,
+                |metadata| metadata.not_after,
+            ))
+        }
+        .boxed()
+    }
+
+    fn delete<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<(), StorageError>> {
+        async move {
+            self.cert.remove(hash).ok_or_else(|| MemoryStorageError::Other {
+                description: "cert not found".to_owned(),
+            })?;
+            self.keys.remove(hash);
+            self.expiry.remove(hash);
+            self.metadata.remove(hash);
+            self.key_identifiers.retain(|_, value| *value != hash);
+            self.hash_lookup.retain(|_, value| *value != hash);
+
+            self.name_history.retain(|_, hashes| {
+                hashes.retain(|value| value != hash);
+                !hashes.is_empty()
+            });
+            let name_history = self.name_history.get_collection();
+            self.name.retain(|name, value| {
+                if *value != hash {
+                    return true;
+                }
+
+                match name_history.get(name).and_then(|hashes| hashes.last()) {
+                    Some(previous) => {
+                        *value = previous.clone();
+                        true
+                    }
+                    None => false,
+                }
+            });
+            drop(name_history);
+
+            self.h_save_snapshot()?;
+
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn append_audit(&self, entry: AuditEntry) -> BoxFuture<'_, Result<(), StorageError>> {
+        async move {
+            self.audit
+                .write()
+                .expect("couldn't get write lock on audit log (poisoned)")
+                .push(entry);
+            self.h_save_snapshot()?;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn list_audit(&self, since: i64, limit: usize) -> BoxFuture<'_, Result<Vec<AuditEntry>, StorageError>> {
+        async move {
+            Ok(self
+                .audit
+                .read()
+                .expect("couldn't get read lock on audit log (poisoned)")
+                .iter()
+                .filter(|entry| entry.timestamp >= since)
+                .take(limit)
+                .cloned()
+                .collect())
+        }
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use picky::pem::parse_pem;
+    use tokio_test::block_on;
+
+    fn scratch_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("picky_memory_{}_{}.json", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn snapshot_round_trip() {
+        let path = scratch_path("round_trip");
+        let cert = parse_pem(include_str!("../../../test_assets/root_ca.crt"))
+            .unwrap()
+            .data()
+            .to_vec();
+        let hash = encode_to_canonical_address(&cert);
+
+        block_on(async {
+            let storage = MemoryStorage::with_snapshot(&path).unwrap();
+            storage
+                .store(
+                    CertificateEntry {
+                        name: "Root CA".to_owned(),
+                        cert: cert.clone(),
+                        key_identifier: "root_kid".to_owned(),
+                        key: Some(vec![1, 2, 3]),
+                    },
+                    ConflictPolicy::Overwrite,
+                )
+                .await
+                .unwrap();
+            drop(storage);
+
+            let storage = MemoryStorage::with_snapshot(&path).unwrap();
+            assert_eq!(storage.get_cert_by_addressing_hash(&hash).await.unwrap(), cert);
+            assert_eq!(storage.get_key_by_addressing_hash(&hash).await.unwrap(), vec![1, 2, 3]);
+            assert_eq!(storage.get_addressing_hash_by_name("Root CA").await.unwrap(), hash);
+            assert_eq!(
+                storage.get_addressing_hash_by_key_identifier("root_kid").await.unwrap(),
+                hash
+            );
+            assert!(storage.get_expiry(&hash).await.unwrap().is_some());
+        });
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn corrupted_snapshot_is_an_error() {
+        let path = scratch_path("corrupted");
+        std::fs::write(&path, b"{\"name\": {").unwrap();
+        let err = MemoryStorage::with_snapshot(&path).unwrap_err();
+        assert!(err.to_string().contains("is corrupted"));
+        std::fs::remove_file(&path).unwrap();
+    }
 }
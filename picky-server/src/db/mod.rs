@@ -1,17 +1,41 @@
+mod backup;
+pub(crate) mod cache;
 mod config;
 mod file;
 mod memory;
 mod mongodb;
+#[cfg(feature = "mysql")]
+mod mysql;
+#[cfg(feature = "postgres")]
+mod postgres;
+#[cfg(feature = "redis")]
+mod redis;
+#[cfg(feature = "sqlite")]
+mod sqlite;
 
+#[cfg(feature = "mysql")]
+use crate::db::mysql::{MysqlStorage, MysqlStorageError};
+#[cfg(feature = "postgres")]
+use crate::db::postgres::{PostgresStorage, PostgresStorageError};
+#[cfg(feature = "redis")]
+use crate::db::redis::{RedisStorage, RedisStorageError};
+#[cfg(feature = "sqlite")]
+use crate::db::sqlite::{SqliteStorage, SqliteStorageError};
 use crate::{
     config::{BackendType, Config},
     db::{
+        cache::CachingStorage,
         file::{FileStorage, FileStorageError},
         memory::{MemoryStorage, MemoryStorageError},
         mongodb::{MongoStorage, MongoStorageError},
     },
 };
-use futures::future::BoxFuture;
+pub use backup::{BackupBundle, ImportReport};
+use chrono::{DateTime, Utc};
+use futures::future::{BoxFuture, FutureExt};
+use picky::x509::{certificate::CertType, Cert};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
 use thiserror::Error;
 
 pub const SCHEMA_LAST_VERSION: u8 = 1;
@@ -26,6 +50,38 @@ pub enum StorageError {
 
     #[error("memory storage error: {}", source)]
     Memory { source: MemoryStorageError },
+
+    #[cfg(feature = "postgres")]
+    #[error("postgres storage error: {}", source)]
+    Postgres { source: PostgresStorageError },
+
+    #[cfg(feature = "redis")]
+    #[error("redis storage error: {}", source)]
+    Redis { source: RedisStorageError },
+
+    #[cfg(feature = "sqlite")]
+    #[error("sqlite storage error: {}", source)]
+    Sqlite { source: SqliteStorageError },
+
+    #[cfg(feature = "mysql")]
+    #[error("mysql storage error: {}", source)]
+    Mysql { source: MysqlStorageError },
+
+    #[error(
+        "certificate {} is a CA that {} stored certificate(s) chain to, deletion must be forced",
+        hash,
+        dependents
+    )]
+    CaInUse { hash: String, dependents: usize },
+
+    #[error("certificate {} is already stored", hash)]
+    AlreadyExists { hash: String },
+
+    #[error("stored certificate {} can't be decoded: {}", hash, description)]
+    Undecodable { hash: String, description: String },
+
+    #[error("backup bundle version {} is not supported", version)]
+    UnsupportedBackup { version: u32 },
 }
 
 impl From<MongoStorageError> for StorageError {
@@ -46,17 +102,153 @@ impl From<MemoryStorageError> for StorageError {
     }
 }
 
+#[cfg(feature = "postgres")]
+impl From<PostgresStorageError> for StorageError {
+    fn from(source: PostgresStorageError) -> Self {
+        Self::Postgres { source }
+    }
+}
+
+#[cfg(feature = "redis")]
+impl From<RedisStorageError> for StorageError {
+    fn from(source: RedisStorageError) -> Self {
+        Self::Redis { source }
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl From<SqliteStorageError> for StorageError {
+    fn from(source: SqliteStorageError) -> Self {
+        Self::Sqlite { source }
+    }
+}
+
+#[cfg(feature = "mysql")]
+impl From<MysqlStorageError> for StorageError {
+    fn from(source: MysqlStorageError) -> Self {
+        Self::Mysql { source }
+    }
+}
+
 pub type BoxedPickyStorage = Box<dyn PickyStorage>;
 
+/// Storage backend selected by the configuration, behind a read-through cache unless `cache_size` is 0
 pub async fn get_storage(config: &Config) -> BoxedPickyStorage {
+    let storage = get_backend_storage(config).await;
+    if config.cache_size == 0 {
+        storage
+    } else {
+        Box::new(CachingStorage::new(
+            storage,
+            config.cache_size,
+            Duration::from_secs(config.cache_ttl_secs),
+        ))
+    }
+}
+
+async fn get_backend_storage(config: &Config) -> BoxedPickyStorage {
     match config.backend {
         BackendType::MongoDb => {
-            let client = mongodb::build_client(&config.database_url).await.expect("mongo client");
-            let db = client.database(&config.database_name);
-            Box::new(MongoStorage::new(db).await)
+            let db = mongodb::connect(config).await.expect("mongo storage");
+            Box::new(MongoStorage::new(db, mongodb::collection_prefix(config)).await)
+        }
+        BackendType::Memory => match &config.memory_snapshot_path {
+            Some(path) => Box::new(MemoryStorage::with_snapshot(path).expect("memory storage snapshot")),
+            None => Box::new(MemoryStorage::new()),
+        },
+        BackendType::File => Box::new(FileStorage::new(config).await),
+        #[cfg(feature = "postgres")]
+        BackendType::Postgres => Box::new(
+            PostgresStorage::new(&config.database_url)
+                .await
+                .expect("postgres storage"),
+        ),
+        #[cfg(not(feature = "postgres"))]
+        BackendType::Postgres => panic!("picky-server was built without the postgres feature"),
+        #[cfg(feature = "redis")]
+        BackendType::Redis => Box::new(RedisStorage::new(config).await.expect("redis storage")),
+        #[cfg(not(feature = "redis"))]
+        BackendType::Redis => panic!("picky-server was built without the redis feature"),
+        #[cfg(feature = "sqlite")]
+        BackendType::Sqlite => Box::new(SqliteStorage::new(config).expect("sqlite storage")),
+        #[cfg(not(feature = "sqlite"))]
+        BackendType::Sqlite => panic!("picky-server was built without the sqlite feature"),
+        #[cfg(feature = "mysql")]
+        BackendType::Mysql => Box::new(MysqlStorage::new(&config.database_url).await.expect("mysql storage")),
+        #[cfg(not(feature = "mysql"))]
+        BackendType::Mysql => panic!("picky-server was built without the mysql feature"),
+    }
+}
+
+/// Number of certificates listed per page when scanning the storage
+const SCAN_PAGE_SIZE: usize = 100;
+
+/// Longest time a health check waits for the backend before reporting it degraded
+pub const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Outcome of a storage health check
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum HealthStatus {
+    Ok,
+    /// The backend can't be reached or didn't answer in time
+    Degraded {
+        message: String,
+    },
+}
+
+/// Pings the storage, a backend not answering within `timeout` being reported as degraded
+pub async fn check_health<S: PickyStorage + ?Sized>(storage: &S, timeout: Duration) -> HealthStatus {
+    match tokio::time::timeout(timeout, storage.ping()).await {
+        Ok(Ok(())) => HealthStatus::Ok,
+        Ok(Err(e)) => HealthStatus::Degraded { message: e.to_string() },
+        Err(_) => HealthStatus::Degraded {
+            message: format!("storage didn't answer within {} ms", timeout.as_millis()),
+        },
+    }
+}
+
+/// Deletes a stored certificate.
+/// A CA certificate that other stored certificates chain to is only deleted when `force` is set.
+pub async fn delete_certificate(storage: &dyn PickyStorage, hash: &str, force: bool) -> Result<(), StorageError> {
+    if !force {
+        let cert_der = storage.get_cert_by_addressing_hash(hash).await?;
+        if let Ok(ca) = Cert::from_der(&cert_der) {
+            if matches!(ca.ty(), CertType::Root | CertType::Intermediate) {
+                let dependents = count_issued_by(storage, &ca, hash).await?;
+                if dependents > 0 {
+                    return Err(StorageError::CaInUse {
+                        hash: hash.to_owned(),
+                        dependents,
+                    });
+                }
+            }
         }
-        BackendType::Memory => Box::new(MemoryStorage::new()),
-        BackendType::File => Box::new(FileStorage::new(config)),
+    }
+
+    storage.delete(hash).await
+}
+
+/// Number of stored certificates, other than the CA itself, signed by the given CA
+async fn count_issued_by(storage: &dyn PickyStorage, ca: &Cert, ca_hash: &str) -> Result<usize, StorageError> {
+    let filter = ListFilter::default();
+    let mut count = 0;
+    let mut offset = 0;
+    loop {
+        let page = storage.list(&filter, offset, SCAN_PAGE_SIZE).await?;
+        let page_len = page.len();
+
+        count += page
+            .into_iter()
+            .filter(|listed| listed.addressing_hash != ca_hash)
+            .filter_map(|listed| Cert::from_der(&listed.cert).ok())
+            .filter(|cert| ca.is_parent_of(cert).is_ok())
+            .count();
+
+        if page_len < SCAN_PAGE_SIZE {
+            return Ok(count);
+        }
+        offset += page_len;
     }
 }
 
@@ -68,15 +260,1032 @@ pub struct CertificateEntry {
     pub key: Option<Vec<u8>>,
 }
 
+/// How `PickyStorage::store` handles a certificate whose DER is already stored.
+/// Certificates being addressed by the hash of their DER, a differing certificate is never a conflict,
+/// even when stored under the name of another one: the name is bound to the latest certificate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    /// Fails with `StorageError::AlreadyExists`
+    ErrorIfExists,
+    /// Writes the entry again, binding its name, key identifier and key to the stored certificate
+    Overwrite,
+    /// Leaves the stored certificate and its mappings untouched
+    Ignore,
+}
+
+/// Result of a successful `PickyStorage::store`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StoreOutcome {
+    Created,
+    /// The same certificate was already stored
+    AlreadyExists,
+}
+
+/// Applies `policy` to a certificate which may already be stored,
+/// returning the outcome to report and whether the entry must be written
+pub(crate) fn resolve_conflict(
+    policy: ConflictPolicy,
+    addressing_hash: &str,
+    already_stored: bool,
+) -> Result<(StoreOutcome, bool), StorageError> {
+    if !already_stored {
+        return Ok((StoreOutcome::Created, true));
+    }
+
+    match policy {
+        ConflictPolicy::ErrorIfExists => Err(StorageError::AlreadyExists {
+            hash: addressing_hash.to_owned(),
+        }),
+        ConflictPolicy::Overwrite => Ok((StoreOutcome::AlreadyExists, true)),
+        ConflictPolicy::Ignore => Ok((StoreOutcome::AlreadyExists, false)),
+    }
+}
+
+/// Revocation record of a certificate issued by one of our CAs
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct RevocationEntry {
+    /// Addressing hash of the revoked certificate
+    pub addressing_hash: String,
+    /// Common name of the CA that issued the revoked certificate
+    pub issuer_name: String,
+    /// Hex-encoded serial number of the revoked certificate
+    pub serial_number: String,
+    /// Revocation date as a unix timestamp
+    pub revocation_date: i64,
+    /// CRLReason code as defined by RFC 5280 #5.3.1
+    pub reason: Option<u8>,
+}
+
+/// Certificate or CA operation recorded in the audit log
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditEvent {
+    /// Certificate signed
+    Issued,
+    /// Signing request denied (authorization, issuance policy or CSR requirements)
+    Rejected,
+    /// Signing request accepted but failed
+    Failed,
+    /// Certificate pushed by a client
+    Registered,
+    /// Certificate revoked
+    Revoked,
+    /// CA certificate chain exported, along with the encrypted CA private key when requested
+    Exported,
+}
+
+/// Audit log record, never updated nor removed once appended
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// Event date as a unix timestamp
+    pub timestamp: i64,
+    pub event: AuditEvent,
+    /// Who asked: provisioner token subject, API key fingerprint or "anonymous"
+    pub requester: String,
+    /// Subject common name of the certificate or CSR
+    pub subject: Option<String>,
+    /// Subject alternative names of the certificate or CSR
+    #[serde(default)]
+    pub sans: Vec<String>,
+    /// Hex-encoded serial number of the certificate
+    pub serial_number: Option<String>,
+    /// Addressing hash of the certificate
+    pub addressing_hash: Option<String>,
+    /// Why the request was rejected or failed
+    pub reason: Option<String>,
+}
+
+/// Expiry status of a stored certificate, recorded when the certificate is stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CertificateExpiry {
+    /// notAfter date as a unix timestamp
+    pub not_after: i64,
+    /// Set by the expiry sweep once the notAfter date is past
+    pub expired: bool,
+}
+
+impl CertificateExpiry {
+    pub fn from_der(cert_der: &[u8]) -> Result<Self, String> {
+        let cert = Cert::from_der(cert_der).map_err(|e| format!("couldn't decode certificate: {}", e))?;
+        Ok(Self {
+            not_after: DateTime::<Utc>::from(cert.valid_not_after()).timestamp(),
+            expired: false,
+        })
+    }
+}
+
+/// Certificate fields recorded when it is stored, so that certificates can be queried without decoding them
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct IssuanceMetadata {
+    pub subject: String,
+    /// Hex-encoded serial number
+    pub serial_number: String,
+    /// Hex-encoded subject key identifier
+    pub ski: Option<String>,
+    /// Hex-encoded authority key identifier
+    pub aki: Option<String>,
+    /// notBefore date as a unix timestamp
+    pub not_before: i64,
+    /// notAfter date as a unix timestamp
+    pub not_after: i64,
+    /// Storage date as a unix timestamp
+    pub issued_at: i64,
+}
+
+impl IssuanceMetadata {
+    /// Extracts metadata from a certificate, `issued_at` defaulting to its notBefore date
+    pub fn from_der(cert_der: &[u8], issued_at: Option<i64>) -> Result<Self, String> {
+        let cert = Cert::from_der(cert_der).map_err(|e| format!("couldn't decode certificate: {}", e))?;
+        let not_before = DateTime::<Utc>::from(cert.valid_not_before()).timestamp();
+        Ok(Self {
+            subject: cert.subject_name().to_string(),
+            serial_number: hex::encode(cert.serial_number().as_signed_bytes_be()),
+            ski: cert.subject_key_identifier().ok().map(hex::encode),
+            aki: cert
+                .authority_key_identifier()
+                .ok()
+                .and_then(|aki| aki.key_identifier().map(hex::encode)),
+            not_before,
+            not_after: DateTime::<Utc>::from(cert.valid_not_after()).timestamp(),
+            issued_at: issued_at.unwrap_or(not_before),
+        })
+    }
+}
+
+/// Metadata of a stored certificate.
+/// Certificates stored before metadata was tracked are backfilled, their notBefore date standing for the storage date.
+pub async fn issuance_metadata(storage: &dyn PickyStorage, hash: &str) -> Result<IssuanceMetadata, StorageError> {
+    if let Some(metadata) = storage.get_metadata(hash).await? {
+        return Ok(metadata);
+    }
+
+    let cert_der = storage.get_cert_by_addressing_hash(hash).await?;
+    let metadata = IssuanceMetadata::from_der(&cert_der, None).map_err(|description| StorageError::Undecodable {
+        hash: hash.to_owned(),
+        description,
+    })?;
+    storage.set_metadata(hash, metadata.clone()).await?;
+    Ok(metadata)
+}
+
+/// Filter applied on stored certificates when listing them
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ListFilter {
+    /// Only list certificates whose notBefore is after this date
+    pub issued_after: Option<DateTime<Utc>>,
+    /// Only list certificates whose notAfter is before this date
+    pub expiring_before: Option<DateTime<Utc>>,
+    /// Only list certificates flagged (or not) as expired by the expiry sweep
+    pub expired: Option<bool>,
+}
+
+impl ListFilter {
+    pub fn matches(&self, cert_der: &[u8], expiry: Option<&CertificateExpiry>) -> bool {
+        if let Some(expired) = self.expired {
+            if expiry.map(|expiry| expiry.expired).unwrap_or(false) != expired {
+                return false;
+            }
+        }
+
+        if self.issued_after.is_none() && self.expiring_before.is_none() {
+            return true;
+        }
+
+        let cert = match Cert::from_der(cert_der) {
+            Ok(cert) => cert,
+            Err(e) => {
+                log::warn!("skipping undecodable certificate while listing: {}", e);
+                return false;
+            }
+        };
+
+        if let Some(issued_after) = self.issued_after {
+            if DateTime::<Utc>::from(cert.valid_not_before()) <= issued_after {
+                return false;
+            }
+        }
+
+        if let Some(expiring_before) = self.expiring_before {
+            if DateTime::<Utc>::from(cert.valid_not_after()) >= expiring_before {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// Certificate returned by `PickyStorage::list`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListedCertificate {
+    pub addressing_hash: String,
+    pub cert: Vec<u8>,
+    /// None for certificates stored before expiry was tracked
+    pub expiry: Option<CertificateExpiry>,
+}
+
 pub trait PickyStorage: Send + Sync {
-    fn health(&self) -> BoxFuture<'_, Result<(), StorageError>>;
-    fn store(&self, entry: CertificateEntry) -> BoxFuture<'_, Result<(), StorageError>>;
+    /// Round trip to the underlying database, failing when it can't be reached
+    fn ping(&self) -> BoxFuture<'_, Result<(), StorageError>>;
+    /// Pings the backend, reporting it degraded when it fails or hangs for more than `HEALTH_CHECK_TIMEOUT`
+    fn health(&self) -> BoxFuture<'_, HealthStatus> {
+        check_health(self, HEALTH_CHECK_TIMEOUT).boxed()
+    }
+    /// Flushes pending writes to durable storage before the server stops
+    fn close(&self) -> BoxFuture<'_, Result<(), StorageError>>;
+    /// Drops values cached in front of the backend, if any
+    fn clear_cache(&self) {}
+    /// Stores a certificate along with its name, key identifier, alternative addresses and key.
+    /// When an error is returned, no name, key identifier or alternative address resolves to a missing certificate:
+    /// backends either apply the whole entry atomically or roll back what was written.
+    /// A certificate already stored is handled according to `policy`.
+    fn store(
+        &self,
+        entry: CertificateEntry,
+        policy: ConflictPolicy,
+    ) -> BoxFuture<'_, Result<StoreOutcome, StorageError>>;
     fn get_cert_by_addressing_hash<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<Vec<u8>, StorageError>>;
     fn get_key_by_addressing_hash<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<Vec<u8>, StorageError>>;
     fn get_addressing_hash_by_name<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<String, StorageError>>;
+    /// Addressing hashes of every certificate stored under the given name, oldest first.
+    /// Returns an empty list when nothing matches.
+    fn get_addressing_hashes_by_name<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<Vec<String>, StorageError>>;
     fn get_addressing_hash_by_key_identifier<'a>(
         &'a self,
         key_identifier: &'a str,
     ) -> BoxFuture<'a, Result<String, StorageError>>;
     fn lookup_addressing_hash<'a>(&'a self, lookup_key: &'a str) -> BoxFuture<'a, Result<String, StorageError>>;
+    fn get_revocations_by_issuer<'a>(
+        &'a self,
+        issuer_name: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<RevocationEntry>, StorageError>>;
+    fn revoke(&self, entry: RevocationEntry) -> BoxFuture<'_, Result<(), StorageError>>;
+    /// Stored certificates matching the filter, ordered by addressing hash.
+    /// `offset` and `limit` apply to the filtered sequence.
+    fn list<'a>(
+        &'a self,
+        filter: &'a ListFilter,
+        offset: usize,
+        limit: usize,
+    ) -> BoxFuture<'a, Result<Vec<ListedCertificate>, StorageError>>;
+    /// Number of stored certificates
+    fn count(&self) -> BoxFuture<'_, Result<usize, StorageError>>;
+    fn get_revocation_by_addressing_hash<'a>(
+        &'a self,
+        hash: &'a str,
+    ) -> BoxFuture<'a, Result<Option<RevocationEntry>, StorageError>>;
+    /// Expiry status recorded for this certificate, None for certificates stored before expiry was tracked
+    fn get_expiry<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<Option<CertificateExpiry>, StorageError>>;
+    /// Flags a stored certificate as expired
+    fn set_expired<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<(), StorageError>>;
+    /// Metadata recorded when this certificate was stored, None for certificates stored before metadata was tracked
+    fn get_metadata<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<Option<IssuanceMetadata>, StorageError>>;
+    /// Records the metadata of a stored certificate, used to backfill certificates stored before metadata was tracked
+    fn set_metadata<'a>(&'a self, hash: &'a str, metadata: IssuanceMetadata)
+        -> BoxFuture<'a, Result<(), StorageError>>;
+    /// Addressing hashes of the certificates with metadata whose notAfter date is before `not_after`, soonest first
+    fn find_expiring_before(&self, not_after: i64) -> BoxFuture<'_, Result<Vec<String>, StorageError>>;
+    /// Removes a certificate along with its key, its expiry status, its metadata and every lookup entry pointing to it.
+    /// Names pointing to it are moved back to the previous certificate stored under the same name, if any.
+    fn delete<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<(), StorageError>>;
+    /// Appends an entry to the audit log
+    fn append_audit(&self, entry: AuditEntry) -> BoxFuture<'_, Result<(), StorageError>>;
+    /// Audit entries recorded at or after the `since` unix timestamp, oldest first
+    fn list_audit(&self, since: i64, limit: usize) -> BoxFuture<'_, Result<Vec<AuditEntry>, StorageError>>;
+    /// Dumps the whole storage, private keys included only when `include_keys` is set
+    fn export(&self, include_keys: bool) -> BoxFuture<'_, Result<BackupBundle, StorageError>> {
+        backup::export(self, include_keys).boxed()
+    }
+    /// Restores a bundle produced by `export`, possibly from another backend
+    fn import(&self, bundle: BackupBundle) -> BoxFuture<'_, Result<ImportReport, StorageError>> {
+        backup::import(self, bundle).boxed()
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::addressing::{encode_to_alternative_addresses, encode_to_canonical_address};
+    use picky::pem::parse_pem;
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+    use tokio_test::block_on;
+
+    fn der(pem: &str) -> Vec<u8> {
+        parse_pem(pem).expect("couldn't parse pem").data().to_vec()
+    }
+
+    /// Memory storage counting the reads reaching it
+    pub(crate) struct SpyStorage {
+        inner: memory::MemoryStorage,
+        reads: Arc<AtomicUsize>,
+    }
+
+    impl SpyStorage {
+        pub(crate) fn new() -> (Self, Arc<AtomicUsize>) {
+            let reads = Arc::new(AtomicUsize::new(0));
+            let spy = Self {
+                inner: memory::MemoryStorage::new(),
+                reads: Arc::clone(&reads),
+            };
+            (spy, reads)
+        }
+
+        fn read(&self) -> &memory::MemoryStorage {
+            self.reads.fetch_add(1, Ordering::SeqCst);
+            &self.inner
+        }
+    }
+
+    impl PickyStorage for SpyStorage {
+        fn ping(&self) -> BoxFuture<'_, Result<(), StorageError>> {
+            self.inner.ping()
+        }
+
+        fn close(&self) -> BoxFuture<'_, Result<(), StorageError>> {
+            self.inner.close()
+        }
+
+        fn store(
+            &self,
+            entry: CertificateEntry,
+            policy: ConflictPolicy,
+        ) -> BoxFuture<'_, Result<StoreOutcome, StorageError>> {
+            self.inner.store(entry, policy)
+        }
+
+        fn get_cert_by_addressing_hash<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<Vec<u8>, StorageError>> {
+            self.read().get_cert_by_addressing_hash(hash)
+        }
+
+        fn get_key_by_addressing_hash<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<Vec<u8>, StorageError>> {
+            self.read().get_key_by_addressing_hash(hash)
+        }
+
+        fn get_addressing_hash_by_name<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<String, StorageError>> {
+            self.read().get_addressing_hash_by_name(name)
+        }
+
+        fn get_addressing_hashes_by_name<'a>(
+            &'a self,
+            name: &'a str,
+        ) -> BoxFuture<'a, Result<Vec<String>, StorageError>> {
+            self.read().get_addressing_hashes_by_name(name)
+        }
+
+        fn get_addressing_hash_by_key_identifier<'a>(
+            &'a self,
+            key_identifier: &'a str,
+        ) -> BoxFuture<'a, Result<String, StorageError>> {
+            self.read().get_addressing_hash_by_key_identifier(key_identifier)
+        }
+
+        fn lookup_addressing_hash<'a>(&'a self, lookup_key: &'a str) -> BoxFuture<'a, Result<String, StorageError>> {
+            self.read().lookup_addressing_hash(lookup_key)
+        }
+
+        fn get_revocations_by_issuer<'a>(
+            &'a self,
+            issuer_name: &'a str,
+        ) -> BoxFuture<'a, Result<Vec<RevocationEntry>, StorageError>> {
+            self.read().get_revocations_by_issuer(issuer_name)
+        }
+
+        fn revoke(&self, entry: RevocationEntry) -> BoxFuture<'_, Result<(), StorageError>> {
+            self.inner.revoke(entry)
+        }
+
+        fn list<'a>(
+            &'a self,
+            filter: &'a ListFilter,
+            offset: usize,
+            limit: usize,
+        ) -> BoxFuture<'a, Result<Vec<ListedCertificate>, StorageError>> {
+            self.read().list(filter, offset, limit)
+        }
+
+        fn count(&self) -> BoxFuture<'_, Result<usize, StorageError>> {
+            self.read().count()
+        }
+
+        fn get_revocation_by_addressing_hash<'a>(
+            &'a self,
+            hash: &'a str,
+        ) -> BoxFuture<'a, Result<Option<RevocationEntry>, StorageError>> {
+            self.read().get_revocation_by_addressing_hash(hash)
+        }
+
+        fn get_expiry<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<Option<CertificateExpiry>, StorageError>> {
+            self.read().get_expiry(hash)
+        }
+
+        fn set_expired<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<(), StorageError>> {
+            self.inner.set_expired(hash)
+        }
+
+        fn get_metadata<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<Option<IssuanceMetadata>, StorageError>> {
+            self.read().get_metadata(hash)
+        }
+
+        fn set_metadata<'a>(
+            &'a self,
+            hash: &'a str,
+            metadata: IssuanceMetadata,
+        ) -> BoxFuture<'a, Result<(), StorageError>> {
+            self.inner.set_metadata(hash, metadata)
+        }
+
+        fn find_expiring_before(&self, not_after: i64) -> BoxFuture<'_, Result<Vec<String>, StorageError>> {
+            self.read().find_expiring_before(not_after)
+        }
+
+        fn delete<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<(), StorageError>> {
+            self.inner.delete(hash)
+        }
+
+        fn append_audit(&self, entry: AuditEntry) -> BoxFuture<'_, Result<(), StorageError>> {
+            self.inner.append_audit(entry)
+        }
+
+        fn list_audit(&self, since: i64, limit: usize) -> BoxFuture<'_, Result<Vec<AuditEntry>, StorageError>> {
+            self.read().list_audit(since, limit)
+        }
+    }
+
+    fn audit_entry(timestamp: i64, event: AuditEvent) -> AuditEntry {
+        AuditEntry {
+            timestamp,
+            event,
+            requester: "anonymous".to_owned(),
+            subject: Some("Test Authority".to_owned()),
+            sans: Vec::new(),
+            serial_number: None,
+            addressing_hash: None,
+            reason: None,
+        }
+    }
+
+    /// Behavior every backend is expected to share, run against an empty storage
+    pub(crate) async fn check_storage(storage: &dyn PickyStorage) {
+        let authority = der(include_str!("../../../test_assets/test_authority.crt"));
+        let intermediate = der(include_str!("../../../test_assets/intermediate_ca.crt"));
+        let root = der(include_str!("../../../test_assets/root_ca.crt"));
+        let authority_hash = encode_to_canonical_address(&authority);
+        let intermediate_hash = encode_to_canonical_address(&intermediate);
+        let root_hash = encode_to_canonical_address(&root);
+        let key = vec![1, 2, 3, 4];
+
+        storage.ping().await.expect("ping");
+
+        // storing the same entry twice is not an error
+        for _ in 0..2 {
+            storage
+                .store(
+                    CertificateEntry {
+                        name: "authority".to_owned(),
+                        cert: authority.clone(),
+                        key_identifier: "kid-authority".to_owned(),
+                        key: Some(key.clone()),
+                    },
+                    ConflictPolicy::Overwrite,
+                )
+                .await
+                .expect("store authority");
+        }
+
+        assert_eq!(
+            storage.get_cert_by_addressing_hash(&authority_hash).await.unwrap(),
+            authority
+        );
+        assert_eq!(storage.get_key_by_addressing_hash(&authority_hash).await.unwrap(), key);
+        assert_eq!(
+            storage.get_addressing_hash_by_name("authority").await.unwrap(),
+            authority_hash
+        );
+        assert_eq!(
+            storage.get_addressing_hashes_by_name("authority").await.unwrap(),
+            vec![authority_hash.clone()]
+        );
+        assert_eq!(
+            storage
+                .get_addressing_hash_by_key_identifier("kid-authority")
+                .await
+                .unwrap(),
+            authority_hash
+        );
+        for alternative_address in encode_to_alternative_addresses(&authority).unwrap() {
+            assert_eq!(
+                storage.lookup_addressing_hash(&alternative_address).await.unwrap(),
+                authority_hash
+            );
+        }
+
+        // a new certificate under an existing name takes it over, history is kept
+        storage
+            .store(
+                CertificateEntry {
+                    name: "authority".to_owned(),
+                    cert: intermediate.clone(),
+                    key_identifier: "kid-intermediate".to_owned(),
+                    key: None,
+                },
+                ConflictPolicy::Overwrite,
+            )
+            .await
+            .expect("store intermediate");
+        storage
+            .store(
+                CertificateEntry {
+                    name: "root".to_owned(),
+                    cert: root.clone(),
+                    key_identifier: "kid-root".to_owned(),
+                    key: None,
+                },
+                ConflictPolicy::Overwrite,
+            )
+            .await
+            .expect("store root");
+
+        assert!(storage.get_key_by_addressing_hash(&intermediate_hash).await.is_err());
+        assert_eq!(
+            storage.get_addressing_hash_by_name("authority").await.unwrap(),
+            intermediate_hash
+        );
+        assert_eq!(
+            storage.get_addressing_hashes_by_name("authority").await.unwrap(),
+            vec![authority_hash.clone(), intermediate_hash.clone()]
+        );
+        assert!(storage
+            .get_addressing_hashes_by_name("unknown")
+            .await
+            .unwrap()
+            .is_empty());
+
+        // listing is ordered by addressing hash
+        let mut hashes = vec![authority_hash.clone(), intermediate_hash.clone(), root_hash.clone()];
+        hashes.sort();
+        let listed = storage.list(&ListFilter::default(), 0, 10).await.unwrap();
+        assert_eq!(
+            listed.iter().map(|c| c.addressing_hash.clone()).collect::<Vec<_>>(),
+            hashes
+        );
+        let page = storage.list(&ListFilter::default(), 1, 1).await.unwrap();
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].addressing_hash, hashes[1]);
+
+        // expiry
+        assert_eq!(
+            storage.get_expiry(&root_hash).await.unwrap(),
+            Some(CertificateExpiry::from_der(&root).unwrap())
+        );
+        storage.set_expired(&root_hash).await.expect("set expired");
+        assert!(storage.get_expiry(&root_hash).await.unwrap().unwrap().expired);
+        let expired_filter = ListFilter {
+            expired: Some(true),
+            ..ListFilter::default()
+        };
+        let expired = storage.list(&expired_filter, 0, 10).await.unwrap();
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].addressing_hash, root_hash);
+        assert!(storage.set_expired("unknown").await.is_err());
+
+        // revocations
+        let revocation = RevocationEntry {
+            addressing_hash: intermediate_hash.clone(),
+            issuer_name: "Test Authority".to_owned(),
+            serial_number: "01".to_owned(),
+            revocation_date: 1_600_000_000,
+            reason: Some(1),
+        };
+        storage.revoke(revocation.clone()).await.expect("revoke");
+        assert_eq!(
+            storage.get_revocations_by_issuer("Test Authority").await.unwrap(),
+            vec![revocation.clone()]
+        );
+        assert_eq!(
+            storage
+                .get_revocation_by_addressing_hash(&intermediate_hash)
+                .await
+                .unwrap(),
+            Some(revocation)
+        );
+        assert_eq!(
+            storage.get_revocation_by_addressing_hash(&root_hash).await.unwrap(),
+            None
+        );
+
+        // deleting moves names back to the previous certificate
+        storage.delete(&intermediate_hash).await.expect("delete");
+        assert!(storage.get_cert_by_addressing_hash(&intermediate_hash).await.is_err());
+        assert!(storage
+            .get_addressing_hash_by_key_identifier("kid-intermediate")
+            .await
+            .is_err());
+        assert_eq!(
+            storage.get_addressing_hash_by_name("authority").await.unwrap(),
+            authority_hash
+        );
+        assert_eq!(
+            storage.get_addressing_hashes_by_name("authority").await.unwrap(),
+            vec![authority_hash.clone()]
+        );
+        assert!(storage.delete(&intermediate_hash).await.is_err());
+
+        // unknown entries
+        assert!(storage.get_cert_by_addressing_hash("unknown").await.is_err());
+        assert!(storage.get_addressing_hash_by_name("unknown").await.is_err());
+        assert!(storage.lookup_addressing_hash("unknown").await.is_err());
+
+        // audit log
+        storage.append_audit(audit_entry(10, AuditEvent::Issued)).await.unwrap();
+        storage
+            .append_audit(audit_entry(20, AuditEvent::Rejected))
+            .await
+            .unwrap();
+        storage
+            .append_audit(audit_entry(30, AuditEvent::Revoked))
+            .await
+            .unwrap();
+        let since = storage.list_audit(20, 10).await.unwrap();
+        assert_eq!(
+            since.iter().map(|entry| entry.event).collect::<Vec<_>>(),
+            vec![AuditEvent::Rejected, AuditEvent::Revoked]
+        );
+        assert_eq!(
+            storage.list_audit(0, 1).await.unwrap(),
+            vec![audit_entry(10, AuditEvent::Issued)]
+        );
+
+        storage.close().await.expect("close");
+    }
+
+    /// Stores the same certificate twice, then another one under the same name, other certificates may already be stored
+    pub(crate) async fn check_conflicts(storage: &dyn PickyStorage) {
+        use picky::{
+            key::PrivateKey,
+            x509::{certificate::CertificateBuilder, date::UTCDate, name::DirectoryName},
+        };
+
+        let key = PrivateKey::from_pem(&parse_pem(crate::test_files::RSA_2048_PK_1).unwrap()).unwrap();
+        let cert = |subject: &str| {
+            CertificateBuilder::new()
+                .valididy(UTCDate::ymd(2020, 1, 1).unwrap(), UTCDate::ymd(2030, 1, 1).unwrap())
+                .self_signed(DirectoryName::new_common_name(subject), &key)
+                .build()
+                .expect("couldn't build certificate")
+                .to_der()
+                .unwrap()
+        };
+        let entry = |cert: &[u8], key_identifier: &str, key: Option<Vec<u8>>| CertificateEntry {
+            name: "Conflicting".to_owned(),
+            cert: cert.to_vec(),
+            key_identifier: key_identifier.to_owned(),
+            key,
+        };
+        let first = cert("Conflicting First");
+        let first_hash = encode_to_canonical_address(&first);
+        let second = cert("Conflicting Second");
+        let second_hash = encode_to_canonical_address(&second);
+        let count_before = storage.count().await.unwrap();
+
+        assert_eq!(
+            storage
+                .store(entry(&first, "conflicting_first", None), ConflictPolicy::ErrorIfExists)
+                .await
+                .unwrap(),
+            StoreOutcome::Created
+        );
+
+        // identical certificate
+        match storage
+            .store(entry(&first, "conflicting_first", None), ConflictPolicy::ErrorIfExists)
+            .await
+        {
+            Err(StorageError::AlreadyExists { hash }) => assert_eq!(hash, first_hash),
+            other => panic!("storing the same certificate twice should fail: {:?}", other),
+        }
+        assert_eq!(
+            storage
+                .store(
+                    entry(&first, "conflicting_ignored", Some(vec![1, 2, 3])),
+                    ConflictPolicy::Ignore
+                )
+                .await
+                .unwrap(),
+            StoreOutcome::AlreadyExists
+        );
+        assert!(storage.get_key_by_addressing_hash(&first_hash).await.is_err());
+        assert!(storage
+            .get_addressing_hash_by_key_identifier("conflicting_ignored")
+            .await
+            .is_err());
+        assert_eq!(
+            storage
+                .store(
+                    entry(&first, "conflicting_first", Some(vec![1, 2, 3])),
+                    ConflictPolicy::Overwrite
+                )
+                .await
+                .unwrap(),
+            StoreOutcome::AlreadyExists
+        );
+        assert_eq!(
+            storage.get_key_by_addressing_hash(&first_hash).await.unwrap(),
+            vec![1, 2, 3]
+        );
+        assert_eq!(storage.count().await.unwrap(), count_before + 1);
+
+        // differing certificate under the same name
+        assert_eq!(
+            storage
+                .store(
+                    entry(&second, "conflicting_second", None),
+                    ConflictPolicy::ErrorIfExists
+                )
+                .await
+                .unwrap(),
+            StoreOutcome::Created
+        );
+        assert_eq!(
+            storage.get_addressing_hash_by_name("Conflicting").await.unwrap(),
+            second_hash
+        );
+        assert_eq!(
+            storage.get_addressing_hashes_by_name("Conflicting").await.unwrap(),
+            vec![first_hash.clone(), second_hash]
+        );
+        assert_eq!(storage.get_cert_by_addressing_hash(&first_hash).await.unwrap(), first);
+        assert_eq!(storage.count().await.unwrap(), count_before + 2);
+    }
+
+    /// Pages through 25 newly stored certificates, other certificates may already be stored
+    pub(crate) async fn check_pagination(storage: &dyn PickyStorage) {
+        use picky::{
+            key::PrivateKey,
+            x509::{certificate::CertificateBuilder, date::UTCDate, name::DirectoryName},
+        };
+
+        let key = PrivateKey::from_pem(&parse_pem(crate::test_files::RSA_2048_PK_1).unwrap()).unwrap();
+        let count_before = storage.count().await.unwrap();
+
+        let mut stored = Vec::new();
+        for i in 0..25 {
+            let name = format!("Paged {}", i);
+            let cert = CertificateBuilder::new()
+                .valididy(UTCDate::ymd(2020, 1, 1).unwrap(), UTCDate::ymd(2030, 1, 1).unwrap())
+                .self_signed(DirectoryName::new_common_name(name.as_str()), &key)
+                .build()
+                .expect("couldn't build certificate")
+                .to_der()
+                .unwrap();
+            stored.push(encode_to_canonical_address(&cert));
+            storage
+                .store(
+                    CertificateEntry {
+                        name: name.clone(),
+                        cert,
+                        key_identifier: name,
+                        key: None,
+                    },
+                    ConflictPolicy::Overwrite,
+                )
+                .await
+                .expect("store");
+        }
+        assert_eq!(storage.count().await.unwrap(), count_before + 25);
+
+        let mut listed = Vec::new();
+        let mut offset = 0;
+        loop {
+            let page = storage.list(&ListFilter::default(), offset, 7).await.unwrap();
+            assert!(page.len() <= 7);
+            offset += page.len();
+            listed.extend(page.into_iter().map(|listed| listed.addressing_hash));
+            if offset >= count_before + 25 {
+                break;
+            }
+        }
+        assert!(storage
+            .list(&ListFilter::default(), offset, 7)
+            .await
+            .unwrap()
+            .is_empty());
+
+        // stable order, no duplicates nor gaps
+        let mut sorted = listed.clone();
+        sorted.sort();
+        sorted.dedup();
+        assert_eq!(listed, sorted);
+        assert_eq!(listed.len(), count_before + 25);
+        for hash in stored {
+            assert!(listed.contains(&hash));
+        }
+    }
+
+    /// Stores certificates with different validities and queries them through their metadata,
+    /// other certificates may already be stored
+    pub(crate) async fn check_metadata(storage: &dyn PickyStorage) {
+        use picky::{
+            key::PrivateKey,
+            x509::{certificate::CertificateBuilder, date::UTCDate, name::DirectoryName},
+        };
+
+        let key = PrivateKey::from_pem(&parse_pem(crate::test_files::RSA_2048_PK_1).unwrap()).unwrap();
+        let timestamp = |year| DateTime::<Utc>::from(UTCDate::ymd(year, 1, 1).unwrap()).timestamp();
+
+        let before = Utc::now().timestamp();
+        let mut stored = Vec::new();
+        for (name, year) in &[("Expiring Late", 2034), ("Expiring Soon", 2032), ("Expiring Mid", 2033)] {
+            let cert = CertificateBuilder::new()
+                .valididy(UTCDate::ymd(2020, 1, 1).unwrap(), UTCDate::ymd(*year, 1, 1).unwrap())
+                .self_signed(DirectoryName::new_common_name(*name), &key)
+                .build()
+                .expect("couldn't build certificate")
+                .to_der()
+                .unwrap();
+            stored.push(encode_to_canonical_address(&cert));
+            storage
+                .store(
+                    CertificateEntry {
+                        name: (*name).to_owned(),
+                        cert,
+                        key_identifier: (*name).to_owned(),
+                        key: None,
+                    },
+                    ConflictPolicy::Overwrite,
+                )
+                .await
+                .expect("store");
+        }
+        let after = Utc::now().timestamp();
+        let ours = |hashes: Vec<String>| {
+            hashes
+                .into_iter()
+                .filter(|hash| stored.contains(hash))
+                .collect::<Vec<_>>()
+        };
+
+        let metadata = storage.get_metadata(&stored[1]).await.unwrap().expect("metadata");
+        assert_eq!(metadata.subject, "CN=Expiring Soon");
+        assert_eq!(metadata.not_before, timestamp(2020));
+        assert_eq!(metadata.not_after, timestamp(2032));
+        assert!(metadata.issued_at >= before && metadata.issued_at <= after);
+        assert!(metadata.ski.is_some());
+        assert_eq!(metadata.ski, metadata.aki);
+        assert!(!metadata.serial_number.is_empty());
+
+        assert_eq!(
+            ours(storage.find_expiring_before(timestamp(2034)).await.unwrap()),
+            vec![stored[1].clone(), stored[2].clone()]
+        );
+        assert!(ours(storage.find_expiring_before(timestamp(2032)).await.unwrap()).is_empty());
+
+        // deleted certificates take their metadata with them
+        storage.delete(&stored[1]).await.expect("delete");
+        assert_eq!(storage.get_metadata(&stored[1]).await.unwrap(), None);
+        assert_eq!(
+            ours(storage.find_expiring_before(timestamp(2034)).await.unwrap()),
+            vec![stored[2].clone()]
+        );
+
+        // metadata can be backfilled
+        let mut backfilled = storage.get_metadata(&stored[0]).await.unwrap().unwrap();
+        backfilled.not_after = timestamp(2000);
+        storage.set_metadata(&stored[0], backfilled.clone()).await.unwrap();
+        assert_eq!(storage.get_metadata(&stored[0]).await.unwrap(), Some(backfilled));
+        assert_eq!(
+            ours(storage.find_expiring_before(timestamp(2001)).await.unwrap()),
+            vec![stored[0].clone()]
+        );
+    }
+
+    #[test]
+    fn memory_storage() {
+        block_on(check_storage(&memory::MemoryStorage::new()));
+    }
+
+    #[test]
+    fn memory_storage_health() {
+        let status = block_on(memory::MemoryStorage::new().health());
+        assert_eq!(status, HealthStatus::Ok);
+        assert_eq!(
+            serde_json::to_value(&status).unwrap(),
+            serde_json::json!({ "status": "ok" })
+        );
+
+        let degraded = HealthStatus::Degraded {
+            message: "unreachable".to_owned(),
+        };
+        assert_eq!(
+            serde_json::to_value(&degraded).unwrap(),
+            serde_json::json!({ "status": "degraded", "message": "unreachable" })
+        );
+    }
+
+    #[test]
+    fn memory_storage_pagination() {
+        block_on(check_pagination(&memory::MemoryStorage::new()));
+    }
+
+    #[test]
+    fn memory_storage_conflicts() {
+        block_on(check_conflicts(&memory::MemoryStorage::new()));
+    }
+
+    #[test]
+    fn memory_storage_metadata() {
+        block_on(check_metadata(&memory::MemoryStorage::new()));
+    }
+
+    #[test]
+    fn metadata_is_backfilled_for_older_certificates() {
+        let storage = memory::MemoryStorage::new();
+        let cert = der(include_str!("../../../test_assets/intermediate_ca.crt"));
+        let hash = encode_to_canonical_address(&cert);
+        block_on(storage.store(
+            CertificateEntry {
+                name: "PolarSSL Server 1".to_owned(),
+                cert,
+                key_identifier: "1f74d63f29c17474453b05122c3da8bd435902a6".to_owned(),
+                key: None,
+            },
+            ConflictPolicy::ErrorIfExists,
+        ))
+        .unwrap();
+        storage.forget_metadata(&hash);
+        assert_eq!(block_on(storage.get_metadata(&hash)).unwrap(), None);
+
+        let metadata = block_on(issuance_metadata(&storage, &hash)).unwrap();
+        assert_eq!(metadata.issued_at, metadata.not_before);
+        assert_eq!(
+            metadata.ski.as_deref(),
+            Some("1f74d63f29c17474453b05122c3da8bd435902a6")
+        );
+        assert_eq!(block_on(storage.get_metadata(&hash)).unwrap(), Some(metadata));
+    }
+
+    #[test]
+    fn delete_ca_requires_force() {
+        use crate::picky_controller::Picky;
+        use picky::{hash::HashAlgorithm, key::PrivateKey, signature::SignatureAlgorithm, x509::KeyIdGenMethod};
+
+        let signature_algorithm = SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_256);
+        let root_key = PrivateKey::from_pem(&parse_pem(crate::test_files::RSA_2048_PK_1).unwrap()).unwrap();
+        let intermediate_key = PrivateKey::from_pem(&parse_pem(crate::test_files::RSA_2048_PK_2).unwrap()).unwrap();
+        let root = Picky::generate_root(
+            "Root",
+            &root_key,
+            signature_algorithm,
+            KeyIdGenMethod::default(),
+            chrono::Duration::days(10),
+        )
+        .unwrap();
+        let intermediate = Picky::generate_intermediate(
+            "Intermediate",
+            intermediate_key.to_public_key(),
+            &root,
+            &root_key,
+            signature_algorithm,
+            KeyIdGenMethod::default(),
+            chrono::Duration::days(5),
+        )
+        .unwrap();
+        let root = root.to_der().unwrap();
+        let intermediate = intermediate.to_der().unwrap();
+        let root_hash = encode_to_canonical_address(&root);
+        let intermediate_hash = encode_to_canonical_address(&intermediate);
+
+        let storage = memory::MemoryStorage::new();
+        block_on(async {
+            for (name, cert) in [("Root", root), ("Intermediate", intermediate)] {
+                storage
+                    .store(
+                        CertificateEntry {
+                            name: name.to_owned(),
+                            cert,
+                            key_identifier: name.to_owned(),
+                            key: None,
+                        },
+                        ConflictPolicy::Overwrite,
+                    )
+                    .await
+                    .unwrap();
+            }
+
+            match delete_certificate(&storage, &root_hash, false).await {
+                Err(StorageError::CaInUse { dependents, .. }) => assert_eq!(dependents, 1),
+                other => panic!("unexpected result: {:?}", other),
+            }
+            assert!(storage.get_cert_by_addressing_hash(&root_hash).await.is_ok());
+
+            delete_certificate(&storage, &root_hash, true).await.unwrap();
+            assert!(storage.get_cert_by_addressing_hash(&root_hash).await.is_err());
+
+            // nothing chains to the intermediate CA
+            delete_certificate(&storage, &intermediate_hash, false).await.unwrap();
+            assert!(delete_certificate(&storage, &intermediate_hash, false).await.is_err());
+        });
+    }
 }
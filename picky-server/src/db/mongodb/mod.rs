@@ -1,37 +1,206 @@
 mod model;
+mod repository;
+mod retry;
 
 use crate::{
     addressing::{encode_to_alternative_addresses, encode_to_canonical_address},
-    db::{CertificateEntry, PickyStorage, StorageError, SCHEMA_LAST_VERSION},
+    config::Config as ServerConfig,
+    db::{
+        resolve_conflict, AuditEntry, CertificateEntry, CertificateExpiry, ConflictPolicy, IssuanceMetadata,
+        ListFilter, ListedCertificate, PickyStorage, RevocationEntry, StorageError, StoreOutcome, SCHEMA_LAST_VERSION,
+    },
 };
+use chrono::Utc;
 use futures::{future::BoxFuture, stream::StreamExt, FutureExt};
 use model::*;
 use mongodm::{
     f,
     mongo::{
-        bson::{doc, oid::ObjectId, spec::BinarySubtype, Binary, Bson},
-        options::{ClientOptions, ReadPreference, ReplaceOptions, SelectionCriteria},
+        bson::{doc, oid::ObjectId, spec::BinarySubtype, Binary, Bson, Document},
+        options::{ClientOptions, FindOneOptions, FindOptions, ReadPreference, ReplaceOptions, SelectionCriteria},
         Client, Database,
     },
-    ToRepository,
 };
-use picky::x509::Cert;
-use std::{collections::HashMap, convert::TryFrom};
+use picky::{key::PrivateKey, pem::parse_pem, x509::Cert};
+use repository::{collection_name, Repository};
+use retry::{retry_once, retry_with_backoff, Backoff};
+use std::{collections::HashMap, convert::TryFrom, time::Duration};
 use thiserror::Error;
 
-const DB_CONNECTION_TIMEOUT_SECS: u64 = 15;
-
-pub async fn build_client(mongo_url: &str) -> mongodm::mongo::error::Result<Client> {
-    let mut client_options = ClientOptions::parse(mongo_url).await?;
+pub async fn build_client(config: &ServerConfig) -> mongodm::mongo::error::Result<Client> {
+    let mut client_options = ClientOptions::parse(&config.database_url).await?;
     client_options.app_name = Some(String::from("Picky"));
     client_options.selection_criteria = Some(SelectionCriteria::ReadPreference(ReadPreference::SecondaryPreferred {
         options: Default::default(),
     }));
-    client_options.connect_timeout = Some(std::time::Duration::from_secs(DB_CONNECTION_TIMEOUT_SECS));
+    client_options.connect_timeout = Some(Duration::from_secs(config.mongo_connect_timeout_secs));
+    client_options.server_selection_timeout = Some(Duration::from_secs(config.mongo_operation_timeout_secs));
+    client_options.max_pool_size = config.mongo_max_pool_size;
 
     Client::with_options(client_options)
 }
 
+/// Connects to the configured database, retrying with exponential backoff until MongoDB answers a ping
+pub async fn connect(config: &ServerConfig) -> Result<Database, MongoStorageError> {
+    let backoff = Backoff {
+        attempts: config.mongo_connect_attempts.max(1),
+        base_delay: Duration::from_millis(config.mongo_retry_delay_ms),
+    };
+
+    let db = build_client(config).await?.database(&config.database_name);
+    retry_with_backoff(&backoff, "connection to mongodb", || {
+        db.run_command(doc! { "ping": 1 }, None)
+    })
+    .await
+    .map_err(|e| MongoStorageError::Other {
+        description: format!(
+            "couldn't reach mongodb at '{}' after {} attempts: {}",
+            config.database_url, backoff.attempts, e
+        ),
+    })?;
+
+    Ok(db)
+}
+
+/// Stores DER content as generic binary
+fn der_to_bson(der: Vec<u8>) -> Bson {
+    Bson::Binary(Binary {
+        subtype: BinarySubtype::Generic,
+        bytes: der,
+    })
+}
+
+/// DER content of a certificate or key document, along with whether it uses the legacy encoding:
+/// an array of signed bytes stored as 32-bit integers
+fn der_from_bson(value: Bson) -> Result<(Vec<u8>, bool), MongoStorageError> {
+    match value {
+        Bson::Binary(Binary {
+            subtype: BinarySubtype::Generic,
+            bytes,
+        }) => Ok((bytes, false)),
+        Bson::Array(values) => {
+            let der = values
+                .into_iter()
+                .map(|value| match value {
+                    Bson::Int32(byte) => {
+                        i8::try_from(byte)
+                            .map(|byte| byte as u8)
+                            .map_err(|_| MongoStorageError::Other {
+                                description: format!("expected signed byte in legacy DB content but got {}", byte),
+                            })
+                    }
+                    unexpected => Err(MongoStorageError::Other {
+                        description: format!("expected signed byte in legacy DB content but got {}", unexpected),
+                    }),
+                })
+                .collect::<Result<Vec<u8>, _>>()?;
+            Ok((der, true))
+        }
+        unexpected => Err(MongoStorageError::Other {
+            description: format!("expected binary DB content but got {}", unexpected),
+        }),
+    }
+}
+
+async fn sync_model_indexes<M: mongodm::Model>(db: &Database, prefix: &str) -> Result<(), MongoStorageError> {
+    let coll_name = collection_name::<M>(prefix);
+    db.run_command(M::indexes().create_indexes_command(&coll_name), None)
+        .await
+        .map_err(|e| MongoStorageError::Other {
+            description: format!(
+            "couldn't create indexes of the '{}' collection, documents sharing the same key must be removed first: {}",
+            coll_name,
+            e
+        ),
+        })?;
+    Ok(())
+}
+
+/// Prefix of the collection names: the configured one, or the realm when `mongo_realm_prefix` is set
+pub fn collection_prefix(config: &ServerConfig) -> String {
+    if !config.mongo_collection_prefix.is_empty() || !config.mongo_realm_prefix {
+        return config.mongo_collection_prefix.clone();
+    }
+
+    let realm: String = config
+        .realm
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("{}_", realm)
+}
+
+/// Entry to store for a document of the legacy single collection
+fn legacy_certificate_entry(legacy: &LegacyCertificate) -> Result<CertificateEntry, String> {
+    let cert_der = parse_pem(&legacy.cert)
+        .map_err(|e| format!("couldn't parse certificate pem: {}", e))?
+        .into_data()
+        .into_owned();
+    let cert = Cert::from_der(&cert_der).map_err(|e| format!("couldn't decode certificate: {}", e))?;
+
+    let name = match legacy.common_name.as_deref() {
+        Some(common_name) if !common_name.is_empty() => common_name.to_owned(),
+        _ => cert
+            .subject_name()
+            .common_name()
+            .ok_or_else(|| "certificate has no common name".to_owned())?
+            .to_string(),
+    };
+    let key_identifier = hex::encode(
+        cert.subject_key_identifier()
+            .map_err(|e| format!("couldn't find subject key identifier: {}", e))?,
+    );
+    let key = legacy
+        .key
+        .as_deref()
+        .map(|key_pem| {
+            let pem = parse_pem(key_pem).map_err(|e| format!("couldn't parse private key pem: {}", e))?;
+            PrivateKey::from_pem(&pem)
+                .and_then(|key| key.to_pkcs8())
+                .map_err(|e| format!("couldn't decode private key: {}", e))
+        })
+        .transpose()?;
+
+    Ok(CertificateEntry {
+        name,
+        cert: cert_der,
+        key_identifier,
+        key,
+    })
+}
+
+/// Stores the certificates of the legacy single collection, skipping those that can't be decoded.
+/// Returns the number of migrated certificates.
+async fn migrate_legacy_certificates(storage: &dyn PickyStorage, legacy_certificates: Vec<LegacyCertificate>) -> usize {
+    let mut migrated = 0;
+    for legacy in legacy_certificates {
+        let entry = match legacy_certificate_entry(&legacy) {
+            Ok(entry) => entry,
+            Err(e) => {
+                log::error!(
+                    "skipping legacy certificate '{}': {}",
+                    legacy.common_name.as_deref().unwrap_or_default(),
+                    e
+                );
+                continue;
+            }
+        };
+
+        // certificates already stored by a previous, interrupted run are counted as migrated
+        match storage.store(entry, ConflictPolicy::Ignore).await {
+            Ok(_) => migrated += 1,
+            Err(e) => log::error!("couldn't store legacy certificate: {}", e),
+        }
+    }
+    migrated
+}
+
 #[derive(Debug, Error)]
 pub enum MongoStorageError {
     #[error("mongo error: {}", source)]
@@ -63,24 +232,13 @@ impl From<mongodm::mongo::error::Error> for StorageError {
 
 pub struct MongoStorage {
     db: Database,
-}
-
-impl ToRepository for MongoStorage {
-    fn repository<M: mongodm::Model>(&self) -> mongodm::Repository<M> {
-        self.db.repository()
-    }
-
-    fn repository_with_options<M: mongodm::Model>(
-        &self,
-        options: mongodm::mongo::options::CollectionOptions,
-    ) -> mongodm::Repository<M> {
-        self.db.repository_with_options(options)
-    }
+    /// Prepended to every collection name
+    collection_prefix: String,
 }
 
 impl MongoStorage {
-    pub async fn new(db: Database) -> Self {
-        let storage = MongoStorage { db };
+    pub async fn new(db: Database, collection_prefix: String) -> Self {
+        let storage = MongoStorage { db, collection_prefix };
 
         let config_repo = storage.repository::<Config>();
         let config_opt = config_repo.find_one(doc!(), None).await.expect("config repo");
@@ -122,11 +280,7 @@ impl MongoStorage {
                     let mut certs_cursor = cert_collection.find(doc!(), None).await.expect("certs cursor");
                     while let Some(cert_model) = certs_cursor.next().await {
                         let cert_model = cert_model.expect("unwrap cert model");
-                        if let Bson::Binary(Binary {
-                            subtype: BinarySubtype::Generic,
-                            bytes: bin,
-                        }) = cert_model.value
-                        {
+                        if let Ok((bin, _)) = der_from_bson(cert_model.value) {
                             original_data.insert(cert_model.key, (bin, None));
                         }
                     }
@@ -134,11 +288,7 @@ impl MongoStorage {
                     let mut keys_cursor = key_collection.find(doc!(), None).await.expect("find private keys");
                     while let Some(key_model) = keys_cursor.next().await {
                         let key_model = key_model.expect("unwrap key model");
-                        if let Bson::Binary(Binary {
-                            subtype: BinarySubtype::Generic,
-                            bytes: bin,
-                        }) = key_model.value
-                        {
+                        if let Ok((bin, _)) = der_from_bson(key_model.value) {
                             original_data
                                 .entry(key_model.key)
                                 .and_modify(|entry| entry.1 = Some(bin));
@@ -158,21 +308,53 @@ impl MongoStorage {
                     for (_, (cert_der, key_pkcs10)) in original_data.into_iter() {
                         let cert = Cert::from_der(&cert_der).expect("decode cert from der");
                         storage
-                            .store(CertificateEntry {
-                                name: cert
-                                    .subject_name()
-                                    .find_common_name()
-                                    .expect("cert common name")
-                                    .to_string(),
-                                cert: cert_der,
-                                key_identifier: hex::encode(cert.subject_key_identifier().expect("cert key id")),
-                                key: key_pkcs10,
-                            })
+                            .store(
+                                CertificateEntry {
+                                    name: cert.subject_name().common_name().expect("cert common name").to_string(),
+                                    cert: cert_der,
+                                    key_identifier: hex::encode(cert.subject_key_identifier().expect("cert key id")),
+                                    key: key_pkcs10,
+                                },
+                                ConflictPolicy::Overwrite,
+                            )
                             .await
                             .expect("couldn't store certificate (migration from v0 schema)");
                     }
 
                     log::info!("migrated to v{} successfully!", SCHEMA_LAST_VERSION);
+                } else if storage
+                    .repository::<LegacyCertificate>()
+                    .estimated_document_count(None)
+                    .await
+                    .expect("count number of certificates in legacy collection")
+                    > 0
+                {
+                    log::info!(
+                        "detected legacy '{}' collection: migrate database to v{}...",
+                        collection_name::<LegacyCertificate>(&storage.collection_prefix),
+                        SCHEMA_LAST_VERSION
+                    );
+
+                    let mut legacy_certificates = Vec::new();
+                    let mut cursor = storage
+                        .repository::<LegacyCertificate>()
+                        .find(doc!(), None)
+                        .await
+                        .expect("legacy certificates cursor");
+                    while let Some(legacy) = cursor.next().await {
+                        match legacy {
+                            Ok(legacy) => legacy_certificates.push(legacy),
+                            Err(e) => log::error!("skipping undecodable legacy certificate document: {}", e),
+                        }
+                    }
+
+                    let migrated = migrate_legacy_certificates(&storage, legacy_certificates).await;
+                    // the legacy collection is kept, the configuration document written below marks the migration as done
+                    log::info!(
+                        "migrated {} legacy certificate(s) to v{}",
+                        migrated,
+                        SCHEMA_LAST_VERSION
+                    );
                 } else {
                     log::info!("fresh new database using v{} schema", SCHEMA_LAST_VERSION);
                 }
@@ -186,115 +368,249 @@ impl MongoStorage {
             }
         }
 
+        storage.sync_indexes().await.expect("couldn't create indexes");
+
         storage
     }
-}
 
-impl PickyStorage for MongoStorage {
-    fn health(&self) -> BoxFuture<'_, Result<(), StorageError>> {
-        async move {
-            self.db
-                .list_collection_names(None)
-                .await
-                .map_err(|e| MongoStorageError::Other {
-                    description: format!("ping to mongo connexion failed: {}", e),
-                })?;
-            Ok(())
-        }
-        .boxed()
+    fn repository<M: mongodm::Model + Send + 'static>(&self) -> Repository<M> {
+        Repository::new(&self.db, &self.collection_prefix)
+    }
+
+    /// Reads are idempotent and thus retried once, so that a dropped connection doesn't fail the request
+    async fn find_one<M: mongodm::Model + Send + 'static>(
+        &self,
+        filter: Document,
+    ) -> Result<Option<M>, MongoStorageError> {
+        let what = format!("read from {}", collection_name::<M>(&self.collection_prefix));
+        let model = retry_once(&what, || {
+            let filter = filter.clone();
+            async move { self.repository::<M>().find_one(filter, None).await }
+        })
+        .await?;
+        Ok(model)
+    }
+
+    /// Creates the indexes declared by the models, enforcing key uniqueness
+    async fn sync_indexes(&self) -> Result<(), MongoStorageError> {
+        let prefix = self.collection_prefix.as_str();
+        sync_model_indexes::<Name>(&self.db, prefix).await?;
+        sync_model_indexes::<NameHistoryEntry>(&self.db, prefix).await?;
+        sync_model_indexes::<Certificate>(&self.db, prefix).await?;
+        sync_model_indexes::<Key>(&self.db, prefix).await?;
+        sync_model_indexes::<KeyIdentifier>(&self.db, prefix).await?;
+        sync_model_indexes::<HashLookupEntry>(&self.db, prefix).await?;
+        sync_model_indexes::<Expiry>(&self.db, prefix).await?;
+        sync_model_indexes::<Metadata>(&self.db, prefix).await?;
+        sync_model_indexes::<Revocation>(&self.db, prefix).await?;
+        sync_model_indexes::<AuditRecord>(&self.db, prefix).await?;
+        Ok(())
+    }
+
+    async fn find_expiry(&self, hash: &str) -> Result<Option<CertificateExpiry>, StorageError> {
+        let model = self.find_one::<Expiry>(doc!(f!(key in Expiry): hash)).await?;
+        Ok(model.map(|model| CertificateExpiry {
+            not_after: model.not_after,
+            expired: model.expired,
+        }))
     }
 
-    fn store(&self, entry: CertificateEntry) -> BoxFuture<'_, Result<(), StorageError>> {
+    /// Writes the certificate and its key before the mappings referring to them, the name last
+    async fn h_store(&self, entry: CertificateEntry) -> Result<(), StorageError> {
         let name = entry.name;
         let cert = entry.cert;
         let key_identifier = entry.key_identifier;
         let key = entry.key;
 
-        async move {
-            let addressing_hash = encode_to_canonical_address(&cert);
+        let addressing_hash = encode_to_canonical_address(&cert);
 
-            let alternative_addresses =
-                encode_to_alternative_addresses(&cert).map_err(|e| MongoStorageError::Other {
-                    description: format!("couldn't encode alternative addresses: {}", e),
-                })?;
+        let alternative_addresses = encode_to_alternative_addresses(&cert).map_err(|e| MongoStorageError::Other {
+            description: format!("couldn't encode alternative addresses: {}", e),
+        })?;
+        let expiry = CertificateExpiry::from_der(&cert).map_err(MongoStorageError::from)?;
+        let metadata =
+            IssuanceMetadata::from_der(&cert, Some(Utc::now().timestamp())).map_err(MongoStorageError::from)?;
 
-            let query = doc! {f!(key in Name): &name};
-            let name = Name {
-                key: name,
-                value: addressing_hash.clone(),
-            };
-            self.repository::<Name>()
-                .replace_one(query, &name, Some(ReplaceOptions::builder().upsert(true).build()))
-                .await?;
+        let query = doc! {f!(key in Certificate): &addressing_hash };
+        let certificate = Certificate {
+            key: addressing_hash.clone(),
+            value: der_to_bson(cert),
+        };
+        self.repository::<Certificate>()
+            .replace_one(
+                query,
+                &certificate,
+                Some(ReplaceOptions::builder().upsert(true).build()),
+            )
+            .await?;
 
-            let query = doc! {f!(key in Certificate): &addressing_hash };
-            let certificate = Certificate {
+        self.upsert_expiry(&addressing_hash, expiry).await?;
+        self.upsert_metadata(&addressing_hash, metadata).await?;
+
+        if let Some(key) = key {
+            let query = doc! {f!(key in Key): &addressing_hash};
+            let key = Key {
                 key: addressing_hash.clone(),
-                value: Bson::Binary(Binary {
-                    subtype: BinarySubtype::Generic,
-                    bytes: cert,
-                }),
+                value: der_to_bson(key),
             };
-            self.repository::<Certificate>()
-                .replace_one(
-                    query,
-                    &certificate,
-                    Some(ReplaceOptions::builder().upsert(true).build()),
-                )
+            self.repository::<Key>()
+                .replace_one(query, &key, Some(ReplaceOptions::builder().upsert(true).build()))
                 .await?;
+        }
+
+        let query = doc! { f!(key in KeyIdentifier): &key_identifier };
+        let key_identifier = KeyIdentifier {
+            key: key_identifier,
+            value: addressing_hash.clone(),
+        };
+        self.repository::<KeyIdentifier>()
+            .replace_one(
+                query,
+                &key_identifier,
+                Some(ReplaceOptions::builder().upsert(true).build()),
+            )
+            .await?;
 
-            let query = doc! { f!(key in KeyIdentifier): &key_identifier };
-            let key_identifier = KeyIdentifier {
-                key: key_identifier,
+        for alternative_address in alternative_addresses.into_iter() {
+            let query = doc! { f!(key in HashLookupEntry): &alternative_address };
+            let alternative_key_identifier = HashLookupEntry {
+                key: alternative_address,
                 value: addressing_hash.clone(),
             };
-            self.repository::<KeyIdentifier>()
+            self.repository::<HashLookupEntry>()
                 .replace_one(
                     query,
-                    &key_identifier,
+                    &alternative_key_identifier,
                     Some(ReplaceOptions::builder().upsert(true).build()),
                 )
                 .await?;
+        }
 
-            for alternative_address in alternative_addresses.into_iter() {
-                let query = doc! { f!(key in HashLookupEntry): &alternative_address };
-                let alternative_key_identifier = HashLookupEntry {
-                    key: alternative_address,
-                    value: addressing_hash.clone(),
-                };
-                self.repository::<HashLookupEntry>()
-                    .replace_one(
-                        query,
-                        &alternative_key_identifier,
-                        Some(ReplaceOptions::builder().upsert(true).build()),
-                    )
-                    .await?;
-            }
+        let query = doc! {f!(value in NameHistoryEntry): &addressing_hash};
+        let name_history_entry = NameHistoryEntry {
+            key: name.clone(),
+            value: addressing_hash.clone(),
+        };
+        self.repository::<NameHistoryEntry>()
+            .replace_one(
+                query,
+                &name_history_entry,
+                Some(ReplaceOptions::builder().upsert(true).build()),
+            )
+            .await?;
 
-            if let Some(key) = key {
-                let query = doc! {f!(key in Key): &addressing_hash};
-                let key = Key {
-                    key: addressing_hash,
-                    value: Bson::Binary(Binary {
-                        subtype: BinarySubtype::Generic,
-                        bytes: key,
-                    }),
-                };
-                self.repository::<Key>()
-                    .replace_one(query, &key, Some(ReplaceOptions::builder().upsert(true).build()))
-                    .await?;
-            }
+        let query = doc! {f!(key in Name): &name};
+        let name = Name {
+            key: name,
+            value: addressing_hash,
+        };
+        self.repository::<Name>()
+            .replace_one(query, &name, Some(ReplaceOptions::builder().upsert(true).build()))
+            .await?;
+
+        Ok(())
+    }
 
+    async fn upsert_expiry(&self, hash: &str, expiry: CertificateExpiry) -> Result<(), StorageError> {
+        let query = doc! { f!(key in Expiry): hash };
+        let model = Expiry {
+            key: hash.to_owned(),
+            not_after: expiry.not_after,
+            expired: expiry.expired,
+        };
+        self.repository::<Expiry>()
+            .replace_one(query, &model, Some(ReplaceOptions::builder().upsert(true).build()))
+            .await?;
+        Ok(())
+    }
+
+    async fn upsert_metadata(&self, hash: &str, metadata: IssuanceMetadata) -> Result<(), StorageError> {
+        let query = doc! { f!(key in Metadata): hash };
+        let model = Metadata {
+            key: hash.to_owned(),
+            subject: metadata.subject,
+            serial_number: metadata.serial_number,
+            ski: metadata.ski,
+            aki: metadata.aki,
+            not_before: metadata.not_before,
+            not_after: metadata.not_after,
+            issued_at: metadata.issued_at,
+        };
+        self.repository::<Metadata>()
+            .replace_one(query, &model, Some(ReplaceOptions::builder().upsert(true).build()))
+            .await?;
+        Ok(())
+    }
+
+    /// Addressing hashes of the certificates whose metadata matches `filter`, sorted by `sort_field`
+    async fn find_metadata_hashes(&self, filter: Document, sort_field: &str) -> Result<Vec<String>, StorageError> {
+        let options = FindOptions::builder()
+            .sort(doc! { sort_field: 1, f!(key in Metadata): 1 })
+            .build();
+        let mut hashes = Vec::new();
+        let mut cursor = self.repository::<Metadata>().find(filter, Some(options)).await?;
+        while let Some(model) = cursor.next().await {
+            hashes.push(model?.key);
+        }
+        Ok(hashes)
+    }
+}
+
+impl PickyStorage for MongoStorage {
+    fn ping(&self) -> BoxFuture<'_, Result<(), StorageError>> {
+        async move {
+            self.db
+                .run_command(doc! { "ping": 1 }, None)
+                .await
+                .map_err(|e| MongoStorageError::Other {
+                    description: format!("ping to mongo connexion failed: {}", e),
+                })?;
             Ok(())
         }
         .boxed()
     }
 
+    fn close(&self) -> BoxFuture<'_, Result<(), StorageError>> {
+        // writes are acknowledged by the server, connections are released when the client is dropped
+        async move { Ok(()) }.boxed()
+    }
+
+    fn store(
+        &self,
+        entry: CertificateEntry,
+        policy: ConflictPolicy,
+    ) -> BoxFuture<'_, Result<StoreOutcome, StorageError>> {
+        async move {
+            let addressing_hash = encode_to_canonical_address(&entry.cert);
+            let already_stored = self
+                .find_one::<Certificate>(doc!(f!(key in Certificate): &addressing_hash))
+                .await?
+                .is_some();
+            let (outcome, write) = resolve_conflict(policy, &addressing_hash, already_stored)?;
+            if !write {
+                return Ok(outcome);
+            }
+
+            let result = self.h_store(entry).await;
+            if result.is_err() && !already_stored {
+                // no transaction support with this driver version: undo what was written by compensating deletes
+                if let Err(e) = self.delete(&addressing_hash).await {
+                    log::error!(
+                        "couldn't roll back partially stored certificate {}: {}",
+                        addressing_hash,
+                        e
+                    );
+                }
+            }
+            result.map(|()| outcome)
+        }
+        .boxed()
+    }
+
     fn get_addressing_hash_by_name<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<String, StorageError>> {
         async move {
             let hash = self
-                .repository::<Name>()
-                .find_one(doc!(f!(key in Name): name), None)
+                .find_one::<Name>(doc!(f!(key in Name): name))
                 .await?
                 .map(|model| model.value)
                 .ok_or_else(|| MongoStorageError::Other {
@@ -305,50 +621,84 @@ impl PickyStorage for MongoStorage {
         .boxed()
     }
 
+    fn get_addressing_hashes_by_name<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<Vec<String>, StorageError>> {
+        async move {
+            let mut hashes = Vec::new();
+            let mut cursor = self
+                .repository::<NameHistoryEntry>()
+                .find(
+                    doc!(f!(key in NameHistoryEntry): name),
+                    Some(FindOptions::builder().sort(doc! { "_id": 1 }).build()),
+                )
+                .await?;
+            while let Some(model) = cursor.next().await {
+                hashes.push(model?.value);
+            }
+
+            if hashes.is_empty() {
+                // certificates stored before name history was tracked
+                if let Some(model) = self.find_one::<Name>(doc!(f!(key in Name): name)).await? {
+                    hashes.push(model.value);
+                }
+            }
+
+            Ok(hashes)
+        }
+        .boxed()
+    }
+
     fn get_cert_by_addressing_hash<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<Vec<u8>, StorageError>> {
         async move {
             let cert = self
-                .repository::<Certificate>()
-                .find_one(doc!(f!(key in Certificate): hash), None)
+                .find_one::<Certificate>(doc!(f!(key in Certificate): hash))
                 .await?
                 .ok_or_else(|| MongoStorageError::Other {
                     description: "cert not found".to_owned(),
                 })?;
 
-            match cert.value {
-                Bson::Binary(Binary {
-                    subtype: BinarySubtype::Generic,
-                    bytes: bin,
-                }) => Ok(bin),
-                unexpected => Err(MongoStorageError::Other {
-                    description: format!("expected binary DB content but got {}", unexpected),
+            let (der, legacy) = der_from_bson(cert.value)?;
+            if legacy {
+                let model = Certificate {
+                    key: cert.key,
+                    value: der_to_bson(der.clone()),
+                };
+                if let Err(e) = self
+                    .repository::<Certificate>()
+                    .replace_one(doc!(f!(key in Certificate): hash), &model, None)
+                    .await
+                {
+                    log::warn!("couldn't migrate legacy certificate document {} to binary: {}", hash, e);
                 }
-                .into()),
             }
+            Ok(der)
         }
         .boxed()
     }
 
     fn get_key_by_addressing_hash<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<Vec<u8>, StorageError>> {
         async move {
-            let key = self
-                .repository::<Key>()
-                .find_one(doc!(f!(key in Key): hash), None)
-                .await?
-                .ok_or_else(|| MongoStorageError::Other {
-                    description: "key not found".to_owned(),
-                })?;
+            let key =
+                self.find_one::<Key>(doc!(f!(key in Key): hash))
+                    .await?
+                    .ok_or_else(|| MongoStorageError::Other {
+                        description: "key not found".to_owned(),
+                    })?;
 
-            match key.value {
-                Bson::Binary(Binary {
-                    subtype: BinarySubtype::Generic,
-                    bytes: bin,
-                }) => Ok(bin),
-                unexpected => Err(MongoStorageError::Other {
-                    description: format!("expected binary DB content but got {}", unexpected),
+            let (der, legacy) = der_from_bson(key.value)?;
+            if legacy {
+                let model = Key {
+                    key: key.key,
+                    value: der_to_bson(der.clone()),
+                };
+                if let Err(e) = self
+                    .repository::<Key>()
+                    .replace_one(doc!(f!(key in Key): hash), &model, None)
+                    .await
+                {
+                    log::warn!("couldn't migrate legacy key document {} to binary: {}", hash, e);
                 }
-                .into()),
             }
+            Ok(der)
         }
         .boxed()
     }
@@ -359,8 +709,7 @@ impl PickyStorage for MongoStorage {
     ) -> BoxFuture<'a, Result<String, StorageError>> {
         async move {
             let addressing_hash = self
-                .repository::<KeyIdentifier>()
-                .find_one(doc!(f!(key in KeyIdentifier): key_identifier), None)
+                .find_one::<KeyIdentifier>(doc!(f!(key in KeyIdentifier): key_identifier))
                 .await?
                 .ok_or_else(|| MongoStorageError::Other {
                     description: format!("addressing hash not found by key identifier \"{}\"", key_identifier),
@@ -374,8 +723,7 @@ impl PickyStorage for MongoStorage {
     fn lookup_addressing_hash<'a>(&'a self, lookup_key: &'a str) -> BoxFuture<'a, Result<String, StorageError>> {
         async move {
             let addressing_hash = self
-                .repository::<HashLookupEntry>()
-                .find_one(doc!(f!(key in HashLookupEntry): lookup_key), None)
+                .find_one::<HashLookupEntry>(doc!(f!(key in HashLookupEntry): lookup_key))
                 .await?
                 .ok_or_else(|| MongoStorageError::Other {
                     description: format!("addressing hash not found using lookup key \"{}\"", lookup_key),
@@ -385,4 +733,620 @@ impl PickyStorage for MongoStorage {
         }
         .boxed()
     }
+
+    fn get_revocations_by_issuer<'a>(
+        &'a self,
+        issuer_name: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<RevocationEntry>, StorageError>> {
+        async move {
+            let mut revocations = Vec::new();
+            let mut cursor = self
+                .repository::<Revocation>()
+                .find(doc!(f!(issuer_name in Revocation): issuer_name), None)
+                .await?;
+            while let Some(model) = cursor.next().await {
+                revocations.push(revocation_entry_from_model(model?)?);
+            }
+            Ok(revocations)
+        }
+        .boxed()
+    }
+
+    fn revoke(&self, entry: RevocationEntry) -> BoxFuture<'_, Result<(), StorageError>> {
+        async move {
+            let query = doc! { f!(key in Revocation): &entry.addressing_hash };
+            let revocation = Revocation {
+                key: entry.addressing_hash,
+                issuer_name: entry.issuer_name,
+                serial_number: entry.serial_number,
+                revocation_date: entry.revocation_date,
+                reason: entry.reason.map(i32::from),
+            };
+            self.repository::<Revocation>()
+                .replace_one(query, &revocation, Some(ReplaceOptions::builder().upsert(true).build()))
+                .await?;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn list<'a>(
+        &'a self,
+        filter: &'a ListFilter,
+        offset: usize,
+        limit: usize,
+    ) -> BoxFuture<'a, Result<Vec<ListedCertificate>, StorageError>> {
+        async move {
+            let mut options = FindOptions::builder().sort(doc! { f!(key in Certificate): 1 }).build();
+            let mut skipped = 0;
+            if *filter == ListFilter::default() {
+                // without filter, skipping is left to the database
+                options.skip = Some(i64::try_from(offset).unwrap_or(i64::MAX));
+                skipped = offset;
+            }
+
+            let mut certs = Vec::new();
+            let mut cursor = self.repository::<Certificate>().find(doc!(), Some(options)).await?;
+            while let Some(model) = cursor.next().await {
+                if certs.len() >= limit {
+                    break;
+                }
+
+                let model = model?;
+                let (cert, _) = der_from_bson(model.value)?;
+                let expiry = self.find_expiry(&model.key).await?;
+                if !filter.matches(&cert, expiry.as_ref()) {
+                    continue;
+                }
+
+                if skipped < offset {
+                    skipped += 1;
+                    continue;
+                }
+
+                certs.push(ListedCertificate {
+                    addressing_hash: model.key,
+                    cert,
+                    expiry,
+                });
+            }
+            Ok(certs)
+        }
+        .boxed()
+    }
+
+    fn count(&self) -> BoxFuture<'_, Result<usize, StorageError>> {
+        async move {
+            let count = self.repository::<Certificate>().count_documents(doc!(), None).await?;
+            Ok(count as usize)
+        }
+        .boxed()
+    }
+
+    fn get_revocation_by_addressing_hash<'a>(
+        &'a self,
+        hash: &'a str,
+    ) -> BoxFuture<'a, Result<Option<RevocationEntry>, StorageError>> {
+        async move {
+            let model = self.find_one::<Revocation>(doc!(f!(key in Revocation): hash)).await?;
+            Ok(model.map(revocation_entry_from_model).transpose()?)
+        }
+        .boxed()
+    }
+
+    fn get_expiry<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<Option<CertificateExpiry>, StorageError>> {
+        async move { self.find_expiry(hash).await }.boxed()
+    }
+
+    fn set_expired<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<(), StorageError>> {
+        async move {
+            let mut expiry = match self.find_expiry(hash).await? {
+                Some(expiry) => expiry,
+                None => CertificateExpiry::from_der(&self.get_cert_by_addressing_hash(hash).await?)
+                    .map_err(MongoStorageError::from)?,
+            };
+            expiry.expired = true;
+            self.upsert_expiry(hash, expiry).await
+        }
+        .boxed()
+    }
+
+    fn get_metadata<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<Option<IssuanceMetadata>, StorageError>> {
+        async move {
+            let model = self.find_one::<Metadata>(doc!(f!(key in Metadata): hash)).await?;
+            Ok(model.map(|model| IssuanceMetadata {
+                subject: model.subject,
+                serial_number: model.serial_number,
+                ski: model.ski,
+                aki: model.aki,
+                not_before: model.not_before,
+                not_after: model.not_after,
+                issued_at: model.issued_at,
+            }))
+        }
+        .boxed()
+    }
+
+    fn set_metadata<'a>(
+        &'a self,
+        hash: &'a str,
+        metadata: IssuanceMetadata,
+    ) -> BoxFuture<'a, Result<(), StorageError>> {
+        async move { self.upsert_metadata(hash, metadata).await }.boxed()
+    }
+
+    fn find_expiring_before(&self, not_after: i64) -> BoxFuture<'_, Result<Vec<String>, StorageError>> {
+        async move {
+            self.find_metadata_hashes(
+                doc! { f!(not_after in Metadata): { "$lt": not_after } },
+                f!(not_after in Metadata),
+            )
+            .await
+        }
+        .boxed()
+    }
+
+    fn delete<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<(), StorageError>> {
+        async move {
+            if self
+                .find_one::<Certificate>(doc!(f!(key in Certificate): hash))
+                .await?
+                .is_none()
+            {
+                return Err(MongoStorageError::Other {
+                    description: "cert not found".to_owned(),
+                }
+                .into());
+            }
+
+            self.repository::<KeyIdentifier>()
+                .delete_many(doc!(f!(value in KeyIdentifier): hash), None)
+                .await?;
+            self.repository::<HashLookupEntry>()
+                .delete_many(doc!(f!(value in HashLookupEntry): hash), None)
+                .await?;
+            self.repository::<NameHistoryEntry>()
+                .delete_many(doc!(f!(value in NameHistoryEntry): hash), None)
+                .await?;
+
+            let mut names = Vec::new();
+            let mut cursor = self
+                .repository::<Name>()
+                .find(doc!(f!(value in Name): hash), None)
+                .await?;
+            while let Some(model) = cursor.next().await {
+                names.push(model?.key);
+            }
+            for name in names {
+                let previous = self
+                    .repository::<NameHistoryEntry>()
+                    .find_one(
+                        doc!(f!(key in NameHistoryEntry): &name),
+                        Some(FindOneOptions::builder().sort(doc! { "_id": -1 }).build()),
+                    )
+                    .await?;
+                match previous {
+                    Some(previous) => {
+                        let query = doc! {f!(key in Name): &name};
+                        let name = Name {
+                            key: name,
+                            value: previous.value,
+                        };
+                        self.repository::<Name>().replace_one(query, &name, None).await?;
+                    }
+                    None => {
+                        self.repository::<Name>()
+                            .delete_one(doc!(f!(key in Name): &name), None)
+                            .await?;
+                    }
+                }
+            }
+
+            self.repository::<Key>()
+                .delete_one(doc!(f!(key in Key): hash), None)
+                .await?;
+            self.repository::<Expiry>()
+                .delete_one(doc!(f!(key in Expiry): hash), None)
+                .await?;
+            self.repository::<Metadata>()
+                .delete_one(doc!(f!(key in Metadata): hash), None)
+                .await?;
+            // removed last so that an interrupted deletion can be retried
+            self.repository::<Certificate>()
+                .delete_one(doc!(f!(key in Certificate): hash), None)
+                .await?;
+
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn append_audit(&self, entry: AuditEntry) -> BoxFuture<'_, Result<(), StorageError>> {
+        async move {
+            let record = AuditRecord {
+                timestamp: entry.timestamp,
+                event: entry.event,
+                requester: entry.requester,
+                subject: entry.subject,
+                sans: entry.sans,
+                serial_number: entry.serial_number,
+                addressing_hash: entry.addressing_hash,
+                reason: entry.reason,
+            };
+            self.repository::<AuditRecord>().insert_one(&record, None).await?;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn list_audit(&self, since: i64, limit: usize) -> BoxFuture<'_, Result<Vec<AuditEntry>, StorageError>> {
+        async move {
+            let limit = i64::try_from(limit).unwrap_or(i64::MAX);
+            let mut entries = Vec::new();
+            let mut cursor = self
+                .repository::<AuditRecord>()
+                .find(
+                    doc! { f!(timestamp in AuditRecord): { "$gte": since } },
+                    Some(FindOptions::builder().sort(doc! { "_id": 1 }).limit(limit).build()),
+                )
+                .await?;
+            while let Some(model) = cursor.next().await {
+                let model = model?;
+                entries.push(AuditEntry {
+                    timestamp: model.timestamp,
+                    event: model.event,
+                    requester: model.requester,
+                    subject: model.subject,
+                    sans: model.sans,
+                    serial_number: model.serial_number,
+                    addressing_hash: model.addressing_hash,
+                    reason: model.reason,
+                });
+            }
+            Ok(entries)
+        }
+        .boxed()
+    }
+}
+
+fn revocation_entry_from_model(model: Revocation) -> Result<RevocationEntry, MongoStorageError> {
+    Ok(RevocationEntry {
+        addressing_hash: model.key,
+        issuer_name: model.issuer_name,
+        serial_number: model.serial_number,
+        revocation_date: model.revocation_date,
+        reason: model
+            .reason
+            .map(u8::try_from)
+            .transpose()
+            .map_err(|e| MongoStorageError::Other {
+                description: format!("invalid revocation reason code: {}", e),
+            })?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::HealthStatus;
+    use mongodm::{
+        mongo::bson::{from_bson, to_bson},
+        Model,
+    };
+    use tokio_test::block_on;
+
+    const TEST_DATABASE_URL_ENV: &str = "PICKY_TEST_MONGODB_URL";
+
+    fn cert_der() -> Vec<u8> {
+        parse_pem(include_str!("../../../../test_assets/intermediate_ca.crt"))
+            .unwrap()
+            .data()
+            .to_vec()
+    }
+
+    fn encoded_len(model: &Certificate) -> usize {
+        let mut buf = Vec::new();
+        to_bson(model)
+            .unwrap()
+            .as_document()
+            .expect("model encodes to a document")
+            .to_writer(&mut buf)
+            .unwrap();
+        buf.len()
+    }
+
+    #[test]
+    fn legacy_and_binary_documents_decode_identically() {
+        let der = cert_der();
+
+        // as written by previous versions, DER bytes converted to i8
+        let legacy = from_bson::<Certificate>(Bson::Document(doc! {
+            "key": "hash",
+            "value": der.iter().map(|byte| Bson::Int32(i32::from(*byte as i8))).collect::<Vec<_>>(),
+        }))
+        .unwrap();
+        let binary = from_bson::<Certificate>(Bson::Document(doc! {
+            "key": "hash",
+            "value": der_to_bson(der.clone()),
+        }))
+        .unwrap();
+
+        let legacy_len = encoded_len(&legacy);
+        let binary_len = encoded_len(&binary);
+
+        assert_eq!(der_from_bson(legacy.value).unwrap(), (der.clone(), true));
+        assert_eq!(der_from_bson(binary.value).unwrap(), (der.clone(), false));
+
+        // each legacy byte takes a type tag, an index key and a 32-bit integer
+        assert!(binary_len < der.len() + 64);
+        assert!(legacy_len > 5 * binary_len);
+    }
+
+    #[test]
+    fn unexpected_content_is_rejected() {
+        assert!(der_from_bson(Bson::String("der".to_owned())).is_err());
+        assert!(der_from_bson(Bson::Array(vec![Bson::Int32(300)])).is_err());
+    }
+
+    #[test]
+    fn legacy_certificates_are_migrated() {
+        let intermediate_pem = include_str!("../../../../test_assets/intermediate_ca.crt");
+        let root_pem = include_str!("../../../../test_assets/root_ca.crt");
+        let legacy_certificates = vec![
+            LegacyCertificate {
+                cert: intermediate_pem.to_owned(),
+                common_name: Some("Legacy Intermediate".to_owned()),
+                key: Some(crate::test_files::RSA_2048_PK_1.to_owned()),
+            },
+            LegacyCertificate {
+                cert: root_pem.to_owned(),
+                common_name: None,
+                key: None,
+            },
+            LegacyCertificate {
+                cert: "-----BEGIN CERTIFICATE-----\ngarbage\n-----END CERTIFICATE-----".to_owned(),
+                common_name: Some("Broken".to_owned()),
+                key: None,
+            },
+        ];
+
+        let storage = crate::db::memory::MemoryStorage::new();
+        block_on(async {
+            assert_eq!(migrate_legacy_certificates(&storage, legacy_certificates).await, 2);
+
+            let intermediate_der = cert_der();
+            let intermediate_hash = encode_to_canonical_address(&intermediate_der);
+            assert_eq!(
+                storage
+                    .get_addressing_hash_by_name("Legacy Intermediate")
+                    .await
+                    .unwrap(),
+                intermediate_hash
+            );
+            assert_eq!(
+                storage
+                    .get_addressing_hash_by_key_identifier("1f74d63f29c17474453b05122c3da8bd435902a6")
+                    .await
+                    .unwrap(),
+                intermediate_hash
+            );
+            assert_eq!(
+                storage.get_cert_by_addressing_hash(&intermediate_hash).await.unwrap(),
+                intermediate_der
+            );
+            let key = PrivateKey::from_pem(&parse_pem(crate::test_files::RSA_2048_PK_1).unwrap()).unwrap();
+            assert_eq!(
+                storage.get_key_by_addressing_hash(&intermediate_hash).await.unwrap(),
+                key.to_pkcs8().unwrap()
+            );
+
+            // the common name is taken from the certificate when missing
+            let root_hash = storage.get_addressing_hash_by_name("DST Root CA X3").await.unwrap();
+            assert_eq!(
+                storage
+                    .get_addressing_hash_by_key_identifier("c4a7b1a47b2c71fadbe14b9075ffc41560858910")
+                    .await
+                    .unwrap(),
+                root_hash
+            );
+            assert!(storage.get_addressing_hash_by_name("Broken").await.is_err());
+        });
+    }
+
+    /// Storage whose client is built without waiting for the server to answer
+    fn unconnected_storage(database_url: String) -> MongoStorage {
+        let config = ServerConfig {
+            database_url,
+            mongo_connect_timeout_secs: 1,
+            mongo_operation_timeout_secs: 1,
+            ..Default::default()
+        };
+        let client = block_on(build_client(&config)).unwrap();
+        MongoStorage {
+            db: client.database(&config.database_name),
+            collection_prefix: String::new(),
+        }
+    }
+
+    #[test]
+    fn unreachable_server_is_degraded() {
+        // nothing listens on the discard port
+        let storage = unconnected_storage("mongodb://127.0.0.1:9".to_owned());
+        match block_on(crate::db::check_health(&storage, Duration::from_secs(10))) {
+            HealthStatus::Degraded { message } => assert!(message.contains("ping to mongo"), "{}", message),
+            HealthStatus::Ok => panic!("unreachable mongodb reported healthy"),
+        }
+    }
+
+    #[test]
+    fn hung_server_is_degraded_quickly() {
+        // connections are accepted by the kernel but never answered
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let storage = unconnected_storage(format!("mongodb://{}", listener.local_addr().unwrap()));
+
+        let start = std::time::Instant::now();
+        match block_on(crate::db::check_health(&storage, Duration::from_millis(200))) {
+            HealthStatus::Degraded { message } => assert!(message.contains("didn't answer"), "{}", message),
+            HealthStatus::Ok => panic!("hung mongodb reported healthy"),
+        }
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn connect_gives_up() {
+        let config = ServerConfig {
+            // nothing listens on the discard port
+            database_url: "mongodb://127.0.0.1:9".to_owned(),
+            mongo_connect_attempts: 2,
+            mongo_retry_delay_ms: 1,
+            mongo_operation_timeout_secs: 1,
+            ..Default::default()
+        };
+
+        let err = block_on(connect(&config)).unwrap_err();
+        assert!(err.to_string().contains("couldn't reach mongodb"), "{}", err);
+        assert!(err.to_string().contains("after 2 attempts"), "{}", err);
+    }
+
+    #[test]
+    fn mongo_storage() {
+        let database_url = match std::env::var(TEST_DATABASE_URL_ENV) {
+            Ok(database_url) => database_url,
+            Err(_) => {
+                eprintln!("{} not set, skipping mongodb integration tests", TEST_DATABASE_URL_ENV);
+                return;
+            }
+        };
+
+        block_on(async {
+            let config = ServerConfig {
+                database_url,
+                database_name: format!("picky_test_{}", std::process::id()),
+                ..Default::default()
+            };
+            let db = connect(&config).await.expect("mongo connection");
+            db.drop(None).await.expect("couldn't clean database");
+            let storage = MongoStorage::new(db.clone(), String::new()).await;
+
+            crate::db::tests::check_storage(&storage).await;
+            crate::db::tests::check_pagination(&storage).await;
+            crate::db::tests::check_conflicts(&storage).await;
+            crate::db::tests::check_metadata(&storage).await;
+
+            // storing the same certificate again doesn't duplicate documents
+            let der = cert_der();
+            let hash = encode_to_canonical_address(&der);
+            for _ in 0..2 {
+                storage
+                    .store(
+                        CertificateEntry {
+                            name: "Twice".to_owned(),
+                            cert: der.clone(),
+                            key_identifier: "twice_kid".to_owned(),
+                            key: Some(vec![1, 2, 3]),
+                        },
+                        ConflictPolicy::Overwrite,
+                    )
+                    .await
+                    .expect("store");
+            }
+            let count = |coll: &'static str, filter: mongodm::mongo::bson::Document| {
+                let db = db.clone();
+                async move { db.collection(coll).count_documents(filter, None).await.unwrap() }
+            };
+            assert_eq!(count(Certificate::coll_name(), doc!("key": &hash)).await, 1);
+            assert_eq!(count(Key::coll_name(), doc!("key": &hash)).await, 1);
+            assert_eq!(count(Name::coll_name(), doc!("key": "Twice")).await, 1);
+            assert_eq!(count(NameHistoryEntry::coll_name(), doc!("value": &hash)).await, 1);
+            assert_eq!(count(KeyIdentifier::coll_name(), doc!("key": "twice_kid")).await, 1);
+
+            // a unique index can't be created over duplicates
+            db.collection(KeyIdentifier::coll_name()).drop(None).await.unwrap();
+            for _ in 0..2 {
+                db.collection(KeyIdentifier::coll_name())
+                    .insert_one(doc!("key": "duplicate", "value": &hash), None)
+                    .await
+                    .unwrap();
+            }
+            let err = storage.sync_indexes().await.unwrap_err();
+            assert!(err.to_string().contains("key_identifier_store"));
+
+            db.drop(None).await.unwrap();
+        });
+    }
+
+    #[test]
+    fn collection_prefixes() {
+        let mut config = ServerConfig::default();
+        assert_eq!(collection_prefix(&config), "");
+        assert_eq!(collection_name::<Certificate>(""), "certificate_store");
+
+        config.realm = "Test Realm".to_owned();
+        config.mongo_realm_prefix = true;
+        assert_eq!(collection_prefix(&config), "test_realm_");
+
+        config.mongo_collection_prefix = "tenant_a.".to_owned();
+        assert_eq!(collection_prefix(&config), "tenant_a.");
+        assert_eq!(
+            collection_name::<Certificate>("tenant_a."),
+            "tenant_a.certificate_store"
+        );
+    }
+
+    #[test]
+    fn prefixed_storages_share_a_database() {
+        let database_url = match std::env::var(TEST_DATABASE_URL_ENV) {
+            Ok(database_url) => database_url,
+            Err(_) => {
+                eprintln!("{} not set, skipping mongodb integration tests", TEST_DATABASE_URL_ENV);
+                return;
+            }
+        };
+
+        block_on(async {
+            let config = ServerConfig {
+                database_url,
+                database_name: format!("picky_test_prefixes_{}", std::process::id()),
+                ..Default::default()
+            };
+            let db = connect(&config).await.expect("mongo connection");
+            db.drop(None).await.expect("couldn't clean database");
+            let first = MongoStorage::new(db.clone(), "first_".to_owned()).await;
+            let second = MongoStorage::new(db.clone(), "second_".to_owned()).await;
+
+            let der = cert_der();
+            let hash = encode_to_canonical_address(&der);
+            first
+                .store(
+                    CertificateEntry {
+                        name: "Shared".to_owned(),
+                        cert: der,
+                        key_identifier: "shared_kid".to_owned(),
+                        key: None,
+                    },
+                    ConflictPolicy::ErrorIfExists,
+                )
+                .await
+                .expect("store");
+
+            assert_eq!(first.get_addressing_hash_by_name("Shared").await.unwrap(), hash);
+            assert!(second.get_addressing_hash_by_name("Shared").await.is_err());
+            assert!(second.get_cert_by_addressing_hash(&hash).await.is_err());
+            assert_eq!(first.count().await.unwrap(), 1);
+            assert_eq!(second.count().await.unwrap(), 0);
+
+            // unprefixed collections are left alone
+            let unprefixed = db
+                .collection(Certificate::coll_name())
+                .count_documents(doc!(), None)
+                .await
+                .unwrap();
+            assert_eq!(unprefixed, 0);
+
+            second.delete(&hash).await.ok();
+            assert!(first.get_cert_by_addressing_hash(&hash).await.is_ok());
+
+            db.drop(None).await.unwrap();
+        });
+    }
 }
@@ -1,3 +1,4 @@
+use crate::db::AuditEvent;
 use mongodm::{
     mongo::bson::{oid::ObjectId, Bson},
     Index, IndexOption, Indexes,
@@ -20,6 +21,24 @@ impl mongodm::Model for Name {
     }
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NameHistoryEntry {
+    pub key: String,
+    pub value: String,
+}
+
+impl mongodm::Model for NameHistoryEntry {
+    fn coll_name() -> &'static str {
+        "name_history_store"
+    }
+
+    fn indexes() -> Indexes {
+        Indexes::new()
+            .with(Index::new("key"))
+            .with(Index::new("value").with_option(IndexOption::Unique))
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Key {
     pub key: String,
@@ -48,7 +67,9 @@ impl mongodm::Model for KeyIdentifier {
     }
 
     fn indexes() -> Indexes {
-        Indexes::new().with(Index::new("key").with_option(IndexOption::Unique))
+        Indexes::new()
+            .with(Index::new("key").with_option(IndexOption::Unique))
+            .with(Index::new("value"))
     }
 }
 
@@ -68,6 +89,47 @@ impl mongodm::Model for Certificate {
     }
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Expiry {
+    pub key: String,
+    pub not_after: i64,
+    pub expired: bool,
+}
+
+impl mongodm::Model for Expiry {
+    fn coll_name() -> &'static str {
+        "expiry_store"
+    }
+
+    fn indexes() -> Indexes {
+        Indexes::new().with(Index::new("key").with_option(IndexOption::Unique))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Metadata {
+    pub key: String,
+    pub subject: String,
+    pub serial_number: String,
+    pub ski: Option<String>,
+    pub aki: Option<String>,
+    pub not_before: i64,
+    pub not_after: i64,
+    pub issued_at: i64,
+}
+
+impl mongodm::Model for Metadata {
+    fn coll_name() -> &'static str {
+        "metadata_store"
+    }
+
+    fn indexes() -> Indexes {
+        Indexes::new()
+            .with(Index::new("key").with_option(IndexOption::Unique))
+            .with(Index::new("not_after"))
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Config {
     #[serde(rename = "_id")]
@@ -93,6 +155,67 @@ impl mongodm::Model for HashLookupEntry {
     }
 
     fn indexes() -> Indexes {
-        Indexes::new().with(Index::new("key").with_option(IndexOption::Unique))
+        Indexes::new()
+            .with(Index::new("key").with_option(IndexOption::Unique))
+            .with(Index::new("value"))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Revocation {
+    pub key: String,
+    pub issuer_name: String,
+    pub serial_number: String,
+    pub revocation_date: i64,
+    pub reason: Option<i32>,
+}
+
+impl mongodm::Model for Revocation {
+    fn coll_name() -> &'static str {
+        "revocation_store"
+    }
+
+    fn indexes() -> Indexes {
+        Indexes::new()
+            .with(Index::new("key").with_option(IndexOption::Unique))
+            .with(Index::new("issuer_name"))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AuditRecord {
+    pub timestamp: i64,
+    pub event: AuditEvent,
+    pub requester: String,
+    pub subject: Option<String>,
+    pub sans: Vec<String>,
+    pub serial_number: Option<String>,
+    pub addressing_hash: Option<String>,
+    pub reason: Option<String>,
+}
+
+impl mongodm::Model for AuditRecord {
+    fn coll_name() -> &'static str {
+        "audit_log"
+    }
+
+    fn indexes() -> Indexes {
+        Indexes::new().with(Index::new("timestamp"))
+    }
+}
+
+/// Single collection used by the first Picky versions, holding PEM-encoded certificates and keys
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LegacyCertificate {
+    pub cert: String,
+    #[serde(default)]
+    pub common_name: Option<String>,
+    #[serde(default)]
+    pub key: Option<String>,
+}
+
+impl mongodm::Model for LegacyCertificate {
+    fn coll_name() -> &'static str {
+        "certificate"
     }
 }
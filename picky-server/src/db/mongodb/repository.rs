@@ -0,0 +1,117 @@
+use futures::stream::{BoxStream, StreamExt};
+use mongodm::{
+    mongo::{
+        bson::{self, Bson, Document},
+        error::Result,
+        options::{
+            CountOptions, DeleteOptions, DropCollectionOptions, EstimatedDocumentCountOptions, FindOneOptions,
+            FindOptions, InsertOneOptions, ReplaceOptions,
+        },
+        results::{DeleteResult, InsertOneResult, UpdateResult},
+        Collection, Database,
+    },
+    Model,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::marker::PhantomData;
+
+/// Name of the collection holding `M` documents once the configured prefix is applied
+pub fn collection_name<M: Model>(prefix: &str) -> String {
+    format!("{}{}", prefix, M::coll_name())
+}
+
+/// Collection of `M` documents, named after `M::coll_name` behind a prefix so that
+/// several Picky instances can share a database
+pub struct Repository<M> {
+    collection: Collection,
+    _model: PhantomData<M>,
+}
+
+impl<M: Model + Send + 'static> Repository<M> {
+    pub fn new(db: &Database, prefix: &str) -> Self {
+        Self {
+            collection: db.collection(&collection_name::<M>(prefix)),
+            _model: PhantomData,
+        }
+    }
+
+    pub async fn find_one(
+        &self,
+        filter: impl Into<Option<Document>>,
+        options: impl Into<Option<FindOneOptions>>,
+    ) -> Result<Option<M>> {
+        match self.collection.find_one(filter, options).await? {
+            Some(document) => Ok(Some(from_document(document)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn find(
+        &self,
+        filter: impl Into<Option<Document>>,
+        options: impl Into<Option<FindOptions>>,
+    ) -> Result<BoxStream<'static, Result<M>>> {
+        let cursor = self.collection.find(filter, options).await?;
+        Ok(cursor
+            .map(|document| -> Result<M> { from_document(document?) })
+            .boxed())
+    }
+
+    pub async fn insert_one(&self, model: &M, options: impl Into<Option<InsertOneOptions>>) -> Result<InsertOneResult> {
+        self.collection.insert_one(to_document(model)?, options).await
+    }
+
+    pub async fn replace_one(
+        &self,
+        query: Document,
+        model: &M,
+        options: impl Into<Option<ReplaceOptions>>,
+    ) -> Result<UpdateResult> {
+        self.collection.replace_one(query, to_document(model)?, options).await
+    }
+
+    pub async fn delete_one(&self, query: Document, options: impl Into<Option<DeleteOptions>>) -> Result<DeleteResult> {
+        self.collection.delete_one(query, options).await
+    }
+
+    pub async fn delete_many(
+        &self,
+        query: Document,
+        options: impl Into<Option<DeleteOptions>>,
+    ) -> Result<DeleteResult> {
+        self.collection.delete_many(query, options).await
+    }
+
+    pub async fn count_documents(
+        &self,
+        filter: impl Into<Option<Document>>,
+        options: impl Into<Option<CountOptions>>,
+    ) -> Result<i64> {
+        self.collection.count_documents(filter, options).await
+    }
+
+    pub async fn estimated_document_count(
+        &self,
+        options: impl Into<Option<EstimatedDocumentCountOptions>>,
+    ) -> Result<i64> {
+        self.collection.estimated_document_count(options).await
+    }
+
+    pub async fn drop(&self, options: impl Into<Option<DropCollectionOptions>>) -> Result<()> {
+        self.collection.drop(options).await
+    }
+}
+
+fn from_document<M: DeserializeOwned>(document: Document) -> Result<M> {
+    Ok(bson::from_bson(Bson::Document(document))?)
+}
+
+fn to_document<M: Serialize>(model: &M) -> Result<Document> {
+    match bson::to_bson(model)? {
+        Bson::Document(document) => Ok(document),
+        other => Err(bson::ser::Error::SerializationError {
+            message: format!("expected a document, got {:?}", other.element_type()),
+        }
+        .into()),
+    }
+}
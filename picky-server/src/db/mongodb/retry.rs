@@ -0,0 +1,150 @@
+use std::{
+    fmt::Display,
+    future::Future,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Longest delay between two attempts
+const MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// Exponential backoff policy for operations against an unreachable database
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    /// Total number of attempts, including the first one
+    pub attempts: u32,
+    /// Delay before the first retry, doubled for every following retry
+    pub base_delay: Duration,
+}
+
+impl Backoff {
+    /// Delay before the given retry (starting at 1), with up to 50% of random jitter
+    /// so that several instances don't hit the database at the same time
+    pub fn delay(&self, retry: u32) -> Duration {
+        let delay = self
+            .base_delay
+            .checked_mul(1 << retry.saturating_sub(1).min(16))
+            .unwrap_or(MAX_DELAY)
+            .min(MAX_DELAY);
+
+        // clock sub-second noise is random enough to spread retries
+        let noise = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|now| now.subsec_nanos())
+            .unwrap_or_default();
+        let jitter = delay.mul_f64(f64::from(noise % 1000) / 2000.0);
+
+        delay + jitter
+    }
+}
+
+/// Runs `operation` until it succeeds or the backoff policy is exhausted, returning the last error
+pub async fn retry_with_backoff<T, E, F, Fut>(backoff: &Backoff, what: &str, mut operation: F) -> Result<T, E>
+where
+    E: Display,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let mut attempt = 1;
+    loop {
+        match operation().await {
+            Ok(value) => return Ok(value),
+            Err(e) if attempt >= backoff.attempts => return Err(e),
+            Err(e) => {
+                let delay = backoff.delay(attempt);
+                log::warn!(
+                    "{} failed (attempt {}/{}), retrying in {:?}: {}",
+                    what,
+                    attempt,
+                    backoff.attempts,
+                    delay,
+                    e
+                );
+                tokio::time::delay_for(delay).await;
+                attempt += 1;
+            }
+        }
+    }
+}
+
+/// Runs an idempotent `operation` a second time should the first attempt fail,
+/// the driver reconnecting in the meantime
+pub async fn retry_once<T, E, F, Fut>(what: &str, mut operation: F) -> Result<T, E>
+where
+    E: Display,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    match operation().await {
+        Ok(value) => Ok(value),
+        Err(e) => {
+            log::warn!("{} failed, retrying once: {}", what, e);
+            operation().await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use tokio_test::block_on;
+
+    /// Fails the first `failures` calls
+    fn flaky(calls: &Cell<u32>, failures: u32) -> impl Future<Output = Result<u32, String>> {
+        calls.set(calls.get() + 1);
+        let call = calls.get();
+        async move {
+            if call <= failures {
+                Err(format!("transient failure {}", call))
+            } else {
+                Ok(call)
+            }
+        }
+    }
+
+    #[test]
+    fn read_succeeds_after_one_retry() {
+        let calls = Cell::new(0);
+        assert_eq!(block_on(retry_once("read", || flaky(&calls, 1))), Ok(2));
+        assert_eq!(calls.get(), 2);
+
+        let calls = Cell::new(0);
+        assert!(block_on(retry_once("read", || flaky(&calls, 2))).is_err());
+        assert_eq!(calls.get(), 2);
+    }
+
+    #[test]
+    fn backoff_gives_up() {
+        let backoff = Backoff {
+            attempts: 4,
+            base_delay: Duration::from_millis(1),
+        };
+
+        let calls = Cell::new(0);
+        assert_eq!(
+            block_on(retry_with_backoff(&backoff, "connect", || flaky(&calls, 2))),
+            Ok(3)
+        );
+
+        let calls = Cell::new(0);
+        assert_eq!(
+            block_on(retry_with_backoff(&backoff, "connect", || flaky(&calls, u32::MAX))),
+            Err("transient failure 4".to_owned())
+        );
+        assert_eq!(calls.get(), 4);
+    }
+
+    #[test]
+    fn delay_grows_exponentially() {
+        let backoff = Backoff {
+            attempts: 10,
+            base_delay: Duration::from_millis(100),
+        };
+        for retry in 1..8 {
+            let base = Duration::from_millis(100 * (1 << (retry - 1)));
+            let delay = backoff.delay(retry);
+            assert!(delay >= base && delay <= base.mul_f64(1.5), "{:?}", delay);
+        }
+        assert!(backoff.delay(40) <= MAX_DELAY.mul_f64(1.5));
+    }
+}
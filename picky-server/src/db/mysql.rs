@@ -0,0 +1,849 @@
+use crate::{
+    addressing::{encode_to_alternative_addresses, encode_to_canonical_address},
+    db::{
+        resolve_conflict, AuditEntry, CertificateEntry, CertificateExpiry, ConflictPolicy, IssuanceMetadata,
+        ListFilter, ListedCertificate, PickyStorage, RevocationEntry, StorageError, StoreOutcome, SCHEMA_LAST_VERSION,
+    },
+};
+use chrono::Utc;
+use futures::{future::BoxFuture, FutureExt};
+use mysql_async::{prelude::Queryable, Opts, Pool, TxOpts, Value};
+use std::convert::TryFrom;
+use thiserror::Error;
+
+const SCHEMA: &[&str] = &[
+    "CREATE TABLE IF NOT EXISTS picky_config (
+        schema_version SMALLINT NOT NULL
+    )",
+    "CREATE TABLE IF NOT EXISTS certificate (
+        addressing_hash VARCHAR(255) NOT NULL PRIMARY KEY,
+        der BLOB NOT NULL,
+        not_after BIGINT,
+        expired BOOLEAN NOT NULL DEFAULT FALSE
+    )",
+    "CREATE TABLE IF NOT EXISTS private_key (
+        addressing_hash VARCHAR(255) NOT NULL PRIMARY KEY,
+        der BLOB NOT NULL
+    )",
+    "CREATE TABLE IF NOT EXISTS certificate_metadata (
+        addressing_hash VARCHAR(255) NOT NULL PRIMARY KEY,
+        subject TEXT NOT NULL,
+        serial_number VARCHAR(255) NOT NULL,
+        ski VARCHAR(255),
+        aki VARCHAR(255),
+        not_before BIGINT NOT NULL,
+        not_after BIGINT NOT NULL,
+        issued_at BIGINT NOT NULL,
+        INDEX (not_after)
+    )",
+    "CREATE TABLE IF NOT EXISTS certificate_name (
+        name VARCHAR(255) NOT NULL PRIMARY KEY,
+        addressing_hash VARCHAR(255) NOT NULL,
+        INDEX (addressing_hash)
+    )",
+    "CREATE TABLE IF NOT EXISTS name_history (
+        id BIGINT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+        name VARCHAR(255) NOT NULL,
+        addressing_hash VARCHAR(255) NOT NULL,
+        UNIQUE (name, addressing_hash),
+        INDEX (addressing_hash)
+    )",
+    "CREATE TABLE IF NOT EXISTS key_identifier (
+        key_identifier VARCHAR(255) NOT NULL PRIMARY KEY,
+        addressing_hash VARCHAR(255) NOT NULL,
+        INDEX (addressing_hash)
+    )",
+    "CREATE TABLE IF NOT EXISTS hash_lookup (
+        lookup_key VARCHAR(255) NOT NULL PRIMARY KEY,
+        addressing_hash VARCHAR(255) NOT NULL,
+        INDEX (addressing_hash)
+    )",
+    "CREATE TABLE IF NOT EXISTS revocation (
+        addressing_hash VARCHAR(255) NOT NULL PRIMARY KEY,
+        issuer_name VARCHAR(255) NOT NULL,
+        serial_number VARCHAR(255) NOT NULL,
+        revocation_date BIGINT NOT NULL,
+        reason TINYINT UNSIGNED,
+        INDEX (issuer_name)
+    )",
+    "CREATE TABLE IF NOT EXISTS audit (
+        id BIGINT NOT NULL AUTO_INCREMENT PRIMARY KEY,
+        timestamp BIGINT NOT NULL,
+        entry TEXT NOT NULL,
+        INDEX (timestamp)
+    )",
+];
+
+#[derive(Debug, Error)]
+pub enum MysqlStorageError {
+    #[error("mysql error: {}", source)]
+    Mysql { source: mysql_async::Error },
+
+    #[error("generic error: {}", description)]
+    Other { description: String },
+}
+
+impl From<String> for MysqlStorageError {
+    fn from(description: String) -> Self {
+        MysqlStorageError::Other { description }
+    }
+}
+
+impl From<mysql_async::Error> for MysqlStorageError {
+    fn from(source: mysql_async::Error) -> Self {
+        MysqlStorageError::Mysql { source }
+    }
+}
+
+impl From<mysql_async::Error> for StorageError {
+    fn from(source: mysql_async::Error) -> Self {
+        MysqlStorageError::Mysql { source }.into()
+    }
+}
+
+fn not_found(description: &str) -> StorageError {
+    MysqlStorageError::Other {
+        description: description.to_owned(),
+    }
+    .into()
+}
+
+/// Prepared statement along with its positional parameters.
+///
+/// Names, hashes and any other value always go through parameters, never through the statement text.
+#[derive(Debug, Clone, PartialEq)]
+struct Query {
+    statement: &'static str,
+    params: Vec<Value>,
+}
+
+impl Query {
+    fn new(statement: &'static str, params: Vec<Value>) -> Self {
+        Self { statement, params }
+    }
+
+    async fn exec_drop<Q: Queryable + Send>(self, queryable: &mut Q) -> Result<(), mysql_async::Error> {
+        queryable.exec_drop(self.statement, self.params).await
+    }
+}
+
+/// Every write performed by `store`, in order, idempotent when replayed with the same entry
+fn store_queries(
+    entry: &CertificateEntry,
+    addressing_hash: &str,
+    alternative_addresses: &[String],
+    expiry: &CertificateExpiry,
+    metadata: &IssuanceMetadata,
+) -> Vec<Query> {
+    let mut queries = vec![
+        Query::new(
+            "INSERT INTO certificate (addressing_hash, der, not_after, expired) VALUES (?, ?, ?, FALSE)
+             ON DUPLICATE KEY UPDATE der = VALUES(der), not_after = VALUES(not_after), expired = FALSE",
+            vec![
+                addressing_hash.into(),
+                entry.cert.clone().into(),
+                expiry.not_after.into(),
+            ],
+        ),
+        Query::new(
+            "INSERT INTO certificate_name (name, addressing_hash) VALUES (?, ?)
+             ON DUPLICATE KEY UPDATE addressing_hash = VALUES(addressing_hash)",
+            vec![entry.name.as_str().into(), addressing_hash.into()],
+        ),
+        Query::new(
+            "INSERT IGNORE INTO name_history (name, addressing_hash) VALUES (?, ?)",
+            vec![entry.name.as_str().into(), addressing_hash.into()],
+        ),
+        Query::new(
+            "INSERT INTO key_identifier (key_identifier, addressing_hash) VALUES (?, ?)
+             ON DUPLICATE KEY UPDATE addressing_hash = VALUES(addressing_hash)",
+            vec![entry.key_identifier.as_str().into(), addressing_hash.into()],
+        ),
+        metadata_query(addressing_hash, metadata),
+    ];
+
+    for alternative_address in alternative_addresses {
+        queries.push(Query::new(
+            "INSERT INTO hash_lookup (lookup_key, addressing_hash) VALUES (?, ?)
+             ON DUPLICATE KEY UPDATE addressing_hash = VALUES(addressing_hash)",
+            vec![alternative_address.as_str().into(), addressing_hash.into()],
+        ));
+    }
+
+    if let Some(key) = &entry.key {
+        queries.push(Query::new(
+            "INSERT INTO private_key (addressing_hash, der) VALUES (?, ?)
+             ON DUPLICATE KEY UPDATE der = VALUES(der)",
+            vec![addressing_hash.into(), key.clone().into()],
+        ));
+    }
+
+    queries
+}
+
+fn metadata_query(addressing_hash: &str, metadata: &IssuanceMetadata) -> Query {
+    Query::new(
+        "INSERT INTO certificate_metadata
+             (addressing_hash, subject, serial_number, ski, aki, not_before, not_after, issued_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+         ON DUPLICATE KEY UPDATE subject = VALUES(subject), serial_number = VALUES(serial_number),
+             ski = VALUES(ski), aki = VALUES(aki), not_before = VALUES(not_before),
+             not_after = VALUES(not_after), issued_at = VALUES(issued_at)",
+        vec![
+            addressing_hash.into(),
+            metadata.subject.as_str().into(),
+            metadata.serial_number.as_str().into(),
+            metadata.ski.as_deref().into(),
+            metadata.aki.as_deref().into(),
+            metadata.not_before.into(),
+            metadata.not_after.into(),
+            metadata.issued_at.into(),
+        ],
+    )
+}
+
+/// Every write performed by `delete` once the certificate is known to exist
+fn delete_queries(addressing_hash: &str) -> Vec<Query> {
+    let hash_param = || vec![Value::from(addressing_hash)];
+    vec![
+        Query::new("DELETE FROM certificate WHERE addressing_hash = ?", hash_param()),
+        Query::new("DELETE FROM private_key WHERE addressing_hash = ?", hash_param()),
+        Query::new(
+            "DELETE FROM certificate_metadata WHERE addressing_hash = ?",
+            hash_param(),
+        ),
+        Query::new("DELETE FROM key_identifier WHERE addressing_hash = ?", hash_param()),
+        Query::new("DELETE FROM hash_lookup WHERE addressing_hash = ?", hash_param()),
+        Query::new("DELETE FROM name_history WHERE addressing_hash = ?", hash_param()),
+        // names move back to the previous certificate stored under them, if any
+        Query::new(
+            "UPDATE certificate_name SET addressing_hash = (
+                 SELECT history.addressing_hash FROM name_history history
+                 WHERE history.name = certificate_name.name ORDER BY history.id DESC LIMIT 1
+             )
+             WHERE addressing_hash = ?
+             AND EXISTS (SELECT 1 FROM name_history history WHERE history.name = certificate_name.name)",
+            hash_param(),
+        ),
+        Query::new("DELETE FROM certificate_name WHERE addressing_hash = ?", hash_param()),
+    ]
+}
+
+fn revoke_query(entry: &RevocationEntry) -> Query {
+    Query::new(
+        "INSERT INTO revocation (addressing_hash, issuer_name, serial_number, revocation_date, reason)
+         VALUES (?, ?, ?, ?, ?)
+         ON DUPLICATE KEY UPDATE issuer_name = VALUES(issuer_name), serial_number = VALUES(serial_number),
+             revocation_date = VALUES(revocation_date), reason = VALUES(reason)",
+        vec![
+            entry.addressing_hash.as_str().into(),
+            entry.issuer_name.as_str().into(),
+            entry.serial_number.as_str().into(),
+            entry.revocation_date.into(),
+            entry.reason.into(),
+        ],
+    )
+}
+
+type ListedRow = (String, Vec<u8>, Option<i64>, bool);
+
+fn listed_from_row(row: ListedRow) -> ListedCertificate {
+    let (addressing_hash, cert, not_after, expired) = row;
+    ListedCertificate {
+        addressing_hash,
+        cert,
+        expiry: not_after.map(|not_after| CertificateExpiry { not_after, expired }),
+    }
+}
+
+type MetadataRow = (String, String, Option<String>, Option<String>, i64, i64, i64);
+
+fn metadata_from_row(row: MetadataRow) -> IssuanceMetadata {
+    let (subject, serial_number, ski, aki, not_before, not_after, issued_at) = row;
+    IssuanceMetadata {
+        subject,
+        serial_number,
+        ski,
+        aki,
+        not_before,
+        not_after,
+        issued_at,
+    }
+}
+
+type RevocationRow = (String, String, String, i64, Option<u8>);
+
+fn revocation_from_row(row: RevocationRow) -> RevocationEntry {
+    let (addressing_hash, issuer_name, serial_number, revocation_date, reason) = row;
+    RevocationEntry {
+        addressing_hash,
+        issuer_name,
+        serial_number,
+        revocation_date,
+        reason,
+    }
+}
+
+/// Storage backed by MySQL (or MariaDB) tables, DER being stored as is in `BLOB` columns
+pub struct MysqlStorage {
+    pool: Pool,
+}
+
+impl MysqlStorage {
+    /// Connects to the database and creates the schema when missing
+    pub async fn new(database_url: &str) -> Result<Self, MysqlStorageError> {
+        let opts = Opts::from_url(database_url).map_err(|e| MysqlStorageError::Other {
+            description: format!("invalid mysql database url: {}", e),
+        })?;
+        let storage = Self { pool: Pool::new(opts) };
+        storage.init().await?;
+        Ok(storage)
+    }
+
+    async fn init(&self) -> Result<(), MysqlStorageError> {
+        let mut conn = self.pool.get_conn().await?;
+        for statement in SCHEMA {
+            conn.query_drop(*statement).await?;
+        }
+
+        let version: Option<i16> = conn.query_first("SELECT schema_version FROM picky_config").await?;
+        match version {
+            Some(version) if version == i16::from(SCHEMA_LAST_VERSION) => {
+                log::info!("detected database using supported v{} schema", version);
+            }
+            Some(unsupported) => {
+                return Err(MysqlStorageError::Other {
+                    description: format!("unsupported schema version: v{}", unsupported),
+                })
+            }
+            None => {
+                log::info!("fresh new database using v{} schema", SCHEMA_LAST_VERSION);
+                conn.exec_drop(
+                    "INSERT INTO picky_config (schema_version) VALUES (?)",
+                    (i16::from(SCHEMA_LAST_VERSION),),
+                )
+                .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn query_one_column<T>(&self, statement: &str, param: &str, what: &str) -> Result<T, StorageError>
+    where
+        T: mysql_async::prelude::FromRow + Send + 'static,
+    {
+        let mut conn = self.pool.get_conn().await?;
+        conn.exec_first(statement, (param,))
+            .await?
+            .ok_or_else(|| not_found(what))
+    }
+}
+
+impl PickyStorage for MysqlStorage {
+    fn ping(&self) -> BoxFuture<'_, Result<(), StorageError>> {
+        async move {
+            let mut conn = self.pool.get_conn().await?;
+            conn.ping().await.map_err(|e| MysqlStorageError::Other {
+                description: format!("ping to mysql connection failed: {}", e),
+            })?;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn close(&self) -> BoxFuture<'_, Result<(), StorageError>> {
+        // every write is committed before returning, connections are closed when the pool is dropped
+        async move { Ok(()) }.boxed()
+    }
+
+    fn store(
+        &self,
+        entry: CertificateEntry,
+        policy: ConflictPolicy,
+    ) -> BoxFuture<'_, Result<StoreOutcome, StorageError>> {
+        async move {
+            let addressing_hash = encode_to_canonical_address(&entry.cert);
+
+            let alternative_addresses =
+                encode_to_alternative_addresses(&entry.cert).map_err(|e| MysqlStorageError::Other {
+                    description: format!("couldn't encode alternative addresses: {}", e),
+                })?;
+            let expiry = CertificateExpiry::from_der(&entry.cert).map_err(MysqlStorageError::from)?;
+            let metadata = IssuanceMetadata::from_der(&entry.cert, Some(Utc::now().timestamp()))
+                .map_err(MysqlStorageError::from)?;
+
+            let mut conn = self.pool.get_conn().await?;
+            let mut transaction = conn.start_transaction(TxOpts::default()).await?;
+
+            let existing: Option<u8> = transaction
+                .exec_first(
+                    "SELECT 1 FROM certificate WHERE addressing_hash = ?",
+                    (addressing_hash.as_str(),),
+                )
+                .await?;
+            let outcome = match resolve_conflict(policy, &addressing_hash, existing.is_some()) {
+                Ok((outcome, true)) => outcome,
+                resolved => {
+                    transaction.rollback().await?;
+                    return resolved.map(|(outcome, _)| outcome);
+                }
+            };
+            for query in store_queries(&entry, &addressing_hash, &alternative_addresses, &expiry, &metadata) {
+                query.exec_drop(&mut transaction).await?;
+            }
+            transaction.commit().await?;
+
+            Ok(outcome)
+        }
+        .boxed()
+    }
+
+    fn get_cert_by_addressing_hash<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<Vec<u8>, StorageError>> {
+        self.query_one_column(
+            "SELECT der FROM certificate WHERE addressing_hash = ?",
+            hash,
+            "cert not found",
+        )
+        .boxed()
+    }
+
+    fn get_key_by_addressing_hash<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<Vec<u8>, StorageError>> {
+        self.query_one_column(
+            "SELECT der FROM private_key WHERE addressing_hash = ?",
+            hash,
+            "key not found",
+        )
+        .boxed()
+    }
+
+    fn get_addressing_hash_by_name<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<String, StorageError>> {
+        async move {
+            self.query_one_column(
+                "SELECT addressing_hash FROM certificate_name WHERE name = ?",
+                name,
+                &format!("hash not found using name {}", name),
+            )
+            .await
+        }
+        .boxed()
+    }
+
+    fn get_addressing_hashes_by_name<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<Vec<String>, StorageError>> {
+        async move {
+            let mut conn = self.pool.get_conn().await?;
+            Ok(conn
+                .exec(
+                    "SELECT addressing_hash FROM name_history WHERE name = ? ORDER BY id",
+                    (name,),
+                )
+                .await?)
+        }
+        .boxed()
+    }
+
+    fn get_addressing_hash_by_key_identifier<'a>(
+        &'a self,
+        key_identifier: &'a str,
+    ) -> BoxFuture<'a, Result<String, StorageError>> {
+        self.query_one_column(
+            "SELECT addressing_hash FROM key_identifier WHERE key_identifier = ?",
+            key_identifier,
+            "hash not found",
+        )
+        .boxed()
+    }
+
+    fn lookup_addressing_hash<'a>(&'a self, lookup_key: &'a str) -> BoxFuture<'a, Result<String, StorageError>> {
+        self.query_one_column(
+            "SELECT addressing_hash FROM hash_lookup WHERE lookup_key = ?",
+            lookup_key,
+            "hash not found",
+        )
+        .boxed()
+    }
+
+    fn get_revocations_by_issuer<'a>(
+        &'a self,
+        issuer_name: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<RevocationEntry>, StorageError>> {
+        async move {
+            let mut conn = self.pool.get_conn().await?;
+            let rows: Vec<RevocationRow> = conn
+                .exec(
+                    "SELECT addressing_hash, issuer_name, serial_number, revocation_date, reason
+                     FROM revocation WHERE issuer_name = ?",
+                    (issuer_name,),
+                )
+                .await?;
+            Ok(rows.into_iter().map(revocation_from_row).collect())
+        }
+        .boxed()
+    }
+
+    fn revoke(&self, entry: RevocationEntry) -> BoxFuture<'_, Result<(), StorageError>> {
+        async move {
+            let mut conn = self.pool.get_conn().await?;
+            revoke_query(&entry).exec_drop(&mut conn).await?;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn list<'a>(
+        &'a self,
+        filter: &'a ListFilter,
+        offset: usize,
+        limit: usize,
+    ) -> BoxFuture<'a, Result<Vec<ListedCertificate>, StorageError>> {
+        async move {
+            let mut conn = self.pool.get_conn().await?;
+
+            // without filter, pagination is left to the database
+            if *filter == ListFilter::default() {
+                let rows: Vec<ListedRow> = conn
+                    .exec(
+                        "SELECT addressing_hash, der, not_after, expired FROM certificate ORDER BY addressing_hash
+                         LIMIT ? OFFSET ?",
+                        (limit as u64, offset as u64),
+                    )
+                    .await?;
+                return Ok(rows.into_iter().map(listed_from_row).collect());
+            }
+
+            let rows: Vec<ListedRow> = conn
+                .query("SELECT addressing_hash, der, not_after, expired FROM certificate ORDER BY addressing_hash")
+                .await?;
+
+            Ok(rows
+                .into_iter()
+                .map(listed_from_row)
+                .filter(|listed| filter.matches(&listed.cert, listed.expiry.as_ref()))
+                .skip(offset)
+                .take(limit)
+                .collect())
+        }
+        .boxed()
+    }
+
+    fn count(&self) -> BoxFuture<'_, Result<usize, StorageError>> {
+        async move {
+            let mut conn = self.pool.get_conn().await?;
+            let count: Option<u64> = conn.query_first("SELECT COUNT(*) FROM certificate").await?;
+            Ok(count.unwrap_or_default() as usize)
+        }
+        .boxed()
+    }
+
+    fn get_revocation_by_addressing_hash<'a>(
+        &'a self,
+        hash: &'a str,
+    ) -> BoxFuture<'a, Result<Option<RevocationEntry>, StorageError>> {
+        async move {
+            let mut conn = self.pool.get_conn().await?;
+            let row: Option<RevocationRow> = conn
+                .exec_first(
+                    "SELECT addressing_hash, issuer_name, serial_number, revocation_date, reason
+                     FROM revocation WHERE addressing_hash = ?",
+                    (hash,),
+                )
+                .await?;
+            Ok(row.map(revocation_from_row))
+        }
+        .boxed()
+    }
+
+    fn get_expiry<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<Option<CertificateExpiry>, StorageError>> {
+        async move {
+            let mut conn = self.pool.get_conn().await?;
+            let row: Option<(Option<i64>, bool)> = conn
+                .exec_first(
+                    "SELECT not_after, expired FROM certificate WHERE addressing_hash = ?",
+                    (hash,),
+                )
+                .await?;
+            Ok(
+                row.and_then(|(not_after, expired)| {
+                    not_after.map(|not_after| CertificateExpiry { not_after, expired })
+                }),
+            )
+        }
+        .boxed()
+    }
+
+    fn set_expired<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<(), StorageError>> {
+        async move {
+            let mut conn = self.pool.get_conn().await?;
+            // affected rows don't account for rows already flagged, existence is checked beforehand
+            let exists: Option<u8> = conn
+                .exec_first("SELECT 1 FROM certificate WHERE addressing_hash = ?", (hash,))
+                .await?;
+            if exists.is_none() {
+                return Err(not_found("cert not found"));
+            }
+
+            conn.exec_drop(
+                "UPDATE certificate SET expired = TRUE WHERE addressing_hash = ?",
+                (hash,),
+            )
+            .await?;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn get_metadata<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<Option<IssuanceMetadata>, StorageError>> {
+        async move {
+            let mut conn = self.pool.get_conn().await?;
+            let row: Option<MetadataRow> = conn
+                .exec_first(
+                    "SELECT subject, serial_number, ski, aki, not_before, not_after, issued_at
+                     FROM certificate_metadata WHERE addressing_hash = ?",
+                    (hash,),
+                )
+                .await?;
+            Ok(row.map(metadata_from_row))
+        }
+        .boxed()
+    }
+
+    fn set_metadata<'a>(
+        &'a self,
+        hash: &'a str,
+        metadata: IssuanceMetadata,
+    ) -> BoxFuture<'a, Result<(), StorageError>> {
+        async move {
+            let mut conn = self.pool.get_conn().await?;
+            metadata_query(hash, &metadata).exec_drop(&mut conn).await?;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn find_expiring_before(&self, not_after: i64) -> BoxFuture<'_, Result<Vec<String>, StorageError>> {
+        async move {
+            let mut conn = self.pool.get_conn().await?;
+            let hashes = conn
+                .exec(
+                    "SELECT addressing_hash FROM certificate_metadata WHERE not_after < ?
+                     ORDER BY not_after, addressing_hash",
+                    (not_after,),
+                )
+                .await?;
+            Ok(hashes)
+        }
+        .boxed()
+    }
+
+    fn delete<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<(), StorageError>> {
+        async move {
+            let mut conn = self.pool.get_conn().await?;
+            let mut transaction = conn.start_transaction(TxOpts::default()).await?;
+
+            let exists: Option<u8> = transaction
+                .exec_first(
+                    "SELECT 1 FROM certificate WHERE addressing_hash = ? FOR UPDATE",
+                    (hash,),
+                )
+                .await?;
+            if exists.is_none() {
+                return Err(not_found("cert not found"));
+            }
+
+            for query in delete_queries(hash) {
+                query.exec_drop(&mut transaction).await?;
+            }
+            transaction.commit().await?;
+
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn append_audit(&self, entry: AuditEntry) -> BoxFuture<'_, Result<(), StorageError>> {
+        async move {
+            let json = serde_json::to_string(&entry).map_err(|e| MysqlStorageError::Other {
+                description: format!("couldn't encode audit entry: {}", e),
+            })?;
+            let mut conn = self.pool.get_conn().await?;
+            conn.exec_drop(
+                "INSERT INTO audit (timestamp, entry) VALUES (?, ?)",
+                (entry.timestamp, json),
+            )
+            .await?;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn list_audit(&self, since: i64, limit: usize) -> BoxFuture<'_, Result<Vec<AuditEntry>, StorageError>> {
+        async move {
+            let limit = u64::try_from(limit).unwrap_or(u64::MAX);
+            let mut conn = self.pool.get_conn().await?;
+            let entries: Vec<String> = conn
+                .exec(
+                    "SELECT entry FROM audit WHERE timestamp >= ? ORDER BY id LIMIT ?",
+                    (since, limit),
+                )
+                .await?;
+
+            entries
+                .iter()
+                .map(|json| {
+                    serde_json::from_str(json).map_err(|e| {
+                        StorageError::from(MysqlStorageError::Other {
+                            description: format!("couldn't decode audit entry: {}", e),
+                        })
+                    })
+                })
+                .collect()
+        }
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_test::block_on;
+
+    const TEST_DATABASE_URL_ENV: &str = "PICKY_TEST_MYSQL_URL";
+
+    fn entry(name: &str, key: Option<Vec<u8>>) -> CertificateEntry {
+        CertificateEntry {
+            name: name.to_owned(),
+            cert: vec![0x30, 0x03, 0x02, 0x01, 0x01],
+            key_identifier: "0a1b2c".to_owned(),
+            key,
+        }
+    }
+
+    fn metadata() -> IssuanceMetadata {
+        IssuanceMetadata {
+            subject: "CN=leaf".to_owned(),
+            serial_number: "01".to_owned(),
+            ski: Some("0a1b2c".to_owned()),
+            aki: None,
+            not_before: 1_500_000_000,
+            not_after: 1_600_000_000,
+            issued_at: 1_500_000_000,
+        }
+    }
+
+    #[test]
+    fn values_never_reach_statements() {
+        let name = "evil'); DROP TABLE certificate; --";
+        let hash = "hash' OR '1'='1";
+        let expiry = CertificateExpiry {
+            not_after: 1_600_000_000,
+            expired: false,
+        };
+        let alternative_addresses = vec!["alternative'--".to_owned()];
+
+        let mut queries = store_queries(
+            &entry(name, Some(vec![1, 2, 3])),
+            hash,
+            &alternative_addresses,
+            &expiry,
+            &metadata(),
+        );
+        queries.extend(delete_queries(hash));
+        queries.push(revoke_query(&RevocationEntry {
+            addressing_hash: hash.to_owned(),
+            issuer_name: name.to_owned(),
+            serial_number: "01".to_owned(),
+            revocation_date: 1_600_000_000,
+            reason: None,
+        }));
+
+        for query in queries {
+            for value in &[name, hash, "alternative"] {
+                assert!(!query.statement.contains(value), "{} in {}", value, query.statement);
+            }
+            assert_eq!(
+                query.statement.matches('?').count(),
+                query.params.len(),
+                "{}",
+                query.statement
+            );
+        }
+    }
+
+    #[test]
+    fn store_queries_are_idempotent_upserts() {
+        let expiry = CertificateExpiry {
+            not_after: 1_600_000_000,
+            expired: false,
+        };
+
+        let without_key = store_queries(
+            &entry("leaf", None),
+            "hash",
+            &["alternative".to_owned()],
+            &expiry,
+            &metadata(),
+        );
+        assert_eq!(without_key.len(), 6);
+        assert_eq!(
+            without_key[0].params,
+            vec![
+                Value::from("hash"),
+                Value::from(vec![0x30u8, 0x03, 0x02, 0x01, 0x01]),
+                Value::from(1_600_000_000i64),
+            ]
+        );
+        assert!(without_key[2].statement.starts_with("INSERT IGNORE"));
+
+        let with_key = store_queries(&entry("ca", Some(vec![1, 2, 3])), "hash", &[], &expiry, &metadata());
+        assert_eq!(with_key.len(), 6);
+        assert_eq!(
+            with_key.last().unwrap().params,
+            vec![Value::from("hash"), Value::from(vec![1u8, 2, 3])]
+        );
+
+        for query in without_key.iter().chain(with_key.iter()) {
+            assert!(
+                query.statement.contains("ON DUPLICATE KEY UPDATE") || query.statement.starts_with("INSERT IGNORE"),
+                "{}",
+                query.statement
+            );
+        }
+    }
+
+    #[test]
+    fn mysql_storage() {
+        let database_url = match std::env::var(TEST_DATABASE_URL_ENV) {
+            Ok(database_url) => database_url,
+            Err(_) => {
+                eprintln!("{} not set, skipping mysql integration tests", TEST_DATABASE_URL_ENV);
+                return;
+            }
+        };
+
+        block_on(async {
+            let storage = MysqlStorage::new(&database_url).await.expect("mysql storage");
+            let mut conn = storage.pool.get_conn().await.unwrap();
+            for table in &[
+                "certificate",
+                "private_key",
+                "certificate_metadata",
+                "certificate_name",
+                "name_history",
+                "key_identifier",
+                "hash_lookup",
+                "revocation",
+                "audit",
+            ] {
+                conn.query_drop(format!("TRUNCATE TABLE {}", table))
+                    .await
+                    .expect("couldn't clean database");
+            }
+            drop(conn);
+
+            crate::db::tests::check_storage(&storage).await;
+            crate::db::tests::check_pagination(&storage).await;
+            crate::db::tests::check_conflicts(&storage).await;
+            crate::db::tests::check_metadata(&storage).await;
+        });
+    }
+}
@@ -0,0 +1,764 @@
+use crate::{
+    addressing::{encode_to_alternative_addresses, encode_to_canonical_address},
+    db::{
+        resolve_conflict, AuditEntry, CertificateEntry, CertificateExpiry, ConflictPolicy, IssuanceMetadata,
+        ListFilter, ListedCertificate, PickyStorage, RevocationEntry, StorageError, StoreOutcome, SCHEMA_LAST_VERSION,
+    },
+};
+use chrono::Utc;
+use futures::{future::BoxFuture, FutureExt};
+use std::convert::TryFrom;
+use thiserror::Error;
+use tokio::sync::Mutex;
+use tokio_postgres::{Client, NoTls, Row};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS picky_config (
+    schema_version SMALLINT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS certificate (
+    addressing_hash TEXT PRIMARY KEY,
+    der BYTEA NOT NULL,
+    not_after BIGINT,
+    expired BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+CREATE TABLE IF NOT EXISTS private_key (
+    addressing_hash TEXT PRIMARY KEY REFERENCES certificate (addressing_hash) ON DELETE CASCADE,
+    der BYTEA NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS certificate_metadata (
+    addressing_hash TEXT PRIMARY KEY REFERENCES certificate (addressing_hash) ON DELETE CASCADE,
+    subject TEXT NOT NULL,
+    serial_number TEXT NOT NULL,
+    ski TEXT,
+    aki TEXT,
+    not_before BIGINT NOT NULL,
+    not_after BIGINT NOT NULL,
+    issued_at BIGINT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS certificate_metadata_not_after_idx ON certificate_metadata (not_after);
+
+CREATE TABLE IF NOT EXISTS certificate_name (
+    name TEXT PRIMARY KEY,
+    addressing_hash TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS certificate_name_addressing_hash_idx ON certificate_name (addressing_hash);
+
+CREATE TABLE IF NOT EXISTS name_history (
+    id BIGSERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    addressing_hash TEXT NOT NULL,
+    UNIQUE (name, addressing_hash)
+);
+CREATE INDEX IF NOT EXISTS name_history_addressing_hash_idx ON name_history (addressing_hash);
+
+CREATE TABLE IF NOT EXISTS key_identifier (
+    key_identifier TEXT PRIMARY KEY,
+    addressing_hash TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS key_identifier_addressing_hash_idx ON key_identifier (addressing_hash);
+
+CREATE TABLE IF NOT EXISTS hash_lookup (
+    lookup_key TEXT PRIMARY KEY,
+    addressing_hash TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS hash_lookup_addressing_hash_idx ON hash_lookup (addressing_hash);
+
+CREATE TABLE IF NOT EXISTS revocation (
+    addressing_hash TEXT PRIMARY KEY,
+    issuer_name TEXT NOT NULL,
+    serial_number TEXT NOT NULL,
+    revocation_date BIGINT NOT NULL,
+    reason SMALLINT
+);
+CREATE INDEX IF NOT EXISTS revocation_issuer_name_idx ON revocation (issuer_name);
+
+CREATE TABLE IF NOT EXISTS audit (
+    id BIGSERIAL PRIMARY KEY,
+    timestamp BIGINT NOT NULL,
+    entry TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS audit_timestamp_idx ON audit (timestamp);
+";
+
+#[derive(Debug, Error)]
+pub enum PostgresStorageError {
+    #[error("postgres error: {}", source)]
+    Postgres { source: tokio_postgres::Error },
+
+    #[error("generic error: {}", description)]
+    Other { description: String },
+}
+
+impl From<String> for PostgresStorageError {
+    fn from(description: String) -> Self {
+        PostgresStorageError::Other { description }
+    }
+}
+
+impl From<tokio_postgres::Error> for PostgresStorageError {
+    fn from(source: tokio_postgres::Error) -> Self {
+        PostgresStorageError::Postgres { source }
+    }
+}
+
+impl From<tokio_postgres::Error> for StorageError {
+    fn from(source: tokio_postgres::Error) -> Self {
+        StorageError::Postgres {
+            source: PostgresStorageError::Postgres { source },
+        }
+    }
+}
+
+fn not_found(description: &str) -> StorageError {
+    PostgresStorageError::Other {
+        description: description.to_owned(),
+    }
+    .into()
+}
+
+/// Storage backed by PostgreSQL tables, DER being stored as is in `bytea` columns.
+///
+/// Operations share a single connection: the lock keeps transactions from interleaving.
+pub struct PostgresStorage {
+    client: Mutex<Client>,
+}
+
+impl PostgresStorage {
+    /// Connects to the database and creates the schema when missing
+    pub async fn new(database_url: &str) -> Result<Self, PostgresStorageError> {
+        let (client, connection) = tokio_postgres::connect(database_url, NoTls).await?;
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                log::error!("postgres connection error: {}", e);
+            }
+        });
+
+        let storage = Self {
+            client: Mutex::new(client),
+        };
+        storage.init().await?;
+        Ok(storage)
+    }
+
+    async fn init(&self) -> Result<(), PostgresStorageError> {
+        let mut client = self.client.lock().await;
+        let transaction = client.transaction().await?;
+        transaction.batch_execute(SCHEMA).await?;
+
+        let rows = transaction
+            .query("SELECT schema_version FROM picky_config", &[])
+            .await?;
+        match rows.first().map(|row| row.get::<_, i16>(0)) {
+            Some(version) if version == i16::from(SCHEMA_LAST_VERSION) => {
+                log::info!("detected database using supported v{} schema", version);
+            }
+            Some(unsupported) => {
+                return Err(PostgresStorageError::Other {
+                    description: format!("unsupported schema version: v{}", unsupported),
+                })
+            }
+            None => {
+                log::info!("fresh new database using v{} schema", SCHEMA_LAST_VERSION);
+                transaction
+                    .execute(
+                        "INSERT INTO picky_config (schema_version) VALUES ($1)",
+                        &[&i16::from(SCHEMA_LAST_VERSION)],
+                    )
+                    .await?;
+            }
+        }
+
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    async fn query_one_column<T>(&self, query: &str, param: &str, what: &str) -> Result<T, StorageError>
+    where
+        T: for<'a> tokio_postgres::types::FromSql<'a>,
+    {
+        let client = self.client.lock().await;
+        let rows = client.query(query, &[&param]).await?;
+        match rows.first() {
+            Some(row) => Ok(row.get(0)),
+            None => Err(not_found(what)),
+        }
+    }
+
+    async fn query_hashes(
+        &self,
+        query: &str,
+        params: &[&(dyn tokio_postgres::types::ToSql + Sync)],
+    ) -> Result<Vec<String>, StorageError> {
+        let client = self.client.lock().await;
+        let rows = client.query(query, params).await?;
+        Ok(rows.iter().map(|row| row.get(0)).collect())
+    }
+}
+
+const UPSERT_METADATA: &str = "
+INSERT INTO certificate_metadata (addressing_hash, subject, serial_number, ski, aki, not_before, not_after, issued_at)
+VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+ON CONFLICT (addressing_hash) DO UPDATE
+SET subject = EXCLUDED.subject, serial_number = EXCLUDED.serial_number, ski = EXCLUDED.ski, aki = EXCLUDED.aki,
+    not_before = EXCLUDED.not_before, not_after = EXCLUDED.not_after, issued_at = EXCLUDED.issued_at";
+
+/// Selected columns are `addressing_hash, der, not_after, expired`
+fn listed_from_row(row: &Row) -> ListedCertificate {
+    ListedCertificate {
+        addressing_hash: row.get(0),
+        cert: row.get(1),
+        expiry: expiry_from_row(row, 2),
+    }
+}
+
+/// LIMIT and OFFSET values, `usize::MAX` standing for no limit
+fn sql_count(count: usize) -> i64 {
+    i64::try_from(count).unwrap_or(i64::MAX)
+}
+
+fn expiry_from_row(row: &Row, not_after_index: usize) -> Option<CertificateExpiry> {
+    row.get::<_, Option<i64>>(not_after_index)
+        .map(|not_after| CertificateExpiry {
+            not_after,
+            expired: row.get(not_after_index + 1),
+        })
+}
+
+/// Selected columns are `subject, serial_number, ski, aki, not_before, not_after, issued_at`
+fn metadata_from_row(row: &Row) -> IssuanceMetadata {
+    IssuanceMetadata {
+        subject: row.get(0),
+        serial_number: row.get(1),
+        ski: row.get(2),
+        aki: row.get(3),
+        not_before: row.get(4),
+        not_after: row.get(5),
+        issued_at: row.get(6),
+    }
+}
+
+fn revocation_from_row(row: &Row) -> RevocationEntry {
+    RevocationEntry {
+        addressing_hash: row.get(0),
+        issuer_name: row.get(1),
+        serial_number: row.get(2),
+        revocation_date: row.get(3),
+        reason: row.get::<_, Option<i16>>(4).map(|reason| reason as u8),
+    }
+}
+
+impl PickyStorage for PostgresStorage {
+    fn ping(&self) -> BoxFuture<'_, Result<(), StorageError>> {
+        async move {
+            self.client
+                .lock()
+                .await
+                .simple_query("SELECT 1")
+                .await
+                .map_err(|e| PostgresStorageError::Other {
+                    description: format!("ping to postgres connection failed: {}", e),
+                })?;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn close(&self) -> BoxFuture<'_, Result<(), StorageError>> {
+        // every write is committed before returning, the connection is closed when the client is dropped
+        async move { Ok(()) }.boxed()
+    }
+
+    fn store(
+        &self,
+        entry: CertificateEntry,
+        policy: ConflictPolicy,
+    ) -> BoxFuture<'_, Result<StoreOutcome, StorageError>> {
+        let name = entry.name;
+        let cert = entry.cert;
+        let key_identifier = entry.key_identifier;
+        let key = entry.key;
+
+        async move {
+            let addressing_hash = encode_to_canonical_address(&cert);
+
+            let alternative_addresses =
+                encode_to_alternative_addresses(&cert).map_err(|e| PostgresStorageError::Other {
+                    description: format!("couldn't encode alternative addresses: {}", e),
+                })?;
+            let expiry = CertificateExpiry::from_der(&cert).map_err(PostgresStorageError::from)?;
+            let metadata =
+                IssuanceMetadata::from_der(&cert, Some(Utc::now().timestamp())).map_err(PostgresStorageError::from)?;
+
+            let mut client = self.client.lock().await;
+            let transaction = client.transaction().await?;
+
+            let already_stored = !transaction
+                .query(
+                    "SELECT 1 FROM certificate WHERE addressing_hash = $1",
+                    &[&addressing_hash],
+                )
+                .await?
+                .is_empty();
+            let (outcome, write) = resolve_conflict(policy, &addressing_hash, already_stored)?;
+            if !write {
+                return Ok(outcome);
+            }
+
+            transaction
+                .execute(
+                    "INSERT INTO certificate (addressing_hash, der, not_after, expired) VALUES ($1, $2, $3, FALSE)
+                     ON CONFLICT (addressing_hash) DO UPDATE
+                     SET der = EXCLUDED.der, not_after = EXCLUDED.not_after, expired = FALSE",
+                    &[&addressing_hash, &cert, &expiry.not_after],
+                )
+                .await?;
+            transaction
+                .execute(
+                    UPSERT_METADATA,
+                    &[
+                        &addressing_hash,
+                        &metadata.subject,
+                        &metadata.serial_number,
+                        &metadata.ski,
+                        &metadata.aki,
+                        &metadata.not_before,
+                        &metadata.not_after,
+                        &metadata.issued_at,
+                    ],
+                )
+                .await?;
+
+            transaction
+                .execute(
+                    "INSERT INTO certificate_name (name, addressing_hash) VALUES ($1, $2)
+                     ON CONFLICT (name) DO UPDATE SET addressing_hash = EXCLUDED.addressing_hash",
+                    &[&name, &addressing_hash],
+                )
+                .await?;
+            transaction
+                .execute(
+                    "INSERT INTO name_history (name, addressing_hash) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+                    &[&name, &addressing_hash],
+                )
+                .await?;
+
+            transaction
+                .execute(
+                    "INSERT INTO key_identifier (key_identifier, addressing_hash) VALUES ($1, $2)
+                     ON CONFLICT (key_identifier) DO UPDATE SET addressing_hash = EXCLUDED.addressing_hash",
+                    &[&key_identifier, &addressing_hash],
+                )
+                .await?;
+
+            for alternative_address in alternative_addresses.iter() {
+                transaction
+                    .execute(
+                        "INSERT INTO hash_lookup (lookup_key, addressing_hash) VALUES ($1, $2)
+                         ON CONFLICT (lookup_key) DO UPDATE SET addressing_hash = EXCLUDED.addressing_hash",
+                        &[alternative_address, &addressing_hash],
+                    )
+                    .await?;
+            }
+
+            if let Some(key) = key {
+                transaction
+                    .execute(
+                        "INSERT INTO private_key (addressing_hash, der) VALUES ($1, $2)
+                         ON CONFLICT (addressing_hash) DO UPDATE SET der = EXCLUDED.der",
+                        &[&addressing_hash, &key],
+                    )
+                    .await?;
+            }
+
+            transaction.commit().await?;
+            Ok(outcome)
+        }
+        .boxed()
+    }
+
+    fn get_cert_by_addressing_hash<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<Vec<u8>, StorageError>> {
+        self.query_one_column(
+            "SELECT der FROM certificate WHERE addressing_hash = $1",
+            hash,
+            "cert not found",
+        )
+        .boxed()
+    }
+
+    fn get_key_by_addressing_hash<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<Vec<u8>, StorageError>> {
+        self.query_one_column(
+            "SELECT der FROM private_key WHERE addressing_hash = $1",
+            hash,
+            "key not found",
+        )
+        .boxed()
+    }
+
+    fn get_addressing_hash_by_name<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<String, StorageError>> {
+        async move {
+            self.query_one_column(
+                "SELECT addressing_hash FROM certificate_name WHERE name = $1",
+                name,
+                &format!("hash not found using name {}", name),
+            )
+            .await
+        }
+        .boxed()
+    }
+
+    fn get_addressing_hashes_by_name<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<Vec<String>, StorageError>> {
+        async move {
+            let client = self.client.lock().await;
+            let rows = client
+                .query(
+                    "SELECT addressing_hash FROM name_history WHERE name = $1 ORDER BY id",
+                    &[&name],
+                )
+                .await?;
+            Ok(rows.iter().map(|row| row.get(0)).collect())
+        }
+        .boxed()
+    }
+
+    fn get_addressing_hash_by_key_identifier<'a>(
+        &'a self,
+        key_identifier: &'a str,
+    ) -> BoxFuture<'a, Result<String, StorageError>> {
+        self.query_one_column(
+            "SELECT addressing_hash FROM key_identifier WHERE key_identifier = $1",
+            key_identifier,
+            "hash not found",
+        )
+        .boxed()
+    }
+
+    fn lookup_addressing_hash<'a>(&'a self, lookup_key: &'a str) -> BoxFuture<'a, Result<String, StorageError>> {
+        self.query_one_column(
+            "SELECT addressing_hash FROM hash_lookup WHERE lookup_key = $1",
+            lookup_key,
+            "hash not found",
+        )
+        .boxed()
+    }
+
+    fn get_revocations_by_issuer<'a>(
+        &'a self,
+        issuer_name: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<RevocationEntry>, StorageError>> {
+        async move {
+            let client = self.client.lock().await;
+            let rows = client
+                .query(
+                    "SELECT addressing_hash, issuer_name, serial_number, revocation_date, reason
+                     FROM revocation WHERE issuer_name = $1",
+                    &[&issuer_name],
+                )
+                .await?;
+            Ok(rows.iter().map(revocation_from_row).collect())
+        }
+        .boxed()
+    }
+
+    fn revoke(&self, entry: RevocationEntry) -> BoxFuture<'_, Result<(), StorageError>> {
+        async move {
+            let reason = entry.reason.map(i16::from);
+            self.client
+                .lock()
+                .await
+                .execute(
+                    "INSERT INTO revocation (addressing_hash, issuer_name, serial_number, revocation_date, reason)
+                     VALUES ($1, $2, $3, $4, $5)
+                     ON CONFLICT (addressing_hash) DO UPDATE
+                     SET issuer_name = EXCLUDED.issuer_name, serial_number = EXCLUDED.serial_number,
+                         revocation_date = EXCLUDED.revocation_date, reason = EXCLUDED.reason",
+                    &[
+                        &entry.addressing_hash,
+                        &entry.issuer_name,
+                        &entry.serial_number,
+                        &entry.revocation_date,
+                        &reason,
+                    ],
+                )
+                .await?;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn list<'a>(
+        &'a self,
+        filter: &'a ListFilter,
+        offset: usize,
+        limit: usize,
+    ) -> BoxFuture<'a, Result<Vec<ListedCertificate>, StorageError>> {
+        async move {
+            let client = self.client.lock().await;
+            // without filter, pagination is left to the database
+            if *filter == ListFilter::default() {
+                let rows = client
+                    .query(
+                        "SELECT addressing_hash, der, not_after, expired FROM certificate ORDER BY addressing_hash
+                         LIMIT $1 OFFSET $2",
+                        &[&sql_count(limit), &sql_count(offset)],
+                    )
+                    .await?;
+                return Ok(rows.iter().map(listed_from_row).collect());
+            }
+
+            let rows = client
+                .query(
+                    "SELECT addressing_hash, der, not_after, expired FROM certificate ORDER BY addressing_hash",
+                    &[],
+                )
+                .await?;
+
+            Ok(rows
+                .iter()
+                .map(listed_from_row)
+                .filter(|listed| filter.matches(&listed.cert, listed.expiry.as_ref()))
+                .skip(offset)
+                .take(limit)
+                .collect())
+        }
+        .boxed()
+    }
+
+    fn count(&self) -> BoxFuture<'_, Result<usize, StorageError>> {
+        async move {
+            let client = self.client.lock().await;
+            let row = client.query_one("SELECT COUNT(*) FROM certificate", &[]).await?;
+            Ok(row.get::<_, i64>(0) as usize)
+        }
+        .boxed()
+    }
+
+    fn get_revocation_by_addressing_hash<'a>(
+        &'a self,
+        hash: &'a str,
+    ) -> BoxFuture<'a, Result<Option<RevocationEntry>, StorageError>> {
+        async move {
+            let client = self.client.lock().await;
+            let rows = client
+                .query(
+                    "SELECT addressing_hash, issuer_name, serial_number, revocation_date, reason
+                     FROM revocation WHERE addressing_hash = $1",
+                    &[&hash],
+                )
+                .await?;
+            Ok(rows.first().map(revocation_from_row))
+        }
+        .boxed()
+    }
+
+    fn get_expiry<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<Option<CertificateExpiry>, StorageError>> {
+        async move {
+            let client = self.client.lock().await;
+            let rows = client
+                .query(
+                    "SELECT not_after, expired FROM certificate WHERE addressing_hash = $1",
+                    &[&hash],
+                )
+                .await?;
+            Ok(rows.first().and_then(|row| expiry_from_row(row, 0)))
+        }
+        .boxed()
+    }
+
+    fn set_expired<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<(), StorageError>> {
+        async move {
+            let updated = self
+                .client
+                .lock()
+                .await
+                .execute(
+                    "UPDATE certificate SET expired = TRUE WHERE addressing_hash = $1",
+                    &[&hash],
+                )
+                .await?;
+            if updated == 0 {
+                return Err(not_found("cert not found"));
+            }
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn get_metadata<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<Option<IssuanceMetadata>, StorageError>> {
+        async move {
+            let client = self.client.lock().await;
+            let rows = client
+                .query(
+                    "SELECT subject, serial_number, ski, aki, not_before, not_after, issued_at
+                     FROM certificate_metadata WHERE addressing_hash = $1",
+                    &[&hash],
+                )
+                .await?;
+            Ok(rows.first().map(metadata_from_row))
+        }
+        .boxed()
+    }
+
+    fn set_metadata<'a>(
+        &'a self,
+        hash: &'a str,
+        metadata: IssuanceMetadata,
+    ) -> BoxFuture<'a, Result<(), StorageError>> {
+        async move {
+            self.client
+                .lock()
+                .await
+                .execute(
+                    UPSERT_METADATA,
+                    &[
+                        &hash,
+                        &metadata.subject,
+                        &metadata.serial_number,
+                        &metadata.ski,
+                        &metadata.aki,
+                        &metadata.not_before,
+                        &metadata.not_after,
+                        &metadata.issued_at,
+                    ],
+                )
+                .await?;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn find_expiring_before(&self, not_after: i64) -> BoxFuture<'_, Result<Vec<String>, StorageError>> {
+        async move {
+            self.query_hashes(
+                "SELECT addressing_hash FROM certificate_metadata WHERE not_after < $1
+                 ORDER BY not_after, addressing_hash",
+                &[&not_after],
+            )
+            .await
+        }
+        .boxed()
+    }
+
+    fn delete<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<(), StorageError>> {
+        async move {
+            let mut client = self.client.lock().await;
+            let transaction = client.transaction().await?;
+
+            // the private key and metadata go along with the certificate (ON DELETE CASCADE)
+            let deleted = transaction
+                .execute("DELETE FROM certificate WHERE addressing_hash = $1", &[&hash])
+                .await?;
+            if deleted == 0 {
+                return Err(not_found("cert not found"));
+            }
+
+            transaction
+                .execute("DELETE FROM key_identifier WHERE addressing_hash = $1", &[&hash])
+                .await?;
+            transaction
+                .execute("DELETE FROM hash_lookup WHERE addressing_hash = $1", &[&hash])
+                .await?;
+            transaction
+                .execute("DELETE FROM name_history WHERE addressing_hash = $1", &[&hash])
+                .await?;
+
+            // names move back to the previous certificate stored under them, if any
+            transaction
+                .execute(
+                    "UPDATE certificate_name SET addressing_hash = (
+                         SELECT history.addressing_hash FROM name_history history
+                         WHERE history.name = certificate_name.name ORDER BY history.id DESC LIMIT 1
+                     )
+                     WHERE addressing_hash = $1
+                     AND EXISTS (SELECT 1 FROM name_history history WHERE history.name = certificate_name.name)",
+                    &[&hash],
+                )
+                .await?;
+            transaction
+                .execute("DELETE FROM certificate_name WHERE addressing_hash = $1", &[&hash])
+                .await?;
+
+            transaction.commit().await?;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn append_audit(&self, entry: AuditEntry) -> BoxFuture<'_, Result<(), StorageError>> {
+        async move {
+            let json = serde_json::to_string(&entry).map_err(|e| PostgresStorageError::Other {
+                description: format!("couldn't encode audit entry: {}", e),
+            })?;
+            self.client
+                .lock()
+                .await
+                .execute(
+                    "INSERT INTO audit (timestamp, entry) VALUES ($1, $2)",
+                    &[&entry.timestamp, &json],
+                )
+                .await?;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn list_audit(&self, since: i64, limit: usize) -> BoxFuture<'_, Result<Vec<AuditEntry>, StorageError>> {
+        async move {
+            let limit = i64::try_from(limit).unwrap_or(i64::MAX);
+            let client = self.client.lock().await;
+            let rows = client
+                .query(
+                    "SELECT entry FROM audit WHERE timestamp >= $1 ORDER BY id LIMIT $2",
+                    &[&since, &limit],
+                )
+                .await?;
+
+            rows.iter()
+                .map(|row| {
+                    serde_json::from_str(row.get(0)).map_err(|e| {
+                        StorageError::from(PostgresStorageError::Other {
+                            description: format!("couldn't decode audit entry: {}", e),
+                        })
+                    })
+                })
+                .collect()
+        }
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_test::block_on;
+
+    const TEST_DATABASE_URL_ENV: &str = "PICKY_TEST_POSTGRES_URL";
+
+    #[test]
+    fn postgres_storage() {
+        let database_url = std::env::var(TEST_DATABASE_URL_ENV)
+            .unwrap_or_else(|_| panic!("{} must point to a scratch database", TEST_DATABASE_URL_ENV));
+
+        block_on(async {
+            let storage = PostgresStorage::new(&database_url).await.expect("postgres storage");
+            storage
+                .client
+                .lock()
+                .await
+                .batch_execute(
+                    "TRUNCATE certificate, private_key, certificate_metadata, certificate_name, name_history, key_identifier, hash_lookup, \
+                     revocation, audit",
+                )
+                .await
+                .expect("couldn't clean database");
+
+            crate::db::tests::check_storage(&storage).await;
+            crate::db::tests::check_pagination(&storage).await;
+            crate::db::tests::check_conflicts(&storage).await;
+            crate::db::tests::check_metadata(&storage).await;
+        });
+    }
+}
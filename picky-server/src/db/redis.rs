@@ -0,0 +1,792 @@
+use crate::{
+    addressing::{encode_to_alternative_addresses, encode_to_canonical_address},
+    config::Config,
+    db::{
+        resolve_conflict, AuditEntry, CertificateEntry, CertificateExpiry, ConflictPolicy, IssuanceMetadata,
+        ListFilter, ListedCertificate, PickyStorage, RevocationEntry, StorageError, StoreOutcome,
+    },
+};
+use chrono::Utc;
+use futures::{future::BoxFuture, FutureExt};
+use picky::x509::Cert;
+use redis::{aio::MultiplexedConnection, Pipeline, RedisError};
+use serde::{de::DeserializeOwned, Serialize};
+use thiserror::Error;
+
+const CERT_KEY: &str = "cert";
+const PRIVATE_KEY_KEY: &str = "key";
+const EXPIRY_KEY: &str = "expiry";
+const METADATA_KEY: &str = "metadata";
+/// Addressing hashes scored by notAfter date
+const BY_NOT_AFTER_KEY: &str = "by_not_after";
+const NAME_KEY: &str = "name";
+const NAME_HISTORY_KEY: &str = "name_history";
+const NAMES_OF_KEY: &str = "names";
+const KEY_IDENTIFIER_KEY: &str = "kid";
+const KEY_IDENTIFIERS_OF_KEY: &str = "kids";
+const HASH_LOOKUP_KEY: &str = "lookup";
+const REVOCATION_KEY: &str = "revocation";
+const REVOCATIONS_BY_ISSUER_KEY: &str = "revocations";
+const CERTIFICATES_KEY: &str = "certs";
+const AUDIT_KEY: &str = "audit";
+
+#[derive(Debug, Error)]
+pub enum RedisStorageError {
+    #[error("redis error: {}", source)]
+    Redis { source: RedisError },
+
+    #[error("generic error: {}", description)]
+    Other { description: String },
+}
+
+impl From<String> for RedisStorageError {
+    fn from(description: String) -> Self {
+        RedisStorageError::Other { description }
+    }
+}
+
+impl From<RedisError> for RedisStorageError {
+    fn from(source: RedisError) -> Self {
+        RedisStorageError::Redis { source }
+    }
+}
+
+impl From<RedisError> for StorageError {
+    fn from(source: RedisError) -> Self {
+        RedisStorageError::Redis { source }.into()
+    }
+}
+
+fn not_found(description: &str) -> StorageError {
+    RedisStorageError::Other {
+        description: description.to_owned(),
+    }
+    .into()
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<String, StorageError> {
+    serde_json::to_string(value).map_err(|e| {
+        RedisStorageError::Other {
+            description: format!("couldn't encode json value: {}", e),
+        }
+        .into()
+    })
+}
+
+fn from_json<T: DeserializeOwned>(json: &str) -> Result<T, StorageError> {
+    serde_json::from_str(json).map_err(|e| {
+        RedisStorageError::Other {
+            description: format!("couldn't decode json value: {}", e),
+        }
+        .into()
+    })
+}
+
+/// `SET` queued on a pipeline, expiring after `ttl` seconds when given
+fn set<V: redis::ToRedisArgs>(pipe: &mut Pipeline, key: String, value: V, ttl: Option<usize>) {
+    match ttl {
+        Some(ttl) => pipe.cmd("SET").arg(key).arg(value).arg("EX").arg(ttl).ignore(),
+        None => pipe.cmd("SET").arg(key).arg(value).ignore(),
+    };
+}
+
+/// Storage backed by Redis keys, every key being prepended with the configured prefix.
+///
+/// CA certificates and certificates stored with a private key never expire. When `redis_leaf_ttl` is
+/// set, leaf certificates and their lookup entries expire along with the certificate, unless already
+/// expired when stored.
+pub struct RedisStorage {
+    connection: MultiplexedConnection,
+    prefix: String,
+    leaf_ttl: bool,
+}
+
+impl RedisStorage {
+    pub async fn new(config: &Config) -> Result<Self, RedisStorageError> {
+        let client = redis::Client::open(config.database_url.as_str())?;
+        let connection = client.get_multiplexed_tokio_connection().await?;
+        Ok(Self {
+            connection,
+            prefix: config.redis_key_prefix.clone(),
+            leaf_ttl: config.redis_leaf_ttl,
+        })
+    }
+
+    fn key(&self, kind: &str, id: &str) -> String {
+        format!("{}{}:{}", self.prefix, kind, id)
+    }
+
+    fn global_key(&self, kind: &str) -> String {
+        format!("{}{}", self.prefix, kind)
+    }
+
+    async fn get<T: redis::FromRedisValue>(&self, key: String) -> Result<Option<T>, StorageError> {
+        Ok(redis::cmd("GET")
+            .arg(key)
+            .query_async(&mut self.connection.clone())
+            .await?)
+    }
+
+    /// `MGET` which, unlike the command itself, accepts an empty list of keys
+    async fn mget<T: redis::FromRedisValue>(&self, keys: Vec<String>) -> Result<Vec<Option<T>>, StorageError> {
+        if keys.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        Ok(redis::cmd("MGET")
+            .arg(keys)
+            .query_async(&mut self.connection.clone())
+            .await?)
+    }
+
+    /// Queues the metadata of a certificate and its index entries on a pipeline
+    fn queue_metadata(
+        &self,
+        pipe: &mut Pipeline,
+        hash: &str,
+        metadata: &IssuanceMetadata,
+        ttl: Option<usize>,
+    ) -> Result<(), StorageError> {
+        set(pipe, self.key(METADATA_KEY, hash), to_json(metadata)?, ttl);
+        pipe.cmd("ZADD")
+            .arg(self.global_key(BY_NOT_AFTER_KEY))
+            .arg(metadata.not_after)
+            .arg(hash)
+            .ignore();
+        Ok(())
+    }
+
+    /// Members of a metadata index scored within the given range, lowest score first.
+    /// Evicted leaves are still indexed and thus skipped.
+    async fn metadata_range(&self, index: &str, min: String, max: String) -> Result<Vec<String>, StorageError> {
+        let hashes: Vec<String> = redis::cmd("ZRANGEBYSCORE")
+            .arg(self.global_key(index))
+            .arg(min)
+            .arg(max)
+            .query_async(&mut self.connection.clone())
+            .await?;
+        let metadata = self
+            .mget::<String>(hashes.iter().map(|hash| self.key(METADATA_KEY, hash)).collect())
+            .await?;
+        Ok(hashes
+            .into_iter()
+            .zip(metadata)
+            .filter(|(_, metadata)| metadata.is_some())
+            .map(|(hash, _)| hash)
+            .collect())
+    }
+
+    /// Seconds left before the certificate should be evicted, None when it must be kept
+    fn ttl(&self, cert_der: &[u8], has_key: bool, expiry: &CertificateExpiry) -> Result<Option<usize>, StorageError> {
+        if !self.leaf_ttl || has_key {
+            return Ok(None);
+        }
+
+        let cert = Cert::from_der(cert_der).map_err(|e| RedisStorageError::Other {
+            description: format!("couldn't decode certificate: {}", e),
+        })?;
+        let is_ca = cert.basic_constraints().ok().and_then(|bc| bc.ca()).unwrap_or(false);
+        if is_ca {
+            return Ok(None);
+        }
+
+        let left = expiry.not_after - Utc::now().timestamp();
+        if left > 0 {
+            Ok(Some(left as usize))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl PickyStorage for RedisStorage {
+    fn ping(&self) -> BoxFuture<'_, Result<(), StorageError>> {
+        async move {
+            redis::cmd("PING")
+                .query_async::<_, String>(&mut self.connection.clone())
+                .await
+                .map_err(|e| RedisStorageError::Other {
+                    description: format!("ping to redis connection failed: {}", e),
+                })?;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn close(&self) -> BoxFuture<'_, Result<(), StorageError>> {
+        // writes are acknowledged by redis, persistence is left to its own configuration
+        async move { Ok(()) }.boxed()
+    }
+
+    fn store(
+        &self,
+        entry: CertificateEntry,
+        policy: ConflictPolicy,
+    ) -> BoxFuture<'_, Result<StoreOutcome, StorageError>> {
+        let name = entry.name;
+        let cert = entry.cert;
+        let key_identifier = entry.key_identifier;
+        let key = entry.key;
+
+        async move {
+            let addressing_hash = encode_to_canonical_address(&cert);
+            let already_stored = redis::cmd("EXISTS")
+                .arg(self.key(CERT_KEY, &addressing_hash))
+                .query_async::<_, bool>(&mut self.connection.clone())
+                .await?;
+            let (outcome, write) = resolve_conflict(policy, &addressing_hash, already_stored)?;
+            if !write {
+                return Ok(outcome);
+            }
+
+            let alternative_addresses =
+                encode_to_alternative_addresses(&cert).map_err(|e| RedisStorageError::Other {
+                    description: format!("couldn't encode alternative addresses: {}", e),
+                })?;
+            let expiry = CertificateExpiry::from_der(&cert).map_err(RedisStorageError::from)?;
+            let metadata =
+                IssuanceMetadata::from_der(&cert, Some(Utc::now().timestamp())).map_err(RedisStorageError::from)?;
+            let ttl = self.ttl(&cert, key.is_some(), &expiry)?;
+
+            // every mapping is written in a single round trip
+            let mut pipe = redis::pipe();
+            pipe.atomic();
+
+            set(&mut pipe, self.key(CERT_KEY, &addressing_hash), cert.as_slice(), ttl);
+            set(
+                &mut pipe,
+                self.key(EXPIRY_KEY, &addressing_hash),
+                to_json(&expiry)?,
+                ttl,
+            );
+            self.queue_metadata(&mut pipe, &addressing_hash, &metadata, ttl)?;
+            if let Some(key) = key {
+                set(&mut pipe, self.key(PRIVATE_KEY_KEY, &addressing_hash), key, None);
+            }
+            pipe.cmd("ZADD")
+                .arg(self.global_key(CERTIFICATES_KEY))
+                .arg(0)
+                .arg(&addressing_hash)
+                .ignore();
+
+            set(&mut pipe, self.key(NAME_KEY, &name), &addressing_hash, ttl);
+            // scored by insertion date so that history is returned oldest first, NX keeps the first insertion
+            pipe.cmd("ZADD")
+                .arg(self.key(NAME_HISTORY_KEY, &name))
+                .arg("NX")
+                .arg(Utc::now().timestamp_nanos() / 1000)
+                .arg(&addressing_hash)
+                .ignore();
+            pipe.cmd("SADD")
+                .arg(self.key(NAMES_OF_KEY, &addressing_hash))
+                .arg(&name)
+                .ignore();
+
+            set(
+                &mut pipe,
+                self.key(KEY_IDENTIFIER_KEY, &key_identifier),
+                &addressing_hash,
+                ttl,
+            );
+            pipe.cmd("SADD")
+                .arg(self.key(KEY_IDENTIFIERS_OF_KEY, &addressing_hash))
+                .arg(&key_identifier)
+                .ignore();
+
+            for alternative_address in alternative_addresses.iter() {
+                set(
+                    &mut pipe,
+                    self.key(HASH_LOOKUP_KEY, alternative_address),
+                    &addressing_hash,
+                    ttl,
+                );
+            }
+
+            if let Some(ttl) = ttl {
+                for reverse_key in &[NAMES_OF_KEY, KEY_IDENTIFIERS_OF_KEY] {
+                    pipe.cmd("EXPIRE")
+                        .arg(self.key(reverse_key, &addressing_hash))
+                        .arg(ttl)
+                        .ignore();
+                }
+            }
+
+            pipe.query_async::<_, ()>(&mut self.connection.clone()).await?;
+            Ok(outcome)
+        }
+        .boxed()
+    }
+
+    fn get_cert_by_addressing_hash<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<Vec<u8>, StorageError>> {
+        async move {
+            self.get(self.key(CERT_KEY, hash))
+                .await?
+                .ok_or_else(|| not_found("cert not found"))
+        }
+        .boxed()
+    }
+
+    fn get_key_by_addressing_hash<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<Vec<u8>, StorageError>> {
+        async move {
+            self.get(self.key(PRIVATE_KEY_KEY, hash))
+                .await?
+                .ok_or_else(|| not_found("key not found"))
+        }
+        .boxed()
+    }
+
+    fn get_addressing_hash_by_name<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<String, StorageError>> {
+        async move {
+            self.get(self.key(NAME_KEY, name))
+                .await?
+                .ok_or_else(|| not_found(&format!("hash not found using name {}", name)))
+        }
+        .boxed()
+    }
+
+    fn get_addressing_hashes_by_name<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<Vec<String>, StorageError>> {
+        async move {
+            let hashes: Vec<String> = redis::cmd("ZRANGE")
+                .arg(self.key(NAME_HISTORY_KEY, name))
+                .arg(0)
+                .arg(-1)
+                .query_async(&mut self.connection.clone())
+                .await?;
+
+            // history entries of evicted leaves are skipped
+            let keys = hashes.iter().map(|hash| self.key(CERT_KEY, hash)).collect();
+            let certs = self.mget::<Vec<u8>>(keys).await?;
+            Ok(hashes
+                .into_iter()
+                .zip(certs)
+                .filter_map(|(hash, cert)| cert.map(|_| hash))
+                .collect())
+        }
+        .boxed()
+    }
+
+    fn get_addressing_hash_by_key_identifier<'a>(
+        &'a self,
+        key_identifier: &'a str,
+    ) -> BoxFuture<'a, Result<String, StorageError>> {
+        async move {
+            self.get(self.key(KEY_IDENTIFIER_KEY, key_identifier))
+                .await?
+                .ok_or_else(|| not_found("hash not found"))
+        }
+        .boxed()
+    }
+
+    fn lookup_addressing_hash<'a>(&'a self, lookup_key: &'a str) -> BoxFuture<'a, Result<String, StorageError>> {
+        async move {
+            self.get(self.key(HASH_LOOKUP_KEY, lookup_key))
+                .await?
+                .ok_or_else(|| not_found("hash not found"))
+        }
+        .boxed()
+    }
+
+    fn get_revocations_by_issuer<'a>(
+        &'a self,
+        issuer_name: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<RevocationEntry>, StorageError>> {
+        async move {
+            let hashes: Vec<String> = redis::cmd("SMEMBERS")
+                .arg(self.key(REVOCATIONS_BY_ISSUER_KEY, issuer_name))
+                .query_async(&mut self.connection.clone())
+                .await?;
+            let keys = hashes.iter().map(|hash| self.key(REVOCATION_KEY, hash)).collect();
+
+            let mut revocations = Vec::new();
+            for json in self.mget::<String>(keys).await?.into_iter().flatten() {
+                let entry: RevocationEntry = from_json(&json)?;
+                if entry.issuer_name == issuer_name {
+                    revocations.push(entry);
+                }
+            }
+            Ok(revocations)
+        }
+        .boxed()
+    }
+
+    fn revoke(&self, entry: RevocationEntry) -> BoxFuture<'_, Result<(), StorageError>> {
+        async move {
+            redis::pipe()
+                .atomic()
+                .cmd("SET")
+                .arg(self.key(REVOCATION_KEY, &entry.addressing_hash))
+                .arg(to_json(&entry)?)
+                .ignore()
+                .cmd("SADD")
+                .arg(self.key(REVOCATIONS_BY_ISSUER_KEY, &entry.issuer_name))
+                .arg(&entry.addressing_hash)
+                .ignore()
+                .query_async::<_, ()>(&mut self.connection.clone())
+                .await?;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn list<'a>(
+        &'a self,
+        filter: &'a ListFilter,
+        offset: usize,
+        limit: usize,
+    ) -> BoxFuture<'a, Result<Vec<ListedCertificate>, StorageError>> {
+        async move {
+            // members share the same score and are thus ordered lexicographically
+            let hashes: Vec<String> = redis::cmd("ZRANGE")
+                .arg(self.global_key(CERTIFICATES_KEY))
+                .arg(0)
+                .arg(-1)
+                .query_async(&mut self.connection.clone())
+                .await?;
+
+            let certs = self
+                .mget::<Vec<u8>>(hashes.iter().map(|hash| self.key(CERT_KEY, hash)).collect())
+                .await?;
+            let expiries = self
+                .mget::<String>(hashes.iter().map(|hash| self.key(EXPIRY_KEY, hash)).collect())
+                .await?;
+
+            let mut listed = Vec::new();
+            for ((addressing_hash, cert), expiry) in hashes.into_iter().zip(certs).zip(expiries) {
+                // evicted leaves are still indexed
+                let cert = match cert {
+                    Some(cert) => cert,
+                    None => continue,
+                };
+                let expiry = expiry.map(|json| from_json::<CertificateExpiry>(&json)).transpose()?;
+                listed.push(ListedCertificate {
+                    addressing_hash,
+                    cert,
+                    expiry,
+                });
+            }
+
+            Ok(listed
+                .into_iter()
+                .filter(|listed| filter.matches(&listed.cert, listed.expiry.as_ref()))
+                .skip(offset)
+                .take(limit)
+                .collect())
+        }
+        .boxed()
+    }
+
+    fn count(&self) -> BoxFuture<'_, Result<usize, StorageError>> {
+        async move {
+            if self.leaf_ttl {
+                // evicted leaves are still indexed
+                return Ok(self.list(&ListFilter::default(), 0, usize::MAX).await?.len());
+            }
+
+            let count: usize = redis::cmd("ZCARD")
+                .arg(self.global_key(CERTIFICATES_KEY))
+                .query_async(&mut self.connection.clone())
+                .await?;
+            Ok(count)
+        }
+        .boxed()
+    }
+
+    fn get_revocation_by_addressing_hash<'a>(
+        &'a self,
+        hash: &'a str,
+    ) -> BoxFuture<'a, Result<Option<RevocationEntry>, StorageError>> {
+        async move {
+            self.get::<String>(self.key(REVOCATION_KEY, hash))
+                .await?
+                .map(|json| from_json(&json))
+                .transpose()
+        }
+        .boxed()
+    }
+
+    fn get_expiry<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<Option<CertificateExpiry>, StorageError>> {
+        async move {
+            self.get::<String>(self.key(EXPIRY_KEY, hash))
+                .await?
+                .map(|json| from_json(&json))
+                .transpose()
+        }
+        .boxed()
+    }
+
+    fn set_expired<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<(), StorageError>> {
+        async move {
+            let cert = self.get_cert_by_addressing_hash(hash).await?;
+            let expiry_key = self.key(EXPIRY_KEY, hash);
+
+            let (expiry, ttl): (Option<String>, i64) = redis::pipe()
+                .cmd("GET")
+                .arg(&expiry_key)
+                .cmd("TTL")
+                .arg(&expiry_key)
+                .query_async(&mut self.connection.clone())
+                .await?;
+            let mut expiry = match expiry {
+                Some(json) => from_json(&json)?,
+                None => CertificateExpiry::from_der(&cert).map_err(RedisStorageError::from)?,
+            };
+            expiry.expired = true;
+
+            // keeps the expiration of the certificate
+            let ttl = if ttl > 0 { Some(ttl as usize) } else { None };
+            let mut pipe = redis::pipe();
+            set(&mut pipe, expiry_key, to_json(&expiry)?, ttl);
+            pipe.query_async::<_, ()>(&mut self.connection.clone()).await?;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn get_metadata<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<Option<IssuanceMetadata>, StorageError>> {
+        async move {
+            self.get::<String>(self.key(METADATA_KEY, hash))
+                .await?
+                .map(|json| from_json(&json))
+                .transpose()
+        }
+        .boxed()
+    }
+
+    fn set_metadata<'a>(
+        &'a self,
+        hash: &'a str,
+        metadata: IssuanceMetadata,
+    ) -> BoxFuture<'a, Result<(), StorageError>> {
+        async move {
+            // expires along with the certificate
+            let ttl: i64 = redis::cmd("TTL")
+                .arg(self.key(CERT_KEY, hash))
+                .query_async(&mut self.connection.clone())
+                .await?;
+            let ttl = if ttl > 0 { Some(ttl as usize) } else { None };
+
+            let mut pipe = redis::pipe();
+            pipe.atomic();
+            self.queue_metadata(&mut pipe, hash, &metadata, ttl)?;
+            pipe.query_async::<_, ()>(&mut self.connection.clone()).await?;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn find_expiring_before(&self, not_after: i64) -> BoxFuture<'_, Result<Vec<String>, StorageError>> {
+        async move {
+            self.metadata_range(BY_NOT_AFTER_KEY, "-inf".to_owned(), format!("({}", not_after))
+                .await
+        }
+        .boxed()
+    }
+
+    fn delete<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<(), StorageError>> {
+        async move {
+            let cert = self.get_cert_by_addressing_hash(hash).await?;
+            let alternative_addresses = encode_to_alternative_addresses(&cert).map_err(RedisStorageError::from)?;
+
+            let (names, key_identifiers): (Vec<String>, Vec<String>) = redis::pipe()
+                .cmd("SMEMBERS")
+                .arg(self.key(NAMES_OF_KEY, hash))
+                .cmd("SMEMBERS")
+                .arg(self.key(KEY_IDENTIFIERS_OF_KEY, hash))
+                .query_async(&mut self.connection.clone())
+                .await?;
+
+            let mut pipe = redis::pipe();
+            pipe.atomic();
+            for kind in &[
+                CERT_KEY,
+                PRIVATE_KEY_KEY,
+                EXPIRY_KEY,
+                METADATA_KEY,
+                NAMES_OF_KEY,
+                KEY_IDENTIFIERS_OF_KEY,
+            ] {
+                pipe.cmd("DEL").arg(self.key(kind, hash)).ignore();
+            }
+            for index in &[CERTIFICATES_KEY, BY_NOT_AFTER_KEY] {
+                pipe.cmd("ZREM").arg(self.global_key(index)).arg(hash).ignore();
+            }
+
+            // lookup entries are only removed when they still point to this certificate
+            let lookup_keys = key_identifiers
+                .iter()
+                .map(|key_identifier| self.key(KEY_IDENTIFIER_KEY, key_identifier))
+                .chain(
+                    alternative_addresses
+                        .iter()
+                        .map(|address| self.key(HASH_LOOKUP_KEY, address)),
+                )
+                .collect::<Vec<_>>();
+            let lookups = self.mget::<String>(lookup_keys.clone()).await?;
+            for (lookup_key, lookup) in lookup_keys.into_iter().zip(lookups) {
+                if lookup.as_deref() == Some(hash) {
+                    pipe.cmd("DEL").arg(lookup_key).ignore();
+                }
+            }
+
+            // names move back to the previous certificate stored under them, if any
+            for name in names {
+                let history_key = self.key(NAME_HISTORY_KEY, &name);
+                let (current, history): (Option<String>, Vec<String>) = redis::pipe()
+                    .cmd("GET")
+                    .arg(self.key(NAME_KEY, &name))
+                    .cmd("ZRANGE")
+                    .arg(&history_key)
+                    .arg(0)
+                    .arg(-1)
+                    .query_async(&mut self.connection.clone())
+                    .await?;
+
+                pipe.cmd("ZREM").arg(&history_key).arg(hash).ignore();
+                if current.as_deref() != Some(hash) {
+                    continue;
+                }
+
+                match history.iter().rev().find(|previous| previous.as_str() != hash) {
+                    Some(previous) => set(&mut pipe, self.key(NAME_KEY, &name), previous, None),
+                    None => {
+                        pipe.cmd("DEL").arg(self.key(NAME_KEY, &name)).ignore();
+                    }
+                }
+            }
+
+            pipe.query_async::<_, ()>(&mut self.connection.clone()).await?;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn append_audit(&self, entry: AuditEntry) -> BoxFuture<'_, Result<(), StorageError>> {
+        async move {
+            redis::cmd("RPUSH")
+                .arg(self.global_key(AUDIT_KEY))
+                .arg(to_json(&entry)?)
+                .query_async::<_, ()>(&mut self.connection.clone())
+                .await?;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn list_audit(&self, since: i64, limit: usize) -> BoxFuture<'_, Result<Vec<AuditEntry>, StorageError>> {
+        async move {
+            let entries: Vec<String> = redis::cmd("LRANGE")
+                .arg(self.global_key(AUDIT_KEY))
+                .arg(0)
+                .arg(-1)
+                .query_async(&mut self.connection.clone())
+                .await?;
+
+            let mut audit = Vec::new();
+            for json in entries {
+                let entry: AuditEntry = from_json(&json)?;
+                if entry.timestamp >= since {
+                    audit.push(entry);
+                }
+                if audit.len() >= limit {
+                    break;
+                }
+            }
+            Ok(audit)
+        }
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use picky::{
+        key::PrivateKey,
+        pem::parse_pem,
+        x509::{certificate::CertificateBuilder, date::UTCDate, name::DirectoryName},
+    };
+    use tokio_test::block_on;
+
+    const TEST_DATABASE_URL_ENV: &str = "PICKY_TEST_REDIS_URL";
+
+    fn test_config() -> Config {
+        let mut config = Config::default();
+        config.database_url = std::env::var(TEST_DATABASE_URL_ENV)
+            .unwrap_or_else(|_| panic!("{} must point to a scratch redis database", TEST_DATABASE_URL_ENV));
+        // every run uses its own keys
+        config.redis_key_prefix = format!("picky-test:{}:", Utc::now().timestamp_nanos());
+        config
+    }
+
+    #[test]
+    fn redis_storage() {
+        let config = test_config();
+        block_on(async {
+            let storage = RedisStorage::new(&config).await.expect("redis storage");
+            crate::db::tests::check_storage(&storage).await;
+            crate::db::tests::check_pagination(&storage).await;
+            crate::db::tests::check_conflicts(&storage).await;
+            crate::db::tests::check_metadata(&storage).await;
+        });
+    }
+
+    #[test]
+    fn leaf_ttl() {
+        let mut config = test_config();
+        config.redis_leaf_ttl = true;
+
+        let key = PrivateKey::from_pem(&parse_pem(crate::test_files::RSA_2048_PK_1).unwrap()).unwrap();
+        let leaf = CertificateBuilder::new()
+            .valididy(UTCDate::now(), UTCDate::from(Utc::now() + chrono::Duration::hours(1)))
+            .self_signed(DirectoryName::new_common_name("Leaf"), &key)
+            .build()
+            .expect("couldn't build leaf")
+            .to_der()
+            .unwrap();
+        let ca = CertificateBuilder::new()
+            .valididy(UTCDate::now(), UTCDate::from(Utc::now() + chrono::Duration::hours(1)))
+            .self_signed(DirectoryName::new_common_name("CA"), &key)
+            .ca(true)
+            .build()
+            .expect("couldn't build ca")
+            .to_der()
+            .unwrap();
+
+        block_on(async {
+            let storage = RedisStorage::new(&config).await.expect("redis storage");
+            let ttl_of = |kind: &'static str, der: &[u8]| {
+                let key = storage.key(kind, &encode_to_canonical_address(der));
+                let mut connection = storage.connection.clone();
+                async move {
+                    redis::cmd("TTL")
+                        .arg(key)
+                        .query_async::<_, i64>(&mut connection)
+                        .await
+                        .unwrap()
+                }
+            };
+
+            for (name, cert) in &[("leaf", &leaf), ("ca", &ca)] {
+                storage
+                    .store(
+                        CertificateEntry {
+                            name: (*name).to_owned(),
+                            cert: (*cert).clone(),
+                            key_identifier: (*name).to_owned(),
+                            key: None,
+                        },
+                        ConflictPolicy::Overwrite,
+                    )
+                    .await
+                    .expect("store");
+            }
+
+            let leaf_ttl = ttl_of(CERT_KEY, &leaf).await;
+            assert!(leaf_ttl > 0 && leaf_ttl <= 3600, "leaf ttl: {}", leaf_ttl);
+            assert!(ttl_of(EXPIRY_KEY, &leaf).await > 0);
+            // -1: the key exists without expiration
+            assert_eq!(ttl_of(CERT_KEY, &ca).await, -1);
+            assert_eq!(ttl_of(EXPIRY_KEY, &ca).await, -1);
+        });
+    }
+}
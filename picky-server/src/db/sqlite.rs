@@ -0,0 +1,801 @@
+use crate::{
+    addressing::{encode_to_alternative_addresses, encode_to_canonical_address},
+    config::Config,
+    db::{
+        resolve_conflict, AuditEntry, CertificateEntry, CertificateExpiry, ConflictPolicy, IssuanceMetadata,
+        ListFilter, ListedCertificate, PickyStorage, RevocationEntry, StorageError, StoreOutcome, SCHEMA_LAST_VERSION,
+    },
+};
+use chrono::Utc;
+use futures::{future::BoxFuture, FutureExt};
+use rusqlite::{params, Connection, OptionalExtension, Row, NO_PARAMS};
+use std::{
+    convert::TryFrom,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::Duration,
+};
+use thiserror::Error;
+
+const SQLITE_FILE_NAME: &str = "picky.sqlite3";
+const SQLITE_URL_SCHEME: &str = "sqlite://";
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Schema of each version, applied in order to bring a database up to `SCHEMA_LAST_VERSION`
+const MIGRATIONS: &[&str] = &["
+CREATE TABLE certificate (
+    addressing_hash TEXT PRIMARY KEY,
+    der BLOB NOT NULL,
+    not_after INTEGER,
+    expired INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE TABLE private_key (
+    addressing_hash TEXT PRIMARY KEY,
+    der BLOB NOT NULL
+);
+
+CREATE TABLE certificate_name (
+    name TEXT PRIMARY KEY,
+    addressing_hash TEXT NOT NULL
+);
+CREATE INDEX certificate_name_addressing_hash_idx ON certificate_name (addressing_hash);
+
+CREATE TABLE name_history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    addressing_hash TEXT NOT NULL,
+    UNIQUE (name, addressing_hash)
+);
+CREATE INDEX name_history_addressing_hash_idx ON name_history (addressing_hash);
+
+CREATE TABLE key_identifier (
+    key_identifier TEXT PRIMARY KEY,
+    addressing_hash TEXT NOT NULL
+);
+CREATE INDEX key_identifier_addressing_hash_idx ON key_identifier (addressing_hash);
+
+CREATE TABLE hash_lookup (
+    lookup_key TEXT PRIMARY KEY,
+    addressing_hash TEXT NOT NULL
+);
+CREATE INDEX hash_lookup_addressing_hash_idx ON hash_lookup (addressing_hash);
+
+CREATE TABLE revocation (
+    addressing_hash TEXT PRIMARY KEY,
+    issuer_name TEXT NOT NULL,
+    serial_number TEXT NOT NULL,
+    revocation_date INTEGER NOT NULL,
+    reason INTEGER
+);
+CREATE INDEX revocation_issuer_name_idx ON revocation (issuer_name);
+
+CREATE TABLE audit (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    timestamp INTEGER NOT NULL,
+    entry TEXT NOT NULL
+);
+CREATE INDEX audit_timestamp_idx ON audit (timestamp);
+"];
+
+/// Tables added within the current schema version, created when missing
+const ADDITIONAL_TABLES: &str = "
+CREATE TABLE IF NOT EXISTS certificate_metadata (
+    addressing_hash TEXT PRIMARY KEY,
+    subject TEXT NOT NULL,
+    serial_number TEXT NOT NULL,
+    ski TEXT,
+    aki TEXT,
+    not_before INTEGER NOT NULL,
+    not_after INTEGER NOT NULL,
+    issued_at INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS certificate_metadata_not_after_idx ON certificate_metadata (not_after);
+";
+
+const UPSERT_METADATA: &str = "
+INSERT INTO certificate_metadata (addressing_hash, subject, serial_number, ski, aki, not_before, not_after, issued_at)
+VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+ON CONFLICT (addressing_hash) DO UPDATE
+SET subject = excluded.subject, serial_number = excluded.serial_number, ski = excluded.ski, aki = excluded.aki,
+    not_before = excluded.not_before, not_after = excluded.not_after, issued_at = excluded.issued_at";
+
+#[derive(Debug, Error)]
+pub enum SqliteStorageError {
+    #[error("sqlite error: {}", source)]
+    Sqlite { source: rusqlite::Error },
+
+    #[error("generic error: {}", description)]
+    Other { description: String },
+}
+
+impl From<String> for SqliteStorageError {
+    fn from(description: String) -> Self {
+        SqliteStorageError::Other { description }
+    }
+}
+
+impl From<rusqlite::Error> for SqliteStorageError {
+    fn from(source: rusqlite::Error) -> Self {
+        SqliteStorageError::Sqlite { source }
+    }
+}
+
+impl From<rusqlite::Error> for StorageError {
+    fn from(source: rusqlite::Error) -> Self {
+        SqliteStorageError::Sqlite { source }.into()
+    }
+}
+
+fn not_found(description: &str) -> StorageError {
+    SqliteStorageError::Other {
+        description: description.to_owned(),
+    }
+    .into()
+}
+
+/// Path of the database: a `sqlite://` database URL, or a file in the file backend folder otherwise
+pub fn database_path(config: &Config) -> PathBuf {
+    if config.database_url.starts_with(SQLITE_URL_SCHEME) {
+        PathBuf::from(&config.database_url[SQLITE_URL_SCHEME.len()..])
+    } else {
+        config.file_backend_path.join(SQLITE_FILE_NAME)
+    }
+}
+
+/// Storage backed by a single SQLite database file, in WAL mode so that readers don't wait on writers.
+///
+/// The connection is shared by every handler thread through a mutex.
+pub struct SqliteStorage {
+    connection: Mutex<Connection>,
+}
+
+impl SqliteStorage {
+    pub fn new(config: &Config) -> Result<Self, SqliteStorageError> {
+        Self::open(&database_path(config))
+    }
+
+    pub fn open(path: &Path) -> Result<Self, SqliteStorageError> {
+        if let Some(folder) = path.parent() {
+            std::fs::create_dir_all(folder)
+                .map_err(|e| format!("couldn't create folder '{}': {}", folder.to_string_lossy(), e))?;
+        }
+
+        let mut connection = Connection::open(path)?;
+        connection.busy_timeout(BUSY_TIMEOUT)?;
+        let journal_mode: String = connection.query_row("PRAGMA journal_mode = WAL", NO_PARAMS, |row| row.get(0))?;
+        if !journal_mode.eq_ignore_ascii_case("wal") {
+            log::warn!("sqlite database is using {} journal mode instead of WAL", journal_mode);
+        }
+
+        migrate(&mut connection)?;
+        connection.execute_batch(ADDITIONAL_TABLES)?;
+
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    fn with_connection<T>(
+        &self,
+        f: impl FnOnce(&mut Connection) -> Result<T, StorageError>,
+    ) -> Result<T, StorageError> {
+        let mut connection = self.connection.lock().map_err(|_| SqliteStorageError::Other {
+            description: "sqlite connection lock is poisoned".to_owned(),
+        })?;
+        f(&mut connection)
+    }
+
+    fn query_hashes(&self, query: &str, params: &[&dyn rusqlite::ToSql]) -> Result<Vec<String>, StorageError> {
+        self.with_connection(|connection| {
+            let mut statement = connection.prepare(query)?;
+            let hashes = statement
+                .query_map(params, |row| row.get(0))?
+                .collect::<rusqlite::Result<Vec<String>>>()?;
+            Ok(hashes)
+        })
+    }
+
+    fn query_one_column<T: rusqlite::types::FromSql>(
+        &self,
+        query: &str,
+        param: &str,
+        what: &str,
+    ) -> Result<T, StorageError> {
+        self.with_connection(|connection| {
+            connection
+                .query_row(query, params![param], |row| row.get(0))
+                .optional()?
+                .ok_or_else(|| not_found(what))
+        })
+    }
+}
+
+/// Brings the schema up to date, the version being tracked with `PRAGMA user_version`
+fn migrate(connection: &mut Connection) -> Result<(), SqliteStorageError> {
+    let version: i64 = connection.query_row("PRAGMA user_version", NO_PARAMS, |row| row.get(0))?;
+    let last_version = i64::from(SCHEMA_LAST_VERSION);
+
+    if version > last_version {
+        return Err(SqliteStorageError::Other {
+            description: format!("unsupported schema version: v{}", version),
+        });
+    }
+
+    if version == last_version {
+        log::info!("detected database using supported v{} schema", version);
+        return Ok(());
+    }
+
+    let transaction = connection.transaction()?;
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        log::info!("migrating database to v{} schema", index + 1);
+        transaction.execute_batch(migration)?;
+    }
+    // pragma values can't be bound as parameters
+    transaction.execute_batch(&format!("PRAGMA user_version = {}", last_version))?;
+    transaction.commit()?;
+
+    Ok(())
+}
+
+/// Selected columns are `addressing_hash, der, not_after, expired`
+fn listed_from_row(row: &Row) -> rusqlite::Result<ListedCertificate> {
+    Ok(ListedCertificate {
+        addressing_hash: row.get(0)?,
+        cert: row.get(1)?,
+        expiry: expiry_from_row(row, 2)?,
+    })
+}
+
+/// LIMIT and OFFSET values, `usize::MAX` standing for no limit
+fn sql_count(count: usize) -> i64 {
+    i64::try_from(count).unwrap_or(i64::MAX)
+}
+
+fn expiry_from_row(row: &Row, not_after_index: usize) -> rusqlite::Result<Option<CertificateExpiry>> {
+    let not_after: Option<i64> = row.get(not_after_index)?;
+    let expired: bool = row.get(not_after_index + 1)?;
+    Ok(not_after.map(|not_after| CertificateExpiry { not_after, expired }))
+}
+
+/// Selected columns are `subject, serial_number, ski, aki, not_before, not_after, issued_at`
+fn metadata_from_row(row: &Row) -> rusqlite::Result<IssuanceMetadata> {
+    Ok(IssuanceMetadata {
+        subject: row.get(0)?,
+        serial_number: row.get(1)?,
+        ski: row.get(2)?,
+        aki: row.get(3)?,
+        not_before: row.get(4)?,
+        not_after: row.get(5)?,
+        issued_at: row.get(6)?,
+    })
+}
+
+fn upsert_metadata(connection: &Connection, hash: &str, metadata: &IssuanceMetadata) -> rusqlite::Result<()> {
+    connection.execute(
+        UPSERT_METADATA,
+        params![
+            hash,
+            metadata.subject,
+            metadata.serial_number,
+            metadata.ski,
+            metadata.aki,
+            metadata.not_before,
+            metadata.not_after,
+            metadata.issued_at
+        ],
+    )?;
+    Ok(())
+}
+
+fn revocation_from_row(row: &Row) -> rusqlite::Result<RevocationEntry> {
+    Ok(RevocationEntry {
+        addressing_hash: row.get(0)?,
+        issuer_name: row.get(1)?,
+        serial_number: row.get(2)?,
+        revocation_date: row.get(3)?,
+        reason: row.get(4)?,
+    })
+}
+
+impl PickyStorage for SqliteStorage {
+    fn ping(&self) -> BoxFuture<'_, Result<(), StorageError>> {
+        async move {
+            self.with_connection(|connection| {
+                connection.query_row("SELECT 1", NO_PARAMS, |row| row.get::<_, i64>(0))?;
+                Ok(())
+            })
+        }
+        .boxed()
+    }
+
+    fn close(&self) -> BoxFuture<'_, Result<(), StorageError>> {
+        async move {
+            // moves the write-ahead log content into the database file
+            self.with_connection(|connection| {
+                connection.execute_batch("PRAGMA wal_checkpoint(TRUNCATE)")?;
+                Ok(())
+            })
+        }
+        .boxed()
+    }
+
+    fn store(
+        &self,
+        entry: CertificateEntry,
+        policy: ConflictPolicy,
+    ) -> BoxFuture<'_, Result<StoreOutcome, StorageError>> {
+        let name = entry.name;
+        let cert = entry.cert;
+        let key_identifier = entry.key_identifier;
+        let key = entry.key;
+
+        async move {
+            let addressing_hash = encode_to_canonical_address(&cert);
+
+            let alternative_addresses =
+                encode_to_alternative_addresses(&cert).map_err(|e| SqliteStorageError::Other {
+                    description: format!("couldn't encode alternative addresses: {}", e),
+                })?;
+            let expiry = CertificateExpiry::from_der(&cert).map_err(SqliteStorageError::from)?;
+            let metadata =
+                IssuanceMetadata::from_der(&cert, Some(Utc::now().timestamp())).map_err(SqliteStorageError::from)?;
+
+            self.with_connection(|connection| {
+                let transaction = connection.transaction()?;
+
+                let already_stored = transaction
+                    .query_row(
+                        "SELECT 1 FROM certificate WHERE addressing_hash = ?1",
+                        params![addressing_hash],
+                        |_| Ok(()),
+                    )
+                    .optional()?
+                    .is_some();
+                let (outcome, write) = resolve_conflict(policy, &addressing_hash, already_stored)?;
+                if !write {
+                    return Ok(outcome);
+                }
+
+                transaction.execute(
+                    "INSERT INTO certificate (addressing_hash, der, not_after, expired) VALUES (?1, ?2, ?3, 0)
+                     ON CONFLICT (addressing_hash) DO UPDATE
+                     SET der = excluded.der, not_after = excluded.not_after, expired = 0",
+                    params![addressing_hash, cert, expiry.not_after],
+                )?;
+                upsert_metadata(&transaction, &addressing_hash, &metadata)?;
+
+                transaction.execute(
+                    "INSERT INTO certificate_name (name, addressing_hash) VALUES (?1, ?2)
+                     ON CONFLICT (name) DO UPDATE SET addressing_hash = excluded.addressing_hash",
+                    params![name, addressing_hash],
+                )?;
+                transaction.execute(
+                    "INSERT OR IGNORE INTO name_history (name, addressing_hash) VALUES (?1, ?2)",
+                    params![name, addressing_hash],
+                )?;
+
+                transaction.execute(
+                    "INSERT INTO key_identifier (key_identifier, addressing_hash) VALUES (?1, ?2)
+                     ON CONFLICT (key_identifier) DO UPDATE SET addressing_hash = excluded.addressing_hash",
+                    params![key_identifier, addressing_hash],
+                )?;
+
+                for alternative_address in alternative_addresses.iter() {
+                    transaction.execute(
+                        "INSERT INTO hash_lookup (lookup_key, addressing_hash) VALUES (?1, ?2)
+                         ON CONFLICT (lookup_key) DO UPDATE SET addressing_hash = excluded.addressing_hash",
+                        params![alternative_address, addressing_hash],
+                    )?;
+                }
+
+                if let Some(key) = &key {
+                    transaction.execute(
+                        "INSERT INTO private_key (addressing_hash, der) VALUES (?1, ?2)
+                         ON CONFLICT (addressing_hash) DO UPDATE SET der = excluded.der",
+                        params![addressing_hash, key],
+                    )?;
+                }
+
+                transaction.commit()?;
+                Ok(outcome)
+            })
+        }
+        .boxed()
+    }
+
+    fn get_cert_by_addressing_hash<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<Vec<u8>, StorageError>> {
+        async move {
+            self.query_one_column(
+                "SELECT der FROM certificate WHERE addressing_hash = ?1",
+                hash,
+                "cert not found",
+            )
+        }
+        .boxed()
+    }
+
+    fn get_key_by_addressing_hash<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<Vec<u8>, StorageError>> {
+        async move {
+            self.query_one_column(
+                "SELECT der FROM private_key WHERE addressing_hash = ?1",
+                hash,
+                "key not found",
+            )
+        }
+        .boxed()
+    }
+
+    fn get_addressing_hash_by_name<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<String, StorageError>> {
+        async move {
+            self.query_one_column(
+                "SELECT addressing_hash FROM certificate_name WHERE name = ?1",
+                name,
+                &format!("hash not found using name {}", name),
+            )
+        }
+        .boxed()
+    }
+
+    fn get_addressing_hashes_by_name<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<Vec<String>, StorageError>> {
+        async move {
+            self.with_connection(|connection| {
+                let mut statement =
+                    connection.prepare("SELECT addressing_hash FROM name_history WHERE name = ?1 ORDER BY id")?;
+                let hashes = statement
+                    .query_map(params![name], |row| row.get(0))?
+                    .collect::<rusqlite::Result<Vec<String>>>()?;
+                Ok(hashes)
+            })
+        }
+        .boxed()
+    }
+
+    fn get_addressing_hash_by_key_identifier<'a>(
+        &'a self,
+        key_identifier: &'a str,
+    ) -> BoxFuture<'a, Result<String, StorageError>> {
+        async move {
+            self.query_one_column(
+                "SELECT addressing_hash FROM key_identifier WHERE key_identifier = ?1",
+                key_identifier,
+                "hash not found",
+            )
+        }
+        .boxed()
+    }
+
+    fn lookup_addressing_hash<'a>(&'a self, lookup_key: &'a str) -> BoxFuture<'a, Result<String, StorageError>> {
+        async move {
+            self.query_one_column(
+                "SELECT addressing_hash FROM hash_lookup WHERE lookup_key = ?1",
+                lookup_key,
+                "hash not found",
+            )
+        }
+        .boxed()
+    }
+
+    fn get_revocations_by_issuer<'a>(
+        &'a self,
+        issuer_name: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<RevocationEntry>, StorageError>> {
+        async move {
+            self.with_connection(|connection| {
+                let mut statement = connection.prepare(
+                    "SELECT addressing_hash, issuer_name, serial_number, revocation_date, reason
+                     FROM revocation WHERE issuer_name = ?1",
+                )?;
+                let revocations = statement
+                    .query_map(params![issuer_name], revocation_from_row)?
+                    .collect::<rusqlite::Result<Vec<_>>>()?;
+                Ok(revocations)
+            })
+        }
+        .boxed()
+    }
+
+    fn revoke(&self, entry: RevocationEntry) -> BoxFuture<'_, Result<(), StorageError>> {
+        async move {
+            self.with_connection(|connection| {
+                connection.execute(
+                    "INSERT INTO revocation (addressing_hash, issuer_name, serial_number, revocation_date, reason)
+                     VALUES (?1, ?2, ?3, ?4, ?5)
+                     ON CONFLICT (addressing_hash) DO UPDATE
+                     SET issuer_name = excluded.issuer_name, serial_number = excluded.serial_number,
+                         revocation_date = excluded.revocation_date, reason = excluded.reason",
+                    params![
+                        entry.addressing_hash,
+                        entry.issuer_name,
+                        entry.serial_number,
+                        entry.revocation_date,
+                        entry.reason,
+                    ],
+                )?;
+                Ok(())
+            })
+        }
+        .boxed()
+    }
+
+    fn list<'a>(
+        &'a self,
+        filter: &'a ListFilter,
+        offset: usize,
+        limit: usize,
+    ) -> BoxFuture<'a, Result<Vec<ListedCertificate>, StorageError>> {
+        async move {
+            // without filter, pagination is left to the database
+            if *filter == ListFilter::default() {
+                return self.with_connection(|connection| {
+                    let mut statement = connection.prepare(
+                        "SELECT addressing_hash, der, not_after, expired FROM certificate ORDER BY addressing_hash
+                         LIMIT ?1 OFFSET ?2",
+                    )?;
+                    let listed = statement
+                        .query_map(params![sql_count(limit), sql_count(offset)], listed_from_row)?
+                        .collect::<rusqlite::Result<Vec<_>>>()?;
+                    Ok(listed)
+                });
+            }
+
+            let listed = self.with_connection(|connection| {
+                let mut statement = connection.prepare(
+                    "SELECT addressing_hash, der, not_after, expired FROM certificate ORDER BY addressing_hash",
+                )?;
+                let listed = statement
+                    .query_map(NO_PARAMS, listed_from_row)?
+                    .collect::<rusqlite::Result<Vec<_>>>()?;
+                Ok(listed)
+            })?;
+
+            Ok(listed
+                .into_iter()
+                .filter(|listed| filter.matches(&listed.cert, listed.expiry.as_ref()))
+                .skip(offset)
+                .take(limit)
+                .collect())
+        }
+        .boxed()
+    }
+
+    fn count(&self) -> BoxFuture<'_, Result<usize, StorageError>> {
+        async move {
+            let count: i64 = self.with_connection(|connection| {
+                Ok(connection.query_row("SELECT COUNT(*) FROM certificate", NO_PARAMS, |row| row.get(0))?)
+            })?;
+            Ok(count as usize)
+        }
+        .boxed()
+    }
+
+    fn get_revocation_by_addressing_hash<'a>(
+        &'a self,
+        hash: &'a str,
+    ) -> BoxFuture<'a, Result<Option<RevocationEntry>, StorageError>> {
+        async move {
+            self.with_connection(|connection| {
+                Ok(connection
+                    .query_row(
+                        "SELECT addressing_hash, issuer_name, serial_number, revocation_date, reason
+                         FROM revocation WHERE addressing_hash = ?1",
+                        params![hash],
+                        revocation_from_row,
+                    )
+                    .optional()?)
+            })
+        }
+        .boxed()
+    }
+
+    fn get_expiry<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<Option<CertificateExpiry>, StorageError>> {
+        async move {
+            self.with_connection(|connection| {
+                Ok(connection
+                    .query_row(
+                        "SELECT not_after, expired FROM certificate WHERE addressing_hash = ?1",
+                        params![hash],
+                        |row| expiry_from_row(row, 0),
+                    )
+                    .optional()?
+                    .flatten())
+            })
+        }
+        .boxed()
+    }
+
+    fn set_expired<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<(), StorageError>> {
+        async move {
+            self.with_connection(|connection| {
+                let updated = connection.execute(
+                    "UPDATE certificate SET expired = 1 WHERE addressing_hash = ?1",
+                    params![hash],
+                )?;
+                if updated == 0 {
+                    return Err(not_found("cert not found"));
+                }
+                Ok(())
+            })
+        }
+        .boxed()
+    }
+
+    fn get_metadata<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<Option<IssuanceMetadata>, StorageError>> {
+        async move {
+            self.with_connection(|connection| {
+                Ok(connection
+                    .query_row(
+                        "SELECT subject, serial_number, ski, aki, not_before, not_after, issued_at
+                         FROM certificate_metadata WHERE addressing_hash = ?1",
+                        params![hash],
+                        metadata_from_row,
+                    )
+                    .optional()?)
+            })
+        }
+        .boxed()
+    }
+
+    fn set_metadata<'a>(
+        &'a self,
+        hash: &'a str,
+        metadata: IssuanceMetadata,
+    ) -> BoxFuture<'a, Result<(), StorageError>> {
+        async move {
+            self.with_connection(|connection| {
+                upsert_metadata(connection, hash, &metadata)?;
+                Ok(())
+            })
+        }
+        .boxed()
+    }
+
+    fn find_expiring_before(&self, not_after: i64) -> BoxFuture<'_, Result<Vec<String>, StorageError>> {
+        async move {
+            self.query_hashes(
+                "SELECT addressing_hash FROM certificate_metadata WHERE not_after < ?1
+                 ORDER BY not_after, addressing_hash",
+                &[&not_after],
+            )
+        }
+        .boxed()
+    }
+
+    fn delete<'a>(&'a self, hash: &'a str) -> BoxFuture<'a, Result<(), StorageError>> {
+        async move {
+            self.with_connection(|connection| {
+                let transaction = connection.transaction()?;
+
+                let deleted =
+                    transaction.execute("DELETE FROM certificate WHERE addressing_hash = ?1", params![hash])?;
+                if deleted == 0 {
+                    return Err(not_found("cert not found"));
+                }
+
+                for table in &[
+                    "private_key",
+                    "certificate_metadata",
+                    "key_identifier",
+                    "hash_lookup",
+                    "name_history",
+                ] {
+                    transaction.execute(
+                        &format!("DELETE FROM {} WHERE addressing_hash = ?1", table),
+                        params![hash],
+                    )?;
+                }
+
+                // names move back to the previous certificate stored under them, if any
+                transaction.execute(
+                    "UPDATE certificate_name SET addressing_hash = (
+                         SELECT history.addressing_hash FROM name_history history
+                         WHERE history.name = certificate_name.name ORDER BY history.id DESC LIMIT 1
+                     )
+                     WHERE addressing_hash = ?1
+                     AND EXISTS (SELECT 1 FROM name_history history WHERE history.name = certificate_name.name)",
+                    params![hash],
+                )?;
+                transaction.execute("DELETE FROM certificate_name WHERE addressing_hash = ?1", params![hash])?;
+
+                transaction.commit()?;
+                Ok(())
+            })
+        }
+        .boxed()
+    }
+
+    fn append_audit(&self, entry: AuditEntry) -> BoxFuture<'_, Result<(), StorageError>> {
+        async move {
+            let json = serde_json::to_string(&entry).map_err(|e| SqliteStorageError::Other {
+                description: format!("couldn't encode audit entry: {}", e),
+            })?;
+            self.with_connection(|connection| {
+                connection.execute(
+                    "INSERT INTO audit (timestamp, entry) VALUES (?1, ?2)",
+                    params![entry.timestamp, json],
+                )?;
+                Ok(())
+            })
+        }
+        .boxed()
+    }
+
+    fn list_audit(&self, since: i64, limit: usize) -> BoxFuture<'_, Result<Vec<AuditEntry>, StorageError>> {
+        async move {
+            let limit = i64::try_from(limit).unwrap_or(i64::MAX);
+            let entries = self.with_connection(|connection| {
+                let mut statement =
+                    connection.prepare("SELECT entry FROM audit WHERE timestamp >= ?1 ORDER BY id LIMIT ?2")?;
+                let entries = statement
+                    .query_map(params![since, limit], |row| row.get(0))?
+                    .collect::<rusqlite::Result<Vec<String>>>()?;
+                Ok(entries)
+            })?;
+
+            entries
+                .iter()
+                .map(|json| {
+                    serde_json::from_str(json).map_err(|e| {
+                        StorageError::from(SqliteStorageError::Other {
+                            description: format!("couldn't decode audit entry: {}", e),
+                        })
+                    })
+                })
+                .collect()
+        }
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_test::block_on;
+
+    fn scratch_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("picky_sqlite_{}_{}.sqlite3", name, std::process::id()));
+        for suffix in &["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.to_string_lossy(), suffix));
+        }
+        path
+    }
+
+    #[test]
+    fn sqlite_storage() {
+        let path = scratch_path("storage");
+        let storage = SqliteStorage::open(&path).expect("sqlite storage");
+        block_on(crate::db::tests::check_storage(&storage));
+        block_on(crate::db::tests::check_pagination(&storage));
+        block_on(crate::db::tests::check_conflicts(&storage));
+        block_on(crate::db::tests::check_metadata(&storage));
+    }
+
+    #[test]
+    fn schema_is_migrated_once() {
+        let path = scratch_path("migration");
+        drop(SqliteStorage::open(&path).expect("sqlite storage"));
+
+        // reopening an up to date database doesn't replay migrations
+        let storage = SqliteStorage::open(&path).expect("sqlite storage reopened");
+        let (version, journal_mode) = storage
+            .with_connection(|connection| {
+                let version: i64 = connection.query_row("PRAGMA user_version", NO_PARAMS, |row| row.get(0))?;
+                let journal_mode: String = connection.query_row("PRAGMA journal_mode", NO_PARAMS, |row| row.get(0))?;
+                Ok((version, journal_mode))
+            })
+            .unwrap();
+        assert_eq!(version, i64::from(SCHEMA_LAST_VERSION));
+        assert_eq!(journal_mode, "wal");
+    }
+
+    #[test]
+    fn database_path_from_config() {
+        let mut config = Config::default();
+        config.file_backend_path = PathBuf::from("database/");
+        assert_eq!(database_path(&config), PathBuf::from("database/picky.sqlite3"));
+
+        config.database_url = "sqlite:///var/lib/picky/picky.db".to_owned();
+        assert_eq!(database_path(&config), PathBuf::from("/var/lib/picky/picky.db"));
+    }
+}
@@ -0,0 +1,234 @@
+use crate::db::{delete_certificate, ListFilter, PickyStorage, StorageError};
+use chrono::{DateTime, Duration, Utc};
+use picky::x509::{certificate::CertType, Cert};
+
+/// Number of certificates fetched at once while sweeping
+const SWEEP_PAGE_SIZE: usize = 100;
+
+/// Outcome of an expiry sweep
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SweepReport {
+    /// Certificates newly flagged as expired
+    pub flagged: usize,
+    /// Expired leaf certificates deleted once past the retention period
+    pub deleted: usize,
+}
+
+/// Flags stored certificates whose notAfter date is past as expired.
+///
+/// When a retention period is given, leaf certificates expired for longer than that are deleted instead. They are
+/// looked up by the notAfter date of their issuance metadata, which certificates stored by earlier versions only get
+/// once described.
+/// CA certificates are never deleted, and deletion is never forced.
+pub async fn sweep_expired(
+    storage: &dyn PickyStorage,
+    retention: Option<Duration>,
+    now: DateTime<Utc>,
+) -> Result<SweepReport, StorageError> {
+    let mut report = SweepReport::default();
+
+    if let Some(retention) = retention {
+        for hash in storage.find_expiring_before((now - retention).timestamp()).await? {
+            let cert_der = storage.get_cert_by_addressing_hash(&hash).await?;
+            match Cert::from_der(&cert_der) {
+                Ok(cert) if !matches!(cert.ty(), CertType::Root | CertType::Intermediate) => {}
+                _ => continue,
+            }
+
+            delete_certificate(storage, &hash, false).await?;
+            log::info!("deleted expired certificate {}", hash);
+            report.deleted += 1;
+        }
+    }
+
+    let filter = ListFilter {
+        expiring_before: Some(now),
+        ..ListFilter::default()
+    };
+
+    let mut to_flag = Vec::new();
+    let mut offset = 0;
+    loop {
+        let page = storage.list(&filter, offset, SWEEP_PAGE_SIZE).await?;
+        let page_len = page.len();
+
+        for listed in page {
+            if !listed.expiry.map(|expiry| expiry.expired).unwrap_or(false) {
+                to_flag.push(listed.addressing_hash);
+            }
+        }
+
+        if page_len < SWEEP_PAGE_SIZE {
+            break;
+        }
+        offset += page_len;
+    }
+
+    for hash in to_flag {
+        storage.set_expired(&hash).await?;
+        report.flagged += 1;
+    }
+
+    Ok(report)
+}
+
+/// Sweeps expired certificates every `interval`, never completes
+pub async fn run_expiry_sweeps(storage: &dyn PickyStorage, interval: std::time::Duration, retention: Option<Duration>) {
+    let mut ticks = tokio::time::interval(interval);
+    loop {
+        ticks.tick().await;
+        match sweep_expired(storage, retention, Utc::now()).await {
+            Ok(report) => log::info!(
+                "expiry sweep: {} certificate(s) flagged as expired, {} deleted",
+                report.flagged,
+                report.deleted
+            ),
+            Err(e) => log::error!("expiry sweep failed: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::{BackendType, Config},
+        db::{get_storage, CertificateEntry, ConflictPolicy},
+        picky_controller::Picky,
+    };
+    use picky::{
+        hash::HashAlgorithm,
+        signature::SignatureAlgorithm,
+        x509::{csr::Csr, name::DirectoryName, KeyIdGenMethod},
+    };
+    use tokio_test::block_on;
+
+    fn store(storage: &dyn PickyStorage, name: &str, cert: &Cert) -> String {
+        let der = cert.to_der().expect("cert der");
+        block_on(storage.store(
+            CertificateEntry {
+                name: name.to_owned(),
+                cert: der.clone(),
+                key_identifier: hex::encode(cert.subject_key_identifier().expect("ski")),
+                key: None,
+            },
+            ConflictPolicy::Overwrite,
+        ))
+        .expect("couldn't store certificate");
+        crate::addressing::encode_to_canonical_address(&der)
+    }
+
+    fn listed_hashes(storage: &dyn PickyStorage, expired: bool) -> Vec<String> {
+        let filter = ListFilter {
+            expired: Some(expired),
+            ..ListFilter::default()
+        };
+        let mut hashes = block_on(storage.list(&filter, 0, 10))
+            .expect("couldn't list certificates")
+            .into_iter()
+            .map(|listed| listed.addressing_hash)
+            .collect::<Vec<_>>();
+        hashes.sort();
+        hashes
+    }
+
+    #[test]
+    fn short_lived_certificates_are_swept() {
+        let config = Config {
+            backend: BackendType::Memory,
+            ..Default::default()
+        };
+        let storage = block_on(get_storage(&config));
+        let signature_algorithm = SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_256);
+
+        let root_key = Picky::generate_private_key(4096).expect("root key");
+        let root = Picky::generate_root(
+            "Sweep Root CA",
+            &root_key,
+            signature_algorithm,
+            KeyIdGenMethod::default(),
+            Duration::days(3650),
+        )
+        .expect("root");
+        let intermediate_key = Picky::generate_private_key(2048).expect("intermediate key");
+        let intermediate = Picky::generate_intermediate(
+            "Sweep Authority",
+            intermediate_key.to_public_key(),
+            &root,
+            &root_key,
+            signature_algorithm,
+            KeyIdGenMethod::default(),
+            Duration::seconds(1),
+        )
+        .expect("intermediate");
+        let leaf_key = Picky::generate_private_key(3072).expect("leaf key");
+        let csr = Csr::generate(
+            DirectoryName::new_common_name("sweep.bushido.local"),
+            &leaf_key,
+            signature_algorithm,
+        )
+        .expect("csr");
+        let leaf = Picky::generate_leaf_from_csr(
+            csr,
+            &root,
+            &root_key,
+            signature_algorithm,
+            KeyIdGenMethod::default(),
+            None,
+            Duration::seconds(1),
+        )
+        .expect("leaf");
+
+        let root_hash = store(storage.as_ref(), "Sweep Root CA", &root);
+        let intermediate_hash = store(storage.as_ref(), "Sweep Authority", &intermediate);
+        let leaf_hash = store(storage.as_ref(), "sweep.bushido.local", &leaf);
+
+        let expiry = block_on(storage.get_expiry(&leaf_hash)).unwrap().expect("expiry");
+        assert_eq!(
+            expiry.not_after,
+            DateTime::<Utc>::from(leaf.valid_not_after()).timestamp()
+        );
+        assert!(!expiry.expired);
+        assert!(listed_hashes(storage.as_ref(), true).is_empty());
+
+        // nothing is expired yet
+        let report = block_on(sweep_expired(storage.as_ref(), None, Utc::now() - Duration::minutes(1))).unwrap();
+        assert_eq!(report, SweepReport::default());
+
+        let later = Utc::now() + Duration::seconds(2);
+        let report = block_on(sweep_expired(storage.as_ref(), Some(Duration::days(1)), later)).unwrap();
+        assert_eq!(report, SweepReport { flagged: 2, deleted: 0 });
+
+        let mut expired = vec![intermediate_hash.clone(), leaf_hash.clone()];
+        expired.sort();
+        assert_eq!(listed_hashes(storage.as_ref(), true), expired);
+        assert_eq!(listed_hashes(storage.as_ref(), false), vec![root_hash.clone()]);
+        assert!(
+            block_on(storage.get_expiry(&leaf_hash))
+                .unwrap()
+                .expect("expiry")
+                .expired
+        );
+
+        // already flagged certificates are left alone
+        let report = block_on(sweep_expired(storage.as_ref(), Some(Duration::days(1)), later)).unwrap();
+        assert_eq!(report, SweepReport::default());
+
+        // past retention, the leaf is deleted but the intermediate CA is kept
+        let report = block_on(sweep_expired(
+            storage.as_ref(),
+            Some(Duration::days(1)),
+            later + Duration::days(2),
+        ))
+        .unwrap();
+        assert_eq!(report, SweepReport { flagged: 0, deleted: 1 });
+        assert!(block_on(storage.get_cert_by_addressing_hash(&leaf_hash)).is_err());
+        assert!(block_on(storage.get_addressing_hash_by_name("sweep.bushido.local")).is_err());
+        assert!(block_on(storage.get_expiry(&leaf_hash)).unwrap().is_none());
+        assert_eq!(
+            block_on(storage.get_addressing_hash_by_name("Sweep Authority")).unwrap(),
+            intermediate_hash
+        );
+        assert_eq!(listed_hashes(storage.as_ref(), true), vec![intermediate_hash]);
+    }
+}
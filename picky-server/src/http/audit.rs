@@ -0,0 +1,149 @@
+use crate::{
+    addressing::encode_to_canonical_address,
+    config::Config,
+    db::{AuditEntry, AuditEvent, PickyStorage},
+    http::{
+        authorization::{identify_api_key, API_KEY_HEADER},
+        middleware::request_log_prefix,
+    },
+    policy::describe_general_name,
+};
+use chrono::Utc;
+use picky::{
+    hash::HashAlgorithm,
+    x509::{name::GeneralNames, Cert, Csr},
+};
+use saphir::{http::header, request::Request};
+
+/// Who asked for an audited operation: the provisioner token subject when a token was presented,
+/// the name of the API key otherwise, or a fingerprint of a key that isn't configured (keys are never written as is)
+pub fn requester<T>(config: &Config, req: &Request<T>, token_subject: Option<&str>) -> String {
+    if let Some(subject) = token_subject {
+        return format!("token:{}", subject);
+    }
+
+    if let Some(api_key) = identify_api_key(config, req) {
+        return format!("api_key:{}", api_key.name);
+    }
+
+    let api_key = req.headers().get(API_KEY_HEADER).map(|key| key.as_bytes()).or_else(|| {
+        let authorization = req.headers().get(header::AUTHORIZATION)?.as_bytes();
+        let mut split = authorization.splitn(2, |b| *b == b' ');
+        match (split.next(), split.next()) {
+            (Some(method), Some(key)) if method.eq_ignore_ascii_case(b"bearer") => Some(key),
+            _ => None,
+        }
+    });
+
+    match api_key {
+        Some(api_key) => format!("api_key:{}", hex::encode(&HashAlgorithm::SHA2_256.digest(api_key)[..8])),
+        None => "anonymous".to_owned(),
+    }
+}
+
+pub fn new_entry(event: AuditEvent, requester: &str) -> AuditEntry {
+    AuditEntry {
+        timestamp: Utc::now().timestamp(),
+        event,
+        requester: requester.to_owned(),
+        subject: None,
+        sans: Vec::new(),
+        serial_number: None,
+        addressing_hash: None,
+        reason: None,
+    }
+}
+
+/// Audit entry describing a certificate
+pub fn cert_entry(event: AuditEvent, requester: &str, cert: &Cert) -> AuditEntry {
+    AuditEntry {
+        subject: cert.subject_name().common_name().map(ToString::to_string),
+        sans: cert
+            .subject_alt_name()
+            .map(|san| describe_sans(&san))
+            .unwrap_or_default(),
+        serial_number: Some(hex::encode(cert.serial_number().as_signed_bytes_be())),
+        addressing_hash: cert.to_der().ok().map(|der| encode_to_canonical_address(&der)),
+        ..new_entry(event, requester)
+    }
+}
+
+/// Audit entry describing a certificate signing request
+pub fn csr_entry(event: AuditEvent, requester: &str, csr: &Csr, reason: Option<String>) -> AuditEntry {
+    let sans = csr
+        .subject_alt_name()
+        .map(|san| describe_sans(&san))
+        .unwrap_or_default();
+
+    AuditEntry {
+        subject: csr.subject_name().common_name().map(ToString::to_string),
+        sans,
+        reason,
+        ..new_entry(event, requester)
+    }
+}
+
+fn describe_sans(san: &GeneralNames) -> Vec<String> {
+    san.to_general_names().iter().map(describe_general_name).collect()
+}
+
+/// Appends an entry to the audit log.
+///
+/// Failures are logged rather than returned so that the audited request isn't failed after the fact.
+pub async fn record(storage: &dyn PickyStorage, entry: AuditEntry) {
+    if let Err(e) = storage.append_audit(entry.clone()).await {
+        log::error!("{}couldn't write audit entry {:?}: {}", request_log_prefix(), entry, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use saphir::prelude::Body;
+
+    #[test]
+    fn requesters() {
+        let request = |headers: &[(&'static str, &'static str)]| {
+            let mut builder = saphir::http::Request::builder();
+            for (name, value) in headers {
+                builder = builder.header(*name, *value);
+            }
+            Request::new(builder.body(Body::empty()).expect("request"), None)
+        };
+
+        let config = Config::default();
+        let api_key = requester(&config, &request(&[(API_KEY_HEADER, "secret-api-key")]), None);
+        assert!(api_key.starts_with("api_key:"));
+        assert!(!api_key.contains("secret"));
+        assert_eq!(
+            api_key,
+            requester(&config, &request(&[("Authorization", "Bearer secret-api-key")]), None)
+        );
+        assert_eq!(
+            requester(
+                &config,
+                &request(&[("Authorization", "Bearer token")]),
+                Some("provisioner")
+            ),
+            "token:provisioner"
+        );
+        assert_eq!(requester(&config, &request(&[]), None), "anonymous");
+
+        // configured keys are named
+        let mut config = Config::default();
+        config.api_key = Some("secret-api-key".to_owned());
+        config.api_keys = vec![crate::config::NamedApiKey {
+            name: "ci".to_owned(),
+            key: "ci-secret".to_owned(),
+            scopes: vec![crate::config::ApiKeyScope::Issue],
+        }];
+        assert_eq!(
+            requester(&config, &request(&[(API_KEY_HEADER, "secret-api-key")]), None),
+            "api_key:default"
+        );
+        assert_eq!(
+            requester(&config, &request(&[(API_KEY_HEADER, "ci-secret")]), None),
+            "api_key:ci"
+        );
+    }
+}
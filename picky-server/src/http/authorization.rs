@@ -1,15 +1,24 @@
 use crate::{
-    config::Config,
-    utils::{unix_epoch, PathOr},
+    config::{ApiKeyScope, AuthMode, Config, NamedApiKey, DEFAULT_API_KEY_NAME},
+    utils::{unix_epoch, wildcard_match, PathOr},
 };
 use picky::{
-    jose::jwt::{JwtDate, JwtSig, JwtValidator},
+    hash::HashAlgorithm,
+    jose::{
+        jwk::JwkSet,
+        jws::Jws,
+        jwt::{JwtDate, JwtError, JwtSig, JwtValidator},
+    },
     key::PublicKey,
     pem::Pem,
 };
-use saphir::{body::Body, http::header, request::Request};
+use saphir::{
+    http::{header, Method},
+    request::Request,
+};
 use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
+use thiserror::Error;
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct ProviderClaims {
@@ -17,6 +26,22 @@ pub struct ProviderClaims {
     pub sub: String,
     pub nbf: u64,
     pub exp: u64,
+    /// Subject common names this token may request, `*` matching any part of a single label.
+    /// Only `sub` is allowed when empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_subjects: Vec<String>,
+}
+
+impl ProviderClaims {
+    pub fn allows_subject(&self, subject: &str) -> bool {
+        if self.allowed_subjects.is_empty() {
+            self.sub == subject
+        } else {
+            self.allowed_subjects
+                .iter()
+                .any(|pattern| wildcard_match(pattern, subject))
+        }
+    }
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -35,7 +60,165 @@ impl From<&str> for AuthorizationMethod {
     }
 }
 
-pub fn check_authorization(config: &Config, req: &Request<Body>) -> Result<JwtSig<serde_json::Value>, String> {
+/// Header carrying the API key when the `Authorization` header is already used by a provisioner token
+pub const API_KEY_HEADER: &str = "Picky-Api-Key";
+
+/// Routes (path prefixes, optionally preceded by a method) never protected by default
+const UNPROTECTED_ROUTES: &[&str] = &["POST /ocsp"];
+
+#[derive(Debug, Error)]
+pub enum ApiKeyError {
+    /// Missing, malformed or unknown API key
+    #[error("{reason}")]
+    Unauthenticated { reason: String },
+    /// Known API key lacking the scope required by the route
+    #[error("API key {name} lacks the {scope} scope")]
+    MissingScope { name: String, scope: ApiKeyScope },
+}
+
+impl ApiKeyError {
+    fn unauthenticated(reason: &str) -> Self {
+        Self::Unauthenticated {
+            reason: reason.to_owned(),
+        }
+    }
+}
+
+/// Configured API keys, `api_key` being a key named "default" with every scope
+pub fn api_keys(config: &Config) -> Vec<Cow<'_, NamedApiKey>> {
+    let default_key = config.api_key.as_ref().map(|key| {
+        Cow::Owned(NamedApiKey {
+            name: DEFAULT_API_KEY_NAME.to_owned(),
+            key: key.clone(),
+            scopes: vec![ApiKeyScope::Admin],
+        })
+    });
+    default_key
+        .into_iter()
+        .chain(config.api_keys.iter().map(Cow::Borrowed))
+        .collect()
+}
+
+/// API key sent in the `Picky-Api-Key` header, or in an `Authorization: Bearer` header
+fn presented_api_key<T>(req: &Request<T>) -> Result<Option<&[u8]>, String> {
+    if let Some(header) = req.headers().get(API_KEY_HEADER) {
+        Ok(Some(header.as_bytes()))
+    } else if let Some(header) = req.headers().get(header::AUTHORIZATION) {
+        let mut split = header.as_bytes().splitn(2, |b| *b == b' ');
+        match (split.next(), split.next()) {
+            (Some(method), Some(token)) if method.eq_ignore_ascii_case(b"bearer") => Ok(Some(token)),
+            _ => Err("Authorization header wrong format".to_owned()),
+        }
+    } else {
+        Ok(None)
+    }
+}
+
+/// Finds the configured API key presented in the request.
+///
+/// Every configured key is compared, so that timing doesn't tell which one matched.
+pub fn identify_api_key<T>(config: &Config, req: &Request<T>) -> Option<NamedApiKey> {
+    let provided = presented_api_key(req).ok()??;
+    let mut found = None;
+    for api_key in api_keys(config) {
+        if constant_time_eq(provided, api_key.key.as_bytes()) && found.is_none() {
+            found = Some(api_key.into_owned());
+        }
+    }
+    found
+}
+
+/// Checks the API key when one is configured and the requested route is protected,
+/// answering the name of the presented key if it was checked.
+pub fn check_api_key<T>(config: &Config, req: &Request<T>, scope: ApiKeyScope) -> Result<Option<String>, ApiKeyError> {
+    if config.auth_mode == AuthMode::Jwt
+        || (config.api_key.is_none() && config.api_keys.is_empty())
+        || !is_protected_route(config, req.method(), req.uri().path())
+    {
+        return Ok(None);
+    }
+
+    verify_api_key(config, req, scope).map(Some)
+}
+
+/// Checks the API key regardless of the requested route, answering the name of the presented key
+pub fn verify_api_key<T>(config: &Config, req: &Request<T>, scope: ApiKeyScope) -> Result<String, ApiKeyError> {
+    if config.api_key.is_none() && config.api_keys.is_empty() {
+        return Err(ApiKeyError::unauthenticated("API key isn't configured"));
+    }
+
+    if presented_api_key(req)
+        .map_err(|e| ApiKeyError::Unauthenticated { reason: e })?
+        .is_none()
+    {
+        return Err(ApiKeyError::unauthenticated("API key is missing"));
+    }
+
+    let api_key = identify_api_key(config, req).ok_or_else(|| ApiKeyError::unauthenticated("invalid API key"))?;
+    if api_key.allows(scope) {
+        Ok(api_key.name)
+    } else {
+        Err(ApiKeyError::MissingScope {
+            name: api_key.name,
+            scope,
+        })
+    }
+}
+
+/// Protected routes are either configured, or every route not using the GET, HEAD or OPTIONS method.
+fn is_protected_route(config: &Config, method: &Method, path: &str) -> bool {
+    let route_matches = |route: &str| {
+        let (route_method, route_path) = match route.find(' ') {
+            Some(idx) => (Some(&route[..idx]), route[idx..].trim()),
+            None => (None, route),
+        };
+        let route_path = route_path.trim_end_matches('/');
+        let path = path.trim_end_matches('/');
+        route_method.is_none_or(|m| m.eq_ignore_ascii_case(method.as_str()))
+            && (path == route_path || path.starts_with(&format!("{}/", route_path)))
+    };
+
+    match &config.protected_routes {
+        Some(routes) => routes.iter().any(|route| route_matches(route)),
+        None => {
+            *method != Method::GET
+                && *method != Method::HEAD
+                && *method != Method::OPTIONS
+                && !UNPROTECTED_ROUTES.iter().any(|route| route_matches(route))
+        }
+    }
+}
+
+/// Compares SHA-256 digests so that timing doesn't depend on where (or whether) lengths differ
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    let a = HashAlgorithm::SHA2_256.digest(a);
+    let b = HashAlgorithm::SHA2_256.digest(b);
+    a.iter().zip(b.iter()).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Authorizes a certificate signing request according to the configured auth mode.
+///
+/// Returns the provisioner claims constraining the certificate to issue, if any.
+pub fn authorize_sign_request<T>(config: &Config, req: &Request<T>) -> Result<Option<ProviderClaims>, String> {
+    match config.auth_mode {
+        AuthMode::ApiKey => {
+            verify_api_key(config, req, ApiKeyScope::Issue).map_err(|e| e.to_string())?;
+            Ok(None)
+        }
+        AuthMode::Jwt | AuthMode::Both => {
+            let token = check_authorization(config, req)?;
+            let claims =
+                serde_json::from_value(token.claims).map_err(|e| format!("invalid provisioner claims: {}", e))?;
+            Ok(Some(claims))
+        }
+    }
+}
+
+/// Validates the provisioner token from the `Authorization: Bearer` header.
+///
+/// The token must be signed by the provisioner public key or one of the provisioner JWKS keys
+/// (matched on `kid` when present) and, if an audience is configured, be issued for it.
+pub fn check_authorization<T>(config: &Config, req: &Request<T>) -> Result<JwtSig<serde_json::Value>, String> {
     let header = match req.headers().get(header::AUTHORIZATION) {
         Some(h) => h,
         None => return Err("Authorization header is missing".to_owned()),
@@ -53,46 +236,91 @@ pub fn check_authorization(config: &Config, req: &Request<Body>) -> Result<JwtSi
     let method = AuthorizationMethod::from(auth_vec[0]);
     match method {
         AuthorizationMethod::Bearer => {
-            let public_key = match config
-                .provisioner_public_key
-                .as_ref()
-                .ok_or_else(|| "provisioner public key is missing".to_owned())?
-            {
-                PathOr::Path(path) => {
-                    let pem_str = std::fs::read_to_string(path)
-                        .map_err(|e| format!("couldn't read provisioner public key: {}", e))?;
-                    let pem = pem_str
-                        .parse::<Pem>()
-                        .map_err(|e| format!("couldn't parse provisioner public key pem: {}", e))?;
-                    Cow::Owned(
-                        PublicKey::from_pem(&pem)
-                            .map_err(|e| format!("couldn't parse provisioner public key: {}", e))?,
-                    )
+            let kid = Jws::decode_without_validation(auth_vec[1])
+                .map_err(|e| format!("couldn't decode json web token: {}", e))?
+                .header
+                .kid;
+            let now = JwtDate::new_with_leeway(unix_epoch() as i64, 10);
+            let mut validator = JwtValidator::strict(&now);
+            if let Some(audience) = &config.provisioner_audience {
+                validator = validator.require_audience(audience);
+            }
+
+            let mut result: Result<JwtSig<serde_json::Value>, String> =
+                Err("provisioner public key is missing".to_owned());
+            for public_key in provisioner_keys(config, kid.as_deref())? {
+                match JwtSig::decode(auth_vec[1], &public_key, &validator) {
+                    Ok(jwt) => {
+                        result = Ok(jwt);
+                        break;
+                    }
+                    Err(JwtError::AudienceMismatch { .. }) | Err(JwtError::RequiredClaimMissing { claim: "aud" }) => {
+                        return Err(format!(
+                            "json web token isn't issued for the {} audience",
+                            config.provisioner_audience.as_deref().unwrap_or_default()
+                        ));
+                    }
+                    Err(e) => result = Err(format!("couldn't validate json web token: {}", e)),
                 }
-                PathOr::Some(key) => Cow::Borrowed(key),
-            };
-
-            Ok(JwtSig::decode(
-                auth_vec[1],
-                &public_key,
-                &JwtValidator::strict(&JwtDate::new_with_leeway(unix_epoch() as i64, 10)),
-            )
-            .map_err(|e| format!("couldn't validate json web token: {}", e))?)
+            }
+
+            result
         }
         AuthorizationMethod::Unknown => Err(format!("Unknown authorization method: {}", auth_vec[0])),
     }
 }
 
+fn provisioner_keys<'a>(config: &'a Config, kid: Option<&str>) -> Result<Vec<Cow<'a, PublicKey>>, String> {
+    let mut keys = Vec::new();
+
+    if let Some(provisioner_public_key) = &config.provisioner_public_key {
+        match provisioner_public_key {
+            PathOr::Path(path) => {
+                let pem_str = std::fs::read_to_string(path)
+                    .map_err(|e| format!("couldn't read provisioner public key: {}", e))?;
+                let pem = pem_str
+                    .parse::<Pem>()
+                    .map_err(|e| format!("couldn't parse provisioner public key pem: {}", e))?;
+                keys.push(Cow::Owned(
+                    PublicKey::from_pem(&pem).map_err(|e| format!("couldn't parse provisioner public key: {}", e))?,
+                ));
+            }
+            PathOr::Some(key) => keys.push(Cow::Borrowed(key)),
+        }
+    }
+
+    if let Some(jwks_path) = &config.provisioner_jwks {
+        let jwks_json =
+            std::fs::read_to_string(jwks_path).map_err(|e| format!("couldn't read provisioner JWKS: {}", e))?;
+        let jwks = JwkSet::from_json(&jwks_json).map_err(|e| format!("couldn't parse provisioner JWKS: {}", e))?;
+        for jwk in jwks.keys {
+            if kid.is_some() && jwk.kid.is_some() && jwk.kid.as_deref() != kid {
+                continue;
+            }
+
+            match jwk.to_public_key() {
+                Ok(key) => keys.push(Cow::Owned(key)),
+                Err(e) => log::warn!("ignoring unsupported provisioner JWK: {}", e),
+            }
+        }
+    }
+
+    Ok(keys)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::{config::BackendType, utils::unix_epoch};
     use picky::{
-        jose::jws::JwsAlg,
+        jose::{jwk::Jwk, jws::JwsAlg},
         key::{PrivateKey, PublicKey},
         pem::Pem,
     };
-    use saphir::http::{request, Method};
+    use saphir::{
+        body::Body,
+        http::{request, Method},
+    };
 
     fn get_private_key_1() -> PrivateKey {
         let pem = include_str!("../../../test_assets/private_keys/rsa-2048-pk_4.key")
@@ -108,14 +336,18 @@ mod tests {
         PrivateKey::from_pem(&pem).expect("key 2")
     }
 
-    fn get_provider_token(private_key: &PrivateKey) -> String {
-        let claims = ProviderClaims {
+    fn get_provider_claims() -> ProviderClaims {
+        ProviderClaims {
             x509_duration_secs: 7_776_000, // 3 months
             sub: "CoolSubject".to_owned(),
             nbf: unix_epoch(),
             exp: unix_epoch() + 10,
-        };
-        let jwt = JwtSig::new(JwsAlg::RS256, claims);
+            allowed_subjects: Vec::new(),
+        }
+    }
+
+    fn get_provider_token(private_key: &PrivateKey) -> String {
+        let jwt = JwtSig::new(JwsAlg::RS256, get_provider_claims());
         jwt.encode(&private_key).expect("jwt encode")
     }
 
@@ -158,6 +390,235 @@ mod tests {
         );
     }
 
+    fn build_api_key_req(method: Method, uri: &str, headers: &[(&str, &str)]) -> Request<Body> {
+        let mut req = request::Builder::new().method(method).uri(uri);
+        for (name, value) in headers {
+            req = req.header(*name, *value);
+        }
+        Request::new(req.body(Body::empty()).expect("couldn't build request"), None)
+    }
+
+    fn api_key_config() -> Config {
+        let mut config = config(None);
+        config.api_key = Some("secret-api-key".to_owned());
+        config
+    }
+
+    #[test]
+    fn api_key_allowed() {
+        let config = api_key_config();
+
+        let req = build_api_key_req(Method::POST, "/cert/", &[(API_KEY_HEADER, "secret-api-key")]);
+        check_api_key(&config, &req, ApiKeyScope::Issue).expect("api key header");
+
+        let req = build_api_key_req(Method::POST, "/cert/", &[("Authorization", "Bearer secret-api-key")]);
+        check_api_key(&config, &req, ApiKeyScope::Issue).expect("bearer api key");
+
+        // unprotected routes
+        let req = build_api_key_req(Method::GET, "/chain", &[]);
+        check_api_key(&config, &req, ApiKeyScope::Issue).expect("GET route");
+        let req = build_api_key_req(Method::POST, "/ocsp", &[]);
+        check_api_key(&config, &req, ApiKeyScope::Issue).expect("OCSP route");
+    }
+
+    #[test]
+    fn api_key_not_configured() {
+        let req = build_api_key_req(Method::POST, "/cert/", &[]);
+        check_api_key(&config(None), &req, ApiKeyScope::Issue).expect("no API key");
+    }
+
+    #[test]
+    fn api_key_missing() {
+        let config = api_key_config();
+        let req = build_api_key_req(Method::POST, "/cert/", &[]);
+        let err = check_api_key(&config, &req, ApiKeyScope::Issue).expect_err("api key err");
+        assert_eq!(err.to_string(), "API key is missing");
+
+        let req = build_api_key_req(Method::DELETE, "/cert/abcdef", &[]);
+        let err = check_api_key(&config, &req, ApiKeyScope::Issue).expect_err("api key err");
+        assert_eq!(err.to_string(), "API key is missing");
+    }
+
+    #[test]
+    fn api_key_wrong() {
+        let config = api_key_config();
+
+        let req = build_api_key_req(Method::POST, "/cert/", &[(API_KEY_HEADER, "secret-api-kez")]);
+        let err = check_api_key(&config, &req, ApiKeyScope::Issue).expect_err("api key err");
+        assert_eq!(err.to_string(), "invalid API key");
+
+        let req = build_api_key_req(Method::POST, "/cert/", &[("Authorization", "Bearer secret")]);
+        let err = check_api_key(&config, &req, ApiKeyScope::Issue).expect_err("api key err");
+        assert_eq!(err.to_string(), "invalid API key");
+
+        let req = build_api_key_req(Method::POST, "/cert/", &[("Authorization", "secret-api-key")]);
+        let err = check_api_key(&config, &req, ApiKeyScope::Issue).expect_err("api key err");
+        assert_eq!(err.to_string(), "Authorization header wrong format");
+    }
+
+    #[test]
+    fn api_key_configured_routes() {
+        let mut config = api_key_config();
+        config.protected_routes = Some(vec!["POST /cert".to_owned(), "/reload".to_owned()]);
+
+        let req = build_api_key_req(Method::POST, "/cert/", &[]);
+        assert!(check_api_key(&config, &req, ApiKeyScope::Issue).is_err());
+        let req = build_api_key_req(Method::GET, "/reload", &[]);
+        assert!(check_api_key(&config, &req, ApiKeyScope::Issue).is_err());
+        let req = build_api_key_req(Method::POST, "/certificate", &[]);
+        check_api_key(&config, &req, ApiKeyScope::Issue).expect("prefix only matches path segments");
+        let req = build_api_key_req(Method::DELETE, "/cert/abcdef", &[]);
+        check_api_key(&config, &req, ApiKeyScope::Issue).expect("unprotected method");
+    }
+
+    fn scoped_api_key_config() -> Config {
+        let mut config = api_key_config();
+        let named = |name: &str, scopes: &[ApiKeyScope]| NamedApiKey {
+            name: name.to_owned(),
+            key: format!("{}-secret", name),
+            scopes: scopes.to_vec(),
+        };
+        config.api_keys = vec![
+            named("monitoring", &[ApiKeyScope::Read]),
+            named("ci", &[ApiKeyScope::Issue, ApiKeyScope::Read]),
+            named("ops", &[ApiKeyScope::Revoke]),
+            named("operator", &[ApiKeyScope::Admin]),
+        ];
+        config
+    }
+
+    #[test]
+    fn scoped_api_keys() {
+        let config = scoped_api_key_config();
+        let cases: &[(&str, ApiKeyScope, Option<&str>)] = &[
+            ("monitoring-secret", ApiKeyScope::Read, Some("monitoring")),
+            ("monitoring-secret", ApiKeyScope::Issue, None),
+            ("monitoring-secret", ApiKeyScope::Admin, None),
+            ("ci-secret", ApiKeyScope::Issue, Some("ci")),
+            ("ci-secret", ApiKeyScope::Read, Some("ci")),
+            ("ci-secret", ApiKeyScope::Revoke, None),
+            ("ops-secret", ApiKeyScope::Revoke, Some("ops")),
+            ("ops-secret", ApiKeyScope::Issue, None),
+            ("operator-secret", ApiKeyScope::Revoke, Some("operator")),
+            ("operator-secret", ApiKeyScope::Admin, Some("operator")),
+            ("secret-api-key", ApiKeyScope::Admin, Some("default")),
+        ];
+
+        for (key, scope, allowed) in cases {
+            let req = build_api_key_req(Method::POST, "/sign", &[(API_KEY_HEADER, *key)]);
+            match (verify_api_key(&config, &req, *scope), allowed) {
+                (Ok(name), Some(expected)) => assert_eq!(name, *expected),
+                (Err(ApiKeyError::MissingScope { name, scope: missing }), None) => {
+                    assert_eq!(missing, *scope);
+                    assert!(!name.contains("secret"));
+                }
+                (result, _) => panic!("{} with {} scope: unexpected {:?}", key, scope, result),
+            }
+        }
+
+        let req = build_api_key_req(Method::POST, "/sign", &[(API_KEY_HEADER, "monitoring-secret")]);
+        let err = verify_api_key(&config, &req, ApiKeyScope::Issue).unwrap_err();
+        assert_eq!(err.to_string(), "API key monitoring lacks the issue scope");
+
+        let req = build_api_key_req(Method::POST, "/sign", &[(API_KEY_HEADER, "unknown-secret")]);
+        assert!(matches!(
+            verify_api_key(&config, &req, ApiKeyScope::Read),
+            Err(ApiKeyError::Unauthenticated { .. })
+        ));
+    }
+
+    #[test]
+    fn scoped_api_keys_without_default_key() {
+        let mut config = scoped_api_key_config();
+        config.api_key = None;
+
+        let req = build_api_key_req(Method::POST, "/sign", &[]);
+        let err = check_api_key(&config, &req, ApiKeyScope::Issue).unwrap_err();
+        assert_eq!(err.to_string(), "API key is missing");
+
+        let req = build_api_key_req(Method::POST, "/sign", &[(API_KEY_HEADER, "secret-api-key")]);
+        let err = check_api_key(&config, &req, ApiKeyScope::Issue).unwrap_err();
+        assert_eq!(err.to_string(), "invalid API key");
+
+        let req = build_api_key_req(Method::POST, "/sign", &[("Authorization", "Bearer ci-secret")]);
+        assert_eq!(
+            check_api_key(&config, &req, ApiKeyScope::Issue).unwrap(),
+            Some("ci".to_owned())
+        );
+    }
+
+    #[test]
+    fn token_authorized_jwks() {
+        let key_1 = get_private_key_1();
+        let key_2 = get_private_key_2();
+
+        let mut jwk_1 = Jwk::from_public_key(&key_1.to_public_key()).expect("jwk 1");
+        jwk_1.kid = Some("key-1".to_owned());
+        let mut jwk_2 = Jwk::from_public_key(&key_2.to_public_key()).expect("jwk 2");
+        jwk_2.kid = Some("key-2".to_owned());
+        let jwks_path = std::env::temp_dir().join("picky_provisioner_jwks_test.json");
+        std::fs::write(
+            &jwks_path,
+            JwkSet {
+                keys: vec![jwk_1, jwk_2],
+            }
+            .to_json()
+            .expect("jwks json"),
+        )
+        .expect("write jwks");
+
+        let mut config = config(None);
+        config.provisioner_jwks = Some(jwks_path);
+
+        let mut jwt = JwtSig::new(JwsAlg::RS256, get_provider_claims());
+        jwt.header.kid = Some("key-2".to_owned());
+        let token = jwt.encode(&key_2).expect("jwt encode");
+        check_authorization(&config, &build_saphir_req(&token)).expect("auth with kid");
+
+        let token = get_provider_token(&key_1);
+        check_authorization(&config, &build_saphir_req(&token)).expect("auth without kid");
+
+        let mut jwt = JwtSig::new(JwsAlg::RS256, get_provider_claims());
+        jwt.header.kid = Some("key-1".to_owned());
+        let token = jwt.encode(&key_2).expect("jwt encode");
+        let err = check_authorization(&config, &build_saphir_req(&token)).expect_err("auth err");
+        assert_eq!(
+            err,
+            "couldn\'t validate json web token: JWS error: signature error: invalid signature"
+        );
+    }
+
+    #[test]
+    fn token_unauthorized_audience() {
+        let key = get_private_key_1();
+        let mut config = config(Some(key.to_public_key()));
+        config.provisioner_audience = Some("picky".to_owned());
+
+        let err = check_authorization(&config, &build_saphir_req(&get_provider_token(&key))).expect_err("auth err");
+        assert_eq!(err, "json web token isn't issued for the picky audience");
+
+        let mut claims = serde_json::to_value(get_provider_claims()).unwrap();
+        claims["aud"] = serde_json::json!(["den", "picky"]);
+        let token = JwtSig::new(JwsAlg::RS256, claims).encode(&key).expect("jwt encode");
+        check_authorization(&config, &build_saphir_req(&token)).expect("auth");
+    }
+
+    #[test]
+    fn allowed_subjects() {
+        let mut claims = get_provider_claims();
+        assert!(claims.allows_subject("CoolSubject"));
+        assert!(!claims.allows_subject("OtherSubject"));
+
+        claims.allowed_subjects = vec!["*.example.com".to_owned(), "web-*.picky.local".to_owned()];
+        assert!(claims.allows_subject("www.example.com"));
+        assert!(claims.allows_subject("WWW.Example.com"));
+        assert!(claims.allows_subject("web-01.picky.local"));
+        assert!(!claims.allows_subject("CoolSubject"));
+        assert!(!claims.allows_subject("example.com"));
+        assert!(!claims.allows_subject("a.b.example.com"));
+        assert!(!claims.allows_subject("db-01.picky.local"));
+    }
+
     #[test]
     fn token_unauthorized_no_den_key() {
         let token = get_provider_token(&get_private_key_1());
@@ -1,666 +1,6124 @@
 use crate::{
-    addressing::{convert_to_canonical_base, CANONICAL_HASH},
-    config::{CertKeyPair, Config},
-    db::{get_storage, BoxedPickyStorage, CertificateEntry, PickyStorage},
+    addressing::{address_candidates, encode_to_canonical_address, CANONICAL_HASH},
+    config::{ApiKeyScope, AuthMode, BackendType, CertKeyPair, Config, KeyType, MIN_CA_RSA_KEY_SIZE},
+    db::{
+        get_storage, issuance_metadata, AuditEntry, AuditEvent, CertificateEntry, ConflictPolicy, HealthStatus,
+        ListFilter, PickyStorage, RevocationEntry, StorageError, StoreOutcome,
+    },
     http::{
-        authorization::{check_authorization, ProviderClaims},
-        utils::{Format, StatusCodeResult},
+        audit,
+        authorization::{
+            authorize_sign_request, check_api_key, check_authorization, identify_api_key, verify_api_key, ApiKeyError,
+            ProviderClaims,
+        },
+        metrics::Metrics,
+        middleware::request_log_prefix,
+        rate_limit::{client_key, RateLimiter, TokenBucketRateLimiter},
+        utils::{load_limited_body, ApiError, ApiErrorKind, Format, SaphirRequestExt, StatusCodeResult},
     },
     logging::build_logger_config,
-    picky_controller::Picky,
-    utils::{GreedyError, PathOr},
+    picky_controller::{IntermediateIssuanceOptions, LeafIssuanceOptions, Picky, PickyError, RootIssuanceOptions},
+    utils::{is_dns_name, GreedyError, PathOr},
+    webhook::{WebhookEvent, Webhooks},
 };
+use chrono::{DateTime, TimeZone, Utc};
 use log4rs::Handle;
 use picky::{
-    pem::{parse_pem, to_pem, Pem},
-    x509::{Cert, Csr},
+    hash::HashAlgorithm,
+    key::PrivateKey,
+    pem::{parse_pem, read_pems, to_pem, Pem},
+    signature::SignatureAlgorithm,
+    x509::{
+        certificate::CertType,
+        crl::CrlReason,
+        csr::{CsrError, VerifiedCsr},
+        date::UTCDate,
+        extension::{AccessDescription, AuthorityInfoAccess},
+        name::{DirectoryName, GeneralName, GeneralNames},
+        ocsp::{OcspRequest, OcspResponse, OcspResponseStatus},
+        Cert, Crl, Csr, Pkcs7,
+    },
 };
 use saphir::{prelude::*, response::Builder as ResponseBuilder};
+use serde::{Deserialize, Serialize};
 use serde_json::{self, Value};
-use std::{borrow::Cow, convert::TryFrom};
+use std::{
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
+    path::Path,
+    str::FromStr,
+    sync::Arc,
+};
+use thiserror::Error;
 use tokio::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
 
-pub struct ServerController {
-    storage: BoxedPickyStorage,
-    config: RwLock<Config>,
-    log_handle: Handle,
+/// A cached CRL is regenerated once its nextUpdate is closer than this
+const CRL_REFRESH_MARGIN_HOURS: i64 = 24;
+
+/// Keys generated for an intermediate CA before giving up finding one not used by another CA
+const MAX_CA_KEY_ATTEMPTS: usize = 10;
+
+/// Validity of certificates signed without provisioner token (3 months)
+const DEFAULT_CERT_DURATION_SECS: u64 = 7_776_000;
+/// Requester recorded in the audit log for operations run by one-off commands (`sign`, `export-ca`)
+const CLI_REQUESTER: &str = "cli";
+/// Header holding the passphrase the private key exported by `GET /ca/export` is encrypted to
+const KEY_PASSPHRASE_HEADER: &str = "Picky-Key-Passphrase";
+
+/// Issuer certificates served under `/aia` never change for a given SKI
+const AIA_CACHE_CONTROL: &str = "public, max-age=31536000, immutable";
+
+#[derive(Deserialize, Default)]
+struct RevocationRequest {
+    /// CRLReason code as defined by RFC 5280 #5.3.1
+    reason: Option<u8>,
 }
 
-impl ServerController {
-    pub async fn new(config: Config, log_handle: Handle) -> Result<Self, String> {
-        let storage = get_storage(&config).await;
-        init_storage_from_config(storage.as_ref(), &config).await?;
+/// Formats accepted for certificates pushed on `POST /cert`
+const CERT_REQUEST_FORMATS: &[Format] = &[
+    Format::PemFile,
+    Format::Json,
+    Format::PkixCertBinary,
+    Format::PkixCertBase64,
+];
+
+/// Formats accepted for certificate signing requests sent to `POST /sign`
+const CSR_REQUEST_FORMATS: &[Format] = &[
+    Format::PemFile,
+    Format::Json,
+    Format::Pkcs10Binary,
+    Format::Pkcs10Base64,
+];
+
+/// Formats accepted by `POST /.well-known/est/simpleenroll`
+const EST_REQUEST_FORMATS: &[Format] = &[Format::Pkcs10Binary, Format::Pkcs10Base64];
+
+/// Line length of base64-encoded EST responses, as per RFC 2045
+const EST_BASE64_LINE_LENGTH: usize = 76;
+
+/// Formats certificates are answered in by `GET /cert/<multihash>` and `POST /sign`, PEM by default
+const CERT_RESPONSE_FORMATS: &[Format] = &[
+    Format::PemFile,
+    Format::PkixCertBinary,
+    Format::PkixCertBase64,
+    Format::Json,
+];
+
+/// Certificate encoded in the format negotiated with the `Accept` header
+#[derive(Debug)]
+struct CertificateBody {
+    content_type: &'static str,
+    file_name: String,
+    body: Vec<u8>,
+}
+
+impl CertificateBody {
+    /// `json` is the body answered when JSON is negotiated, as it depends on the endpoint
+    fn new(format: Format, content_type: &'static str, der: &[u8], json: Option<String>) -> Result<Self, ApiError> {
+        let body = match (format, json) {
+            (Format::PemFile, _) => to_pem("CERTIFICATE", der).into_bytes(),
+            (Format::PkixCertBinary, _) => der.to_vec(),
+            (Format::PkixCertBase64, _) => base64::encode(der).into_bytes(),
+            (Format::Json, Some(json)) => json.into_bytes(),
+            (unexpected, _) => return Err(unexpected_response_format(unexpected)),
+        };
+
         Ok(Self {
-            storage,
-            config: RwLock::new(config),
-            log_handle,
+            content_type,
+            file_name: format!("{}.{}", encode_to_canonical_address(der), format.file_extension()),
+            body,
         })
     }
 
-    async fn read_conf(&self) -> RwLockReadGuard<'_, Config> {
-        self.config.read().await
+    fn respond(self, builder: ResponseBuilder) -> ResponseBuilder {
+        builder
+            .header("Content-Type", self.content_type)
+            .header(
+                "Content-Disposition",
+                format!("attachment; filename=\"{}\"", self.file_name),
+            )
+            .body(self.body)
     }
+}
 
-    async fn write_conf(&self) -> RwLockWriteGuard<'_, Config> {
-        self.config.write().await
-    }
+/// `GET /cert/<multihash>` response body when JSON is requested
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct CertificateResponse {
+    #[serde(flatten)]
+    metadata: CertificateMetadata,
+    certificate: String,
 }
 
-#[controller(name = "")]
-impl ServerController {
-    #[get("/health")]
-    async fn health(&self) -> Result<&'static str, StatusCode> {
-        self.storage.health().await.service_unavailable()?;
-        Ok("I'm alive!")
+/// `POST /sign` response body when JSON is requested
+#[derive(Serialize, Deserialize)]
+struct SignedCertificateResponse {
+    certificate: String,
+    chain: Vec<String>,
+    fingerprint_sha256: String,
+    not_after: String,
+    serial: String,
+    /// Set when the signed certificate is saved by the server
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    stored: Option<StoredCertificate>,
+}
+
+impl SignedCertificateResponse {
+    fn new(cert: &Cert, chain: Vec<String>) -> Result<Self, String> {
+        let der = cert
+            .to_der()
+            .map_err(|e| format!("couldn't get certificate der: {}", e))?;
+        Ok(Self {
+            certificate: to_pem("CERTIFICATE", &der),
+            chain,
+            fingerprint_sha256: hex::encode(HashAlgorithm::SHA2_256.digest(&der)),
+            not_after: DateTime::<Utc>::from(cert.valid_not_after()).to_rfc3339(),
+            serial: hex::encode(cert.serial_number().as_signed_bytes_be()),
+            stored: None,
+        })
     }
+}
 
-    #[post("/cert")]
-    async fn post_cert(&self, req: Request) -> Result<StatusCode, StatusCode> {
-        let req = req.load_body().await.bad_request()?;
+/// Identifiers of a stored certificate, answered by `POST /cert`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+struct StoredCertificate {
+    /// Canonical multihash address
+    addressing_hash: String,
+    /// Hex-encoded subject key identifier
+    ski: String,
+    /// Hex-encoded serial number
+    serial: String,
+    /// `GET /cert/<multihash>` URL of the certificate
+    location: String,
+}
 
-        let (cert, der) = extract_cert_from_request(&req).await.bad_request()?;
-        let ski = hex::encode(cert.subject_key_identifier().bad_request_desc("couldn't fetch SKI")?);
-        let issuer_name = cert
-            .issuer_name()
-            .find_common_name()
-            .bad_request_desc("couldn't find issuer common name")?
-            .to_string();
+impl StoredCertificate {
+    fn new(cert: &Cert) -> Result<Self, String> {
+        let der = cert
+            .to_der()
+            .map_err(|e| format!("couldn't get certificate der: {}", e))?;
+        let ski = cert
+            .subject_key_identifier()
+            .map_err(|e| format!("couldn't get SKI: {}", e))?;
+        Ok(Self::from_der(&der, cert, hex::encode(ski)))
+    }
 
-        if issuer_name != format!("{} Authority", &self.read_conf().await.realm) {
-            log::error!("this certificate was not signed by the CA of this server.");
-            return Err(StatusCode::UNAUTHORIZED);
+    /// Uses the same address as the storage backends
+    fn from_der(der: &[u8], cert: &Cert, ski: String) -> Self {
+        let addressing_hash = encode_to_canonical_address(der);
+        Self {
+            location: format!("/cert/{}", addressing_hash),
+            addressing_hash,
+            ski,
+            serial: hex::encode(cert.serial_number().as_signed_bytes_be()),
         }
+    }
+}
 
-        let subject_name = cert
-            .subject_name()
-            .find_common_name()
-            .bad_request_desc("couldn't find subject issuer common name")?
-            .to_string();
+const PICKY_VERSION: &str = env!("CARGO_PKG_VERSION");
 
-        if let Err(e) = self
-            .storage
-            .store(CertificateEntry {
-                name: subject_name.clone(),
-                cert: der,
-                key_identifier: ski,
-                key: None,
-            })
-            .await
-        {
-            log::error!("insertion failed for leaf {}: {}", subject_name, e);
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
-        }
+/// Readiness report answered by `GET /health/ready`
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct ReadinessReport {
+    /// "ok" when ready, "unavailable" otherwise
+    status: String,
+    backend: BackendType,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    db_latency_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    intermediate_expires_in_days: Option<i64>,
+    version: String,
+    /// Component preventing readiness: "database", "root_ca", "intermediate_ca" or "signing_key"
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    failing_component: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    error: Option<String>,
+}
+
+impl ReadinessReport {
+    fn fail(mut self, component: &str, error: String) -> Self {
+        log::warn!("picky isn't ready, {} check failed: {}", component, error);
+        self.status = "unavailable".to_owned();
+        self.failing_component = Some(component.to_owned());
+        self.error = Some(error);
+        self
+    }
 
-        Ok(StatusCode::OK)
+    fn status_code(&self) -> StatusCode {
+        if self.failing_component.is_some() {
+            StatusCode::SERVICE_UNAVAILABLE
+        } else {
+            StatusCode::OK
+        }
     }
+}
+
+/// Outcome for one certificate of a PEM bundle pushed on `POST /cert`
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct BundleEntryOutcome {
+    /// Position of the PEM block in the bundle
+    index: usize,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    stored: Option<StoredCertificate>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    error: Option<String>,
+}
+
+/// Report answered by `POST /cert` for a PEM bundle
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct BundleReport {
+    certificates: Vec<BundleEntryOutcome>,
+}
 
-    #[options("/sign")]
-    async fn cert_signature_request_cors(&self, req: Request) -> ResponseBuilder {
-        let builder = ResponseBuilder::new()
-            .header("Access-Control-Allow-Methods", "POST")
-            .header("Access-Control-Allow-Headers", "Authorization, Content-Type");
+impl BundleReport {
+    fn new(results: Vec<Result<StoredCertificate, RegisterError>>) -> Self {
+        let certificates = results
+            .into_iter()
+            .enumerate()
+            .map(|(index, result)| match result {
+                Ok(stored) => BundleEntryOutcome {
+                    index,
+                    stored: Some(stored),
+                    error: None,
+                },
+                Err(e) => BundleEntryOutcome {
+                    index,
+                    stored: None,
+                    error: Some(e.to_string()),
+                },
+            })
+            .collect();
+        Self { certificates }
+    }
 
-        if let Some(origin_header) = req.headers().get("Origin") {
-            builder.header("Access-Control-Allow-Origin", origin_header)
+    /// 200 when all certificates are stored, 400 when none is, 207 otherwise
+    fn status_code(&self) -> StatusCode {
+        let stored = self.certificates.iter().filter(|entry| entry.stored.is_some()).count();
+        if stored == self.certificates.len() {
+            StatusCode::OK
+        } else if stored == 0 {
+            StatusCode::BAD_REQUEST
         } else {
-            builder
+            StatusCode::MULTI_STATUS
         }
     }
+}
 
-    #[post("/sign")]
-    async fn cert_signature_request(&self, req: Request) -> Result<ResponseBuilder, StatusCode> {
-        let (locked_subject_name, x509_duration_secs) = match check_authorization(&*self.read_conf().await, &req) {
-            Ok(token) => {
-                let provider_claims: ProviderClaims = serde_json::from_value(token.claims).bad_request()?;
-                (provider_claims.sub, provider_claims.x509_duration_secs)
-            }
-            Err(e) => {
-                log::error!("authorization failed: {}", e);
-                return Err(StatusCode::UNAUTHORIZED);
-            }
-        };
+/// Maximum number of certificates listed by a single `GET /certs` request
+const MAX_CERTS_PAGE_SIZE: usize = 100;
 
-        let req = req.load_body().await.bad_request()?;
+/// Number of certificates listed by `GET /certs` when no limit is given
+const DEFAULT_CERTS_PAGE_SIZE: usize = 20;
 
-        let csr = extract_csr_from_request(&req).await.bad_request()?;
+/// Maximum number of entries answered by a single `GET /audit` request
+const MAX_AUDIT_PAGE_SIZE: usize = 1000;
 
-        let subject_name = csr
-            .subject_name()
-            .find_common_name()
-            .bad_request_desc("couldn't find signed CSR subject common name")?
-            .to_string();
+/// Number of entries answered by `GET /audit` when no limit is given
+const DEFAULT_AUDIT_PAGE_SIZE: usize = 100;
 
-        if locked_subject_name != subject_name {
-            log::error!(
-                "Requested a certificate with an unauthorized subject name: {}, expected: {}",
-                subject_name,
-                locked_subject_name
-            );
-            return Err(StatusCode::UNAUTHORIZED);
-        }
+/// Audit entries answered by `GET /audit`, oldest first
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct AuditList {
+    entries: Vec<AuditEntry>,
+}
 
-        // Sign CSR
-        let conf = self.read_conf().await;
-        let ca_name = format!("{} Authority", &conf.realm);
-        let signed_cert = sign_certificate(&ca_name, csr, &conf, self.storage.as_ref(), x509_duration_secs)
-            .await
-            .internal_error()?;
-        drop(conf); // release lock early
+/// Description of a stored certificate, as answered by `GET /certs` and `GET /cert/name/<name>`
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct CertificateMetadata {
+    hash: String,
+    subject: String,
+    serial: String,
+    ski: String,
+    not_before: String,
+    not_after: String,
+    /// Storage date, notBefore date for certificates stored before it was recorded
+    issued_at: String,
+    revoked: bool,
+    /// Flagged as expired by the expiry sweep
+    expired: bool,
+}
 
-        let builder = match Format::response_format(&req).unwrap_or(Format::PemFile) {
-            Format::PemFile => {
-                let pem = signed_cert
-                    .to_pem()
-                    .internal_error_desc("couldn't get certificate pem")?;
-                ResponseBuilder::new().body(pem.to_string())
-            }
-            Format::PkixCertBinary => {
-                let der = signed_cert
-                    .to_der()
-                    .internal_error_desc("couldn't get certificate der")?;
-                ResponseBuilder::new().body(der)
-            }
-            Format::PkixCertBase64 => {
-                let der = signed_cert
-                    .to_der()
-                    .internal_error_desc("couldn't get certificate der")?;
-                ResponseBuilder::new().body(base64::encode(&der))
-            }
-            unexpected => {
-                log::error!("unexpected response format: {}", unexpected);
-                return Err(StatusCode::BAD_REQUEST);
-            }
-        };
+impl CertificateMetadata {
+    async fn load(storage: &dyn PickyStorage, hash: String) -> Result<Self, String> {
+        let metadata = issuance_metadata(storage, &hash)
+            .await
+            .map_err(|e| format!("couldn't fetch metadata of {}: {}", hash, e))?;
+        let revoked = storage
+            .get_revocation_by_addressing_hash(&hash)
+            .await
+            .map_err(|e| format!("couldn't fetch revocation status of {}: {}", hash, e))?
+            .is_some();
+        let expired = storage
+            .get_expiry(&hash)
+            .await
+            .map_err(|e| format!("couldn't fetch expiry status of {}: {}", hash, e))?
+            .map(|expiry| expiry.expired)
+            .unwrap_or(false);
 
-        Ok(builder)
+        Ok(Self {
+            hash,
+            subject: metadata.subject,
+            serial: metadata.serial_number,
+            ski: metadata.ski.unwrap_or_default(),
+            not_before: Utc.timestamp(metadata.not_before, 0).to_rfc3339(),
+            not_after: Utc.timestamp(metadata.not_after, 0).to_rfc3339(),
+            issued_at: Utc.timestamp(metadata.issued_at, 0).to_rfc3339(),
+            revoked,
+            expired,
+        })
     }
+}
 
-    #[get("/cert/<multihash>")]
-    async fn get_cert(&self, multihash: String, req: Request) -> Result<ResponseBuilder, StatusCode> {
-        let addressing_hash_any_base = multihash;
-        let (addressing_hash, hash) = convert_to_canonical_base(&addressing_hash_any_base).internal_error()?;
-        let canonical_address = if hash == CANONICAL_HASH {
-            addressing_hash
-        } else {
-            let converted = self
-                .storage
-                .lookup_addressing_hash(&addressing_hash)
-                .await
-                .internal_error_desc("address lookup failed")?;
-            log::info!("converted cert address {} -> {}", addressing_hash_any_base, converted);
-            converted
-        };
+/// Names requested by a CSR, as answered by `POST /name?format=json`
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct RequestedNames {
+    common_name: Option<String>,
+    subject: String,
+    dns_sans: Vec<String>,
+    ip_sans: Vec<String>,
+}
 
-        let cert_der = match self.storage.get_cert_by_addressing_hash(&canonical_address).await {
-            Ok(cert_der) => cert_der,
-            Err(e) => {
-                log::error!("couldn't fetch certificate using hash {}: {}", canonical_address, e);
-                return Err(StatusCode::NOT_FOUND);
-            }
-        };
+impl RequestedNames {
+    fn new(csr: &Csr) -> Self {
+        let san = csr.subject_alt_name();
 
-        match Format::response_format(&req).unwrap_or(Format::PemFile) {
-            Format::PemFile => Ok(ResponseBuilder::new().body(to_pem("CERTIFICATE", &cert_der))),
-            Format::PkixCertBinary => Ok(ResponseBuilder::new().body(cert_der)),
-            Format::PkixCertBase64 => Ok(ResponseBuilder::new().body(base64::encode(&cert_der))),
-            unexpected => {
-                log::error!("unexpected response format: {}", unexpected);
-                Err(StatusCode::BAD_REQUEST)
-            }
+        Self {
+            common_name: csr.subject_name().common_name().map(ToString::to_string),
+            subject: csr.subject_name().to_string(),
+            dns_sans: san
+                .as_ref()
+                .map(|san| san.dns_names().into_iter().map(ToString::to_string).collect())
+                .unwrap_or_default(),
+            ip_sans: san
+                .as_ref()
+                .map(|san| san.ips().into_iter().map(|ip| ip.to_string()).collect())
+                .unwrap_or_default(),
         }
     }
+}
 
-    #[get("/chain")]
-    async fn get_default_chain(&self, req: Request) -> Result<ResponseBuilder, StatusCode> {
-        let builder = if let Some(origin_header) = req.headers().get("Origin") {
-            ResponseBuilder::new().header("Access-Control-Allow-Origin", origin_header)
-        } else {
-            ResponseBuilder::new()
-        };
-
-        let ca = format!("{} Authority", &self.read_conf().await.realm);
-        let chain = find_ca_chain(self.storage.as_ref(), &ca).await.not_found()?;
-        Ok(builder.body(chain.join("\n")))
-    }
+/// Intermediate CA certificates can be signed with, as answered by `GET /cas`
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct CaDescription {
+    name: String,
+    ski: String,
+    not_before: String,
+    not_after: String,
+    default: bool,
+}
 
-    #[get("/reload")]
-    async fn reload_yaml_conf(&self) -> (&'static str, StatusCode) {
-        match self.reload_yaml_conf_impl().await {
-            Ok(()) => ("Config reloaded successfully!", StatusCode::OK),
-            Err(e) => {
-                log::error!("couldn't reload config: {}", e);
-                ("Couldn't reload config... See logs", StatusCode::INTERNAL_SERVER_ERROR)
-            }
+impl CaDescription {
+    fn new(name: String, cert: &Cert, default: bool) -> Self {
+        Self {
+            ski: cert.subject_key_identifier().map(hex::encode).unwrap_or_default(),
+            not_before: DateTime::<Utc>::from(cert.valid_not_before()).to_rfc3339(),
+            not_after: DateTime::<Utc>::from(cert.valid_not_after()).to_rfc3339(),
+            default,
+            name,
         }
     }
 }
 
-impl ServerController {
-    async fn reload_yaml_conf_impl(&self) -> Result<(), String> {
-        match Config::init_yaml() {
-            Ok(new_conf) => {
-                log::info!("new config: {:#?}", new_conf);
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct CaList {
+    cas: Vec<CaDescription>,
+}
 
-                init_storage_from_config(self.storage.as_ref(), &new_conf).await?;
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum CaRole {
+    Root,
+    Intermediate,
+}
 
-                match build_logger_config(&new_conf) {
-                    Ok(logger_config) => self.log_handle.set_config(logger_config),
-                    Err(e) => {
-                        log::warn!("couldn't reload logger configuration: {}", e);
-                    }
-                }
+/// `GET /ca/export` response body
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct CaExport {
+    name: String,
+    /// PEM certificates, the exported CA first and the root last
+    chain: Vec<String>,
+    /// PEM PKCS#8 private key encrypted to the requested passphrase (`ENCRYPTED PRIVATE KEY`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    encrypted_key: Option<String>,
+}
 
-                let mut old_conf = self.write_conf().await;
-                if old_conf.database_url != new_conf.database_url {
-                    log::warn!("'database_url' modification require service restart");
-                }
-                if old_conf.file_backend_path != new_conf.file_backend_path {
-                    log::warn!("'file_backend_path' modification require service restart");
-                }
-                if old_conf.backend != new_conf.backend {
-                    log::warn!("'backend' modification require service restart");
-                }
-                *old_conf = new_conf;
+/// `POST /ca` request body
+#[derive(Deserialize)]
+struct CaImportRequest {
+    /// PEM certificate
+    certificate: String,
+    /// PEM private key
+    key: String,
+    /// Passphrase the private key is encrypted to, when given as a PEM PKCS#8 `ENCRYPTED PRIVATE KEY`
+    #[serde(default)]
+    passphrase: Option<String>,
+    role: CaRole,
+}
 
-                log::info!("reloaded successfully");
-                Ok(())
-            }
-            Err(e) => Err(format!("couldn't reload config: {}", e)),
-        }
+/// `POST /ca` response body
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct ImportedCa {
+    name: String,
+    ski: String,
+    role: CaRole,
+}
+
+/// CA chain answered by `GET /chain` when JSON is requested
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct ChainResponse {
+    chain: Vec<ChainEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct ChainEntry {
+    subject: String,
+    pem: String,
+    ski: String,
+}
+
+/// Page of certificates answered by `GET /certs`
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct CertificateList {
+    certificates: Vec<CertificateMetadata>,
+    /// Number of stored certificates, filters aside
+    total: usize,
+    /// Offset of the next page, absent on the last page
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    next_offset: Option<usize>,
+}
+
+/// Configuration swapped at runtime, shared by the controller and whoever triggers a reload (`/reload`, SIGHUP)
+#[derive(Clone)]
+pub struct ConfigReloader {
+    config: Arc<RwLock<Config>>,
+    rate_limiter: Arc<std::sync::RwLock<Option<Arc<dyn RateLimiter>>>>,
+    storage: Arc<dyn PickyStorage>,
+    /// Reconfigured with the new log settings, the logger being left alone when absent
+    log_handle: Option<Handle>,
+    /// Serializes reloads, requests keep being served with the previous configuration meanwhile
+    reloading: Arc<tokio::sync::Mutex<()>>,
+}
+
+fn build_rate_limiter(config: &Config) -> Option<Arc<dyn RateLimiter>> {
+    if config.rate_limit_per_minute > 0 {
+        Some(Arc::new(TokenBucketRateLimiter::new(
+            config.rate_limit_per_minute,
+            config.rate_limit_burst,
+        )))
+    } else {
+        None
     }
 }
 
-async fn extract_cert_from_request(req: &Request<Bytes>) -> Result<(Cert, Vec<u8>), GreedyError> {
-    match Format::request_format(&req)? {
-        Format::PemFile => {
-            let pem = parse_pem(req.body())?;
-            Ok((Cert::from_der(pem.data())?, pem.into_data().into_owned()))
-        }
-        Format::Json => {
-            let json = serde_json::from_slice::<Value>(req.body())?;
-            let pem = json["certificate"]
-                .to_string()
-                .trim_matches('"')
-                .replace("\\n", "\n")
-                .parse::<Pem>()?;
-            Ok((Cert::from_der(pem.data())?, pem.into_data().into_owned()))
-        }
-        Format::PkixCertBinary => Ok((Cert::from_der(req.body())?, req.body().to_vec())),
-        Format::PkixCertBase64 => {
-            let der = base64::decode(req.body())?;
-            Ok((Cert::from_der(&der)?, der))
+impl ConfigReloader {
+    fn new(config: Config, storage: Arc<dyn PickyStorage>, log_handle: Option<Handle>) -> Self {
+        Self {
+            rate_limiter: Arc::new(std::sync::RwLock::new(build_rate_limiter(&config))),
+            config: Arc::new(RwLock::new(config)),
+            storage,
+            log_handle,
+            reloading: Arc::new(tokio::sync::Mutex::new(())),
         }
-        unexpected => Err(GreedyError(format!("unexpected request format: {}", unexpected))),
     }
-}
 
-async fn extract_csr_from_request(req: &Request<Bytes>) -> Result<Csr, GreedyError> {
-    match Format::request_format(req)? {
-        Format::PemFile => {
-            let pem = parse_pem(req.body())?;
-            Ok(Csr::from_der(pem.data())?)
+    /// Reads the configuration file and the environment again, then swaps the new configuration in
+    /// and answers the names of the settings that changed.
+    ///
+    /// The running configuration is kept when the new one is invalid or changes a setting only read
+    /// on startup, such as the backend or the listen address.
+    pub async fn reload(&self) -> Result<Vec<String>, String> {
+        let _reloading = self.reloading.lock().await;
+
+        let old_conf = self.config.read().await.clone();
+        let (new_conf, unknown_keys) = old_conf.reread()?;
+        if !unknown_keys.is_empty() {
+            log::warn!("unknown configuration keys ignored: {}", unknown_keys.join(", "));
         }
-        Format::Json => {
-            let json = serde_json::from_slice::<Value>(req.body())?;
-            let pem = json["csr"]
-                .to_string()
-                .trim_matches('"')
-                .replace("\\n", "\n")
-                .parse::<Pem>()?;
-            Ok(Csr::from_der(pem.data())?)
+        new_conf.validate().map_err(|e| e.to_string())?;
+        old_conf.check_reloadable(&new_conf)?;
+
+        self.storage.clear_cache();
+        init_storage_from_config(self.storage.as_ref(), &new_conf).await?;
+
+        if let Some(log_handle) = &self.log_handle {
+            match build_logger_config(&new_conf) {
+                Ok(logger_config) => log_handle.set_config(logger_config),
+                Err(e) => {
+                    log::warn!("couldn't reload logger configuration: {}", e);
+                }
+            }
         }
-        Format::Pkcs10Binary => Ok(Csr::from_der(req.body())?),
-        Format::Pkcs10Base64 => {
-            let der = base64::decode(req.body())?;
-            Ok(Csr::from_der(&der)?)
+
+        let changed = old_conf.changed_settings(&new_conf);
+        if old_conf.rate_limit_per_minute != new_conf.rate_limit_per_minute
+            || old_conf.rate_limit_burst != new_conf.rate_limit_burst
+        {
+            *self
+                .rate_limiter
+                .write()
+                .expect("couldn't lock rate limiter (poisoned)") = build_rate_limiter(&new_conf);
         }
-        unexpected => Err(GreedyError(format!("unexpected request format: {}", unexpected))),
+        *self.config.write().await = new_conf;
+
+        if changed.is_empty() {
+            log::info!("configuration reloaded, nothing changed");
+        } else {
+            log::info!("configuration reloaded, changed settings: {}", changed.join(", "));
+        }
+        Ok(changed)
+    }
+
+    fn rate_limiter(&self) -> Option<Arc<dyn RateLimiter>> {
+        self.rate_limiter
+            .read()
+            .expect("couldn't lock rate limiter (poisoned)")
+            .clone()
     }
 }
 
-async fn sign_certificate(
-    ca_name: &str,
-    csr: Csr,
-    config: &Config,
-    storage: &dyn PickyStorage,
-    duration_secs: u64,
-) -> Result<Cert, String> {
-    let ca_hash = storage
-        .get_addressing_hash_by_name(ca_name)
-        .await
-        .map_err(|e| format!("couldn't fetch CA: {}", e))?;
+/// Reloads the configuration on every SIGHUP
+#[cfg(unix)]
+pub async fn reload_on_hangup(reloader: ConfigReloader) {
+    use tokio::signal::unix::{signal, SignalKind};
 
-    let ca_cert_der = storage
-        .get_cert_by_addressing_hash(&ca_hash)
-        .await
-        .map_err(|e| format!("couldn't get CA cert der: {}", e))?;
-    let ca_cert = Cert::from_der(&ca_cert_der).map_err(|e| format!("couldn't deserialize CA cert: {}", e))?;
+    let mut hangup = signal(SignalKind::hangup()).expect("couldn't listen to SIGHUP");
+    while hangup.recv().await.is_some() {
+        log::info!("SIGHUP received, reloading configuration");
+        if let Err(e) = reloader.reload().await {
+            log::error!("couldn't reload configuration: {}", e);
+        }
+    }
+}
 
-    let ca_pk_der = storage
-        .get_key_by_addressing_hash(&ca_hash)
-        .await
-        .map_err(|e| format!("couldn't fetch CA private key: {}", e))?;
-    let ca_pk = Picky::parse_pk_from_magic_der(&ca_pk_der).map_err(|e| e.to_string())?;
+pub struct ServerController {
+    storage: Arc<dyn PickyStorage>,
+    reloader: ConfigReloader,
+    crl_cache: RwLock<HashMap<String, Crl>>,
+    metrics: Arc<Metrics>,
+    webhooks: Webhooks,
+}
 
-    let dns_name = csr
-        .subject_name()
-        .find_common_name()
-        .ok_or_else(|| "couldn't find signed cert subject common name")?
-        .to_string();
+impl ServerController {
+    pub async fn new(config: Config, log_handle: Handle, metrics: Arc<Metrics>) -> Result<Self, String> {
+        let storage: Arc<dyn PickyStorage> = Arc::from(get_storage(&config).await);
+        init_storage_from_config(storage.as_ref(), &config).await?;
+        Ok(Self {
+            webhooks: Webhooks::start(&config),
+            reloader: ConfigReloader::new(config, Arc::clone(&storage), Some(log_handle)),
+            storage,
+            crl_cache: RwLock::new(HashMap::new()),
+            metrics,
+        })
+    }
 
-    let signed_cert = Picky::generate_leaf_from_csr(
-        csr,
-        &ca_cert,
-        &ca_pk,
-        config.signing_algorithm,
-        &dns_name,
-        chrono::Duration::seconds(
-            i64::try_from(duration_secs).map_err(|e| format!("invalid x509 duration (too big?): {}", e))?,
-        ),
-    )
-    .map_err(|e| format!("couldn't generate leaf certificate: {}", e))?;
+    pub fn storage(&self) -> Arc<dyn PickyStorage> {
+        Arc::clone(&self.storage)
+    }
 
-    if config.save_certificate {
-        let cert_der = signed_cert
-            .to_der()
-            .map_err(|e| format!("couldn't serialize certificate to der: {}", e))?;
-        let ski = hex::encode(
-            signed_cert
-                .subject_key_identifier()
-                .map_err(|e| format!("couldn't get SKI: {}", e))?,
-        );
+    pub fn reloader(&self) -> ConfigReloader {
+        self.reloader.clone()
+    }
 
-        storage
-            .store(CertificateEntry {
-                name: dns_name.clone(),
-                cert: cert_der,
-                key_identifier: ski,
-                key: None,
-            })
-            .await
-            .map_err(|e| format!("insertion error for leaf {}: {}", dns_name, e))?;
+    /// Issues a server certificate for picky itself, returning the PEM certificate chain and private key
+    pub async fn bootstrap_tls_identity(&self) -> Result<(String, String), String> {
+        generate_tls_identity(&*self.read_conf().await, self.storage.as_ref()).await
     }
 
-    Ok(signed_cert)
+    async fn read_conf(&self) -> RwLockReadGuard<'_, Config> {
+        self.reloader.config.read().await
+    }
+
+    async fn write_conf(&self) -> RwLockWriteGuard<'_, Config> {
+        self.reloader.config.write().await
+    }
 }
 
-async fn find_ca_chain(storage: &dyn PickyStorage, ca_name: &str) -> Result<Vec<String>, String> {
-    let ca_hash = storage
-        .get_addressing_hash_by_name(ca_name)
-        .await
-        .map_err(|e| format!("couldn't fetch CA hash id for {}: {}", ca_name, e))?;
+#[controller(name = "")]
+impl ServerController {
+    // same as `/health/live`, kept for existing probes
+    #[get("/health")]
+    async fn health(&self, req: Request) -> Result<ResponseBuilder, ApiError> {
+        self.authorize_api_key(&req, ApiKeyScope::Read).await?;
+        Ok(ResponseBuilder::new()
+            .header("Content-Type", "application/json")
+            .body(liveness_report().to_string()))
+    }
 
-    let mut cert_der = storage
-        .get_cert_by_addressing_hash(&ca_hash)
-        .await
-        .map_err(|e| format!("couldn't fetch CA certificate der: {}", e))?;
-    let mut chain = vec![to_pem("CERTIFICATE", &cert_der)];
-    let mut current_key_id = String::default();
-    loop {
-        let cert = Cert::from_der(&cert_der).map_err(|e| format!("couldn't deserialize certificate: {}", e))?;
+    #[get("/health/live")]
+    async fn health_live(&self, req: Request) -> Result<ResponseBuilder, ApiError> {
+        self.authorize_api_key(&req, ApiKeyScope::Read).await?;
+        Ok(ResponseBuilder::new()
+            .header("Content-Type", "application/json")
+            .body(liveness_report().to_string()))
+    }
 
-        let parent_key_id = hex::encode(
-            cert.authority_key_identifier()
-                .map_err(|e| format!("couldn't fetch authority key identifier: {}", e))?
-                .key_identifier()
-                .ok_or_else(|| "parent key identifier not found".to_owned())?,
+    #[get("/health/ready")]
+    async fn health_ready(&self, req: Request) -> Result<ResponseBuilder, ApiError> {
+        self.authorize_api_key(&req, ApiKeyScope::Read).await?;
+        let report = check_readiness(&*self.read_conf().await, self.storage.as_ref()).await;
+
+        let json = serde_json::to_string(&report).internal_error_desc("couldn't encode readiness report")?;
+        Ok(ResponseBuilder::new()
+            .status(report.status_code())
+            .header("Content-Type", "application/json")
+            .body(json))
+    }
+
+    #[get("/metrics")]
+    async fn metrics(&self, req: Request) -> Result<ResponseBuilder, ApiError> {
+        if !self.read_conf().await.metrics_enabled {
+            return Err(ApiError::new(ApiErrorKind::NotFound, "metrics are disabled"));
+        }
+        self.authorize_api_key(&req, ApiKeyScope::Read).await?;
+
+        let text = scrape_metrics(&self.metrics, &*self.read_conf().await, self.storage.as_ref()).await;
+        Ok(ResponseBuilder::new()
+            .header("Content-Type", "text/plain; version=0.0.4")
+            .body(text))
+    }
+
+    #[post("/cert")]
+    async fn post_cert(&self, req: Request) -> Result<ResponseBuilder, ApiError> {
+        let req = self.load_body(req).await?;
+        self.post_cert_impl(req).await
+    }
+
+    #[post("/sign")]
+    async fn cert_signature_request(&self, req: Request) -> Result<ResponseBuilder, ApiError> {
+        self.authorize_api_key(&req, ApiKeyScope::Issue).await?;
+        let provider_claims =
+            authorize_sign_request(&*self.read_conf().await, &req).unauthorized_desc("authorization failed")?;
+        self.check_rate_limit(&req, provider_claims.as_ref().map(|claims| claims.sub.as_str()))
+            .await?;
+        let requester = audit::requester(
+            &*self.read_conf().await,
+            &req,
+            provider_claims.as_ref().map(|claims| claims.sub.as_str()),
         );
 
-        if current_key_id == parent_key_id {
-            // The authority is itself. It is a root.
-            break;
+        check_request_format(&req, CSR_REQUEST_FORMATS)?;
+        let (format, content_type) = Format::negotiate(&req, CERT_RESPONSE_FORMATS)?;
+
+        let req = self.load_body(req).await?;
+
+        let csr = extract_csr_from_request(&req)
+            .await
+            .api_error(ApiErrorKind::InvalidCsr, "couldn't deserialize CSR")?;
+
+        let signed_cert = self
+            .issue_certificate(
+                csr,
+                extract_requested_ca(&req).as_deref(),
+                extract_reuse_request(&req),
+                provider_claims,
+                &requester,
+            )
+            .await?;
+        let stored = if self.read_conf().await.save_certificate {
+            Some(StoredCertificate::new(&signed_cert).internal_error_desc("couldn't identify stored certificate")?)
+        } else {
+            None
+        };
+
+        let json = if format == Format::Json {
+            let max_chain_depth = self.read_conf().await.max_chain_depth;
+            let chain = find_issuer_chain(self.storage.as_ref(), &signed_cert, max_chain_depth)
+                .await
+                .internal_error_desc("couldn't fetch CA chain")?;
+            let mut response = SignedCertificateResponse::new(&signed_cert, chain)
+                .internal_error_desc("couldn't build signed certificate response")?;
+            response.stored = stored.clone();
+            Some(serde_json::to_string(&response).internal_error_desc("couldn't encode signed certificate")?)
+        } else {
+            None
+        };
+        let der = signed_cert
+            .to_der()
+            .internal_error_desc("couldn't get certificate der")?;
+        let builder = match stored {
+            Some(stored) => ResponseBuilder::new().header("Location", stored.location),
+            None => ResponseBuilder::new(),
+        };
+
+        Ok(CertificateBody::new(format, content_type, &der, json)?.respond(builder))
+    }
+
+    // EST "Distribution of CA Certificates" (RFC 7030 #4.1):
+    // the default CA chain, root included, as a base64-encoded certs-only PKCS#7
+    #[get("/.well-known/est/cacerts")]
+    async fn est_ca_certs(&self, req: Request) -> Result<ResponseBuilder, ApiError> {
+        self.authorize_api_key(&req, ApiKeyScope::Read).await?;
+
+        let conf = self.read_conf().await;
+        let ca_name = select_ca(&conf, None)?;
+        let max_chain_depth = conf.max_chain_depth;
+        drop(conf); // release lock early
+        let chain = find_ca_chain_der(self.storage.as_ref(), &ca_name, max_chain_depth)
+            .await
+            .api_error(ApiErrorKind::CaNotFound, "couldn't find CA chain")?;
+        let body = est_certs_body(&chain).internal_error_desc("couldn't encode CA chain")?;
+        Ok(est_certs_response(body))
+    }
+
+    // EST "Simple Enrollment" (RFC 7030 #4.2.1):
+    // a base64-encoded PKCS#10 request is answered with the issued certificate as a base64-encoded certs-only PKCS#7.
+    // Clients are authenticated as for `POST /sign` rather than by TLS client certificates.
+    #[post("/.well-known/est/simpleenroll")]
+    async fn est_simple_enroll(&self, req: Request) -> Result<ResponseBuilder, ApiError> {
+        self.authorize_api_key(&req, ApiKeyScope::Issue).await?;
+        let provider_claims =
+            authorize_sign_request(&*self.read_conf().await, &req).unauthorized_desc("authorization failed")?;
+        self.check_rate_limit(&req, provider_claims.as_ref().map(|claims| claims.sub.as_str()))
+            .await?;
+        let requester = audit::requester(
+            &*self.read_conf().await,
+            &req,
+            provider_claims.as_ref().map(|claims| claims.sub.as_str()),
+        );
+
+        check_request_format(&req, EST_REQUEST_FORMATS)?;
+        let req = self.load_body(req).await?;
+        let csr = extract_est_csr(req.body()).api_error(ApiErrorKind::InvalidCsr, "couldn't deserialize CSR")?;
+
+        let signed_cert = self
+            .issue_certificate(csr, None, None, provider_claims, &requester)
+            .await?;
+        let der = signed_cert
+            .to_der()
+            .internal_error_desc("couldn't get certificate der")?;
+        let body = est_certs_body(&[der]).internal_error_desc("couldn't encode issued certificate")?;
+        Ok(est_certs_response(body))
+    }
+
+    // names a certificate signed for this CSR would be issued for: the subject common name as plain text,
+    // or every requested name as JSON with `?format=json` or `Accept: application/json`
+    #[post("/name")]
+    async fn request_name(&self, req: Request) -> Result<ResponseBuilder, ApiError> {
+        self.authorize_api_key(&req, ApiKeyScope::Read).await?;
+        check_request_format(&req, CSR_REQUEST_FORMATS)?;
+        let json = req.get_query_param("format").as_deref() == Some("json")
+            || Format::response_format(&req) == Ok(Format::Json);
+
+        let req = self.load_body(req).await?;
+
+        let csr = extract_csr_from_request(&req)
+            .await
+            .api_error(ApiErrorKind::InvalidCsr, "couldn't deserialize CSR")?;
+        let names = RequestedNames::new(&csr);
+
+        if json {
+            let json = serde_json::to_string(&names).internal_error_desc("couldn't encode requested names")?;
+            Ok(ResponseBuilder::new()
+                .header("Content-Type", "application/json")
+                .body(json))
+        } else {
+            let common_name = names
+                .common_name
+                .api_error(ApiErrorKind::InvalidCsr, "couldn't find CSR subject common name")?;
+            Ok(ResponseBuilder::new()
+                .header("Content-Type", "text/plain")
+                .body(common_name))
         }
+    }
 
-        let hash_address = storage
-            .get_addressing_hash_by_key_identifier(&parent_key_id)
+    #[get("/cert/<multihash>")]
+    async fn get_cert(&self, multihash: String, req: Request) -> Result<ResponseBuilder, ApiError> {
+        self.authorize_api_key(&req, ApiKeyScope::Read).await?;
+        let canonical_address = find_canonical_address(self.storage.as_ref(), &multihash).await?;
+
+        let (body, revocation) = fetch_certificate(&req, self.storage.as_ref(), &canonical_address).await?;
+        let builder = revocation_status_headers(ResponseBuilder::new(), revocation.as_ref());
+        Ok(body.respond(builder))
+    }
+
+    #[get("/certs")]
+    async fn get_certs(&self, req: Request) -> Result<ResponseBuilder, ApiError> {
+        self.authorize_api_key(&req, ApiKeyScope::Read).await?;
+
+        let offset = match req.get_query_param("offset") {
+            Some(offset) => offset.parse::<usize>().bad_request_desc("invalid offset")?,
+            None => 0,
+        };
+        let limit = match req.get_query_param("limit") {
+            Some(limit) => limit.parse::<usize>().bad_request_desc("invalid limit")?,
+            None => DEFAULT_CERTS_PAGE_SIZE,
+        };
+        let limit = limit.clamp(1, MAX_CERTS_PAGE_SIZE);
+        let filter = ListFilter {
+            issued_after: query_date(&req, "issued_after")?,
+            expiring_before: query_date(&req, "expiring_before")?,
+            expired: match req.get_query_param("expired") {
+                Some(expired) => Some(expired.parse::<bool>().bad_request_desc("invalid expired flag")?),
+                None => None,
+            },
+        };
+
+        let list = list_certificates(self.storage.as_ref(), &filter, offset, limit)
             .await
-            .map_err(|e| format!("couldn't fetch hash: {}", e))?;
+            .internal_error_desc("couldn't list certificates")?;
 
-        cert_der = storage
-            .get_cert_by_addressing_hash(&hash_address)
+        let json = serde_json::to_string(&list).internal_error_desc("couldn't encode certificate list")?;
+        Ok(ResponseBuilder::new()
+            .header("Content-Type", "application/json")
+            .body(json))
+    }
+
+    #[get("/cert/ski/<ski>")]
+    async fn get_cert_by_ski(&self, ski: String, req: Request) -> Result<ResponseBuilder, ApiError> {
+        self.authorize_api_key(&req, ApiKeyScope::Read).await?;
+        let canonical_address = find_address_by_ski(self.storage.as_ref(), &ski).await?;
+
+        let (body, revocation) = fetch_certificate(&req, self.storage.as_ref(), &canonical_address).await?;
+        let builder = revocation_status_headers(ResponseBuilder::new(), revocation.as_ref());
+        Ok(body.respond(builder))
+    }
+
+    #[get("/cert/name/<name>")]
+    async fn get_certs_by_name(&self, name: String, req: Request) -> Result<ResponseBuilder, ApiError> {
+        self.authorize_api_key(&req, ApiKeyScope::Read).await?;
+        let name = base64::decode_config(name.trim_end_matches('='), base64::URL_SAFE_NO_PAD)
+            .bad_request_desc("name isn't valid base64url")?;
+        let name = String::from_utf8(name).bad_request_desc("name isn't valid UTF-8")?;
+
+        let entries = find_certs_by_name(self.storage.as_ref(), &name)
             .await
-            .map_err(|e| format!("couldn't fetch certificate der: {}", e))?;
+            .internal_error_desc("couldn't look up certificates by name")?;
+        if entries.is_empty() {
+            return Err(ApiError::new(
+                ApiErrorKind::NotFound,
+                format!("no certificate found for name '{}'", name),
+            ));
+        }
 
-        chain.push(to_pem("CERTIFICATE", &cert_der));
+        let json = serde_json::to_string(&entries).internal_error_desc("couldn't encode certificate entries")?;
+        Ok(ResponseBuilder::new()
+            .header("Content-Type", "application/json")
+            .body(json))
+    }
 
-        current_key_id = parent_key_id;
+    #[delete("/cert/<multihash>")]
+    async fn revoke_cert(&self, multihash: String, req: Request) -> Result<ResponseBuilder, ApiError> {
+        let req = self.load_body(req).await?;
+        self.revoke_cert_impl(&multihash, req).await
     }
 
-    Ok(chain)
-}
+    #[get("/audit")]
+    async fn get_audit(&self, req: Request) -> Result<ResponseBuilder, ApiError> {
+        self.get_audit_impl(&req).await
+    }
 
-async fn generate_root_ca(config: &Config, storage: &dyn PickyStorage) -> Result<bool, String> {
-    let name = format!("{} Root CA", config.realm);
+    #[get("/cas")]
+    async fn get_cas(&self, req: Request) -> Result<ResponseBuilder, ApiError> {
+        self.authorize_api_key(&req, ApiKeyScope::Read).await?;
+        let cas = list_cas(&*self.read_conf().await, self.storage.as_ref())
+            .await
+            .internal_error_desc("couldn't list CAs")?;
+        let json = serde_json::to_string(&CaList { cas }).internal_error_desc("couldn't encode CA list")?;
+        Ok(ResponseBuilder::new()
+            .header("Content-Type", "application/json")
+            .body(json))
+    }
 
-    if let Ok(certs) = storage.get_addressing_hash_by_name(&name).await {
-        if !certs.is_empty() {
-            // already exists
-            return Ok(false);
+    #[post("/ca")]
+    async fn post_ca(&self, req: Request) -> Result<ResponseBuilder, ApiError> {
+        let req = self.load_body(req).await?;
+        self.post_ca_impl(req).await
+    }
+
+    #[post("/ca/rotate-intermediate")]
+    async fn rotate_intermediate(&self, req: Request) -> Result<ResponseBuilder, ApiError> {
+        self.rotate_intermediate_impl(&req).await
+    }
+
+    #[get("/ca/export")]
+    async fn get_ca_export(&self, req: Request) -> Result<ResponseBuilder, ApiError> {
+        self.get_ca_export_impl(&req).await
+    }
+
+    #[get("/aia/<file_name>")]
+    async fn get_aia(&self, file_name: String, req: Request) -> Result<ResponseBuilder, ApiError> {
+        self.authorize_api_key(&req, ApiKeyScope::Read).await?;
+        let cert_der = fetch_aia_certificate(self.storage.as_ref(), &file_name).await?;
+        Ok(ResponseBuilder::new()
+            .header("Content-Type", "application/pkix-cert")
+            .header("Cache-Control", AIA_CACHE_CONTROL)
+            .body(cert_der))
+    }
+
+    #[get("/chain")]
+    async fn get_default_chain(&self, req: Request) -> Result<ResponseBuilder, ApiError> {
+        self.authorize_api_key(&req, ApiKeyScope::Read).await?;
+        let ca = req.get_query_param("ca");
+        self.get_chain_impl(&req, ca.as_deref()).await
+    }
+
+    #[get("/chain/<ca>")]
+    async fn get_chain(&self, ca: String, req: Request) -> Result<ResponseBuilder, ApiError> {
+        self.authorize_api_key(&req, ApiKeyScope::Read).await?;
+        let ca = base64::decode_config(ca.trim_end_matches('='), base64::URL_SAFE_NO_PAD)
+            .bad_request_desc("CA name isn't valid base64url")?;
+        let ca = String::from_utf8(ca).bad_request_desc("CA name isn't valid UTF-8")?;
+        self.get_chain_impl(&req, Some(&ca)).await
+    }
+
+    #[get("/crl")]
+    async fn get_default_crl(&self, req: Request) -> Result<ResponseBuilder, ApiError> {
+        self.authorize_api_key(&req, ApiKeyScope::Read).await?;
+        let ca = format!("{} Authority", &self.read_conf().await.realm);
+        let crl = self.get_crl_impl(&ca).await.internal_error_desc("couldn't get CRL")?;
+        crl_response(&crl, &req)
+    }
+
+    #[get("/crl/<ca>")]
+    async fn get_crl(&self, ca: String, req: Request) -> Result<ResponseBuilder, ApiError> {
+        self.authorize_api_key(&req, ApiKeyScope::Read).await?;
+        let ca = base64::decode_config(ca.trim_end_matches('='), base64::URL_SAFE_NO_PAD)
+            .bad_request_desc("CA name isn't valid base64url")?;
+        let ca = String::from_utf8(ca).bad_request_desc("CA name isn't valid UTF-8")?;
+
+        let ca_hash = self
+            .storage
+            .get_addressing_hash_by_name(&ca)
+            .await
+            .api_error(ApiErrorKind::CaNotFound, "unknown CA")?;
+        self.storage
+            .get_key_by_addressing_hash(&ca_hash)
+            .await
+            .api_error(ApiErrorKind::CaNotFound, "no private key available for this CA")?;
+
+        let crl = self.get_crl_impl(&ca).await.internal_error_desc("couldn't get CRL")?;
+        crl_response(&crl, &req)
+    }
+
+    #[post("/ocsp")]
+    async fn post_ocsp(&self, req: Request) -> Result<ResponseBuilder, ApiError> {
+        self.authorize_api_key(&req, ApiKeyScope::Read).await?;
+        check_request_format(&req, &[Format::OcspRequest])?;
+
+        let req = self.load_body(req).await?;
+
+        self.ocsp_impl(req.body()).await
+    }
+
+    #[get("/ocsp/<encoded_request>")]
+    async fn get_ocsp(&self, encoded_request: String, req: Request) -> Result<ResponseBuilder, ApiError> {
+        self.authorize_api_key(&req, ApiKeyScope::Read).await?;
+        match decode_ocsp_get_request(&encoded_request) {
+            Ok(der) => self.ocsp_impl(&der).await,
+            Err(e) => {
+                log::error!("{}invalid OCSP GET request: {}", request_log_prefix(), e);
+                ocsp_response(&OcspResponse::new_error(OcspResponseStatus::MalformedRequest))
+            }
         }
     }
 
-    let pk = Picky::generate_private_key(4096).map_err(|e| format!("couldn't generate private key: {}", e))?;
-    let root = Picky::generate_root(&name, &pk, config.signing_algorithm)
-        .map_err(|e| format!("couldn't generate root certificate: {}", e))?;
-    let ski = root
-        .subject_key_identifier()
-        .map_err(|e| format!("couldn't fetch subject key identifier: {}", e))?;
+    #[get("/reload")]
+    async fn reload_yaml_conf(&self, req: Request) -> Result<&'static str, ApiError> {
+        self.reload_yaml_conf_impl(&req).await
+    }
+}
 
-    let cert_der = root
-        .to_der()
-        .map_err(|e| format!("couldn't serialize root certificate into der: {}", e))?;
+impl ServerController {
+    /// `token_subject` is the subject of the provisioner token the request was authorized with, if any
+    async fn check_rate_limit<T>(&self, req: &Request<T>, token_subject: Option<&str>) -> Result<(), ApiError> {
+        if let Some(rate_limiter) = self.reloader.rate_limiter() {
+            let principal = match token_subject {
+                Some(subject) => Some(format!("token:{}", subject)),
+                None => {
+                    identify_api_key(&*self.read_conf().await, req).map(|api_key| format!("api_key:{}", api_key.name))
+                }
+            };
+            if let Err(retry_after) = rate_limiter.check(&client_key(req, principal.as_deref())) {
+                log::warn!("{}rate limit exceeded on {}", request_log_prefix(), req.uri().path());
+                let retry_after_secs = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+                return Err(ApiError::too_many_requests("rate limit exceeded", retry_after_secs));
+            }
+        }
+        Ok(())
+    }
 
-    let pk_pkcs8 = pk
-        .to_pkcs8()
-        .map_err(|e| format!("couldn't get private key pkcs8: {}", e))?;
+    /// Reads the request body within the configured size limit
+    async fn load_body(&self, req: Request) -> Result<Request<Bytes>, ApiError> {
+        let max_body_size = self.read_conf().await.max_body_size;
+        load_limited_body(req, max_body_size).await
+    }
 
-    storage
-        .store(CertificateEntry {
-            name,
-            cert: cert_der,
-            key_identifier: hex::encode(ski),
-            key: Some(pk_pkcs8),
+    async fn authorize_api_key<T>(&self, req: &Request<T>, scope: ApiKeyScope) -> Result<(), ApiError> {
+        match check_api_key(&*self.read_conf().await, req, scope) {
+            Ok(_) => Ok(()),
+            Err(e @ ApiKeyError::MissingScope { .. }) => Err(e).forbidden(),
+            Err(e) => Err(e).unauthorized(),
+        }
+    }
+
+    /// Signs a CSR with the requested CA (the default one if none), within the limits of the provisioner claims
+    async fn issue_certificate(
+        &self,
+        csr: Csr,
+        requested_ca: Option<&str>,
+        reuse: Option<bool>,
+        provider_claims: Option<ProviderClaims>,
+        requester: &str,
+    ) -> Result<Cert, ApiError> {
+        let subject_name = csr
+            .subject_name()
+            .common_name()
+            .api_error(ApiErrorKind::InvalidCsr, "couldn't find signed CSR subject common name")?
+            .to_string();
+
+        let x509_duration_secs = match provider_claims {
+            Some(provider_claims) if !provider_claims.allows_subject(&subject_name) => {
+                self.metrics.inc_csrs_rejected("unauthorized_subject");
+                log::error!(
+                    "{}Requested a certificate with an unauthorized subject name: {}, expected: {}",
+                    request_log_prefix(),
+                    subject_name,
+                    provider_claims.sub
+                );
+                let reason = format!("subject {} not allowed by provisioner token", subject_name);
+                let entry = audit::csr_entry(AuditEvent::Rejected, requester, &csr, Some(reason));
+                audit::record(self.storage.as_ref(), entry).await;
+                return Err(ApiError::new(
+                    ApiErrorKind::Forbidden,
+                    format!("not authorized to request a certificate for {}", subject_name),
+                ));
+            }
+            Some(provider_claims) => provider_claims.x509_duration_secs,
+            None => DEFAULT_CERT_DURATION_SECS,
+        };
+
+        // Sign CSR
+        let conf = self.read_conf().await;
+        let ca_name = match select_ca(&conf, requested_ca) {
+            Ok(ca_name) => ca_name,
+            Err(e) => {
+                let entry = audit::csr_entry(AuditEvent::Rejected, requester, &csr, Some(e.message.clone()));
+                audit::record(self.storage.as_ref(), entry).await;
+                return Err(e);
+            }
+        };
+        let reuse = reuse.unwrap_or(conf.reuse_existing_certs);
+        let result = sign_and_audit(
+            &ca_name,
+            csr,
+            &conf,
+            self.storage.as_ref(),
+            &self.webhooks,
+            x509_duration_secs,
+            requester,
+            reuse,
+        )
+        .await;
+        record_sign_result(&self.metrics, &result);
+        result.or_else(|e| match e {
+            e @ SignError::PolicyViolation { .. } => Err(ApiError::new(ApiErrorKind::Forbidden, e.to_string())),
+            e @ SignError::WeakCsr { .. } | e @ SignError::InvalidSignature { .. } => {
+                Err(ApiError::new(ApiErrorKind::InvalidCsr, e.to_string()))
+            }
+            SignError::Issuance { source } => match ApiErrorKind::from(source.kind()) {
+                ApiErrorKind::Internal => Err(source).internal_error_desc("couldn't sign certificate"),
+                kind => Err(ApiError::new(kind, format!("couldn't sign certificate: {}", source))),
+            },
+            SignError::Other { description } => Err(description).internal_error_desc("couldn't sign certificate"),
         })
+    }
+
+    async fn post_cert_impl(&self, req: Request<Bytes>) -> Result<ResponseBuilder, ApiError> {
+        self.authorize_api_key(&req, ApiKeyScope::Issue).await?;
+        let token_subject = provisioner_token_subject(&*self.read_conf().await, &req)?;
+        self.check_rate_limit(&req, token_subject.as_deref()).await?;
+        check_request_format(&req, CERT_REQUEST_FORMATS)?;
+        let requester = audit::requester(&*self.read_conf().await, &req, token_subject.as_deref());
+
+        if matches!(Format::request_format(&req), Ok(Format::PemFile)) && is_pem_bundle(req.body()) {
+            let results = register_pem_bundle(
+                req.body(),
+                &*self.read_conf().await,
+                self.storage.as_ref(),
+                &self.webhooks,
+                &requester,
+            )
+            .await;
+            for result in &results {
+                record_register_result(&self.metrics, result);
+            }
+            let report = BundleReport::new(results);
+            let json = serde_json::to_string(&report).internal_error_desc("couldn't encode bundle report")?;
+            return Ok(ResponseBuilder::new()
+                .status(report.status_code())
+                .header("Content-Type", "application/json")
+                .body(json));
+        }
+
+        let (cert, der) = extract_cert_from_request(&req)
+            .await
+            .api_error(ApiErrorKind::InvalidCertificate, "couldn't deserialize certificate")?;
+
+        let result = register_and_audit(
+            &cert,
+            der,
+            &*self.read_conf().await,
+            self.storage.as_ref(),
+            &self.webhooks,
+            &requester,
+        )
+        .await;
+        record_register_result(&self.metrics, &result);
+        let stored = result?;
+
+        let json = serde_json::to_string(&stored).internal_error_desc("couldn't encode stored certificate")?;
+        Ok(ResponseBuilder::new()
+            .status(StatusCode::CREATED)
+            .header("Location", stored.location.as_str())
+            .header("Content-Type", "application/json")
+            .body(json))
+    }
+
+    async fn revoke_cert_impl(&self, multihash: &str, req: Request<Bytes>) -> Result<ResponseBuilder, ApiError> {
+        self.authorize_api_key(&req, ApiKeyScope::Revoke).await?;
+        let conf = self.read_conf().await;
+        let token_subject = authorize_revocation(&conf, &req)?;
+        let requester = audit::requester(&conf, &req, token_subject.as_deref());
+        drop(conf); // release lock early
+
+        let revocation_request = if req.body().is_empty() {
+            RevocationRequest::default()
+        } else {
+            serde_json::from_slice::<RevocationRequest>(req.body()).bad_request_desc("invalid revocation request")?
+        };
+        let reason = match revocation_request.reason {
+            Some(code) => {
+                Some(CrlReason::from_u8(code).bad_request_desc(&format!("invalid revocation reason code: {}", code))?)
+            }
+            None => None,
+        };
+
+        let canonical_address = find_canonical_address(self.storage.as_ref(), multihash).await?;
+        let cert_der = self
+            .storage
+            .get_cert_by_addressing_hash(&canonical_address)
+            .await
+            .not_found_desc("couldn't fetch certificate")?;
+        let cert = Cert::from_der(&cert_der).internal_error_desc("couldn't deserialize certificate")?;
+
+        let revocation = revoke_certificate(self.storage.as_ref(), &canonical_address, &cert, reason)
+            .await
+            .internal_error_desc("couldn't revoke certificate")?;
+        self.crl_cache.write().await.remove(&revocation.issuer_name);
+        let entry = AuditEntry {
+            reason: revocation.reason.map(|code| format!("CRLReason {}", code)),
+            ..audit::cert_entry(AuditEvent::Revoked, &requester, &cert)
+        };
+        audit::record(self.storage.as_ref(), entry).await;
+        self.webhooks.notify(WebhookEvent::Revoked, &cert);
+
+        let json = serde_json::to_string(&revocation).internal_error_desc("couldn't encode revocation")?;
+        Ok(revocation_status_headers(ResponseBuilder::new(), Some(&revocation)).body(json))
+    }
+
+    async fn get_audit_impl<T>(&self, req: &Request<T>) -> Result<ResponseBuilder, ApiError> {
+        authorize_admin(&*self.read_conf().await, req)?;
+
+        let since = query_date(req, "since")?.map_or(0, |since| since.timestamp());
+        let limit = match req.get_query_param("limit") {
+            Some(limit) => limit.parse::<usize>().bad_request_desc("invalid limit")?,
+            None => DEFAULT_AUDIT_PAGE_SIZE,
+        };
+        let limit = limit.clamp(1, MAX_AUDIT_PAGE_SIZE);
+
+        let entries = self
+            .storage
+            .list_audit(since, limit)
+            .await
+            .internal_error_desc("couldn't list audit entries")?;
+        let json =
+            serde_json::to_string(&AuditList { entries }).internal_error_desc("couldn't encode audit entries")?;
+        Ok(ResponseBuilder::new()
+            .header("Content-Type", "application/json")
+            .body(json))
+    }
+
+    async fn get_ca_export_impl<T>(&self, req: &Request<T>) -> Result<ResponseBuilder, ApiError> {
+        let conf = self.read_conf().await;
+        let token_subject = authorize_admin(&conf, req)?;
+        let requester = audit::requester(&conf, req, token_subject.as_deref());
+        let ca_name = select_ca(&conf, req.get_query_param("ca").as_deref())?;
+        let passphrase = export_key_passphrase(req)?;
+
+        let export = export_ca(
+            &ca_name,
+            &conf,
+            self.storage.as_ref(),
+            passphrase.as_deref(),
+            &requester,
+        )
         .await
-        .map_err(|e| format!("couldn't store generated root certificate: {}", e))?;
+        .internal_error_desc("couldn't export CA")?;
+        drop(conf); // release lock early
 
-    Ok(true)
-}
+        log::info!(
+            "{}CA {} exported by {}{}",
+            request_log_prefix(),
+            ca_name,
+            requester,
+            if export.encrypted_key.is_some() {
+                " along with its private key"
+            } else {
+                ""
+            }
+        );
 
-async fn generate_intermediate_ca(config: &Config, storage: &dyn PickyStorage) -> Result<bool, String> {
-    let root_name = format!("{} Root CA", config.realm);
-    let intermediate_name = format!("{} Authority", config.realm);
+        let json = serde_json::to_string(&export).internal_error_desc("couldn't encode CA export")?;
+        Ok(ResponseBuilder::new()
+            .header("Content-Type", "application/json")
+            .header("Cache-Control", "no-store")
+            .body(json))
+    }
 
-    if let Ok(certs) = storage.get_addressing_hash_by_name(&intermediate_name).await {
-        if !certs.is_empty() {
-            // already exists
-            return Ok(false);
+    async fn post_ca_impl(&self, req: Request<Bytes>) -> Result<ResponseBuilder, ApiError> {
+        authorize_admin(&*self.read_conf().await, &req)?;
+
+        let request = serde_json::from_slice::<CaImportRequest>(req.body())
+            .bad_request_desc("couldn't deserialize CA import request")?;
+
+        let imported = match import_ca(request, self.storage.as_ref()).await {
+            Ok(imported) => imported,
+            Err(e) => {
+                log::error!("{}couldn't import CA: {}", request_log_prefix(), e);
+                return Err(e.into());
+            }
+        };
+
+        if imported.role == CaRole::Intermediate {
+            let mut config = self.write_conf().await;
+            if !intermediate_ca_names(&config).contains(&imported.name) {
+                config.intermediates.push(imported.name.clone());
+            }
         }
+
+        let json = serde_json::to_string(&imported).internal_error_desc("couldn't encode imported CA")?;
+        Ok(ResponseBuilder::new()
+            .status(StatusCode::CREATED)
+            .header("Content-Type", "application/json")
+            .body(json))
     }
 
-    let root_hash = storage
-        .get_addressing_hash_by_name(&root_name)
-        .await
-        .map_err(|e| format!("error while fetching root: {}", e))?;
+    async fn reload_yaml_conf_impl<T>(&self, req: &Request<T>) -> Result<&'static str, ApiError> {
+        // not covered by the API key protecting mutating routes, being a GET
+        authorize_admin(&*self.read_conf().await, req)?;
+        self.reloader
+            .reload()
+            .await
+            .internal_error_desc("couldn't reload config")?;
+        Ok("Config reloaded successfully!")
+    }
 
-    let root_cert_der = storage
-        .get_cert_by_addressing_hash(&root_hash)
-        .await
-        .map_err(|e| format!("couldn't fetch root CA: {}", e))?;
+    async fn rotate_intermediate_impl<T>(&self, req: &Request<T>) -> Result<ResponseBuilder, ApiError> {
+        let conf = self.read_conf().await;
+        authorize_admin(&conf, req)?;
+        let ca_name = select_ca(&conf, req.get_query_param("ca").as_deref())?;
+        let cert = issue_intermediate_ca(&ca_name, &conf, self.storage.as_ref())
+            .await
+            .internal_error_desc("couldn't rotate intermediate CA")?;
+        let default = ca_name == format!("{} Authority", conf.realm);
+        drop(conf); // release lock early
 
-    let root_key_der = storage
-        .get_key_by_addressing_hash(&root_hash)
-        .await
-        .map_err(|e| format!("couldn't fetch root CA private key: {}", e))?;
+        // the cached CRL is signed by the previous key
+        self.crl_cache.write().await.remove(&ca_name);
+        log::info!("{}intermediate CA {} rotated", request_log_prefix(), ca_name);
+
+        let json = serde_json::to_string(&CaDescription::new(ca_name, &cert, default))
+            .internal_error_desc("couldn't encode rotated CA")?;
+        Ok(ResponseBuilder::new()
+            .status(StatusCode::CREATED)
+            .header("Content-Type", "application/json")
+            .body(json))
+    }
+
+    async fn get_chain_impl(&self, req: &Request, ca: Option<&str>) -> Result<ResponseBuilder, ApiError> {
+        let builder = ResponseBuilder::new();
+
+        let root = match (req.get_query_param("root"), req.get_query_param("include_root")) {
+            (Some(root), _) => root
+                .parse::<RootMode>()
+                .bad_request_desc("root must be include, exclude or only")?,
+            // kept for compatibility with clients predating the root parameter
+            (None, Some(include_root)) => {
+                let include_root = include_root
+                    .parse::<bool>()
+                    .bad_request_desc("include_root must be either true or false")?;
+                if include_root {
+                    RootMode::Include
+                } else {
+                    RootMode::Exclude
+                }
+            }
+            (None, None) => RootMode::Include,
+        };
+        // PEM is answered unless JSON is explicitly requested
+        let (format, builder) = match Format::response_format(req) {
+            Ok(Format::Json) => (Format::Json, builder.header("Content-Type", "application/json")),
+            _ => (Format::PemFile, builder),
+        };
+
+        let conf = self.read_conf().await;
+        let ca = select_ca(&conf, ca)?;
+        let max_chain_depth = conf.max_chain_depth;
+        drop(conf); // release lock early
+        let chain = find_ca_chain_der(self.storage.as_ref(), &ca, max_chain_depth)
+            .await
+            .api_error(ApiErrorKind::CaNotFound, "couldn't find CA chain")?;
+        let body = encode_chain(&chain, format, root).internal_error_desc("couldn't encode CA chain")?;
+        Ok(builder.body(body))
+    }
+
+    async fn get_crl_impl(&self, ca_name: &str) -> Result<Crl, String> {
+        if let Some(crl) = self.crl_cache.read().await.get(ca_name) {
+            if !crl_needs_refresh(crl) {
+                return Ok(crl.clone());
+            }
+        }
+
+        let conf = self.read_conf().await;
+        let crl = generate_crl(ca_name, &conf, self.storage.as_ref()).await?;
+        drop(conf);
+
+        self.crl_cache.write().await.insert(ca_name.to_owned(), crl.clone());
+
+        Ok(crl)
+    }
+
+    async fn ocsp_impl(&self, request_der: &[u8]) -> Result<ResponseBuilder, ApiError> {
+        let response = match OcspRequest::from_der(request_der) {
+            Ok(request) if request.cert_ids().next().is_some() => {
+                let conf = self.read_conf().await;
+                let ca_name = format!("{} Authority", &conf.realm);
+                match generate_ocsp_response(&ca_name, &request, &conf, self.storage.as_ref()).await {
+                    Ok(response) => response,
+                    Err(e) => {
+                        log::error!("{}couldn't generate OCSP response: {}", request_log_prefix(), e);
+                        OcspResponse::new_error(OcspResponseStatus::InternalError)
+                    }
+                }
+            }
+            Ok(_) => {
+                log::error!("{}OCSP request doesn't contain any certificate", request_log_prefix());
+                OcspResponse::new_error(OcspResponseStatus::MalformedRequest)
+            }
+            Err(e) => {
+                log::error!("{}couldn't parse OCSP request: {}", request_log_prefix(), e);
+                OcspResponse::new_error(OcspResponseStatus::MalformedRequest)
+            }
+        };
+
+        ocsp_response(&response)
+    }
+}
+
+/// Authenticates requests on admin routes, which are authenticated whether GET routes are protected or not:
+/// an API key with the admin scope is required, or a provisioner token with an `admin` claim set to true
+/// when no API key is configured. Tokens handed out for issuance alone are refused.
+///
+/// Returns the subject of the provisioner token, if any.
+fn authorize_admin<T>(config: &Config, req: &Request<T>) -> Result<Option<String>, ApiError> {
+    if config.api_key.is_some() || !config.api_keys.is_empty() {
+        match verify_api_key(config, req, ApiKeyScope::Admin) {
+            Ok(_) => Ok(None),
+            Err(e @ ApiKeyError::MissingScope { .. }) => Err(e).forbidden(),
+            Err(e) => Err(e).unauthorized_desc("authorization failed"),
+        }
+    } else {
+        let token = check_authorization(config, req).unauthorized_desc("authorization failed")?;
+        if token.claims["admin"].as_bool() != Some(true) {
+            return Err(ApiError::new(
+                ApiErrorKind::Forbidden,
+                "provisioner token isn't allowed on admin routes",
+            ));
+        }
+        Ok(token.claims["sub"].as_str().map(str::to_owned))
+    }
+}
+
+/// Authenticates revocation requests according to the configured auth mode: an API key with the revoke
+/// (or admin) scope in `api_key` mode, a provisioner token otherwise.
+///
+/// Returns the subject of the provisioner token, if any.
+fn authorize_revocation<T>(config: &Config, req: &Request<T>) -> Result<Option<String>, ApiError> {
+    match config.auth_mode {
+        AuthMode::ApiKey => match verify_api_key(config, req, ApiKeyScope::Revoke) {
+            Ok(_) => Ok(None),
+            Err(e @ ApiKeyError::MissingScope { .. }) => Err(e).forbidden(),
+            Err(e) => Err(e).unauthorized_desc("authorization failed"),
+        },
+        AuthMode::Jwt | AuthMode::Both => provisioner_token_subject(config, req),
+    }
+}
+
+/// Validates the provisioner token required outside of `api_key` mode.
+///
+/// Returns the subject of the token, if any.
+fn provisioner_token_subject<T>(config: &Config, req: &Request<T>) -> Result<Option<String>, ApiError> {
+    match config.auth_mode {
+        AuthMode::ApiKey => Ok(None),
+        AuthMode::Jwt | AuthMode::Both => {
+            let token = check_authorization(config, req).unauthorized_desc("authorization failed")?;
+            Ok(token.claims["sub"].as_str().map(str::to_owned))
+        }
+    }
+}
+
+/// Fails with a 415 listing supported media types if the request body isn't in one of the `supported` formats
+fn check_request_format<T>(req: &Request<T>, supported: &[Format]) -> Result<(), ApiError> {
+    let error = match Format::request_format(req) {
+        Ok(format) if supported.contains(&format) => return Ok(()),
+        Ok(format) => format!("unsupported request format: {}", format),
+        Err(e) => e,
+    };
+    log::error!("{}{}", request_log_prefix(), error);
+
+    let supported_types = supported
+        .iter()
+        .flat_map(|format| format.mime_types().iter().copied())
+        .collect();
+    Err(ApiError::unsupported_media_type(error, supported_types))
+}
+
+fn unexpected_response_format(format: Format) -> ApiError {
+    let error = format!("unexpected response format: {}", format);
+    log::error!("{}{}", request_log_prefix(), error);
+    ApiError::new(ApiErrorKind::BadRequest, error)
+}
+
+/// Binary bodies are sniffed: DER is used as is, while PEM and base64
+/// (as sent along `Content-Transfer-Encoding: base64`) are decoded first.
+fn binary_body_to_der(body: &[u8]) -> Result<Vec<u8>, GreedyError> {
+    if body.starts_with(b"-----BEGIN") {
+        Ok(parse_pem(body)?.into_data().into_owned())
+    } else if body.first() == Some(&0x30) {
+        Ok(body.to_vec())
+    } else {
+        let body = String::from_utf8_lossy(body);
+        let base64: String = body.split_whitespace().collect();
+        Ok(base64::decode(&base64)?)
+    }
+}
+
+async fn extract_cert_from_request(req: &Request<Bytes>) -> Result<(Cert, Vec<u8>), GreedyError> {
+    match Format::request_format(&req)? {
+        Format::PemFile => {
+            let pem = parse_pem(req.body())?;
+            Ok((Cert::from_der(pem.data())?, pem.into_data().into_owned()))
+        }
+        Format::Json => {
+            let json = serde_json::from_slice::<Value>(req.body())?;
+            let pem = json["certificate"]
+                .to_string()
+                .trim_matches('"')
+                .replace("\\n", "\n")
+                .parse::<Pem>()?;
+            Ok((Cert::from_der(pem.data())?, pem.into_data().into_owned()))
+        }
+        Format::PkixCertBinary => {
+            let der = binary_body_to_der(req.body())?;
+            Ok((Cert::from_der(&der)?, der))
+        }
+        Format::PkixCertBase64 => {
+            let der = base64::decode(req.body())?;
+            Ok((Cert::from_der(&der)?, der))
+        }
+        unexpected => Err(GreedyError(format!("unexpected request format: {}", unexpected))),
+    }
+}
+
+async fn extract_csr_from_request(req: &Request<Bytes>) -> Result<Csr, GreedyError> {
+    match Format::request_format(req)? {
+        Format::PemFile => {
+            let pem = parse_pem(req.body())?;
+            Ok(Csr::from_der(pem.data())?)
+        }
+        Format::Json => {
+            let json = serde_json::from_slice::<Value>(req.body())?;
+            let pem = json["csr"]
+                .to_string()
+                .trim_matches('"')
+                .replace("\\n", "\n")
+                .parse::<Pem>()?;
+            Ok(Csr::from_der(pem.data())?)
+        }
+        Format::Pkcs10Binary => Ok(Csr::from_der(&binary_body_to_der(req.body())?)?),
+        Format::Pkcs10Base64 => {
+            let der = base64::decode(req.body())?;
+            Ok(Csr::from_der(&der)?)
+        }
+        unexpected => Err(GreedyError(format!("unexpected request format: {}", unexpected))),
+    }
+}
+
+/// EST requests carry the DER-encoded CSR as (possibly folded) base64
+fn extract_est_csr(body: &[u8]) -> Result<Csr, GreedyError> {
+    Ok(Csr::from_der(&binary_body_to_der(body)?)?)
+}
+
+/// Base64-encoded certs-only PKCS#7 answered by EST endpoints, folded into RFC 2045 lines
+fn est_certs_body(certs_der: &[Vec<u8>]) -> Result<String, String> {
+    let certs = certs_der
+        .iter()
+        .map(Cert::from_der)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("couldn't deserialize certificate: {}", e))?;
+    let der = Pkcs7::from_certs(&certs)
+        .to_der()
+        .map_err(|e| format!("couldn't encode pkcs7: {}", e))?;
+    let base64 = base64::encode(&der);
+
+    let lines: Vec<&str> = base64
+        .as_bytes()
+        .chunks(EST_BASE64_LINE_LENGTH)
+        .map(|line| std::str::from_utf8(line).expect("base64 is ascii"))
+        .collect();
+    Ok(lines.join("\r\n"))
+}
+
+fn est_certs_response(body: String) -> ResponseBuilder {
+    ResponseBuilder::new()
+        .header("Content-Type", "application/pkcs7-mime; smime-type=certs-only")
+        .header("Content-Transfer-Encoding", "base64")
+        .body(body)
+}
+
+/// CA named in the `ca` field of a JSON sign request
+fn extract_requested_ca(req: &Request<Bytes>) -> Option<String> {
+    match Format::request_format(req) {
+        Ok(Format::Json) => serde_json::from_slice::<Value>(req.body())
+            .ok()
+            .and_then(|json| json["ca"].as_str().map(str::to_owned)),
+        _ => None,
+    }
+}
+
+fn extract_reuse_request(req: &Request<Bytes>) -> Option<bool> {
+    match Format::request_format(req) {
+        Ok(Format::Json) => serde_json::from_slice::<Value>(req.body())
+            .ok()
+            .and_then(|json| json["reuse"].as_bool()),
+        _ => None,
+    }
+}
+
+/// Passphrase of a `GET /ca/export` request asking for the private key with `include_key=true`
+///
+/// The passphrase is taken from a header rather than from the query, which tends to end up in access logs.
+fn export_key_passphrase<T>(req: &Request<T>) -> Result<Option<String>, ApiError> {
+    let include_key = match req.get_query_param("include_key") {
+        Some(include_key) => include_key
+            .parse::<bool>()
+            .bad_request_desc("include_key must be either true or false")?,
+        None => false,
+    };
+    if !include_key {
+        return Ok(None);
+    }
+
+    match req.get_header_string_value(KEY_PASSPHRASE_HEADER) {
+        Some(passphrase) => Ok(Some(passphrase)),
+        None => Err(ApiError::new(
+            ApiErrorKind::BadRequest,
+            format!(
+                "a passphrase is required to export the private key ({} header)",
+                KEY_PASSPHRASE_HEADER
+            ),
+        )),
+    }
+}
+
+async fn load_ca(ca_name: &str, storage: &dyn PickyStorage) -> Result<(Cert, PrivateKey), String> {
+    let ca_hash = storage
+        .get_addressing_hash_by_name(ca_name)
+        .await
+        .map_err(|e| format!("couldn't fetch CA: {}", e))?;
+
+    let ca_cert_der = storage
+        .get_cert_by_addressing_hash(&ca_hash)
+        .await
+        .map_err(|e| format!("couldn't get CA cert der: {}", e))?;
+    let ca_cert = Cert::from_der(&ca_cert_der).map_err(|e| format!("couldn't deserialize CA cert: {}", e))?;
+
+    let ca_pk_der = storage
+        .get_key_by_addressing_hash(&ca_hash)
+        .await
+        .map_err(|e| format!("couldn't fetch CA private key: {}", e))?;
+    let ca_pk = Picky::parse_pk_from_magic_der(&ca_pk_der).map_err(|e| e.to_string())?;
+
+    Ok((ca_cert, ca_pk))
+}
+
+#[derive(Debug, Error)]
+enum SignError {
+    /// names rejected by the issuance policy
+    #[error("names rejected by issuance policy: {}", rejected.join(", "))]
+    PolicyViolation { rejected: Vec<String> },
+
+    /// CSR key or signature doesn't meet the configured requirements
+    #[error("CSR rejected ({}): {}", rule, description)]
+    WeakCsr { rule: &'static str, description: String },
+
+    /// CSR isn't signed by the key it requests a certificate for
+    #[error("invalid CSR signature: {}", source)]
+    InvalidSignature { source: CsrError },
+
+    /// leaf certificate generation failed
+    #[error("couldn't generate leaf certificate: {}", source)]
+    Issuance { source: PickyError },
+
+    /// other signing error
+    #[error("{}", description)]
+    Other { description: String },
+}
+
+impl From<String> for SignError {
+    fn from(description: String) -> Self {
+        Self::Other { description }
+    }
+}
+
+impl From<&str> for SignError {
+    fn from(description: &str) -> Self {
+        Self::Other {
+            description: description.to_owned(),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+enum ImportCaError {
+    /// certificate or key rejected
+    #[error("{}", description)]
+    Invalid { description: String },
+
+    /// a CA with the same subject key identifier is already stored
+    #[error("a CA with subject key identifier {} is already stored", ski)]
+    Duplicate { ski: String },
+
+    /// storage error
+    #[error("{}", description)]
+    Other { description: String },
+}
+
+impl ImportCaError {
+    fn invalid<S: Into<String>>(description: S) -> Self {
+        Self::Invalid {
+            description: description.into(),
+        }
+    }
+}
+
+impl From<ImportCaError> for ApiError {
+    fn from(e: ImportCaError) -> Self {
+        let kind = match e {
+            ImportCaError::Invalid { .. } => ApiErrorKind::InvalidCertificate,
+            ImportCaError::Duplicate { .. } => ApiErrorKind::Conflict,
+            ImportCaError::Other { .. } => ApiErrorKind::Internal,
+        };
+        ApiError::new(kind, e.to_string())
+    }
+}
+
+#[derive(Debug, Error)]
+enum RegisterError {
+    /// certificate isn't issued by one of our intermediate CAs
+    #[error("this certificate was not signed by a CA of this server: {}", description)]
+    UnknownIssuer { description: String },
+
+    /// another certificate is stored with the same subject key identifier
+    #[error("another certificate with subject key identifier {} is already stored", ski)]
+    Conflict { ski: String },
+
+    /// the same certificate is already stored
+    #[error("certificate {} is already stored", hash)]
+    AlreadyStored { hash: String },
+
+    /// malformed certificate
+    #[error("{}", description)]
+    Invalid { description: String },
+
+    /// storage error
+    #[error("{}", description)]
+    Other { description: String },
+}
+
+impl From<RegisterError> for ApiError {
+    fn from(e: RegisterError) -> Self {
+        let kind = match e {
+            RegisterError::UnknownIssuer { .. } => ApiErrorKind::Forbidden,
+            RegisterError::Conflict { .. } | RegisterError::AlreadyStored { .. } => ApiErrorKind::Conflict,
+            RegisterError::Invalid { .. } => ApiErrorKind::InvalidCertificate,
+            RegisterError::Other { .. } => ApiErrorKind::Internal,
+        };
+        ApiError::new(kind, e.to_string())
+    }
+}
+
+/// Registers a certificate like `register_certificate`, recording the outcome in the audit log
+/// and notifying webhooks on success
+async fn register_and_audit(
+    cert: &Cert,
+    der: Vec<u8>,
+    config: &Config,
+    storage: &dyn PickyStorage,
+    webhooks: &Webhooks,
+    requester: &str,
+) -> Result<StoredCertificate, RegisterError> {
+    let result = register_certificate(cert, der, config, storage).await;
+    let entry = match &result {
+        Ok(_) => {
+            webhooks.notify(WebhookEvent::Registered, cert);
+            audit::cert_entry(AuditEvent::Registered, requester, cert)
+        }
+        Err(e @ RegisterError::Other { .. }) => AuditEntry {
+            reason: Some(e.to_string()),
+            ..audit::cert_entry(AuditEvent::Failed, requester, cert)
+        },
+        Err(e) => {
+            log::error!("{}certificate rejected: {}", request_log_prefix(), e);
+            AuditEntry {
+                reason: Some(e.to_string()),
+                ..audit::cert_entry(AuditEvent::Rejected, requester, cert)
+            }
+        }
+    };
+    audit::record(storage, entry).await;
+    result
+}
+
+fn record_register_result(metrics: &Metrics, result: &Result<StoredCertificate, RegisterError>) {
+    match result {
+        Ok(_) => metrics.inc_certificates_registered("success"),
+        Err(RegisterError::Other { .. }) => metrics.inc_certificates_registered("error"),
+        Err(_) => metrics.inc_certificates_registered("rejected"),
+    }
+}
+
+/// Whether the body holds more than one PEM block
+fn is_pem_bundle(body: &[u8]) -> bool {
+    const PEM_HEADER_START: &[u8] = b"-----BEGIN";
+    body.windows(PEM_HEADER_START.len())
+        .filter(|window| *window == PEM_HEADER_START)
+        .nth(1)
+        .is_some()
+}
+
+/// Registers each certificate of a PEM bundle individually, PEM blocks being parsed one at a time.
+///
+/// Results are in the order of the PEM blocks.
+async fn register_pem_bundle(
+    body: &[u8],
+    config: &Config,
+    storage: &dyn PickyStorage,
+    webhooks: &Webhooks,
+    requester: &str,
+) -> Vec<Result<StoredCertificate, RegisterError>> {
+    let mut results = Vec::new();
+    for pem in read_pems(body) {
+        let result = match pem {
+            Ok(pem) => match Cert::from_pem(&pem) {
+                Ok(cert) => {
+                    let der = pem.into_data().into_owned();
+                    register_and_audit(&cert, der, config, storage, webhooks, requester).await
+                }
+                Err(e) => Err(RegisterError::Invalid {
+                    description: format!("couldn't deserialize certificate: {}", e),
+                }),
+            },
+            Err(e) => Err(RegisterError::Invalid {
+                description: format!("couldn't parse PEM: {}", e),
+            }),
+        };
+        results.push(result);
+    }
+    results
+}
+
+/// Stores a certificate pushed by a client once verified to be issued by one of our intermediate CAs
+async fn register_certificate(
+    cert: &Cert,
+    der: Vec<u8>,
+    config: &Config,
+    storage: &dyn PickyStorage,
+) -> Result<StoredCertificate, RegisterError> {
+    let unknown_issuer = |description: String| RegisterError::UnknownIssuer { description };
+
+    let chain = find_issuer_chain_der(storage, cert, config.max_chain_depth)
+        .await
+        .map_err(unknown_issuer)?
+        .iter()
+        .map(Cert::from_der)
+        .collect::<Result<Vec<Cert>, _>>()
+        .map_err(|e| RegisterError::Other {
+            description: format!("couldn't deserialize CA certificate: {}", e),
+        })?;
+
+    // the issuer is looked up by key identifier, any CA stored in the backend could match
+    let issuer_name = chain[0]
+        .subject_name()
+        .common_name()
+        .map(ToString::to_string)
+        .unwrap_or_default();
+    if !intermediate_ca_names(config).contains(&issuer_name) {
+        return Err(unknown_issuer(format!("{} isn't an intermediate CA", issuer_name)));
+    }
+
+    cert.verifier()
+        .chain(chain.iter())
+        .exact_date(&UTCDate::now())
+        .verify()
+        .map_err(|e| unknown_issuer(e.to_string()))?;
+
+    let ski = hex::encode(cert.subject_key_identifier().map_err(|e| RegisterError::Invalid {
+        description: format!("couldn't fetch SKI: {}", e),
+    })?);
+    let stored = StoredCertificate::from_der(&der, cert, ski.clone());
+    if let Ok(stored_hash) = storage.get_addressing_hash_by_key_identifier(&ski).await {
+        if stored_hash != stored.addressing_hash {
+            return Err(RegisterError::Conflict { ski });
+        }
+    }
+
+    let subject_name = cert
+        .subject_name()
+        .common_name()
+        .ok_or_else(|| RegisterError::Invalid {
+            description: "couldn't find subject common name".to_owned(),
+        })?
+        .to_string();
+
+    storage
+        .store(
+            CertificateEntry {
+                name: subject_name.clone(),
+                cert: der,
+                key_identifier: ski,
+                key: None,
+            },
+            ConflictPolicy::ErrorIfExists,
+        )
+        .await
+        .map_err(|e| match e {
+            StorageError::AlreadyExists { hash } => RegisterError::AlreadyStored { hash },
+            e => RegisterError::Other {
+                description: format!("insertion failed for leaf {}: {}", subject_name, e),
+            },
+        })?;
+
+    Ok(stored)
+}
+
+/// Validates a CA certificate and its private key, then stores them.
+///
+/// Intermediate CAs must chain to a root CA already known by this server.
+async fn import_ca(request: CaImportRequest, storage: &dyn PickyStorage) -> Result<ImportedCa, ImportCaError> {
+    let cert_pem = request
+        .certificate
+        .parse::<Pem>()
+        .map_err(|e| ImportCaError::invalid(format!("couldn't parse certificate pem: {}", e)))?;
+    let cert =
+        Cert::from_pem(&cert_pem).map_err(|e| ImportCaError::invalid(format!("couldn't parse certificate: {}", e)))?;
+
+    let key_pem = request
+        .key
+        .parse::<Pem>()
+        .map_err(|e| ImportCaError::invalid(format!("couldn't parse key pem: {}", e)))?;
+    let key = match (key_pem.label(), request.passphrase.as_deref()) {
+        ("ENCRYPTED PRIVATE KEY", Some(passphrase)) => PrivateKey::from_encrypted_pem(&key_pem, passphrase)
+            .map_err(|e| ImportCaError::invalid(format!("couldn't decrypt private key: {}", e)))?,
+        ("ENCRYPTED PRIVATE KEY", None) => {
+            return Err(ImportCaError::invalid(
+                "a passphrase is required to import an encrypted private key",
+            ))
+        }
+        (_, Some(_)) => {
+            return Err(ImportCaError::invalid(
+                "passphrase given for an unencrypted private key",
+            ))
+        }
+        (_, None) => Picky::parse_pk_from_magic_der(key_pem.data())
+            .map_err(|e| ImportCaError::invalid(format!("couldn't parse private key: {}", e)))?,
+    };
+
+    // proves the key matches the certificate without depending on how the public key is encoded
+    let proof_algorithm = match cert.public_key().ec_curve_bits() {
+        Some(384) => SignatureAlgorithm::EcdsaSha384,
+        Some(_) => SignatureAlgorithm::EcdsaSha256,
+        None => SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_256),
+    };
+    let cert_der = cert
+        .to_der()
+        .map_err(|e| ImportCaError::invalid(format!("couldn't encode certificate: {}", e)))?;
+    let matches = proof_algorithm
+        .sign(&cert_der, &key)
+        .and_then(|signature| proof_algorithm.verify(cert.public_key(), &cert_der, &signature));
+    if matches.is_err() {
+        return Err(ImportCaError::invalid("private key doesn't match the certificate"));
+    }
+
+    let (expected_type, issuer) = match request.role {
+        CaRole::Root => (CertType::Root, None),
+        CaRole::Intermediate => {
+            let issuer_key_id = cert
+                .authority_key_identifier()
+                .ok()
+                .and_then(|aki| aki.key_identifier().map(hex::encode))
+                .ok_or_else(|| ImportCaError::invalid("intermediate CA has no authority key identifier"))?;
+            let issuer_hash = storage
+                .get_addressing_hash_by_key_identifier(&issuer_key_id)
+                .await
+                .map_err(|e| ImportCaError::invalid(format!("issuer {} isn't known: {}", cert.issuer_name(), e)))?;
+            let issuer_der =
+                storage
+                    .get_cert_by_addressing_hash(&issuer_hash)
+                    .await
+                    .map_err(|e| ImportCaError::Other {
+                        description: format!("couldn't fetch issuer certificate: {}", e),
+                    })?;
+            let issuer = Cert::from_der(&issuer_der).map_err(|e| ImportCaError::Other {
+                description: format!("couldn't deserialize issuer certificate: {}", e),
+            })?;
+            (CertType::Intermediate, Some(issuer))
+        }
+    };
+
+    if cert.ty() != expected_type {
+        return Err(ImportCaError::invalid(format!(
+            "certificate is not a {} CA",
+            match request.role {
+                CaRole::Root => "root",
+                CaRole::Intermediate => "intermediate",
+            }
+        )));
+    }
+
+    // a root is verified against itself, checking its self-signature
+    let now = UTCDate::now();
+    let chain = issuer.as_ref().unwrap_or(&cert);
+    cert.verifier()
+        .chain(std::iter::once(chain))
+        .exact_date(&now)
+        .verify()
+        .map_err(|e| ImportCaError::invalid(format!("certificate chain is invalid: {}", e)))?;
+
+    let ski = hex::encode(
+        cert.subject_key_identifier()
+            .map_err(|e| ImportCaError::invalid(format!("couldn't fetch subject key identifier: {}", e)))?,
+    );
+    if request.role == CaRole::Root {
+        // chains are built by following authority key identifiers up to a self-issued certificate
+        let aki = cert
+            .authority_key_identifier()
+            .ok()
+            .and_then(|aki| aki.key_identifier().map(hex::encode));
+        if aki.as_deref() != Some(ski.as_str()) {
+            return Err(ImportCaError::invalid(
+                "root CA authority key identifier must match its subject key identifier",
+            ));
+        }
+    }
+
+    if storage.get_addressing_hash_by_key_identifier(&ski).await.is_ok() {
+        return Err(ImportCaError::Duplicate { ski });
+    }
+
+    let name = cert
+        .subject_name()
+        .common_name()
+        .ok_or_else(|| ImportCaError::invalid("couldn't find subject common name"))?
+        .to_string();
+    let key_der = key.to_pkcs8().map_err(|e| ImportCaError::Other {
+        description: format!("couldn't convert key to pkcs8: {}", e),
+    })?;
+
+    storage
+        .store(
+            CertificateEntry {
+                name: name.clone(),
+                cert: cert_der,
+                key_identifier: ski.clone(),
+                key: Some(key_der),
+            },
+            ConflictPolicy::ErrorIfExists,
+        )
+        .await
+        .map_err(|e| match e {
+            StorageError::AlreadyExists { .. } => ImportCaError::Duplicate { ski: ski.clone() },
+            e => ImportCaError::Other {
+                description: format!("couldn't store CA {}: {}", name, e),
+            },
+        })?;
+
+    Ok(ImportedCa {
+        name,
+        ski,
+        role: request.role,
+    })
+}
+
+/// Exports the chain of a CA and, when a passphrase is given, its private key encrypted to it.
+/// The export is recorded in the audit log.
+async fn export_ca(
+    ca_name: &str,
+    config: &Config,
+    storage: &dyn PickyStorage,
+    passphrase: Option<&str>,
+    requester: &str,
+) -> Result<CaExport, String> {
+    let chain_der = find_ca_chain_der(storage, ca_name, config.max_chain_depth).await?;
+    let chain = order_chain(&chain_der)?;
+    let ca_cert = chain
+        .first()
+        .map(|ca| ca.cert.clone())
+        .ok_or_else(|| format!("no certificate found for CA {}", ca_name))?;
+
+    let encrypted_key = match passphrase {
+        Some(passphrase) => {
+            let (_, ca_pk) = load_ca(ca_name, storage).await?;
+            let encrypted = ca_pk
+                .to_encrypted_pem(passphrase)
+                .map_err(|e| format!("couldn't encrypt CA private key: {}", e))?;
+            Some(encrypted)
+        }
+        None => None,
+    };
+
+    let what = if encrypted_key.is_some() {
+        "certificate chain and encrypted private key"
+    } else {
+        "certificate chain"
+    };
+    let entry = AuditEntry {
+        reason: Some(what.to_owned()),
+        ..audit::cert_entry(AuditEvent::Exported, requester, &ca_cert)
+    };
+    audit::record(storage, entry).await;
+
+    Ok(CaExport {
+        name: ca_name.to_owned(),
+        chain: chain.iter().map(|cert| to_pem("CERTIFICATE", &cert.der)).collect(),
+        encrypted_key,
+    })
+}
+
+/// Checks a CSR against the key strength requirements and the issuance policy, answering the subject
+/// common name and alternative names a certificate signed for it is issued for
+fn check_csr(csr: &Csr, config: &Config) -> Result<(String, Option<GeneralNames>), SignError> {
+    check_csr_strength(csr, config)?;
+
+    let dns_name = csr
+        .subject_name()
+        .common_name()
+        .ok_or("couldn't find signed cert subject common name")?
+        .to_string();
+
+    let san = match csr.subject_alt_name() {
+        Some(san) => Some(san),
+        None if config.synthesize_san_from_cn && is_dns_name(&dns_name) => Some(GeneralNames::new(
+            GeneralName::new_dns_name(dns_name.as_str())
+                .map_err(|e| format!("invalid subject common name {}: {}", dns_name, e))?,
+        )),
+        None => None,
+    };
+
+    if let Some(policy) = &config.issuance_policy {
+        let no_san = GeneralNames::from(Vec::<GeneralName>::new());
+        policy
+            .check(Some(&dns_name), san.as_ref().unwrap_or(&no_san))
+            .map_err(|rejected| SignError::PolicyViolation { rejected })?;
+    }
+
+    Ok((dns_name, san))
+}
+
+fn check_csr_strength(csr: &Csr, config: &Config) -> Result<(), SignError> {
+    let public_key = csr.public_key();
+
+    if let Some(modulus_bits) = public_key.rsa_modulus_bits() {
+        if modulus_bits < config.csr_min_rsa_key_size {
+            return Err(SignError::WeakCsr {
+                rule: "csr_min_rsa_key_size",
+                description: format!(
+                    "RSA key size of {} bits is below the required {} bits",
+                    modulus_bits, config.csr_min_rsa_key_size
+                ),
+            });
+        }
+    }
+
+    if let Some(public_exponent) = public_key.rsa_public_exponent() {
+        if public_exponent < config.csr_min_rsa_public_exponent {
+            return Err(SignError::WeakCsr {
+                rule: "csr_min_rsa_public_exponent",
+                description: format!(
+                    "RSA public exponent {} is below the required {}",
+                    public_exponent, config.csr_min_rsa_public_exponent
+                ),
+            });
+        }
+    }
+
+    let hash_algorithm = SignatureAlgorithm::from_algorithm_identifier(csr.signature_algorithm())
+        .ok()
+        .map(SignatureAlgorithm::hash_algorithm);
+    match hash_algorithm {
+        Some(hash_algorithm) if config.csr_allowed_hash_algorithms.contains(&hash_algorithm) => Ok(()),
+        Some(hash_algorithm) => Err(SignError::WeakCsr {
+            rule: "csr_allowed_hash_algorithms",
+            description: format!("{:?} signatures are not allowed", hash_algorithm),
+        }),
+        None => Err(SignError::WeakCsr {
+            rule: "csr_allowed_hash_algorithms",
+            description: "unsupported signature algorithm".to_owned(),
+        }),
+    }
+}
+
+async fn sign_certificate(
+    ca_name: &str,
+    csr: VerifiedCsr,
+    config: &Config,
+    storage: &dyn PickyStorage,
+    duration_secs: u64,
+) -> Result<Cert, SignError> {
+    let (dns_name, san) = check_csr(csr.csr(), config)?;
+
+    let (ca_cert, ca_pk) = load_ca(ca_name, storage).await?;
+
+    let validity = chrono::Duration::seconds(
+        i64::try_from(duration_secs).map_err(|e| format!("invalid x509 duration (too big?): {}", e))?,
+    );
+    let authority_info_access = match &config.external_url {
+        Some(external_url) => {
+            let url = aia_url(external_url, &ca_cert)?;
+            let location = GeneralName::new_uri(url.as_str()).map_err(|e| format!("invalid AIA URL {}: {}", url, e))?;
+            Some(AuthorityInfoAccess::from(vec![AccessDescription::new_ca_issuers(
+                location,
+            )]))
+        }
+        None => None,
+    };
+
+    let options = LeafIssuanceOptions {
+        subject_alt_name: san,
+        authority_info_access,
+        ..LeafIssuanceOptions::new(validity)
+    };
+    let signed_cert = Picky::generate_leaf_from_csr_with_options(
+        csr,
+        &ca_cert,
+        &ca_pk,
+        config.signing_algorithm,
+        config.key_id_method,
+        options,
+    )
+    .map_err(|source| SignError::Issuance { source })?;
+
+    if config.save_certificate {
+        let cert_der = signed_cert
+            .to_der()
+            .map_err(|e| format!("couldn't serialize certificate to der: {}", e))?;
+        let ski = hex::encode(
+            signed_cert
+                .subject_key_identifier()
+                .map_err(|e| format!("couldn't get SKI: {}", e))?,
+        );
+
+        storage
+            .store(
+                CertificateEntry {
+                    name: dns_name.clone(),
+                    cert: cert_der,
+                    key_identifier: ski,
+                    key: None,
+                },
+                ConflictPolicy::ErrorIfExists,
+            )
+            .await
+            .map_err(|e| format!("insertion error for leaf {}: {}", dns_name, e))?;
+    }
+
+    Ok(signed_cert)
+}
+
+async fn generate_crl(ca_name: &str, config: &Config, storage: &dyn PickyStorage) -> Result<Crl, String> {
+    let (ca_cert, ca_pk) = load_ca(ca_name, storage).await?;
+
+    let revocations = storage
+        .get_revocations_by_issuer(ca_name)
+        .await
+        .map_err(|e| format!("couldn't fetch revocations: {}", e))?;
+
+    Picky::generate_crl(&ca_cert, &ca_pk, config.signing_algorithm, &revocations)
+        .map_err(|e| format!("couldn't generate CRL: {}", e))
+}
+
+async fn generate_ocsp_response(
+    ca_name: &str,
+    request: &OcspRequest,
+    config: &Config,
+    storage: &dyn PickyStorage,
+) -> Result<OcspResponse, String> {
+    let (ca_cert, ca_pk) = load_ca(ca_name, storage).await?;
+
+    let revocations = storage
+        .get_revocations_by_issuer(ca_name)
+        .await
+        .map_err(|e| format!("couldn't fetch revocations: {}", e))?;
+
+    let (responder_cert, responder_key) = match &config.ocsp_responder {
+        Some(responder_cert_key_pair) => load_cert_key_pair(responder_cert_key_pair)
+            .await
+            .map_err(|e| format!("couldn't load OCSP responder: {}", e))?,
+        None => (ca_cert.clone(), ca_pk),
+    };
+
+    Picky::generate_ocsp_response(
+        &ca_cert,
+        &responder_cert,
+        &responder_key,
+        config.signing_algorithm,
+        request,
+        &revocations,
+    )
+    .map_err(|e| format!("couldn't generate OCSP response: {}", e))
+}
+
+/// Decodes the `{url-encoding of base64 encoding of DER encoding of OCSPRequest}` path segment
+/// of a GET request ([RFC 6960 #A.1](https://tools.ietf.org/html/rfc6960#appendix-A.1)).
+///
+/// base64url is accepted as well.
+fn decode_ocsp_get_request(request: &str) -> Result<Vec<u8>, String> {
+    let request = request
+        .replace("%2B", "+")
+        .replace("%2b", "+")
+        .replace("%2F", "/")
+        .replace("%2f", "/")
+        .replace("%3D", "")
+        .replace("%3d", "")
+        .replace('-', "+")
+        .replace('_', "/");
+    base64::decode_config(request.trim_end_matches('='), base64::STANDARD_NO_PAD)
+        .map_err(|e| format!("request isn't valid base64: {}", e))
+}
+
+fn ocsp_response(response: &OcspResponse) -> Result<ResponseBuilder, ApiError> {
+    let der = response
+        .to_der()
+        .internal_error_desc("couldn't get OCSP response der")?;
+    Ok(ResponseBuilder::new()
+        .header("Content-Type", "application/ocsp-response")
+        .body(der))
+}
+
+/// Records the revocation of the given certificate.
+///
+/// Revoking an already revoked certificate doesn't alter the original revocation entry.
+async fn revoke_certificate(
+    storage: &dyn PickyStorage,
+    addressing_hash: &str,
+    cert: &Cert,
+    reason: Option<CrlReason>,
+) -> Result<RevocationEntry, String> {
+    if let Some(revocation) = storage
+        .get_revocation_by_addressing_hash(addressing_hash)
+        .await
+        .map_err(|e| format!("couldn't fetch revocation status: {}", e))?
+    {
+        log::info!("certificate {} is already revoked", addressing_hash);
+        return Ok(revocation);
+    }
+
+    let issuer_name = cert
+        .issuer_name()
+        .common_name()
+        .ok_or_else(|| "couldn't find issuer common name".to_owned())?
+        .to_string();
+
+    let revocation = RevocationEntry {
+        addressing_hash: addressing_hash.to_owned(),
+        issuer_name,
+        serial_number: hex::encode(cert.serial_number().as_signed_bytes_be()),
+        revocation_date: Utc::now().timestamp(),
+        reason: reason.map(|reason| reason as u8),
+    };
+
+    storage
+        .revoke(revocation.clone())
+        .await
+        .map_err(|e| format!("couldn't store revocation: {}", e))?;
+
+    log::info!("revoked certificate {}", addressing_hash);
+
+    Ok(revocation)
+}
+
+/// Looks for a stored leaf certificate issued by the given CA for the CSR subject, alternative names
+/// (`san`, as they would be issued) and public key, still valid and not revoked.
+///
+/// Public keys are compared on their SubjectPublicKeyInfo DER encoding.
+async fn find_reusable_certificate(
+    ca_name: &str,
+    csr: &VerifiedCsr,
+    san: Option<&GeneralNames>,
+    storage: &dyn PickyStorage,
+) -> Result<Option<Cert>, String> {
+    let subject_name = match csr.csr().subject_name().common_name() {
+        Some(common_name) => common_name.to_string(),
+        None => return Ok(None),
+    };
+    let csr_key = csr
+        .csr()
+        .public_key()
+        .to_der()
+        .map_err(|e| format!("couldn't encode CSR public key: {}", e))?;
+
+    let hashes = storage
+        .get_addressing_hashes_by_name(&subject_name)
+        .await
+        .map_err(|e| format!("couldn't fetch certificates for {}: {}", subject_name, e))?;
+    let now = Utc::now();
+
+    // most recent certificates come last
+    for hash in hashes.iter().rev() {
+        let cert_der = storage
+            .get_cert_by_addressing_hash(hash)
+            .await
+            .map_err(|e| format!("couldn't fetch certificate {}: {}", hash, e))?;
+        let cert = Cert::from_der(&cert_der).map_err(|e| format!("couldn't decode certificate {}: {}", hash, e))?;
+
+        let issued_by_ca = cert
+            .issuer_name()
+            .common_name()
+            .map(|common_name| common_name.to_string() == ca_name)
+            .unwrap_or(false);
+        if cert.ty() != CertType::Leaf || !issued_by_ca || DateTime::<Utc>::from(cert.valid_not_after()) <= now {
+            continue;
+        }
+
+        let cert_key = cert
+            .public_key()
+            .to_der()
+            .map_err(|e| format!("couldn't encode certificate public key: {}", e))?;
+        if cert_key != csr_key || !same_names(cert.subject_alt_name().ok().as_ref(), san) {
+            continue;
+        }
+
+        let revocation = storage
+            .get_revocation_by_addressing_hash(hash)
+            .await
+            .map_err(|e| format!("couldn't fetch revocation status of {}: {}", hash, e))?;
+        if revocation.is_none() {
+            return Ok(Some(cert));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Whether both hold the same names, regardless of their order
+fn same_names(a: Option<&GeneralNames>, b: Option<&GeneralNames>) -> bool {
+    let names = |names: Option<&GeneralNames>| names.map(GeneralNames::to_general_names).unwrap_or_default();
+    let (a, b) = (names(a), names(b));
+    a.iter().all(|name| b.contains(name)) && b.iter().all(|name| a.contains(name))
+}
+
+/// Verifies the CSR signature then signs it like `sign_certificate`, recording the outcome in the audit
+/// log and notifying webhooks of the issued certificate.
+///
+/// When `reuse` is set and the CSR is still allowed, a previously issued certificate matching the CSR
+/// subject, alternative names and public key is returned instead, without minting (nor auditing) a new one.
+#[allow(clippy::too_many_arguments)]
+async fn sign_and_audit(
+    ca_name: &str,
+    csr: Csr,
+    config: &Config,
+    storage: &dyn PickyStorage,
+    webhooks: &Webhooks,
+    duration_secs: u64,
+    requester: &str,
+    reuse: bool,
+) -> Result<Cert, SignError> {
+    let csr_entry = audit::csr_entry(AuditEvent::Rejected, requester, &csr, None);
+
+    // checked once, for both the lookup of a reusable certificate and the issuance
+    let csr = match csr.into_verified() {
+        Ok(csr) => csr,
+        Err(source) => {
+            let e = SignError::InvalidSignature { source };
+            let entry = AuditEntry {
+                reason: Some(e.to_string()),
+                ..csr_entry
+            };
+            audit::record(storage, entry).await;
+            return Err(e);
+        }
+    };
+
+    if reuse {
+        // a rejected CSR is left to `sign_certificate`, for the rejection to be audited
+        if let Ok((_, san)) = check_csr(csr.csr(), config) {
+            match find_reusable_certificate(ca_name, &csr, san.as_ref(), storage).await {
+                Ok(Some(cert)) => {
+                    log::info!(
+                        "{}returning previously issued certificate for {}",
+                        request_log_prefix(),
+                        csr.csr().subject_name()
+                    );
+                    return Ok(cert);
+                }
+                Ok(None) => {}
+                Err(e) => log::warn!("{}couldn't look up reusable certificate: {}", request_log_prefix(), e),
+            }
+        }
+    }
+
+    let result = sign_certificate(ca_name, csr, config, storage, duration_secs).await;
+
+    let entry = match &result {
+        Ok(cert) => {
+            webhooks.notify(WebhookEvent::Issued, cert);
+            audit::cert_entry(AuditEvent::Issued, requester, cert)
+        }
+        Err(e @ SignError::PolicyViolation { .. })
+        | Err(e @ SignError::WeakCsr { .. })
+        | Err(e @ SignError::InvalidSignature { .. }) => AuditEntry {
+            reason: Some(e.to_string()),
+            ..csr_entry
+        },
+        Err(e) => AuditEntry {
+            event: AuditEvent::Failed,
+            reason: Some(e.to_string()),
+            ..csr_entry
+        },
+    };
+    audit::record(storage, entry).await;
+
+    result
+}
+
+fn record_sign_result(metrics: &Metrics, result: &Result<Cert, SignError>) {
+    match result {
+        Ok(_) => metrics.inc_certificates_issued("success"),
+        Err(SignError::PolicyViolation { .. }) => {
+            metrics.inc_certificates_issued("rejected");
+            metrics.inc_csrs_rejected("issuance_policy");
+        }
+        Err(SignError::WeakCsr { rule, .. }) => {
+            metrics.inc_certificates_issued("rejected");
+            metrics.inc_csrs_rejected(rule);
+        }
+        Err(SignError::InvalidSignature { .. }) => {
+            metrics.inc_certificates_issued("rejected");
+            metrics.inc_csrs_rejected("invalid_signature");
+        }
+        Err(SignError::Issuance { .. }) | Err(SignError::Other { .. }) => metrics.inc_certificates_issued("error"),
+    }
+}
+
+/// Refreshes gauges and renders metrics in Prometheus text format
+async fn scrape_metrics(metrics: &Metrics, config: &Config, storage: &dyn PickyStorage) -> String {
+    let report = check_readiness(config, storage).await;
+    metrics.set_backend_up(report.failing_component.as_deref() != Some("database"));
+    if let Some(days) = report.intermediate_expires_in_days {
+        metrics.set_intermediate_expiry_days(days);
+    }
+    metrics.render()
+}
+
+/// Answered as long as the process is up, without reaching the backend
+fn liveness_report() -> serde_json::Value {
+    serde_json::json!({ "status": "ok", "version": PICKY_VERSION })
+}
+
+async fn check_readiness(config: &Config, storage: &dyn PickyStorage) -> ReadinessReport {
+    let mut report = ReadinessReport {
+        status: "ok".to_owned(),
+        backend: config.backend.clone(),
+        db_latency_ms: None,
+        intermediate_expires_in_days: None,
+        version: PICKY_VERSION.to_owned(),
+        failing_component: None,
+        error: None,
+    };
+
+    let start = std::time::Instant::now();
+    if let HealthStatus::Degraded { message } = storage.health().await {
+        return report.fail("database", format!("storage is unavailable: {}", message));
+    }
+    report.db_latency_ms = Some(u64::try_from(start.elapsed().as_millis()).unwrap_or(u64::MAX));
+
+    let root_name = format!("{} Root CA", config.realm);
+    if let Err(e) = find_valid_ca(storage, &root_name).await {
+        return report.fail("root_ca", e);
+    }
+
+    let intermediate_name = format!("{} Authority", config.realm);
+    match find_valid_ca(storage, &intermediate_name).await {
+        Ok(intermediate) => {
+            let expires_in = DateTime::<Utc>::from(intermediate.valid_not_after()) - Utc::now();
+            report.intermediate_expires_in_days = Some(expires_in.num_days());
+        }
+        Err(e) => return report.fail("intermediate_ca", e),
+    }
+
+    if let Err(e) = load_ca(&intermediate_name, storage).await {
+        return report.fail("signing_key", e);
+    }
+
+    report
+}
+
+/// Names of the intermediate CAs certificates can be signed with, the default one first
+fn intermediate_ca_names(config: &Config) -> Vec<String> {
+    let mut names = vec![format!("{} Authority", config.realm)];
+    names.extend(config.intermediates.iter().cloned());
+    names
+}
+
+/// Picks the requested intermediate CA, or the default one when none is requested
+fn select_ca(config: &Config, requested: Option<&str>) -> Result<String, ApiError> {
+    match requested {
+        None => Ok(format!("{} Authority", config.realm)),
+        Some(requested) if intermediate_ca_names(config).iter().any(|name| name == requested) => {
+            Ok(requested.to_owned())
+        }
+        Some(requested) => Err(ApiError::new(
+            ApiErrorKind::CaNotFound,
+            format!("unknown CA: {}", requested),
+        )),
+    }
+}
+
+async fn list_cas(config: &Config, storage: &dyn PickyStorage) -> Result<Vec<CaDescription>, String> {
+    let mut cas = Vec::new();
+    for (idx, name) in intermediate_ca_names(config).into_iter().enumerate() {
+        let hash = storage
+            .get_addressing_hash_by_name(&name)
+            .await
+            .map_err(|e| format!("couldn't fetch CA {}: {}", name, e))?;
+        let cert_der = storage
+            .get_cert_by_addressing_hash(&hash)
+            .await
+            .map_err(|e| format!("couldn't fetch CA {} certificate: {}", name, e))?;
+        let cert = Cert::from_der(&cert_der).map_err(|e| format!("couldn't deserialize CA {}: {}", name, e))?;
+
+        cas.push(CaDescription::new(name, &cert, idx == 0));
+    }
+    Ok(cas)
+}
+
+/// Fetches a CA certificate by name, failing when it is missing or expired
+async fn find_valid_ca(storage: &dyn PickyStorage, ca_name: &str) -> Result<Cert, String> {
+    let hash = storage
+        .get_addressing_hash_by_name(ca_name)
+        .await
+        .map_err(|e| format!("'{}' not found: {}", ca_name, e))?;
+    let cert_der = storage
+        .get_cert_by_addressing_hash(&hash)
+        .await
+        .map_err(|e| format!("couldn't fetch '{}': {}", ca_name, e))?;
+    let cert = Cert::from_der(&cert_der).map_err(|e| format!("couldn't deserialize '{}': {}", ca_name, e))?;
+
+    if DateTime::<Utc>::from(cert.valid_not_after()) <= Utc::now() {
+        return Err(format!("'{}' is expired", ca_name));
+    }
+
+    Ok(cert)
+}
+
+async fn find_certs_by_name(storage: &dyn PickyStorage, name: &str) -> Result<Vec<CertificateMetadata>, String> {
+    let hashes = storage
+        .get_addressing_hashes_by_name(name)
+        .await
+        .map_err(|e| format!("couldn't fetch addressing hashes: {}", e))?;
+
+    let mut entries = Vec::with_capacity(hashes.len());
+    for hash in hashes {
+        entries.push(CertificateMetadata::load(storage, hash).await?);
+    }
+
+    Ok(entries)
+}
+
+async fn list_certificates(
+    storage: &dyn PickyStorage,
+    filter: &ListFilter,
+    offset: usize,
+    limit: usize,
+) -> Result<CertificateList, String> {
+    // one more certificate is fetched to know whether there is a next page
+    let mut listed = storage
+        .list(filter, offset, limit + 1)
+        .await
+        .map_err(|e| format!("couldn't list certificates: {}", e))?;
+
+    let next_offset = if listed.len() > limit {
+        listed.truncate(limit);
+        Some(offset + limit)
+    } else {
+        None
+    };
+    let total = storage
+        .count()
+        .await
+        .map_err(|e| format!("couldn't count certificates: {}", e))?;
+
+    let mut certificates = Vec::with_capacity(listed.len());
+    for entry in listed {
+        certificates.push(CertificateMetadata::load(storage, entry.addressing_hash).await?);
+    }
+
+    Ok(CertificateList {
+        certificates,
+        total,
+        next_offset,
+    })
+}
+
+/// Parses an RFC 3339 date from the query string
+fn query_date<T>(req: &Request<T>, param_name: &str) -> Result<Option<DateTime<Utc>>, ApiError> {
+    match req.get_query_param(param_name) {
+        Some(date) => DateTime::parse_from_rfc3339(&date)
+            .map(|date| Some(date.with_timezone(&Utc)))
+            .bad_request_desc(&format!("invalid {} date (RFC 3339 expected)", param_name)),
+        None => Ok(None),
+    }
+}
+
+fn revocation_status_headers(builder: ResponseBuilder, revocation: Option<&RevocationEntry>) -> ResponseBuilder {
+    match revocation {
+        Some(revocation) => {
+            let builder = builder.header("Picky-Revocation-Status", "revoked").header(
+                "Picky-Revocation-Date",
+                Utc.timestamp(revocation.revocation_date, 0).to_rfc3339(),
+            );
+            match revocation.reason {
+                Some(reason) => builder.header("Picky-Revocation-Reason", reason.to_string()),
+                None => builder,
+            }
+        }
+        None => builder.header("Picky-Revocation-Status", "good"),
+    }
+}
+
+/// Address of the latest certificate stored for a hex-encoded subject key identifier (colons allowed)
+async fn find_address_by_ski(storage: &dyn PickyStorage, ski: &str) -> Result<String, ApiError> {
+    let ski = ski.replace(':', "").to_ascii_lowercase();
+    if ski.is_empty() {
+        return Err(ApiError::new(
+            ApiErrorKind::BadRequest,
+            "subject key identifier is empty",
+        ));
+    }
+    hex::decode(&ski).bad_request_desc("subject key identifier isn't valid hex")?;
+
+    storage
+        .get_addressing_hash_by_key_identifier(&ski)
+        .await
+        .map_err(|_| ApiError::new(ApiErrorKind::NotFound, format!("no certificate found for SKI {}", ski)))
+}
+
+/// URL at which the issuer certificate is served, embedded as caIssuers location in issued certificates.
+///
+/// Derived from the issuer SKI rather than its name, so that it stays valid across restarts and rotations.
+fn aia_url(external_url: &str, issuer_cert: &Cert) -> Result<String, String> {
+    let ski = issuer_cert
+        .subject_key_identifier()
+        .map_err(|e| format!("couldn't get issuer SKI: {}", e))?;
+    Ok(format!(
+        "{}/aia/{}.cer",
+        external_url.trim_end_matches('/'),
+        hex::encode(ski)
+    ))
+}
+
+/// DER of the CA certificate named by an AIA file name (`<hex-ski>.cer`)
+async fn fetch_aia_certificate(storage: &dyn PickyStorage, file_name: &str) -> Result<Vec<u8>, ApiError> {
+    let ski = file_name.strip_suffix(".cer").ok_or_else(|| {
+        ApiError::new(
+            ApiErrorKind::NotFound,
+            format!("{} isn't a certificate file", file_name),
+        )
+    })?;
+    let canonical_address = find_address_by_ski(storage, ski).await?;
+
+    let cert_der = storage
+        .get_cert_by_addressing_hash(&canonical_address)
+        .await
+        .not_found_desc("couldn't fetch certificate")?;
+    let cert = Cert::from_der(&cert_der).internal_error_desc("couldn't parse stored certificate")?;
+    match cert.basic_constraints() {
+        Ok(constraints) if constraints.ca() == Some(true) => Ok(cert_der),
+        _ => Err(ApiError::new(
+            ApiErrorKind::NotFound,
+            format!("no CA certificate found for SKI {}", ski),
+        )),
+    }
+}
+
+/// Certificate stored at this address, encoded as negotiated with the `Accept` header, along with its revocation
+async fn fetch_certificate(
+    req: &Request,
+    storage: &dyn PickyStorage,
+    canonical_address: &str,
+) -> Result<(CertificateBody, Option<RevocationEntry>), ApiError> {
+    let (format, content_type) = Format::negotiate(req, CERT_RESPONSE_FORMATS)?;
+
+    let cert_der = storage
+        .get_cert_by_addressing_hash(canonical_address)
+        .await
+        .not_found_desc("couldn't fetch certificate")?;
+
+    let revocation = storage
+        .get_revocation_by_addressing_hash(canonical_address)
+        .await
+        .internal_error_desc("couldn't fetch revocation status")?;
+
+    let json = if format == Format::Json {
+        let response = CertificateResponse {
+            metadata: CertificateMetadata::load(storage, canonical_address.to_owned())
+                .await
+                .internal_error_desc("couldn't describe certificate")?,
+            certificate: to_pem("CERTIFICATE", &cert_der),
+        };
+        Some(serde_json::to_string(&response).internal_error_desc("couldn't encode certificate")?)
+    } else {
+        None
+    };
+
+    Ok((CertificateBody::new(format, content_type, &cert_der, json)?, revocation))
+}
+
+fn crl_needs_refresh(crl: &Crl) -> bool {
+    match crl.next_update() {
+        Some(next_update) => {
+            DateTime::<Utc>::from(next_update) - chrono::Duration::hours(CRL_REFRESH_MARGIN_HOURS) <= Utc::now()
+        }
+        None => true,
+    }
+}
+
+fn crl_response(crl: &Crl, req: &Request) -> Result<ResponseBuilder, ApiError> {
+    match Format::response_format(req).unwrap_or(Format::PemFile) {
+        Format::PemFile => {
+            let pem = crl.to_pem().internal_error_desc("couldn't get CRL pem")?;
+            Ok(ResponseBuilder::new().body(pem.to_string()))
+        }
+        Format::PkixCrlBinary => {
+            let der = crl.to_der().internal_error_desc("couldn't get CRL der")?;
+            Ok(ResponseBuilder::new().body(der))
+        }
+        unexpected => Err(unexpected_response_format(unexpected)),
+    }
+}
+
+/// Canonical address of a stored certificate, given as a multihash or as a bare hex digest.
+///
+/// Each form the address may stand for is tried in turn, alternative hashes being looked up.
+async fn find_canonical_address(storage: &dyn PickyStorage, address: &str) -> Result<String, ApiError> {
+    let candidates = address_candidates(address).bad_request()?;
+    let last = candidates.len() - 1;
+    for (index, (addressing_hash, hash)) in candidates.into_iter().enumerate() {
+        if hash == CANONICAL_HASH {
+            if index == last || storage.get_cert_by_addressing_hash(&addressing_hash).await.is_ok() {
+                return Ok(addressing_hash);
+            }
+        } else if let Ok(converted) = storage.lookup_addressing_hash(&addressing_hash).await {
+            log::info!("converted cert address {} -> {}", address, converted);
+            return Ok(converted);
+        }
+    }
+    Err(ApiError::new(ApiErrorKind::NotFound, "address lookup failed"))
+}
+
+/// DER-encoded CA chain, from the given CA to the root
+async fn find_ca_chain_der(
+    storage: &dyn PickyStorage,
+    ca_name: &str,
+    max_depth: usize,
+) -> Result<Vec<Vec<u8>>, String> {
+    let ca_hash = storage
+        .get_addressing_hash_by_name(ca_name)
+        .await
+        .map_err(|e| format!("couldn't fetch CA hash id for {}: {}", ca_name, e))?;
+
+    let cert_der = storage
+        .get_cert_by_addressing_hash(&ca_hash)
+        .await
+        .map_err(|e| format!("couldn't fetch CA certificate der: {}", e))?;
+    build_ca_chain_der(storage, cert_der, max_depth).await
+}
+
+async fn find_issuer_chain(storage: &dyn PickyStorage, cert: &Cert, max_depth: usize) -> Result<Vec<String>, String> {
+    Ok(find_issuer_chain_der(storage, cert, max_depth)
+        .await?
+        .iter()
+        .map(|der| to_pem("CERTIFICATE", der))
+        .collect())
+}
+
+/// DER-encoded CA chain of a certificate, from its issuer to the root.
+///
+/// The issuer is found by authority key identifier rather than by name so that certificates signed
+/// before an intermediate rotation still get the chain of the key that signed them.
+async fn find_issuer_chain_der(
+    storage: &dyn PickyStorage,
+    cert: &Cert,
+    max_depth: usize,
+) -> Result<Vec<Vec<u8>>, String> {
+    let issuer_key_id = hex::encode(
+        cert.authority_key_identifier()
+            .map_err(|e| format!("couldn't fetch authority key identifier: {}", e))?
+            .key_identifier()
+            .ok_or_else(|| "issuer key identifier not found".to_owned())?,
+    );
+    let issuer_hash = storage
+        .get_addressing_hash_by_key_identifier(&issuer_key_id)
+        .await
+        .map_err(|e| format!("couldn't fetch issuer hash: {}", e))?;
+    let issuer_der = storage
+        .get_cert_by_addressing_hash(&issuer_hash)
+        .await
+        .map_err(|e| format!("couldn't fetch issuer certificate der: {}", e))?;
+    build_ca_chain_der(storage, issuer_der, max_depth).await
+}
+
+/// Follows authority key identifiers from a CA certificate up to its root.
+///
+/// Stored data isn't trusted to be well formed: the walk stops with an error after `max_depth`
+/// certificates or when it comes back to an already visited key identifier (e.g. a cross-signed pair).
+async fn build_ca_chain_der(
+    storage: &dyn PickyStorage,
+    mut cert_der: Vec<u8>,
+    max_depth: usize,
+) -> Result<Vec<Vec<u8>>, String> {
+    let mut chain = vec![cert_der.clone()];
+    let mut visited = HashSet::new();
+    let mut current_key_id = String::default();
+    loop {
+        let cert = Cert::from_der(&cert_der).map_err(|e| format!("couldn't deserialize certificate: {}", e))?;
+        // the key identifier the certificate was found by, unless it's the first one
+        let key_id = match cert.subject_key_identifier() {
+            Ok(ski) => hex::encode(ski),
+            Err(_) => current_key_id,
+        };
+
+        let parent_key_id = hex::encode(
+            cert.authority_key_identifier()
+                .map_err(|e| format!("couldn't fetch authority key identifier: {}", e))?
+                .key_identifier()
+                .ok_or_else(|| "parent key identifier not found".to_owned())?,
+        );
+
+        if key_id == parent_key_id {
+            // The authority is itself. It is a root.
+            break;
+        }
+
+        visited.insert(key_id);
+        if visited.contains(&parent_key_id) {
+            return Err(format!(
+                "CA chain cycle: {} is issued by already visited key {}",
+                cert.subject_name(),
+                parent_key_id
+            ));
+        }
+        if chain.len() >= max_depth {
+            return Err(format!(
+                "CA chain exceeds the maximum depth of {} certificates",
+                max_depth
+            ));
+        }
+
+        let hash_address = storage
+            .get_addressing_hash_by_key_identifier(&parent_key_id)
+            .await
+            .map_err(|e| {
+                format!(
+                    "CA chain incomplete: issuer {} of {} not found: {}",
+                    parent_key_id,
+                    cert.subject_name(),
+                    e
+                )
+            })?;
+
+        cert_der = storage
+            .get_cert_by_addressing_hash(&hash_address)
+            .await
+            .map_err(|e| format!("couldn't fetch certificate der: {}", e))?;
+
+        chain.push(cert_der.clone());
+
+        current_key_id = parent_key_id;
+    }
+
+    Ok(chain)
+}
+
+/// Orders the certificates of a CA chain leaf-most CA first, whatever the order they were found in,
+/// dropping the ones reachable twice.
+fn order_chain(chain_der: &[Vec<u8>]) -> Result<Vec<ChainCert>, String> {
+    let mut certs: Vec<ChainCert> = Vec::with_capacity(chain_der.len());
+    for der in chain_der {
+        if certs.iter().any(|known| known.der == *der) {
+            continue;
+        }
+        certs.push(ChainCert::new(der)?);
+    }
+
+    // the leaf-most CA is the only one issuing no other certificate of the chain
+    let mut leaves = certs.iter().enumerate().filter(|(_, cert)| {
+        !certs
+            .iter()
+            .any(|other| !other.is_root() && other.authority_key_id.as_deref() == Some(cert.ski.as_str()))
+    });
+    let mut current = match (leaves.next(), leaves.next()) {
+        (Some((index, _)), None) => index,
+        (None, _) if certs.is_empty() => return Ok(certs),
+        _ => return Err("CA chain certificates don't form a single chain".to_owned()),
+    };
+
+    let mut ordered = Vec::with_capacity(certs.len());
+    loop {
+        let cert = certs.swap_remove(current);
+        let parent = if cert.is_root() {
+            None
+        } else {
+            certs
+                .iter()
+                .position(|parent| cert.authority_key_id.as_deref() == Some(parent.ski.as_str()))
+        };
+        ordered.push(cert);
+        match parent {
+            Some(parent) => current = parent,
+            None => break,
+        }
+    }
+
+    if certs.is_empty() {
+        Ok(ordered)
+    } else {
+        Err("CA chain certificates don't form a single chain".to_owned())
+    }
+}
+
+struct ChainCert {
+    cert: Cert,
+    der: Vec<u8>,
+    ski: String,
+    authority_key_id: Option<String>,
+}
+
+impl ChainCert {
+    fn new(der: &[u8]) -> Result<Self, String> {
+        let cert = Cert::from_der(der).map_err(|e| format!("couldn't deserialize certificate: {}", e))?;
+        let ski = hex::encode(
+            cert.subject_key_identifier()
+                .map_err(|e| format!("couldn't fetch subject key identifier: {}", e))?,
+        );
+        let authority_key_id = cert
+            .authority_key_identifier()
+            .ok()
+            .and_then(|aki| aki.key_identifier().map(hex::encode));
+        Ok(Self {
+            cert,
+            der: der.to_vec(),
+            ski,
+            authority_key_id,
+        })
+    }
+
+    fn is_root(&self) -> bool {
+        self.authority_key_id.as_deref() == Some(self.ski.as_str())
+    }
+}
+
+/// Certificates of a CA chain answered by `GET /chain`, depending on the `root` query parameter
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum RootMode {
+    /// The whole chain, root included
+    Include,
+    /// CAs without the root, as sent by TLS servers
+    Exclude,
+    /// The root alone, to provision trust stores
+    Only,
+}
+
+impl FromStr for RootMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "include" => Ok(RootMode::Include),
+            "exclude" => Ok(RootMode::Exclude),
+            "only" => Ok(RootMode::Only),
+            _ => Err(format!("unknown root mode: {}", s)),
+        }
+    }
+}
+
+/// Encodes a CA chain as answered by `GET /chain`, leaf-most CA first
+fn encode_chain(chain_der: &[Vec<u8>], format: Format, root: RootMode) -> Result<String, String> {
+    let chain: Vec<ChainEntry> = order_chain(chain_der)?
+        .into_iter()
+        .filter(|cert| match root {
+            RootMode::Include => true,
+            RootMode::Exclude => !cert.is_root(),
+            RootMode::Only => cert.is_root(),
+        })
+        .map(|cert| ChainEntry {
+            subject: cert.cert.subject_name().to_string(),
+            pem: to_pem("CERTIFICATE", &cert.der),
+            ski: cert.ski,
+        })
+        .collect();
+
+    match format {
+        Format::Json => {
+            serde_json::to_string(&ChainResponse { chain }).map_err(|e| format!("couldn't encode chain: {}", e))
+        }
+        _ => Ok(chain.into_iter().map(|entry| entry.pem).collect::<Vec<_>>().join("\n")),
+    }
+}
+
+fn generate_ca_key(key_type: KeyType, bits: usize) -> Result<PrivateKey, String> {
+    match key_type {
+        KeyType::Rsa if bits < MIN_CA_RSA_KEY_SIZE => Err(format!(
+            "RSA keys must be at least {} bits (got {})",
+            MIN_CA_RSA_KEY_SIZE, bits
+        )),
+        KeyType::Rsa => Picky::generate_private_key(bits).map_err(|e| format!("couldn't generate private key: {}", e)),
+    }
+}
+
+async fn generate_root_ca(config: &Config, storage: &dyn PickyStorage) -> Result<bool, String> {
+    let name = format!("{} Root CA", config.realm);
+
+    if let Ok(certs) = storage.get_addressing_hash_by_name(&name).await {
+        if !certs.is_empty() {
+            // already exists
+            return Ok(false);
+        }
+    }
+
+    let pk = generate_ca_key(config.key_type, config.root_key_size)?;
+    let options = RootIssuanceOptions {
+        pathlen: config.root_pathlen,
+        key_id_gen_method: config.key_id_method,
+        ..RootIssuanceOptions::new(config.root_validity)
+    };
+    let root = Picky::generate_root_with_options(&name, &pk, config.signing_algorithm, options)
+        .map_err(|e| format!("couldn't generate root certificate: {}", e))?;
+    let ski = root
+        .subject_key_identifier()
+        .map_err(|e| format!("couldn't fetch subject key identifier: {}", e))?;
+
+    let cert_der = root
+        .to_der()
+        .map_err(|e| format!("couldn't serialize root certificate into der: {}", e))?;
+
+    let pk_pkcs8 = pk
+        .to_pkcs8()
+        .map_err(|e| format!("couldn't get private key pkcs8: {}", e))?;
+
+    storage
+        .store(
+            CertificateEntry {
+                name,
+                cert: cert_der,
+                key_identifier: hex::encode(ski),
+                key: Some(pk_pkcs8),
+            },
+            ConflictPolicy::ErrorIfExists,
+        )
+        .await
+        .map_err(|e| format!("couldn't store generated root certificate: {}", e))?;
+
+    Ok(true)
+}
+
+async fn generate_intermediate_ca(config: &Config, storage: &dyn PickyStorage) -> Result<bool, String> {
+    generate_named_intermediate_ca(&format!("{} Authority", config.realm), config, storage).await
+}
+
+async fn generate_named_intermediate_ca(
+    intermediate_name: &str,
+    config: &Config,
+    storage: &dyn PickyStorage,
+) -> Result<bool, String> {
+    if let Ok(certs) = storage.get_addressing_hash_by_name(intermediate_name).await {
+        if !certs.is_empty() {
+            // already exists
+            return Ok(false);
+        }
+    }
+
+    issue_intermediate_ca(intermediate_name, config, storage).await?;
+    Ok(true)
+}
+
+/// Generates a new key and certificate for an intermediate CA, signed by the stored root.
+///
+/// Previous certificates of this intermediate are kept, its name resolving to the newest one.
+async fn issue_intermediate_ca(
+    intermediate_name: &str,
+    config: &Config,
+    storage: &dyn PickyStorage,
+) -> Result<Cert, String> {
+    let root_name = format!("{} Root CA", config.realm);
+    let root_hash = storage
+        .get_addressing_hash_by_name(&root_name)
+        .await
+        .map_err(|e| format!("error while fetching root: {}", e))?;
+
+    let root_cert_der = storage
+        .get_cert_by_addressing_hash(&root_hash)
+        .await
+        .map_err(|e| format!("couldn't fetch root CA: {}", e))?;
+
+    let root_key_der = storage
+        .get_key_by_addressing_hash(&root_hash)
+        .await
+        .map_err(|e| format!("couldn't fetch root CA private key: {}", e))?;
+
+    let root_cert = Cert::from_der(&root_cert_der).map_err(|e| format!("couldn't parse root cert from der: {}", e))?;
+    let root_key = Picky::parse_pk_from_magic_der(&root_key_der).map_err(|e| e.to_string())?;
+
+    // issuers are resolved by key identifier, a key already used by a stored CA would make chains ambiguous
+    let mut attempts = 0;
+    let (pk, intermediate_cert, ski) = loop {
+        let pk = generate_ca_key(config.key_type, config.intermediate_key_size)?;
+        let options = IntermediateIssuanceOptions {
+            key_id_gen_method: config.key_id_method,
+            ..IntermediateIssuanceOptions::new(config.intermediate_validity)
+        };
+        let intermediate_cert = Picky::generate_intermediate_with_options(
+            intermediate_name,
+            pk.to_public_key(),
+            &root_cert,
+            &root_key,
+            config.signing_algorithm,
+            options,
+        )
+        .map_err(|e| format!("couldn't generate intermediate certificate: {}", e))?;
+
+        let ski = hex::encode(
+            intermediate_cert
+                .subject_key_identifier()
+                .map_err(|e| format!("couldn't fetch key id: {}", e))?,
+        );
+
+        if storage.get_addressing_hash_by_key_identifier(&ski).await.is_err() {
+            break (pk, intermediate_cert, ski);
+        }
+
+        attempts += 1;
+        if attempts == MAX_CA_KEY_ATTEMPTS {
+            return Err("couldn't generate a key not already used by a stored CA".to_owned());
+        }
+    };
+
+    let cert_der = intermediate_cert
+        .to_der()
+        .map_err(|e| format!("couldn't serialize intermediate certificate into der: {}", e))?;
+
+    let pk_pkcs8 = pk
+        .to_pkcs8()
+        .map_err(|e| format!("couldn't get private key pkcs8: {}", e))?;
+
+    storage
+        .store(
+            CertificateEntry {
+                name: intermediate_name.to_owned(),
+                cert: cert_der,
+                key_identifier: ski,
+                key: Some(pk_pkcs8),
+            },
+            ConflictPolicy::ErrorIfExists,
+        )
+        .await
+        .map_err(|e| format!("couldn't store generated intermediate certificate: {}", e))?;
+
+    Ok(intermediate_cert)
+}
+
+async fn generate_tls_identity(config: &Config, storage: &dyn PickyStorage) -> Result<(String, String), String> {
+    let intermediate_name = format!("{} Authority", config.realm);
+    let (ca_cert, ca_pk) = load_ca(&intermediate_name, storage).await?;
+
+    let dns_name = config.tls_bootstrap_dns_name.as_str();
+    let pk = Picky::generate_private_key(2048).map_err(|e| e.to_string())?;
+    let csr = Csr::generate(DirectoryName::new_common_name(dns_name), &pk, config.signing_algorithm)
+        .map_err(|e| format!("couldn't generate server csr: {}", e))?;
+    let san = GeneralNames::new(
+        GeneralName::new_dns_name(dns_name).map_err(|e| format!("invalid server dns name {}: {}", dns_name, e))?,
+    );
+
+    let server_cert = Picky::generate_leaf_from_csr(
+        csr,
+        &ca_cert,
+        &ca_pk,
+        config.signing_algorithm,
+        config.key_id_method,
+        Some(san),
+        chrono::Duration::seconds(DEFAULT_CERT_DURATION_SECS as i64),
+    )
+    .map_err(|e| format!("couldn't generate server certificate: {}", e))?;
+
+    let certificate_chain = [&server_cert, &ca_cert]
+        .iter()
+        .map(|cert| cert.to_pem().map(|pem| pem.to_string()))
+        .collect::<Result<Vec<String>, _>>()
+        .map_err(|e| format!("couldn't encode server certificate chain: {}", e))?
+        .concat();
+    let private_key = pk
+        .to_pem()
+        .map_err(|e| format!("couldn't encode server private key: {}", e))?;
+
+    Ok((certificate_chain, private_key))
+}
+
+async fn read_pem_file(path: &Path, what: &str) -> Result<Pem<'static>, String> {
+    let pem_str = tokio::fs::read_to_string(path)
+        .await
+        .map_err(|e| format!("couldn't read {} file {}: {}", what, path.display(), e))?;
+    pem_str
+        .parse::<Pem>()
+        .map_err(|e| format!("couldn't parse {} pem in {}: {}", what, path.display(), e))
+}
+
+async fn load_cert_key_pair(cert_key_pair: &CertKeyPair) -> Result<(Cert, PrivateKey), String> {
+    let cert = match &cert_key_pair.cert {
+        PathOr::Path(path) => {
+            let pem = read_pem_file(path, "cert").await?;
+            Cert::from_pem(&pem).map_err(|e| format!("couldn't parse cert in {}: {}", path.display(), e))?
+        }
+        PathOr::Some(cert) => cert.clone(),
+    };
+
+    let key = match &cert_key_pair.key {
+        PathOr::Path(path) => {
+            let pem = read_pem_file(path, "key").await?;
+            Picky::parse_pk_from_magic_der(pem.data())
+                .map_err(|e| format!("couldn't parse key in {}: {}", path.display(), e))?
+        }
+        PathOr::Some(key) => key.clone(),
+    };
+
+    Ok((cert, key))
+}
+
+async fn inject_config_provided_cert(
+    expected_subject_name: &str,
+    cert_key_pair: &CertKeyPair,
+    storage: &dyn PickyStorage,
+) -> Result<(), String> {
+    let (cert, key) = load_cert_key_pair(cert_key_pair).await?;
+    // the storage holds DER, whatever form the configuration provides
+    let cert_der = cert
+        .to_der()
+        .map_err(|e| format!("couldn't encode cert to der: {}", e))?;
+
+    let ski = hex::encode(
+        cert.subject_key_identifier()
+            .map_err(|e| format!("couldn't parse fetch subject key identifier: {}", e))?,
+    );
+    let subject_name = cert
+        .subject_name()
+        .common_name()
+        .ok_or_else(|| "couldn't find subject common name".to_owned())?
+        .to_string();
+
+    if subject_name != expected_subject_name {
+        return Err(format!(
+            "unexpected subject name: {} ; expected: {}",
+            subject_name, expected_subject_name
+        ));
+    }
+
+    let key_der = key
+        .to_pkcs8()
+        .map_err(|e| format!("couldn't convert key to pkcs8: {}", e))?;
+
+    // provided again on every startup
+    let outcome = storage
+        .store(
+            CertificateEntry {
+                name: subject_name,
+                cert: cert_der,
+                key_identifier: ski,
+                key: Some(key_der),
+            },
+            ConflictPolicy::Ignore,
+        )
+        .await
+        .map_err(|e| format!("couldn't store certificate: {}", e))?;
+    if outcome == StoreOutcome::AlreadyExists {
+        log::debug!("{} is already stored", expected_subject_name);
+    }
+
+    Ok(())
+}
+
+async fn init_storage_from_config(storage: &dyn PickyStorage, config: &Config) -> Result<(), String> {
+    log::info!("init storage from config");
+
+    if let Some(root_cert_key_pair) = &config.root {
+        log::info!("inject root CA provided by settings");
+        let expected = format!("{} Root CA", config.realm);
+        if let Err(e) = inject_config_provided_cert(&expected, root_cert_key_pair, storage).await {
+            return Err(format!("couldn't inject root CA: {}", e));
+        }
+    } else {
+        log::info!("root CA...");
+        let created = generate_root_ca(&config, storage)
+            .await
+            .map_err(|e| format!("couldn't generate root CA: {}", e))?;
+        if created {
+            log::info!("created");
+        } else {
+            log::info!("already exists");
+        }
+    }
+
+    if let Some(intermediate_cert_key_pair) = &config.intermediate {
+        log::info!("inject intermediate CA provided by settings");
+        let expected = format!("{} Authority", config.realm);
+        if let Err(e) = inject_config_provided_cert(&expected, intermediate_cert_key_pair, storage).await {
+            return Err(format!("couldn't inject intermediate CA: {}", e));
+        }
+    } else {
+        log::info!("intermediate CA...");
+        let created = generate_intermediate_ca(&config, storage)
+            .await
+            .map_err(|e| format!("couldn't generate intermediate CA: {}", e))?;
+        if created {
+            log::info!("created");
+        } else {
+            log::info!("already exists");
+        }
+    }
+
+    for intermediate_name in &config.intermediates {
+        log::info!("intermediate CA {}...", intermediate_name);
+        let created = generate_named_intermediate_ca(intermediate_name, config, storage)
+            .await
+            .map_err(|e| format!("couldn't generate intermediate CA {}: {}", intermediate_name, e))?;
+        if created {
+            log::info!("created");
+        } else {
+            log::info!("already exists");
+        }
+    }
+
+    Ok(())
+}
+
+// === one-off commands === //
+
+/// `init` command: generates or imports the CAs as on server startup
+pub async fn init_command(config: &Config, storage: &dyn PickyStorage) -> Result<(), String> {
+    init_storage_from_config(storage, config).await
+}
+
+/// `show-chain` command: PEM chain of the realm's CA, root included
+pub async fn show_chain_command(config: &Config, storage: &dyn PickyStorage) -> Result<String, String> {
+    let ca_name = select_ca(config, None).map_err(|e| e.message)?;
+    let chain = find_ca_chain_der(storage, &ca_name, config.max_chain_depth).await?;
+    encode_chain(&chain, Format::PemFile, RootMode::Include)
+}
+
+/// `sign` command: issues a certificate with the realm's CA for a PEM, DER or base64 CSR,
+/// returning the PEM certificate
+pub async fn sign_command(config: &Config, storage: &dyn PickyStorage, csr: &[u8]) -> Result<String, String> {
+    let csr = binary_body_to_der(csr)
+        .and_then(|der| Ok(Csr::from_der(&der)?))
+        .map_err(|e| format!("invalid CSR: {}", e))?;
+    let ca_name = select_ca(config, None).map_err(|e| e.message)?;
+    let cert = sign_and_audit(
+        &ca_name,
+        csr,
+        config,
+        storage,
+        // the process exits right away, before any notification could be delivered
+        &Webhooks::disabled(),
+        DEFAULT_CERT_DURATION_SECS,
+        CLI_REQUESTER,
+        false,
+    )
+    .await
+    .map_err(|e| e.to_string())?;
+    cert.to_pem()
+        .map(|pem| pem.to_string())
+        .map_err(|e| format!("couldn't encode certificate: {}", e))
+}
+
+/// `export-ca` command: PEM chain of a CA, the realm's one by default, followed by its private key
+/// encrypted to `passphrase` when one is given
+pub async fn export_ca_command(
+    config: &Config,
+    storage: &dyn PickyStorage,
+    ca: Option<&str>,
+    passphrase: Option<&str>,
+) -> Result<String, String> {
+    if passphrase == Some("") {
+        return Err("the private key can't be exported with an empty passphrase".to_owned());
+    }
+    let ca_name = select_ca(config, ca).map_err(|e| e.message)?;
+    let export = export_ca(&ca_name, config, storage, passphrase, CLI_REQUESTER).await?;
+    Ok(export
+        .chain
+        .into_iter()
+        .chain(export.encrypted_key)
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::AuthMode,
+        db::{cache::CachingStorage, tests::SpyStorage},
+        policy::IssuancePolicy,
+        utils::{unix_epoch, PathOr},
+    };
+    use picky::{
+        jose::{jws::JwsAlg, jwt::JwtSig},
+        oids,
+        x509::{
+            certificate::CertificateBuilder,
+            csr::Attribute,
+            extension::ExtendedKeyUsage,
+            name::{DirectoryName, NameAttr},
+            ocsp::{cert_id, CertStatus},
+            Extension, KeyIdGenMethod,
+        },
+    };
+    use std::net::IpAddr;
+    use tokio_test::block_on;
+
+    async fn find_ca_chain(storage: &dyn PickyStorage, ca_name: &str) -> Result<Vec<String>, String> {
+        Ok(find_ca_chain_der(storage, ca_name, Config::default().max_chain_depth)
+            .await?
+            .iter()
+            .map(|der| to_pem("CERTIFICATE", der))
+            .collect())
+    }
+
+    fn config() -> Config {
+        let mut config = Config::default();
+        config.backend = BackendType::Memory;
+        config
+    }
+
+    /// Controller serving `config` from a fresh storage, without logger to reconfigure on reload
+    fn controller(config: Config) -> ServerController {
+        let storage: Arc<dyn PickyStorage> = Arc::from(block_on(get_storage(&config)));
+        block_on(init_storage_from_config(storage.as_ref(), &config)).expect("couldn't init storage");
+        ServerController {
+            webhooks: Webhooks::start(&config),
+            reloader: ConfigReloader::new(config, Arc::clone(&storage), None),
+            storage,
+            crl_cache: RwLock::new(HashMap::new()),
+            metrics: Arc::new(Metrics::new()),
+        }
+    }
+
+    fn request(method: &str, uri: &str, headers: &[(&str, &str)], body: Vec<u8>) -> Request<Bytes> {
+        let mut builder = saphir::http::Request::builder().method(method).uri(uri);
+        for (name, value) in headers {
+            builder = builder.header(*name, *value);
+        }
+        Request::new(builder.body(Bytes::from(body)).expect("request"), None)
+    }
+
+    fn response_status(response: Result<ResponseBuilder, ApiError>) -> StatusCode {
+        match response {
+            Ok(builder) => builder.build().expect("response").status(),
+            Err(e) => e.status(),
+        }
+    }
+
+    fn named_api_key(name: &str, scopes: &[ApiKeyScope]) -> crate::config::NamedApiKey {
+        crate::config::NamedApiKey {
+            name: name.to_owned(),
+            key: format!("{}-secret", name),
+            scopes: scopes.to_vec(),
+        }
+    }
+
+    #[test]
+    fn second_issuance_performs_no_ca_reads() {
+        let config = config();
+        let (spy, reads) = SpyStorage::new();
+        let storage = CachingStorage::new(
+            Box::new(spy),
+            config.cache_size,
+            std::time::Duration::from_secs(config.cache_ttl_secs),
+        );
+        block_on(init_storage_from_config(&storage, &config)).expect("couldn't init storage");
+        let ca_name = format!("{} Authority", config.realm);
+        // not of the CA key sizes, so that the leaf can't share a key identifier with a CA
+        let pk = PrivateKey::from_pem(&crate::test_files::RSA_3072_PK_1.parse::<Pem>().unwrap()).unwrap();
+
+        let issue = || {
+            let csr = Csr::generate(
+                DirectoryName::new_common_name("cached.local"),
+                &pk,
+                SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_256),
+            )
+            .expect("couldn't generate csr");
+            block_on(async {
+                let cert = sign_certificate(&ca_name, csr.into_verified().unwrap(), &config, &storage, 3600)
+                    .await
+                    .expect("couldn't sign certificate");
+                find_issuer_chain_der(&storage, &cert, config.max_chain_depth)
+                    .await
+                    .expect("couldn't find issuer chain")
+            })
+        };
+
+        let chain = issue();
+        assert_eq!(chain.len(), 2);
+        reads.store(0, std::sync::atomic::Ordering::SeqCst);
+        assert_eq!(issue(), chain);
+        assert_eq!(reads.load(std::sync::atomic::Ordering::SeqCst), 0);
+
+        // dropped on configuration reload
+        storage.clear_cache();
+        issue();
+        assert!(reads.load(std::sync::atomic::Ordering::SeqCst) > 0);
+    }
+
+    #[test]
+    fn generate_chain_and_verify() {
+        let config = config();
+        let storage = block_on(get_storage(&config));
+
+        let ca_name = format!("{} Authority", config.realm);
+
+        block_on(generate_root_ca(&config, storage.as_ref())).expect("couldn't generate root ca");
+        block_on(generate_intermediate_ca(&config, storage.as_ref())).expect("couldn't generate intermediate ca");
+
+        let pk = Picky::generate_private_key(2048).expect("couldn't generate private key");
+        let requested_san = GeneralNames::from(vec![
+            GeneralName::new_dns_name("bushido.local").unwrap(),
+            GeneralName::new_dns_name("www.bushido.local").unwrap(),
+        ]);
+        let csr = Csr::generate_with_attributes(
+            DirectoryName::new_common_name("Mister Bushido"),
+            &pk,
+            SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_384),
+            vec![Attribute::new_extension_request(vec![Extension::new_subject_alt_name(
+                requested_san.clone(),
+            )])],
+        )
+        .expect("couldn't generate csr");
+
+        let signed_cert = block_on(sign_certificate(
+            &ca_name,
+            csr.into_verified().unwrap(),
+            &config,
+            storage.as_ref(),
+            7_776_000, // 3 months,
+        ))
+        .expect("couldn't sign certificate");
+
+        assert_eq!(signed_cert.subject_alt_name().expect("subject alt name"), requested_san);
+
+        let issuer_name = signed_cert.issuer_name().common_name().unwrap().to_string();
+        let chain_pem = block_on(find_ca_chain(storage.as_ref(), &issuer_name)).expect("couldn't fetch CA chain");
+
+        assert_eq!(chain_pem.len(), 2);
+
+        let chain = chain_pem
+            .iter()
+            .map(|cert_pem| {
+                let pem = cert_pem.parse::<Pem>().expect("couldn't parse cert pem");
+                Cert::from_der(pem.data()).expect("couldn't parse cert from der")
+            })
+            .collect::<Vec<Cert>>();
+
+        assert_eq!(chain[0].subject_name().to_string(), "CN=Picky Authority");
+        assert_eq!(chain[1].subject_name().to_string(), "CN=Picky Root CA");
+
+        signed_cert
+            .verifier()
+            .chain(chain.iter())
+            .exact_date(&UTCDate::now())
+            .verify()
+            .expect("couldn't validate ca chain");
+    }
+
+    #[test]
+    fn configured_signing_algorithm_is_used() {
+        let mut config = config();
+        config.signing_algorithm = SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_384);
+        let storage = block_on(get_storage(&config));
+        let ca_name = format!("{} Authority", config.realm);
+
+        block_on(generate_root_ca(&config, storage.as_ref())).expect("couldn't generate root ca");
+        block_on(generate_intermediate_ca(&config, storage.as_ref())).expect("couldn't generate intermediate ca");
+
+        let pk = PrivateKey::from_pem(&crate::test_files::RSA_2048_PK_3.parse::<Pem>().unwrap()).unwrap();
+        let csr = Csr::generate(
+            DirectoryName::new_common_name("sha384.local"),
+            &pk,
+            SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_256),
+        )
+        .expect("couldn't generate csr");
+        let leaf = block_on(sign_certificate(
+            &ca_name,
+            csr.into_verified().unwrap(),
+            &config,
+            storage.as_ref(),
+            3600,
+        ))
+        .expect("couldn't sign certificate");
+
+        let chain = block_on(find_issuer_chain_der(storage.as_ref(), &leaf, config.max_chain_depth))
+            .expect("couldn't find issuer chain");
+        assert_eq!(chain.len(), 2);
+        let certs = std::iter::once(leaf).chain(chain.iter().map(|der| Cert::from_der(der).unwrap()));
+        for cert in certs {
+            assert_eq!(
+                SignatureAlgorithm::from_algorithm_identifier(cert.signature_algorithm()).unwrap(),
+                config.signing_algorithm,
+                "{}",
+                cert.subject_name()
+            );
+        }
+    }
+
+    #[test]
+    fn configured_key_id_method_is_used() {
+        let mut config = config();
+        config.key_id_method = "spk-value-sha1".parse().unwrap();
+        let storage = block_on(get_storage(&config));
+        let ca_name = format!("{} Authority", config.realm);
+
+        block_on(generate_root_ca(&config, storage.as_ref())).expect("couldn't generate root ca");
+        block_on(generate_intermediate_ca(&config, storage.as_ref())).expect("couldn't generate intermediate ca");
+
+        let pk = PrivateKey::from_pem(&crate::test_files::RSA_2048_PK_3.parse::<Pem>().unwrap()).unwrap();
+        let csr = Csr::generate(
+            DirectoryName::new_common_name("sha1-ski.local"),
+            &pk,
+            SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_256),
+        )
+        .expect("couldn't generate csr");
+        let leaf = block_on(sign_certificate(
+            &ca_name,
+            csr.into_verified().unwrap(),
+            &config,
+            storage.as_ref(),
+            3600,
+        ))
+        .expect("couldn't sign certificate");
+
+        let chain = block_on(find_issuer_chain_der(storage.as_ref(), &leaf, config.max_chain_depth))
+            .expect("couldn't find issuer chain");
+        let certs: Vec<Cert> = std::iter::once(leaf)
+            .chain(chain.iter().map(|der| Cert::from_der(der).unwrap()))
+            .collect();
+        for (cert, issuer) in certs.iter().zip(certs.iter().skip(1)) {
+            let ski = config.key_id_method.generate_from(cert.public_key()).unwrap();
+            assert_eq!(cert.subject_key_identifier().unwrap(), ski.as_slice());
+            assert_eq!(
+                cert.authority_key_identifier().unwrap().key_identifier(),
+                Some(issuer.subject_key_identifier().unwrap())
+            );
+        }
+
+        // same value as `openssl x509 -noout -ext subjectKeyIdentifier` for this key
+        let root_key = PrivateKey::from_pem(&crate::test_files::RSA_2048_PK_1.parse::<Pem>().unwrap()).unwrap();
+        let root = Picky::generate_root(
+            "OpenSSL Compatible Root CA",
+            &root_key,
+            config.signing_algorithm,
+            config.key_id_method,
+            chrono::Duration::days(1),
+        )
+        .expect("couldn't generate root");
+        assert_eq!(
+            hex::encode(root.subject_key_identifier().unwrap()),
+            "4a835a617eb62ca458622c6bcecc6f5e7134ad3c"
+        );
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn concurrent_issuance_with_sqlite() {
+        use crate::addressing::encode_to_canonical_address;
+        use std::{sync::Arc, thread};
+
+        let database_path =
+            std::env::temp_dir().join(format!("picky_concurrent_issuance_{}.sqlite3", std::process::id()));
+        for suffix in &["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", database_path.to_string_lossy(), suffix));
+        }
+
+        let mut config = config();
+        config.backend = BackendType::Sqlite;
+        config.database_url = format!("sqlite://{}", database_path.to_string_lossy());
+        config.save_certificate = true;
+        let storage = Arc::new(block_on(get_storage(&config)));
+
+        let ca_name = format!("{} Authority", config.realm);
+        block_on(generate_root_ca(&config, &**storage)).expect("couldn't generate root ca");
+        block_on(generate_intermediate_ca(&config, &**storage)).expect("couldn't generate intermediate ca");
+
+        // both threads sign and store their certificate at the same time
+        let handles = [crate::test_files::RSA_2048_PK_1, crate::test_files::RSA_2048_PK_2]
+            .iter()
+            .enumerate()
+            .map(|(i, key_pem)| {
+                let storage = Arc::clone(&storage);
+                let config = config.clone();
+                let ca_name = ca_name.clone();
+                let key_pem = *key_pem;
+                thread::spawn(move || {
+                    let pk = PrivateKey::from_pem(&key_pem.parse::<Pem>().unwrap()).unwrap();
+                    let common_name = format!("concurrent{}.local", i);
+                    let csr = Csr::generate_with_attributes(
+                        DirectoryName::new_common_name(common_name.as_str()),
+                        &pk,
+                        SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_256),
+                        Vec::new(),
+                    )
+                    .expect("couldn't generate csr");
+                    let cert = block_on(sign_certificate(
+                        &ca_name,
+                        csr.into_verified().unwrap(),
+                        &config,
+                        &**storage,
+                        3600,
+                    ))
+                    .expect("couldn't sign certificate");
+                    (common_name, cert)
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for handle in handles {
+            let (common_name, cert) = handle.join().expect("signing thread panicked");
+            let hash = block_on(storage.get_addressing_hash_by_name(&common_name)).expect("certificate not stored");
+            assert_eq!(hash, encode_to_canonical_address(&cert.to_der().unwrap()));
+        }
+    }
+
+    #[test]
+    fn ca_validity_periods() {
+        let mut config = config();
+        config.root_validity = chrono::Duration::days(20 * 365);
+        config.intermediate_validity = chrono::Duration::days(5 * 365);
+        let storage = block_on(get_storage(&config));
+
+        assert!(block_on(generate_root_ca(&config, storage.as_ref())).expect("couldn't generate root ca"));
+        assert!(
+            block_on(generate_intermediate_ca(&config, storage.as_ref())).expect("couldn't generate intermediate ca")
+        );
+
+        // existing CAs are never regenerated
+        assert!(!block_on(generate_root_ca(&config, storage.as_ref())).unwrap());
+        assert!(!block_on(generate_intermediate_ca(&config, storage.as_ref())).unwrap());
+
+        let not_after = |name: &str| {
+            let hash = block_on(storage.get_addressing_hash_by_name(name)).unwrap();
+            let cert = Cert::from_der(&block_on(storage.get_cert_by_addressing_hash(&hash)).unwrap()).unwrap();
+            DateTime::<Utc>::from(cert.valid_not_after())
+        };
+        let root_drift = Utc::now() + config.root_validity - not_after("Picky Root CA");
+        assert!(root_drift.num_seconds().abs() < 60);
+        let intermediate_drift = Utc::now() + config.intermediate_validity - not_after("Picky Authority");
+        assert!(intermediate_drift.num_seconds().abs() < 60);
+
+        let mut outliving_config = config.clone();
+        outliving_config.root_validity = chrono::Duration::days(365);
+        outliving_config.intermediate_validity = chrono::Duration::days(2 * 365);
+        let storage = block_on(get_storage(&outliving_config));
+
+        block_on(generate_root_ca(&outliving_config, storage.as_ref())).expect("couldn't generate root ca");
+        let err = block_on(generate_intermediate_ca(&outliving_config, storage.as_ref())).unwrap_err();
+        assert!(err.contains("after its issuer"), "{}", err);
+    }
+
+    #[test]
+    fn ca_key_sizes() {
+        let mut config = config();
+        config.intermediate_key_size = 3072;
+        let storage = block_on(get_storage(&config));
+
+        block_on(generate_root_ca(&config, storage.as_ref())).expect("couldn't generate root ca");
+        block_on(generate_intermediate_ca(&config, storage.as_ref())).expect("couldn't generate intermediate ca");
+
+        let key_size = |name: &str| {
+            let hash = block_on(storage.get_addressing_hash_by_name(name)).unwrap();
+            let cert = Cert::from_der(&block_on(storage.get_cert_by_addressing_hash(&hash)).unwrap()).unwrap();
+            cert.public_key().rsa_modulus_bits()
+        };
+        assert_eq!(key_size("Picky Root CA"), Some(4096));
+        assert_eq!(key_size("Picky Authority"), Some(3072));
+
+        let mut weak_config = config.clone();
+        weak_config.root_key_size = 1024;
+        let storage = block_on(get_storage(&weak_config));
+        let err = block_on(generate_root_ca(&weak_config, storage.as_ref())).unwrap_err();
+        assert_eq!(err, "RSA keys must be at least 2048 bits (got 1024)");
+    }
+
+    #[test]
+    fn generate_intermediate_crl() {
+        let config = config();
+        let storage = block_on(get_storage(&config));
+
+        block_on(generate_root_ca(&config, storage.as_ref())).expect("couldn't generate root ca");
+        block_on(generate_intermediate_ca(&config, storage.as_ref())).expect("couldn't generate intermediate ca");
+
+        let ca_name = format!("{} Authority", config.realm);
+        let crl = block_on(generate_crl(&ca_name, &config, storage.as_ref())).expect("couldn't generate CRL");
+
+        let ca_hash = block_on(storage.get_addressing_hash_by_name(&ca_name)).unwrap();
+        let ca_cert = Cert::from_der(&block_on(storage.get_cert_by_addressing_hash(&ca_hash)).unwrap()).unwrap();
+
+        crl.verify(&ca_cert).expect("couldn't verify CRL");
+        assert!(crl.revoked_certificates().is_empty());
+        assert!(!crl_needs_refresh(&crl));
+
+        let crl = Crl::from_pem(&crl.to_pem().unwrap()).expect("couldn't parse CRL pem");
+        assert_eq!(crl.issuer_name().to_string(), "CN=Picky Authority");
+    }
+
+    #[test]
+    fn revoke_and_list_in_crl() {
+        let mut config = config();
+        config.save_certificate = true;
+        let storage = block_on(get_storage(&config));
+
+        let ca_name = format!("{} Authority", config.realm);
+
+        block_on(generate_root_ca(&config, storage.as_ref())).expect("couldn't generate root ca");
+        block_on(generate_intermediate_ca(&config, storage.as_ref())).expect("couldn't generate intermediate ca");
+
+        let pk = Picky::generate_private_key(2048).expect("couldn't generate private key");
+        let csr = Csr::generate(
+            DirectoryName::new_common_name("Revoked Bushido"),
+            &pk,
+            SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_256),
+        )
+        .expect("couldn't generate csr");
+        let signed_cert = block_on(sign_certificate(
+            &ca_name,
+            csr.into_verified().unwrap(),
+            &config,
+            storage.as_ref(),
+            7_776_000,
+        ))
+        .expect("couldn't sign certificate");
+
+        let addressing_hash = encode_to_canonical_address(&signed_cert.to_der().unwrap());
+        assert_eq!(
+            block_on(storage.get_revocation_by_addressing_hash(&addressing_hash)).unwrap(),
+            None
+        );
+
+        let revocation = block_on(revoke_certificate(
+            storage.as_ref(),
+            &addressing_hash,
+            &signed_cert,
+            Some(CrlReason::KeyCompromise),
+        ))
+        .expect("couldn't revoke certificate");
+        assert_eq!(revocation.issuer_name, ca_name);
+        assert_eq!(revocation.reason, Some(1));
+
+        // revoking twice is idempotent
+        let again = block_on(revoke_certificate(
+            storage.as_ref(),
+            &addressing_hash,
+            &signed_cert,
+            Some(CrlReason::Superseded),
+        ))
+        .expect("couldn't revoke certificate twice");
+        assert_eq!(again, revocation);
+        assert_eq!(
+            block_on(storage.get_revocation_by_addressing_hash(&addressing_hash)).unwrap(),
+            Some(revocation)
+        );
+
+        let crl = block_on(generate_crl(&ca_name, &config, storage.as_ref())).expect("couldn't generate CRL");
+        assert_eq!(crl.revoked_certificates().len(), 1);
+        assert!(crl.is_revoked(signed_cert.serial_number()));
+        assert_eq!(
+            crl.revoked_certificates()[0].reason_code(),
+            Some(CrlReason::KeyCompromise)
+        );
+    }
+
+    #[test]
+    fn certificates_found_by_name() {
+        let mut config = config();
+        config.save_certificate = true;
+        let storage = block_on(get_storage(&config));
+
+        let ca_name = format!("{} Authority", config.realm);
+
+        block_on(generate_root_ca(&config, storage.as_ref())).expect("couldn't generate root ca");
+        block_on(generate_intermediate_ca(&config, storage.as_ref())).expect("couldn't generate intermediate ca");
+
+        assert!(block_on(find_certs_by_name(storage.as_ref(), "bushido.local"))
+            .expect("couldn't look up certificates")
+            .is_empty());
+
+        let mut hashes = Vec::new();
+        for _ in 0..2 {
+            let pk = Picky::generate_private_key(2048).expect("couldn't generate private key");
+            let csr = Csr::generate(
+                DirectoryName::new_common_name("bushido.local"),
+                &pk,
+                SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_256),
+            )
+            .expect("couldn't generate csr");
+            let signed_cert = block_on(sign_certificate(
+                &ca_name,
+                csr.into_verified().unwrap(),
+                &config,
+                storage.as_ref(),
+                7_776_000,
+            ))
+            .expect("couldn't sign certificate");
+            hashes.push((encode_to_canonical_address(&signed_cert.to_der().unwrap()), signed_cert));
+        }
+
+        block_on(revoke_certificate(storage.as_ref(), &hashes[0].0, &hashes[0].1, None))
+            .expect("couldn't revoke certificate");
+
+        let entries =
+            block_on(find_certs_by_name(storage.as_ref(), "bushido.local")).expect("couldn't look up certificates");
+        assert_eq!(entries.len(), 2);
+        for (entry, (hash, cert)) in entries.iter().zip(hashes.iter()) {
+            assert_eq!(&entry.hash, hash);
+            assert_eq!(entry.serial, hex::encode(cert.serial_number().as_signed_bytes_be()));
+        }
+        assert!(entries[0].revoked);
+        assert!(!entries[1].revoked);
+
+        // name lookup used by CA chain resolution still returns the latest certificate
+        assert_eq!(
+            block_on(storage.get_addressing_hash_by_name("bushido.local")).unwrap(),
+            hashes[1].0
+        );
+    }
+
+    #[test]
+    fn list_certificates_in_pages() {
+        let mut config = config();
+        config.save_certificate = true;
+        let storage = block_on(get_storage(&config));
+
+        let ca_name = format!("{} Authority", config.realm);
+
+        block_on(generate_root_ca(&config, storage.as_ref())).expect("couldn't generate root ca");
+        block_on(generate_intermediate_ca(&config, storage.as_ref())).expect("couldn't generate intermediate ca");
+
+        let issued_after = Utc::now() - chrono::Duration::minutes(1);
+        let pk = Picky::generate_private_key(2048).expect("couldn't generate private key");
+        let mut issued = Vec::new();
+        for i in 0..5 {
+            let csr = Csr::generate(
+                DirectoryName::new_common_name(format!("node{}.bushido.local", i)),
+                &pk,
+                SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_256),
+            )
+            .expect("couldn't generate csr");
+            let signed_cert = block_on(sign_certificate(
+                &ca_name,
+                csr.into_verified().unwrap(),
+                &config,
+                storage.as_ref(),
+                7_776_000,
+            ))
+            .expect("couldn't sign certificate");
+            issued.push(encode_to_canonical_address(&signed_cert.to_der().unwrap()));
+        }
+
+        // only leaf certificates expire within a year, CAs are valid longer
+        let filter = ListFilter {
+            issued_after: Some(issued_after),
+            expiring_before: Some(Utc::now() + chrono::Duration::days(365)),
+            expired: None,
+        };
+
+        let mut offset = 0;
+        let mut listed = Vec::new();
+        let mut pages = 0;
+        loop {
+            let page =
+                block_on(list_certificates(storage.as_ref(), &filter, offset, 2)).expect("couldn't list certificates");
+            assert!(page.certificates.len() <= 2);
+            assert_eq!(page.total, 7);
+            listed.extend(page.certificates);
+            pages += 1;
+            match page.next_offset {
+                Some(next_offset) => offset = next_offset,
+                None => break,
+            }
+        }
+        assert_eq!(pages, 3);
+        assert_eq!(listed.len(), 5);
+
+        let mut listed_hashes = listed.iter().map(|entry| entry.hash.clone()).collect::<Vec<_>>();
+        let mut sorted = listed_hashes.clone();
+        sorted.sort();
+        assert_eq!(listed_hashes, sorted);
+        listed_hashes.sort();
+        issued.sort();
+        assert_eq!(listed_hashes, issued);
+        assert!(listed
+            .iter()
+            .all(|entry| entry.subject.ends_with(".bushido.local") && !entry.revoked));
+
+        // without filter, root and intermediate CAs are listed as well
+        let page = block_on(list_certificates(storage.as_ref(), &ListFilter::default(), 0, 10))
+            .expect("couldn't list certificates");
+        assert_eq!(page.certificates.len(), 7);
+        assert_eq!(page.total, 7);
+        assert_eq!(page.next_offset, None);
+    }
+
+    #[test]
+    fn ocsp_statuses() {
+        let mut config = config();
+        let storage = block_on(get_storage(&config));
+
+        let ca_name = format!("{} Authority", config.realm);
+
+        block_on(generate_root_ca(&config, storage.as_ref())).expect("couldn't generate root ca");
+        block_on(generate_intermediate_ca(&config, storage.as_ref())).expect("couldn't generate intermediate ca");
+        let (ca_cert, ca_pk) = block_on(load_ca(&ca_name, storage.as_ref())).unwrap();
+
+        let pk = Picky::generate_private_key(2048).expect("couldn't generate private key");
+        let csr = Csr::generate(
+            DirectoryName::new_common_name("Ocsp Bushido"),
+            &pk,
+            SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_256),
+        )
+        .expect("couldn't generate csr");
+        let signed_cert = block_on(sign_certificate(
+            &ca_name,
+            csr.into_verified().unwrap(),
+            &config,
+            storage.as_ref(),
+            7_776_000,
+        ))
+        .expect("couldn't sign certificate");
+
+        let signed_cert_id = cert_id(&ca_cert, signed_cert.serial_number().clone(), HashAlgorithm::SHA1).unwrap();
+        let root_name = format!("{} Root CA", config.realm);
+        let (root_cert, _) = block_on(load_ca(&root_name, storage.as_ref())).unwrap();
+        let foreign_cert_id = cert_id(&root_cert, signed_cert.serial_number().clone(), HashAlgorithm::SHA1).unwrap();
+        let request = OcspRequest::new(vec![signed_cert_id.clone(), foreign_cert_id.clone()]);
+
+        let response = block_on(generate_ocsp_response(&ca_name, &request, &config, storage.as_ref()))
+            .expect("couldn't generate OCSP response");
+        response.verify(&ca_cert).expect("couldn't verify OCSP response");
+        assert_eq!(response.cert_status(&signed_cert_id), Some(CertStatus::Good));
+        assert_eq!(response.cert_status(&foreign_cert_id), Some(CertStatus::Unknown));
+
+        let addressing_hash = encode_to_canonical_address(&signed_cert.to_der().unwrap());
+        let revocation = block_on(revoke_certificate(
+            storage.as_ref(),
+            &addressing_hash,
+            &signed_cert,
+            Some(CrlReason::CessationOfOperation),
+        ))
+        .expect("couldn't revoke certificate");
+
+        let response = block_on(generate_ocsp_response(&ca_name, &request, &config, storage.as_ref()))
+            .expect("couldn't generate OCSP response");
+        response.verify(&ca_cert).expect("couldn't verify OCSP response");
+        assert_eq!(
+            response.cert_status(&signed_cert_id),
+            Some(CertStatus::Revoked {
+                revocation_time: UTCDate::from(Utc.timestamp(revocation.revocation_date, 0)),
+                reason: Some(CrlReason::CessationOfOperation),
+            })
+        );
+
+        // delegated responder
+        let responder_key = Picky::generate_private_key(2048).unwrap();
+        let responder_cert = CertificateBuilder::new()
+            .valididy(ca_cert.valid_not_before(), ca_cert.valid_not_after())
+            .subject(
+                DirectoryName::new_common_name("Picky OCSP Responder"),
+                responder_key.to_public_key(),
+            )
+            .issuer_cert(&ca_cert, &ca_pk)
+            .extended_key_usage(ExtendedKeyUsage::new(vec![oids::kp_ocsp_signing()]))
+            .build()
+            .unwrap();
+        config.ocsp_responder = Some(CertKeyPair {
+            cert: PathOr::Some(responder_cert.clone()),
+            key: PathOr::Some(responder_key),
+        });
+
+        let response = block_on(generate_ocsp_response(&ca_name, &request, &config, storage.as_ref()))
+            .expect("couldn't generate OCSP response");
+        response
+            .verify(&ca_cert)
+            .expect("couldn't verify delegated OCSP response");
+        assert!(matches!(
+            response.cert_status(&signed_cert_id),
+            Some(CertStatus::Revoked { .. })
+        ));
+    }
+
+    #[test]
+    fn ocsp_get_request_decoding() {
+        let der = vec![0x30, 0x3f, 0xfb, 0xef, 0x00];
+        assert_eq!(decode_ocsp_get_request("MD/77wA=").unwrap(), der);
+        assert_eq!(decode_ocsp_get_request("MD%2F77wA%3D").unwrap(), der);
+        assert_eq!(decode_ocsp_get_request("MD_77wA").unwrap(), der);
+        assert!(decode_ocsp_get_request("not base64!").is_err());
+    }
+
+    #[test]
+    fn signed_certificate_json_response() {
+        let config = config();
+        let storage = block_on(get_storage(&config));
+
+        let ca_name = format!("{} Authority", config.realm);
+
+        block_on(generate_root_ca(&config, storage.as_ref())).expect("couldn't generate root ca");
+        block_on(generate_intermediate_ca(&config, storage.as_ref())).expect("couldn't generate intermediate ca");
+
+        let pk = Picky::generate_private_key(2048).expect("couldn't generate private key");
+        let csr = Csr::generate(
+            DirectoryName::new_common_name("Json Bushido"),
+            &pk,
+            SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_256),
+        )
+        .expect("couldn't generate csr");
+        let signed_cert = block_on(sign_certificate(
+            &ca_name,
+            csr.into_verified().unwrap(),
+            &config,
+            storage.as_ref(),
+            7_776_000,
+        ))
+        .expect("couldn't sign certificate");
+
+        let chain = block_on(find_ca_chain(storage.as_ref(), &ca_name)).expect("couldn't fetch CA chain");
+        let response = SignedCertificateResponse::new(&signed_cert, chain).expect("couldn't build response");
+        let json = serde_json::to_string(&response).unwrap();
+
+        let response: SignedCertificateResponse = serde_json::from_str(&json).expect("couldn't parse response");
+        let leaf = Cert::from_pem(&response.certificate.parse::<Pem>().unwrap()).unwrap();
+        assert_eq!(leaf, signed_cert);
+        assert_eq!(
+            response.fingerprint_sha256,
+            hex::encode(HashAlgorithm::SHA2_256.digest(&signed_cert.to_der().unwrap()))
+        );
+        assert_eq!(
+            DateTime::parse_from_rfc3339(&response.not_after).unwrap(),
+            DateTime::<Utc>::from(signed_cert.valid_not_after())
+        );
+        assert_eq!(
+            response.serial,
+            hex::encode(signed_cert.serial_number().as_signed_bytes_be())
+        );
+
+        let chain = response
+            .chain
+            .iter()
+            .map(|cert_pem| Cert::from_pem(&cert_pem.parse::<Pem>().unwrap()).unwrap())
+            .collect::<Vec<Cert>>();
+        assert_eq!(chain.len(), 2);
+        leaf.verifier()
+            .chain(chain.iter())
+            .exact_date(&UTCDate::now())
+            .verify()
+            .expect("couldn't validate ca chain");
+    }
+
+    #[test]
+    fn chain_formats() {
+        let config = config();
+        let storage = block_on(get_storage(&config));
+
+        let root_name = format!("{} Root CA", config.realm);
+        let ca_name = format!("{} Authority", config.realm);
+
+        block_on(generate_root_ca(&config, storage.as_ref())).expect("couldn't generate root ca");
+        block_on(generate_intermediate_ca(&config, storage.as_ref())).expect("couldn't generate intermediate ca");
+
+        let chain_der = block_on(find_ca_chain_der(storage.as_ref(), &ca_name, config.max_chain_depth))
+            .expect("couldn't fetch CA chain");
+        assert_eq!(chain_der.len(), 2);
+
+        let pem = encode_chain(&chain_der, Format::PemFile, RootMode::Include).expect("couldn't encode PEM chain");
+        assert_eq!(
+            pem,
+            block_on(find_ca_chain(storage.as_ref(), &ca_name)).unwrap().join("\n")
+        );
+
+        let json = encode_chain(&chain_der, Format::Json, RootMode::Include).expect("couldn't encode JSON chain");
+        let response = serde_json::from_str::<ChainResponse>(&json).expect("couldn't decode JSON chain");
+        let subjects = response
+            .chain
+            .iter()
+            .map(|entry| entry.subject.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(subjects, vec![format!("CN={}", ca_name), format!("CN={}", root_name)]);
+        for (entry, der) in response.chain.iter().zip(chain_der.iter()) {
+            let cert = Cert::from_der(der).unwrap();
+            assert_eq!(entry.pem, to_pem("CERTIFICATE", der));
+            assert_eq!(entry.ski, hex::encode(cert.subject_key_identifier().unwrap()));
+        }
+
+        let json = encode_chain(&chain_der, Format::Json, RootMode::Exclude).expect("couldn't encode JSON chain");
+        let response = serde_json::from_str::<ChainResponse>(&json).expect("couldn't decode JSON chain");
+        assert_eq!(response.chain.len(), 1);
+        assert_eq!(response.chain[0].subject, format!("CN={}", ca_name));
+
+        let pem = encode_chain(&chain_der, Format::PemFile, RootMode::Exclude).expect("couldn't encode PEM chain");
+        assert_eq!(pem, to_pem("CERTIFICATE", &chain_der[0]));
+    }
+
+    #[test]
+    fn chain_root_modes() {
+        let config = config();
+        let storage = block_on(get_storage(&config));
+
+        let root_name = format!("{} Root CA", config.realm);
+        let ca_name = format!("{} Authority", config.realm);
+
+        block_on(generate_root_ca(&config, storage.as_ref())).expect("couldn't generate root ca");
+        block_on(generate_intermediate_ca(&config, storage.as_ref())).expect("couldn't generate intermediate ca");
+
+        let chain_der = block_on(find_ca_chain_der(storage.as_ref(), &ca_name, config.max_chain_depth))
+            .expect("couldn't fetch CA chain");
+        let (intermediate, root) = (chain_der[0].clone(), chain_der[1].clone());
+
+        let subjects = |chain_der: &[Vec<u8>], mode: RootMode| {
+            let json = encode_chain(chain_der, Format::Json, mode).expect("couldn't encode JSON chain");
+            serde_json::from_str::<ChainResponse>(&json)
+                .expect("couldn't decode JSON chain")
+                .chain
+                .into_iter()
+                .map(|entry| entry.subject)
+                .collect::<Vec<_>>()
+        };
+
+        // ordering and deduplication don't depend on the order certificates were found in
+        let scrambled = vec![root.clone(), intermediate.clone(), root.clone(), intermediate.clone()];
+        for chain_der in &[chain_der.clone(), scrambled] {
+            assert_eq!(
+                subjects(chain_der, RootMode::Include),
+                vec![format!("CN={}", ca_name), format!("CN={}", root_name)]
+            );
+            assert_eq!(subjects(chain_der, RootMode::Exclude), vec![format!("CN={}", ca_name)]);
+            assert_eq!(subjects(chain_der, RootMode::Only), vec![format!("CN={}", root_name)]);
+        }
+
+        let pem = encode_chain(&[root.clone(), intermediate], Format::PemFile, RootMode::Only).expect("PEM chain");
+        assert_eq!(pem, to_pem("CERTIFICATE", &root));
+
+        assert_eq!("exclude".parse::<RootMode>(), Ok(RootMode::Exclude));
+        assert!("none".parse::<RootMode>().is_err());
+
+        // a foreign root doesn't belong to the chain
+        let foreign_key = Picky::generate_private_key(4096).expect("couldn't generate private key");
+        let foreign_root = CertificateBuilder::new()
+            .valididy(UTCDate::ymd(2020, 1, 1).unwrap(), UTCDate::ymd(2030, 1, 1).unwrap())
+            .self_signed(DirectoryName::new_common_name("Foreign Root CA"), &foreign_key)
+            .ca(true)
+            .build()
+            .expect("couldn't build foreign root");
+        let err = encode_chain(
+            &[
+                chain_der[0].clone(),
+                chain_der[1].clone(),
+                foreign_root.to_der().unwrap(),
+            ],
+            Format::PemFile,
+            RootMode::Include,
+        )
+        .unwrap_err();
+        assert_eq!(err, "CA chain certificates don't form a single chain");
+    }
+
+    #[test]
+    fn chain_walk_is_bounded() {
+        let config = config();
+        let storage = block_on(get_storage(&config));
+
+        let key_id = |key: &PrivateKey| {
+            KeyIdGenMethod::SPKFullDER(HashAlgorithm::SHA2_256)
+                .generate_from(&key.to_public_key())
+                .unwrap()
+        };
+        let store_ca = |subject: &str, key: &PrivateKey, issuer: &str, issuer_key: &PrivateKey| {
+            let cert = CertificateBuilder::new()
+                .valididy(UTCDate::now(), UTCDate::from(Utc::now() + chrono::Duration::days(1)))
+                .subject(DirectoryName::new_common_name(subject), key.to_public_key())
+                .issuer(DirectoryName::new_common_name(issuer), issuer_key)
+                .authority_key_identifier(key_id(issuer_key))
+                .ca(true)
+                .build()
+                .expect("couldn't build certificate");
+            block_on(storage.store(
+                CertificateEntry {
+                    name: subject.to_owned(),
+                    cert: cert.to_der().unwrap(),
+                    key_identifier: hex::encode(key_id(key)),
+                    key: None,
+                },
+                ConflictPolicy::Overwrite,
+            ))
+            .expect("couldn't store certificate");
+        };
+
+        // distinct keys, pool keys could be drawn twice and CA keys are drawn from the 2048 and 4096 bits pools
+        let test_key = |pem: &str| PrivateKey::from_pem(&pem.parse::<Pem>().unwrap()).unwrap();
+
+        // A is issued by B, and B by A
+        let key_a = test_key(crate::test_files::RSA_3072_PK_1);
+        let key_b = test_key(crate::test_files::RSA_3072_PK_2);
+        store_ca("Cycle A", &key_a, "Cycle B", &key_b);
+        store_ca("Cycle B", &key_b, "Cycle A", &key_a);
+
+        let err = block_on(find_ca_chain_der(storage.as_ref(), "Cycle A", config.max_chain_depth)).unwrap_err();
+        assert_eq!(
+            err,
+            format!(
+                "CA chain cycle: CN=Cycle B is issued by already visited key {}",
+                hex::encode(key_id(&key_a))
+            )
+        );
+
+        // issuer never stored
+        let missing_key = test_key(crate::test_files::RSA_2048_PK_1);
+        store_ca("Orphan", &key_a, "Missing", &missing_key);
+        let err = block_on(find_ca_chain_der(storage.as_ref(), "Orphan", config.max_chain_depth)).unwrap_err();
+        assert!(
+            err.starts_with(&format!(
+                "CA chain incomplete: issuer {} of CN=Orphan not found",
+                hex::encode(key_id(&missing_key))
+            )),
+            "{}",
+            err
+        );
+
+        block_on(generate_root_ca(&config, storage.as_ref())).expect("couldn't generate root ca");
+        block_on(generate_intermediate_ca(&config, storage.as_ref())).expect("couldn't generate intermediate ca");
+        let ca_name = format!("{} Authority", config.realm);
+        let chain = block_on(find_ca_chain_der(storage.as_ref(), &ca_name, 2)).expect("couldn't fetch CA chain");
+        assert_eq!(chain.len(), 2);
+        let err = block_on(find_ca_chain_der(storage.as_ref(), &ca_name, 1)).unwrap_err();
+        assert_eq!(err, "CA chain exceeds the maximum depth of 1 certificates");
+
+        let root_name = format!("{} Root CA", config.realm);
+        let chain = block_on(find_ca_chain_der(storage.as_ref(), &root_name, 1)).expect("couldn't fetch root");
+        assert_eq!(chain.len(), 1);
+    }
+
+    #[test]
+    fn certificate_addresses() {
+        let config = config();
+        let storage = block_on(get_storage(&config));
+
+        block_on(generate_root_ca(&config, storage.as_ref())).expect("couldn't generate root ca");
+        let root_name = format!("{} Root CA", config.realm);
+        let canonical = block_on(storage.get_addressing_hash_by_name(&root_name)).expect("root address");
+        let der = block_on(storage.get_cert_by_addressing_hash(&canonical)).expect("root der");
+
+        let sha1_hex = hex::encode(HashAlgorithm::SHA1.digest(&der));
+        let sha256_hex = hex::encode(HashAlgorithm::SHA2_256.digest(&der));
+        let sha512_hex = hex::encode(HashAlgorithm::SHA2_512.digest(&der));
+        for address in &[canonical.as_str(), &sha1_hex, &sha256_hex, &sha512_hex] {
+            let found = block_on(find_canonical_address(storage.as_ref(), address)).expect("couldn't find address");
+            assert_eq!(found, canonical);
+        }
+
+        let err = block_on(find_canonical_address(storage.as_ref(), &sha256_hex[..50])).unwrap_err();
+        assert_eq!(err.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            err.message,
+            "hex digest of 50 characters doesn't match any of SHA-1, SHA-256 or SHA-512"
+        );
+
+        // unknown SHA-256 digests are answered as their canonical address, only other digests are looked up
+        let unknown = hex::encode(HashAlgorithm::SHA1.digest(b"unknown"));
+        let err = block_on(find_canonical_address(storage.as_ref(), &unknown)).unwrap_err();
+        assert_eq!(err.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn sign_with_multiple_intermediates() {
+        let mut config = config();
+        config.intermediates = vec!["Picky Servers Authority".to_owned()];
+        let storage = block_on(get_storage(&config));
+        block_on(init_storage_from_config(storage.as_ref(), &config)).expect("couldn't init storage");
+
+        let cas = block_on(list_cas(&config, storage.as_ref())).expect("couldn't list CAs");
+        let names = cas.iter().map(|ca| ca.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["Picky Authority", "Picky Servers Authority"]);
+        assert!(cas[0].default);
+        assert!(!cas[1].default);
+        assert_ne!(cas[0].ski, cas[1].ski);
+
+        assert_eq!(select_ca(&config, None).unwrap(), "Picky Authority");
+        assert_eq!(
+            select_ca(&config, Some("Picky Servers Authority")).unwrap(),
+            "Picky Servers Authority"
+        );
+        let err = select_ca(&config, Some("Picky Server Authority")).unwrap_err();
+        assert_eq!(err.status(), StatusCode::NOT_FOUND);
+
+        let mut chains = Vec::new();
+        for ca_name in &["Picky Authority", "Picky Servers Authority"] {
+            let pk = Picky::generate_private_key(2048).expect("couldn't generate private key");
+            let csr = Csr::generate(
+                DirectoryName::new_common_name("bushido.local"),
+                &pk,
+                SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_256),
+            )
+            .expect("couldn't generate csr");
+            let signed_cert = block_on(sign_certificate(
+                ca_name,
+                csr.into_verified().unwrap(),
+                &config,
+                storage.as_ref(),
+                DEFAULT_CERT_DURATION_SECS,
+            ))
+            .expect("couldn't sign certificate");
+            assert_eq!(signed_cert.issuer_name().to_string(), format!("CN={}", ca_name));
+
+            let chain = block_on(find_ca_chain_der(storage.as_ref(), ca_name, config.max_chain_depth))
+                .expect("couldn't fetch CA chain")
+                .iter()
+                .map(|der| Cert::from_der(der).expect("couldn't parse CA cert"))
+                .collect::<Vec<Cert>>();
+            signed_cert
+                .verifier()
+                .chain(chain.iter())
+                .exact_date(&UTCDate::now())
+                .verify()
+                .expect("couldn't validate ca chain");
+            chains.push(chain);
+        }
+
+        // both chains end with the same root
+        assert_ne!(chains[0][0], chains[1][0]);
+        assert_eq!(chains[0][1], chains[1][1]);
+    }
+
+    #[test]
+    fn ca_loaded_from_files() {
+        let dir = std::env::temp_dir().join(format!("picky_ca_files_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut config = config();
+        let root_name = format!("{} Root CA", config.realm);
+
+        let root_key_pem = crate::test_files::RSA_2048_PK_4;
+        let root_key = PrivateKey::from_pem(&root_key_pem.parse::<Pem>().unwrap()).unwrap();
+        let root = Picky::generate_root(
+            &root_name,
+            &root_key,
+            config.signing_algorithm,
+            config.key_id_method,
+            // outlives the generated intermediate
+            config.root_validity,
+        )
+        .expect("couldn't generate root");
+        let cert_path = dir.join("root.crt");
+        let key_path = dir.join("root.key");
+        std::fs::write(&cert_path, root.to_pem().unwrap().to_string()).unwrap();
+        std::fs::write(&key_path, root_key_pem).unwrap();
+
+        config.root = Some(CertKeyPair {
+            cert: PathOr::Path(cert_path.clone()),
+            key: PathOr::Path(dir.join("missing.key")),
+        });
+        let storage = block_on(get_storage(&config));
+        let err = block_on(init_storage_from_config(storage.as_ref(), &config)).unwrap_err();
+        assert!(err.contains(&dir.join("missing.key").display().to_string()), "{}", err);
+
+        config.root = Some(CertKeyPair {
+            cert: PathOr::Path(cert_path),
+            key: PathOr::Path(key_path),
+        });
+        let storage = block_on(get_storage(&config));
+        block_on(init_storage_from_config(storage.as_ref(), &config)).expect("couldn't init storage");
+        let (stored, stored_key) = block_on(load_ca(&root_name, storage.as_ref())).expect("couldn't load root");
+        assert_eq!(stored.to_der().unwrap(), root.to_der().unwrap());
+        assert_eq!(stored_key.to_pkcs8().unwrap(), root_key.to_pkcs8().unwrap());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn import_ca_then_sign() {
+        let config = config();
+        // nothing generated so that imported keys can't collide with pre-generated test keys
+        let storage = block_on(get_storage(&config));
+
+        let signature_algorithm = SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_256);
+        let root_key = Picky::generate_private_key(3072).expect("couldn't generate root key");
+        let root = Picky::generate_root(
+            "Imported Root CA",
+            &root_key,
+            signature_algorithm,
+            KeyIdGenMethod::default(),
+            chrono::Duration::days(365),
+        )
+        .expect("couldn't generate root");
+        let intermediate_key = Picky::generate_private_key(2048).expect("couldn't generate intermediate key");
+        let intermediate = Picky::generate_intermediate(
+            "Imported Authority",
+            intermediate_key.to_public_key(),
+            &root,
+            &root_key,
+            signature_algorithm,
+            KeyIdGenMethod::default(),
+            chrono::Duration::days(180),
+        )
+        .expect("couldn't generate intermediate");
+
+        let request = |cert: &Cert, key: &PrivateKey, role: CaRole| CaImportRequest {
+            certificate: cert.to_pem().expect("cert pem").to_string(),
+            key: key.to_pem().expect("key pem"),
+            passphrase: None,
+            role,
+        };
+        let import = |request: CaImportRequest| block_on(import_ca(request, storage.as_ref())).map_err(ApiError::from);
+
+        // root isn't known yet
+        let err = import(request(&intermediate, &intermediate_key, CaRole::Intermediate)).unwrap_err();
+        assert_eq!(err.kind, ApiErrorKind::InvalidCertificate);
+
+        let err = import(request(&root, &intermediate_key, CaRole::Root)).unwrap_err();
+        assert_eq!(err.message, "private key doesn't match the certificate");
+
+        let err = import(request(&root, &root_key, CaRole::Intermediate)).unwrap_err();
+        assert_eq!(err.kind, ApiErrorKind::InvalidCertificate);
+
+        let mut unencrypted = request(&root, &root_key, CaRole::Root);
+        unencrypted.passphrase = Some("hunter2".to_owned());
+        let err = import(unencrypted).unwrap_err();
+        assert_eq!(err.message, "passphrase given for an unencrypted private key");
+
+        let encrypted_key = root_key.to_encrypted_pem("hunter2").expect("encrypted key pem");
+        let encrypted = |passphrase: Option<&str>| CaImportRequest {
+            key: encrypted_key.clone(),
+            passphrase: passphrase.map(str::to_owned),
+            ..request(&root, &root_key, CaRole::Root)
+        };
+        let err = import(encrypted(None)).unwrap_err();
+        assert_eq!(
+            err.message,
+            "a passphrase is required to import an encrypted private key"
+        );
+        let err = import(encrypted(Some("hunter3"))).unwrap_err();
+        assert_eq!(err.status(), StatusCode::BAD_REQUEST);
+
+        let imported = import(encrypted(Some("hunter2"))).expect("couldn't import root with encrypted key");
+        assert_eq!(imported.name, "Imported Root CA");
+        assert_eq!(imported.ski, hex::encode(root.subject_key_identifier().unwrap()));
+
+        let err = import(request(&root, &root_key, CaRole::Root)).unwrap_err();
+        assert_eq!(err.status(), StatusCode::CONFLICT);
+
+        let imported = import(request(&intermediate, &intermediate_key, CaRole::Intermediate))
+            .expect("couldn't import intermediate");
+        assert_eq!(imported.name, "Imported Authority");
+
+        let pk = Picky::generate_private_key(2048).expect("couldn't generate private key");
+        let csr = Csr::generate(
+            DirectoryName::new_common_name("imported.local"),
+            &pk,
+            signature_algorithm,
+        )
+        .expect("couldn't generate csr");
+        let signed_cert = block_on(sign_certificate(
+            "Imported Authority",
+            csr.into_verified().unwrap(),
+            &config,
+            storage.as_ref(),
+            DEFAULT_CERT_DURATION_SECS,
+        ))
+        .expect("couldn't sign certificate");
+
+        let chain = block_on(find_ca_chain_der(
+            storage.as_ref(),
+            "Imported Authority",
+            config.max_chain_depth,
+        ))
+        .expect("couldn't fetch CA chain")
+        .iter()
+        .map(|der| Cert::from_der(der).expect("couldn't parse CA cert"))
+        .collect::<Vec<Cert>>();
+        assert_eq!(chain, vec![intermediate, root]);
+        signed_cert
+            .verifier()
+            .chain(chain.iter())
+            .exact_date(&UTCDate::now())
+            .verify()
+            .expect("couldn't validate ca chain");
+    }
+
+    #[test]
+    fn import_ec_ca_then_sign() {
+        let mut config = config();
+        config.signing_algorithm = SignatureAlgorithm::EcdsaSha256;
+        let storage = block_on(get_storage(&config));
+
+        let test_key = |pem: &str| PrivateKey::from_pem(&pem.parse::<Pem>().unwrap()).unwrap();
+        let root_key = test_key(crate::test_files::EC_P384_PK_1);
+        let root = Picky::generate_root(
+            "Imported EC Root CA",
+            &root_key,
+            SignatureAlgorithm::EcdsaSha384,
+            KeyIdGenMethod::default(),
+            chrono::Duration::days(365),
+        )
+        .expect("couldn't generate root");
+        let intermediate_key = test_key(crate::test_files::EC_P256_PK_1);
+        let intermediate = Picky::generate_intermediate(
+            "Imported EC Authority",
+            intermediate_key.to_public_key(),
+            &root,
+            &root_key,
+            SignatureAlgorithm::EcdsaSha384,
+            KeyIdGenMethod::default(),
+            chrono::Duration::days(180),
+        )
+        .expect("couldn't generate intermediate");
+
+        let request = |cert: &Cert, key: &PrivateKey, role: CaRole| CaImportRequest {
+            certificate: cert.to_pem().expect("cert pem").to_string(),
+            key: key.to_pem().expect("key pem"),
+            passphrase: None,
+            role,
+        };
+        let import = |request: CaImportRequest| block_on(import_ca(request, storage.as_ref())).map_err(ApiError::from);
+
+        let err = import(request(&root, &intermediate_key, CaRole::Root)).unwrap_err();
+        assert_eq!(err.message, "private key doesn't match the certificate");
+        let rsa_key = test_key(crate::test_files::RSA_2048_PK_1);
+        let err = import(request(&root, &rsa_key, CaRole::Root)).unwrap_err();
+        assert_eq!(err.message, "private key doesn't match the certificate");
+
+        import(request(&root, &root_key, CaRole::Root)).expect("couldn't import P-384 root");
+        import(request(&intermediate, &intermediate_key, CaRole::Intermediate))
+            .expect("couldn't import P-256 intermediate");
+
+        let pk = test_key(crate::test_files::RSA_3072_PK_1);
+        let csr = Csr::generate(
+            DirectoryName::new_common_name("imported-ec.local"),
+            &pk,
+            SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_256),
+        )
+        .expect("couldn't generate csr");
+        let signed_cert = block_on(sign_certificate(
+            "Imported EC Authority",
+            csr.into_verified().unwrap(),
+            &config,
+            storage.as_ref(),
+            DEFAULT_CERT_DURATION_SECS,
+        ))
+        .expect("couldn't sign certificate");
+        signed_cert
+            .verifier()
+            .chain([intermediate, root].iter())
+            .exact_date(&UTCDate::now())
+            .verify()
+            .expect("couldn't validate ca chain");
+    }
+
+    #[test]
+    fn post_ca_requires_admin() {
+        let post_ca = |config: Config, headers: &[(&str, &str)]| {
+            let controller = controller(config);
+            response_status(block_on(controller.post_ca_impl(request(
+                "POST",
+                "/ca",
+                headers,
+                Vec::new(),
+            ))))
+        };
+
+        // without API key, a provisioner token is required
+        assert_eq!(post_ca(config(), &[]), StatusCode::UNAUTHORIZED);
+        let mut jwt_config = config();
+        jwt_config.auth_mode = AuthMode::Jwt;
+        assert_eq!(post_ca(jwt_config, &[]), StatusCode::UNAUTHORIZED);
+
+        let mut config = config();
+        config.api_keys = vec![
+            named_api_key("monitoring", &[ApiKeyScope::Read]),
+            named_api_key("operator", &[ApiKeyScope::Admin]),
+        ];
+        assert_eq!(post_ca(config.clone(), &[]), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            post_ca(config.clone(), &[("Picky-Api-Key", "monitoring-secret")]),
+            StatusCode::FORBIDDEN
+        );
+        // authorized, then refused for the missing body
+        assert_eq!(
+            post_ca(config, &[("Picky-Api-Key", "operator-secret")]),
+            StatusCode::BAD_REQUEST
+        );
+    }
+
+    #[test]
+    fn plain_provisioner_tokens_are_not_admin() {
+        let provisioner_key = PrivateKey::from_pem(
+            &include_str!("../../../test_assets/private_keys/rsa-2048-pk_4.key")
+                .parse::<Pem>()
+                .expect("pem"),
+        )
+        .expect("provisioner key");
+        let mut config = config();
+        config.provisioner_public_key = Some(PathOr::Some(provisioner_key.to_public_key()));
+        let controller = controller(config);
+
+        let bearer = |admin: Option<bool>| {
+            let mut claims = serde_json::json!({
+                "x509_duration_secs": 3600,
+                "sub": "provisioner",
+                "nbf": unix_epoch(),
+                "exp": unix_epoch() + 60,
+            });
+            if let Some(admin) = admin {
+                claims["admin"] = admin.into();
+            }
+            let token = JwtSig::new(JwsAlg::RS256, claims)
+                .encode(&provisioner_key)
+                .expect("jwt encode");
+            format!("Bearer {}", token)
+        };
+        let statuses = |authorization: &str| {
+            let headers = [("Authorization", authorization)];
+            let get = |uri: &str| request("GET", uri, &headers, Vec::new());
+            let post = |uri: &str| request("POST", uri, &headers, Vec::new());
+            vec![
+                response_status(block_on(controller.post_ca_impl(post("/ca")))),
+                response_status(block_on(
+                    controller.rotate_intermediate_impl(&post("/ca/rotate-intermediate")),
+                )),
+                response_status(block_on(controller.get_ca_export_impl(&get("/ca/export")))),
+                response_status(block_on(controller.get_audit_impl(&get("/audit")))),
+                match block_on(controller.reload_yaml_conf_impl(&get("/reload"))) {
+                    Ok(_) => StatusCode::OK,
+                    Err(e) => e.status(),
+                },
+            ]
+        };
+
+        assert_eq!(statuses(&bearer(None)), vec![StatusCode::FORBIDDEN; 5]);
+        assert_eq!(statuses(&bearer(Some(false))), vec![StatusCode::FORBIDDEN; 5]);
+
+        // authorized, the empty CA import then being refused
+        let admin = statuses(&bearer(Some(true)));
+        assert_eq!(admin[0], StatusCode::BAD_REQUEST);
+        assert_eq!(admin[1], StatusCode::CREATED);
+        assert_eq!(admin[2], StatusCode::OK);
+        assert_eq!(admin[3], StatusCode::OK);
+    }
+
+    #[test]
+    fn made_up_api_keys_share_a_rate_limit() {
+        let mut config = config();
+        config.rate_limit_per_minute = 1;
+        config.rate_limit_burst = 1;
+        config.api_keys = vec![named_api_key("operator", &[ApiKeyScope::Issue])];
+        let controller = controller(config);
+        let check_rate_limit = |api_key: &str| {
+            let req = request("POST", "/cert", &[("Picky-Api-Key", api_key)], Vec::new());
+            block_on(controller.check_rate_limit(&req, None)).map_err(|e| e.status())
+        };
+
+        assert_eq!(check_rate_limit("made-up-1"), Ok(()));
+        assert_eq!(check_rate_limit("made-up-2"), Err(StatusCode::TOO_MANY_REQUESTS));
+        assert_eq!(check_rate_limit("operator-secret"), Ok(()));
+        assert_eq!(check_rate_limit("operator-secret"), Err(StatusCode::TOO_MANY_REQUESTS));
+    }
+
+    #[test]
+    fn reload_requires_admin() {
+        let reload = |controller: &ServerController, headers: &[(&str, &str)]| {
+            let req = request("GET", "/reload", headers, Vec::new());
+            block_on(controller.reload_yaml_conf_impl(&req)).unwrap_err().status()
+        };
+
+        // without API key, a provisioner token is required
+        assert_eq!(reload(&controller(config()), &[]), StatusCode::UNAUTHORIZED);
+
+        let mut config = config();
+        config.api_keys = vec![named_api_key("monitoring", &[ApiKeyScope::Read])];
+        let controller = controller(config);
+        assert_eq!(reload(&controller, &[]), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            reload(&controller, &[("Picky-Api-Key", "monitoring-secret")]),
+            StatusCode::FORBIDDEN
+        );
+    }
+
+    #[test]
+    fn rotate_intermediate_requires_admin() {
+        let rotate = |controller: &ServerController, headers: &[(&str, &str)]| {
+            let req = request("POST", "/ca/rotate-intermediate", headers, Vec::new());
+            response_status(block_on(controller.rotate_intermediate_impl(&req)))
+        };
+
+        // without API key, a provisioner token is required
+        assert_eq!(rotate(&controller(config()), &[]), StatusCode::UNAUTHORIZED);
+
+        let mut config = config();
+        config.api_keys = vec![
+            named_api_key("monitoring", &[ApiKeyScope::Read]),
+            named_api_key("operator", &[ApiKeyScope::Admin]),
+        ];
+        let controller = controller(config);
+        assert_eq!(rotate(&controller, &[]), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            rotate(&controller, &[("Picky-Api-Key", "monitoring-secret")]),
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            rotate(&controller, &[("Picky-Api-Key", "operator-secret")]),
+            StatusCode::CREATED
+        );
+    }
+
+    #[test]
+    fn revoke_requires_authorization() {
+        // not of the CA key sizes, so that no key is shared with a CA
+        let test_key = |pem: &str| PrivateKey::from_pem(&pem.parse::<Pem>().unwrap()).unwrap();
+        let issue = |controller: &ServerController, name: &str, pk_pem: &str| {
+            let csr = Csr::generate(
+                DirectoryName::new_common_name(name),
+                &test_key(pk_pem),
+                SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_256),
+            )
+            .expect("couldn't generate csr");
+            let cert = block_on(sign_certificate(
+                "Picky Authority",
+                csr.into_verified().unwrap(),
+                &block_on(controller.read_conf()),
+                controller.storage.as_ref(),
+                DEFAULT_CERT_DURATION_SECS,
+            ))
+            .expect("couldn't sign certificate");
+            encode_to_canonical_address(&cert.to_der().unwrap())
+        };
+        let revoke = |controller: &ServerController, address: &str, headers: &[(&str, &str)]| {
+            let req = request("DELETE", &format!("/cert/{}", address), headers, Vec::new());
+            response_status(block_on(controller.revoke_cert_impl(address, req)))
+        };
+
+        let mut api_key_config = config();
+        api_key_config.save_certificate = true;
+        api_key_config.auth_mode = AuthMode::ApiKey;
+        api_key_config.api_keys = vec![
+            named_api_key("monitoring", &[ApiKeyScope::Read]),
+            named_api_key("revoker", &[ApiKeyScope::Revoke]),
+            named_api_key("operator", &[ApiKeyScope::Admin]),
+        ];
+        let api_key_controller = controller(api_key_config);
+        let first = issue(
+            &api_key_controller,
+            "first.revoked.local",
+            crate::test_files::RSA_3072_PK_1,
+        );
+        let second = issue(
+            &api_key_controller,
+            "second.revoked.local",
+            crate::test_files::RSA_3072_PK_2,
+        );
+
+        // no provisioner token is expected in api_key mode
+        assert_eq!(revoke(&api_key_controller, &first, &[]), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            revoke(&api_key_controller, &first, &[("Picky-Api-Key", "made-up-secret")]),
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            revoke(&api_key_controller, &first, &[("Picky-Api-Key", "monitoring-secret")]),
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            revoke(&api_key_controller, &first, &[("Picky-Api-Key", "revoker-secret")]),
+            StatusCode::OK
+        );
+        assert_eq!(
+            revoke(&api_key_controller, &second, &[("Picky-Api-Key", "operator-secret")]),
+            StatusCode::OK
+        );
+
+        // an API key isn't enough in jwt mode
+        let mut jwt_config = config();
+        jwt_config.save_certificate = true;
+        jwt_config.auth_mode = AuthMode::Jwt;
+        jwt_config.api_keys = vec![named_api_key("operator", &[ApiKeyScope::Admin])];
+        let jwt_controller = controller(jwt_config);
+        let address = issue(&jwt_controller, "revoked.local", crate::test_files::RSA_3072_PK_1);
+        assert_eq!(
+            revoke(&jwt_controller, &address, &[("Picky-Api-Key", "operator-secret")]),
+            StatusCode::UNAUTHORIZED
+        );
+    }
+
+    #[test]
+    fn post_cert_requires_authorization() {
+        // not of the CA key sizes, so that no key is shared with a CA
+        let test_key = |pem: &str| PrivateKey::from_pem(&pem.parse::<Pem>().unwrap()).unwrap();
+        let issue = |controller: &ServerController, name: &str, pk_pem: &str| {
+            let csr = Csr::generate(
+                DirectoryName::new_common_name(name),
+                &test_key(pk_pem),
+                SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_256),
+            )
+            .expect("couldn't generate csr");
+            let cert = block_on(sign_certificate(
+                "Picky Authority",
+                csr.into_verified().unwrap(),
+                &block_on(controller.read_conf()),
+                controller.storage.as_ref(),
+                DEFAULT_CERT_DURATION_SECS,
+            ))
+            .expect("couldn't sign certificate");
+            cert.to_pem().expect("cert pem").to_string().into_bytes()
+        };
+        let post_cert = |controller: &ServerController, headers: &[(&str, &str)], pem: Vec<u8>| {
+            let mut headers = headers.to_vec();
+            headers.push(("Content-Type", "application/x-pem-file"));
+            response_status(block_on(
+                controller.post_cert_impl(request("POST", "/cert", &headers, pem)),
+            ))
+        };
+
+        let mut api_key_config = config();
+        api_key_config.auth_mode = AuthMode::ApiKey;
+        api_key_config.api_keys = vec![
+            named_api_key("monitoring", &[ApiKeyScope::Read]),
+            named_api_key("issuer", &[ApiKeyScope::Issue]),
+        ];
+        let api_key_controller = controller(api_key_config);
+        let pem = issue(
+            &api_key_controller,
+            "registered.local",
+            crate::test_files::RSA_3072_PK_1,
+        );
+
+        assert_eq!(
+            post_cert(&api_key_controller, &[], pem.clone()),
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            post_cert(&api_key_controller, &[("Picky-Api-Key", "made-up-secret")], pem.clone()),
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            post_cert(
+                &api_key_controller,
+                &[("Picky-Api-Key", "monitoring-secret")],
+                pem.clone()
+            ),
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            post_cert(&api_key_controller, &[("Picky-Api-Key", "issuer-secret")], pem),
+            StatusCode::CREATED
+        );
+
+        // a provisioner token is required in jwt mode, API keys not being checked
+        let provisioner_key = test_key(crate::test_files::RSA_2048_PK_4);
+        let mut jwt_config = config();
+        jwt_config.auth_mode = AuthMode::Jwt;
+        jwt_config.provisioner_public_key = Some(PathOr::Some(provisioner_key.to_public_key()));
+        jwt_config.api_keys = vec![named_api_key("issuer", &[ApiKeyScope::Issue])];
+        let jwt_controller = controller(jwt_config);
+        let pem = issue(&jwt_controller, "registered.local", crate::test_files::RSA_3072_PK_2);
+        let token = JwtSig::new(
+            JwsAlg::RS256,
+            serde_json::json!({
+                "x509_duration_secs": 3600,
+                "sub": "provisioner",
+                "nbf": unix_epoch(),
+                "exp": unix_epoch() + 60,
+            }),
+        )
+        .encode(&provisioner_key)
+        .expect("jwt encode");
+        let bearer = format!("Bearer {}", token);
+
+        assert_eq!(post_cert(&jwt_controller, &[], pem.clone()), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            post_cert(&jwt_controller, &[("Picky-Api-Key", "issuer-secret")], pem.clone()),
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            post_cert(&jwt_controller, &[("Authorization", "Bearer not.a.token")], pem.clone()),
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            post_cert(&jwt_controller, &[("Authorization", &bearer)], pem),
+            StatusCode::CREATED
+        );
+    }
+
+    #[test]
+    fn rotate_intermediate_ca() {
+        let config = config();
+        let storage = block_on(get_storage(&config));
+        block_on(init_storage_from_config(storage.as_ref(), &config)).expect("couldn't init storage");
+        let ca_name = "Picky Authority";
+
+        let sign = || {
+            // not picked from the pre-generated pool the CA keys come from in tests
+            let pk = Picky::generate_private_key(3072).expect("couldn't generate private key");
+            let csr = Csr::generate(
+                DirectoryName::new_common_name("rotated.local"),
+                &pk,
+                SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_256),
+            )
+            .expect("couldn't generate csr");
+            block_on(sign_certificate(
+                ca_name,
+                csr.into_verified().unwrap(),
+                &config,
+                storage.as_ref(),
+                DEFAULT_CERT_DURATION_SECS,
+            ))
+            .expect("couldn't sign certificate")
+        };
+
+        let old_leaf = sign();
+        let (old_ca, _) = block_on(load_ca(ca_name, storage.as_ref())).unwrap();
+
+        let new_ca = block_on(issue_intermediate_ca(ca_name, &config, storage.as_ref())).expect("couldn't rotate CA");
+        assert_ne!(
+            old_ca.subject_key_identifier().unwrap(),
+            new_ca.subject_key_identifier().unwrap()
+        );
+        assert_eq!(
+            block_on(storage.get_addressing_hashes_by_name(ca_name)).unwrap().len(),
+            2
+        );
+
+        // new leaves are signed by the new intermediate
+        let (current_ca, _) = block_on(load_ca(ca_name, storage.as_ref())).unwrap();
+        assert_eq!(current_ca, new_ca);
+        let new_leaf = sign();
+
+        for (leaf, ca) in &[(old_leaf, old_ca), (new_leaf, new_ca)] {
+            let chain = block_on(find_issuer_chain_der(storage.as_ref(), leaf, config.max_chain_depth))
+                .expect("couldn't fetch issuer chain")
+                .iter()
+                .map(|der| Cert::from_der(der).expect("couldn't parse CA cert"))
+                .collect::<Vec<Cert>>();
+            assert_eq!(&chain[0], ca);
+            leaf.verifier()
+                .chain(chain.iter())
+                .exact_date(&UTCDate::now())
+                .verify()
+                .expect("couldn't validate ca chain");
+        }
+    }
+
+    #[test]
+    fn issuance_audit_log() {
+        let mut config = config();
+        config.issuance_policy = Some(IssuancePolicy {
+            allowed_names: vec!["*.bushido.local".to_owned()],
+            ..IssuancePolicy::default()
+        });
+        let storage = block_on(get_storage(&config));
+        block_on(init_storage_from_config(storage.as_ref(), &config)).expect("couldn't init storage");
+        let ca_name = format!("{} Authority", config.realm);
+        let since = Utc::now().timestamp();
+
+        let pk = Picky::generate_private_key(2048).expect("couldn't generate private key");
+        let csr = |common_name: &str| {
+            Csr::generate(
+                DirectoryName::new_common_name(common_name),
+                &pk,
+                SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_256),
+            )
+            .expect("couldn't generate csr")
+        };
+
+        let signed_cert = block_on(sign_and_audit(
+            &ca_name,
+            csr("web.bushido.local"),
+            &config,
+            storage.as_ref(),
+            &Webhooks::disabled(),
+            DEFAULT_CERT_DURATION_SECS,
+            "token:web.bushido.local",
+            false,
+        ))
+        .expect("couldn't sign certificate");
+        block_on(sign_and_audit(
+            &ca_name,
+            csr("login.microsoft.com"),
+            &config,
+            storage.as_ref(),
+            &Webhooks::disabled(),
+            DEFAULT_CERT_DURATION_SECS,
+            "api_key:0123456789abcdef",
+            false,
+        ))
+        .unwrap_err();
+
+        let entries = block_on(storage.list_audit(since, 10)).expect("couldn't list audit entries");
+        assert_eq!(entries.len(), 2);
+
+        let issued = &entries[0];
+        assert_eq!(issued.event, AuditEvent::Issued);
+        assert_eq!(issued.requester, "token:web.bushido.local");
+        assert_eq!(issued.subject.as_deref(), Some("web.bushido.local"));
+        assert_eq!(issued.sans, vec!["web.bushido.local".to_owned()]);
+        assert_eq!(
+            issued.serial_number,
+            Some(hex::encode(signed_cert.serial_number().as_signed_bytes_be()))
+        );
+        assert_eq!(
+            issued.addressing_hash,
+            Some(encode_to_canonical_address(&signed_cert.to_der().unwrap()))
+        );
+        assert_eq!(issued.reason, None);
+
+        let rejected = &entries[1];
+        assert_eq!(rejected.event, AuditEvent::Rejected);
+        assert_eq!(rejected.requester, "api_key:0123456789abcdef");
+        assert_eq!(rejected.subject.as_deref(), Some("login.microsoft.com"));
+        assert_eq!(rejected.serial_number, None);
+        assert_eq!(
+            rejected.reason.as_deref(),
+            Some("names rejected by issuance policy: login.microsoft.com")
+        );
+
+        // entries older than `since` aren't listed, the limit applies
+        assert!(block_on(storage.list_audit(since + 3600, 10)).unwrap().is_empty());
+        assert_eq!(block_on(storage.list_audit(since, 1)).unwrap(), vec![issued.clone()]);
+    }
+
+    #[test]
+    fn reuse_existing_certificate() {
+        let mut config = config();
+        config.save_certificate = true;
+        let storage = block_on(get_storage(&config));
+        block_on(init_storage_from_config(storage.as_ref(), &config)).expect("couldn't init storage");
+        let ca_name = format!("{} Authority", config.realm);
+
+        let csr_with_san = |pk: &PrivateKey, san: &[&str]| {
+            let san = san
+                .iter()
+                .map(|name| GeneralName::new_dns_name(*name).unwrap())
+                .collect::<Vec<GeneralName>>();
+            Csr::generate_with_attributes(
+                DirectoryName::new_common_name("ci.bushido.local"),
+                pk,
+                SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_256),
+                vec![Attribute::new_extension_request(vec![Extension::new_subject_alt_name(
+                    GeneralNames::from(san),
+                )])],
+            )
+            .expect("couldn't generate csr")
+        };
+        let csr = |pk: &PrivateKey| csr_with_san(pk, &["ci.bushido.local"]);
+        let sign_with = |config: &Config, csr: Csr, reuse: bool| {
+            block_on(sign_and_audit(
+                &ca_name,
+                csr,
+                config,
+                storage.as_ref(),
+                &Webhooks::disabled(),
+                DEFAULT_CERT_DURATION_SECS,
+                "token:ci.bushido.local",
+                reuse,
+            ))
+        };
+        let sign = |csr: Csr, reuse: bool| sign_with(&config, csr, reuse).expect("couldn't sign certificate");
+
+        // not of the CA key sizes, so that no key is shared with a CA
+        let test_key = |pem: &str| PrivateKey::from_pem(&pem.parse::<Pem>().unwrap()).unwrap();
+        let pk = test_key(crate::test_files::RSA_3072_PK_1);
+        let first = sign(csr(&pk), true);
+        let second = sign(csr(&pk), true);
+        assert_eq!(second.serial_number(), first.serial_number());
+        assert_eq!(second, first);
+
+        // without reuse, a new certificate is minted
+        let third = sign(csr(&pk), false);
+        assert_ne!(third.serial_number(), first.serial_number());
+
+        // other alternative names
+        let wider = sign(csr_with_san(&pk, &["ci.bushido.local", "cd.bushido.local"]), true);
+        assert_ne!(wider.serial_number(), first.serial_number());
+        let reordered = sign(csr_with_san(&pk, &["cd.bushido.local", "ci.bushido.local"]), true);
+        assert_eq!(reordered.serial_number(), wider.serial_number());
+
+        // CSRs no longer allowed get no certificate, even a previously issued one
+        let mut strict_config = config.clone();
+        strict_config.issuance_policy = Some(IssuancePolicy {
+            allowed_names: vec!["*.example.com".to_owned()],
+            ..IssuancePolicy::default()
+        });
+        let err = sign_with(&strict_config, csr(&pk), true).unwrap_err();
+        assert!(matches!(err, SignError::PolicyViolation { .. }), "{}", err);
+        let mut strict_config = config.clone();
+        strict_config.csr_min_rsa_key_size = 4096;
+        let err = sign_with(&strict_config, csr(&pk), true).unwrap_err();
+        assert!(matches!(err, SignError::WeakCsr { .. }), "{}", err);
+
+        // nor do CSRs whose signature doesn't match the requested key
+        let mut tampered = csr(&pk).to_der().unwrap();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 0xFF;
+        let err = sign_with(&config, Csr::from_der(&tampered).unwrap(), true).unwrap_err();
+        assert!(matches!(err, SignError::InvalidSignature { .. }), "{}", err);
+
+        // same subject name, different key
+        let other_pk = test_key(crate::test_files::RSA_3072_PK_2);
+        let other = sign(csr(&other_pk), true);
+        assert_ne!(other.serial_number(), first.serial_number());
+        assert_ne!(other.serial_number(), third.serial_number());
+        assert_eq!(other.public_key(), &other_pk.to_public_key());
+
+        // revoked certificates aren't reused
+        for cert in &[&first, &third] {
+            let hash = encode_to_canonical_address(&cert.to_der().unwrap());
+            block_on(revoke_certificate(storage.as_ref(), &hash, cert, None)).expect("couldn't revoke");
+        }
+        let renewed = sign(csr(&pk), true);
+        assert_ne!(renewed.serial_number(), first.serial_number());
+        assert_ne!(renewed.serial_number(), third.serial_number());
+    }
+
+    #[test]
+    fn registered_certificates_are_verified() {
+        let config = config();
+        let storage = block_on(get_storage(&config));
+        block_on(init_storage_from_config(storage.as_ref(), &config)).expect("couldn't init storage");
+        let ca_name = format!("{} Authority", config.realm);
+        let signature_algorithm = SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_256);
+
+        // keys of another size than the CA ones, never picked from the same pre-generated pool in tests
+        let pk = Picky::generate_private_key(3072).expect("couldn't generate private key");
+        let csr = || {
+            Csr::generate(DirectoryName::new_common_name("pushed.local"), &pk, signature_algorithm)
+                .expect("couldn't generate csr")
+        };
+        let register = |cert: &Cert| {
+            block_on(register_certificate(
+                cert,
+                cert.to_der().unwrap(),
+                &config,
+                storage.as_ref(),
+            ))
+            .map_err(ApiError::from)
+        };
+
+        let (ca_cert, ca_key) = block_on(load_ca(&ca_name, storage.as_ref())).unwrap();
+        let genuine = Picky::generate_leaf_from_csr(
+            csr(),
+            &ca_cert,
+            &ca_key,
+            signature_algorithm,
+            KeyIdGenMethod::default(),
+            None,
+            chrono::Duration::days(1),
+        )
+        .expect("couldn't generate leaf");
+        let stored = register(&genuine).expect("couldn't register genuine certificate");
+        assert_eq!(register(&genuine).unwrap_err().status(), StatusCode::CONFLICT);
+        assert_eq!(stored.location, format!("/cert/{}", stored.addressing_hash));
+        assert_eq!(stored.ski, hex::encode(genuine.subject_key_identifier().unwrap()));
+        assert_eq!(stored.serial, hex::encode(genuine.serial_number().as_signed_bytes_be()));
+        let fetched = block_on(storage.get_cert_by_addressing_hash(&stored.addressing_hash))
+            .expect("couldn't fetch registered certificate by its address");
+        assert_eq!(fetched, genuine.to_der().unwrap());
+
+        // same issuer name, issued by a look-alike CA
+        let evil_root_key = Picky::generate_private_key(3072).unwrap();
+        let evil_root = Picky::generate_root(
+            "Evil Root CA",
+            &evil_root_key,
+            signature_algorithm,
+            KeyIdGenMethod::default(),
+            chrono::Duration::days(30),
+        )
+        .unwrap();
+        let evil_key = Picky::generate_private_key(3072).unwrap();
+        let evil_ca = Picky::generate_intermediate(
+            &ca_name,
+            evil_key.to_public_key(),
+            &evil_root,
+            &evil_root_key,
+            signature_algorithm,
+            KeyIdGenMethod::default(),
+            // within the root validity, started before the intermediate key was generated
+            chrono::Duration::days(29),
+        )
+        .unwrap();
+        let forged = Picky::generate_leaf_from_csr(
+            csr(),
+            &evil_ca,
+            &evil_key,
+            signature_algorithm,
+            KeyIdGenMethod::default(),
+            None,
+            chrono::Duration::days(1),
+        )
+        .unwrap();
+        assert_eq!(forged.issuer_name(), genuine.issuer_name());
+        assert_eq!(register(&forged).unwrap_err().status(), StatusCode::FORBIDDEN);
+
+        // same issuer name and authority key identifier, signed by another key
+        let forged = CertificateBuilder::new()
+            .valididy(UTCDate::now(), UTCDate::from(Utc::now() + chrono::Duration::days(1)))
+            .subject(DirectoryName::new_common_name("forged.local"), evil_key.to_public_key())
+            .issuer(DirectoryName::new_common_name(ca_name.as_str()), &evil_key)
+            .authority_key_identifier(ca_cert.subject_key_identifier().unwrap().to_vec())
+            .build()
+            .expect("couldn't build forged certificate");
+        let err = register(&forged).unwrap_err();
+        assert_eq!(err.status(), StatusCode::FORBIDDEN);
+
+        // another certificate for the same key
+        let reissued = Picky::generate_leaf_from_csr(
+            csr(),
+            &ca_cert,
+            &ca_key,
+            signature_algorithm,
+            KeyIdGenMethod::default(),
+            None,
+            chrono::Duration::days(2),
+        )
+        .unwrap();
+        assert_eq!(register(&reissued).unwrap_err().status(), StatusCode::CONFLICT);
+    }
+
+    #[test]
+    fn register_pem_bundle_reports_each_certificate() {
+        let config = config();
+        let storage = block_on(get_storage(&config));
+        block_on(init_storage_from_config(storage.as_ref(), &config)).expect("couldn't init storage");
+        let ca_name = format!("{} Authority", config.realm);
+        let signature_algorithm = SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_256);
+        let (ca_cert, ca_key) = block_on(load_ca(&ca_name, storage.as_ref())).unwrap();
+
+        // certificates sharing a key identifier can't all be stored: each leaf gets its own key, not the CA one
+        let mut keys = [
+            crate::test_files::RSA_2048_PK_1,
+            crate::test_files::RSA_2048_PK_2,
+            crate::test_files::RSA_2048_PK_3,
+            crate::test_files::RSA_2048_PK_4,
+            crate::test_files::RSA_2048_PK_5,
+        ]
+        .iter()
+        .map(|pem| PrivateKey::from_pem(&pem.parse::<Pem>().unwrap()).unwrap())
+        .filter(|key| key.to_public_key() != ca_key.to_public_key())
+        .collect::<Vec<PrivateKey>>()
+        .into_iter();
+        let mut leaf = |name: &str| {
+            let pk = keys.next().expect("test key");
+            let csr = Csr::generate(DirectoryName::new_common_name(name), &pk, signature_algorithm)
+                .expect("couldn't generate csr");
+            Picky::generate_leaf_from_csr(
+                csr,
+                &ca_cert,
+                &ca_key,
+                signature_algorithm,
+                KeyIdGenMethod::default(),
+                None,
+                chrono::Duration::days(1),
+            )
+            .expect("couldn't generate leaf")
+        };
+        let first = leaf("first.bundle.local");
+        let second = leaf("second.bundle.local");
+        let bundle = format!(
+            "{}\n{}\n{}",
+            first.to_pem().unwrap(),
+            to_pem("CERTIFICATE", b"not a certificate"),
+            second.to_pem().unwrap()
+        );
+        assert!(is_pem_bundle(bundle.as_bytes()));
+        assert!(!is_pem_bundle(first.to_pem().unwrap().to_string().as_bytes()));
+
+        let register = |bundle: &str| {
+            BundleReport::new(block_on(register_pem_bundle(
+                bundle.as_bytes(),
+                &config,
+                storage.as_ref(),
+                &Webhooks::disabled(),
+                "api_key:0123456789abcdef",
+            )))
+        };
+
+        let report = register(&bundle);
+        assert_eq!(report.status_code(), StatusCode::MULTI_STATUS);
+        assert_eq!(report.certificates.len(), 3);
+        for (entry, cert) in [(&report.certificates[0], &first), (&report.certificates[2], &second)].iter() {
+            let stored = entry.stored.as_ref().expect("certificate should be stored");
+            assert_eq!(stored, &StoredCertificate::new(cert).unwrap());
+            assert_eq!(entry.error, None);
+            let fetched = block_on(storage.get_cert_by_addressing_hash(&stored.addressing_hash)).unwrap();
+            assert_eq!(fetched, cert.to_der().unwrap());
+        }
+        assert_eq!(report.certificates[1].index, 1);
+        assert_eq!(report.certificates[1].stored, None);
+        assert!(report.certificates[1]
+            .error
+            .as_deref()
+            .unwrap()
+            .starts_with("couldn't deserialize certificate"));
+
+        // everything is already stored or invalid
+        let report = register(&bundle);
+        assert_eq!(report.status_code(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            report.certificates[0].error,
+            Some(format!(
+                "certificate {} is already stored",
+                StoredCertificate::new(&first).unwrap().addressing_hash
+            ))
+        );
+
+        let third = leaf("third.bundle.local");
+        let fourth = leaf("fourth.bundle.local");
+        let report = register(&format!("{}{}", third.to_pem().unwrap(), fourth.to_pem().unwrap()));
+        assert_eq!(report.status_code(), StatusCode::OK, "{:?}", report);
+    }
+
+    #[test]
+    fn requested_names() {
+        let pk = Picky::generate_private_key(2048).expect("couldn't generate private key");
+        let mut subject = DirectoryName::new_common_name("web.bushido.local");
+        subject.add_attr(NameAttr::OrganizationName, "CN=Bushido, Inc.");
+        subject.add_attr(NameAttr::OrganizationalUnitName, "Web");
+        let requested_san = GeneralNames::from(vec![
+            GeneralName::new_dns_name("web.bushido.local").unwrap(),
+            GeneralName::new_dns_name("www.bushido.local").unwrap(),
+            GeneralName::from("10.0.0.1".parse::<IpAddr>().unwrap()),
+            GeneralName::from("2001:db8::1".parse::<IpAddr>().unwrap()),
+        ]);
+        let csr = Csr::generate_with_attributes(
+            subject,
+            &pk,
+            SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_256),
+            vec![Attribute::new_extension_request(vec![Extension::new_subject_alt_name(
+                requested_san,
+            )])],
+        )
+        .expect("couldn't generate csr");
+
+        let names = RequestedNames::new(&Csr::from_der(&csr.to_der().unwrap()).unwrap());
+        assert_eq!(
+            names,
+            RequestedNames {
+                common_name: Some("web.bushido.local".to_owned()),
+                subject: "CN=web.bushido.local,O=CN=Bushido, Inc.,OU=Web".to_owned(),
+                dns_sans: vec!["web.bushido.local".to_owned(), "www.bushido.local".to_owned()],
+                ip_sans: vec!["10.0.0.1".to_owned(), "2001:db8::1".to_owned()],
+            }
+        );
+
+        let csr = Csr::generate(
+            DirectoryName::new_common_name("bushido.local"),
+            &pk,
+            SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_256),
+        )
+        .expect("couldn't generate csr");
+        let json = serde_json::to_value(RequestedNames::new(&csr)).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "common_name": "bushido.local",
+                "subject": "CN=bushido.local",
+                "dns_sans": [],
+                "ip_sans": [],
+            })
+        );
+    }
+
+    #[test]
+    fn issuer_fetched_by_ski() {
+        let config = config();
+        let storage = block_on(get_storage(&config));
+        block_on(init_storage_from_config(storage.as_ref(), &config)).expect("couldn't init storage");
+        let ca_name = format!("{} Authority", config.realm);
+
+        let pk = Picky::generate_private_key(3072).expect("couldn't generate private key");
+        let csr = Csr::generate(
+            DirectoryName::new_common_name("leaf.local"),
+            &pk,
+            SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_256),
+        )
+        .expect("couldn't generate csr");
+        let leaf = block_on(sign_certificate(
+            &ca_name,
+            csr.into_verified().unwrap(),
+            &config,
+            storage.as_ref(),
+            DEFAULT_CERT_DURATION_SECS,
+        ))
+        .expect("couldn't sign certificate");
+
+        let aki = leaf.authority_key_identifier().expect("authority key identifier");
+        let aki = hex::encode_upper(aki.key_identifier().expect("key identifier"));
+        let address = block_on(find_address_by_ski(storage.as_ref(), &aki)).expect("couldn't find issuer");
+
+        let req = Request::new(
+            saphir::http::Request::builder()
+                .uri(format!("/cert/ski/{}", aki))
+                .header("Accept", "application/pkix-cert")
+                .body(Body::empty())
+                .expect("request"),
+            None,
+        );
+        let (body, _) = block_on(fetch_certificate(&req, storage.as_ref(), &address)).expect("couldn't fetch issuer");
+        assert_eq!(body.content_type, "application/pkix-cert");
+        let issuer = Cert::from_der(&body.body).expect("issuer certificate");
+        assert_eq!(issuer.subject_name().to_string(), format!("CN={}", ca_name));
+        assert_eq!(hex::encode_upper(issuer.subject_key_identifier().unwrap()), aki);
+
+        let err = block_on(find_address_by_ski(storage.as_ref(), "not hex")).unwrap_err();
+        assert_eq!(err.status(), StatusCode::BAD_REQUEST);
+        let err = block_on(find_address_by_ski(storage.as_ref(), "0badc0de")).unwrap_err();
+        assert_eq!(err.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn certificate_response_formats() {
+        let mut config = config();
+        config.save_certificate = true;
+        let storage = block_on(get_storage(&config));
+        block_on(init_storage_from_config(storage.as_ref(), &config)).expect("couldn't init storage");
+        let ca_name = format!("{} Authority", config.realm);
+
+        let pk = Picky::generate_private_key(3072).expect("couldn't generate private key");
+        let csr = Csr::generate(
+            DirectoryName::new_common_name("formats.local"),
+            &pk,
+            SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_256),
+        )
+        .expect("couldn't generate csr");
+        let signed_cert = block_on(sign_certificate(
+            &ca_name,
+            csr.into_verified().unwrap(),
+            &config,
+            storage.as_ref(),
+            DEFAULT_CERT_DURATION_SECS,
+        ))
+        .expect("couldn't sign certificate");
+        let der = signed_cert.to_der().unwrap();
+        let address = encode_to_canonical_address(&der);
+
+        let request = |accept: Option<&'static str>| {
+            let mut req = saphir::http::Request::builder()
+                .method("GET")
+                .uri(format!("/cert/{}", address));
+            if let Some(accept) = accept {
+                req = req.header("Accept", accept);
+            }
+            Request::new(req.body(Body::empty()).expect("request"), None)
+        };
+
+        let cases: &[(Option<&'static str>, &str, &str)] = &[
+            (None, "application/x-pem-file", "pem"),
+            (Some("*/*"), "application/x-pem-file", "pem"),
+            (Some("application/x-pem-file"), "application/x-pem-file", "pem"),
+            (Some("text/plain"), "text/plain", "pem"),
+            (Some("application/pkix-cert"), "application/pkix-cert", "cer"),
+            (
+                Some("application/pkix-cert-base64"),
+                "application/pkix-cert-base64",
+                "b64",
+            ),
+            (Some("application/json"), "application/json", "json"),
+            (Some("application/json;q=0.2, text/plain;q=0.5"), "text/plain", "pem"),
+        ];
+        for (accept, content_type, extension) in cases {
+            let (body, revocation) = block_on(fetch_certificate(&request(*accept), storage.as_ref(), &address))
+                .unwrap_or_else(|e| panic!("Accept: {:?}: {}", accept, e));
+            assert_eq!(body.content_type, *content_type, "Accept: {:?}", accept);
+            assert_eq!(body.file_name, format!("{}.{}", address, extension));
+            assert_eq!(revocation, None);
+
+            let body_der = match *extension {
+                "pem" => parse_pem(&body.body).unwrap().into_data().into_owned(),
+                "cer" => body.body,
+                "b64" => base64::decode(&body.body).unwrap(),
+                _ => {
+                    let response: CertificateResponse = serde_json::from_slice(&body.body).unwrap();
+                    assert_eq!(response.metadata.hash, address);
+                    assert_eq!(response.metadata.subject, "CN=formats.local");
+                    assert!(!response.metadata.revoked);
+                    parse_pem(response.certificate.as_bytes())
+                        .unwrap()
+                        .into_data()
+                        .into_owned()
+                }
+            };
+            assert_eq!(body_der, der, "Accept: {:?}", accept);
+        }
+
+        for accept in &["application/pkcs10", "image/png, application/x-pem-file;q=0"] {
+            let err = block_on(fetch_certificate(&request(Some(*accept)), storage.as_ref(), &address)).unwrap_err();
+            assert_eq!(err.status(), StatusCode::NOT_ACCEPTABLE, "Accept: {}", accept);
+        }
+    }
+
+    #[test]
+    fn readiness() {
+        let config = config();
+        let storage = block_on(get_storage(&config));
+
+        let report = block_on(check_readiness(&config, storage.as_ref()));
+        assert_eq!(report.status, "unavailable");
+        assert_eq!(report.failing_component.as_deref(), Some("root_ca"));
+        assert!(report.db_latency_ms.is_some());
+
+        // intermediate CA is missing
+        block_on(generate_root_ca(&config, storage.as_ref())).expect("couldn't generate root ca");
+        let report = block_on(check_readiness(&config, storage.as_ref()));
+        assert_eq!(report.status, "unavailable");
+        assert_eq!(report.failing_component.as_deref(), Some("intermediate_ca"));
+        assert_eq!(report.intermediate_expires_in_days, None);
+
+        block_on(generate_intermediate_ca(&config, storage.as_ref())).expect("couldn't generate intermediate ca");
+        let report = block_on(check_readiness(&config, storage.as_ref()));
+        assert_eq!(report.status, "ok");
+        assert_eq!(report.backend, BackendType::Memory);
+        assert_eq!(report.failing_component, None);
+        assert_eq!(report.error, None);
+        let expires_in_days = report.intermediate_expires_in_days.expect("intermediate expiration");
+        assert!(expires_in_days > 1800 && expires_in_days <= 1825);
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["backend"], "memory");
+        assert!(json.get("failing_component").is_none());
+    }
+
+    #[test]
+    fn liveness_ignores_backend() {
+        let mut config = config();
+        config.backend = BackendType::File;
+        config.file_backend_path = std::env::temp_dir().join(format!("picky_liveness_{}", std::process::id()));
+        let storage = block_on(get_storage(&config));
+        block_on(generate_root_ca(&config, storage.as_ref())).expect("couldn't generate root ca");
+        block_on(generate_intermediate_ca(&config, storage.as_ref())).expect("couldn't generate intermediate ca");
+
+        let report = block_on(check_readiness(&config, storage.as_ref()));
+        assert_eq!(report.status_code(), StatusCode::OK);
+        assert_eq!(liveness_report()["status"], "ok");
+
+        // the backend now points at nowhere
+        std::fs::remove_dir_all(&config.file_backend_path).unwrap();
+        let report = block_on(check_readiness(&config, storage.as_ref()));
+        assert_eq!(report.status_code(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(report.failing_component.as_deref(), Some("database"));
+        assert_eq!(liveness_report()["status"], "ok");
+    }
+
+    #[test]
+    fn metrics_count_issued_certificates() {
+        let config = config();
+        let storage = block_on(get_storage(&config));
+        let metrics = Metrics::new();
+
+        let ca_name = format!("{} Authority", config.realm);
+
+        block_on(generate_root_ca(&config, storage.as_ref())).expect("couldn't generate root ca");
+        block_on(generate_intermediate_ca(&config, storage.as_ref())).expect("couldn't generate intermediate ca");
+
+        let pk = Picky::generate_private_key(2048).expect("couldn't generate private key");
+        let csr = Csr::generate(
+            DirectoryName::new_common_name("metrics.bushido.local"),
+            &pk,
+            SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_256),
+        )
+        .expect("couldn't generate csr");
+        let result = block_on(sign_certificate(
+            &ca_name,
+            csr.into_verified().unwrap(),
+            &config,
+            storage.as_ref(),
+            7_776_000,
+        ));
+        record_sign_result(&metrics, &result);
+        result.expect("couldn't sign certificate");
+
+        let text = block_on(scrape_metrics(&metrics, &config, storage.as_ref()));
+        assert!(text.contains("picky_certificates_issued_total{outcome=\"success\"} 1\n"));
+        assert!(text.contains("picky_backend_up 1\n"));
+        assert!(!text.contains("picky_intermediate_expiry_days 0\n"));
+        assert_eq!(metrics.csrs_rejected("csr_allowed_hash_algorithms"), 0);
+
+        let csr = Csr::generate(
+            DirectoryName::new_common_name("metrics.bushido.local"),
+            &pk,
+            SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA1),
+        )
+        .expect("couldn't generate csr");
+        let result = block_on(sign_certificate(
+            &ca_name,
+            csr.into_verified().unwrap(),
+            &config,
+            storage.as_ref(),
+            7_776_000,
+        ));
+        record_sign_result(&metrics, &result);
+        assert!(result.is_err());
+        assert_eq!(metrics.certificates_issued("success"), 1);
+        assert_eq!(metrics.certificates_issued("rejected"), 1);
+        assert_eq!(metrics.csrs_rejected("csr_allowed_hash_algorithms"), 1);
+    }
+
+    #[test]
+    fn lookup_errors() {
+        let config = config();
+        let storage = block_on(get_storage(&config));
+
+        let err = block_on(find_ca_chain(storage.as_ref(), "Unknown Authority"))
+            .api_error(ApiErrorKind::CaNotFound, "couldn't find CA chain")
+            .unwrap_err();
+        assert_eq!(err.status(), StatusCode::NOT_FOUND);
+        assert_eq!(
+            serde_json::from_str::<Value>(&err.to_json()).unwrap(),
+            serde_json::json!({ "error": "couldn't find CA chain", "kind": "ca_not_found" })
+        );
+
+        let err = block_on(storage.get_cert_by_addressing_hash("unknown"))
+            .not_found_desc("couldn't fetch certificate")
+            .unwrap_err();
+        assert_eq!(err.status(), StatusCode::NOT_FOUND);
+        assert_eq!(
+            serde_json::from_str::<Value>(&err.to_json()).unwrap(),
+            serde_json::json!({ "error": "couldn't fetch certificate", "kind": "not_found" })
+        );
+
+        let err = unexpected_response_format(Format::OcspRequest);
+        assert_eq!(err.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(err.kind, ApiErrorKind::BadRequest);
+        assert_eq!(err.message, "unexpected response format: binary-encoded ocsp-request");
+    }
+
+    #[test]
+    fn provisioner_token_constrains_subject() {
+        let provisioner_key = PrivateKey::from_pem(
+            &include_str!("../../../test_assets/private_keys/rsa-2048-pk_4.key")
+                .parse::<Pem>()
+                .expect("pem"),
+        )
+        .expect("provisioner key");
+
+        let mut config = config();
+        config.auth_mode = AuthMode::Jwt;
+        config.provisioner_public_key = Some(PathOr::Some(provisioner_key.to_public_key()));
+        config.provisioner_audience = Some("picky".to_owned());
+        let storage = block_on(get_storage(&config));
+        let ca_name = format!("{} Authority", config.realm);
+
+        block_on(generate_root_ca(&config, storage.as_ref())).expect("couldn't generate root ca");
+        block_on(generate_intermediate_ca(&config, storage.as_ref())).expect("couldn't generate intermediate ca");
+
+        let sign_request = |aud: &str| {
+            let claims = serde_json::json!({
+                "x509_duration_secs": 3600,
+                "sub": "provisioner",
+                "nbf": unix_epoch(),
+                "exp": unix_epoch() + 60,
+                "aud": aud,
+                "allowed_subjects": ["*.bushido.local"],
+            });
+            let token = JwtSig::new(JwsAlg::RS256, claims)
+                .encode(&provisioner_key)
+                .expect("jwt encode");
+            let req = saphir::http::Request::builder()
+                .method("POST")
+                .uri("/sign")
+                .header("Authorization", format!("Bearer {}", token))
+                .body(Body::empty())
+                .expect("request");
+            Request::new(req, None)
+        };
+
+        let claims = authorize_sign_request(&config, &sign_request("picky"))
+            .expect("authorization")
+            .expect("provider claims");
+        assert!(claims.allows_subject("web.bushido.local"));
+        assert!(!claims.allows_subject("web.samurai.local"));
+        assert!(!claims.allows_subject("bushido.local"));
+
+        let pk = Picky::generate_private_key(2048).expect("couldn't generate private key");
+        let csr = Csr::generate(
+            DirectoryName::new_common_name("web.bushido.local"),
+            &pk,
+            SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_256),
+        )
+        .expect("couldn't generate csr");
+        let signed_cert = block_on(sign_certificate(
+            &ca_name,
+            csr.into_verified().unwrap(),
+            &config,
+            storage.as_ref(),
+            claims.x509_duration_secs,
+        ))
+        .expect("couldn't sign certificate");
+        assert_eq!(
+            signed_cert.subject_name().common_name().unwrap().to_string(),
+            "web.bushido.local"
+        );
+
+        let err = authorize_sign_request(&config, &sign_request("someone-else")).expect_err("audience mismatch");
+        assert_eq!(err, "json web token isn't issued for the picky audience");
+    }
+
+    #[test]
+    fn issuance_policy_restricts_names() {
+        let mut config = config();
+        config.issuance_policy = Some(IssuancePolicy {
+            allowed_names: vec!["*.bushido.local".to_owned()],
+            ..IssuancePolicy::default()
+        });
+        let storage = block_on(get_storage(&config));
+        let ca_name = format!("{} Authority", config.realm);
 
-    let pk = Picky::generate_private_key(2048).map_err(|e| e.to_string())?;
-    let root_cert = Cert::from_der(&root_cert_der).map_err(|e| format!("couldn't parse root cert from der: {}", e))?;
-    let root_key = Picky::parse_pk_from_magic_der(&root_key_der).map_err(|e| e.to_string())?;
+        block_on(generate_root_ca(&config, storage.as_ref())).expect("couldn't generate root ca");
+        block_on(generate_intermediate_ca(&config, storage.as_ref())).expect("couldn't generate intermediate ca");
 
-    let intermediate_cert = Picky::generate_intermediate(
-        &intermediate_name,
-        pk.to_public_key(),
-        &root_cert,
-        &root_key,
-        config.signing_algorithm,
-    )
-    .map_err(|e| format!("couldn't generate intermediate certificate: {}", e))?;
+        let pk = Picky::generate_private_key(2048).expect("couldn't generate private key");
+        let csr = |common_name: &str| {
+            Csr::generate(
+                DirectoryName::new_common_name(common_name),
+                &pk,
+                SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_256),
+            )
+            .expect("couldn't generate csr")
+        };
 
-    let ski = intermediate_cert
-        .subject_key_identifier()
-        .map_err(|e| format!("couldn't fetch key id: {}", e))?;
+        let signed_cert = block_on(sign_certificate(
+            &ca_name,
+            csr("web.bushido.local").into_verified().unwrap(),
+            &config,
+            storage.as_ref(),
+            DEFAULT_CERT_DURATION_SECS,
+        ))
+        .expect("couldn't sign certificate");
+        // SAN is synthesized from the common name
+        assert_eq!(
+            signed_cert.subject_alt_name().expect("subject alt name"),
+            GeneralNames::new(GeneralName::new_dns_name("web.bushido.local").unwrap())
+        );
 
-    let cert_der = intermediate_cert
-        .to_der()
-        .map_err(|e| format!("couldn't serialize intermediate certificate into der: {}", e))?;
+        let err = block_on(sign_certificate(
+            &ca_name,
+            csr("login.microsoft.com").into_verified().unwrap(),
+            &config,
+            storage.as_ref(),
+            DEFAULT_CERT_DURATION_SECS,
+        ))
+        .unwrap_err();
+        match err {
+            SignError::PolicyViolation { rejected } => assert_eq!(rejected, vec!["login.microsoft.com".to_owned()]),
+            unexpected => panic!("unexpected error: {}", unexpected),
+        }
+    }
 
-    let pk_pkcs8 = pk
-        .to_pkcs8()
-        .map_err(|e| format!("couldn't get private key pkcs8: {}", e))?;
+    #[test]
+    fn weak_csr_rejected() {
+        let config = config();
+        let storage = block_on(get_storage(&config));
+        let ca_name = format!("{} Authority", config.realm);
 
-    storage
-        .store(CertificateEntry {
-            name: intermediate_name,
-            cert: cert_der,
-            key_identifier: hex::encode(ski),
-            key: Some(pk_pkcs8),
-        })
-        .await
-        .map_err(|e| format!("couldn't store generated intermediate certificate: {}", e))?;
+        block_on(generate_root_ca(&config, storage.as_ref())).expect("couldn't generate root ca");
+        block_on(generate_intermediate_ca(&config, storage.as_ref())).expect("couldn't generate intermediate ca");
 
-    Ok(true)
-}
+        let sign = |pk: &PrivateKey, signature_algorithm: SignatureAlgorithm| {
+            let csr = Csr::generate(
+                DirectoryName::new_common_name("weak.bushido.local"),
+                pk,
+                signature_algorithm,
+            )
+            .expect("couldn't generate csr");
+            block_on(sign_certificate(
+                &ca_name,
+                csr.into_verified().unwrap(),
+                &config,
+                storage.as_ref(),
+                DEFAULT_CERT_DURATION_SECS,
+            ))
+        };
 
-async fn inject_config_provided_cert(
-    expected_subject_name: &str,
-    cert_key_pair: &CertKeyPair,
-    storage: &dyn PickyStorage,
-) -> Result<(), String> {
-    let (cert, cert_der) = match &cert_key_pair.cert {
-        PathOr::Path(path) => {
-            let pem_str = tokio::fs::read_to_string(path)
-                .await
-                .map_err(|e| format!("couldn't read cert: {}", e))?;
-            let pem = pem_str
+        let small_pk = PrivateKey::from_pem(
+            &include_str!("../../../test_assets/private_keys/rsa-1024-pk_1.key")
                 .parse::<Pem>()
-                .map_err(|e| format!("couldn't parse cert pem: {}", e))?;
-            let cert = Cert::from_pem(&pem).map_err(|e| format!("couldn't parse cert: {}", e))?;
-            (Cow::Owned(cert), pem.into_data().into_owned())
-        }
-        PathOr::Some(cert) => {
-            let cert_der = cert
-                .to_der()
-                .map_err(|e| format!("couldn't encode cert to der: {}", e))?;
-            (Cow::Borrowed(cert), cert_der)
+                .expect("pem"),
+        )
+        .expect("small private key");
+        match sign(&small_pk, SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_256)).unwrap_err() {
+            SignError::WeakCsr { rule, .. } => assert_eq!(rule, "csr_min_rsa_key_size"),
+            unexpected => panic!("unexpected error: {}", unexpected),
         }
-    };
 
-    let ski = hex::encode(
-        cert.subject_key_identifier()
-            .map_err(|e| format!("couldn't parse fetch subject key identifier: {}", e))?,
-    );
-    let subject_name = cert
-        .subject_name()
-        .find_common_name()
-        .ok_or_else(|| "couldn't find subject common name".to_owned())?
-        .to_string();
+        let pk = Picky::generate_private_key(2048).expect("couldn't generate private key");
+        let err = sign(&pk, SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA1)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "CSR rejected (csr_allowed_hash_algorithms): SHA1 signatures are not allowed"
+        );
 
-    if subject_name != expected_subject_name {
-        return Err(format!(
-            "unexpected subject name: {} ; expected: {}",
-            subject_name, expected_subject_name
-        ));
+        sign(&pk, SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_256)).expect("couldn't sign certificate");
     }
 
-    let key_der = match &cert_key_pair.key {
-        PathOr::Path(path) => {
-            let pem_str = tokio::fs::read_to_string(path)
-                .await
-                .map_err(|e| format!("couldn't read key: {}", e))?;
-            let pem = pem_str
-                .parse::<Pem>()
-                .map_err(|e| format!("couldn't parse key pem: {}", e))?;
-            pem.into_data().into_owned()
-        }
-        PathOr::Some(key) => key
-            .to_pkcs8()
-            .map_err(|e| format!("couldn't convert key to pkcs8: {}", e))?,
-    };
+    fn request_with_body(content_type: &'static str, body: Vec<u8>) -> Request<Bytes> {
+        let mut request = saphir::http::Request::builder();
+        request.headers_mut().expect("headers mut").insert(
+            "Content-Type",
+            saphir::http::header::HeaderValue::from_static(content_type),
+        );
+        Request::new(request.body(Bytes::from(body)).expect("request"), None)
+    }
 
-    storage
-        .store(CertificateEntry {
-            name: subject_name,
-            cert: cert_der,
-            key_identifier: ski,
-            key: Some(key_der),
-        })
-        .await
-        .map_err(|e| format!("couldn't store certificate: {}", e))?;
+    #[test]
+    fn csr_request_formats() {
+        let config = config();
+        let storage = block_on(get_storage(&config));
 
-    Ok(())
-}
+        let ca_name = format!("{} Authority", config.realm);
 
-async fn init_storage_from_config(storage: &dyn PickyStorage, config: &Config) -> Result<(), String> {
-    log::info!("init storage from config");
+        block_on(generate_root_ca(&config, storage.as_ref())).expect("couldn't generate root ca");
+        block_on(generate_intermediate_ca(&config, storage.as_ref())).expect("couldn't generate intermediate ca");
 
-    if let Some(root_cert_key_pair) = &config.root {
-        log::info!("inject root CA provided by settings");
-        let expected = format!("{} Root CA", config.realm);
-        if let Err(e) = inject_config_provided_cert(&expected, root_cert_key_pair, storage).await {
-            return Err(format!("couldn't inject root CA: {}", e));
-        }
-    } else {
-        log::info!("root CA...");
-        let created = generate_root_ca(&config, storage)
-            .await
-            .map_err(|e| format!("couldn't generate root CA: {}", e))?;
-        if created {
-            log::info!("created");
-        } else {
-            log::info!("already exists");
-        }
-    }
+        let pk = Picky::generate_private_key(2048).expect("couldn't generate private key");
+        let csr = Csr::generate(
+            DirectoryName::new_common_name("Format Bushido"),
+            &pk,
+            SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_256),
+        )
+        .expect("couldn't generate csr");
+        let der = csr.to_der().unwrap();
+        let pem = csr.to_pem().unwrap().to_string();
+        let json = serde_json::json!({ "csr": pem }).to_string();
 
-    if let Some(intermediate_cert_key_pair) = &config.intermediate {
-        log::info!("inject intermediate CA provided by settings");
-        let expected = format!("{} Authority", config.realm);
-        if let Err(e) = inject_config_provided_cert(&expected, intermediate_cert_key_pair, storage).await {
-            return Err(format!("couldn't inject intermediate CA: {}", e));
-        }
-    } else {
-        log::info!("intermediate CA...");
-        let created = generate_intermediate_ca(&config, storage)
-            .await
-            .map_err(|e| format!("couldn't generate intermediate CA: {}", e))?;
-        if created {
-            log::info!("created");
-        } else {
-            log::info!("already exists");
+        let requests = vec![
+            request_with_body("application/x-pem-file", pem.clone().into_bytes()),
+            request_with_body("text/plain", pem.clone().into_bytes()),
+            request_with_body("text/plain; charset=utf-8", pem.clone().into_bytes()),
+            request_with_body("application/json", json.into_bytes()),
+            request_with_body("application/pkcs10", der.clone()),
+            request_with_body("application/pkcs10", pem.into_bytes()),
+            request_with_body("application/pkcs10", base64::encode(&der).into_bytes()),
+            request_with_body("application/pkcs10-base64", base64::encode(&der).into_bytes()),
+        ];
+
+        for req in requests {
+            assert!(check_request_format(&req, CSR_REQUEST_FORMATS).is_ok());
+            let parsed = block_on(extract_csr_from_request(&req)).expect("couldn't extract csr");
+            assert_eq!(parsed.to_der().unwrap(), der);
+            block_on(sign_certificate(
+                &ca_name,
+                parsed.into_verified().unwrap(),
+                &config,
+                storage.as_ref(),
+                7_776_000,
+            ))
+            .expect("couldn't sign certificate");
         }
+
+        let req = request_with_body("application/pkix-cert", der);
+        let err = check_request_format(&req, CSR_REQUEST_FORMATS).unwrap_err();
+        assert_eq!(err.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert_eq!(
+            serde_json::from_str::<Value>(&err.to_json()).unwrap(),
+            serde_json::json!({
+                "error": "unsupported request format: binary-encoded pkix-cert",
+                "kind": "unsupported_media_type",
+                "supported_types": [
+                    "application/x-pem-file",
+                    "text/plain",
+                    "application/json",
+                    "application/pkcs10",
+                    "application/pkcs10-base64",
+                ],
+            })
+        );
+
+        let req = request_with_body("application/octet-stream", Vec::new());
+        let err = check_request_format(&req, CSR_REQUEST_FORMATS).unwrap_err();
+        assert_eq!(err.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert_eq!(err.message, "unsupported format: application/octet-stream");
+
+        let req = request_with_body("application/pkcs10", b"not a csr".to_vec());
+        let err = block_on(extract_csr_from_request(&req))
+            .api_error(ApiErrorKind::InvalidCsr, "couldn't deserialize CSR")
+            .unwrap_err();
+        assert_eq!(err.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            serde_json::from_str::<Value>(&err.to_json()).unwrap(),
+            serde_json::json!({ "error": "couldn't deserialize CSR", "kind": "invalid_csr" })
+        );
     }
 
-    Ok(())
-}
+    #[test]
+    fn cert_request_formats() {
+        let config = config();
+        let storage = block_on(get_storage(&config));
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::config::BackendType;
-    use picky::{
-        hash::HashAlgorithm,
-        signature::SignatureAlgorithm,
-        x509::{date::UTCDate, name::DirectoryName},
-    };
-    use tokio_test::block_on;
+        block_on(generate_root_ca(&config, storage.as_ref())).expect("couldn't generate root ca");
+        block_on(generate_intermediate_ca(&config, storage.as_ref())).expect("couldn't generate intermediate ca");
 
-    fn config() -> Config {
-        let mut config = Config::default();
-        config.backend = BackendType::Memory;
-        config
+        let (cert, _) = block_on(load_ca(&format!("{} Authority", config.realm), storage.as_ref())).unwrap();
+        let der = cert.to_der().unwrap();
+        let pem = cert.to_pem().unwrap().to_string();
+        let json = serde_json::json!({ "certificate": pem }).to_string();
+
+        let requests = vec![
+            request_with_body("application/x-pem-file", pem.clone().into_bytes()),
+            request_with_body("text/plain", pem.clone().into_bytes()),
+            request_with_body("application/json", json.into_bytes()),
+            request_with_body("application/pkix-cert", der.clone()),
+            request_with_body("application/pkix-cert", pem.into_bytes()),
+            request_with_body("application/pkix-cert-base64", base64::encode(&der).into_bytes()),
+        ];
+
+        for req in requests {
+            assert!(check_request_format(&req, CERT_REQUEST_FORMATS).is_ok());
+            let (parsed, parsed_der) = block_on(extract_cert_from_request(&req)).expect("couldn't extract cert");
+            assert_eq!(parsed, cert);
+            assert_eq!(parsed_der, der);
+        }
+
+        let req = request_with_body("application/pkcs10", der);
+        let err = check_request_format(&req, CERT_REQUEST_FORMATS).unwrap_err();
+        assert_eq!(err.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert_eq!(
+            err.supported_types.unwrap(),
+            vec![
+                "application/x-pem-file",
+                "text/plain",
+                "application/json",
+                "application/pkix-cert",
+                "application/pkix-cert-base64",
+            ]
+        );
     }
 
     #[test]
-    fn generate_chain_and_verify() {
+    fn est_enrollment() {
         let config = config();
         let storage = block_on(get_storage(&config));
 
@@ -669,44 +6127,389 @@ mod tests {
         block_on(generate_root_ca(&config, storage.as_ref())).expect("couldn't generate root ca");
         block_on(generate_intermediate_ca(&config, storage.as_ref())).expect("couldn't generate intermediate ca");
 
+        // RFC 7030 #4.2.1: base64-encoded PKCS#10, folded as done by `openssl base64`
         let pk = Picky::generate_private_key(2048).expect("couldn't generate private key");
         let csr = Csr::generate(
-            DirectoryName::new_common_name("Mister Bushido"),
+            DirectoryName::new_common_name("est.bushido.local"),
             &pk,
-            SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_384),
+            SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_256),
         )
         .expect("couldn't generate csr");
+        let der = csr.to_der().unwrap();
+        let base64 = base64::encode(&der);
+        let folded = base64
+            .as_bytes()
+            .chunks(64)
+            .flat_map(|line| line.iter().copied().chain(std::iter::once(b'\n')))
+            .collect::<Vec<u8>>();
+
+        let req = request_with_body("application/pkcs10", folded);
+        assert!(check_request_format(&req, EST_REQUEST_FORMATS).is_ok());
+        let parsed = extract_est_csr(req.body()).expect("couldn't extract csr");
+        assert_eq!(parsed.to_der().unwrap(), der);
+
+        let req = request_with_body("application/json", Vec::new());
+        let err = check_request_format(&req, EST_REQUEST_FORMATS).unwrap_err();
+        assert_eq!(err.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
 
         let signed_cert = block_on(sign_certificate(
             &ca_name,
-            csr,
+            parsed.into_verified().unwrap(),
             &config,
             storage.as_ref(),
-            7_776_000, // 3 months,
+            DEFAULT_CERT_DURATION_SECS,
         ))
         .expect("couldn't sign certificate");
 
-        let issuer_name = signed_cert.issuer_name().find_common_name().unwrap().to_string();
-        let chain_pem = block_on(find_ca_chain(storage.as_ref(), &issuer_name)).expect("couldn't fetch CA chain");
-
-        assert_eq!(chain_pem.len(), 2);
+        let decode = |body: String| {
+            assert!(body.split("\r\n").all(|line| line.len() <= EST_BASE64_LINE_LENGTH));
+            let der = base64::decode(body.split_whitespace().collect::<String>()).expect("base64 body");
+            Pkcs7::from_der(&der).expect("pkcs7 body").certs()
+        };
 
-        let chain = chain_pem
-            .iter()
-            .map(|cert_pem| {
-                let pem = cert_pem.parse::<Pem>().expect("couldn't parse cert pem");
-                Cert::from_der(pem.data()).expect("couldn't parse cert from der")
-            })
-            .collect::<Vec<Cert>>();
+        // RFC 7030 #4.2.3: the issued certificate alone
+        let enrolled = decode(est_certs_body(&[signed_cert.to_der().unwrap()]).expect("couldn't encode certificate"));
+        assert_eq!(enrolled, vec![signed_cert.clone()]);
 
+        // RFC 7030 #4.1.3: the CA chain, root included
+        let chain_der = block_on(find_ca_chain_der(storage.as_ref(), &ca_name, config.max_chain_depth))
+            .expect("couldn't fetch CA chain");
+        let chain = decode(est_certs_body(&chain_der).expect("couldn't encode CA chain"));
+        assert_eq!(chain.len(), 2);
         assert_eq!(chain[0].subject_name().to_string(), "CN=Picky Authority");
         assert_eq!(chain[1].subject_name().to_string(), "CN=Picky Root CA");
 
-        signed_cert
+        enrolled[0]
             .verifier()
             .chain(chain.iter())
             .exact_date(&UTCDate::now())
             .verify()
             .expect("couldn't validate ca chain");
     }
+
+    #[test]
+    fn reload_swaps_api_key() {
+        let conf_path = std::env::temp_dir().join(format!("picky_reload_{}.yaml", std::process::id()));
+        let write_conf = |api_key: &str, backend: &str| {
+            std::fs::write(&conf_path, format!("backend: {}\napi_key: {}\n", backend, api_key)).unwrap();
+        };
+        write_conf("before-rotation", "memory");
+
+        let mut config = Config::init_file(Some(&conf_path)).unwrap().0;
+        config.config_path = Some(conf_path.clone());
+        let storage: Arc<dyn PickyStorage> = Arc::from(block_on(get_storage(&config)));
+        block_on(init_storage_from_config(storage.as_ref(), &config)).expect("couldn't init storage");
+        let reloader = ConfigReloader::new(config, storage, None);
+
+        let sign_request = |api_key: &str| {
+            let req = saphir::http::Request::builder()
+                .method("POST")
+                .uri("/sign")
+                .header("Picky-Api-Key", api_key);
+            Request::new(req.body(Body::empty()).expect("request"), None)
+        };
+        let check = |api_key: &str| {
+            check_api_key(
+                &block_on(reloader.config.read()),
+                &sign_request(api_key),
+                ApiKeyScope::Issue,
+            )
+        };
+        check("before-rotation").expect("api key refused");
+
+        write_conf("after-rotation", "memory");
+        let changed = block_on(reloader.reload()).expect("couldn't reload");
+        assert_eq!(changed, vec!["api_key"]);
+        check("after-rotation").expect("rotated api key refused");
+        assert!(check("before-rotation").is_err());
+
+        // refused as a whole, the running configuration is kept
+        write_conf("refused", "file");
+        let err = block_on(reloader.reload()).unwrap_err();
+        assert_eq!(err, "changing backend requires a restart");
+        check("after-rotation").expect("api key changed by a refused reload");
+
+        std::fs::remove_file(&conf_path).unwrap();
+    }
+
+    #[test]
+    fn provisioning_commands() {
+        let config = config();
+        let storage = block_on(get_storage(&config));
+
+        let err = block_on(show_chain_command(&config, storage.as_ref())).unwrap_err();
+        assert!(err.starts_with("couldn't fetch CA hash id"), "{}", err);
+
+        block_on(init_command(&config, storage.as_ref())).expect("couldn't init");
+        let chain = block_on(show_chain_command(&config, storage.as_ref())).expect("couldn't show chain");
+        let chain: Vec<Cert> = read_pems(chain.as_bytes())
+            .map(|pem| Cert::from_pem(&pem.expect("chain pem")).expect("chain cert"))
+            .collect();
+        assert_eq!(chain.len(), 2);
+        assert_eq!(
+            chain[0].subject_name().to_string(),
+            format!("CN={} Authority", config.realm)
+        );
+        assert_eq!(
+            chain[1].subject_name().to_string(),
+            format!("CN={} Root CA", config.realm)
+        );
+
+        // CAs are kept on subsequent runs
+        block_on(init_command(&config, storage.as_ref())).expect("couldn't init again");
+        let again = block_on(show_chain_command(&config, storage.as_ref())).expect("couldn't show chain");
+        let again: Vec<Cert> = read_pems(again.as_bytes())
+            .map(|pem| Cert::from_pem(&pem.expect("chain pem")).expect("chain cert"))
+            .collect();
+        assert_eq!(again, chain);
+
+        let pk = PrivateKey::from_pem(&crate::test_files::RSA_2048_PK_5.parse::<Pem>().unwrap()).unwrap();
+        let csr = Csr::generate(
+            DirectoryName::new_common_name("offline.local"),
+            &pk,
+            SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_256),
+        )
+        .expect("couldn't generate csr");
+        let csr_pem = csr.to_pem().unwrap().to_string();
+
+        let cert_pem = block_on(sign_command(&config, storage.as_ref(), csr_pem.as_bytes())).expect("couldn't sign");
+        let cert = Cert::from_pem(&cert_pem.parse::<Pem>().unwrap()).expect("issued cert");
+        assert_eq!(cert.subject_name().to_string(), "CN=offline.local");
+        cert.verifier()
+            .chain(chain.iter())
+            .exact_date(&cert.valid_not_before())
+            .verify()
+            .expect("issued cert isn't valid for the chain");
+
+        let audit = block_on(storage.list_audit(0, 10)).unwrap();
+        assert!(audit
+            .iter()
+            .any(|entry| entry.requester == CLI_REQUESTER && entry.event == AuditEvent::Issued));
+
+        let err = block_on(sign_command(&config, storage.as_ref(), b"not a csr")).unwrap_err();
+        assert!(err.starts_with("invalid CSR"), "{}", err);
+    }
+
+    #[test]
+    fn aia_serves_issuer_certificate() {
+        let mut config = config();
+        config.save_certificate = true;
+        config.external_url = Some("http://pki.example.com/picky/".to_owned());
+        let storage = block_on(get_storage(&config));
+        block_on(init_storage_from_config(storage.as_ref(), &config)).expect("couldn't init storage");
+        let ca_name = select_ca(&config, None).unwrap();
+
+        let issue = |config: &Config, name: &str, key_pem: &str| {
+            let pk = PrivateKey::from_pem(&key_pem.parse::<Pem>().unwrap()).unwrap();
+            let csr = Csr::generate(
+                DirectoryName::new_common_name(name),
+                &pk,
+                SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_256),
+            )
+            .expect("couldn't generate csr");
+            block_on(sign_certificate(
+                &ca_name,
+                csr.into_verified().unwrap(),
+                config,
+                storage.as_ref(),
+                3600,
+            ))
+            .expect("couldn't sign certificate")
+        };
+        let ca_issuers_url = |leaf: &Cert| {
+            let ca_issuers: Vec<GeneralName> = leaf
+                .authority_info_access()
+                .expect("AIA is missing")
+                .ca_issuers()
+                .cloned()
+                .map(GeneralName::from)
+                .collect();
+            match ca_issuers.as_slice() {
+                [GeneralName::URI(url)] => url.to_string(),
+                unexpected => panic!("unexpected caIssuers: {:?}", unexpected),
+            }
+        };
+
+        // not of the CA key sizes, so that a leaf can't share its subject key identifier with a CA
+        let leaf = issue(&config, "aia.local", crate::test_files::RSA_3072_PK_1);
+        let url = ca_issuers_url(&leaf);
+        let file_name = url
+            .strip_prefix("http://pki.example.com/picky/aia/")
+            .expect("AIA URL isn't derived from the external URL");
+        let issuer_der = block_on(fetch_aia_certificate(storage.as_ref(), file_name)).expect("couldn't fetch issuer");
+        let (intermediate, _) = block_on(load_ca(&ca_name, storage.as_ref())).unwrap();
+        assert_eq!(issuer_der, intermediate.to_der().unwrap());
+
+        let issuer = Cert::from_der(&issuer_der).unwrap();
+        let root_file_name = format!(
+            "{}.cer",
+            hex::encode(issuer.authority_key_identifier().unwrap().key_identifier().unwrap())
+        );
+        let root_der = block_on(fetch_aia_certificate(storage.as_ref(), &root_file_name)).expect("couldn't fetch root");
+        leaf.verifier()
+            .chain([issuer, Cert::from_der(&root_der).unwrap()].iter())
+            .exact_date(&UTCDate::now())
+            .verify()
+            .expect("couldn't verify leaf against the AIA issuer");
+
+        // previous URLs keep answering the previous intermediate after a rotation
+        block_on(issue_intermediate_ca(&ca_name, &config, storage.as_ref())).expect("couldn't rotate intermediate");
+        let rotated_leaf = issue(&config, "rotated.aia.local", crate::test_files::RSA_3072_PK_2);
+        assert_ne!(ca_issuers_url(&rotated_leaf), url);
+        assert_eq!(
+            block_on(fetch_aia_certificate(storage.as_ref(), file_name)).unwrap(),
+            issuer_der
+        );
+
+        // only CA certificates are served
+        let leaf_file_name = format!("{}.cer", hex::encode(leaf.subject_key_identifier().unwrap()));
+        for file_name in &[leaf_file_name.as_str(), "0102.cer", "0102", "zz.cer"] {
+            assert!(block_on(fetch_aia_certificate(storage.as_ref(), file_name)).is_err());
+        }
+
+        // no AIA without an external URL
+        config.external_url = None;
+        let leaf = issue(&config, "no-aia.local", crate::test_files::RSA_3072_PK_1);
+        assert!(leaf.authority_info_access().is_err());
+    }
+
+    #[test]
+    fn ca_export() {
+        let config = config();
+        let storage = block_on(get_storage(&config));
+        block_on(init_storage_from_config(storage.as_ref(), &config)).expect("couldn't init storage");
+        let ca_name = select_ca(&config, None).unwrap();
+        let requester = "api_key:operator";
+
+        // private key is left out unless requested
+        let export =
+            block_on(export_ca(&ca_name, &config, storage.as_ref(), None, requester)).expect("couldn't export CA");
+        assert_eq!(export.name, ca_name);
+        assert!(export.encrypted_key.is_none());
+        assert!(serde_json::to_value(&export).unwrap().get("encrypted_key").is_none());
+        let chain: Vec<Cert> = export
+            .chain
+            .iter()
+            .map(|pem| Cert::from_pem(&pem.parse::<Pem>().unwrap()).expect("chain cert"))
+            .collect();
+        assert_eq!(chain.len(), 2);
+        assert_eq!(chain[0].subject_name().to_string(), format!("CN={}", ca_name));
+        assert_eq!(
+            chain[1].subject_name().to_string(),
+            format!("CN={} Root CA", config.realm)
+        );
+
+        // requested private key is encrypted to the passphrase
+        let passphrase = "correct horse battery staple";
+        let export = block_on(export_ca(
+            &ca_name,
+            &config,
+            storage.as_ref(),
+            Some(passphrase),
+            requester,
+        ))
+        .expect("couldn't export CA with its key");
+        let key_pem = export
+            .encrypted_key
+            .expect("private key is missing")
+            .parse::<Pem>()
+            .unwrap();
+        assert_eq!(key_pem.label(), "ENCRYPTED PRIVATE KEY");
+        assert!(PrivateKey::from_pem(&key_pem).is_err());
+        assert!(PrivateKey::from_encrypted_pem(&key_pem, "wrong passphrase").is_err());
+        let key = PrivateKey::from_encrypted_pem(&key_pem, passphrase).expect("couldn't decrypt private key");
+        let (_, ca_key) = block_on(load_ca(&ca_name, storage.as_ref())).unwrap();
+        assert_eq!(key.to_pkcs8().unwrap(), ca_key.to_pkcs8().unwrap());
+
+        // every export is audited along with its requester
+        let exports: Vec<AuditEntry> = block_on(storage.list_audit(0, 10))
+            .unwrap()
+            .into_iter()
+            .filter(|entry| entry.event == AuditEvent::Exported)
+            .collect();
+        assert_eq!(exports.len(), 2);
+        assert!(exports
+            .iter()
+            .all(|entry| entry.requester == requester && entry.subject.as_deref() == Some(ca_name.as_str())));
+        assert_eq!(exports[0].reason.as_deref(), Some("certificate chain"));
+        assert_eq!(
+            exports[1].reason.as_deref(),
+            Some("certificate chain and encrypted private key")
+        );
+
+        // export-ca command
+        let pems = block_on(export_ca_command(&config, storage.as_ref(), None, Some(passphrase)))
+            .expect("couldn't run export-ca");
+        let labels: Vec<String> = read_pems(pems.as_bytes())
+            .map(|pem| pem.expect("exported pem").label().to_owned())
+            .collect();
+        assert_eq!(labels, ["CERTIFICATE", "CERTIFICATE", "ENCRYPTED PRIVATE KEY"]);
+        let err = block_on(export_ca_command(&config, storage.as_ref(), None, Some(""))).unwrap_err();
+        assert_eq!(err, "the private key can't be exported with an empty passphrase");
+        let err = block_on(export_ca_command(
+            &config,
+            storage.as_ref(),
+            Some("Unknown Authority"),
+            None,
+        ))
+        .unwrap_err();
+        assert_eq!(err, "unknown CA: Unknown Authority");
+        let last = block_on(storage.list_audit(0, 10)).unwrap().pop().unwrap();
+        assert_eq!(
+            (last.event, last.requester.as_str()),
+            (AuditEvent::Exported, CLI_REQUESTER)
+        );
+    }
+
+    #[test]
+    fn ca_export_requires_admin() {
+        let mut config = config();
+        let named = |name: &str, scopes: &[ApiKeyScope]| crate::config::NamedApiKey {
+            name: name.to_owned(),
+            key: format!("{}-secret", name),
+            scopes: scopes.to_vec(),
+        };
+        config.api_keys = vec![
+            named("monitoring", &[ApiKeyScope::Read]),
+            named("operator", &[ApiKeyScope::Admin]),
+        ];
+        let request = |uri: &str, headers: &[(&str, &str)]| {
+            let mut builder = saphir::http::Request::builder().method("GET").uri(uri);
+            for (name, value) in headers {
+                builder = builder.header(*name, *value);
+            }
+            Request::new(builder.body(Body::empty()).expect("request"), None)
+        };
+        let export_request = |api_key: &str| {
+            request(
+                "/ca/export?include_key=true",
+                &[("Picky-Api-Key", api_key), (KEY_PASSPHRASE_HEADER, "passphrase")],
+            )
+        };
+
+        let err = authorize_admin(&config, &export_request("monitoring-secret")).unwrap_err();
+        assert_eq!(err.kind.status(), StatusCode::FORBIDDEN);
+        let err = authorize_admin(&config, &request("/ca/export", &[])).unwrap_err();
+        assert_eq!(err.kind.status(), StatusCode::UNAUTHORIZED);
+
+        let req = export_request("operator-secret");
+        assert_eq!(authorize_admin(&config, &req).expect("admin key refused"), None);
+        assert_eq!(audit::requester(&config, &req, None), "api_key:operator");
+
+        // the passphrase is only needed, and read, when the private key is requested
+        let passphrase = |uri: &str, headers: &[(&str, &str)]| export_key_passphrase(&request(uri, headers));
+        assert_eq!(
+            passphrase("/ca/export", &[(KEY_PASSPHRASE_HEADER, "passphrase")]).unwrap(),
+            None
+        );
+        assert_eq!(passphrase("/ca/export?include_key=false", &[]).unwrap(), None);
+        assert_eq!(
+            passphrase("/ca/export?include_key=true", &[(KEY_PASSPHRASE_HEADER, "passphrase")]).unwrap(),
+            Some("passphrase".to_owned())
+        );
+        let err = passphrase("/ca/export?include_key=true", &[]).unwrap_err();
+        assert_eq!(err.kind.status(), StatusCode::BAD_REQUEST);
+        let err = passphrase("/ca/export?include_key=maybe", &[]).unwrap_err();
+        assert_eq!(err.kind.status(), StatusCode::BAD_REQUEST);
+    }
 }
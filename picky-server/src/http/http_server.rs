@@ -1,39 +1,316 @@
 use crate::{
     config::Config,
-    http::{controller::ServerController, middleware},
+    db::PickyStorage,
+    expiry,
+    http::{
+        controller::{ConfigReloader, ServerController},
+        metrics::{Metrics, MetricsMiddleware},
+        middleware,
+        shutdown::{InFlightMiddleware, InFlightRequests, ShutdownHandle},
+    },
+    utils::PathOr,
 };
 use log4rs::Handle;
-use saphir::server::Server as SaphirServer;
+use saphir::server::{Server as SaphirServer, SslConfig};
+use std::{sync::Arc, time::Duration};
 
 pub struct HttpServer {
     pub server: SaphirServer,
+    storage: Arc<dyn PickyStorage>,
+    reloader: ConfigReloader,
+    in_flight: Arc<InFlightRequests>,
+    shutdown: ShutdownHandle,
+    drain_timeout: Duration,
+    expiry_sweep_interval: Option<Duration>,
+    expired_retention: Option<chrono::Duration>,
 }
 
 impl HttpServer {
     pub async fn new(config: Config, log_handle: Handle) -> Self {
-        let controller = match ServerController::new(config, log_handle).await {
+        let request_log =
+            middleware::RequestLogMiddleware::new(config.request_log_level, config.request_log_excluded_paths.clone());
+        let cors = middleware::CorsMiddleware::new(config.cors_allowed_origins.clone());
+        let metrics = Arc::new(Metrics::new());
+        let listen_address = config.listen_address.clone();
+        let tls = config.tls.clone();
+        let tls_bootstrap = config.tls_bootstrap;
+        let insecure_http = config.insecure_http;
+        let drain_timeout = Duration::from_secs(config.shutdown_drain_timeout_secs);
+        let in_flight = Arc::new(InFlightRequests::default());
+        let expiry_sweep_interval = match config.expiry_sweep_interval_secs {
+            0 => None,
+            secs => Some(Duration::from_secs(secs)),
+        };
+        let expired_retention = config
+            .expired_retention_days
+            .map(|days| chrono::Duration::days(i64::from(days)));
+
+        let controller = match ServerController::new(config, log_handle, metrics.clone()).await {
             Ok(controller) => controller,
             Err(e) => panic!("Couldn't build server controller: {}", e),
         };
+        let storage = controller.storage();
+        let reloader = controller.reloader();
+
+        let ssl_config = if let Some(tls) = tls {
+            Some((ssl_config(tls.certificate_chain), ssl_config(tls.private_key)))
+        } else if tls_bootstrap {
+            match controller.bootstrap_tls_identity().await {
+                Ok((certificate_chain, private_key)) => Some((
+                    ssl_config(PathOr::Some(certificate_chain)),
+                    ssl_config(PathOr::Some(private_key)),
+                )),
+                Err(e) => panic!("Couldn't bootstrap TLS identity: {}", e),
+            }
+        } else if insecure_http {
+            log::warn!("no TLS identity configured, listening on plain HTTP");
+            None
+        } else {
+            panic!("No TLS identity configured: provide a certificate chain and a private key, enable TLS bootstrap or allow plain HTTP with --insecure-http");
+        };
 
         let server = SaphirServer::builder()
             .configure_router(|r| r.controller(controller))
-            .configure_listener(|l| l.interface("0.0.0.0:12345"))
+            .configure_listener(|l| {
+                let l = l.interface(&listen_address);
+                match ssl_config {
+                    Some((certificate_chain, private_key)) => l.set_ssl_config(certificate_chain, private_key),
+                    None => l,
+                }
+            })
             .configure_middlewares(|m| {
-                m.apply(middleware::log_middleware, vec!["/"], None).apply(
-                    middleware::cors_middleware,
-                    vec!["/sign"],
-                    None,
-                )
+                m.apply(InFlightMiddleware::new(in_flight.clone()), vec!["/"], None)
+                    .apply(request_log, vec!["/"], None)
+                    .apply(MetricsMiddleware::new(metrics), vec!["/"], None)
+                    .apply(cors, vec!["/"], None)
             })
             .build();
 
-        HttpServer { server }
+        HttpServer {
+            server,
+            storage,
+            reloader,
+            in_flight,
+            shutdown: ShutdownHandle::default(),
+            drain_timeout,
+            expiry_sweep_interval,
+            expired_retention,
+        }
+    }
+
+    /// Handle stopping the server while `run` is being awaited
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.shutdown.clone()
+    }
+
+    /// Reloads the configuration of the running server
+    pub fn reloader(&self) -> ConfigReloader {
+        self.reloader.clone()
     }
 
+    /// Serves requests until the server fails or a shutdown is requested.
+    /// Expired certificates are swept in the meantime when an expiry sweep interval is configured.
+    ///
+    /// On shutdown, new connections are refused, in-flight requests get up to the drain timeout to complete
+    /// and the storage is closed.
     pub async fn run(self) {
-        if let Err(e) = self.server.run().await {
-            log::error!("{:?}", e);
+        let HttpServer {
+            server,
+            storage,
+            in_flight,
+            shutdown,
+            drain_timeout,
+            reloader: _,
+            expiry_sweep_interval,
+            expired_retention,
+        } = self;
+
+        let expiry_sweeps = async {
+            match expiry_sweep_interval {
+                Some(interval) => expiry::run_expiry_sweeps(storage.as_ref(), interval, expired_retention).await,
+                None => futures::future::pending().await,
+            }
+        };
+
+        tokio::select! {
+            res = server.run() => {
+                if let Err(e) = res {
+                    log::error!("{:?}", e);
+                }
+            }
+            _ = shutdown.requested() => {
+                log::info!("shutting down, {} request(s) in flight", in_flight.count());
+            }
+            _ = expiry_sweeps => {}
+        }
+
+        if tokio::time::timeout(drain_timeout, in_flight.idle()).await.is_err() {
+            log::warn!(
+                "{} request(s) still in flight after {} seconds, stopping anyway",
+                in_flight.count(),
+                drain_timeout.as_secs()
+            );
         }
+
+        if let Err(e) = storage.close().await {
+            log::error!("couldn't close storage: {}", e);
+        }
+
+        log::info!("server stopped");
+    }
+}
+
+fn ssl_config(pem: PathOr<String>) -> SslConfig {
+    match pem {
+        PathOr::Path(path) => SslConfig::FilePath(path.to_string_lossy().into_owned()),
+        // saphir base64-decodes everything between the PEM markers, line breaks included
+        PathOr::Some(pem) => SslConfig::FileData(pem.lines().map(str::trim).collect()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::{BackendType, CertKeyPair},
+        logging::init_logs,
+        picky_controller::Picky,
+    };
+    use picky::{signature::SignatureAlgorithm, x509::KeyIdGenMethod};
+    use std::time::Duration;
+    use tokio_test::block_on;
+
+    #[test]
+    fn https_requests_and_graceful_shutdown() {
+        let signature_algorithm = SignatureAlgorithm::RsaPkcs1v15(picky::hash::HashAlgorithm::SHA2_256);
+        let root_key = Picky::generate_private_key(2048).expect("root key");
+        let root = Picky::generate_root(
+            "Picky Root CA",
+            &root_key,
+            signature_algorithm,
+            KeyIdGenMethod::default(),
+            chrono::Duration::days(3650),
+        )
+        .expect("root");
+        let intermediate_key = Picky::generate_private_key(2048).expect("intermediate key");
+        let intermediate = Picky::generate_intermediate(
+            "Picky Authority",
+            intermediate_key.to_public_key(),
+            &root,
+            &root_key,
+            signature_algorithm,
+            KeyIdGenMethod::default(),
+            chrono::Duration::days(1825),
+        )
+        .expect("intermediate");
+
+        let config = Config {
+            backend: BackendType::Memory,
+            listen_address: "127.0.0.1:12346".to_owned(),
+            root: Some(CertKeyPair {
+                cert: PathOr::Some(root.clone()),
+                key: PathOr::Some(root_key),
+            }),
+            intermediate: Some(CertKeyPair {
+                cert: PathOr::Some(intermediate),
+                key: PathOr::Some(intermediate_key),
+            }),
+            tls_bootstrap: true,
+            shutdown_drain_timeout_secs: 2,
+            cors_allowed_origins: vec!["https://dashboard.example.com".to_owned()],
+            ..Default::default()
+        };
+
+        block_on(async {
+            let log_handle = init_logs(&config);
+            let server = HttpServer::new(config, log_handle).await;
+            let shutdown = server.shutdown_handle();
+            let running = tokio::spawn(server.run());
+
+            let root_pem = root.to_pem().expect("root pem").to_string();
+            let client = reqwest::Client::builder()
+                .add_root_certificate(reqwest::Certificate::from_pem(root_pem.as_bytes()).expect("root certificate"))
+                .build()
+                .expect("client");
+
+            let mut response = None;
+            for _ in 0..50 {
+                match client.get("https://localhost:12346/health").send().await {
+                    Ok(res) => {
+                        response = Some(res);
+                        break;
+                    }
+                    Err(_) => tokio::time::delay_for(Duration::from_millis(100)).await,
+                }
+            }
+
+            let response = response.expect("server didn't answer over TLS");
+            assert_eq!(response.status(), reqwest::StatusCode::OK);
+            // no Origin header, not a CORS request
+            assert!(response
+                .headers()
+                .keys()
+                .all(|name| !name.as_str().starts_with("access-control-") && name != "vary"));
+            let body = response.text().await.expect("health body");
+            let health: serde_json::Value = serde_json::from_str(&body).expect("health json");
+            assert_eq!(health["status"], "ok");
+
+            let preflight = |origin: &'static str| {
+                client
+                    .request(reqwest::Method::OPTIONS, "https://localhost:12346/sign")
+                    .header("Origin", origin)
+                    .header("Access-Control-Request-Method", "POST")
+                    .header("Access-Control-Request-Headers", "authorization, content-type")
+                    .send()
+            };
+
+            let allowed = preflight("https://dashboard.example.com").await.expect("preflight");
+            assert_eq!(allowed.status(), reqwest::StatusCode::NO_CONTENT);
+            let headers = allowed.headers();
+            assert_eq!(headers["Access-Control-Allow-Origin"], "https://dashboard.example.com");
+            assert_eq!(headers["Access-Control-Allow-Methods"], "POST");
+            let allowed_headers = headers["Access-Control-Allow-Headers"].to_str().unwrap();
+            assert!(allowed_headers.contains("Authorization"));
+            assert!(allowed_headers.contains("Content-Type"));
+            assert_eq!(headers["Access-Control-Max-Age"], "3600");
+
+            let denied = preflight("https://evil.example.com").await.expect("denied preflight");
+            assert_eq!(denied.status(), reqwest::StatusCode::FORBIDDEN);
+            assert!(denied.headers().get("Access-Control-Allow-Origin").is_none());
+
+            let cross_origin = client
+                .get("https://localhost:12346/health")
+                .header("Origin", "https://dashboard.example.com")
+                .send()
+                .await
+                .expect("cross-origin request");
+            assert_eq!(cross_origin.status(), reqwest::StatusCode::OK);
+            assert_eq!(
+                cross_origin.headers()["Access-Control-Allow-Origin"],
+                "https://dashboard.example.com"
+            );
+
+            let oversized = tokio::time::timeout(
+                Duration::from_secs(5),
+                client
+                    .post("https://localhost:12346/cert")
+                    .header("Content-Type", "text/plain")
+                    .body(vec![b'A'; 1024 * 1024])
+                    .send(),
+            )
+            .await
+            .expect("oversized body wasn't refused quickly")
+            .expect("oversized body request");
+            assert_eq!(oversized.status(), reqwest::StatusCode::PAYLOAD_TOO_LARGE);
+            let body = oversized.text().await.expect("error body");
+            let error: serde_json::Value = serde_json::from_str(&body).expect("error json");
+            assert_eq!(error["kind"], "payload_too_large");
+
+            shutdown.shutdown();
+            tokio::time::timeout(Duration::from_secs(3), running)
+                .await
+                .expect("server didn't stop within the drain timeout")
+                .expect("server task");
+        });
     }
 }
@@ -0,0 +1,238 @@
+use saphir::prelude::*;
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    sync::{
+        atomic::{AtomicI64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+/// Counter split by label values
+#[derive(Debug, Default)]
+struct LabeledCounter {
+    values: Mutex<BTreeMap<Vec<String>, u64>>,
+}
+
+impl LabeledCounter {
+    fn inc(&self, label_values: &[&str]) {
+        let mut values = self.values.lock().expect("couldn't lock counter (poisoned)");
+        let key = label_values.iter().map(|value| (*value).to_owned()).collect();
+        *values.entry(key).or_insert(0) += 1;
+    }
+
+    #[cfg(test)]
+    fn get(&self, label_values: &[&str]) -> u64 {
+        let key = label_values.iter().map(|value| (*value).to_owned()).collect::<Vec<_>>();
+        self.values
+            .lock()
+            .expect("couldn't lock counter (poisoned)")
+            .get(&key)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    fn render(&self, out: &mut String, name: &str, help: &str, label_names: &[&str]) {
+        let _ = writeln!(out, "# HELP {} {}", name, help);
+        let _ = writeln!(out, "# TYPE {} counter", name);
+        let values = self.values.lock().expect("couldn't lock counter (poisoned)");
+        for (label_values, value) in values.iter() {
+            let labels = label_names
+                .iter()
+                .zip(label_values.iter())
+                .map(|(name, value)| format!("{}=\"{}\"", name, escape_label_value(value)))
+                .collect::<Vec<_>>()
+                .join(",");
+            let _ = writeln!(out, "{}{{{}}} {}", name, labels, value);
+        }
+    }
+}
+
+/// Metrics exposed in Prometheus text format on `GET /metrics`
+#[derive(Debug, Default)]
+pub struct Metrics {
+    certificates_issued: LabeledCounter,
+    certificates_registered: LabeledCounter,
+    csrs_rejected: LabeledCounter,
+    http_requests: LabeledCounter,
+    intermediate_expiry_days: AtomicI64,
+    backend_up: AtomicI64,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Signing attempts, `outcome` being "success", "rejected" or "error"
+    pub fn inc_certificates_issued(&self, outcome: &str) {
+        self.certificates_issued.inc(&[outcome]);
+    }
+
+    #[cfg(test)]
+    pub fn certificates_issued(&self, outcome: &str) -> u64 {
+        self.certificates_issued.get(&[outcome])
+    }
+
+    /// Certificates pushed on `POST /cert`, `outcome` being "success", "rejected" or "error"
+    pub fn inc_certificates_registered(&self, outcome: &str) {
+        self.certificates_registered.inc(&[outcome]);
+    }
+
+    pub fn inc_csrs_rejected(&self, reason: &str) {
+        self.csrs_rejected.inc(&[reason]);
+    }
+
+    #[cfg(test)]
+    pub fn csrs_rejected(&self, reason: &str) -> u64 {
+        self.csrs_rejected.get(&[reason])
+    }
+
+    pub fn inc_http_requests(&self, method: &str, path: &str, status: u16) {
+        self.http_requests
+            .inc(&[method, &route_label(path), &status.to_string()]);
+    }
+
+    pub fn set_intermediate_expiry_days(&self, days: i64) {
+        self.intermediate_expiry_days.store(days, Ordering::Relaxed);
+    }
+
+    pub fn set_backend_up(&self, up: bool) {
+        self.backend_up.store(i64::from(up), Ordering::Relaxed);
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+
+        self.certificates_issued.render(
+            &mut out,
+            "picky_certificates_issued_total",
+            "Certificate signing requests processed, by outcome.",
+            &["outcome"],
+        );
+        self.certificates_registered.render(
+            &mut out,
+            "picky_certificates_registered_total",
+            "Certificates pushed to the server, by outcome.",
+            &["outcome"],
+        );
+        self.csrs_rejected.render(
+            &mut out,
+            "picky_csrs_rejected_total",
+            "Certificate signing requests rejected, by reason.",
+            &["reason"],
+        );
+        self.http_requests.render(
+            &mut out,
+            "picky_http_requests_total",
+            "HTTP requests handled, by method, route and status.",
+            &["method", "route", "status"],
+        );
+
+        let _ = writeln!(
+            out,
+            "# HELP picky_intermediate_expiry_days Days until the intermediate CA expires."
+        );
+        let _ = writeln!(out, "# TYPE picky_intermediate_expiry_days gauge");
+        let _ = writeln!(
+            out,
+            "picky_intermediate_expiry_days {}",
+            self.intermediate_expiry_days.load(Ordering::Relaxed)
+        );
+
+        let _ = writeln!(out, "# HELP picky_backend_up Whether the storage backend is reachable.");
+        let _ = writeln!(out, "# TYPE picky_backend_up gauge");
+        let _ = writeln!(out, "picky_backend_up {}", self.backend_up.load(Ordering::Relaxed));
+
+        out
+    }
+}
+
+/// Route template of a request path, keeping label cardinality bounded
+fn route_label(path: &str) -> String {
+    let mut segments = path.split('/').filter(|segment| !segment.is_empty());
+    match (segments.next(), segments.next()) {
+        (None, _) => "/".to_owned(),
+        (Some(first), None) => format!("/{}", first),
+        (Some("cert"), Some("name")) => "/cert/name/<name>".to_owned(),
+        (Some("cert"), Some("ski")) => "/cert/ski/<ski>".to_owned(),
+        (Some("health"), Some("live")) => "/health/live".to_owned(),
+        (Some("health"), Some("ready")) => "/health/ready".to_owned(),
+        (Some("ca"), Some("rotate-intermediate")) => "/ca/rotate-intermediate".to_owned(),
+        (Some("ca"), Some("export")) => "/ca/export".to_owned(),
+        (Some(".well-known"), Some("est")) => match segments.next() {
+            Some(operation @ "cacerts") | Some(operation @ "simpleenroll") => format!("/.well-known/est/{}", operation),
+            _ => "/.well-known/est/<param>".to_owned(),
+        },
+        (Some(first), Some(_)) => format!("/{}/<param>", first),
+    }
+}
+
+fn escape_label_value(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// Counts handled requests by method, route and status
+pub struct MetricsMiddleware {
+    metrics: Arc<Metrics>,
+}
+
+impl MetricsMiddleware {
+    pub fn new(metrics: Arc<Metrics>) -> Self {
+        Self { metrics }
+    }
+}
+
+#[middleware]
+impl MetricsMiddleware {
+    async fn next(&self, ctx: HttpContext, chain: &dyn MiddlewareChain) -> Result<HttpContext, SaphirError> {
+        let req = ctx.state.request().unwrap(); // should not panic because this is before the chain.next(..) call
+        let path = req.uri().path().to_owned();
+        let method = req.method().to_owned();
+
+        let ctx = chain.next(ctx).await?;
+
+        let res = ctx.state.response().unwrap(); // should not panic because this is after the chain.next(..) call
+        self.metrics
+            .inc_http_requests(method.as_str(), &path, res.status().as_u16());
+
+        Ok(ctx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_prometheus_text() {
+        let metrics = Metrics::new();
+        metrics.inc_certificates_issued("success");
+        metrics.inc_certificates_issued("success");
+        metrics.inc_csrs_rejected("csr_min_rsa_key_size");
+        metrics.inc_http_requests("GET", "/cert/uEiCcvAfD-ZFyWDajqipYHKICkZiqQgudmbwOEx2fPiy-Rw", 200);
+        metrics.inc_http_requests("GET", "/health/ready/", 503);
+        metrics.inc_http_requests("GET", "/health/live", 200);
+        metrics.inc_http_requests("POST", "/.well-known/est/simpleenroll", 200);
+        metrics.set_intermediate_expiry_days(512);
+        metrics.set_backend_up(true);
+
+        let text = metrics.render();
+        assert!(text.contains("# TYPE picky_certificates_issued_total counter\n"));
+        assert!(text.contains("picky_certificates_issued_total{outcome=\"success\"} 2\n"));
+        assert!(text.contains("picky_csrs_rejected_total{reason=\"csr_min_rsa_key_size\"} 1\n"));
+        assert!(text.contains("picky_http_requests_total{method=\"GET\",route=\"/cert/<param>\",status=\"200\"} 1\n"));
+        assert!(text.contains("picky_http_requests_total{method=\"GET\",route=\"/health/ready\",status=\"503\"} 1\n"));
+        assert!(text.contains("picky_http_requests_total{method=\"GET\",route=\"/health/live\",status=\"200\"} 1\n"));
+        assert!(text.contains(
+            "picky_http_requests_total{method=\"POST\",route=\"/.well-known/est/simpleenroll\",status=\"200\"} 1\n"
+        ));
+        assert!(text.contains("picky_intermediate_expiry_days 512\n"));
+        assert!(text.contains("picky_backend_up 1\n"));
+    }
+
+    #[test]
+    fn label_values_are_escaped() {
+        assert_eq!(escape_label_value("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+    }
+}
@@ -1,28 +1,356 @@
-use saphir::prelude::*;
+use log::LevelFilter;
+use saphir::{
+    http::{
+        header::{self, HeaderValue},
+        Method, StatusCode,
+    },
+    http_context::State,
+    prelude::*,
+    response::Builder as ResponseBuilder,
+};
+use std::{
+    future::Future,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
 
-pub async fn log_middleware(ctx: HttpContext, chain: &dyn MiddlewareChain) -> Result<HttpContext, SaphirError> {
-    let req = ctx.state.request().unwrap(); // should not panic because this is before the chain.next(..) call
-    let uri = req.uri().path().to_owned();
-    let method = req.method().to_owned();
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
 
-    let ctx = chain.next(ctx).await?;
+/// Incoming request IDs longer than this are replaced by a generated one
+const MAX_REQUEST_ID_LEN: usize = 128;
 
-    let res = ctx.state.response().unwrap(); // should not panic because this is after the chain.next(..) call
-    log::info!("{} {} {}", method, uri, res.status());
+/// Request being handled by the current task
+struct RequestContext {
+    id: String,
+    /// `<method> <path>`
+    route: String,
+}
+
+tokio::task_local! {
+    static REQUEST: RequestContext;
+}
+
+/// Runs `future` as the handling of the given request
+pub async fn in_request_scope<F: Future>(id: String, route: String, future: F) -> F::Output {
+    REQUEST.scope(RequestContext { id, route }, future).await
+}
+
+/// ID of the request being handled by the current task, if any
+pub fn current_request_id() -> Option<String> {
+    REQUEST.try_with(|request| request.id.clone()).ok()
+}
+
+/// Method and path of the request being handled by the current task, if any
+pub fn current_route() -> Option<String> {
+    REQUEST.try_with(|request| request.route.clone()).ok()
+}
+
+/// `[<request id>] ` when handling a request, to correlate error logs with the request log
+pub fn request_log_prefix() -> String {
+    current_request_id().map(|id| format!("[{}] ", id)).unwrap_or_default()
+}
+
+/// Keeps the incoming request ID when it is printable ASCII of a sensible length, generates one otherwise
+pub fn resolve_request_id(incoming: Option<&str>) -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    match incoming {
+        Some(id) if !id.is_empty() && id.len() <= MAX_REQUEST_ID_LEN && id.bytes().all(|b| b.is_ascii_graphic()) => {
+            id.to_owned()
+        }
+        _ => {
+            let nanos = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_nanos())
+                .unwrap_or(0);
+            format!(
+                "{:x}-{:x}-{:x}",
+                std::process::id(),
+                nanos,
+                COUNTER.fetch_add(1, Ordering::Relaxed)
+            )
+        }
+    }
+}
+
+/// Assigns an ID to each request and logs it on completion
+pub struct RequestLogMiddleware {
+    level: LevelFilter,
+    excluded_paths: Vec<String>,
+}
+
+impl RequestLogMiddleware {
+    pub fn new(level: LevelFilter, excluded_paths: Vec<String>) -> Self {
+        Self { level, excluded_paths }
+    }
 
-    Ok(ctx)
+    fn is_excluded(&self, path: &str) -> bool {
+        let path = path.trim_end_matches('/');
+        self.excluded_paths.iter().any(|excluded| {
+            let excluded = excluded.trim_end_matches('/');
+            path == excluded || path.starts_with(&format!("{}/", excluded))
+        })
+    }
 }
 
-pub async fn cors_middleware(ctx: HttpContext, chain: &dyn MiddlewareChain) -> Result<HttpContext, SaphirError> {
-    let req = ctx.state.request().unwrap(); // should not panic because this is before chain.next(..) call
-    let origin_header = req.headers().get("Origin").cloned();
+#[middleware]
+impl RequestLogMiddleware {
+    async fn next(&self, mut ctx: HttpContext, chain: &dyn MiddlewareChain) -> Result<HttpContext, SaphirError> {
+        let start = Instant::now();
 
-    let mut ctx = chain.next(ctx).await?;
+        // saphir's request_mut only lends the request immutably
+        let req = ctx.state.request_unchecked_mut(); // should not panic because this is before the chain.next(..) call
+        let uri = req.uri().path().to_owned();
+        let method = req.method().to_owned();
+        let request_id = resolve_request_id(
+            req.headers()
+                .get(REQUEST_ID_HEADER)
+                .and_then(|header| header.to_str().ok()),
+        );
+        let header_value = HeaderValue::from_str(&request_id).expect("request ID is a valid header value");
+        // makes the ID available to the controller
+        req.headers_mut().insert(REQUEST_ID_HEADER, header_value.clone());
 
-    if let Some(origin_header) = origin_header {
-        let res = ctx.state.response_mut().unwrap(); // should not panic because this is after chain.next(..) call
-        res.headers_mut().insert("Access-Control-Allow-Origin", origin_header);
+        let route = format!("{} {}", method, uri);
+        let mut ctx = in_request_scope(request_id.clone(), route, chain.next(ctx)).await?;
+
+        let res = ctx.state.response_mut().unwrap(); // should not panic because this is after the chain.next(..) call
+        res.headers_mut().insert(REQUEST_ID_HEADER, header_value);
+
+        if let Some(level) = self.level.to_level() {
+            if !self.is_excluded(&uri) {
+                let body_size = res
+                    .headers()
+                    .get("Content-Length")
+                    .and_then(|header| header.to_str().ok())
+                    .unwrap_or("-");
+                log::log!(
+                    level,
+                    "[{}] {} {} {} {}ms {}",
+                    request_id,
+                    method,
+                    uri,
+                    res.status(),
+                    start.elapsed().as_millis(),
+                    body_size
+                );
+            }
+        }
+
+        Ok(ctx)
     }
+}
+
+/// How long browsers may cache a preflight response
+const CORS_MAX_AGE_SECS: u64 = 3600;
+
+/// Request headers browser clients may send, the API key and the request payload types included
+const CORS_ALLOWED_HEADERS: &str = "Authorization, Content-Type, Picky-Api-Key, Picky-Key-Passphrase, X-Request-Id";
+
+/// Response headers browser clients may read
+const CORS_EXPOSED_HEADERS: &str = "Location, X-Request-Id";
+
+/// Routes of the controller with their methods, `<...>` segments matching any value
+const CORS_ROUTES: &[(&str, &str)] = &[
+    ("/health", "GET"),
+    ("/health/live", "GET"),
+    ("/health/ready", "GET"),
+    ("/metrics", "GET"),
+    ("/cert", "POST"),
+    ("/sign", "POST"),
+    ("/.well-known/est/cacerts", "GET"),
+    ("/.well-known/est/simpleenroll", "POST"),
+    ("/name", "POST"),
+    ("/cert/<multihash>", "GET, DELETE"),
+    ("/certs", "GET"),
+    ("/cert/ski/<ski>", "GET"),
+    ("/cert/name/<name>", "GET"),
+    ("/audit", "GET"),
+    ("/cas", "GET"),
+    ("/ca", "POST"),
+    ("/ca/rotate-intermediate", "POST"),
+    ("/ca/export", "GET"),
+    ("/aia/<file_name>", "GET"),
+    ("/chain", "GET"),
+    ("/chain/<ca>", "GET"),
+    ("/crl", "GET"),
+    ("/crl/<ca>", "GET"),
+    ("/ocsp", "POST"),
+    ("/ocsp/<encoded_request>", "GET"),
+    ("/reload", "GET"),
+];
+
+/// Methods allowed on the route matching `path`
+fn route_methods(path: &str) -> Option<&'static str> {
+    let path = path.trim_end_matches('/');
+    CORS_ROUTES.iter().find_map(|(route, methods)| {
+        let mut route_segments = route.split('/');
+        let mut path_segments = path.split('/');
+        loop {
+            match (route_segments.next(), path_segments.next()) {
+                (None, None) => return Some(*methods),
+                (Some(route_segment), Some(path_segment))
+                    if route_segment == path_segment
+                        || (route_segment.starts_with('<') && !path_segment.is_empty()) => {}
+                _ => return None,
+            }
+        }
+    })
+}
+
+/// Answers CORS preflight requests and allows browsers to read responses for the configured origins.
+/// Requests without an `Origin` header are left untouched, as is everything when no origin is configured.
+pub struct CorsMiddleware {
+    allowed_origins: Vec<String>,
+}
 
-    Ok(ctx)
+impl CorsMiddleware {
+    pub fn new(allowed_origins: Vec<String>) -> Self {
+        Self { allowed_origins }
+    }
+
+    fn is_enabled(&self) -> bool {
+        !self.allowed_origins.is_empty()
+    }
+
+    fn allows(&self, origin: &str) -> bool {
+        self.allowed_origins
+            .iter()
+            .any(|allowed| allowed == "*" || allowed == origin)
+    }
+
+    /// Methods to advertise in the preflight response, or the status refusing it
+    fn preflight(&self, path: &str, origin: &str) -> Result<&'static str, StatusCode> {
+        let methods = route_methods(path).ok_or(StatusCode::NOT_FOUND)?;
+        if self.allows(origin) {
+            Ok(methods)
+        } else {
+            Err(StatusCode::FORBIDDEN)
+        }
+    }
+}
+
+#[middleware]
+impl CorsMiddleware {
+    async fn next(&self, mut ctx: HttpContext, chain: &dyn MiddlewareChain) -> Result<HttpContext, SaphirError> {
+        let req = ctx.state.request().unwrap(); // should not panic because this is before the chain.next(..) call
+        let origin = match req.headers().get(header::ORIGIN) {
+            Some(origin) if self.is_enabled() => origin.clone(),
+            _ => return chain.next(ctx).await,
+        };
+        let origin_str = origin.to_str().unwrap_or_default();
+
+        if req.method() == Method::OPTIONS && req.headers().contains_key(header::ACCESS_CONTROL_REQUEST_METHOD) {
+            let response = match self.preflight(req.uri().path(), origin_str) {
+                Ok(methods) => ResponseBuilder::new()
+                    .status(StatusCode::NO_CONTENT)
+                    .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin)
+                    .header(header::ACCESS_CONTROL_ALLOW_METHODS, methods)
+                    .header(header::ACCESS_CONTROL_ALLOW_HEADERS, CORS_ALLOWED_HEADERS)
+                    .header(header::ACCESS_CONTROL_MAX_AGE, CORS_MAX_AGE_SECS.to_string())
+                    .header(header::VARY, "Origin"),
+                Err(status) => ResponseBuilder::new().status(status).header(header::VARY, "Origin"),
+            };
+            ctx.state = State::After(Box::new(response.build()?));
+            return Ok(ctx);
+        }
+
+        let allowed = self.allows(origin_str);
+        let mut ctx = chain.next(ctx).await?;
+
+        let res = ctx.state.response_mut().unwrap(); // should not panic because this is after the chain.next(..) call
+        let headers = res.headers_mut();
+        headers.append(header::VARY, HeaderValue::from_static("Origin"));
+        if allowed {
+            headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+            headers.insert(
+                header::ACCESS_CONTROL_EXPOSE_HEADERS,
+                HeaderValue::from_static(CORS_EXPOSED_HEADERS),
+            );
+        }
+
+        Ok(ctx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_test::block_on;
+
+    #[test]
+    fn supplied_request_id_is_kept() {
+        assert_eq!(resolve_request_id(Some("d3b07384-d9a0")), "d3b07384-d9a0");
+    }
+
+    #[test]
+    fn invalid_request_id_is_replaced() {
+        let generated = resolve_request_id(None);
+        assert!(!generated.is_empty());
+        assert_ne!(generated, resolve_request_id(None));
+
+        assert_ne!(resolve_request_id(Some("")), "");
+        assert_ne!(resolve_request_id(Some("two words")), "two words");
+        let too_long = "a".repeat(MAX_REQUEST_ID_LEN + 1);
+        assert_ne!(resolve_request_id(Some(&too_long)), too_long);
+    }
+
+    #[test]
+    fn request_id_available_in_scope() {
+        assert_eq!(current_request_id(), None);
+        let (id, route) = block_on(in_request_scope(
+            "d3b07384".to_owned(),
+            "GET /chain".to_owned(),
+            async { (current_request_id(), current_route()) },
+        ));
+        assert_eq!(id.as_deref(), Some("d3b07384"));
+        assert_eq!(route.as_deref(), Some("GET /chain"));
+    }
+
+    #[test]
+    fn excluded_paths() {
+        let middleware = RequestLogMiddleware::new(LevelFilter::Info, vec!["/health".to_owned()]);
+        assert!(middleware.is_excluded("/health"));
+        assert!(middleware.is_excluded("/health/"));
+        assert!(middleware.is_excluded("/health/ready"));
+        assert!(!middleware.is_excluded("/healthz"));
+        assert!(!middleware.is_excluded("/sign"));
+    }
+
+    #[test]
+    fn cors_routes() {
+        assert_eq!(route_methods("/sign"), Some("POST"));
+        assert_eq!(route_methods("/sign/"), Some("POST"));
+        assert_eq!(
+            route_methods("/cert/uEiCcvAfD-ZFyWDajqipYHKICkZiqQgudmbwOEx2fPiy-Rw"),
+            Some("GET, DELETE")
+        );
+        assert_eq!(route_methods("/cert/ski/0a1b"), Some("GET"));
+        assert_eq!(route_methods("/cert"), Some("POST"));
+        assert_eq!(route_methods("/signcert"), None);
+        assert_eq!(route_methods("/cert/a/b/c"), None);
+    }
+
+    #[test]
+    fn cors_preflight() {
+        let cors = CorsMiddleware::new(vec!["https://dashboard.example.com".to_owned()]);
+        assert_eq!(cors.preflight("/sign", "https://dashboard.example.com"), Ok("POST"));
+        assert_eq!(
+            cors.preflight("/sign", "https://evil.example.com"),
+            Err(StatusCode::FORBIDDEN)
+        );
+        assert_eq!(
+            cors.preflight("/sign", "https://dashboard.example.com.evil.example.com"),
+            Err(StatusCode::FORBIDDEN)
+        );
+        assert_eq!(
+            cors.preflight("/unknown", "https://dashboard.example.com"),
+            Err(StatusCode::NOT_FOUND)
+        );
+
+        let any = CorsMiddleware::new(vec!["*".to_owned()]);
+        assert_eq!(any.preflight("/chain/Picky", "https://evil.example.com"), Ok("GET"));
+
+        assert!(!CorsMiddleware::new(Vec::new()).is_enabled());
+    }
 }
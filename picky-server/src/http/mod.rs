@@ -1,5 +1,9 @@
+pub mod audit;
 pub mod authorization;
 pub mod controller;
 pub mod http_server;
+pub mod metrics;
 pub mod middleware;
+pub mod rate_limit;
+pub mod shutdown;
 pub mod utils;
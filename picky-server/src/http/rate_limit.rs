@@ -0,0 +1,195 @@
+use saphir::request::Request;
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Number of tracked clients above which the least recently seen one is forgotten
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+/// Limits the rate at which a client may use an endpoint.
+///
+/// Implementations keep their state wherever they see fit (in-process, shared cache…).
+pub trait RateLimiter: Send + Sync {
+    /// Consumes one request for this client, returning how long to wait when the limit is reached
+    fn check(&self, client_key: &str) -> Result<(), Duration>;
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    last_seen: u64,
+}
+
+/// Buckets of the tracked clients, along with the order they were last seen in
+#[derive(Default)]
+struct Buckets {
+    clock: u64,
+    by_client: HashMap<String, Bucket>,
+    by_last_seen: BTreeMap<u64, String>,
+}
+
+impl Buckets {
+    /// Bucket of this client, a full one when it isn't tracked yet
+    fn touch(&mut self, client_key: &str, now: Instant, burst: f64) -> &mut Bucket {
+        self.clock += 1;
+        let clock = self.clock;
+
+        match self.by_client.get(client_key).map(|bucket| bucket.last_seen) {
+            Some(last_seen) => {
+                self.by_last_seen.remove(&last_seen);
+            }
+            None if self.by_client.len() >= MAX_TRACKED_CLIENTS => {
+                let least_recently_seen = self.by_last_seen.keys().next().copied();
+                if let Some(client) = least_recently_seen.and_then(|seen| self.by_last_seen.remove(&seen)) {
+                    self.by_client.remove(&client);
+                }
+            }
+            None => {}
+        }
+        self.by_last_seen.insert(clock, client_key.to_owned());
+
+        let bucket = self.by_client.entry(client_key.to_owned()).or_insert(Bucket {
+            tokens: burst,
+            updated: now,
+            last_seen: clock,
+        });
+        bucket.last_seen = clock;
+        bucket
+    }
+}
+
+/// In-process token bucket rate limiter: each client may send `burst` requests at once,
+/// then requests are allowed at the sustained `rate_per_minute`.
+pub struct TokenBucketRateLimiter {
+    rate_per_sec: f64,
+    burst: f64,
+    buckets: Mutex<Buckets>,
+}
+
+impl TokenBucketRateLimiter {
+    pub fn new(rate_per_minute: u32, burst: u32) -> Self {
+        Self {
+            rate_per_sec: f64::from(rate_per_minute) / 60.0,
+            burst: f64::from(burst.max(1)),
+            buckets: Mutex::new(Buckets::default()),
+        }
+    }
+
+    fn check_at(&self, client_key: &str, now: Instant) -> Result<(), Duration> {
+        let mut buckets = self
+            .buckets
+            .lock()
+            .expect("couldn't lock rate limiter buckets (poisoned)");
+
+        let bucket = buckets.touch(client_key, now, self.burst);
+        refill(bucket, now, self.rate_per_sec, self.burst);
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else if self.rate_per_sec > 0.0 {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / self.rate_per_sec))
+        } else {
+            Err(Duration::from_secs(u64::from(u32::MAX)))
+        }
+    }
+}
+
+fn refill(bucket: &mut Bucket, now: Instant, rate_per_sec: f64, burst: f64) {
+    let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+    bucket.tokens = (bucket.tokens + elapsed * rate_per_sec).min(burst);
+    bucket.updated = now;
+}
+
+impl RateLimiter for TokenBucketRateLimiter {
+    fn check(&self, client_key: &str) -> Result<(), Duration> {
+        self.check_at(client_key, Instant::now())
+    }
+}
+
+/// Key identifying the client of a request: the principal it authenticated as (e.g. the name of its API key)
+/// when it did, its IP address otherwise.
+///
+/// Credentials that didn't authenticate are ignored, made-up ones would otherwise get a fresh bucket each.
+pub fn client_key<T>(req: &Request<T>, principal: Option<&str>) -> String {
+    match (principal, req.peer_addr()) {
+        (Some(principal), _) => format!("principal:{}", principal),
+        (None, Some(peer_addr)) => format!("ip:{}", peer_addr.ip()),
+        (None, None) => "anonymous".to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn burst_then_sustained_rate() {
+        let limiter = TokenBucketRateLimiter::new(60, 3);
+        let start = Instant::now();
+
+        for _ in 0..3 {
+            assert!(limiter.check_at("client", start).is_ok());
+        }
+        let retry_after = limiter.check_at("client", start).unwrap_err();
+        assert_eq!(retry_after, Duration::from_secs(1));
+        assert!(limiter.check_at("client", start + Duration::from_millis(500)).is_err());
+
+        // other clients have their own bucket
+        assert!(limiter.check_at("other client", start).is_ok());
+
+        // one request per second is allowed once the burst is consumed
+        assert!(limiter.check_at("client", start + Duration::from_secs(1)).is_ok());
+        assert!(limiter.check_at("client", start + Duration::from_secs(1)).is_err());
+
+        // bucket is refilled up to the burst
+        let later = start + Duration::from_secs(60);
+        for _ in 0..3 {
+            assert!(limiter.check_at("client", later).is_ok());
+        }
+        assert!(limiter.check_at("client", later).is_err());
+    }
+
+    #[test]
+    fn client_keys() {
+        use crate::http::authorization::API_KEY_HEADER;
+        use saphir::prelude::Body;
+
+        let request = |peer_addr: Option<&str>| {
+            let builder = saphir::http::Request::builder().header(API_KEY_HEADER, "made-up-api-key");
+            Request::new(
+                builder.body(Body::empty()).expect("request"),
+                peer_addr.map(|addr| addr.parse().expect("peer address")),
+            )
+        };
+
+        assert_eq!(
+            client_key(&request(Some("192.0.2.1:443")), Some("api_key:monitoring")),
+            "principal:api_key:monitoring"
+        );
+        // unauthenticated credentials don't tell clients apart
+        assert_eq!(client_key(&request(Some("192.0.2.1:443")), None), "ip:192.0.2.1");
+        assert_eq!(client_key(&request(Some("192.0.2.1:8443")), None), "ip:192.0.2.1");
+        assert_eq!(client_key(&request(None), None), "anonymous");
+    }
+
+    #[test]
+    fn least_recently_seen_clients_are_forgotten() {
+        let limiter = TokenBucketRateLimiter::new(60, 1);
+        let start = Instant::now();
+
+        for i in 0..MAX_TRACKED_CLIENTS {
+            limiter.check_at(&i.to_string(), start).unwrap();
+        }
+        assert!(limiter.check_at("0", start).is_err());
+
+        assert!(limiter.check_at("client", start).is_ok());
+        let buckets = limiter.buckets.lock().unwrap();
+        assert_eq!(buckets.by_client.len(), MAX_TRACKED_CLIENTS);
+        assert_eq!(buckets.by_last_seen.len(), MAX_TRACKED_CLIENTS);
+        assert!(!buckets.by_client.contains_key("1"));
+        assert!(buckets.by_client.contains_key("0"));
+    }
+}
@@ -0,0 +1,127 @@
+use saphir::prelude::*;
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+use tokio::sync::Notify;
+
+/// Requests the server to stop: new connections are refused and in-flight requests are drained
+#[derive(Clone, Default)]
+pub struct ShutdownHandle {
+    notify: Arc<Notify>,
+}
+
+impl ShutdownHandle {
+    pub fn shutdown(&self) {
+        self.notify.notify();
+    }
+
+    pub(crate) async fn requested(&self) {
+        self.notify.notified().await;
+    }
+}
+
+/// Completes on SIGINT (Ctrl-C) or, on unix, SIGTERM
+pub async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut terminate = signal(SignalKind::terminate()).expect("couldn't listen to SIGTERM");
+        tokio::select! {
+            _ = terminate.recv() => {}
+            _ = tokio::signal::ctrl_c() => {}
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
+
+/// Number of requests being handled
+#[derive(Debug, Default)]
+pub struct InFlightRequests {
+    count: AtomicUsize,
+    idle: Notify,
+}
+
+impl InFlightRequests {
+    fn start(self: &Arc<Self>) -> InFlightGuard {
+        self.count.fetch_add(1, Ordering::SeqCst);
+        InFlightGuard {
+            requests: Arc::clone(self),
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.count.load(Ordering::SeqCst)
+    }
+
+    /// Completes once no request is being handled
+    pub async fn idle(&self) {
+        while self.count() > 0 {
+            self.idle.notified().await;
+        }
+    }
+}
+
+struct InFlightGuard {
+    requests: Arc<InFlightRequests>,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        if self.requests.count.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.requests.idle.notify();
+        }
+    }
+}
+
+/// Keeps track of in-flight requests so that shutdown can wait for them
+pub struct InFlightMiddleware {
+    requests: Arc<InFlightRequests>,
+}
+
+impl InFlightMiddleware {
+    pub fn new(requests: Arc<InFlightRequests>) -> Self {
+        Self { requests }
+    }
+}
+
+#[middleware]
+impl InFlightMiddleware {
+    async fn next(&self, ctx: HttpContext, chain: &dyn MiddlewareChain) -> Result<HttpContext, SaphirError> {
+        let _guard = self.requests.start();
+        chain.next(ctx).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio_test::block_on;
+
+    #[test]
+    fn idle_once_requests_are_done() {
+        block_on(async {
+            let requests = Arc::new(InFlightRequests::default());
+            requests.idle().await;
+
+            let first = requests.start();
+            let second = requests.start();
+            assert_eq!(requests.count(), 2);
+
+            drop(first);
+            let not_idle = tokio::time::timeout(Duration::from_millis(50), requests.idle()).await;
+            assert!(not_idle.is_err());
+
+            drop(second);
+            let idle = tokio::time::timeout(Duration::from_millis(50), requests.idle()).await;
+            assert!(idle.is_ok());
+            assert_eq!(requests.count(), 0);
+        });
+    }
+}
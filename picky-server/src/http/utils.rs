@@ -1,142 +1,277 @@
-use saphir::{http::StatusCode, request::Request};
+use crate::{http::middleware::request_log_prefix, logging::with_error_kind};
+use picky::error::ErrorKind;
+use saphir::{
+    http::{header::CONTENT_LENGTH, StatusCode},
+    http_context::HttpContext,
+    prelude::Bytes,
+    request::Request,
+    responder::Responder,
+    response::Builder as ResponseBuilder,
+};
+use serde::Serialize;
 use std::fmt;
 
+// === api error === //
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiErrorKind {
+    BadRequest,
+    InvalidCsr,
+    InvalidCertificate,
+    Unauthorized,
+    Forbidden,
+    NotFound,
+    CaNotFound,
+    Conflict,
+    NotAcceptable,
+    PayloadTooLarge,
+    UnsupportedMediaType,
+    TooManyRequests,
+    Internal,
+}
+
+impl ApiErrorKind {
+    pub fn status(self) -> StatusCode {
+        match self {
+            ApiErrorKind::BadRequest | ApiErrorKind::InvalidCsr | ApiErrorKind::InvalidCertificate => {
+                StatusCode::BAD_REQUEST
+            }
+            ApiErrorKind::Unauthorized => StatusCode::UNAUTHORIZED,
+            ApiErrorKind::Forbidden => StatusCode::FORBIDDEN,
+            ApiErrorKind::NotFound | ApiErrorKind::CaNotFound => StatusCode::NOT_FOUND,
+            ApiErrorKind::Conflict => StatusCode::CONFLICT,
+            ApiErrorKind::NotAcceptable => StatusCode::NOT_ACCEPTABLE,
+            ApiErrorKind::PayloadTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            ApiErrorKind::UnsupportedMediaType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            ApiErrorKind::TooManyRequests => StatusCode::TOO_MANY_REQUESTS,
+            ApiErrorKind::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl From<ErrorKind> for ApiErrorKind {
+    /// Client errors for malformed or rejected input, forbidden for trust failures, internal otherwise
+    fn from(kind: ErrorKind) -> Self {
+        match kind {
+            ErrorKind::Decoding
+            | ErrorKind::InvalidInput
+            | ErrorKind::UnsupportedAlgorithm
+            | ErrorKind::BadSignature
+            | ErrorKind::NotFound => ApiErrorKind::BadRequest,
+            ErrorKind::NotYetValid | ErrorKind::Expired | ErrorKind::InvalidChain => ApiErrorKind::Forbidden,
+            _ => ApiErrorKind::Internal,
+        }
+    }
+}
+
+/// Error returned by controllers, answered as a JSON body along the status matching its kind
+#[derive(Clone, Debug, Serialize)]
+pub struct ApiError {
+    #[serde(rename = "error")]
+    pub message: String,
+    pub kind: ApiErrorKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub supported_types: Option<Vec<&'static str>>,
+    /// Seconds to wait before retrying, sent in a `Retry-After` header
+    #[serde(skip)]
+    pub retry_after: Option<u64>,
+}
+
+impl ApiError {
+    pub fn new<S: Into<String>>(kind: ApiErrorKind, message: S) -> Self {
+        Self {
+            message: message.into(),
+            kind,
+            supported_types: None,
+            retry_after: None,
+        }
+    }
+
+    pub fn unsupported_media_type<S: Into<String>>(message: S, supported_types: Vec<&'static str>) -> Self {
+        Self {
+            message: message.into(),
+            kind: ApiErrorKind::UnsupportedMediaType,
+            supported_types: Some(supported_types),
+            retry_after: None,
+        }
+    }
+
+    pub fn not_acceptable<S: Into<String>>(message: S, supported_types: Vec<&'static str>) -> Self {
+        Self {
+            message: message.into(),
+            kind: ApiErrorKind::NotAcceptable,
+            supported_types: Some(supported_types),
+            retry_after: None,
+        }
+    }
+
+    pub fn too_many_requests<S: Into<String>>(message: S, retry_after: u64) -> Self {
+        Self {
+            message: message.into(),
+            kind: ApiErrorKind::TooManyRequests,
+            supported_types: None,
+            retry_after: Some(retry_after),
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        self.kind.status()
+    }
+
+    pub fn to_json(&self) -> String {
+        // cannot fail: only strings and unit variants are serialized
+        serde_json::to_string(self).expect("api error serialization")
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(f, "{} ({})", self.message, self.status())
+    }
+}
+
+impl Responder for ApiError {
+    fn respond_with_builder(self, builder: ResponseBuilder, _: &HttpContext) -> ResponseBuilder {
+        let builder = if self.kind == ApiErrorKind::Unauthorized {
+            builder.header("WWW-Authenticate", "Bearer")
+        } else {
+            builder
+        };
+        let builder = match self.retry_after {
+            Some(retry_after) => builder.header("Retry-After", retry_after.to_string()),
+            None => builder,
+        };
+
+        builder
+            .status(self.status())
+            .header("Content-Type", "application/json")
+            .body(self.to_json())
+    }
+}
+
 // === result extension === //
 
 pub trait StatusCodeResult<T> {
-    fn bad_request(self) -> Result<T, StatusCode>;
-    fn bad_request_desc(self, desc: &str) -> Result<T, StatusCode>;
+    fn api_error(self, kind: ApiErrorKind, desc: &str) -> Result<T, ApiError>;
 
-    fn not_found(self) -> Result<T, StatusCode>;
-    fn not_found_desc(self, desc: &str) -> Result<T, StatusCode>;
+    fn bad_request(self) -> Result<T, ApiError>;
+    fn bad_request_desc(self, desc: &str) -> Result<T, ApiError>;
 
-    fn internal_error(self) -> Result<T, StatusCode>;
-    fn internal_error_desc(self, desc: &str) -> Result<T, StatusCode>;
+    fn not_found_desc(self, desc: &str) -> Result<T, ApiError>;
 
-    fn service_unavailable(self) -> Result<T, StatusCode>;
-    fn service_unavailable_desc(self, desc: &str) -> Result<T, StatusCode>;
+    fn internal_error_desc(self, desc: &str) -> Result<T, ApiError>;
 
-    fn unauthorized(self) -> Result<T, StatusCode>;
-    fn unauthorized_desc(self, desc: &str) -> Result<T, StatusCode>;
+    fn unauthorized(self) -> Result<T, ApiError>;
+    fn unauthorized_desc(self, desc: &str) -> Result<T, ApiError>;
+
+    fn forbidden(self) -> Result<T, ApiError>;
 }
 
 macro_rules! status_code_on_error {
-    ($result:ident, $code:ident) => {{
+    ($result:ident, $kind:ident) => {{
         match $result {
             Ok(val) => Ok(val),
             Err(e) => {
-                log::error!("{}", e);
-                Err(StatusCode::$code)
+                with_error_kind(ApiErrorKind::$kind, || log::error!("{}{}", request_log_prefix(), e));
+                Err(ApiError::new(ApiErrorKind::$kind, e.to_string()))
             }
         }
     }};
-    ($result:ident, $code:ident, $desc:ident) => {{
+    ($result:ident, $kind:ident, $desc:ident) => {{
         match $result {
             Ok(val) => Ok(val),
             Err(e) => {
-                log::error!("{}: {}", $desc, e);
-                Err(StatusCode::$code)
+                with_error_kind($kind, || log::error!("{}{}: {}", request_log_prefix(), $desc, e));
+                Err(ApiError::new($kind, $desc))
             }
         }
     }};
 }
 
 impl<T, E: fmt::Display> StatusCodeResult<T> for Result<T, E> {
-    fn bad_request(self) -> Result<T, StatusCode> {
-        status_code_on_error!(self, BAD_REQUEST)
-    }
-
-    fn bad_request_desc(self, desc: &str) -> Result<T, StatusCode> {
-        status_code_on_error!(self, BAD_REQUEST, desc)
+    fn api_error(self, kind: ApiErrorKind, desc: &str) -> Result<T, ApiError> {
+        status_code_on_error!(self, kind, desc)
     }
 
-    fn not_found(self) -> Result<T, StatusCode> {
-        status_code_on_error!(self, NOT_FOUND)
+    fn bad_request(self) -> Result<T, ApiError> {
+        status_code_on_error!(self, BadRequest)
     }
 
-    fn not_found_desc(self, desc: &str) -> Result<T, StatusCode> {
-        status_code_on_error!(self, NOT_FOUND, desc)
+    fn bad_request_desc(self, desc: &str) -> Result<T, ApiError> {
+        self.api_error(ApiErrorKind::BadRequest, desc)
     }
 
-    fn internal_error(self) -> Result<T, StatusCode> {
-        status_code_on_error!(self, INTERNAL_SERVER_ERROR)
+    fn not_found_desc(self, desc: &str) -> Result<T, ApiError> {
+        self.api_error(ApiErrorKind::NotFound, desc)
     }
 
-    fn internal_error_desc(self, desc: &str) -> Result<T, StatusCode> {
-        status_code_on_error!(self, INTERNAL_SERVER_ERROR, desc)
+    fn internal_error_desc(self, desc: &str) -> Result<T, ApiError> {
+        self.api_error(ApiErrorKind::Internal, desc)
     }
 
-    fn service_unavailable(self) -> Result<T, StatusCode> {
-        status_code_on_error!(self, SERVICE_UNAVAILABLE)
+    fn unauthorized(self) -> Result<T, ApiError> {
+        status_code_on_error!(self, Unauthorized)
     }
 
-    fn service_unavailable_desc(self, desc: &str) -> Result<T, StatusCode> {
-        status_code_on_error!(self, SERVICE_UNAVAILABLE, desc)
+    fn unauthorized_desc(self, desc: &str) -> Result<T, ApiError> {
+        self.api_error(ApiErrorKind::Unauthorized, desc)
     }
 
-    fn unauthorized(self) -> Result<T, StatusCode> {
-        status_code_on_error!(self, UNAUTHORIZED)
-    }
-
-    fn unauthorized_desc(self, desc: &str) -> Result<T, StatusCode> {
-        status_code_on_error!(self, UNAUTHORIZED, desc)
+    fn forbidden(self) -> Result<T, ApiError> {
+        status_code_on_error!(self, Forbidden)
     }
 }
 
 macro_rules! status_code_on_none {
-    ($opt:ident, $code:ident) => {{
-        $opt.ok_or(StatusCode::$code)
+    ($opt:ident, $kind:ident) => {{
+        $opt.ok_or_else(|| {
+            let kind = ApiErrorKind::$kind;
+            ApiError::new(kind, kind.status().canonical_reason().unwrap_or_default())
+        })
     }};
-    ($opt:ident, $code:ident, $desc:ident) => {{
+    ($opt:ident, $kind:ident, $desc:ident) => {{
         match $opt {
             Some(val) => Ok(val),
             None => {
                 log::error!("{}", $desc);
-                Err(StatusCode::$code)
+                Err(ApiError::new($kind, $desc))
             }
         }
     }};
 }
 
 impl<T> StatusCodeResult<T> for Option<T> {
-    fn bad_request(self) -> Result<T, StatusCode> {
-        status_code_on_none!(self, BAD_REQUEST)
-    }
-
-    fn bad_request_desc(self, desc: &str) -> Result<T, StatusCode> {
-        status_code_on_none!(self, BAD_REQUEST, desc)
-    }
-
-    fn not_found(self) -> Result<T, StatusCode> {
-        status_code_on_none!(self, NOT_FOUND)
+    fn api_error(self, kind: ApiErrorKind, desc: &str) -> Result<T, ApiError> {
+        status_code_on_none!(self, kind, desc)
     }
 
-    fn not_found_desc(self, desc: &str) -> Result<T, StatusCode> {
-        status_code_on_none!(self, NOT_FOUND, desc)
+    fn bad_request(self) -> Result<T, ApiError> {
+        status_code_on_none!(self, BadRequest)
     }
 
-    fn internal_error(self) -> Result<T, StatusCode> {
-        status_code_on_none!(self, INTERNAL_SERVER_ERROR)
+    fn bad_request_desc(self, desc: &str) -> Result<T, ApiError> {
+        self.api_error(ApiErrorKind::BadRequest, desc)
     }
 
-    fn internal_error_desc(self, desc: &str) -> Result<T, StatusCode> {
-        status_code_on_none!(self, INTERNAL_SERVER_ERROR, desc)
+    fn not_found_desc(self, desc: &str) -> Result<T, ApiError> {
+        self.api_error(ApiErrorKind::NotFound, desc)
     }
 
-    fn service_unavailable(self) -> Result<T, StatusCode> {
-        status_code_on_none!(self, SERVICE_UNAVAILABLE)
+    fn internal_error_desc(self, desc: &str) -> Result<T, ApiError> {
+        self.api_error(ApiErrorKind::Internal, desc)
     }
 
-    fn service_unavailable_desc(self, desc: &str) -> Result<T, StatusCode> {
-        status_code_on_none!(self, SERVICE_UNAVAILABLE, desc)
+    fn unauthorized(self) -> Result<T, ApiError> {
+        status_code_on_none!(self, Unauthorized)
     }
 
-    fn unauthorized(self) -> Result<T, StatusCode> {
-        status_code_on_none!(self, UNAUTHORIZED)
+    fn unauthorized_desc(self, desc: &str) -> Result<T, ApiError> {
+        self.api_error(ApiErrorKind::Unauthorized, desc)
     }
 
-    fn unauthorized_desc(self, desc: &str) -> Result<T, StatusCode> {
-        status_code_on_none!(self, UNAUTHORIZED, desc)
+    fn forbidden(self) -> Result<T, ApiError> {
+        status_code_on_none!(self, Forbidden)
     }
 }
 
@@ -144,6 +279,9 @@ impl<T> StatusCodeResult<T> for Option<T> {
 
 pub trait SaphirRequestExt {
     fn get_header_string_value(&self, header_name: &str) -> Option<String>;
+
+    /// Percent-decoded value of the first query parameter with this name
+    fn get_query_param(&self, param_name: &str) -> Option<String>;
 }
 
 impl<T> SaphirRequestExt for Request<T> {
@@ -157,6 +295,70 @@ impl<T> SaphirRequestExt for Request<T> {
         }
         None
     }
+
+    fn get_query_param(&self, param_name: &str) -> Option<String> {
+        self.uri()
+            .query()?
+            .split('&')
+            .filter_map(|pair| {
+                let mut split = pair.splitn(2, '=');
+                Some((split.next()?, split.next().unwrap_or("")))
+            })
+            .find(|(name, _)| percent_decode(name).as_deref() == Some(param_name))
+            .and_then(|(_, value)| percent_decode(value))
+    }
+}
+
+/// Reads the request body, refusing bodies larger than `max_size` bytes.
+///
+/// An announced `Content-Length` over the limit is refused before anything is read,
+/// bodies sent without one are checked once read.
+pub async fn load_limited_body(req: Request, max_size: usize) -> Result<Request<Bytes>, ApiError> {
+    let too_large = |size: u64| {
+        let kind = ApiErrorKind::PayloadTooLarge;
+        with_error_kind(kind, || {
+            log::error!(
+                "{}request body of {} bytes exceeds the {} bytes limit",
+                request_log_prefix(),
+                size,
+                max_size
+            )
+        });
+        ApiError::new(kind, format!("request body exceeds {} bytes", max_size))
+    };
+
+    let announced_size = req
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|header| header.to_str().ok())
+        .and_then(|header| header.parse::<u64>().ok());
+    if let Some(size) = announced_size.filter(|size| *size > max_size as u64) {
+        return Err(too_large(size));
+    }
+
+    let req = req.load_body().await.bad_request_desc("couldn't read request body")?;
+    if req.body().len() > max_size {
+        return Err(too_large(req.body().len() as u64));
+    }
+
+    Ok(req)
+}
+
+fn percent_decode(encoded: &str) -> Option<String> {
+    let mut decoded = Vec::with_capacity(encoded.len());
+    let mut bytes = encoded.bytes();
+    while let Some(byte) = bytes.next() {
+        match byte {
+            b'+' => decoded.push(b' '),
+            b'%' => {
+                let hex = [bytes.next()?, bytes.next()?];
+                let hex = std::str::from_utf8(&hex).ok()?;
+                decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            }
+            byte => decoded.push(byte),
+        }
+    }
+    String::from_utf8(decoded).ok()
 }
 
 // === header format === //
@@ -169,6 +371,8 @@ pub enum Format {
     PkixCertBase64,
     Pkcs10Binary,
     Pkcs10Base64,
+    PkixCrlBinary,
+    OcspRequest,
 }
 
 impl fmt::Display for Format {
@@ -180,13 +384,18 @@ impl fmt::Display for Format {
             Format::PkixCertBase64 => write!(f, "base64-encoded pkix-cert"),
             Format::Pkcs10Binary => write!(f, "binary-encoded pkcs10"),
             Format::Pkcs10Base64 => write!(f, "base64-encoded pkcs10"),
+            Format::PkixCrlBinary => write!(f, "binary-encoded pkix-crl"),
+            Format::OcspRequest => write!(f, "binary-encoded ocsp-request"),
         }
     }
 }
 
 impl Format {
     pub fn request_format<T>(req: &Request<T>) -> Result<Self, String> {
-        let content_type_opt = req.get_header_string_value("Content-Type");
+        let content_type_opt = req.get_header_string_value("Content-Type").map(|s| {
+            // cannot panic
+            s.split(';').next().unwrap().trim().to_owned()
+        });
 
         if let Some(content_type) = content_type_opt {
             Self::new(content_type.as_str())
@@ -208,14 +417,129 @@ impl Format {
         }
     }
 
+    /// Picks the format to answer with among the `offered` ones, along with the media type
+    /// to send as `Content-Type`.
+    ///
+    /// Media ranges of the `Accept` header are weighted by their quality value, ties being broken
+    /// by the order of the header. The first offered format is answered when the header is missing.
+    pub fn negotiate<T>(req: &Request<T>, offered: &[Format]) -> Result<(Self, &'static str), ApiError> {
+        let accept = match req.get_header_string_value("Accept") {
+            Some(accept) => accept,
+            None => return Ok((offered[0], offered[0].mime_types()[0])),
+        };
+
+        let ranges = accept
+            .split(',')
+            .filter_map(|range| {
+                let mut params = range.split(';');
+                // cannot panic
+                let media_range = params.next().unwrap().trim().to_ascii_lowercase();
+                let quality = params
+                    .filter_map(|param| {
+                        let mut param = param.splitn(2, '=');
+                        match (param.next()?.trim(), param.next()) {
+                            ("q", Some(quality)) => Some(quality.trim().parse::<f32>().unwrap_or(0.0)),
+                            _ => None,
+                        }
+                    })
+                    .next()
+                    .unwrap_or(1.0);
+                if media_range.is_empty() {
+                    None
+                } else {
+                    Some((media_range, quality))
+                }
+            })
+            .collect::<Vec<_>>();
+
+        // the most specific media range matching a media type gives its quality
+        let mut best: Option<(f32, usize, Format, &'static str)> = None;
+        for format in offered {
+            for mime in format.mime_types() {
+                let (main_type, _) = mime.split_at(mime.find('/').unwrap_or(0));
+                let matching = ranges
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(position, (range, quality))| {
+                        let specificity = if range == mime {
+                            2
+                        } else if range.strip_suffix("/*") == Some(main_type) {
+                            1
+                        } else if range == "*/*" {
+                            0
+                        } else {
+                            return None;
+                        };
+                        Some((specificity, position, *quality))
+                    })
+                    .max_by_key(|(specificity, _, _)| *specificity);
+
+                if let Some((specificity, position, quality)) = matching {
+                    let is_better = match best {
+                        Some((best_quality, best_position, _, _)) => {
+                            quality > best_quality || (quality == best_quality && position < best_position)
+                        }
+                        None => true,
+                    };
+                    if quality > 0.0 && is_better {
+                        // media types are answered as requested, wildcards with the main media type of the format
+                        let answered = if specificity == 2 { mime } else { format.mime_types()[0] };
+                        best = Some((quality, position, *format, answered));
+                    }
+                }
+            }
+        }
+
+        match best {
+            Some((_, _, format, mime)) => Ok((format, mime)),
+            None => Err(ApiError::not_acceptable(
+                format!("none of the accepted formats can be answered: {}", accept),
+                offered
+                    .iter()
+                    .flat_map(|format| format.mime_types().iter().copied())
+                    .collect(),
+            )),
+        }
+    }
+
+    /// File extension of a file holding this format
+    pub fn file_extension(self) -> &'static str {
+        match self {
+            Format::PemFile => "pem",
+            Format::Json => "json",
+            Format::PkixCertBinary => "cer",
+            Format::PkixCertBase64 => "b64",
+            Format::Pkcs10Binary => "p10",
+            Format::Pkcs10Base64 => "b64",
+            Format::PkixCrlBinary => "crl",
+            Format::OcspRequest => "ocsp",
+        }
+    }
+
+    /// Media types mapping to this format
+    pub fn mime_types(self) -> &'static [&'static str] {
+        match self {
+            Format::PemFile => &["application/x-pem-file", "text/plain"],
+            Format::Json => &["application/json"],
+            Format::PkixCertBinary => &["application/pkix-cert"],
+            Format::PkixCertBase64 => &["application/pkix-cert-base64"],
+            Format::Pkcs10Binary => &["application/pkcs10"],
+            Format::Pkcs10Base64 => &["application/pkcs10-base64"],
+            Format::PkixCrlBinary => &["application/pkix-crl"],
+            Format::OcspRequest => &["application/ocsp-request"],
+        }
+    }
+
     fn new(mime: &str) -> Result<Self, String> {
         match mime {
-            "application/x-pem-file" => Ok(Self::PemFile),
+            "application/x-pem-file" | "text/plain" => Ok(Self::PemFile),
             "application/json" => Ok(Self::Json),
             "application/pkix-cert" => Ok(Self::PkixCertBinary),
             "application/pkix-cert-base64" => Ok(Self::PkixCertBase64),
             "application/pkcs10" => Ok(Self::Pkcs10Binary),
             "application/pkcs10-base64" => Ok(Self::Pkcs10Base64),
+            "application/pkix-crl" => Ok(Self::PkixCrlBinary),
+            "application/ocsp-request" => Ok(Self::OcspRequest),
             unsupported => Err(format!("unsupported format: {}", unsupported)),
         }
     }
@@ -225,6 +549,7 @@ impl Format {
 mod tests {
     use super::*;
     use saphir::prelude::Body;
+    use tokio_test::block_on;
 
     fn new_saphir_request(headers: Vec<(&'static str, &'static str)>) -> Request<Body> {
         use saphir::http::header::HeaderValue;
@@ -239,6 +564,111 @@ mod tests {
         Request::new(request.body(Body::empty()).expect("request"), None)
     }
 
+    #[test]
+    fn api_errors() {
+        let err = Err::<(), _>("bad input").bad_request().unwrap_err();
+        assert_eq!(err.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(err.to_json(), r#"{"error":"bad input","kind":"bad_request"}"#);
+
+        let err = Err::<(), _>("no such key")
+            .not_found_desc("couldn't fetch certificate")
+            .unwrap_err();
+        assert_eq!(err.status(), StatusCode::NOT_FOUND);
+        assert_eq!(
+            err.to_json(),
+            r#"{"error":"couldn't fetch certificate","kind":"not_found"}"#
+        );
+
+        let err = None::<()>.forbidden().unwrap_err();
+        assert_eq!(err.status(), StatusCode::FORBIDDEN);
+        assert_eq!(err.to_json(), r#"{"error":"Forbidden","kind":"forbidden"}"#);
+
+        let err = None::<()>
+            .api_error(ApiErrorKind::InvalidCertificate, "couldn't fetch SKI")
+            .unwrap_err();
+        assert_eq!(err.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            err.to_json(),
+            r#"{"error":"couldn't fetch SKI","kind":"invalid_certificate"}"#
+        );
+
+        let err = ApiError::new(ApiErrorKind::Conflict, "CA already imported");
+        assert_eq!(err.status(), StatusCode::CONFLICT);
+        assert_eq!(err.to_json(), r#"{"error":"CA already imported","kind":"conflict"}"#);
+
+        let err = ApiError::too_many_requests("rate limit exceeded", 2);
+        assert_eq!(err.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(err.retry_after, Some(2));
+        assert_eq!(
+            err.to_json(),
+            r#"{"error":"rate limit exceeded","kind":"too_many_requests"}"#
+        );
+
+        let err = picky::x509::Cert::from_der(&[0x30, 0x03, 0x02, 0x01]).unwrap_err();
+        assert_eq!(ApiErrorKind::from(err.kind()).status(), StatusCode::BAD_REQUEST);
+        assert_eq!(ApiErrorKind::from(ErrorKind::Expired).status(), StatusCode::FORBIDDEN);
+        assert_eq!(
+            ApiErrorKind::from(ErrorKind::Crypto).status(),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+
+        let err = ApiError::unsupported_media_type("unsupported format: a/b", vec!["application/json"]);
+        assert_eq!(err.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert_eq!(
+            err.to_json(),
+            r#"{"error":"unsupported format: a/b","kind":"unsupported_media_type","supported_types":["application/json"]}"#
+        );
+    }
+
+    #[test]
+    fn body_size_limit() {
+        let err = block_on(load_limited_body(
+            new_saphir_request(vec![("Content-Length", "1073741824")]),
+            128 * 1024,
+        ))
+        .err()
+        .expect("oversized body accepted");
+        assert_eq!(err.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(
+            err.to_json(),
+            r#"{"error":"request body exceeds 131072 bytes","kind":"payload_too_large"}"#
+        );
+
+        let req = block_on(load_limited_body(
+            new_saphir_request(vec![("Content-Length", "0")]),
+            128 * 1024,
+        ))
+        .expect("empty body");
+        assert!(req.body().is_empty());
+    }
+
+    #[test]
+    fn query_params() {
+        let request = saphir::http::Request::builder()
+            .uri("/certs?limit=2&issued_after=2020-01-01T00%3A00%3A00%2B00%3A00&name=picky+server&empty=")
+            .body(Body::empty())
+            .expect("request");
+        let request = Request::new(request, None);
+
+        assert_eq!(request.get_query_param("limit").as_deref(), Some("2"));
+        assert_eq!(
+            request.get_query_param("issued_after").as_deref(),
+            Some("2020-01-01T00:00:00+00:00")
+        );
+        assert_eq!(request.get_query_param("name").as_deref(), Some("picky server"));
+        assert_eq!(request.get_query_param("empty").as_deref(), Some(""));
+        assert_eq!(request.get_query_param("offset"), None);
+
+        let request = Request::new(
+            saphir::http::Request::builder()
+                .uri("/certs")
+                .body(Body::empty())
+                .expect("request"),
+            None,
+        );
+        assert_eq!(request.get_query_param("limit"), None);
+    }
+
     #[test]
     fn request_format() {
         let format =
@@ -255,6 +685,14 @@ mod tests {
         let format =
             Format::request_format(&new_saphir_request(vec![("Content-Type", "application/pkcs10-base64")])).unwrap();
         assert_eq!(format, Format::Pkcs10Base64);
+
+        let format =
+            Format::request_format(&new_saphir_request(vec![("Content-Type", "application/ocsp-request")])).unwrap();
+        assert_eq!(format, Format::OcspRequest);
+
+        let format =
+            Format::request_format(&new_saphir_request(vec![("Content-Type", "text/plain; charset=utf-8")])).unwrap();
+        assert_eq!(format, Format::PemFile);
     }
 
     #[test]
@@ -290,6 +728,75 @@ mod tests {
         let format =
             Format::response_format(&new_saphir_request(vec![("Accept", "application/pkcs10-base64;q=1")])).unwrap();
         assert_eq!(format, Format::Pkcs10Base64);
+
+        let format = Format::response_format(&new_saphir_request(vec![("Accept", "application/pkix-crl")])).unwrap();
+        assert_eq!(format, Format::PkixCrlBinary);
+    }
+
+    #[test]
+    fn negotiate() {
+        const OFFERED: &[Format] = &[
+            Format::PemFile,
+            Format::PkixCertBinary,
+            Format::PkixCertBase64,
+            Format::Json,
+        ];
+        let cases: &[(Option<&'static str>, Format, &str)] = &[
+            (None, Format::PemFile, "application/x-pem-file"),
+            (Some("*/*"), Format::PemFile, "application/x-pem-file"),
+            (
+                Some("application/pkix-cert"),
+                Format::PkixCertBinary,
+                "application/pkix-cert",
+            ),
+            (
+                Some("application/x-pem-file"),
+                Format::PemFile,
+                "application/x-pem-file",
+            ),
+            (Some("text/plain"), Format::PemFile, "text/plain"),
+            (Some("text/*"), Format::PemFile, "application/x-pem-file"),
+            (
+                Some("application/pkix-cert-base64"),
+                Format::PkixCertBase64,
+                "application/pkix-cert-base64",
+            ),
+            (Some("application/json"), Format::Json, "application/json"),
+            (
+                Some("Application/JSON; charset=utf-8"),
+                Format::Json,
+                "application/json",
+            ),
+            (
+                Some("application/json;q=0.5, application/pkix-cert"),
+                Format::PkixCertBinary,
+                "application/pkix-cert",
+            ),
+            (
+                Some("application/pkix-cert, application/pkix-cert-base64, snateinsrturiest"),
+                Format::PkixCertBinary,
+                "application/pkix-cert",
+            ),
+            (Some("image/png, */*;q=0.1"), Format::PemFile, "application/x-pem-file"),
+            (
+                Some("application/x-pem-file;q=0, text/plain;q=0, application/*"),
+                Format::PkixCertBinary,
+                "application/pkix-cert",
+            ),
+        ];
+
+        for (accept, format, content_type) in cases {
+            let headers = accept.map(|accept| vec![("Accept", accept)]).unwrap_or_default();
+            let negotiated = Format::negotiate(&new_saphir_request(headers), OFFERED).unwrap();
+            assert_eq!(negotiated, (*format, *content_type), "Accept: {:?}", accept);
+        }
+
+        for accept in &["image/png", "application/pkix-cert;q=0", "application/pkcs10"] {
+            let err = Format::negotiate(&new_saphir_request(vec![("Accept", *accept)]), OFFERED).unwrap_err();
+            assert_eq!(err.status(), StatusCode::NOT_ACCEPTABLE, "Accept: {}", accept);
+            assert_eq!(err.kind, ApiErrorKind::NotAcceptable);
+            assert!(err.supported_types.unwrap().contains(&"application/pkix-cert-base64"));
+        }
     }
 
     #[test]
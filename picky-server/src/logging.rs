@@ -1,20 +1,62 @@
-use crate::config::Config as ServerConfig;
-use log::LevelFilter;
-use log4rs::{config::Config as LoggerConfig, Handle};
+use crate::{
+    config::{Config as ServerConfig, LogFormat},
+    http::{
+        middleware::{current_request_id, current_route},
+        utils::ApiErrorKind,
+    },
+};
+use log::{LevelFilter, Record};
+use log4rs::{
+    append::Append,
+    config::Config as LoggerConfig,
+    encode::{pattern::PatternEncoder, writer::simple::SimpleWriter, Encode},
+    Handle,
+};
+use serde::Serialize;
+use std::{
+    cell::Cell,
+    error::Error,
+    fs::{File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+
+/// Set on SIGHUP, so that the log file is reopened before the next record is written
+static REOPEN_LOG_FILE: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    static ERROR_KIND: Cell<Option<ApiErrorKind>> = const { Cell::new(None) };
+}
 
 pub fn init_logs(config: &ServerConfig) -> Handle {
     let config = build_logger_config(config).expect("unable to configure logger");
     log4rs::init_config(config).expect("can't init log4rs")
 }
 
-pub fn build_logger_config(config: &ServerConfig) -> Result<LoggerConfig, log4rs::config::Errors> {
+pub fn build_logger_config(config: &ServerConfig) -> Result<LoggerConfig, String> {
     use log4rs::{
         append::console::ConsoleAppender,
         config::{Appender, Logger, Root},
     };
 
+    let encoder: Box<dyn Encode> = match config.log_format {
+        LogFormat::Text => Box::new(PatternEncoder::default()),
+        LogFormat::Json => Box::new(JsonLineEncoder),
+    };
+    let appender: Box<dyn Append> = match &config.log_file {
+        Some(path) => Box::new(
+            LogFileAppender::new(path.clone(), encoder)
+                .map_err(|e| format!("couldn't open log file {}: {}", path.display(), e))?,
+        ),
+        None => Box::new(ConsoleAppender::builder().encoder(encoder).build()),
+    };
+
     LoggerConfig::builder()
-        .appender(Appender::builder().build("stdout", Box::new(ConsoleAppender::builder().build())))
+        .appender(Appender::builder().build("stdout", appender))
         .logger(Logger::builder().build("poston", LevelFilter::Off))
         .logger(Logger::builder().build("mio", LevelFilter::Off))
         .logger(Logger::builder().build("mio_extras", LevelFilter::Off))
@@ -25,4 +67,175 @@ pub fn build_logger_config(config: &ServerConfig) -> Result<LoggerConfig, log4rs
         .logger(Logger::builder().build("tokio_threadpool", LevelFilter::Off))
         .logger(Logger::builder().build("tokio_core", LevelFilter::Off))
         .build(Root::builder().appender("stdout").build(config.log_level))
+        .map_err(|e| e.to_string())
+}
+
+/// Records logged by `f` report an API error of the given kind
+pub fn with_error_kind<R>(kind: ApiErrorKind, f: impl FnOnce() -> R) -> R {
+    ERROR_KIND.with(|error_kind| {
+        let previous = error_kind.replace(Some(kind));
+        let result = f();
+        error_kind.set(previous);
+        result
+    })
+}
+
+/// Makes the log file appender reopen its file, after it has been moved by logrotate
+pub fn reopen_log_file() {
+    REOPEN_LOG_FILE.store(true, Ordering::SeqCst);
+}
+
+/// Reopens the log file on every SIGHUP
+#[cfg(unix)]
+pub async fn reopen_log_file_on_hangup() {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangup = signal(SignalKind::hangup()).expect("couldn't listen to SIGHUP");
+    while hangup.recv().await.is_some() {
+        reopen_log_file();
+    }
+}
+
+/// Appends records to a file, reopened when asked to by `reopen_log_file`
+#[derive(Debug)]
+struct LogFileAppender {
+    path: PathBuf,
+    file: Mutex<File>,
+    encoder: Box<dyn Encode>,
+}
+
+impl LogFileAppender {
+    fn new(path: PathBuf, encoder: Box<dyn Encode>) -> std::io::Result<Self> {
+        Ok(Self {
+            file: Mutex::new(open_log_file(&path)?),
+            path,
+            encoder,
+        })
+    }
+}
+
+fn open_log_file(path: &Path) -> std::io::Result<File> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+impl Append for LogFileAppender {
+    fn append(&self, record: &Record) -> Result<(), Box<dyn Error + Sync + Send>> {
+        let mut file = self.file.lock().map_err(|_| "log file lock poisoned")?;
+        if REOPEN_LOG_FILE.swap(false, Ordering::SeqCst) {
+            *file = open_log_file(&self.path)?;
+        }
+        self.encoder.encode(&mut SimpleWriter(&mut *file), record)
+    }
+
+    fn flush(&self) {
+        if let Ok(mut file) = self.file.lock() {
+            let _ = file.flush();
+        }
+    }
+}
+
+/// Writes each record as a JSON object on its own line
+#[derive(Debug)]
+struct JsonLineEncoder;
+
+#[derive(Serialize)]
+struct JsonLine<'a> {
+    timestamp: String,
+    level: String,
+    target: &'a str,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    route: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error_kind: Option<ApiErrorKind>,
+}
+
+impl JsonLineEncoder {
+    fn line<'a>(record: &Record<'a>) -> JsonLine<'a> {
+        JsonLine {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            level: record.level().to_string(),
+            target: record.target(),
+            message: record.args().to_string(),
+            request_id: current_request_id(),
+            route: current_route(),
+            error_kind: ERROR_KIND.with(Cell::get),
+        }
+    }
+}
+
+impl Encode for JsonLineEncoder {
+    fn encode(&self, w: &mut dyn log4rs::encode::Write, record: &Record) -> Result<(), Box<dyn Error + Sync + Send>> {
+        serde_json::to_writer(&mut *w, &Self::line(record))?;
+        w.write_all(b"\n")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::middleware::in_request_scope;
+    use log::Level;
+    use serde_json::Value;
+    use tokio_test::block_on;
+
+    fn encode(record: &Record) -> Value {
+        let mut output = Vec::new();
+        JsonLineEncoder
+            .encode(&mut SimpleWriter(&mut output), record)
+            .expect("couldn't encode record");
+        assert_eq!(output.last(), Some(&b'\n'));
+        serde_json::from_slice(&output).expect("not a JSON line")
+    }
+
+    #[test]
+    fn json_line_shape() {
+        let line = encode(
+            &Record::builder()
+                .args(format_args!("storage is fine"))
+                .level(Level::Info)
+                .target("picky_server::db")
+                .build(),
+        );
+        let object = line.as_object().unwrap();
+        assert_eq!(object["level"], "INFO");
+        assert_eq!(object["target"], "picky_server::db");
+        assert_eq!(object["message"], "storage is fine");
+        assert!(object["timestamp"].as_str().is_some());
+        assert!(!object.contains_key("request_id"));
+        assert!(!object.contains_key("route"));
+        assert!(!object.contains_key("error_kind"));
+    }
+
+    #[test]
+    fn json_line_request_fields() {
+        let line = block_on(in_request_scope(
+            "d3b07384".to_owned(),
+            "POST /sign".to_owned(),
+            async {
+                with_error_kind(ApiErrorKind::InvalidCsr, || {
+                    encode(
+                        &Record::builder()
+                            .args(format_args!("couldn't parse csr"))
+                            .level(Level::Error)
+                            .target("picky_server::http")
+                            .build(),
+                    )
+                })
+            },
+        ));
+        assert_eq!(line["request_id"], "d3b07384");
+        assert_eq!(line["route"], "POST /sign");
+        assert_eq!(line["error_kind"], "invalid_csr");
+        assert_eq!(line["level"], "ERROR");
+
+        // only set while logging the error
+        assert_eq!(ERROR_KIND.with(Cell::get), None);
+    }
 }
@@ -1,27 +1,151 @@
 mod addressing;
 mod config;
 mod db;
+mod expiry;
 mod http;
 mod logging;
 mod picky_controller;
+mod policy;
 mod utils;
+mod webhook;
 
-use crate::{config::Config, http::http_server::HttpServer};
+use crate::{
+    config::{Command, Config},
+    db::{get_storage, BackupBundle},
+    http::{
+        controller::{export_ca_command, init_command, show_chain_command, sign_command},
+        http_server::HttpServer,
+        shutdown::shutdown_signal,
+    },
+};
 
 #[tokio::main]
 async fn main() {
     let conf = Config::startup_init();
+    if conf.command.is_none() {
+        if let Err(errors) = conf.validate() {
+            // logging isn't initialized yet, and its own settings may be among the errors
+            eprintln!("{}", errors);
+            std::process::exit(1);
+        }
+    }
     let log_handle = logging::init_logs(&conf);
 
+    if let Some(command) = &conf.command {
+        if let Err(e) = run_command(&conf, command).await {
+            log::error!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     log::info!("building http server ...");
     let http_server = HttpServer::new(conf, log_handle).await;
 
+    #[cfg(unix)]
+    {
+        tokio::spawn(logging::reopen_log_file_on_hangup());
+        tokio::spawn(http::controller::reload_on_hangup(http_server.reloader()));
+    }
+
+    let shutdown = http_server.shutdown_handle();
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        shutdown.shutdown();
+    });
+
     log::info!("starting http server ...");
     http_server.run().await;
 }
 
+async fn run_command(conf: &Config, command: &Command) -> Result<(), String> {
+    let storage = get_storage(conf).await;
+
+    match command {
+        Command::Export { out, include_keys } => {
+            let bundle = storage
+                .export(*include_keys)
+                .await
+                .map_err(|e| format!("couldn't export storage: {}", e))?;
+            let json = serde_json::to_vec_pretty(&bundle).map_err(|e| format!("couldn't serialize backup: {}", e))?;
+            std::fs::write(out, json).map_err(|e| format!("couldn't write {}: {}", out.display(), e))?;
+            log::info!(
+                "exported {} certificate(s) and {} key(s) to {}",
+                bundle.certificates.len(),
+                bundle.keys.len(),
+                out.display()
+            );
+        }
+        Command::Import { input } => {
+            let json = std::fs::read(input).map_err(|e| format!("couldn't read {}: {}", input.display(), e))?;
+            let bundle: BackupBundle =
+                serde_json::from_slice(&json).map_err(|e| format!("invalid backup {}: {}", input.display(), e))?;
+            let report = storage
+                .import(bundle)
+                .await
+                .map_err(|e| format!("couldn't import backup: {}", e))?;
+            log::info!(
+                "imported {} certificate(s), {} key(s), {} revocation(s) and {} audit entries from {}",
+                report.certificates,
+                report.keys,
+                report.revocations,
+                report.audit,
+                input.display()
+            );
+        }
+        Command::Init => {
+            init_command(conf, storage.as_ref()).await?;
+            log::info!("CAs of realm {} are ready", conf.realm);
+        }
+        Command::ShowChain => {
+            print!("{}", show_chain_command(conf, storage.as_ref()).await?);
+        }
+        Command::Sign { csr, out } => {
+            let csr = std::fs::read(csr).map_err(|e| format!("couldn't read {}: {}", csr.display(), e))?;
+            let cert = sign_command(conf, storage.as_ref(), &csr).await?;
+            match out {
+                Some(out) => {
+                    std::fs::write(out, cert).map_err(|e| format!("couldn't write {}: {}", out.display(), e))?;
+                    log::info!("certificate written to {}", out.display());
+                }
+                None => print!("{}", cert),
+            }
+        }
+        Command::ExportCa {
+            ca,
+            include_key,
+            passphrase_file,
+            out,
+        } => {
+            let passphrase = match passphrase_file {
+                Some(path) if *include_key => {
+                    let passphrase = std::fs::read_to_string(path)
+                        .map_err(|e| format!("couldn't read {}: {}", path.display(), e))?;
+                    Some(passphrase.trim_end_matches(&['\r', '\n'][..]).to_owned())
+                }
+                _ => None,
+            };
+            let export = export_ca_command(conf, storage.as_ref(), ca.as_deref(), passphrase.as_deref()).await?;
+            match out {
+                Some(out) => {
+                    std::fs::write(out, export).map_err(|e| format!("couldn't write {}: {}", out.display(), e))?;
+                    log::info!("CA exported to {}", out.display());
+                }
+                None => print!("{}", export),
+            }
+        }
+    }
+
+    storage
+        .close()
+        .await
+        .map_err(|e| format!("couldn't close storage: {}", e))
+}
+
 #[cfg(any(feature = "pre-gen-pk", all(debug_assertions, test)))]
 pub mod test_files {
+    pub const EC_P256_PK_1: &str = include_str!("../../test_assets/private_keys/ec-p256-pk_1.key");
+    pub const EC_P384_PK_1: &str = include_str!("../../test_assets/private_keys/ec-p384-pk_1.key");
     pub const RSA_2048_PK_1: &str = include_str!("../../test_assets/private_keys/rsa-2048-pk_1.key");
     pub const RSA_2048_PK_2: &str = include_str!("../../test_assets/private_keys/rsa-2048-pk_2.key");
     pub const RSA_2048_PK_3: &str = include_str!("../../test_assets/private_keys/rsa-2048-pk_3.key");
@@ -29,6 +153,8 @@ pub mod test_files {
     pub const RSA_2048_PK_5: &str = include_str!("../../test_assets/private_keys/rsa-2048-pk_5.key");
     pub const RSA_2048_PK_6: &str = include_str!("../../test_assets/private_keys/rsa-2048-pk_6.key");
     pub const RSA_2048_PK_7: &str = include_str!("../../test_assets/private_keys/rsa-2048-pk_7.key");
+    pub const RSA_3072_PK_1: &str = include_str!("../../test_assets/private_keys/rsa-3072-pk_1.key");
+    pub const RSA_3072_PK_2: &str = include_str!("../../test_assets/private_keys/rsa-3072-pk_2.key");
     pub const RSA_4096_PK_1: &str = include_str!("../../test_assets/private_keys/rsa-4096-pk_1.key");
     pub const RSA_4096_PK_2: &str = include_str!("../../test_assets/private_keys/rsa-4096-pk_2.key");
     pub const RSA_4096_PK_3: &str = include_str!("../../test_assets/private_keys/rsa-4096-pk_3.key");
@@ -1,20 +1,24 @@
+use crate::db::RevocationEntry;
+use chrono::TimeZone;
 use picky::{
+    error::ErrorKind,
     key::{KeyError, PrivateKey, PublicKey},
-    oids,
     signature::SignatureAlgorithm,
     x509::{
         certificate::{Cert, CertError, CertificateBuilder},
-        csr::Csr,
+        crl::{Crl, CrlBuilder, CrlError, CrlReason},
+        csr::{Csr, CsrError, VerifiedCsr},
         date::UTCDate,
-        extension::KeyUsage,
-        name::{DirectoryName, GeneralName, GeneralNames},
+        extension::{AuthorityInfoAccess, ExtendedKeyUsage, KeyUsage},
+        name::{DirectoryName, GeneralNames},
+        ocsp::{cert_id_matches_issuer, CertStatus, OcspError, OcspRequest, OcspResponse, OcspResponseBuilder},
+        KeyIdGenMethod,
     },
 };
-use picky_asn1::restricted_string::CharSetError;
+use picky_asn1::wrapper::IntegerAsn1;
 use thiserror::Error;
 
-const DEFAULT_ROOT_DURATION_DAYS: i64 = 3650;
-const DEFAULT_INTERMEDIATE_DURATION_DAYS: i64 = 1825;
+pub const DEFAULT_CRL_DURATION_DAYS: i64 = 7;
 
 #[derive(Debug, Error)]
 pub enum PickyError {
@@ -22,19 +26,40 @@ pub enum PickyError {
     #[error("certificate error: {}", source)]
     Certificate { source: CertError },
 
-    /// input has invalid charset
-    #[error("input has invalid charset: {}", input)]
-    InvalidCharSet { input: String, source: CharSetError },
+    /// CSR error
+    #[error("CSR error: {}", source)]
+    Csr { source: CsrError },
+
+    /// CRL error
+    #[error("CRL error: {}", source)]
+    Crl { source: CrlError },
+
+    /// OCSP error
+    #[error("OCSP error: {}", source)]
+    Ocsp { source: OcspError },
+
+    /// certificate would outlive its issuer
+    #[error("certificate would expire on {}, after its issuer ({})", not_after, issuer_not_after)]
+    ValidityOutsideIssuer {
+        not_after: UTCDate,
+        issuer_not_after: UTCDate,
+    },
+
+    /// CA certificate options not allowed by its issuer
+    #[error("certificate doesn't fit within its issuer constraints: {}", description)]
+    OutsideIssuerConstraints { description: String },
+
+    /// invalid revocation entry
+    #[error("invalid revocation entry for {}: {}", addressing_hash, description)]
+    InvalidRevocationEntry {
+        addressing_hash: String,
+        description: String,
+    },
 
     /// couldn't generate private key
     #[error("couldn't generate private key: {}", source)]
     PrivateKeyGeneration { source: KeyError },
 
-    /// no pre-generated private key for given size
-    #[cfg(any(feature = "pre-gen-pk", all(debug_assertions, test)))]
-    #[error("no {}-bits pre-generated private key available", num_bits)]
-    NoPreGenKey { num_bits: usize },
-
     /// couldn't parse private key der (two sources)
     #[error(
         "couldn't parse private key as pkcs8: {} ; couldn't parse private key as raw der-encoded RSA key either: {}",
@@ -49,67 +74,260 @@ pub enum PickyError {
     PrivateKeyPem { source: picky::pem::PemError },
 }
 
+impl PickyError {
+    /// Stable classification of this error, see `picky::error::ErrorKind`
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            PickyError::Certificate { source } => source.kind(),
+            PickyError::Csr { source } => source.kind(),
+            PickyError::Crl { source } => source.kind(),
+            PickyError::Ocsp { source } => source.kind(),
+            PickyError::ValidityOutsideIssuer { .. }
+            | PickyError::OutsideIssuerConstraints { .. }
+            | PickyError::InvalidRevocationEntry { .. } => ErrorKind::InvalidInput,
+            PickyError::PrivateKeyGeneration { source } => source.kind(),
+            PickyError::PrivateKeyParsing { .. } => ErrorKind::Decoding,
+            #[cfg(any(feature = "pre-gen-pk", all(debug_assertions, test)))]
+            PickyError::PrivateKeyPem { .. } => ErrorKind::Decoding,
+        }
+    }
+}
+
 impl From<CertError> for PickyError {
     fn from(source: CertError) -> Self {
         Self::Certificate { source }
     }
 }
 
+/// Parameters of a leaf certificate issued from a CSR
+#[derive(Clone, Debug)]
+pub struct LeafIssuanceOptions {
+    pub valid_from: UTCDate,
+    pub valid_to: UTCDate,
+    pub extended_key_usage: ExtendedKeyUsage,
+    pub subject_alt_name: Option<GeneralNames>,
+    pub key_usage: KeyUsage,
+    pub authority_info_access: Option<AuthorityInfoAccess>,
+}
+
+impl LeafIssuanceOptions {
+    /// Options used by `Picky::generate_leaf_from_csr`: valid from now for the given duration,
+    /// digital signature and key encipherment usages, server and client authentication.
+    pub fn new(validity_duration: chrono::Duration) -> Self {
+        let now = chrono::offset::Utc::now();
+        Self {
+            valid_from: UTCDate::from(now),
+            valid_to: UTCDate::from(now + validity_duration),
+            extended_key_usage: ExtendedKeyUsage::default().with_server_auth().with_client_auth(),
+            subject_alt_name: None,
+            key_usage: KeyUsage::default()
+                .with_digital_signature(true)
+                .with_key_encipherment(true),
+            authority_info_access: None,
+        }
+    }
+}
+
+/// Parameters of a self-signed root CA certificate
+#[derive(Clone, Debug)]
+pub struct RootIssuanceOptions {
+    pub valid_from: UTCDate,
+    pub valid_to: UTCDate,
+    /// Maximum number of intermediate CAs below the root, unlimited when `None`
+    pub pathlen: Option<u8>,
+    pub key_usage: KeyUsage,
+    pub key_id_gen_method: KeyIdGenMethod,
+}
+
+impl RootIssuanceOptions {
+    /// Options used by `Picky::generate_root`: valid from now for the given duration,
+    /// no path length constraint, certificate and CRL signing usages.
+    pub fn new(validity_duration: chrono::Duration) -> Self {
+        let now = chrono::offset::Utc::now();
+        Self {
+            valid_from: UTCDate::from(now),
+            valid_to: UTCDate::from(now + validity_duration),
+            pathlen: None,
+            key_usage: KeyUsage::default().with_key_cert_sign(true).with_crl_sign(true),
+            key_id_gen_method: KeyIdGenMethod::default(),
+        }
+    }
+}
+
+/// Parameters of an intermediate CA certificate
+#[derive(Clone, Debug)]
+pub struct IntermediateIssuanceOptions {
+    pub valid_from: UTCDate,
+    pub valid_to: UTCDate,
+    /// Maximum number of CAs below this one, unlimited when `None`
+    pub pathlen: Option<u8>,
+    pub key_usage: KeyUsage,
+    pub key_id_gen_method: KeyIdGenMethod,
+}
+
+impl IntermediateIssuanceOptions {
+    /// Options used by `Picky::generate_intermediate`: valid from now for the given duration,
+    /// a path length of 0, digital signature, certificate and CRL signing usages.
+    pub fn new(validity_duration: chrono::Duration) -> Self {
+        let now = chrono::offset::Utc::now();
+        Self {
+            valid_from: UTCDate::from(now),
+            valid_to: UTCDate::from(now + validity_duration),
+            pathlen: Some(0),
+            key_usage: KeyUsage::default()
+                .with_digital_signature(true)
+                .with_key_cert_sign(true)
+                .with_crl_sign(true),
+            key_id_gen_method: KeyIdGenMethod::default(),
+        }
+    }
+
+    /// Checks these options fit within the constraints of the issuer certificate
+    fn check_issuer(&self, issuer_cert: &Cert) -> Result<(), PickyError> {
+        let issuer_not_after = issuer_cert.valid_not_after();
+        if self.valid_to > issuer_not_after {
+            return Err(PickyError::ValidityOutsideIssuer {
+                not_after: self.valid_to.clone(),
+                issuer_not_after,
+            });
+        }
+
+        let outside_issuer = |description: String| Err(PickyError::OutsideIssuerConstraints { description });
+
+        let issuer_not_before = issuer_cert.valid_not_before();
+        if self.valid_from < issuer_not_before {
+            return outside_issuer(format!(
+                "certificate would be valid from {}, before its issuer ({})",
+                self.valid_from, issuer_not_before
+            ));
+        }
+
+        match issuer_cert.basic_constraints() {
+            Ok(constraints) if constraints.ca() == Some(true) => match (constraints.pathlen(), self.pathlen) {
+                (Some(0), _) => return outside_issuer("issuer path length doesn't allow CA certificates".to_owned()),
+                (Some(issuer_pathlen), None) => {
+                    return outside_issuer(format!(
+                        "unlimited path length exceeds the issuer path length of {}",
+                        issuer_pathlen
+                    ))
+                }
+                (Some(issuer_pathlen), Some(pathlen)) if pathlen >= issuer_pathlen => {
+                    return outside_issuer(format!(
+                        "path length of {} must be lower than the issuer path length of {}",
+                        pathlen, issuer_pathlen
+                    ))
+                }
+                _ => {}
+            },
+            _ => return outside_issuer("issuer isn't a CA".to_owned()),
+        }
+
+        if let Ok(issuer_key_usage) = issuer_cert.key_usage() {
+            if !issuer_key_usage.key_cert_sign() {
+                return outside_issuer("issuer key usage doesn't allow certificate signing".to_owned());
+            }
+        }
+
+        if !self.key_usage.key_cert_sign() {
+            return outside_issuer("key usage must allow certificate signing".to_owned());
+        }
+
+        Ok(())
+    }
+}
+
 pub struct Picky;
 impl Picky {
+    #[cfg(test)]
     pub fn generate_root(
         name: &str,
         key: &PrivateKey,
         signature_hash_type: SignatureAlgorithm,
+        key_id_gen_method: KeyIdGenMethod,
+        validity_duration: chrono::Duration,
     ) -> Result<Cert, PickyError> {
-        // validity
-        let now = chrono::offset::Utc::now();
-        let valid_from = UTCDate::from(now);
-        let valid_to = UTCDate::from(now + chrono::Duration::days(DEFAULT_ROOT_DURATION_DAYS));
-
-        let mut key_usage = KeyUsage::default();
-        key_usage.set_key_cert_sign(true);
-        key_usage.set_crl_sign(true);
+        let options = RootIssuanceOptions {
+            key_id_gen_method,
+            ..RootIssuanceOptions::new(validity_duration)
+        };
+        Self::generate_root_with_options(name, key, signature_hash_type, options)
+    }
 
-        CertificateBuilder::new()
-            .valididy(valid_from, valid_to)
+    pub fn generate_root_with_options(
+        name: &str,
+        key: &PrivateKey,
+        signature_hash_type: SignatureAlgorithm,
+        options: RootIssuanceOptions,
+    ) -> Result<Cert, PickyError> {
+        let builder = CertificateBuilder::new();
+        builder
+            .valididy(options.valid_from, options.valid_to)
             .self_signed(DirectoryName::new_common_name(name), &key)
             .signature_hash_type(signature_hash_type)
+            .key_id_gen_method(options.key_id_gen_method)
             .ca(true)
-            .key_usage(key_usage)
-            .build()
-            .map_err(|e| PickyError::Certificate { source: e })
+            .key_usage(options.key_usage);
+
+        if let Some(pathlen) = options.pathlen {
+            builder.pathlen(pathlen);
+        }
+
+        builder.build().map_err(|e| PickyError::Certificate { source: e })
     }
 
+    #[cfg(test)]
     pub fn generate_intermediate(
         intermediate_name: &str,
         intermediate_key: PublicKey,
         issuer_cert: &Cert,
         issuer_key: &PrivateKey,
         signature_hash_type: SignatureAlgorithm,
+        key_id_gen_method: KeyIdGenMethod,
+        validity_duration: chrono::Duration,
     ) -> Result<Cert, PickyError> {
-        // validity
-        let now = chrono::offset::Utc::now();
-        let valid_from = UTCDate::from(now);
-        let valid_to = UTCDate::from(now + chrono::Duration::days(DEFAULT_INTERMEDIATE_DURATION_DAYS));
+        let options = IntermediateIssuanceOptions {
+            key_id_gen_method,
+            ..IntermediateIssuanceOptions::new(validity_duration)
+        };
+        Self::generate_intermediate_with_options(
+            intermediate_name,
+            intermediate_key,
+            issuer_cert,
+            issuer_key,
+            signature_hash_type,
+            options,
+        )
+    }
 
-        let subject_name = DirectoryName::new_common_name(intermediate_name);
+    /// Generates an intermediate CA certificate, failing if the options don't fit within the issuer constraints
+    /// (validity, path length and key usage).
+    pub fn generate_intermediate_with_options(
+        intermediate_name: &str,
+        intermediate_key: PublicKey,
+        issuer_cert: &Cert,
+        issuer_key: &PrivateKey,
+        signature_hash_type: SignatureAlgorithm,
+        options: IntermediateIssuanceOptions,
+    ) -> Result<Cert, PickyError> {
+        options.check_issuer(issuer_cert)?;
 
-        let mut key_usage = KeyUsage::default();
-        key_usage.set_digital_signature(true);
-        key_usage.set_key_cert_sign(true);
-        key_usage.set_crl_sign(true);
+        let subject_name = DirectoryName::new_common_name(intermediate_name);
 
-        CertificateBuilder::new()
-            .valididy(valid_from, valid_to)
+        let builder = CertificateBuilder::new();
+        builder
+            .valididy(options.valid_from, options.valid_to)
             .subject(subject_name, intermediate_key)
             .issuer_cert(issuer_cert, issuer_key)
             .signature_hash_type(signature_hash_type)
-            .key_usage(key_usage)
-            .pathlen(0)
-            .ca(true)
-            .build()
-            .map_err(|e| PickyError::Certificate { source: e })
+            .key_id_gen_method(options.key_id_gen_method)
+            .key_usage(options.key_usage)
+            .ca(true);
+
+        if let Some(pathlen) = options.pathlen {
+            builder.pathlen(pathlen);
+        }
+
+        builder.build().map_err(|e| PickyError::Certificate { source: e })
     }
 
     pub fn generate_leaf_from_csr(
@@ -117,36 +335,136 @@ impl Picky {
         issuer_cert: &Cert,
         issuer_key: &PrivateKey,
         signature_hash_type: SignatureAlgorithm,
-        dns_name: &str,
+        key_id_gen_method: KeyIdGenMethod,
+        subject_alt_name: Option<GeneralNames>,
         validity_duration: chrono::Duration,
     ) -> Result<Cert, PickyError> {
-        // validity
-        let now = chrono::offset::Utc::now();
-        let valid_from = UTCDate::from(now);
-        let valid_to = UTCDate::from(now + validity_duration);
+        let csr = csr.into_verified().map_err(|source| PickyError::Csr { source })?;
+        let options = LeafIssuanceOptions {
+            subject_alt_name,
+            ..LeafIssuanceOptions::new(validity_duration)
+        };
+        Self::generate_leaf_from_csr_with_options(
+            csr,
+            issuer_cert,
+            issuer_key,
+            signature_hash_type,
+            key_id_gen_method,
+            options,
+        )
+    }
 
-        let mut key_usage = KeyUsage::default();
-        key_usage.set_digital_signature(true);
-        key_usage.set_key_encipherment(true);
+    pub fn generate_leaf_from_csr_with_options(
+        csr: VerifiedCsr,
+        issuer_cert: &Cert,
+        issuer_key: &PrivateKey,
+        signature_hash_type: SignatureAlgorithm,
+        key_id_gen_method: KeyIdGenMethod,
+        options: LeafIssuanceOptions,
+    ) -> Result<Cert, PickyError> {
+        let LeafIssuanceOptions {
+            valid_from,
+            valid_to,
+            extended_key_usage,
+            subject_alt_name,
+            key_usage,
+            authority_info_access,
+        } = options;
+
+        let builder = CertificateBuilder::new();
+        builder
+            .valididy(valid_from, valid_to)
+            .subject_from_verified_csr(csr)
+            .issuer_cert(issuer_cert, issuer_key)
+            .signature_hash_type(signature_hash_type)
+            .key_id_gen_method(key_id_gen_method)
+            .key_usage(key_usage)
+            .extended_key_usage(extended_key_usage);
 
-        let eku = vec![oids::kp_server_auth(), oids::kp_client_auth()];
+        if let Some(subject_alt_name) = subject_alt_name {
+            builder.subject_alt_name(subject_alt_name);
+        }
 
-        let dns_gn = GeneralName::new_dns_name(dns_name).map_err(|e| PickyError::InvalidCharSet {
-            source: e,
-            input: dns_name.to_owned(),
-        })?;
-        let san = GeneralNames::new(dns_gn);
+        if let Some(authority_info_access) = authority_info_access {
+            builder.authority_info_access(authority_info_access);
+        }
 
-        CertificateBuilder::new()
-            .valididy(valid_from, valid_to)
-            .subject_from_csr(csr)
+        builder.build().map_err(|e| PickyError::Certificate { source: e })
+    }
+
+    /// Generates a CRL listing the given revoked certificates.
+    ///
+    /// The current unix timestamp is used as CRL number so that it keeps increasing across restarts.
+    pub fn generate_crl(
+        issuer_cert: &Cert,
+        issuer_key: &PrivateKey,
+        signature_hash_type: SignatureAlgorithm,
+        revocations: &[RevocationEntry],
+    ) -> Result<Crl, PickyError> {
+        let now = chrono::offset::Utc::now();
+        let this_update = UTCDate::from(now);
+        let next_update = UTCDate::from(now + chrono::Duration::days(DEFAULT_CRL_DURATION_DAYS));
+
+        let builder = CrlBuilder::new();
+        builder
             .issuer_cert(issuer_cert, issuer_key)
             .signature_hash_type(signature_hash_type)
-            .key_usage(key_usage)
-            .extended_key_usage(eku.into())
-            .subject_alt_name(san)
-            .build()
-            .map_err(|e| PickyError::Certificate { source: e })
+            .this_update(this_update)
+            .next_update(next_update)
+            .crl_number(now.timestamp() as u64);
+
+        for revocation in revocations {
+            let (serial_number, revocation_date, reason) = parse_revocation_entry(revocation)?;
+            builder.revoked_certificate(serial_number, revocation_date, reason);
+        }
+
+        builder.build().map_err(|e| PickyError::Crl { source: e })
+    }
+
+    /// Answers each `CertID` of the request: certificates issued by `issuer_cert` are either revoked
+    /// (as listed in `revocations`) or good, anything else is unknown to this responder.
+    pub fn generate_ocsp_response(
+        issuer_cert: &Cert,
+        responder_cert: &Cert,
+        responder_key: &PrivateKey,
+        signature_hash_type: SignatureAlgorithm,
+        request: &OcspRequest,
+        revocations: &[RevocationEntry],
+    ) -> Result<OcspResponse, PickyError> {
+        let now = UTCDate::from(chrono::offset::Utc::now());
+
+        let builder = OcspResponseBuilder::new();
+        builder
+            .responder(responder_cert.clone(), responder_key)
+            .signature_hash_type(signature_hash_type)
+            .produced_at(now.clone());
+
+        if let Some(nonce) = request.nonce() {
+            builder.nonce(nonce.clone());
+        }
+
+        for cert_id in request.cert_ids() {
+            let status = if cert_id_matches_issuer(cert_id, issuer_cert).unwrap_or(false) {
+                let mut status = CertStatus::Good;
+                for revocation in revocations {
+                    let (serial_number, revocation_time, reason) = parse_revocation_entry(revocation)?;
+                    if serial_number.as_unsigned_bytes_be() == cert_id.serial_number.as_unsigned_bytes_be() {
+                        status = CertStatus::Revoked {
+                            revocation_time,
+                            reason,
+                        };
+                        break;
+                    }
+                }
+                status
+            } else {
+                CertStatus::Unknown
+            };
+
+            builder.single_response(cert_id.clone(), status, now.clone(), None);
+        }
+
+        builder.build().map_err(|e| PickyError::Ocsp { source: e })
     }
 
     /// This function is also used by tests in release mode.
@@ -159,6 +477,7 @@ impl Picky {
     /// Private Key generation is insanely slow on debug builds.
     /// Therefore this function (only to be used in debug profile please) doesn't generate new private keys.
     /// It returns a random pre-generated private key from a pool: security-wise, this is extremely bad.
+    /// Sizes without pre-generated keys are generated anyway so that configured sizes take effect.
     #[cfg(any(feature = "pre-gen-pk", all(debug_assertions, test)))]
     pub fn generate_private_key(bits: usize) -> Result<PrivateKey, PickyError> {
         use crate::test_files::*;
@@ -179,6 +498,7 @@ impl Picky {
             RSA_2048_PK_6,
             RSA_2048_PK_7,
         ];
+        const RSA_3072_POOL: [&str; 2] = [RSA_3072_PK_1, RSA_3072_PK_2];
         const RSA_4096_POOL: [&str; 3] = [RSA_4096_PK_1, RSA_4096_PK_2, RSA_4096_PK_3];
 
         let choice: usize = random();
@@ -187,12 +507,17 @@ impl Picky {
                 log::info!("selected pk number {} from RSA_2048_POOL", choice % RSA_2048_POOL.len());
                 RSA_2048_POOL[choice % RSA_2048_POOL.len()]
             }
+            3072 => {
+                log::info!("selected pk number {} from RSA_3072_POOL", choice % RSA_3072_POOL.len());
+                RSA_3072_POOL[choice % RSA_3072_POOL.len()]
+            }
             4096 => {
                 log::info!("selected pk number {} from RSA_4096_POOL", choice % RSA_4096_POOL.len());
                 RSA_4096_POOL[choice % RSA_4096_POOL.len()]
             }
             num_bits => {
-                return Err(PickyError::NoPreGenKey { num_bits });
+                log::warn!("no pre-generated {}-bits private key, generating one", num_bits);
+                return PrivateKey::generate_rsa(num_bits).map_err(|e| PickyError::PrivateKeyGeneration { source: e });
             }
         };
 
@@ -211,6 +536,26 @@ impl Picky {
     }
 }
 
+fn parse_revocation_entry(
+    revocation: &RevocationEntry,
+) -> Result<(IntegerAsn1, UTCDate, Option<CrlReason>), PickyError> {
+    let serial_number = hex::decode(&revocation.serial_number).map_err(|e| PickyError::InvalidRevocationEntry {
+        addressing_hash: revocation.addressing_hash.clone(),
+        description: format!("invalid serial number: {}", e),
+    })?;
+    let revocation_date = UTCDate::from(chrono::Utc.timestamp(revocation.revocation_date, 0));
+    let reason = match revocation.reason {
+        Some(code) => Some(
+            CrlReason::from_u8(code).ok_or_else(|| PickyError::InvalidRevocationEntry {
+                addressing_hash: revocation.addressing_hash.clone(),
+                description: format!("invalid reason code: {}", code),
+            })?,
+        ),
+        None => None,
+    };
+    Ok((serial_number.into(), revocation_date, reason))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -261,5 +606,227 @@ mod tests {
             "couldn't parse private key as pkcs8: (asn1) couldn't deserialize private key info (pkcs8): InvalidData ; \
              couldn't parse private key as raw der-encoded RSA key either: (asn1) couldn't deserialize rsa private key: InvalidData"
         );
+        assert_eq!(err.kind(), ErrorKind::Decoding);
+    }
+
+    #[test]
+    fn leaf_issuance_options() {
+        let ca_key = Picky::generate_private_key(2048).unwrap();
+        let ca = Picky::generate_root(
+            "Picky Test Root CA",
+            &ca_key,
+            SignatureAlgorithm::RsaPkcs1v15(picky::hash::HashAlgorithm::SHA2_256),
+            KeyIdGenMethod::default(),
+            chrono::Duration::days(3650),
+        )
+        .unwrap();
+
+        let leaf_key = Picky::generate_private_key(2048).unwrap();
+        let csr = Csr::generate(
+            DirectoryName::new_common_name("signer.example.com"),
+            &leaf_key,
+            SignatureAlgorithm::RsaPkcs1v15(picky::hash::HashAlgorithm::SHA2_256),
+        )
+        .unwrap();
+
+        let options = LeafIssuanceOptions {
+            extended_key_usage: ExtendedKeyUsage::default().with_code_signing(),
+            key_usage: KeyUsage::default().with_digital_signature(true),
+            ..LeafIssuanceOptions::new(chrono::Duration::days(30))
+        };
+        let valid_from = options.valid_from.clone();
+        let leaf = Picky::generate_leaf_from_csr_with_options(
+            csr.into_verified().unwrap(),
+            &ca,
+            &ca_key,
+            SignatureAlgorithm::RsaPkcs1v15(picky::hash::HashAlgorithm::SHA2_256),
+            KeyIdGenMethod::default(),
+            options,
+        )
+        .unwrap();
+
+        let leaf = Cert::from_der(&leaf.to_der().unwrap()).unwrap();
+        assert_eq!(leaf.valid_not_before(), valid_from);
+        assert_eq!(
+            leaf.valid_not_after(),
+            UTCDate::from(chrono::DateTime::<chrono::Utc>::from(valid_from) + chrono::Duration::days(30))
+        );
+        let eku = leaf.extended_key_usage().unwrap();
+        assert!(eku.code_signing());
+        assert!(!eku.server_auth());
+        assert_eq!(leaf.key_usage().unwrap().to_string(), "Digital Signature");
+    }
+
+    #[test]
+    fn constrained_ca_hierarchy() {
+        let sha256 = SignatureAlgorithm::RsaPkcs1v15(picky::hash::HashAlgorithm::SHA2_256);
+        let root_key = Picky::generate_private_key(2048).unwrap();
+        let root = Picky::generate_root_with_options(
+            "Picky Test Root CA",
+            &root_key,
+            sha256,
+            RootIssuanceOptions {
+                pathlen: Some(1),
+                ..RootIssuanceOptions::new(chrono::Duration::days(25 * 365))
+            },
+        )
+        .unwrap();
+        let root = Cert::from_der(&root.to_der().unwrap()).unwrap();
+        assert_eq!(root.basic_constraints().unwrap().ca(), Some(true));
+        assert_eq!(root.basic_constraints().unwrap().pathlen(), Some(1));
+        assert_eq!(root.key_usage().unwrap().to_string(), "Certificate Sign, CRL Sign");
+
+        let intermediate_key = Picky::generate_private_key(2048).unwrap();
+        let options = IntermediateIssuanceOptions {
+            key_usage: KeyUsage::default().with_key_cert_sign(true).with_crl_sign(true),
+            key_id_gen_method: "spk-value-sha1".parse().unwrap(),
+            ..IntermediateIssuanceOptions::new(chrono::Duration::days(5 * 365))
+        };
+        let intermediate = Picky::generate_intermediate_with_options(
+            "Picky Test Authority",
+            intermediate_key.to_public_key(),
+            &root,
+            &root_key,
+            sha256,
+            options.clone(),
+        )
+        .unwrap();
+        let intermediate = Cert::from_der(&intermediate.to_der().unwrap()).unwrap();
+        assert_eq!(intermediate.basic_constraints().unwrap().ca(), Some(true));
+        assert_eq!(intermediate.basic_constraints().unwrap().pathlen(), Some(0));
+        assert_eq!(
+            intermediate.key_usage().unwrap().to_string(),
+            "Certificate Sign, CRL Sign"
+        );
+        assert_eq!(intermediate.valid_not_after(), options.valid_to);
+        assert_eq!(
+            intermediate.subject_key_identifier().unwrap(),
+            options
+                .key_id_gen_method
+                .generate_from(intermediate.public_key())
+                .unwrap()
+                .as_slice()
+        );
+
+        let generate = |options: IntermediateIssuanceOptions, issuer: &Cert| {
+            Picky::generate_intermediate_with_options(
+                "Picky Test Authority",
+                intermediate_key.to_public_key(),
+                issuer,
+                &root_key,
+                sha256,
+                options,
+            )
+            .unwrap_err()
+            .to_string()
+        };
+
+        let unlimited = IntermediateIssuanceOptions {
+            pathlen: None,
+            ..options.clone()
+        };
+        assert_eq!(
+            generate(unlimited, &root),
+            "certificate doesn't fit within its issuer constraints: \
+             unlimited path length exceeds the issuer path length of 1"
+        );
+
+        let too_deep = IntermediateIssuanceOptions {
+            pathlen: Some(1),
+            ..options.clone()
+        };
+        assert_eq!(
+            generate(too_deep, &root),
+            "certificate doesn't fit within its issuer constraints: \
+             path length of 1 must be lower than the issuer path length of 1"
+        );
+
+        let no_cert_sign = IntermediateIssuanceOptions {
+            key_usage: KeyUsage::default().with_crl_sign(true),
+            ..options.clone()
+        };
+        assert_eq!(
+            generate(no_cert_sign, &root),
+            "certificate doesn't fit within its issuer constraints: key usage must allow certificate signing"
+        );
+
+        let outliving = IntermediateIssuanceOptions {
+            valid_to: UTCDate::from(chrono::Utc::now() + chrono::Duration::days(30 * 365)),
+            ..options.clone()
+        };
+        assert!(generate(outliving, &root).contains("after its issuer"));
+
+        assert_eq!(
+            generate(options, &intermediate),
+            "certificate doesn't fit within its issuer constraints: issuer path length doesn't allow CA certificates"
+        );
+    }
+
+    #[test]
+    fn generate_crl_with_revocations() {
+        let root_key = Picky::generate_private_key(2048).unwrap();
+        let root = Picky::generate_root(
+            "Picky Test Root CA",
+            &root_key,
+            SignatureAlgorithm::RsaPkcs1v15(picky::hash::HashAlgorithm::SHA2_256),
+            KeyIdGenMethod::default(),
+            chrono::Duration::days(3650),
+        )
+        .unwrap();
+
+        let revocations = vec![
+            RevocationEntry {
+                addressing_hash: "hash_1".to_owned(),
+                issuer_name: "Picky Test Root CA".to_owned(),
+                serial_number: "1a2b3c4d".to_owned(),
+                revocation_date: 1_600_000_000,
+                reason: Some(1),
+            },
+            RevocationEntry {
+                addressing_hash: "hash_2".to_owned(),
+                issuer_name: "Picky Test Root CA".to_owned(),
+                serial_number: "00ff".to_owned(),
+                revocation_date: 1_600_000_100,
+                reason: None,
+            },
+        ];
+
+        let crl = Picky::generate_crl(
+            &root,
+            &root_key,
+            SignatureAlgorithm::RsaPkcs1v15(picky::hash::HashAlgorithm::SHA2_256),
+            &revocations,
+        )
+        .expect("couldn't generate CRL");
+
+        crl.verify(&root).expect("couldn't verify CRL");
+        assert!(crl.next_update().unwrap() > UTCDate::from(chrono::Utc::now()));
+        assert!(crl.is_revoked(&vec![0x1a, 0x2b, 0x3c, 0x4d].into()));
+        assert!(crl.is_revoked(&vec![0x00, 0xff].into()));
+
+        let revoked = crl.revoked_certificates();
+        assert_eq!(revoked.len(), 2);
+        assert_eq!(revoked[0].reason_code(), Some(CrlReason::KeyCompromise));
+        assert_eq!(
+            UTCDate::from(revoked[1].revocation_date.clone()),
+            UTCDate::from(chrono::Utc.timestamp(1_600_000_100, 0))
+        );
+
+        let invalid = vec![RevocationEntry {
+            reason: Some(7),
+            ..revocations[0].clone()
+        }];
+        let err = Picky::generate_crl(
+            &root,
+            &root_key,
+            SignatureAlgorithm::RsaPkcs1v15(picky::hash::HashAlgorithm::SHA2_256),
+            &invalid,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid revocation entry for hash_1: invalid reason code: 7"
+        );
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
     }
 }
@@ -0,0 +1,184 @@
+use crate::utils::wildcard_match;
+use picky::x509::name::{GeneralName, GeneralNames};
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+
+/// Restricts names certificates can be issued for.
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct IssuancePolicy {
+    /// DNS name patterns (`*` matching any part of a single label) or suffixes (starting with a dot).
+    /// Any DNS name is allowed when empty.
+    #[serde(default)]
+    pub allowed_names: Vec<String>,
+    #[serde(default)]
+    pub allow_ip_addresses: bool,
+    #[serde(default)]
+    pub require_cn_in_san: bool,
+}
+
+impl IssuancePolicy {
+    /// Evaluates the subject common name and subject alternative names of a certificate to issue.
+    ///
+    /// Returns the rejected names on failure.
+    pub fn check(&self, common_name: Option<&str>, san: &GeneralNames) -> Result<(), Vec<String>> {
+        let mut rejected = Vec::new();
+        let mut san_dns_names = Vec::new();
+
+        for name in san.to_general_names() {
+            match name {
+                GeneralName::DNSName(dns_name) => {
+                    let dns_name = String::from_utf8_lossy(dns_name.as_bytes()).into_owned();
+                    if !self.allows_dns_name(&dns_name) {
+                        rejected.push(dns_name.clone());
+                    }
+                    san_dns_names.push(dns_name);
+                }
+                GeneralName::IpAddress(addr) if self.allow_ip_addresses && (addr.len() == 4 || addr.len() == 16) => {}
+                other => rejected.push(describe_general_name(&other)),
+            }
+        }
+
+        if let Some(common_name) = common_name {
+            let in_san = san_dns_names
+                .iter()
+                .any(|dns_name| dns_name.eq_ignore_ascii_case(common_name));
+            if ((self.require_cn_in_san && !in_san) || !self.allows_dns_name(common_name))
+                && !rejected.iter().any(|name| name == common_name)
+            {
+                rejected.push(common_name.to_owned());
+            }
+        }
+
+        if rejected.is_empty() {
+            Ok(())
+        } else {
+            Err(rejected)
+        }
+    }
+
+    fn allows_dns_name(&self, name: &str) -> bool {
+        self.allowed_names.is_empty()
+            || self.allowed_names.iter().any(|allowed| {
+                if allowed.starts_with('.') {
+                    name.len() > allowed.len() && name.to_lowercase().ends_with(&allowed.to_lowercase())
+                } else {
+                    wildcard_match(allowed, name)
+                }
+            })
+    }
+}
+
+pub(crate) fn describe_general_name(name: &GeneralName) -> String {
+    match name {
+        GeneralName::RFC822Name(email) => format!("email:{}", String::from_utf8_lossy(email.as_bytes())),
+        GeneralName::DNSName(dns_name) => String::from_utf8_lossy(dns_name.as_bytes()).into_owned(),
+        GeneralName::URI(uri) => format!("uri:{}", String::from_utf8_lossy(uri.as_bytes())),
+        GeneralName::IpAddress(addr) if addr.len() == 4 || addr.len() == 16 => {
+            let addr = if addr.len() == 4 {
+                let mut octets = [0; 4];
+                octets.copy_from_slice(addr);
+                IpAddr::from(octets)
+            } else {
+                let mut octets = [0; 16];
+                octets.copy_from_slice(addr);
+                IpAddr::from(octets)
+            };
+            format!("ip:{}", addr)
+        }
+        GeneralName::IpAddress(addr) => format!("ip:{}", hex::encode(addr)),
+        GeneralName::DirectoryName(name) => format!("dirname:{}", name),
+        other => format!("{:?}", other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn san(names: Vec<GeneralName>) -> GeneralNames {
+        GeneralNames::from(names)
+    }
+
+    fn dns(name: &str) -> GeneralName {
+        GeneralName::new_dns_name(name).unwrap()
+    }
+
+    fn policy() -> IssuancePolicy {
+        IssuancePolicy {
+            allowed_names: vec!["*.internal.example.com".to_owned(), ".lab.example.com".to_owned()],
+            allow_ip_addresses: false,
+            require_cn_in_san: true,
+        }
+    }
+
+    #[test]
+    fn allowed_name() {
+        let policy = policy();
+        policy
+            .check(
+                Some("web.internal.example.com"),
+                &san(vec![dns("web.internal.example.com")]),
+            )
+            .unwrap();
+        policy
+            .check(Some("a.b.lab.example.com"), &san(vec![dns("a.b.lab.example.com")]))
+            .unwrap();
+        IssuancePolicy::default()
+            .check(Some("login.microsoft.com"), &san(vec![dns("login.microsoft.com")]))
+            .unwrap();
+    }
+
+    #[test]
+    fn rejected_name() {
+        let policy = policy();
+        let rejected = policy
+            .check(Some("login.microsoft.com"), &san(vec![dns("login.microsoft.com")]))
+            .unwrap_err();
+        assert_eq!(rejected, vec!["login.microsoft.com".to_owned()]);
+
+        let rejected = policy
+            .check(Some("lab.example.com"), &san(vec![dns("lab.example.com")]))
+            .unwrap_err();
+        assert_eq!(rejected, vec!["lab.example.com".to_owned()]);
+
+        // common name missing from SAN
+        let rejected = policy
+            .check(
+                Some("web.internal.example.com"),
+                &san(vec![dns("db.internal.example.com")]),
+            )
+            .unwrap_err();
+        assert_eq!(rejected, vec!["web.internal.example.com".to_owned()]);
+    }
+
+    #[test]
+    fn mixed_san_list() {
+        let names = san(vec![
+            dns("web.internal.example.com"),
+            dns("evil.example.org"),
+            GeneralName::from(IpAddr::from([10, 0, 0, 1])),
+            GeneralName::new_rfc822_name("admin@internal.example.com").unwrap(),
+        ]);
+
+        let rejected = policy().check(Some("web.internal.example.com"), &names).unwrap_err();
+        assert_eq!(
+            rejected,
+            vec![
+                "evil.example.org".to_owned(),
+                "ip:10.0.0.1".to_owned(),
+                "email:admin@internal.example.com".to_owned(),
+            ]
+        );
+
+        let mut policy = policy();
+        policy.allow_ip_addresses = true;
+        let rejected = policy.check(Some("web.internal.example.com"), &names).unwrap_err();
+        assert_eq!(
+            rejected,
+            vec![
+                "evil.example.org".to_owned(),
+                "email:admin@internal.example.com".to_owned()
+            ]
+        );
+    }
+}
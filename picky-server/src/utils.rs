@@ -19,6 +19,44 @@ pub fn unix_epoch() -> u64 {
         .as_secs()
 }
 
+/// Matches a DNS name against a pattern, label by label and case-insensitively.
+///
+/// A `*` in a pattern label matches any non-empty part of the corresponding label.
+pub fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern_labels = pattern.split('.').collect::<Vec<&str>>();
+    let name_labels = name.split('.').collect::<Vec<&str>>();
+
+    pattern_labels.len() == name_labels.len()
+        && pattern_labels
+            .iter()
+            .zip(name_labels.iter())
+            .all(|(pattern, label)| match pattern.find('*') {
+                Some(idx) => {
+                    let (prefix, suffix) = (&pattern[..idx], &pattern[idx + 1..]);
+                    !label.is_empty()
+                        && label.len() >= prefix.len() + suffix.len()
+                        && label.to_lowercase().starts_with(&prefix.to_lowercase())
+                        && label.to_lowercase().ends_with(&suffix.to_lowercase())
+                }
+                None => unicase::eq_ascii(*pattern, *label),
+            })
+}
+
+/// Checks whether a name is a syntactically valid DNS name (a leading `*` label being allowed).
+pub fn is_dns_name(name: &str) -> bool {
+    let labels = name.split('.').collect::<Vec<&str>>();
+
+    name.len() <= 253
+        && labels.iter().enumerate().all(|(idx, label)| {
+            (idx == 0 && *label == "*")
+                || (!label.is_empty()
+                    && label.len() <= 63
+                    && !label.starts_with('-')
+                    && !label.ends_with('-')
+                    && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-'))
+        })
+}
+
 // === Greedy Error === //
 
 /// I eat and format any error I can
@@ -143,3 +181,45 @@ macro_rules! path_or_impl_serde {
 path_or_impl_serde!(Cert);
 path_or_impl_serde!(PrivateKey);
 path_or_impl_serde!(PublicKey);
+
+// PEM text is kept as is: a certificate chain holds several PEM blocks
+impl<'de> de::Deserialize<'de> for PathOr<String> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        struct V;
+        impl<'de> de::Visitor<'de> for V {
+            type Value = PathOr<String>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a path or some pem-formatted text")
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                if v.trim_start().starts_with("-----BEGIN") {
+                    Ok(PathOr::Some(v.to_owned()))
+                } else {
+                    Ok(PathOr::Path(PathBuf::from(v)))
+                }
+            }
+        }
+
+        deserializer.deserialize_str(V)
+    }
+}
+
+impl Serialize for PathOr<String> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        match self {
+            PathOr::Path(path) => serializer.serialize_str(&path.to_string_lossy()),
+            PathOr::Some(pem) => serializer.serialize_str(pem),
+        }
+    }
+}
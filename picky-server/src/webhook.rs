@@ -0,0 +1,368 @@
+//! Webhook notifications sent whenever a certificate is issued, registered or revoked.
+//!
+//! Payloads are queued and delivered by a background task, so that an unreachable webhook never
+//! delays nor fails the request that triggered the notification.
+
+use crate::{addressing::encode_to_canonical_address, config::Config};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac, NewMac};
+use picky::x509::Cert;
+use serde::Serialize;
+use sha2::Sha256;
+use std::{sync::Arc, time::Duration};
+use tokio::sync::mpsc;
+
+/// Header carrying `sha256=<hex>`, the HMAC-SHA256 of the request body keyed with the webhook secret
+pub const SIGNATURE_HEADER: &str = "Picky-Signature";
+
+/// Number of payloads waiting for delivery past which new notifications are dropped
+const QUEUE_SIZE: usize = 1024;
+
+/// How long a webhook may take to answer a delivery attempt
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEvent {
+    /// Certificate signed by one of our CAs
+    Issued,
+    /// Certificate pushed to `/cert`
+    Registered,
+    Revoked,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct WebhookPayload {
+    pub event: WebhookEvent,
+    pub subject: String,
+    /// Hex-encoded serial number
+    pub serial: String,
+    /// Hex-encoded subject key identifier, if any
+    pub ski: Option<String>,
+    /// RFC 3339 notAfter date
+    pub not_after: String,
+    /// Canonical address the certificate is served at
+    pub multihash: String,
+    /// Unix timestamp of the event, letting receivers reject replayed payloads
+    pub timestamp: i64,
+}
+
+impl WebhookPayload {
+    pub fn new(event: WebhookEvent, cert: &Cert) -> Result<Self, String> {
+        let der = cert
+            .to_der()
+            .map_err(|e| format!("couldn't encode certificate: {}", e))?;
+        Ok(Self {
+            event,
+            subject: cert.subject_name().to_string(),
+            serial: hex::encode(cert.serial_number().as_signed_bytes_be()),
+            ski: cert.subject_key_identifier().ok().map(hex::encode),
+            not_after: DateTime::<Utc>::from(cert.valid_not_after()).to_rfc3339(),
+            multihash: encode_to_canonical_address(&der),
+            timestamp: Utc::now().timestamp(),
+        })
+    }
+}
+
+/// Value of the signature header for `body`
+pub fn sign_payload(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_varkey(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Handle queuing notifications for the configured webhooks, cheap to clone
+#[derive(Clone, Default)]
+pub struct Webhooks {
+    queue: Option<mpsc::Sender<WebhookPayload>>,
+}
+
+impl Webhooks {
+    /// Notifications are discarded
+    pub fn disabled() -> Self {
+        Self::default()
+    }
+
+    /// Spawns the delivery task when webhooks are configured.
+    ///
+    /// Must be called from within the tokio runtime.
+    pub fn start(config: &Config) -> Self {
+        let secret = match &config.webhook_secret {
+            Some(secret) if !config.webhook_urls.is_empty() => secret.clone(),
+            _ => return Self::disabled(),
+        };
+
+        let client = match reqwest::Client::builder().timeout(DELIVERY_TIMEOUT).build() {
+            Ok(client) => client,
+            Err(e) => {
+                log::error!("couldn't build webhook client, webhooks are disabled: {}", e);
+                return Self::disabled();
+            }
+        };
+
+        let delivery = Arc::new(Delivery {
+            client,
+            secret,
+            max_attempts: config.webhook_max_attempts,
+            retry_delay: Duration::from_millis(config.webhook_retry_delay_ms),
+        });
+        let (queue, receiver) = mpsc::channel(QUEUE_SIZE);
+        tokio::spawn(run_deliveries(config.webhook_urls.clone(), delivery, receiver));
+
+        Self { queue: Some(queue) }
+    }
+
+    /// Queues a notification about `cert`, never waiting for its delivery
+    pub fn notify(&self, event: WebhookEvent, cert: &Cert) {
+        let queue = match &self.queue {
+            Some(queue) => queue,
+            None => return,
+        };
+
+        match WebhookPayload::new(event, cert) {
+            Ok(payload) => {
+                if let Err(e) = queue.clone().try_send(payload) {
+                    log::error!("couldn't queue {:?} webhook notification: {}", event, e);
+                }
+            }
+            Err(e) => log::error!("couldn't build {:?} webhook notification: {}", event, e),
+        }
+    }
+}
+
+struct Delivery {
+    client: reqwest::Client,
+    secret: String,
+    max_attempts: u32,
+    retry_delay: Duration,
+}
+
+/// Fans every queued payload out to the webhooks, each delivery being retried on its own
+async fn run_deliveries(urls: Vec<String>, delivery: Arc<Delivery>, mut receiver: mpsc::Receiver<WebhookPayload>) {
+    while let Some(payload) = receiver.recv().await {
+        let body = match serde_json::to_vec(&payload) {
+            Ok(body) => body,
+            Err(e) => {
+                log::error!("couldn't encode webhook payload {:?}: {}", payload, e);
+                continue;
+            }
+        };
+        let signature = sign_payload(&delivery.secret, &body);
+
+        for url in &urls {
+            tokio::spawn(deliver(
+                Arc::clone(&delivery),
+                url.clone(),
+                body.clone(),
+                signature.clone(),
+            ));
+        }
+    }
+}
+
+/// POSTs the payload until the webhook answers with a success status, waiting twice as long after every failure
+async fn deliver(delivery: Arc<Delivery>, url: String, body: Vec<u8>, signature: String) {
+    let mut delay = delivery.retry_delay;
+    for attempt in 1..=delivery.max_attempts {
+        let result = delivery
+            .client
+            .post(&url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(SIGNATURE_HEADER, signature.as_str())
+            .body(body.clone())
+            .send()
+            .await;
+        let error = match result {
+            Ok(response) if response.status().is_success() => return,
+            Ok(response) => format!("answered {}", response.status()),
+            Err(e) => e.to_string(),
+        };
+
+        if attempt == delivery.max_attempts {
+            log::error!("giving up on webhook {} after {} attempt(s): {}", url, attempt, error);
+            return;
+        }
+        log::warn!(
+            "webhook {} delivery attempt {} failed, retrying in {:?}: {}",
+            url,
+            attempt,
+            delay,
+            error
+        );
+        tokio::time::delay_for(delay).await;
+        delay *= 2;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::picky_controller::Picky;
+    use picky::{hash::HashAlgorithm, key::PrivateKey, pem::Pem, signature::SignatureAlgorithm, x509::KeyIdGenMethod};
+    use std::{
+        collections::HashMap,
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+    };
+    use tokio_test::block_on;
+
+    struct Received {
+        headers: HashMap<String, String>,
+        body: Vec<u8>,
+    }
+
+    /// Answers requests with the given statuses, in order, forwarding every request received
+    fn spawn_listener(statuses: Vec<u16>) -> (String, tokio::sync::mpsc::UnboundedReceiver<Received>) {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind listener");
+        let url = format!(
+            "http://{}/hooks/picky",
+            listener.local_addr().expect("listener address")
+        );
+        let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+
+        std::thread::spawn(move || {
+            for status in statuses {
+                let (stream, _) = listener.accept().expect("accept");
+                let mut reader = BufReader::new(stream);
+
+                let mut headers = HashMap::new();
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).expect("read header");
+                    let line = line.trim_end();
+                    if line.is_empty() {
+                        break;
+                    }
+                    let mut split = line.splitn(2, ':');
+                    if let (Some(name), Some(value)) = (split.next(), split.next()) {
+                        headers.insert(name.trim().to_lowercase(), value.trim().to_owned());
+                    }
+                }
+                let len = headers
+                    .get("content-length")
+                    .map_or(0, |len| len.parse::<usize>().expect("content length"));
+                let mut body = vec![0; len];
+                reader.read_exact(&mut body).expect("read body");
+
+                let response = format!(
+                    "HTTP/1.1 {} Webhook\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    status
+                );
+                reader.get_mut().write_all(response.as_bytes()).expect("write response");
+                if sender.send(Received { headers, body }).is_err() {
+                    break;
+                }
+            }
+        });
+
+        (url, receiver)
+    }
+
+    fn test_cert() -> Cert {
+        let key = PrivateKey::from_pem(&crate::test_files::RSA_2048_PK_1.parse::<Pem>().unwrap()).unwrap();
+        Picky::generate_root(
+            "Webhook Root CA",
+            &key,
+            SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_256),
+            KeyIdGenMethod::default(),
+            chrono::Duration::days(365),
+        )
+        .expect("root")
+    }
+
+    fn webhook_config(url: String) -> Config {
+        Config {
+            webhook_urls: vec![url],
+            webhook_secret: Some("hook-secret".to_owned()),
+            webhook_retry_delay_ms: 10,
+            ..Default::default()
+        }
+    }
+
+    async fn next(receiver: &mut tokio::sync::mpsc::UnboundedReceiver<Received>) -> Received {
+        tokio::time::timeout(Duration::from_secs(10), receiver.recv())
+            .await
+            .expect("webhook wasn't called")
+            .expect("listener stopped")
+    }
+
+    #[test]
+    fn signed_payload_is_delivered() {
+        let cert = test_cert();
+        let (url, mut receiver) = spawn_listener(vec![200]);
+
+        // the delivery task lives in the runtime the webhooks are started from
+        let received = block_on(async {
+            let webhooks = Webhooks::start(&webhook_config(url));
+            webhooks.notify(WebhookEvent::Issued, &cert);
+            next(&mut receiver).await
+        });
+
+        assert_eq!(received.headers["content-type"], "application/json");
+        let signature = &received.headers[&SIGNATURE_HEADER.to_lowercase()];
+        assert_eq!(signature, &sign_payload("hook-secret", &received.body));
+        let mut mac = Hmac::<Sha256>::new_varkey(b"hook-secret").unwrap();
+        mac.update(&received.body);
+        mac.verify(&hex::decode(signature.strip_prefix("sha256=").unwrap()).unwrap())
+            .expect("signature doesn't match payload");
+
+        let payload: serde_json::Value = serde_json::from_slice(&received.body).expect("json payload");
+        assert_eq!(payload["event"], "issued");
+        assert_eq!(payload["subject"], "CN=Webhook Root CA");
+        assert_eq!(
+            payload["serial"],
+            hex::encode(cert.serial_number().as_signed_bytes_be()).as_str()
+        );
+        assert_eq!(
+            payload["ski"],
+            hex::encode(cert.subject_key_identifier().unwrap()).as_str()
+        );
+        assert_eq!(
+            payload["not_after"],
+            DateTime::<Utc>::from(cert.valid_not_after()).to_rfc3339().as_str()
+        );
+        assert_eq!(
+            payload["multihash"],
+            encode_to_canonical_address(&cert.to_der().unwrap()).as_str()
+        );
+        assert!(payload["timestamp"].as_i64().expect("timestamp") > 0);
+
+        assert_ne!(
+            sign_payload("other-secret", &received.body),
+            *signature,
+            "signature must depend on the secret"
+        );
+    }
+
+    #[test]
+    fn failed_delivery_is_retried() {
+        let cert = test_cert();
+        let (url, mut receiver) = spawn_listener(vec![500, 503, 204]);
+
+        let (first, second, third) = block_on(async {
+            let webhooks = Webhooks::start(&webhook_config(url));
+            webhooks.notify(WebhookEvent::Revoked, &cert);
+            (
+                next(&mut receiver).await,
+                next(&mut receiver).await,
+                next(&mut receiver).await,
+            )
+        });
+
+        assert_eq!(first.body, second.body);
+        assert_eq!(second.body, third.body);
+        assert_eq!(
+            third.headers[&SIGNATURE_HEADER.to_lowercase()],
+            sign_payload("hook-secret", &third.body)
+        );
+        let payload: serde_json::Value = serde_json::from_slice(&third.body).expect("json payload");
+        assert_eq!(payload["event"], "revoked");
+    }
+
+    #[test]
+    fn disabled_without_webhooks() {
+        let webhooks = block_on(async { Webhooks::start(&Config::default()) });
+        assert!(webhooks.queue.is_none());
+        webhooks.notify(WebhookEvent::Registered, &test_cert());
+    }
+}
@@ -0,0 +1,33 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use picky::{
+    jose::jws::{peek_header, Jws, JwsAlg, JwsHeader},
+    key::PrivateKey,
+    pem::Pem,
+};
+
+const KEY_PEM: &str = include_str!("../../test_assets/private_keys/rsa-2048-pk_1.key");
+const PAYLOAD_SIZE: usize = 8 * 1024;
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let pem = KEY_PEM.parse::<Pem>().expect("couldn't parse pem");
+    let private_key = PrivateKey::from_pem(&pem).expect("couldn't parse private key");
+    let token = Jws {
+        header: JwsHeader {
+            kid: Some(String::from("bench-key")),
+            ..JwsHeader::new(JwsAlg::RS256)
+        },
+        payload: vec![b'a'; PAYLOAD_SIZE],
+    }
+    .encode(&private_key)
+    .expect("couldn't encode jws");
+
+    let mut group = c.benchmark_group("jws header of a 8 KiB payload token");
+    group.bench_function("peek_header", |b| b.iter(|| peek_header(&token).unwrap()));
+    group.bench_function("decode_without_validation", |b| {
+        b.iter(|| Jws::decode_without_validation(&token).unwrap().header)
+    });
+    group.finish();
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
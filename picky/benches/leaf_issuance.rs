@@ -0,0 +1,74 @@
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use picky::{
+    hash::HashAlgorithm,
+    key::PrivateKey,
+    pem::Pem,
+    signature::SignatureAlgorithm,
+    x509::{certificate::CertificateBuilder, csr::Csr, date::UTCDate, name::DirectoryName, Cert},
+};
+
+const ISSUER_KEY_PEM: &str = include_str!("../../test_assets/private_keys/rsa-2048-pk_1.key");
+const SUBJECT_KEY_PEM: &str = include_str!("../../test_assets/private_keys/rsa-2048-pk_2.key");
+
+fn parse_key(pem_str: &str) -> PrivateKey {
+    let pem = pem_str.parse::<Pem>().expect("couldn't parse pem");
+    PrivateKey::from_pem(&pem).expect("couldn't parse private key")
+}
+
+fn builder(issuer_key: &PrivateKey) -> CertificateBuilder<'_> {
+    let builder = CertificateBuilder::new();
+    builder
+        .valididy(UTCDate::ymd(2020, 1, 1).unwrap(), UTCDate::ymd(2021, 1, 1).unwrap())
+        .issuer(DirectoryName::new_common_name("Bench Authority"), issuer_key)
+        .authority_key_identifier(vec![1, 2, 3, 4])
+        .serial_number(vec![1]);
+    builder
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let issuer_key = parse_key(ISSUER_KEY_PEM);
+    let subject_key = parse_key(SUBJECT_KEY_PEM);
+    let csr = Csr::generate(
+        DirectoryName::new_common_name("bench.example"),
+        &subject_key,
+        SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_256),
+    )
+    .expect("couldn't generate csr");
+    let verified_csr = csr.clone().into_verified().expect("couldn't verify csr");
+
+    let from_csr = |csr: Csr| -> Cert { builder(&issuer_key).subject_from_csr(csr).build().unwrap() };
+    let expected = from_csr(csr.clone());
+    let from_verified = builder(&issuer_key)
+        .subject_from_verified_csr(verified_csr)
+        .build()
+        .unwrap();
+    assert_eq!(expected, from_verified, "issued certificates should be identical");
+
+    // a CSR checked by the issuer before issuance, as when looking for a reusable certificate first
+    let mut group = c.benchmark_group("leaf issuance");
+    group.sample_size(1000);
+    group.bench_function("verify then issue from csr", |b| {
+        b.iter_batched(
+            || csr.clone(),
+            |csr| {
+                csr.verify().unwrap();
+                from_csr(csr)
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    group.bench_function("issue from verified csr", |b| {
+        b.iter_batched(
+            || csr.clone(),
+            |csr| {
+                let csr = csr.into_verified().unwrap();
+                builder(&issuer_key).subject_from_verified_csr(csr).build().unwrap()
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
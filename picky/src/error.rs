@@ -0,0 +1,32 @@
+//! Error classification shared by all picky error types
+//!
+//! Every error type exposes a `kind` method returning an [`ErrorKind`](enum.ErrorKind.html).
+//! Kinds are stable across releases and allow matching on the nature of a failure
+//! without depending on the exact variant, which may change as new variants are added.
+//! Wrapping variants (e.g. `CertError::InvalidCertificate`) report the kind of the error they carry.
+
+/// Stable classification of picky errors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// Input couldn't be decoded (PEM, base64, ASN.1 DER, JSON, UTF-8…)
+    Decoding,
+    /// Value couldn't be encoded
+    Encoding,
+    /// Input is well-formed but rejected (unexpected PEM label, missing builder argument, invalid parameter…)
+    InvalidInput,
+    /// Algorithm or method isn't supported
+    UnsupportedAlgorithm,
+    /// Signature doesn't match the signed data
+    BadSignature,
+    /// Certificate, token or signature isn't valid yet
+    NotYetValid,
+    /// Certificate, token or signature expired
+    Expired,
+    /// Certification path is broken (missing root, issuer mismatch, path too long…)
+    InvalidChain,
+    /// Requested element (extension, claim…) is missing
+    NotFound,
+    /// Cryptographic backend failure
+    Crypto,
+}
@@ -1,3 +1,4 @@
+use crate::error::ErrorKind;
 use std::borrow::Cow;
 use thiserror::Error;
 
@@ -13,6 +14,16 @@ pub enum HttpRequestError {
     Unexpected { reason: String },
 }
 
+impl HttpRequestError {
+    /// Stable classification of this error
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            HttpRequestError::HeaderValueToStr { .. } => ErrorKind::Decoding,
+            HttpRequestError::Unexpected { .. } => ErrorKind::InvalidInput,
+        }
+    }
+}
+
 pub trait HttpRequest {
     fn get_header_concatenated_values<'a>(&'a self, header_name: &str) -> Result<Cow<'a, str>, HttpRequestError>;
     fn get_lowercased_method(&self) -> Result<Cow<'_, str>, HttpRequestError>;
@@ -1,4 +1,5 @@
 use crate::{
+    error::ErrorKind,
     hash::HashAlgorithm,
     http::http_request::{HttpRequest, HttpRequestError},
     key::{PrivateKey, PublicKey},
@@ -68,6 +69,26 @@ pub enum HttpSignatureError {
     IncompatibleAlgorithm { value: SignatureAlgorithm },
 }
 
+impl HttpSignatureError {
+    /// Stable classification of this error
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            HttpSignatureError::Base64Decoding { .. } => ErrorKind::Decoding,
+            HttpSignatureError::NotYetValid { .. } => ErrorKind::NotYetValid,
+            HttpSignatureError::Expired { .. } => ErrorKind::Expired,
+            HttpSignatureError::Signature { source } => source.kind(),
+            HttpSignatureError::SigningStringGeneration { source } => source.kind(),
+            HttpSignatureError::InvalidSigningString { .. }
+            | HttpSignatureError::MissingBuilderArgument { .. }
+            | HttpSignatureError::BuilderEmptyHeaders
+            | HttpSignatureError::BuilderHeadersProvidedWithPreGenerated
+            | HttpSignatureError::MissingRequiredParameter { .. }
+            | HttpSignatureError::InvalidParameter { .. }
+            | HttpSignatureError::IncompatibleAlgorithm { .. } => ErrorKind::InvalidInput,
+        }
+    }
+}
+
 impl From<DecodeError> for HttpSignatureError {
     fn from(e: DecodeError) -> Self {
         Self::Base64Decoding { source: e }
@@ -833,6 +854,14 @@ const HTTP_SIG_ALGO_RSA_SHA2_512: &str = "rsa-sha2-512";
 const HTTP_SIG_ALGO_RSA_SHA3_384: &str = "rsa-sha3-384";
 const HTTP_SIG_ALGO_RSA_SHA3_512: &str = "rsa-sha3-512";
 
+const HTTP_SIG_ALGO_RSA_PSS_SHA_256: &str = "rsa-pss-sha256";
+const HTTP_SIG_ALGO_RSA_PSS_SHA_384: &str = "rsa-pss-sha384";
+const HTTP_SIG_ALGO_RSA_PSS_SHA_512: &str = "rsa-pss-sha512";
+
+const HTTP_SIG_ALGO_ECDSA_SHA_256: &str = "ecdsa-sha256";
+const HTTP_SIG_ALGO_ECDSA_SHA_384: &str = "ecdsa-sha384";
+const HTTP_SIG_ALGO_ECDSA_SHA_512: &str = "ecdsa-sha512";
+
 fn to_http_sig_algo_str(algo: SignatureAlgorithm) -> &'static str {
     match algo {
         SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA1) => HTTP_SIG_ALGO_RSA_SHA_1,
@@ -842,6 +871,12 @@ fn to_http_sig_algo_str(algo: SignatureAlgorithm) -> &'static str {
         SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_512) => HTTP_SIG_ALGO_RSA_SHA_512,
         SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA3_384) => HTTP_SIG_ALGO_RSA_SHA3_384,
         SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA3_512) => HTTP_SIG_ALGO_RSA_SHA3_512,
+        SignatureAlgorithm::RsaPssSha256 => HTTP_SIG_ALGO_RSA_PSS_SHA_256,
+        SignatureAlgorithm::RsaPssSha384 => HTTP_SIG_ALGO_RSA_PSS_SHA_384,
+        SignatureAlgorithm::RsaPssSha512 => HTTP_SIG_ALGO_RSA_PSS_SHA_512,
+        SignatureAlgorithm::EcdsaSha256 => HTTP_SIG_ALGO_ECDSA_SHA_256,
+        SignatureAlgorithm::EcdsaSha384 => HTTP_SIG_ALGO_ECDSA_SHA_384,
+        SignatureAlgorithm::EcdsaSha512 => HTTP_SIG_ALGO_ECDSA_SHA_512,
     }
 }
 
@@ -862,6 +897,12 @@ fn from_http_sig_algo_str(s: &str) -> Option<SignatureAlgorithm> {
         }
         HTTP_SIG_ALGO_RSA_SHA3_384 => Some(SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA3_384)),
         HTTP_SIG_ALGO_RSA_SHA3_512 => Some(SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA3_512)),
+        HTTP_SIG_ALGO_RSA_PSS_SHA_256 => Some(SignatureAlgorithm::RsaPssSha256),
+        HTTP_SIG_ALGO_RSA_PSS_SHA_384 => Some(SignatureAlgorithm::RsaPssSha384),
+        HTTP_SIG_ALGO_RSA_PSS_SHA_512 => Some(SignatureAlgorithm::RsaPssSha512),
+        HTTP_SIG_ALGO_ECDSA_SHA_256 => Some(SignatureAlgorithm::EcdsaSha256),
+        HTTP_SIG_ALGO_ECDSA_SHA_384 => Some(SignatureAlgorithm::EcdsaSha384),
+        HTTP_SIG_ALGO_ECDSA_SHA_512 => Some(SignatureAlgorithm::EcdsaSha512),
         _ => None,
     }
 }
@@ -871,8 +912,22 @@ fn is_algo_compatible_with_key(algo: SignatureAlgorithm, key: &PublicKey) -> boo
 
     let key_algo = Into::<String>::into(key.as_inner().algorithm.oid());
     match algo {
-        // Currently, SignatureHashType only contains RSA methods, so this is a an auto-win
-        _ if key_algo == RSA_ENCRYPTION => true,
+        // Any RSA method is an auto-win with a generic RSA key
+        SignatureAlgorithm::RsaPkcs1v15(_)
+        | SignatureAlgorithm::RsaPssSha256
+        | SignatureAlgorithm::RsaPssSha384
+        | SignatureAlgorithm::RsaPssSha512
+            if key_algo == RSA_ENCRYPTION =>
+        {
+            true
+        }
+
+        // Same goes for ECDSA methods with an elliptic curve key
+        SignatureAlgorithm::EcdsaSha256 | SignatureAlgorithm::EcdsaSha384 | SignatureAlgorithm::EcdsaSha512
+            if key_algo == EC_PUBLIC_KEY =>
+        {
+            true
+        }
 
         // Otherwise we need to check for specific hash algorithm
         SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA1) if key_algo == SHA1_WITH_RSA_ENCRYPTION => true,
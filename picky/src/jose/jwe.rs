@@ -3,6 +3,7 @@
 //! See [RFC7516](https://tools.ietf.org/html/rfc7516).
 
 use crate::{
+    error::ErrorKind,
     jose::jwk::Jwk,
     key::{PrivateKey, PublicKey},
 };
@@ -23,8 +24,8 @@ type Aes192Gcm = aes_gcm::AesGcm<aes_gcm::aes::Aes192, aes_gcm::aead::generic_ar
 #[non_exhaustive]
 pub enum JweError {
     /// RSA error
-    #[error("RSA error: {context}")]
-    Rsa { context: String },
+    #[error("RSA error: {source}")]
+    Rsa { source: rsa::errors::Error },
 
     /// AES-GCM error (opaque)
     #[error("AES-GCM error (opaque)")]
@@ -66,9 +67,25 @@ pub enum JweError {
     },
 }
 
+impl JweError {
+    /// Stable classification of this error
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            JweError::Rsa { .. } | JweError::AesGcm => ErrorKind::Crypto,
+            JweError::Key { source } => source.kind(),
+            JweError::Json { .. }
+            | JweError::InvalidEncoding { .. }
+            | JweError::Base64Decoding { .. }
+            | JweError::InvalidUtf8 { .. } => ErrorKind::Decoding,
+            JweError::UnsupportedAlgorithm { .. } => ErrorKind::UnsupportedAlgorithm,
+            JweError::InvalidSize { .. } => ErrorKind::InvalidInput,
+        }
+    }
+}
+
 impl From<rsa::errors::Error> for JweError {
     fn from(e: rsa::errors::Error) -> Self {
-        Self::Rsa { context: e.to_string() }
+        Self::Rsa { source: e }
     }
 }
 
@@ -3,13 +3,16 @@
 //! See [RFC7517](https://tools.ietf.org/html/rfc7517).
 
 use crate::{
+    error::ErrorKind,
     jose::{
         jwe::{JweAlg, JweEnc},
         jws::JwsAlg,
     },
-    key::PublicKey,
+    key::{KeyError, PublicKey, PUBLIC_KEY_PEM_LABEL},
+    pem::Pem,
 };
 use base64::DecodeError;
+use picky_asn1::wrapper::IntegerAsn1;
 use picky_asn1_x509::SubjectPublicKeyInfo;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
@@ -17,6 +20,7 @@ use thiserror::Error;
 // === error type === //
 
 #[derive(Debug, Error)]
+#[non_exhaustive]
 pub enum JwkError {
     /// Json error
     #[error("JSON error: {source}")]
@@ -29,6 +33,21 @@ pub enum JwkError {
     /// unsupported algorithm
     #[error("unsupported algorithm: {algorithm}")]
     UnsupportedAlgorithm { algorithm: &'static str },
+
+    /// key error
+    #[error("key error: {source}")]
+    Key { source: KeyError },
+}
+
+impl JwkError {
+    /// Stable classification of this error
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            JwkError::Json { .. } | JwkError::Base64Decoding { .. } => ErrorKind::Decoding,
+            JwkError::UnsupportedAlgorithm { .. } => ErrorKind::UnsupportedAlgorithm,
+            JwkError::Key { source } => source.kind(),
+        }
+    }
 }
 
 impl From<serde_json::Error> for JwkError {
@@ -43,6 +62,12 @@ impl From<DecodeError> for JwkError {
     }
 }
 
+impl From<KeyError> for JwkError {
+    fn from(e: KeyError) -> Self {
+        Self::Key { source: e }
+    }
+}
+
 // === key type === //
 
 /// Algorithm type for JWK
@@ -225,8 +250,8 @@ impl Jwk {
 
         match &public_key.as_inner().subject_public_key {
             SerdePublicKey::RSA(BitStringAsn1Container(rsa)) => Ok(Self::new(JwkKeyType::new_rsa_key(
-                rsa.modulus.as_signed_bytes_be(),
-                rsa.public_exponent.as_signed_bytes_be(),
+                rsa.modulus.as_unsigned_bytes_be(),
+                rsa.public_exponent.as_unsigned_bytes_be(),
             ))),
             SerdePublicKey::EC(_) => Err(JwkError::UnsupportedAlgorithm {
                 algorithm: "elliptic curves",
@@ -245,7 +270,10 @@ impl Jwk {
     pub fn to_public_key(&self) -> Result<PublicKey, JwkError> {
         match &self.key {
             JwkKeyType::Rsa(rsa) => {
-                let spki = SubjectPublicKeyInfo::new_rsa_key(rsa.modulus()?.into(), rsa.public_exponent()?.into());
+                let spki = SubjectPublicKeyInfo::new_rsa_key(
+                    IntegerAsn1::from_unsigned_bytes_be(rsa.modulus()?),
+                    IntegerAsn1::from_unsigned_bytes_be(rsa.public_exponent()?),
+                );
                 Ok(spki.into())
             }
             JwkKeyType::Ec => Err(JwkError::UnsupportedAlgorithm {
@@ -256,6 +284,18 @@ impl Jwk {
             }),
         }
     }
+
+    /// Parses a `PUBLIC KEY` or `RSA PUBLIC KEY` PEM into a JWK
+    pub fn from_pem(pem: &Pem) -> Result<Self, JwkError> {
+        let public_key = PublicKey::from_pem(pem)?;
+        Self::from_public_key(&public_key)
+    }
+
+    /// Encodes the key as a `PUBLIC KEY` (SubjectPublicKeyInfo) PEM
+    pub fn to_pem(&self) -> Result<Pem<'static>, JwkError> {
+        let der = self.to_public_key()?.to_der()?;
+        Ok(Pem::new(PUBLIC_KEY_PEM_LABEL, der))
+    }
 }
 
 // === jwk set === //
@@ -404,4 +444,25 @@ mod tests {
         let decoded = JwkSet::from_json(&encoded).unwrap();
         pretty_assertions::assert_eq!(decoded, expected);
     }
+
+    #[test]
+    fn pem_round_trip() {
+        let jwk = get_jwk_set().keys.remove(0);
+
+        let pem = jwk.to_pem().unwrap();
+        assert_eq!(pem.label(), "PUBLIC KEY");
+        let public_key = PublicKey::from_pem(&pem).unwrap();
+        assert_eq!(Jwk::from_public_key(&public_key).unwrap().key, jwk.key);
+
+        let pem = pem.to_string().parse::<Pem>().unwrap();
+        let decoded = Jwk::from_pem(&pem).unwrap();
+        pretty_assertions::assert_eq!(decoded.key, jwk.key);
+    }
+
+    #[test]
+    fn pem_with_unexpected_label() {
+        let pem = Pem::new("CERTIFICATE", vec![0x30, 0x00]);
+        let err = Jwk::from_pem(&pem).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    }
 }
@@ -3,6 +3,7 @@
 //! See [RFC7515](https://tools.ietf.org/html/rfc7515).
 
 use crate::{
+    error::ErrorKind,
     hash::HashAlgorithm,
     jose::jwk::Jwk,
     key::{PrivateKey, PublicKey},
@@ -19,8 +20,8 @@ use thiserror::Error;
 #[non_exhaustive]
 pub enum JwsError {
     /// RSA error
-    #[error("RSA error: {context}")]
-    Rsa { context: String },
+    #[error("RSA error: {source}")]
+    Rsa { source: rsa::errors::Error },
 
     /// Json error
     #[error("JSON error: {source}")]
@@ -46,9 +47,23 @@ pub enum JwsError {
     },
 }
 
+impl JwsError {
+    /// Stable classification of this error
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            JwsError::Rsa { .. } => ErrorKind::Crypto,
+            JwsError::Signature { source } => source.kind(),
+            JwsError::Json { .. }
+            | JwsError::InvalidEncoding { .. }
+            | JwsError::Base64Decoding { .. }
+            | JwsError::InvalidUtf8 { .. } => ErrorKind::Decoding,
+        }
+    }
+}
+
 impl From<rsa::errors::Error> for JwsError {
     fn from(e: rsa::errors::Error) -> Self {
-        Self::Rsa { context: e.to_string() }
+        Self::Rsa { source: e }
     }
 }
 
@@ -110,13 +125,13 @@ pub enum JwsAlg {
     /// ECDSA using P-521 and SHA-512 (unsupported)
     ES512,
 
-    /// RSASSA-PSS using SHA-256 and MGF1 with SHA-256 (unsupported)
+    /// RSASSA-PSS using SHA-256 and MGF1 with SHA-256
     PS256,
 
-    /// RSASSA-PSS using SHA-384 and MGF1 with SHA-384 (unsupported)
+    /// RSASSA-PSS using SHA-384 and MGF1 with SHA-384
     PS384,
 
-    /// RSASSA-PSS using SHA-512 and MGF1 with SHA-512 (unsupported)
+    /// RSASSA-PSS using SHA-512 and MGF1 with SHA-512
     PS512,
 }
 
@@ -128,6 +143,9 @@ impl TryFrom<SignatureAlgorithm> for JwsAlg {
             SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_256) => Ok(Self::RS256),
             SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_384) => Ok(Self::RS384),
             SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_512) => Ok(Self::RS512),
+            SignatureAlgorithm::RsaPssSha256 => Ok(Self::PS256),
+            SignatureAlgorithm::RsaPssSha384 => Ok(Self::PS384),
+            SignatureAlgorithm::RsaPssSha512 => Ok(Self::PS512),
             unsupported => Err(SignatureError::UnsupportedAlgorithm {
                 algorithm: format!("{:?}", unsupported),
             }),
@@ -143,6 +161,9 @@ impl TryFrom<JwsAlg> for SignatureAlgorithm {
             JwsAlg::RS256 => Ok(SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_256)),
             JwsAlg::RS384 => Ok(SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_384)),
             JwsAlg::RS512 => Ok(SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_512)),
+            JwsAlg::PS256 => Ok(SignatureAlgorithm::RsaPssSha256),
+            JwsAlg::PS384 => Ok(SignatureAlgorithm::RsaPssSha384),
+            JwsAlg::PS512 => Ok(SignatureAlgorithm::RsaPssSha512),
             unsupported => Err(SignatureError::UnsupportedAlgorithm {
                 algorithm: format!("{:?}", unsupported),
             }),
@@ -293,6 +314,27 @@ impl Jws {
     }
 }
 
+/// Decodes the header of a compact JWS without touching its payload or signature.
+///
+/// Useful to pick a key (e.g. using `kid`) before decoding the whole token.
+///
+/// **Beware**: nothing is verified, the returned header is untrusted input.
+pub fn peek_header(compact: &str) -> Result<JwsHeader, JwsError> {
+    let invalid_encoding = || JwsError::InvalidEncoding {
+        input: compact.to_owned(),
+    };
+
+    // `find`, `rfind` and `contains` with a char pattern are backed by memchr
+    let first_dot_idx = compact.find('.').ok_or_else(invalid_encoding)?;
+    let last_dot_idx = compact.rfind('.').ok_or_else(invalid_encoding)?;
+    if first_dot_idx == 0 || first_dot_idx == last_dot_idx || compact[first_dot_idx + 1..last_dot_idx].contains('.') {
+        return Err(invalid_encoding());
+    }
+
+    let header_json = base64::decode_config(&compact[..first_dot_idx], base64::URL_SAFE_NO_PAD)?;
+    Ok(serde_json::from_slice::<JwsHeader>(&header_json)?)
+}
+
 fn decode_impl(encoded_token: &str, public_key: Option<&PublicKey>) -> Result<Jws, JwsError> {
     let first_dot_idx = encoded_token.find('.').ok_or_else(|| JwsError::InvalidEncoding {
         input: encoded_token.to_owned(),
@@ -359,6 +401,17 @@ mod tests {
         assert_eq!(jwt.payload.as_slice(), PAYLOAD.as_bytes());
     }
 
+    #[test]
+    fn rsa_pss_sha256_round_trip() {
+        let jws = Jws::new(JwsAlg::PS256, PAYLOAD.as_bytes().to_vec());
+        let encoded = jws.encode(&get_private_key_1()).unwrap();
+
+        let public_key = get_private_key_1().to_public_key();
+        let decoded = Jws::decode(&encoded, &public_key).unwrap();
+        assert_eq!(decoded.header.alg, JwsAlg::PS256);
+        assert_eq!(decoded.payload.as_slice(), PAYLOAD.as_bytes());
+    }
+
     #[test]
     fn decode_rsa_sha256_delayed_signature_check() {
         let jws = Jws::decode_without_validation(crate::test_files::JOSE_JWT_SIG_EXAMPLE).unwrap();
@@ -422,4 +475,51 @@ mod tests {
         let err = Jws::decode("abc", &public_key).err().unwrap();
         assert_eq!(err.to_string(), "input isn\'t a valid token string: abc");
     }
+
+    #[test]
+    fn peek_header_rsa_sha256() {
+        let header = peek_header(crate::test_files::JOSE_JWT_SIG_EXAMPLE).unwrap();
+        assert_eq!(
+            header,
+            JwsHeader {
+                typ: Some(String::from("JWT")),
+                ..JwsHeader::new(JwsAlg::RS256)
+            }
+        );
+    }
+
+    #[test]
+    fn peek_header_ignores_payload_and_signature() {
+        let header_base64 = base64::encode_config(br#"{"alg":"RS256","kid":"key-1"}"#, base64::URL_SAFE_NO_PAD);
+        let header = peek_header(&format!("{}.not base64!.nor this", header_base64)).unwrap();
+        assert_eq!(header.alg, JwsAlg::RS256);
+        assert_eq!(header.kid.as_deref(), Some("key-1"));
+    }
+
+    #[test]
+    fn peek_header_invalid_segments_err() {
+        for token in &[".abc.abc", "abc.abc", "abc", "abc.abc.abc.abc", "abc.abc.abc.abc.abc"] {
+            let err = peek_header(token).err().unwrap();
+            assert_eq!(err.to_string(), format!("input isn't a valid token string: {}", token));
+        }
+    }
+
+    #[test]
+    fn peek_header_malformed_header_err() {
+        let err = peek_header("aieoè~†.abc.abc").err().unwrap();
+        assert_eq!(err.to_string(), "couldn't decode base64: Invalid byte 195, offset 4.");
+
+        let err = peek_header("abc.abc.abc").err().unwrap();
+        assert_eq!(err.to_string(), "JSON error: expected value at line 1 column 1");
+
+        // valid JSON, but not a JWS header (missing `alg`)
+        let header_base64 = base64::encode_config(br#"{"typ":"JWT"}"#, base64::URL_SAFE_NO_PAD);
+        let err = peek_header(&format!("{}.abc.abc", header_base64)).err().unwrap();
+        assert_eq!(err.to_string(), "JSON error: missing field `alg` at line 1 column 13");
+
+        // unsupported algorithm
+        let header_base64 = base64::encode_config(br#"{"alg":"none"}"#, base64::URL_SAFE_NO_PAD);
+        let err = peek_header(&format!("{}.abc.abc", header_base64)).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::Decoding);
+    }
 }
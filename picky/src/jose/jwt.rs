@@ -1,5 +1,6 @@
 use super::jwe::Jwe;
 use crate::{
+    error::ErrorKind,
     jose::{
         jwe::{JweAlg, JweEnc, JweError, JweHeader},
         jws::{Jws, JwsAlg, JwsError, JwsHeader},
@@ -8,6 +9,7 @@ use crate::{
 };
 use core::fmt;
 use serde::{de::DeserializeOwned, Serialize};
+use std::{convert::TryFrom, time::Duration};
 use thiserror::Error;
 
 // === error type === //
@@ -43,11 +45,54 @@ pub enum JwtError {
     #[error("token expired (not after: {}, now: {} [leeway: {}])", not_after, now.numeric_date, now.leeway)]
     Expired { not_after: i64, now: JwtDate },
 
+    /// token was issued too long ago
+    #[error("token is too old (issued at: {}, max age: {}s, now: {} [leeway: {}])", issued_at, max_age.as_secs(), now.numeric_date, now.leeway)]
+    TooOld {
+        issued_at: i64,
+        max_age: Duration,
+        now: JwtDate,
+    },
+
+    /// token is issued in the future
+    #[error("token is issued in the future (issued at: {}, now: {} [leeway: {}])", issued_at, now.numeric_date, now.leeway)]
+    IssuedInFuture { issued_at: i64, now: JwtDate },
+
+    /// token isn't intended for any of the expected audiences
+    #[error("token audience mismatch (expected one of: {}, found: {})", expected.join(", "), found.join(", "))]
+    AudienceMismatch { expected: Vec<String>, found: Vec<String> },
+
+    /// token isn't issued by the expected issuer
+    #[error("token issuer mismatch (expected: {expected}, found: {found})")]
+    IssuerMismatch { expected: String, found: String },
+
+    /// token subject isn't the expected one
+    #[error("token subject mismatch (expected: {expected}, found: {found})")]
+    SubjectMismatch { expected: String, found: String },
+
     /// validator is invalid
     #[error("invalid validator: {description}")]
     InvalidValidator { description: &'static str },
 }
 
+impl JwtError {
+    /// Stable classification of this error
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            JwtError::Jws { source } => source.kind(),
+            JwtError::Jwe { source } => source.kind(),
+            JwtError::Json { .. } => ErrorKind::Decoding,
+            JwtError::InvalidRegisteredClaimType { .. }
+            | JwtError::InvalidValidator { .. }
+            | JwtError::AudienceMismatch { .. }
+            | JwtError::IssuerMismatch { .. }
+            | JwtError::SubjectMismatch { .. } => ErrorKind::InvalidInput,
+            JwtError::RequiredClaimMissing { .. } => ErrorKind::NotFound,
+            JwtError::NotYetValid { .. } | JwtError::IssuedInFuture { .. } => ErrorKind::NotYetValid,
+            JwtError::Expired { .. } | JwtError::TooOld { .. } => ErrorKind::Expired,
+        }
+    }
+}
+
 impl From<JwsError> for JwtError {
     fn from(s: JwsError) -> Self {
         Self::Jws { source: s }
@@ -119,8 +164,14 @@ enum CheckStrictness {
 #[derive(Debug, Clone)]
 pub struct JwtValidator<'a> {
     current_date: Option<&'a JwtDate>,
+    leeway: Option<u16>,
     expiration_claim: CheckStrictness,
     not_before_claim: CheckStrictness,
+    issued_at_claim: CheckStrictness,
+    max_age: Option<Duration>,
+    audiences: Vec<&'a str>,
+    issuer: Option<&'a str>,
+    subject: Option<&'a str>,
 }
 
 pub const NO_CHECK_VALIDATOR: JwtValidator<'static> = JwtValidator::no_check();
@@ -130,8 +181,14 @@ impl<'a> JwtValidator<'a> {
     pub const fn strict(current_date: &'a JwtDate) -> Self {
         Self {
             current_date: Some(current_date),
+            leeway: None,
             expiration_claim: CheckStrictness::Required,
             not_before_claim: CheckStrictness::Required,
+            issued_at_claim: CheckStrictness::Ignored,
+            max_age: None,
+            audiences: Vec::new(),
+            issuer: None,
+            subject: None,
         }
     }
 
@@ -139,8 +196,14 @@ impl<'a> JwtValidator<'a> {
     pub const fn lenient(current_date: &'a JwtDate) -> Self {
         Self {
             current_date: Some(current_date),
+            leeway: None,
             expiration_claim: CheckStrictness::Optional,
             not_before_claim: CheckStrictness::Optional,
+            issued_at_claim: CheckStrictness::Ignored,
+            max_age: None,
+            audiences: Vec::new(),
+            issuer: None,
+            subject: None,
         }
     }
 
@@ -148,8 +211,14 @@ impl<'a> JwtValidator<'a> {
     pub const fn no_check() -> Self {
         Self {
             current_date: None,
+            leeway: None,
             expiration_claim: CheckStrictness::Ignored,
             not_before_claim: CheckStrictness::Ignored,
+            issued_at_claim: CheckStrictness::Ignored,
+            max_age: None,
+            audiences: Vec::new(),
+            issuer: None,
+            subject: None,
         }
     }
 
@@ -158,6 +227,7 @@ impl<'a> JwtValidator<'a> {
             current_date: Some(current_date),
             expiration_claim: CheckStrictness::Required,
             not_before_claim: CheckStrictness::Required,
+            ..self
         }
     }
 
@@ -202,6 +272,69 @@ impl<'a> JwtValidator<'a> {
             ..self
         }
     }
+
+    /// Reject tokens issued in the future (according to the registered iat claim)
+    pub fn issued_at_check_required(self) -> Self {
+        Self {
+            issued_at_claim: CheckStrictness::Required,
+            ..self
+        }
+    }
+
+    pub fn issued_at_check_optional(self) -> Self {
+        Self {
+            issued_at_claim: CheckStrictness::Optional,
+            ..self
+        }
+    }
+
+    pub fn issued_at_check_ignored(self) -> Self {
+        Self {
+            issued_at_claim: CheckStrictness::Ignored,
+            ..self
+        }
+    }
+
+    /// Reject tokens issued more than `max_age` ago (according to the registered iat claim).
+    /// Tokens without iat claim are rejected. Current date leeway applies.
+    pub fn max_age(self, max_age: Duration) -> Self {
+        Self {
+            max_age: Some(max_age),
+            ..self
+        }
+    }
+
+    /// Overrides the leeway of the current date (whole seconds, saturating at `u16::MAX`).
+    pub fn leeway(self, leeway: Duration) -> Self {
+        Self {
+            leeway: Some(u16::try_from(leeway.as_secs()).unwrap_or(u16::MAX)),
+            ..self
+        }
+    }
+
+    /// Reject tokens whose registered aud claim (a string or an array of strings) doesn't contain `audience`.
+    ///
+    /// May be called several times: tokens intended for any of the given audiences are accepted.
+    pub fn require_audience(mut self, audience: &'a str) -> Self {
+        self.audiences.push(audience);
+        self
+    }
+
+    /// Reject tokens whose registered iss claim isn't `issuer`.
+    pub fn require_issuer(self, issuer: &'a str) -> Self {
+        Self {
+            issuer: Some(issuer),
+            ..self
+        }
+    }
+
+    /// Reject tokens whose registered sub claim isn't `subject`.
+    pub fn require_subject(self, subject: &'a str) -> Self {
+        Self {
+            subject: Some(subject),
+            ..self
+        }
+    }
 }
 
 // === JWT === //
@@ -209,6 +342,10 @@ impl<'a> JwtValidator<'a> {
 const JWT_TYPE: &str = "JWT";
 const EXPIRATION_TIME_CLAIM: &str = "exp";
 const NOT_BEFORE_CLAIM: &str = "nbf";
+const ISSUED_AT_CLAIM: &str = "iat";
+const AUDIENCE_CLAIM: &str = "aud";
+const ISSUER_CLAIM: &str = "iss";
+const SUBJECT_CLAIM: &str = "sub";
 
 pub struct Jwt<H, C> {
     pub header: H,
@@ -371,67 +508,145 @@ fn h_decode_and_validate_claims<C: DeserializeOwned>(
     claims_json: &[u8],
     validator: &JwtValidator,
 ) -> Result<C, JwtError> {
-    let claims = match (
+    if validator.max_age.is_some() && validator.current_date.is_none() {
+        return Err(JwtError::InvalidValidator {
+            description: "current date is missing",
+        });
+    }
+
+    let current_date = match (
         validator.current_date,
         validator.not_before_claim,
         validator.expiration_claim,
+        validator.issued_at_claim,
     ) {
-        (None, CheckStrictness::Required, _) | (None, _, CheckStrictness::Required) => {
+        (None, CheckStrictness::Required, _, _)
+        | (None, _, CheckStrictness::Required, _)
+        | (None, _, _, CheckStrictness::Required) => {
             return Err(JwtError::InvalidValidator {
                 description: "current date is missing",
             })
         }
-        (Some(current_date), nbf_strictness, exp_strictness) => {
-            let claims = serde_json::from_slice::<serde_json::Value>(claims_json)?;
-
-            let nbf_opt = claims.get(NOT_BEFORE_CLAIM);
-            match (nbf_strictness, nbf_opt) {
-                (CheckStrictness::Ignored, _) | (CheckStrictness::Optional, None) => {}
-                (CheckStrictness::Required, None) => {
-                    return Err(JwtError::RequiredClaimMissing {
-                        claim: NOT_BEFORE_CLAIM,
-                    })
-                }
-                (_, Some(nbf)) => {
-                    let nbf_i64 = nbf.as_i64().ok_or_else(|| JwtError::InvalidRegisteredClaimType {
-                        claim: NOT_BEFORE_CLAIM,
-                    })?;
-                    if !current_date.is_after(nbf_i64) {
-                        return Err(JwtError::NotYetValid {
-                            not_before: nbf_i64,
-                            now: current_date.clone(),
-                        });
-                    }
-                }
+        (Some(current_date), ..) => match validator.leeway {
+            Some(leeway) => Some(JwtDate::new_with_leeway(current_date.numeric_date, leeway)),
+            None => Some(current_date.clone()),
+        },
+        (None, ..) => None,
+    };
+
+    let checks_claims = validator.issuer.is_some() || validator.subject.is_some() || !validator.audiences.is_empty();
+    if current_date.is_none() && !checks_claims {
+        return Ok(serde_json::from_slice(claims_json)?);
+    }
+
+    let claims = serde_json::from_slice::<serde_json::Value>(claims_json)?;
+
+    if let Some(current_date) = &current_date {
+        if let Some(nbf) = h_get_numeric_date_claim(&claims, NOT_BEFORE_CLAIM, validator.not_before_claim)? {
+            if !current_date.is_after(nbf) {
+                return Err(JwtError::NotYetValid {
+                    not_before: nbf,
+                    now: current_date.clone(),
+                });
+            }
+        }
+
+        if let Some(exp) = h_get_numeric_date_claim(&claims, EXPIRATION_TIME_CLAIM, validator.expiration_claim)? {
+            if !current_date.is_before_strict(exp) {
+                return Err(JwtError::Expired {
+                    not_after: exp,
+                    now: current_date.clone(),
+                });
             }
+        }
 
-            let exp_opt = claims.get(EXPIRATION_TIME_CLAIM);
-            match (exp_strictness, exp_opt) {
-                (CheckStrictness::Ignored, _) | (CheckStrictness::Optional, None) => {}
-                (CheckStrictness::Required, None) => {
-                    return Err(JwtError::RequiredClaimMissing {
-                        claim: EXPIRATION_TIME_CLAIM,
-                    })
-                }
-                (_, Some(exp)) => {
-                    let exp_i64 = exp.as_i64().ok_or_else(|| JwtError::InvalidRegisteredClaimType {
-                        claim: EXPIRATION_TIME_CLAIM,
-                    })?;
-                    if !current_date.is_before_strict(exp_i64) {
-                        return Err(JwtError::Expired {
-                            not_after: exp_i64,
-                            now: current_date.clone(),
-                        });
-                    }
-                }
+        if let Some(iat) = h_get_numeric_date_claim(&claims, ISSUED_AT_CLAIM, validator.issued_at_claim)? {
+            if !current_date.is_after(iat) {
+                return Err(JwtError::IssuedInFuture {
+                    issued_at: iat,
+                    now: current_date.clone(),
+                });
             }
+        }
 
-            serde_json::value::from_value(claims)?
+        if let Some(max_age) = validator.max_age {
+            let iat = h_get_numeric_date_claim(&claims, ISSUED_AT_CLAIM, CheckStrictness::Required)?
+                .ok_or(JwtError::RequiredClaimMissing { claim: ISSUED_AT_CLAIM })?;
+            let max_age_i64 = i64::try_from(max_age.as_secs()).unwrap_or(i64::MAX);
+            if !current_date.is_before(iat.saturating_add(max_age_i64)) {
+                return Err(JwtError::TooOld {
+                    issued_at: iat,
+                    max_age,
+                    now: current_date.clone(),
+                });
+            }
         }
-        (None, _, _) => serde_json::from_slice(claims_json)?,
-    };
+    }
+
+    if !validator.audiences.is_empty() {
+        let found = match claims.get(AUDIENCE_CLAIM) {
+            None => return Err(JwtError::RequiredClaimMissing { claim: AUDIENCE_CLAIM }),
+            Some(serde_json::Value::String(aud)) => vec![aud.clone()],
+            Some(serde_json::Value::Array(auds)) => auds
+                .iter()
+                .map(|aud| aud.as_str().map(str::to_owned))
+                .collect::<Option<Vec<String>>>()
+                .ok_or(JwtError::InvalidRegisteredClaimType { claim: AUDIENCE_CLAIM })?,
+            Some(_) => return Err(JwtError::InvalidRegisteredClaimType { claim: AUDIENCE_CLAIM }),
+        };
 
-    Ok(claims)
+        if !found.iter().any(|aud| validator.audiences.contains(&aud.as_str())) {
+            return Err(JwtError::AudienceMismatch {
+                expected: validator.audiences.iter().map(|aud| (*aud).to_owned()).collect(),
+                found,
+            });
+        }
+    }
+
+    if let Some(expected) = validator.issuer {
+        let found = h_get_string_claim(&claims, ISSUER_CLAIM)?;
+        if found != expected {
+            return Err(JwtError::IssuerMismatch {
+                expected: expected.to_owned(),
+                found: found.to_owned(),
+            });
+        }
+    }
+
+    if let Some(expected) = validator.subject {
+        let found = h_get_string_claim(&claims, SUBJECT_CLAIM)?;
+        if found != expected {
+            return Err(JwtError::SubjectMismatch {
+                expected: expected.to_owned(),
+                found: found.to_owned(),
+            });
+        }
+    }
+
+    Ok(serde_json::value::from_value(claims)?)
+}
+
+fn h_get_numeric_date_claim(
+    claims: &serde_json::Value,
+    claim: &'static str,
+    strictness: CheckStrictness,
+) -> Result<Option<i64>, JwtError> {
+    match (strictness, claims.get(claim)) {
+        (CheckStrictness::Ignored, _) | (CheckStrictness::Optional, None) => Ok(None),
+        (CheckStrictness::Required, None) => Err(JwtError::RequiredClaimMissing { claim }),
+        (_, Some(value)) => value
+            .as_i64()
+            .map(Some)
+            .ok_or(JwtError::InvalidRegisteredClaimType { claim }),
+    }
+}
+
+fn h_get_string_claim<'a>(claims: &'a serde_json::Value, claim: &'static str) -> Result<&'a str, JwtError> {
+    claims
+        .get(claim)
+        .ok_or(JwtError::RequiredClaimMissing { claim })?
+        .as_str()
+        .ok_or(JwtError::InvalidRegisteredClaimType { claim })
 }
 
 #[cfg(test)]
@@ -605,4 +820,167 @@ mod tests {
             "token not yet valid (not before: 1545263000, now: 1545262998 [leeway: 1])"
         );
     }
+
+    #[test]
+    fn decode_jws_max_age() {
+        const IAT: i64 = 1516239022;
+        let public_key = get_private_key_1().to_public_key();
+        let max_age = Duration::from_secs(300);
+
+        let decode = |now: &JwtDate| {
+            JwtSig::<MyClaims>::decode(
+                crate::test_files::JOSE_JWT_SIG_EXAMPLE,
+                &public_key,
+                &JwtValidator::lenient(now).max_age(max_age),
+            )
+        };
+
+        decode(&JwtDate::new(IAT + 300)).expect("token at exactly max age should be accepted");
+        let err = decode(&JwtDate::new(IAT + 301)).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::Expired);
+        assert_eq!(
+            err.to_string(),
+            "token is too old (issued at: 1516239022, max age: 300s, now: 1516239323 [leeway: 0])"
+        );
+
+        decode(&JwtDate::new_with_leeway(IAT + 310, 10)).expect("token at max age + leeway should be accepted");
+        let err = decode(&JwtDate::new_with_leeway(IAT + 311, 10)).err().unwrap();
+        assert!(matches!(err, JwtError::TooOld { issued_at: IAT, .. }));
+    }
+
+    #[test]
+    fn decode_jws_max_age_requires_iat() {
+        let public_key = get_private_key_1().to_public_key();
+
+        let err = JwtSig::<MyExpirableClaims>::decode(
+            crate::test_files::JOSE_JWT_SIG_WITH_EXP,
+            &public_key,
+            &JwtValidator::strict(&JwtDate::new(1545263999)).max_age(Duration::from_secs(300)),
+        )
+        .err()
+        .unwrap();
+        assert_eq!(err.to_string(), "required claim `iat` is missing");
+
+        let err = JwtSig::<MyClaims>::decode(
+            crate::test_files::JOSE_JWT_SIG_EXAMPLE,
+            &public_key,
+            &JwtValidator::no_check().max_age(Duration::from_secs(300)),
+        )
+        .err()
+        .unwrap();
+        assert_eq!(err.to_string(), "invalid validator: current date is missing");
+    }
+
+    fn encode_registered_claims(aud: serde_json::Value) -> String {
+        let claims = serde_json::json!({
+            "iss": "https://picky.example",
+            "sub": "alice",
+            "aud": aud,
+            "iat": 1545263000,
+            "nbf": 1545263000,
+            "exp": 1545264000,
+        });
+        JwtSig::new(JwsAlg::RS256, claims).encode(&get_private_key_1()).unwrap()
+    }
+
+    #[test]
+    fn decode_jws_registered_claims() {
+        let public_key = get_private_key_1().to_public_key();
+        let now = JwtDate::new(1545263500);
+        let decode =
+            |token: &str, validator: &JwtValidator| JwtSig::<serde_json::Value>::decode(token, &public_key, validator);
+
+        let token = encode_registered_claims(serde_json::json!(["other-api", "my-api"]));
+        let validator = JwtValidator::strict(&now)
+            .require_audience("my-api")
+            .require_issuer("https://picky.example")
+            .require_subject("alice");
+        let jwt = decode(&token, &validator).expect("couldn't validate registered claims");
+        assert_eq!(jwt.claims["sub"], "alice");
+
+        // any of the expected audiences is accepted
+        let single_audience = encode_registered_claims(serde_json::json!("my-api"));
+        let validator = JwtValidator::strict(&now)
+            .require_audience("admin-api")
+            .require_audience("my-api");
+        decode(&single_audience, &validator).expect("couldn't validate string audience");
+
+        let err = decode(&token, &JwtValidator::strict(&now).require_audience("admin-api"))
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        assert_eq!(
+            err.to_string(),
+            "token audience mismatch (expected one of: admin-api, found: other-api, my-api)"
+        );
+
+        let err = decode(
+            &token,
+            &JwtValidator::strict(&now).require_issuer("https://evil.example"),
+        )
+        .err()
+        .unwrap();
+        assert!(matches!(err, JwtError::IssuerMismatch { .. }));
+
+        let err = decode(&token, &JwtValidator::strict(&now).require_subject("bob"))
+            .err()
+            .unwrap();
+        assert!(matches!(err, JwtError::SubjectMismatch { ref found, .. } if found == "alice"));
+
+        // claims are checked without current date as well
+        let err = decode(
+            crate::test_files::JOSE_JWT_SIG_EXAMPLE,
+            &JwtValidator::no_check().require_audience("my-api"),
+        )
+        .err()
+        .unwrap();
+        assert_eq!(err.to_string(), "required claim `aud` is missing");
+
+        let invalid_audience = encode_registered_claims(serde_json::json!(["my-api", 42]));
+        let err = decode(&invalid_audience, &JwtValidator::no_check().require_audience("my-api"))
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "registered claim `aud` has invalid type");
+    }
+
+    #[test]
+    fn decode_jws_leeway_and_issued_at() {
+        let public_key = get_private_key_1().to_public_key();
+        let token = encode_registered_claims(serde_json::json!("my-api"));
+        let decode = |validator: &JwtValidator| JwtSig::<serde_json::Value>::decode(&token, &public_key, validator);
+
+        let after_expiration = JwtDate::new(1545264005);
+        let err = decode(&JwtValidator::strict(&after_expiration)).err().unwrap();
+        assert!(matches!(err, JwtError::Expired { .. }));
+        decode(&JwtValidator::strict(&after_expiration).leeway(Duration::from_secs(10)))
+            .expect("leeway should account for expiration");
+
+        // nbf is ignored to only check iat
+        let before_issuance = JwtDate::new(1545262990);
+        let validator = JwtValidator::strict(&before_issuance)
+            .not_before_check_ignored()
+            .issued_at_check_required();
+        let err = decode(&validator).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::NotYetValid);
+        assert_eq!(
+            err.to_string(),
+            "token is issued in the future (issued at: 1545263000, now: 1545262990 [leeway: 0])"
+        );
+        decode(&validator.clone().leeway(Duration::from_secs(10))).expect("leeway should account for iat");
+        decode(&validator.issued_at_check_ignored()).expect("iat check is ignored");
+
+        let err = JwtSig::<MyExpirableClaims>::decode(
+            crate::test_files::JOSE_JWT_SIG_WITH_EXP,
+            &public_key,
+            &JwtValidator::strict(&JwtDate::new(1545263999)).issued_at_check_required(),
+        )
+        .err()
+        .unwrap();
+        assert_eq!(err.to_string(), "required claim `iat` is missing");
+
+        let err = decode(&JwtValidator::no_check().issued_at_check_required())
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "invalid validator: current date is missing");
+    }
 }
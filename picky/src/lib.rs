@@ -7,6 +7,7 @@ pub mod jose;
 #[cfg(feature = "x509")]
 pub mod x509;
 
+pub mod error;
 pub mod hash;
 pub mod key;
 pub mod pem;
@@ -19,6 +20,11 @@ mod test_files {
     pub const RSA_2048_PK_1: &str = include_str!("../../test_assets/private_keys/rsa-2048-pk_1.key");
     pub const RSA_2048_PK_7: &str = include_str!("../../test_assets/private_keys/rsa-2048-pk_7.key");
     pub const RSA_4096_PK_3: &str = include_str!("../../test_assets/private_keys/rsa-4096-pk_3.key");
+    pub const EC_P256_PK_1: &str = include_str!("../../test_assets/private_keys/ec-p256-pk_1.key");
+    pub const EC_P384_PK_1: &str = include_str!("../../test_assets/private_keys/ec-p384-pk_1.key");
+    #[cfg(feature = "pkcs8_encryption")]
+    pub const RSA_2048_PK_1_ENCRYPTED: &str =
+        include_str!("../../test_assets/private_keys/rsa-2048-pk_1_encrypted.key");
 
     cfg_if::cfg_if! { if #[cfg(feature = "x509")] {
         pub const RSA_2048_PK_2: &str =
@@ -27,9 +33,25 @@ mod test_files {
             include_str!("../../test_assets/private_keys/rsa-2048-pk_3.key");
         pub const RSA_2048_PK_4: &str =
             include_str!("../../test_assets/private_keys/rsa-2048-pk_4.key");
+        pub const RSA_2048_PK_5: &str =
+            include_str!("../../test_assets/private_keys/rsa-2048-pk_5.key");
 
         pub const INTERMEDIATE_CA: &str = include_str!("../../test_assets/intermediate_ca.crt");
         pub const ROOT_CA: &str = include_str!("../../test_assets/root_ca.crt");
+        pub const TLS_SERVER: &str = include_str!("../../test_assets/tls_server.crt");
+        pub const TEST_AUTHORITY: &str = include_str!("../../test_assets/test_authority.crt");
+        pub const TEST_AUTHORITY_CRL: &str = include_str!("../../test_assets/test_authority.crl");
+        pub const TEST_AUTHORITY_OCSP_REQUEST: &[u8] =
+            include_bytes!("../../test_assets/test_authority_ocsp_request.der");
+        pub const TEST_AUTHORITY_OCSP_RESPONSE: &[u8] =
+            include_bytes!("../../test_assets/test_authority_ocsp_response.der");
+        pub const TEST_AUTHORITY_P7B: &[u8] = include_bytes!("../../test_assets/test_authority.p7b");
+        pub const VENDOR_EXTENSIONS_CA: &str = include_str!("../../test_assets/vendor_extensions_ca.crt");
+        pub const VENDOR_EXTENSIONS: &str = include_str!("../../test_assets/vendor_extensions.crt");
+        pub const ECDSA_ROOT_CA: &str = include_str!("../../test_assets/ecdsa_root_ca.crt");
+        pub const ECDSA_LEAF: &str = include_str!("../../test_assets/ecdsa_leaf.crt");
+        pub const RSA_PSS_ROOT_CA: &str = include_str!("../../test_assets/rsa_pss_root_ca.crt");
+        pub const RSA_PSS_LEAF: &str = include_str!("../../test_assets/rsa_pss_leaf.crt");
     }}
 
     cfg_if::cfg_if! { if #[cfg(feature = "jose")] {
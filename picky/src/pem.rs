@@ -3,8 +3,9 @@
 //! Based on the RFC-7468
 //! ([Textual Encodings of PKIX, PKCS, and CMS Structures](https://tools.ietf.org/html/rfc7468)).
 
+use crate::error::ErrorKind;
 use base64::DecodeError;
-use serde::export::Formatter;
+use serde::{de, export::Formatter, Deserialize, Deserializer, Serializer};
 use std::{borrow::Cow, fmt, io::BufRead, str::FromStr};
 use thiserror::Error;
 
@@ -13,6 +14,7 @@ const PEM_FOOTER_START: &str = "-----END";
 const PEM_DASHES_BOUNDARIES: &str = "-----";
 
 #[derive(Debug, Clone, Error)]
+#[non_exhaustive]
 pub enum PemError {
     /// header not found
     #[error("header not found")]
@@ -31,6 +33,18 @@ pub enum PemError {
     Base64Decoding { source: DecodeError },
 }
 
+impl PemError {
+    /// Stable classification of this error
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            PemError::HeaderNotFound
+            | PemError::InvalidHeader
+            | PemError::FooterNotFound
+            | PemError::Base64Decoding { .. } => ErrorKind::Decoding,
+        }
+    }
+}
+
 /// Privacy-Enhanced Mail (PEM) format structured representation
 #[derive(Debug, Clone, PartialEq)]
 pub struct Pem<'a> {
@@ -182,6 +196,32 @@ pub fn read_pem(reader: &mut impl BufRead) -> Result<Pem<'static>, PemError> {
     })
 }
 
+/// Iterate over the PEM-encoded structures of a BufRead object.
+///
+/// Structures are parsed one at a time as the iterator is advanced.
+/// Iteration stops once no other PEM header is found; an invalid structure yields an error
+/// and iteration resumes with the next one.
+pub fn read_pems<R: BufRead>(reader: R) -> ReadPems<R> {
+    ReadPems { reader }
+}
+
+/// Iterator returned by [`read_pems`](fn.read_pems.html)
+#[derive(Debug)]
+pub struct ReadPems<R> {
+    reader: R,
+}
+
+impl<R: BufRead> Iterator for ReadPems<R> {
+    type Item = Result<Pem<'static>, PemError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match read_pem(&mut self.reader) {
+            Err(PemError::HeaderNotFound) => None,
+            result => Some(result),
+        }
+    }
+}
+
 // Helper to read until some pattern is matched. Returns None on any error
 // (cannot be copy pasted for any purpose and should stay private!).
 fn h_read_until(reader: &mut impl BufRead, pat: &[u8], buf: &mut Vec<u8>) -> Option<usize> {
@@ -226,6 +266,68 @@ where
     Pem::new(label, data.as_ref()).to_string()
 }
 
+/// Serializes DER-encoded `data` as a PEM string in human-readable formats (JSON, TOML…)
+/// and as raw DER bytes in binary formats (bincode…).
+pub(crate) fn serialize_pem_or_der<S: Serializer>(serializer: S, label: &str, data: &[u8]) -> Result<S::Ok, S::Error> {
+    if serializer.is_human_readable() {
+        serializer.collect_str(&Pem::new(label, data))
+    } else {
+        serializer.serialize_bytes(data)
+    }
+}
+
+/// Counterpart of `serialize_pem_or_der`.
+///
+/// The payload is fully decoded by `from_pem` or `from_der` so that invalid data is
+/// rejected at deserialization time with the underlying picky error message.
+pub(crate) fn deserialize_pem_or_der<'de, D, T, E, P, R>(
+    deserializer: D,
+    from_pem: P,
+    from_der: R,
+) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    E: fmt::Display,
+    P: FnOnce(&Pem) -> Result<T, E>,
+    R: FnOnce(&[u8]) -> Result<T, E>,
+{
+    if deserializer.is_human_readable() {
+        let pem = Cow::<str>::deserialize(deserializer)?
+            .parse::<Pem>()
+            .map_err(de::Error::custom)?;
+        from_pem(&pem).map_err(de::Error::custom)
+    } else {
+        let der = deserializer.deserialize_byte_buf(DerBytesVisitor)?;
+        from_der(&der).map_err(de::Error::custom)
+    }
+}
+
+struct DerBytesVisitor;
+
+impl<'de> de::Visitor<'de> for DerBytesVisitor {
+    type Value = Vec<u8>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("DER-encoded bytes")
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+        Ok(v.to_vec())
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+        Ok(v)
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(byte) = seq.next_element()? {
+            bytes.push(byte);
+        }
+        Ok(bytes)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -281,4 +383,20 @@ mod tests {
         let pem2 = read_pem(&mut reader).unwrap();
         assert_eq!(pem2.label, "GARBAGE2");
     }
+
+    #[test]
+    fn iterate() {
+        let bundle = format!(
+            "some comment\n{}\n-----BEGIN BROKEN-----\n%%%\n-----END BROKEN-----\n{}\ntrailing text",
+            PEM_STR, MULTIPLE_PEM
+        );
+        let pems = read_pems(bundle.as_bytes()).collect::<Vec<_>>();
+        assert_eq!(pems.len(), 4);
+        assert_eq!(pems[0].as_ref().unwrap().label, "CERTIFICATE");
+        assert!(matches!(pems[1], Err(PemError::Base64Decoding { .. })));
+        assert_eq!(pems[2].as_ref().unwrap().label, "GARBAGE1");
+        assert_eq!(pems[3].as_ref().unwrap().label, "GARBAGE2");
+
+        assert_eq!(read_pems(&b"no pem here"[..]).count(), 0);
+    }
 }
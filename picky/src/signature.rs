@@ -1,11 +1,14 @@
 //! Signature algorithms supported by picky
 
 use crate::{
+    error::ErrorKind,
     hash::HashAlgorithm,
-    key::{KeyError, PrivateKey, PublicKey},
+    key::{EcCurve, KeyError, PrivateKey, PublicKey},
 };
-use core::convert::TryFrom;
-use picky_asn1_x509::{oids, AlgorithmIdentifier};
+use core::{convert::TryFrom, fmt, str::FromStr};
+use digest::{Digest, DynDigest};
+use p256::ecdsa::signature::hazmat::{PrehashSigner, PrehashVerifier};
+use picky_asn1_x509::{oids, AlgorithmIdentifier, AlgorithmIdentifierParameters, RsassaPssParams, SHAVariant};
 use rsa::{PublicKey as RsaPublicKeyInterface, RSAPrivateKey, RSAPublicKey};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
@@ -18,8 +21,12 @@ pub enum SignatureError {
     Key { source: KeyError },
 
     /// RSA error
-    #[error("RSA error: {context}")]
-    Rsa { context: String },
+    #[error("RSA error: {source}")]
+    Rsa { source: rsa::errors::Error },
+
+    /// ECDSA error
+    #[error("ECDSA error: {source}")]
+    Ecdsa { source: p256::ecdsa::Error },
 
     /// invalid signature
     #[error("invalid signature")]
@@ -30,9 +37,28 @@ pub enum SignatureError {
     UnsupportedAlgorithm { algorithm: String },
 }
 
+impl SignatureError {
+    /// Stable classification of this error
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            SignatureError::Key { source } => source.kind(),
+            SignatureError::Rsa { .. } => ErrorKind::Crypto,
+            SignatureError::Ecdsa { .. } => ErrorKind::Crypto,
+            SignatureError::BadSignature => ErrorKind::BadSignature,
+            SignatureError::UnsupportedAlgorithm { .. } => ErrorKind::UnsupportedAlgorithm,
+        }
+    }
+}
+
 impl From<rsa::errors::Error> for SignatureError {
     fn from(e: rsa::errors::Error) -> Self {
-        SignatureError::Rsa { context: e.to_string() }
+        SignatureError::Rsa { source: e }
+    }
+}
+
+impl From<p256::ecdsa::Error> for SignatureError {
+    fn from(e: p256::ecdsa::Error) -> Self {
+        SignatureError::Ecdsa { source: e }
     }
 }
 
@@ -47,6 +73,18 @@ impl From<KeyError> for SignatureError {
 #[non_exhaustive]
 pub enum SignatureAlgorithm {
     RsaPkcs1v15(HashAlgorithm),
+    /// RSASSA-PSS with SHA-256 and MGF1 with SHA-256
+    RsaPssSha256,
+    /// RSASSA-PSS with SHA-384 and MGF1 with SHA-384
+    RsaPssSha384,
+    /// RSASSA-PSS with SHA-512 and MGF1 with SHA-512
+    RsaPssSha512,
+    /// ECDSA with SHA-256, on the P-256 or P-384 curve
+    EcdsaSha256,
+    /// ECDSA with SHA-384, on the P-256 or P-384 curve
+    EcdsaSha384,
+    /// ECDSA with SHA-512, on the P-256 or P-384 curve
+    EcdsaSha512,
 }
 
 impl TryFrom<&'_ AlgorithmIdentifier> for SignatureAlgorithm {
@@ -62,6 +100,15 @@ impl TryFrom<&'_ AlgorithmIdentifier> for SignatureAlgorithm {
             oids::SHA512_WITH_RSA_ENCRYPTION => Ok(Self::RsaPkcs1v15(HashAlgorithm::SHA2_512)),
             oids::ID_RSASSA_PKCS1_V1_5_WITH_SHA3_384 => Ok(Self::RsaPkcs1v15(HashAlgorithm::SHA3_384)),
             oids::ID_RSASSA_PKCS1_V1_5_WITH_SHA3_512 => Ok(Self::RsaPkcs1v15(HashAlgorithm::SHA3_512)),
+            oids::RSASSA_PSS => match v.parameters() {
+                AlgorithmIdentifierParameters::RsassaPss(params) => Self::from_rsassa_pss_params(params),
+                _ => Err(SignatureError::UnsupportedAlgorithm {
+                    algorithm: "RSASSA-PSS without parameters".to_owned(),
+                }),
+            },
+            oids::ECDSA_WITH_SHA256 => Ok(Self::EcdsaSha256),
+            oids::ECDSA_WITH_SHA384 => Ok(Self::EcdsaSha384),
+            oids::ECDSA_WITH_SHA512 => Ok(Self::EcdsaSha512),
             _ => Err(SignatureError::UnsupportedAlgorithm { algorithm: oid_string }),
         }
     }
@@ -89,15 +136,121 @@ impl From<SignatureAlgorithm> for AlgorithmIdentifier {
             SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA3_512) => {
                 AlgorithmIdentifier::new_sha3_512_with_rsa_encryption()
             }
+            // salt is as long as the hash output, as recommended by RFC 4055
+            SignatureAlgorithm::RsaPssSha256 => {
+                AlgorithmIdentifier::new_rsassa_pss(RsassaPssParams::new(SHAVariant::SHA2_256, 32))
+            }
+            SignatureAlgorithm::RsaPssSha384 => {
+                AlgorithmIdentifier::new_rsassa_pss(RsassaPssParams::new(SHAVariant::SHA2_384, 48))
+            }
+            SignatureAlgorithm::RsaPssSha512 => {
+                AlgorithmIdentifier::new_rsassa_pss(RsassaPssParams::new(SHAVariant::SHA2_512, 64))
+            }
+            SignatureAlgorithm::EcdsaSha256 => AlgorithmIdentifier::new_ecdsa_with_sha256(),
+            SignatureAlgorithm::EcdsaSha384 => AlgorithmIdentifier::new_ecdsa_with_sha384(),
+            SignatureAlgorithm::EcdsaSha512 => AlgorithmIdentifier::new_ecdsa_with_sha512(),
+        }
+    }
+}
+
+/// Parses names such as `rsa-sha256`, `rsa-sha3-512`, `rsa-pss-sha256` or `ecdsa-sha384`, ignoring case
+impl FromStr for SignatureAlgorithm {
+    type Err = SignatureError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "rsa-sha1" => Ok(Self::RsaPkcs1v15(HashAlgorithm::SHA1)),
+            "rsa-sha224" => Ok(Self::RsaPkcs1v15(HashAlgorithm::SHA2_224)),
+            "rsa-sha256" => Ok(Self::RsaPkcs1v15(HashAlgorithm::SHA2_256)),
+            "rsa-sha384" => Ok(Self::RsaPkcs1v15(HashAlgorithm::SHA2_384)),
+            "rsa-sha512" => Ok(Self::RsaPkcs1v15(HashAlgorithm::SHA2_512)),
+            "rsa-sha3-384" => Ok(Self::RsaPkcs1v15(HashAlgorithm::SHA3_384)),
+            "rsa-sha3-512" => Ok(Self::RsaPkcs1v15(HashAlgorithm::SHA3_512)),
+            "rsa-pss-sha256" => Ok(Self::RsaPssSha256),
+            "rsa-pss-sha384" => Ok(Self::RsaPssSha384),
+            "rsa-pss-sha512" => Ok(Self::RsaPssSha512),
+            "ecdsa-sha256" => Ok(Self::EcdsaSha256),
+            "ecdsa-sha384" => Ok(Self::EcdsaSha384),
+            "ecdsa-sha512" => Ok(Self::EcdsaSha512),
+            _ => Err(SignatureError::UnsupportedAlgorithm {
+                algorithm: s.to_owned(),
+            }),
         }
     }
 }
 
+/// Name parsed by the `FromStr` implementation
+impl fmt::Display for SignatureAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA1) => "rsa-sha1",
+            SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_224) => "rsa-sha224",
+            SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_256) => "rsa-sha256",
+            SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_384) => "rsa-sha384",
+            SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_512) => "rsa-sha512",
+            SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA3_384) => "rsa-sha3-384",
+            SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA3_512) => "rsa-sha3-512",
+            SignatureAlgorithm::RsaPssSha256 => "rsa-pss-sha256",
+            SignatureAlgorithm::RsaPssSha384 => "rsa-pss-sha384",
+            SignatureAlgorithm::RsaPssSha512 => "rsa-pss-sha512",
+            SignatureAlgorithm::EcdsaSha256 => "ecdsa-sha256",
+            SignatureAlgorithm::EcdsaSha384 => "ecdsa-sha384",
+            SignatureAlgorithm::EcdsaSha512 => "ecdsa-sha512",
+        };
+        f.write_str(name)
+    }
+}
+
 impl SignatureAlgorithm {
     pub fn from_algorithm_identifier(algorithm_identifier: &AlgorithmIdentifier) -> Result<Self, SignatureError> {
         Self::try_from(algorithm_identifier)
     }
 
+    /// Only SHA-256, SHA-384 and SHA-512 are supported, and MGF1 must use the same hash as the message digest.
+    ///
+    /// The salt length isn't checked: verification recovers it from the signature itself.
+    fn from_rsassa_pss_params(params: &RsassaPssParams) -> Result<Self, SignatureError> {
+        let hash_oid: String = params.hash_algorithm.oid().into();
+        let algorithm = match hash_oid.as_str() {
+            oids::SHA256 => Self::RsaPssSha256,
+            oids::SHA384 => Self::RsaPssSha384,
+            oids::SHA512 => Self::RsaPssSha512,
+            _ => {
+                return Err(SignatureError::UnsupportedAlgorithm {
+                    algorithm: format!("RSASSA-PSS with hash algorithm {}", hash_oid),
+                })
+            }
+        };
+
+        let mask_gen = &params.mask_gen_algorithm;
+        if mask_gen.algorithm.0 != oids::mgf1() || mask_gen.hash_algorithm.oid() != params.hash_algorithm.oid() {
+            return Err(SignatureError::UnsupportedAlgorithm {
+                algorithm: "RSASSA-PSS with a mask generation function other than MGF1 using the message hash"
+                    .to_owned(),
+            });
+        }
+
+        if params.trailer_field != 1 {
+            return Err(SignatureError::UnsupportedAlgorithm {
+                algorithm: format!("RSASSA-PSS with trailer field {}", params.trailer_field),
+            });
+        }
+
+        Ok(algorithm)
+    }
+
+    pub fn hash_algorithm(self) -> HashAlgorithm {
+        match self {
+            SignatureAlgorithm::RsaPkcs1v15(hash_algo) => hash_algo,
+            SignatureAlgorithm::RsaPssSha256 => HashAlgorithm::SHA2_256,
+            SignatureAlgorithm::RsaPssSha384 => HashAlgorithm::SHA2_384,
+            SignatureAlgorithm::RsaPssSha512 => HashAlgorithm::SHA2_512,
+            SignatureAlgorithm::EcdsaSha256 => HashAlgorithm::SHA2_256,
+            SignatureAlgorithm::EcdsaSha384 => HashAlgorithm::SHA2_384,
+            SignatureAlgorithm::EcdsaSha512 => HashAlgorithm::SHA2_512,
+        }
+    }
+
     pub fn sign(self, msg: &[u8], private_key: &PrivateKey) -> Result<Vec<u8>, SignatureError> {
         let signature = match self {
             SignatureAlgorithm::RsaPkcs1v15(picky_hash_algo) => {
@@ -107,6 +260,26 @@ impl SignatureAlgorithm {
                 let padding_scheme = rsa::PaddingScheme::new_pkcs1v15_sign(Some(rsa_hash_algo));
                 rsa_private_key.sign_blinded(&mut rand::rngs::OsRng, padding_scheme, &digest)?
             }
+            SignatureAlgorithm::RsaPssSha256 => rsa_pss_sign::<sha2::Sha256>(msg, private_key)?,
+            SignatureAlgorithm::RsaPssSha384 => rsa_pss_sign::<sha2::Sha384>(msg, private_key)?,
+            SignatureAlgorithm::RsaPssSha512 => rsa_pss_sign::<sha2::Sha512>(msg, private_key)?,
+            SignatureAlgorithm::EcdsaSha256 | SignatureAlgorithm::EcdsaSha384 | SignatureAlgorithm::EcdsaSha512 => {
+                let (curve, secret) = private_key.as_ec()?;
+                let digest = self.hash_algorithm().digest(msg);
+                // deterministic signatures (RFC 6979), DER-encoded as required by RFC 5480
+                match curve {
+                    EcCurve::P256 => {
+                        let signing_key = p256::ecdsa::SigningKey::from_bytes(secret).map_err(KeyError::from)?;
+                        let signature: p256::ecdsa::Signature = signing_key.sign_prehash(&digest)?;
+                        signature.to_der().as_bytes().to_vec()
+                    }
+                    EcCurve::P384 => {
+                        let signing_key = p384::ecdsa::SigningKey::from_bytes(secret).map_err(KeyError::from)?;
+                        let signature: p384::ecdsa::Signature = signing_key.sign_prehash(&digest)?;
+                        signature.to_der().as_bytes().to_vec()
+                    }
+                }
+            }
         };
 
         Ok(signature)
@@ -123,8 +296,180 @@ impl SignatureAlgorithm {
                     .verify(padding_scheme, &digest, signature)
                     .map_err(|_| SignatureError::BadSignature)?;
             }
+            SignatureAlgorithm::RsaPssSha256 => rsa_pss_verify::<sha2::Sha256>(public_key, msg, signature)?,
+            SignatureAlgorithm::RsaPssSha384 => rsa_pss_verify::<sha2::Sha384>(public_key, msg, signature)?,
+            SignatureAlgorithm::RsaPssSha512 => rsa_pss_verify::<sha2::Sha512>(public_key, msg, signature)?,
+            SignatureAlgorithm::EcdsaSha256 | SignatureAlgorithm::EcdsaSha384 | SignatureAlgorithm::EcdsaSha512 => {
+                let (curve, point) = public_key.as_ec()?;
+                let digest = self.hash_algorithm().digest(msg);
+                match curve {
+                    EcCurve::P256 => {
+                        let verifying_key =
+                            p256::ecdsa::VerifyingKey::from_sec1_bytes(point).map_err(KeyError::from)?;
+                        let signature =
+                            p256::ecdsa::Signature::from_der(signature).map_err(|_| SignatureError::BadSignature)?;
+                        verifying_key
+                            .verify_prehash(&digest, &signature)
+                            .map_err(|_| SignatureError::BadSignature)?;
+                    }
+                    EcCurve::P384 => {
+                        let verifying_key =
+                            p384::ecdsa::VerifyingKey::from_sec1_bytes(point).map_err(KeyError::from)?;
+                        let signature =
+                            p384::ecdsa::Signature::from_der(signature).map_err(|_| SignatureError::BadSignature)?;
+                        verifying_key
+                            .verify_prehash(&digest, &signature)
+                            .map_err(|_| SignatureError::BadSignature)?;
+                    }
+                }
+            }
         }
 
         Ok(())
     }
 }
+
+fn rsa_pss_sign<D>(msg: &[u8], private_key: &PrivateKey) -> Result<Vec<u8>, SignatureError>
+where
+    D: 'static + Digest + DynDigest,
+{
+    let rsa_private_key = RSAPrivateKey::try_from(private_key)?;
+    let digest = D::digest(msg);
+    let salt_len = <D as Digest>::output_size();
+    let padding_scheme = rsa::PaddingScheme::new_pss_with_salt::<D, _>(rand::rngs::OsRng, salt_len);
+    Ok(rsa_private_key.sign_blinded(&mut rand::rngs::OsRng, padding_scheme, &digest)?)
+}
+
+fn rsa_pss_verify<D>(public_key: &PublicKey, msg: &[u8], signature: &[u8]) -> Result<(), SignatureError>
+where
+    D: 'static + Digest + DynDigest,
+{
+    let rsa_public_key = RSAPublicKey::try_from(public_key)?;
+    let digest = D::digest(msg);
+    let padding_scheme = rsa::PaddingScheme::new_pss::<D, _>(rand::rngs::OsRng);
+    rsa_public_key
+        .verify(padding_scheme, &digest, signature)
+        .map_err(|_| SignatureError::BadSignature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_round_trip() {
+        for name in &[
+            "rsa-sha1",
+            "rsa-sha224",
+            "rsa-sha256",
+            "rsa-sha384",
+            "rsa-sha512",
+            "rsa-sha3-384",
+            "rsa-sha3-512",
+            "rsa-pss-sha256",
+            "rsa-pss-sha384",
+            "rsa-pss-sha512",
+            "ecdsa-sha256",
+            "ecdsa-sha384",
+            "ecdsa-sha512",
+        ] {
+            let algorithm = name.parse::<SignatureAlgorithm>().unwrap();
+            assert_eq!(algorithm.to_string(), *name);
+        }
+
+        assert_eq!(
+            "RSA-SHA384".parse::<SignatureAlgorithm>().unwrap(),
+            SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_384)
+        );
+        assert!("rsa-sha265".parse::<SignatureAlgorithm>().is_err());
+    }
+
+    fn parse_key(pem_str: &str) -> PrivateKey {
+        PrivateKey::from_pem(&pem_str.parse().unwrap()).unwrap()
+    }
+
+    const ECDSA_MSG: &[u8] = b"picky ecdsa test vector";
+
+    // openssl dgst -sha256 -sign ec-p256-pk_1.key
+    const ECDSA_P256_SHA256_SIGNATURE: &str =
+        "MEUCIH+ZdO51dXVpg09WoA5VXZMGsvQQ45qcM9u3EtjwW+p6AiEAlEVhBbmgGKE8LJfmOYkjKChNBxG+9JaHbuWtQqFJz5o=";
+
+    // openssl dgst -sha512 -sign ec-p384-pk_1.key
+    const ECDSA_P384_SHA512_SIGNATURE: &str = "MGUCMAFV5d/eF0QctXAn1v3nic/SiQ5zI+mYg6XtGCxvdE4mAh2umylgTdt4Kuk6NIDZ/AIxAPPAjFo3bLvYPI7Bi/L8O5MMxJVVzBog7uJLdys+bi4JG5c7l9c2XADZjtzEIWGwLQ==";
+
+    #[test]
+    fn ecdsa_test_vectors() {
+        let p256_key = parse_key(crate::test_files::EC_P256_PK_1).to_public_key();
+        let p384_key = parse_key(crate::test_files::EC_P384_PK_1).to_public_key();
+        let p256_signature = base64::decode(ECDSA_P256_SHA256_SIGNATURE).unwrap();
+        let p384_signature = base64::decode(ECDSA_P384_SHA512_SIGNATURE).unwrap();
+
+        SignatureAlgorithm::EcdsaSha256
+            .verify(&p256_key, ECDSA_MSG, &p256_signature)
+            .expect("P-256 signature");
+        SignatureAlgorithm::EcdsaSha512
+            .verify(&p384_key, ECDSA_MSG, &p384_signature)
+            .expect("P-384 signature");
+
+        let err = SignatureAlgorithm::EcdsaSha384
+            .verify(&p256_key, ECDSA_MSG, &p256_signature)
+            .unwrap_err();
+        assert!(matches!(err, SignatureError::BadSignature));
+        let err = SignatureAlgorithm::EcdsaSha512
+            .verify(&p384_key, b"another message", &p384_signature)
+            .unwrap_err();
+        assert!(matches!(err, SignatureError::BadSignature));
+    }
+
+    #[test]
+    fn ecdsa_sign_and_verify() {
+        for pem_str in &[crate::test_files::EC_P256_PK_1, crate::test_files::EC_P384_PK_1] {
+            let private_key = parse_key(pem_str);
+            let public_key = private_key.to_public_key();
+            for algorithm in &[
+                SignatureAlgorithm::EcdsaSha256,
+                SignatureAlgorithm::EcdsaSha384,
+                SignatureAlgorithm::EcdsaSha512,
+            ] {
+                let signature = algorithm.sign(ECDSA_MSG, &private_key).unwrap();
+                algorithm.verify(&public_key, ECDSA_MSG, &signature).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn ecdsa_with_rsa_key() {
+        let rsa_key = parse_key(crate::test_files::RSA_2048_PK_1);
+        let err = SignatureAlgorithm::EcdsaSha256.sign(ECDSA_MSG, &rsa_key).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnsupportedAlgorithm);
+
+        let ec_key = parse_key(crate::test_files::EC_P256_PK_1);
+        let err = SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_256)
+            .sign(ECDSA_MSG, &ec_key)
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnsupportedAlgorithm);
+    }
+
+    #[test]
+    fn rsa_pss_sign_and_verify() {
+        let private_key = parse_key(crate::test_files::RSA_2048_PK_1);
+        let public_key = private_key.to_public_key();
+        for algorithm in &[
+            SignatureAlgorithm::RsaPssSha256,
+            SignatureAlgorithm::RsaPssSha384,
+            SignatureAlgorithm::RsaPssSha512,
+        ] {
+            let signature = algorithm.sign(ECDSA_MSG, &private_key).unwrap();
+            algorithm.verify(&public_key, ECDSA_MSG, &signature).unwrap();
+
+            // PSS signatures are not PKCS#1 v1.5 signatures
+            let pkcs1 = SignatureAlgorithm::RsaPkcs1v15(algorithm.hash_algorithm());
+            let err = pkcs1.verify(&public_key, ECDSA_MSG, &signature).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::BadSignature);
+        }
+
+        let ec_key = parse_key(crate::test_files::EC_P256_PK_1);
+        let err = SignatureAlgorithm::RsaPssSha256.sign(ECDSA_MSG, &ec_key).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnsupportedAlgorithm);
+    }
+}
@@ -1,25 +1,30 @@
 use crate::{
+    error::ErrorKind,
     hash::HashAlgorithm,
     key::{PrivateKey, PublicKey},
-    pem::Pem,
+    pem::{deserialize_pem_or_der, serialize_pem_or_der, Pem},
     signature::{SignatureAlgorithm, SignatureError},
     x509::{
-        csr::{Csr, CsrError},
+        csr::{Csr, CsrError, VerifiedCsr},
         date::UTCDate,
         key_id_gen_method::{KeyIdGenError, KeyIdGenMethod},
         name::{DirectoryName, GeneralNames},
     },
 };
+use oid::ObjectIdentifier;
 use picky_asn1::{bit_string::BitString, wrapper::IntegerAsn1};
 use picky_asn1_der::Asn1DerError;
 use picky_asn1_x509::{
-    oids, AlgorithmIdentifier, AuthorityKeyIdentifier, BasicConstraints, Certificate, ExtendedKeyUsage, Extension,
-    ExtensionView, Extensions, KeyIdentifier, KeyUsage, TBSCertificate, Validity, Version,
+    AlgorithmIdentifier, AuthorityInfoAccess, AuthorityKeyIdentifier, BasicConstraints, Certificate, ExtendedKeyUsage,
+    Extension, Extensions, ExtensionsError, KeyIdentifier, KeyUsage, TBSCertificate, Validity, Version,
 };
+use rand::{CryptoRng, RngCore};
+use serde::{ser, Deserialize, Deserializer, Serialize, Serializer};
 use std::cell::RefCell;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
+#[non_exhaustive]
 pub enum CertError {
     /// couldn't generate certificate
     #[error("couldn't generate certificate: {source}")]
@@ -63,10 +68,18 @@ pub enum CertError {
     #[error("extension not found: {name}")]
     ExtensionNotFound { name: &'static str },
 
+    /// invalid extensions set
+    #[error("invalid extensions: {source}")]
+    InvalidExtensions { source: ExtensionsError },
+
     /// missing required builder argument
     #[error("missing required builder argument `{arg}`")]
     MissingBuilderArgument { arg: &'static str },
 
+    /// serial number generation error
+    #[error("couldn't generate serial number: {source}")]
+    SerialNumberGeneration { source: rand::Error },
+
     /// certificate is not yet valid
     #[error("certificate is not yet valid (not before: {not_before}, now: {now})")]
     CertificateNotYetValid { not_before: UTCDate, now: UTCDate },
@@ -78,9 +91,43 @@ pub enum CertError {
     /// invalid PEM label error
     #[error("invalid PEM label: {label}")]
     InvalidPemLabel { label: String },
+
+    /// issuer certificate has no subject key identifier to use as authority key identifier
+    #[error("issuer certificate '{issuer}' has no subject key identifier")]
+    IssuerWithoutKeyIdentifier { issuer: String },
+
+    /// issuer private key doesn't match the issuer certificate
+    #[error("issuer private key doesn't match the public key of issuer certificate '{issuer}'")]
+    IssuerKeyMismatch { issuer: String },
+}
+
+impl CertError {
+    /// Stable classification of this error
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            CertError::CertGeneration { source } => source.kind(),
+            CertError::InvalidCertificate { source, .. } => source.kind(),
+            CertError::Asn1Serialization { .. } => ErrorKind::Encoding,
+            CertError::Asn1Deserialization { .. } => ErrorKind::Decoding,
+            CertError::Signature { source } => source.kind(),
+            CertError::KeyIdGen { source } => source.kind(),
+            CertError::InvalidChain { source } => source.kind(),
+            CertError::InvalidCsr { source } => source.kind(),
+            CertError::ExtensionNotFound { .. } => ErrorKind::NotFound,
+            CertError::InvalidExtensions { .. } => ErrorKind::InvalidInput,
+            CertError::SerialNumberGeneration { .. } => ErrorKind::Crypto,
+            CertError::MissingBuilderArgument { .. }
+            | CertError::InvalidPemLabel { .. }
+            | CertError::IssuerWithoutKeyIdentifier { .. }
+            | CertError::IssuerKeyMismatch { .. } => ErrorKind::InvalidInput,
+            CertError::CertificateNotYetValid { .. } => ErrorKind::NotYetValid,
+            CertError::CertificateExpired { .. } => ErrorKind::Expired,
+        }
+    }
 }
 
 #[derive(Debug, Error)]
+#[non_exhaustive]
 pub enum CaChainError {
     /// chain depth does't satisfy basic constraints extension
     #[error(
@@ -105,6 +152,19 @@ pub enum CaChainError {
     IssuerNameMismatch { expected: String, actual: String },
 }
 
+impl CaChainError {
+    /// Stable classification of this error
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            CaChainError::TooDeep { .. }
+            | CaChainError::NoRoot
+            | CaChainError::IssuerIsNotCA { .. }
+            | CaChainError::AuthorityKeyIdMismatch { .. }
+            | CaChainError::IssuerNameMismatch { .. } => ErrorKind::InvalidChain,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum CertType {
     Root,
@@ -130,17 +190,6 @@ impl From<Cert> for Certificate {
     }
 }
 
-macro_rules! find_ext {
-    ($oid:expr, $certificate:ident, $ext_name:literal) => {{
-        let key_identifier_oid = $oid;
-        ($certificate.tbs_certificate.extensions.0)
-            .0
-            .iter()
-            .find(|ext| ext.extn_id() == &key_identifier_oid)
-            .ok_or(CertError::ExtensionNotFound { name: $ext_name })
-    }};
-}
-
 impl Cert {
     pub fn from_der<T: ?Sized + AsRef<[u8]>>(der: &T) -> Result<Self, CertError> {
         Ok(Self(picky_asn1_der::from_bytes(der.as_ref()).map_err(|e| {
@@ -195,6 +244,11 @@ impl Cert {
         &self.0.tbs_certificate.signature
     }
 
+    /// Signature algorithm of the certificate, `None` when not supported by picky
+    pub fn signature_hash_type(&self) -> Option<SignatureAlgorithm> {
+        SignatureAlgorithm::from_algorithm_identifier(self.signature_algorithm()).ok()
+    }
+
     pub fn valid_not_before(&self) -> UTCDate {
         self.0.tbs_certificate.validity.not_before.clone().into()
     }
@@ -203,37 +257,72 @@ impl Cert {
         self.0.tbs_certificate.validity.not_after.clone().into()
     }
 
-    pub fn subject_key_identifier(&self) -> Result<&[u8], CertError> {
-        let certificate = &self.0;
+    fn extension_set(&self) -> &Extensions {
+        &self.0.tbs_certificate.extensions.0
+    }
 
-        let ext = find_ext!(oids::subject_key_identifier(), certificate, "subject key identifier")?;
-        match ext.extn_value() {
-            ExtensionView::SubjectKeyIdentifier(ski) => Ok(&ski.0),
-            _ => unreachable!("invalid extension (expected subject key identifier)"),
-        }
+    pub fn subject_key_identifier(&self) -> Result<&[u8], CertError> {
+        self.extension_set()
+            .subject_key_identifier()
+            .ok_or(CertError::ExtensionNotFound {
+                name: "subject key identifier",
+            })
     }
 
     pub fn authority_key_identifier(&self) -> Result<&AuthorityKeyIdentifier, CertError> {
-        let certificate = &self.0;
-
-        let ext = find_ext!(
-            oids::authority_key_identifier(),
-            certificate,
-            "authority key identifier"
-        )?;
-        match ext.extn_value() {
-            ExtensionView::AuthorityKeyIdentifier(aki) => Ok(aki),
-            _ => unreachable!("invalid extension (expected authority key identifier)"),
-        }
+        self.extension_set()
+            .authority_key_identifier()
+            .ok_or(CertError::ExtensionNotFound {
+                name: "authority key identifier",
+            })
     }
 
     pub fn basic_constraints(&self) -> Result<&BasicConstraints, CertError> {
-        let certificate = &self.0;
-        let ext = find_ext!(oids::basic_constraints(), certificate, "basic constraints")?;
-        match ext.extn_value() {
-            ExtensionView::BasicConstraints(bc) => Ok(bc),
-            _ => unreachable!("invalid extension (expected basic constraints)"),
-        }
+        self.extension_set()
+            .basic_constraints()
+            .ok_or(CertError::ExtensionNotFound {
+                name: "basic constraints",
+            })
+    }
+
+    pub fn key_usage(&self) -> Result<&KeyUsage, CertError> {
+        self.extension_set()
+            .key_usage()
+            .ok_or(CertError::ExtensionNotFound { name: "key usage" })
+    }
+
+    pub fn extended_key_usage(&self) -> Result<&ExtendedKeyUsage, CertError> {
+        self.extension_set()
+            .extended_key_usage()
+            .ok_or(CertError::ExtensionNotFound {
+                name: "extended key usage",
+            })
+    }
+
+    pub fn subject_alt_name(&self) -> Result<GeneralNames, CertError> {
+        self.extension_set()
+            .subject_alt_name()
+            .map(GeneralNames::from)
+            .ok_or(CertError::ExtensionNotFound {
+                name: "subject alternative name",
+            })
+    }
+
+    pub fn issuer_alt_name(&self) -> Result<GeneralNames, CertError> {
+        self.extension_set()
+            .issuer_alt_name()
+            .map(GeneralNames::from)
+            .ok_or(CertError::ExtensionNotFound {
+                name: "issuer alternative name",
+            })
+    }
+
+    pub fn authority_info_access(&self) -> Result<&AuthorityInfoAccess, CertError> {
+        self.extension_set()
+            .authority_info_access()
+            .ok_or(CertError::ExtensionNotFound {
+                name: "authority information access",
+            })
     }
 
     pub fn subject_name(&self) -> DirectoryName {
@@ -309,6 +398,19 @@ impl Cert {
     }
 }
 
+impl Serialize for Cert {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let der = self.to_der().map_err(ser::Error::custom)?;
+        serialize_pem_or_der(serializer, CERT_PEM_LABEL, &der)
+    }
+}
+
+impl<'de> Deserialize<'de> for Cert {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_pem_or_der(deserializer, Self::from_pem, Self::from_der)
+    }
+}
+
 // === certificate verifier === /
 
 #[derive(Debug, Clone)]
@@ -552,6 +654,7 @@ fn verify_cert_validity(cert: &Cert, strictness: &CheckStrictness, now: Validity
 #[derive(Clone, Debug)]
 enum SubjectInfos {
     Csr(Csr),
+    VerifiedCsr(VerifiedCsr),
     NameAndPublicKey { name: DirectoryName, public_key: PublicKey },
 }
 
@@ -560,6 +663,14 @@ struct IssuerInfos<'a> {
     name: DirectoryName,
     key: &'a PrivateKey,
     self_signed: bool,
+    /// Set when the issuer is given by its certificate
+    cert: Option<IssuerCertInfos>,
+}
+
+#[derive(Clone, Debug)]
+struct IssuerCertInfos {
+    public_key: PublicKey,
+    ski: Option<Vec<u8>>,
 }
 
 // Statically checks the field actually exists and returns a &'static str of the field name
@@ -576,6 +687,7 @@ macro_rules! field_str {
 struct CertificateBuilderInner<'a> {
     valid_from: Option<UTCDate>,
     valid_to: Option<UTCDate>,
+    serial_number: Option<Vec<u8>>,
     subject_infos: Option<SubjectInfos>,
     issuer_infos: Option<IssuerInfos<'a>>,
     authority_key_identifier: Option<Vec<u8>>,
@@ -587,6 +699,8 @@ struct CertificateBuilderInner<'a> {
     extended_key_usage: Option<ExtendedKeyUsage>,
     subject_alt_name: Option<GeneralNames>,
     issuer_alt_name: Option<GeneralNames>,
+    authority_info_access: Option<AuthorityInfoAccess>,
+    copied_requested_extensions: Option<Vec<ObjectIdentifier>>,
 }
 
 #[derive(Default, Clone, Debug)]
@@ -609,7 +723,14 @@ impl<'a> CertificateBuilder<'a> {
         self
     }
 
-    /// Required (alternatives: `subject_from_csr`, `self_signed`)
+    /// Optional (a random 32-bit serial number is generated otherwise)
+    #[inline]
+    pub fn serial_number(&self, serial_number: Vec<u8>) -> &Self {
+        self.inner.borrow_mut().serial_number = Some(serial_number);
+        self
+    }
+
+    /// Required (alternatives: `subject_from_csr`, `subject_from_verified_csr`, `self_signed`)
     #[inline]
     pub fn subject(&self, subject_name: DirectoryName, public_key: PublicKey) -> &Self {
         self.inner.borrow_mut().subject_infos = Some(SubjectInfos::NameAndPublicKey {
@@ -619,13 +740,22 @@ impl<'a> CertificateBuilder<'a> {
         self
     }
 
-    /// Required (alternatives: `subject`, `self_signed`)
+    /// Required (alternatives: `subject`, `subject_from_verified_csr`, `self_signed`)
     #[inline]
     pub fn subject_from_csr(&self, csr: Csr) -> &Self {
         self.inner.borrow_mut().subject_infos = Some(SubjectInfos::Csr(csr));
         self
     }
 
+    /// Required (alternatives: `subject`, `subject_from_csr`, `self_signed`)
+    ///
+    /// CSR signature isn't checked again.
+    #[inline]
+    pub fn subject_from_verified_csr(&self, csr: VerifiedCsr) -> &Self {
+        self.inner.borrow_mut().subject_infos = Some(SubjectInfos::VerifiedCsr(csr));
+        self
+    }
+
     /// Required (alternative: `self_signed`, `issuer_cert`)
     #[inline]
     pub fn issuer(&self, issuer_name: DirectoryName, issuer_key: &'a PrivateKey) -> &Self {
@@ -633,6 +763,7 @@ impl<'a> CertificateBuilder<'a> {
             name: issuer_name,
             key: issuer_key,
             self_signed: false,
+            cert: None,
         });
         self
     }
@@ -644,20 +775,28 @@ impl<'a> CertificateBuilder<'a> {
             name,
             key,
             self_signed: true,
+            cert: None,
         });
         self
     }
 
     /// Required (alternative: `issuer`, `self_signed`)
+    ///
+    /// Issuer name and authority key identifier are taken from the issuer certificate. Building fails
+    /// when the certificate has no subject key identifier (unless `authority_key_identifier` is given),
+    /// or when `issuer_key` isn't the private key of the certificate.
     #[inline]
     pub fn issuer_cert(&self, issuer_cert: &Cert, issuer_key: &'a PrivateKey) -> &Self {
-        let builder = self.issuer(issuer_cert.subject_name(), &issuer_key);
-
-        if let Ok(issuer_ski) = issuer_cert.subject_key_identifier() {
-            self.authority_key_identifier(issuer_ski.to_vec())
-        } else {
-            builder
-        }
+        self.inner.borrow_mut().issuer_infos = Some(IssuerInfos {
+            name: issuer_cert.subject_name(),
+            key: issuer_key,
+            self_signed: false,
+            cert: Some(IssuerCertInfos {
+                public_key: issuer_cert.public_key().clone(),
+                ski: issuer_cert.subject_key_identifier().ok().map(<[u8]>::to_vec),
+            }),
+        });
+        self
     }
 
     /// Optional (alternative: `issuer_cert`, `self_signed`)
@@ -723,7 +862,35 @@ impl<'a> CertificateBuilder<'a> {
         self
     }
 
+    /// Optional
+    #[inline]
+    pub fn authority_info_access(&self, authority_info_access: AuthorityInfoAccess) -> &Self {
+        self.inner.borrow_mut().authority_info_access = Some(authority_info_access);
+        self
+    }
+
+    /// Optional
+    ///
+    /// Copies the extensions requested in the CSR (pkcs-9 extensionRequest attribute) whose identifier
+    /// is part of `approved` (e.g. `oids::subject_alternative_name()`) into the certificate.
+    /// Extensions set on the builder take precedence over requested ones.
+    /// Ignored unless the subject is given by `subject_from_csr` or `subject_from_verified_csr`.
+    #[inline]
+    pub fn copy_requested_extensions(&self, approved: Vec<ObjectIdentifier>) -> &Self {
+        self.inner.borrow_mut().copied_requested_extensions = Some(approved);
+        self
+    }
+
+    /// Builds the certificate using the operating system's random number generator
     pub fn build(&self) -> Result<Cert, CertError> {
+        self.build_with_rng(&mut rand::rngs::OsRng)
+    }
+
+    /// Builds the certificate using the provided random number generator.
+    ///
+    /// Randomness is only used to generate the serial number when none was provided.
+    /// Together with fixed validity dates, a seeded generator makes the output deterministic.
+    pub fn build_with_rng<R: RngCore + CryptoRng>(&self, rng: &mut R) -> Result<Cert, CertError> {
         let mut inner = self.inner.borrow_mut();
 
         let valid_from = inner.valid_from.take().ok_or(CertError::MissingBuilderArgument {
@@ -738,10 +905,7 @@ impl<'a> CertificateBuilder<'a> {
             .take()
             .unwrap_or(SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_256));
 
-        let key_id_gen_method = inner
-            .key_id_gen_method
-            .take()
-            .unwrap_or(KeyIdGenMethod::SPKFullDER(HashAlgorithm::SHA2_256));
+        let key_id_gen_method = inner.key_id_gen_method.take().unwrap_or_default();
 
         let issuer_infos = inner.issuer_infos.take().ok_or(CertError::MissingBuilderArgument {
             arg: field_str!(issuer_infos),
@@ -759,12 +923,28 @@ impl<'a> CertificateBuilder<'a> {
                 };
                 (aki, subject_infos)
             } else {
-                let aki = inner
-                    .authority_key_identifier
-                    .take()
-                    .ok_or(CertError::MissingBuilderArgument {
-                        arg: field_str!(authority_key_identifier),
-                    })?;
+                if let Some(issuer_cert) = &issuer_infos.cert {
+                    if issuer_infos.key.to_public_key() != issuer_cert.public_key {
+                        return Err(CertError::IssuerKeyMismatch {
+                            issuer: issuer_infos.name.to_string(),
+                        });
+                    }
+                }
+
+                let aki = match (inner.authority_key_identifier.take(), issuer_infos.cert) {
+                    (Some(aki), _) => aki,
+                    (None, Some(IssuerCertInfos { ski: Some(ski), .. })) => ski,
+                    (None, Some(IssuerCertInfos { ski: None, .. })) => {
+                        return Err(CertError::IssuerWithoutKeyIdentifier {
+                            issuer: issuer_infos.name.to_string(),
+                        })
+                    }
+                    (None, None) => {
+                        return Err(CertError::MissingBuilderArgument {
+                            arg: field_str!(authority_key_identifier),
+                        })
+                    }
+                };
                 let subject_infos = inner.subject_infos.take().ok_or(CertError::MissingBuilderArgument {
                     arg: field_str!(subject_infos),
                 })?;
@@ -773,24 +953,46 @@ impl<'a> CertificateBuilder<'a> {
 
             (issuer_infos.name, issuer_infos.key, aki, subject_infos)
         };
-        let (subject_name, subject_public_key) = match subject_infos {
+        let approved_extensions = inner.copied_requested_extensions.take().unwrap_or_default();
+        let approved_requested_extensions = |csr: &Csr| -> Vec<Extension> {
+            csr.requested_extensions()
+                .iter()
+                .filter(|extension| approved_extensions.iter().any(|oid| extension.extn_id().0 == *oid))
+                .cloned()
+                .collect()
+        };
+        let (subject_name, subject_public_key, requested_extensions) = match subject_infos {
             SubjectInfos::Csr(csr) => {
                 csr.verify().map_err(|e| CertError::InvalidCsr { source: e })?;
-                csr.into_subject_infos()
+                let requested_extensions = approved_requested_extensions(&csr);
+                let (name, public_key) = csr.into_subject_infos();
+                (name, public_key, requested_extensions)
+            }
+            SubjectInfos::VerifiedCsr(csr) => {
+                let requested_extensions = approved_requested_extensions(csr.csr());
+                let (name, public_key) = csr.into_subject_infos();
+                (name, public_key, requested_extensions)
             }
-            SubjectInfos::NameAndPublicKey { name, public_key } => (name, public_key),
+            SubjectInfos::NameAndPublicKey { name, public_key } => (name, public_key, Vec::new()),
         };
 
+        let serial_number_opt = inner.serial_number.take();
         let ca = inner.ca.take().unwrap_or(false);
         let pathlen = inner.pathlen.take();
         let key_usage_opt = inner.key_usage.take();
         let extended_key_usage_opt = inner.extended_key_usage.take();
         let subject_alt_name_opt = inner.subject_alt_name.take();
         let issuer_alt_name_opt = inner.issuer_alt_name.take();
+        let authority_info_access_opt = inner.authority_info_access.take();
 
         drop(inner);
 
-        let serial_number = generate_serial_number();
+        let serial_number = match serial_number_opt {
+            Some(serial_number) => serial_number,
+            None => generate_serial_number(rng)
+                .map_err(|e| CertError::SerialNumberGeneration { source: e })
+                .map_err(|e| CertError::CertGeneration { source: Box::new(e) })?,
+        };
 
         let validity = Validity {
             not_before: valid_from.into(),
@@ -798,33 +1000,39 @@ impl<'a> CertificateBuilder<'a> {
         };
 
         let extensions = {
-            let mut extensions = Vec::new();
+            let mut extensions = Extensions(Vec::new());
+            let mut insert = |extension| {
+                extensions
+                    .insert(extension)
+                    .map_err(|e| CertError::InvalidExtensions { source: e })
+                    .map_err(|e| CertError::CertGeneration { source: Box::new(e) })
+            };
 
             // key usage + basic constraints
             if let Some(key_usage) = key_usage_opt {
                 if key_usage.digital_signature() {
-                    extensions.push(Extension::new_basic_constraints(ca, pathlen).into_critical());
+                    insert(Extension::new_basic_constraints(ca, pathlen).into_critical())?;
                 } else {
-                    extensions.push(Extension::new_basic_constraints(ca, pathlen).into_non_critical());
+                    insert(Extension::new_basic_constraints(ca, pathlen).into_non_critical())?;
                 }
-                extensions.push(Extension::new_key_usage(key_usage));
+                insert(Extension::new_key_usage(key_usage))?;
             } else {
-                extensions.push(Extension::new_basic_constraints(ca, pathlen).into_non_critical());
+                insert(Extension::new_basic_constraints(ca, pathlen).into_non_critical())?;
             }
 
             // eku
             if let Some(extended_key_usage) = extended_key_usage_opt {
-                extensions.push(Extension::new_extended_key_usage(extended_key_usage));
+                insert(Extension::new_extended_key_usage(extended_key_usage))?;
             }
 
             // san
             if let Some(san) = subject_alt_name_opt {
-                extensions.push(Extension::new_subject_alt_name(san));
+                insert(Extension::new_subject_alt_name(san))?;
             }
 
             // ian
             if let Some(ian) = issuer_alt_name_opt {
-                extensions.push(Extension::new_issuer_alt_name(ian));
+                insert(Extension::new_issuer_alt_name(ian))?;
             }
 
             // ski
@@ -832,16 +1040,28 @@ impl<'a> CertificateBuilder<'a> {
                 .generate_from(&subject_public_key)
                 .map_err(|e| CertError::KeyIdGen { source: e })
                 .map_err(|e| CertError::CertGeneration { source: Box::new(e) })?;
-            extensions.push(Extension::new_subject_key_identifier(ski));
+            insert(Extension::new_subject_key_identifier(ski))?;
 
             // aki
-            extensions.push(Extension::new_authority_key_identifier(
+            insert(Extension::new_authority_key_identifier(
                 KeyIdentifier::from(aki),
                 None,
                 None,
-            ));
+            ))?;
+
+            // aia
+            if let Some(aia) = authority_info_access_opt {
+                insert(Extension::new_authority_info_access(aia))?;
+            }
+
+            // approved extensions requested in the csr
+            for extension in requested_extensions {
+                if extensions.find(extension.extn_id().0.clone()).is_none() {
+                    extensions.0.push(extension);
+                }
+            }
 
-            Extensions(extensions)
+            extensions
         };
 
         let tbs_certificate = TBSCertificate {
@@ -876,19 +1096,51 @@ impl<'a> CertificateBuilder<'a> {
     }
 }
 
-fn generate_serial_number() -> Vec<u8> {
-    let x = rand::random::<u32>();
-    let b1 = ((x >> 24) & 0xff) as u8;
-    let b2 = ((x >> 16) & 0xff) as u8;
-    let b3 = ((x >> 8) & 0xff) as u8;
-    let b4 = (x & 0xff) as u8;
-    vec![b1, b2, b3, b4]
+fn generate_serial_number<R: RngCore>(rng: &mut R) -> Result<Vec<u8>, rand::Error> {
+    let mut serial_number = vec![0; 4];
+    rng.try_fill_bytes(&mut serial_number)?;
+    Ok(serial_number)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::pem::{parse_pem, Pem};
+    use picky_asn1_x509::oids;
+    use std::error::Error as _;
+
+    #[test]
+    fn asn1_error_is_carried_as_source() {
+        let err = Cert::from_der(&[0x30, 0x03, 0x02, 0x01]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Decoding);
+        assert!(err
+            .source()
+            .and_then(|source| source.downcast_ref::<Asn1DerError>())
+            .is_some());
+    }
+
+    #[test]
+    fn serde_round_trip() {
+        let cert = Cert::from_pem(&crate::test_files::INTERMEDIATE_CA.parse::<Pem>().unwrap()).unwrap();
+
+        let json = serde_json::to_string(&cert).unwrap();
+        assert!(json.starts_with("\"-----BEGIN CERTIFICATE-----"));
+        assert_eq!(serde_json::from_str::<Cert>(&json).unwrap(), cert);
+
+        let bytes = bincode::serialize(&cert).unwrap();
+        assert_eq!(bincode::deserialize::<Cert>(&bytes).unwrap(), cert);
+    }
+
+    #[test]
+    fn serde_rejects_invalid_payload() {
+        let json = serde_json::to_string(&Pem::new("PUBLIC KEY", vec![0x30, 0x00]).to_string()).unwrap();
+        let err = serde_json::from_str::<Cert>(&json).unwrap_err();
+        assert_eq!(err.to_string(), "invalid PEM label: PUBLIC KEY");
+
+        let bytes = bincode::serialize(&vec![0x30u8, 0x03, 0x02, 0x01]).unwrap();
+        let err = bincode::deserialize::<Cert>(&bytes).unwrap_err();
+        assert!(err.to_string().starts_with("(asn1) couldn't deserialize certificate"));
+    }
 
     #[test]
     fn read_pem_and_parse_certificate() {
@@ -906,6 +1158,92 @@ mod tests {
         assert_eq!(cert.issuer_name().to_string(), "C=NL,O=PolarSSL,CN=PolarSSL Test CA");
     }
 
+    #[test]
+    fn serial_number_validity_and_signature_hash_type() {
+        let root = Cert::from_pem(&crate::test_files::ROOT_CA.parse::<Pem>().unwrap()).unwrap();
+        assert_eq!(
+            hex::encode(root.serial_number().as_unsigned_bytes_be()),
+            "44afb080d6a327ba893039862ef8406b"
+        );
+        assert_eq!(root.valid_not_before(), UTCDate::new(2000, 9, 30, 21, 12, 19).unwrap());
+        assert_eq!(root.valid_not_after(), UTCDate::new(2021, 9, 30, 14, 1, 15).unwrap());
+        assert_eq!(
+            root.signature_hash_type(),
+            Some(SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA1))
+        );
+
+        let key = parse_key(crate::test_files::RSA_2048_PK_1);
+        let built = CertificateBuilder::new()
+            .valididy(UTCDate::ymd(2065, 6, 15).unwrap(), UTCDate::ymd(2070, 6, 15).unwrap())
+            .self_signed(DirectoryName::new_common_name("Accessors Root CA"), &key)
+            .serial_number(vec![0x0A, 0xB1, 0x2C])
+            .signature_hash_type(SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_384))
+            .build()
+            .expect("couldn't build certificate");
+        let built = Cert::from_der(&built.to_der().unwrap()).unwrap();
+        assert_eq!(built.serial_number().as_unsigned_bytes_be(), [0x0A, 0xB1, 0x2C]);
+        assert_eq!(built.valid_not_before(), UTCDate::ymd(2065, 6, 15).unwrap());
+        assert_eq!(built.valid_not_after(), UTCDate::ymd(2070, 6, 15).unwrap());
+        assert_eq!(
+            built.signature_hash_type(),
+            Some(SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_384))
+        );
+
+        let mut ecdsa = Certificate::from(built);
+        ecdsa.tbs_certificate.signature = AlgorithmIdentifier::new_ecdsa_with_sha384();
+        let ecdsa = Cert::from(ecdsa);
+        assert_eq!(ecdsa.signature_hash_type(), Some(SignatureAlgorithm::EcdsaSha384));
+
+        let mut unsupported = Certificate::from(ecdsa);
+        unsupported.tbs_certificate.signature = AlgorithmIdentifier::new_rsa_encryption();
+        assert_eq!(Cert::from(unsupported).signature_hash_type(), None);
+    }
+
+    #[test]
+    fn vendor_extensions_are_preserved() {
+        use picky_asn1_x509::extension::ExtensionView;
+
+        let ca_pem = crate::test_files::VENDOR_EXTENSIONS_CA.parse::<Pem>().unwrap();
+        let ca = Cert::from_der(ca_pem.data()).expect("couldn't parse CA");
+        let pem = crate::test_files::VENDOR_EXTENSIONS.parse::<Pem>().unwrap();
+        let cert = Cert::from_der(pem.data()).expect("couldn't parse certificate with vendor extensions");
+        assert_eq!(cert.to_der().unwrap(), pem.data());
+
+        let unknown: Vec<String> = cert
+            .extensions()
+            .iter()
+            .filter_map(|ext| match ext.extn_value() {
+                ExtensionView::Unknown { oid, critical, .. } => {
+                    assert!(!critical);
+                    Some(oid.0.clone().into())
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            unknown,
+            [
+                "2.5.29.32",               // certificate policies
+                "2.5.29.31",               // CRL distribution points
+                "2.16.840.1.113730.1.13",  // Netscape comment
+                "1.3.6.1.4.1.311.21.7",    // Microsoft certificate template
+                "1.3.6.1.4.1.11129.2.4.2", // CT precertificate SCTs
+            ]
+        );
+        assert_eq!(
+            cert.subject_alt_name().unwrap().find_dns_name().unwrap().to_string(),
+            "vendor.example"
+        );
+        assert!(cert.extended_key_usage().unwrap().server_auth());
+        assert_eq!(cert.authority_info_access().unwrap().ocsp().count(), 1);
+
+        cert.verifier()
+            .chain(std::iter::once(&ca))
+            .exact_date(&UTCDate::ymd(2030, 1, 1).unwrap())
+            .verify()
+            .expect("couldn't verify certificate with vendor extensions");
+    }
+
     #[test]
     fn get_identifier() {
         let pem = crate::test_files::RSA_2048_PK_1
@@ -932,6 +1270,190 @@ mod tests {
         assert_eq!(root.ty(), CertType::Root);
     }
 
+    #[test]
+    fn key_usage() {
+        let private_key = parse_key(crate::test_files::RSA_2048_PK_1);
+        let key_usage = KeyUsage::default().with_key_cert_sign(true).with_crl_sign(true);
+
+        let root = CertificateBuilder::new()
+            .valididy(UTCDate::ymd(2019, 10, 10).unwrap(), UTCDate::ymd(2019, 10, 11).unwrap())
+            .self_signed(DirectoryName::new_common_name("test"), &private_key)
+            .ca(true)
+            .key_usage(key_usage.clone())
+            .build()
+            .expect("couldn't generate root ca");
+
+        let root = Cert::from_der(&root.to_der().unwrap()).unwrap();
+        let parsed_key_usage = root.key_usage().expect("couldn't get key usage");
+        assert_eq!(parsed_key_usage, &key_usage);
+        assert_eq!(parsed_key_usage.to_string(), "Certificate Sign, CRL Sign");
+        assert!(!parsed_key_usage.digital_signature());
+    }
+
+    #[test]
+    fn deterministic_build_with_seeded_rng() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let private_key = parse_key(crate::test_files::RSA_2048_PK_1);
+
+        let build = || {
+            CertificateBuilder::new()
+                .valididy(UTCDate::ymd(2019, 10, 10).unwrap(), UTCDate::ymd(2019, 10, 11).unwrap())
+                .self_signed(DirectoryName::new_common_name("test"), &private_key)
+                .ca(true)
+                .build_with_rng(&mut StdRng::seed_from_u64(42))
+                .expect("couldn't generate root ca")
+                .to_der()
+                .unwrap()
+        };
+
+        assert_eq!(build(), build());
+
+        let with_serial = CertificateBuilder::new()
+            .valididy(UTCDate::ymd(2019, 10, 10).unwrap(), UTCDate::ymd(2019, 10, 11).unwrap())
+            .self_signed(DirectoryName::new_common_name("test"), &private_key)
+            .serial_number(vec![0x12, 0x34])
+            .build()
+            .expect("couldn't generate root ca");
+        assert_eq!(with_serial.serial_number(), &vec![0x12, 0x34]);
+    }
+
+    #[test]
+    fn issuance_from_verified_csr() {
+        let issuer_key = parse_key(crate::test_files::RSA_2048_PK_1);
+        let subject_key = parse_key(crate::test_files::RSA_2048_PK_2);
+        let csr = Csr::generate(
+            DirectoryName::new_common_name("test.contoso.local"),
+            &subject_key,
+            SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_256),
+        )
+        .unwrap();
+
+        let builder = || {
+            let builder = CertificateBuilder::new();
+            builder
+                .valididy(UTCDate::ymd(2019, 10, 10).unwrap(), UTCDate::ymd(2019, 10, 11).unwrap())
+                .issuer(DirectoryName::new_common_name("test authority"), &issuer_key)
+                .authority_key_identifier(vec![1, 2, 3])
+                .serial_number(vec![1]);
+            builder
+        };
+
+        let from_csr = builder().subject_from_csr(csr.clone()).build().unwrap();
+        let verified = csr.into_verified().expect("couldn't verify csr");
+        let from_verified_csr = builder().subject_from_verified_csr(verified).build().unwrap();
+        assert_eq!(from_csr.to_der().unwrap(), from_verified_csr.to_der().unwrap());
+    }
+
+    #[test]
+    fn copy_requested_extensions() {
+        use crate::x509::{csr::Attribute, name::GeneralName};
+
+        let issuer_key = parse_key(crate::test_files::RSA_2048_PK_1);
+        let subject_key = parse_key(crate::test_files::RSA_2048_PK_2);
+        let san = GeneralNames::from(vec![
+            GeneralName::new_dns_name("test.contoso.local").unwrap(),
+            GeneralName::new_dns_name("www.contoso.local").unwrap(),
+        ]);
+        let csr = Csr::generate_with_attributes(
+            DirectoryName::new_common_name("test.contoso.local"),
+            &subject_key,
+            SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_256),
+            vec![Attribute::new_extension_request(vec![
+                Extension::new_subject_alt_name(san.clone()),
+                Extension::new_basic_constraints(Some(true), None),
+            ])],
+        )
+        .unwrap();
+        let csr = Csr::from_pem(&csr.to_pem().unwrap()).unwrap();
+        assert_eq!(csr.subject_alt_name(), Some(san.clone()));
+
+        let builder = || {
+            let builder = CertificateBuilder::new();
+            builder
+                .valididy(UTCDate::ymd(2019, 10, 10).unwrap(), UTCDate::ymd(2019, 10, 11).unwrap())
+                .issuer(DirectoryName::new_common_name("test authority"), &issuer_key)
+                .authority_key_identifier(vec![1, 2, 3])
+                .subject_from_csr(csr.clone());
+            builder
+        };
+
+        // requested extensions are ignored by default
+        let leaf = builder().build().unwrap();
+        assert!(leaf.subject_alt_name().is_err());
+
+        let leaf = builder()
+            .copy_requested_extensions(vec![oids::subject_alternative_name()])
+            .build()
+            .expect("couldn't build leaf");
+        let leaf = Cert::from_der(&leaf.to_der().unwrap()).unwrap();
+        assert_eq!(leaf.subject_alt_name().unwrap(), san);
+        assert_eq!(leaf.subject_alt_name().unwrap().dns_names().len(), 2);
+        // basic constraints requested in the CSR were not approved
+        assert_ne!(leaf.basic_constraints().unwrap().ca(), Some(true));
+
+        // san given to the builder wins over the requested one
+        let builder_san = GeneralNames::new(GeneralName::new_dns_name("other.contoso.local").unwrap());
+        let leaf = builder()
+            .copy_requested_extensions(vec![oids::subject_alternative_name(), oids::basic_constraints()])
+            .subject_alt_name(builder_san.clone())
+            .build()
+            .expect("couldn't build leaf");
+        assert_eq!(leaf.subject_alt_name().unwrap(), builder_san);
+        assert_ne!(leaf.basic_constraints().unwrap().ca(), Some(true));
+    }
+
+    #[test]
+    fn authority_info_access() {
+        use crate::x509::{extension::AccessDescription, name::GeneralName};
+
+        let issuer_key = parse_key(crate::test_files::RSA_2048_PK_1);
+        let ca_issuers = GeneralName::new_uri("http://ca.example/aia/0102.cer").unwrap();
+        let cert = CertificateBuilder::new()
+            .valididy(UTCDate::ymd(2019, 10, 10).unwrap(), UTCDate::ymd(2019, 10, 11).unwrap())
+            .self_signed(DirectoryName::new_common_name("test authority"), &issuer_key)
+            .authority_info_access(vec![AccessDescription::new_ca_issuers(ca_issuers.clone())].into())
+            .build()
+            .expect("couldn't build certificate");
+
+        let cert = Cert::from_der(&cert.to_der().unwrap()).unwrap();
+        let aia = cert
+            .authority_info_access()
+            .expect("couldn't get authority information access");
+        let locations: Vec<GeneralName> = aia.ca_issuers().cloned().map(GeneralName::from).collect();
+        assert_eq!(locations, [ca_issuers]);
+        assert_eq!(aia.ocsp().count(), 0);
+
+        let without_aia = Cert::from_pem(&crate::test_files::ROOT_CA.parse::<Pem>().unwrap()).unwrap();
+        assert!(matches!(
+            without_aia.authority_info_access(),
+            Err(CertError::ExtensionNotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn tls_server_extended_key_usage() {
+        let pem = crate::test_files::TLS_SERVER
+            .parse::<Pem>()
+            .expect("couldn't parse PEM");
+        let cert = Cert::from_pem(&pem).expect("couldn't deserialize certificate");
+        let eku = cert.extended_key_usage().expect("couldn't get extended key usage");
+        assert_eq!(eku.len(), 2);
+        assert!(eku.server_auth());
+        assert!(eku.client_auth());
+        assert!(!eku.code_signing());
+        assert!(!eku.ocsp_signing());
+        assert!(eku.contains(oids::kp_server_auth()));
+
+        let key_usage = cert.key_usage().expect("couldn't get key usage");
+        assert_eq!(key_usage.to_string(), "Digital Signature, Key Encipherment");
+
+        let san = cert.subject_alt_name().expect("couldn't get subject alternative name");
+        let dns_names: Vec<String> = san.dns_names().into_iter().map(|name| name.to_string()).collect();
+        assert_eq!(dns_names, vec!["www.example.com", "example.com"]);
+        assert!(san.ips().is_empty());
+    }
+
     #[test]
     fn key_id_and_cert() {
         let kid = "c4a7b1a47b2c71fadbe14b9075ffc41560858910";
@@ -1048,6 +1570,7 @@ mod tests {
             "invalid certificate \'CN=ChillingInTheFuture.usobakkari\': \
             certificate expired (not after: 2072-01-01 00:00:00, now: 2080-10-01 00:00:00)"
         );
+        assert_eq!(expired_err.kind(), ErrorKind::Expired);
 
         let intermediate_expired_err = signed_leaf
             .verifier()
@@ -1084,6 +1607,7 @@ mod tests {
             "invalid certificate \'CN=ChillingInTheFuture.usobakkari\': \
             certificate is not yet valid (not before: 2069-01-01 00:00:00, now: 2019-11-14 00:00:00)"
         );
+        assert_eq!(still_in_2019_err.kind(), ErrorKind::NotYetValid);
 
         let not_yet_valid_with_interval_err = signed_leaf
             .verifier()
@@ -1105,6 +1629,210 @@ mod tests {
             date_is_missing_err.to_string(),
             "missing required builder argument `now`"
         );
+        assert_eq!(date_is_missing_err.kind(), ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn ecdsa_chain() {
+        let root_pem = crate::test_files::ECDSA_ROOT_CA.parse::<Pem>().unwrap();
+        let root = Cert::from_der(root_pem.data()).unwrap();
+        assert_eq!(root.signature_hash_type(), Some(SignatureAlgorithm::EcdsaSha256));
+
+        let leaf_pem = crate::test_files::ECDSA_LEAF.parse::<Pem>().unwrap();
+        let leaf = Cert::from_der(leaf_pem.data()).unwrap();
+        assert_eq!(leaf.signature_hash_type(), Some(SignatureAlgorithm::EcdsaSha384));
+        assert_eq!(leaf.to_der().unwrap(), leaf_pem.data());
+
+        leaf.verifier()
+            .chain(std::iter::once(&root))
+            .exact_date(&UTCDate::ymd(2030, 1, 1).unwrap())
+            .verify()
+            .expect("couldn't verify ecdsa chain");
+
+        let other_root = CertificateBuilder::new()
+            .valididy(UTCDate::ymd(2020, 1, 1).unwrap(), UTCDate::ymd(2040, 1, 1).unwrap())
+            .self_signed(
+                DirectoryName::new_common_name("Picky ECDSA Test Root CA"),
+                &parse_key(crate::test_files::EC_P384_PK_1),
+            )
+            .ca(true)
+            .signature_hash_type(SignatureAlgorithm::EcdsaSha384)
+            .build()
+            .unwrap();
+        let err = leaf
+            .verifier()
+            .chain(std::iter::once(&other_root))
+            .exact_date(&UTCDate::ymd(2030, 1, 1).unwrap())
+            .verify()
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidChain);
+    }
+
+    #[test]
+    fn mixed_rsa_ecdsa_chain() {
+        let root_key = parse_key(crate::test_files::RSA_2048_PK_1);
+        let intermediate_key = parse_key(crate::test_files::EC_P256_PK_1);
+        let leaf_key = parse_key(crate::test_files::RSA_2048_PK_3);
+
+        let root = CertificateBuilder::new()
+            .valididy(UTCDate::ymd(2065, 6, 15).unwrap(), UTCDate::ymd(2070, 6, 15).unwrap())
+            .self_signed(DirectoryName::new_common_name("Mixed Root CA"), &root_key)
+            .ca(true)
+            .signature_hash_type(SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_256))
+            .build()
+            .expect("couldn't build root ca");
+
+        let intermediate = CertificateBuilder::new()
+            .valididy(UTCDate::ymd(2068, 1, 1).unwrap(), UTCDate::ymd(2071, 1, 1).unwrap())
+            .subject(
+                DirectoryName::new_common_name("Mixed ECDSA Authority"),
+                intermediate_key.to_public_key(),
+            )
+            .issuer_cert(&root, &root_key)
+            .signature_hash_type(SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_384))
+            .ca(true)
+            .pathlen(0)
+            .build()
+            .expect("couldn't build intermediate ca");
+
+        let leaf = CertificateBuilder::new()
+            .valididy(UTCDate::ymd(2069, 1, 1).unwrap(), UTCDate::ymd(2072, 1, 1).unwrap())
+            .subject(
+                DirectoryName::new_common_name("mixed.example"),
+                leaf_key.to_public_key(),
+            )
+            .issuer_cert(&intermediate, &intermediate_key)
+            .signature_hash_type(SignatureAlgorithm::EcdsaSha256)
+            .build()
+            .expect("couldn't build leaf");
+        assert_eq!(leaf.signature_hash_type(), Some(SignatureAlgorithm::EcdsaSha256));
+
+        let chain = [intermediate, root];
+        leaf.verifier()
+            .chain(chain.iter())
+            .exact_date(&UTCDate::ymd(2069, 10, 1).unwrap())
+            .verify()
+            .expect("couldn't verify mixed chain");
+
+        // an ECDSA signature can't be produced with an RSA key
+        let err = CertificateBuilder::new()
+            .valididy(UTCDate::ymd(2069, 1, 1).unwrap(), UTCDate::ymd(2072, 1, 1).unwrap())
+            .subject(
+                DirectoryName::new_common_name("mixed.example"),
+                leaf_key.to_public_key(),
+            )
+            .issuer_cert(&chain[1], &root_key)
+            .signature_hash_type(SignatureAlgorithm::EcdsaSha256)
+            .build()
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnsupportedAlgorithm);
+    }
+
+    #[test]
+    fn rsa_pss_chain() {
+        let root_pem = crate::test_files::RSA_PSS_ROOT_CA.parse::<Pem>().unwrap();
+        let root = Cert::from_der(root_pem.data()).unwrap();
+        assert_eq!(root.signature_hash_type(), Some(SignatureAlgorithm::RsaPssSha256));
+        assert_eq!(root.to_der().unwrap(), root_pem.data());
+
+        let leaf_pem = crate::test_files::RSA_PSS_LEAF.parse::<Pem>().unwrap();
+        let leaf = Cert::from_der(leaf_pem.data()).unwrap();
+        assert_eq!(leaf.signature_hash_type(), Some(SignatureAlgorithm::RsaPssSha512));
+        assert_eq!(leaf.to_der().unwrap(), leaf_pem.data());
+
+        leaf.verifier()
+            .chain(std::iter::once(&root))
+            .exact_date(&UTCDate::ymd(2030, 1, 1).unwrap())
+            .verify()
+            .expect("couldn't verify rsa-pss chain");
+
+        // issue a PSS-signed leaf from the OpenSSL root
+        let root_key = parse_key(crate::test_files::RSA_2048_PK_5);
+        let leaf_key = parse_key(crate::test_files::RSA_2048_PK_3);
+        let issued = CertificateBuilder::new()
+            .valididy(UTCDate::ymd(2029, 1, 1).unwrap(), UTCDate::ymd(2031, 1, 1).unwrap())
+            .subject(
+                DirectoryName::new_common_name("issued.pss.example"),
+                leaf_key.to_public_key(),
+            )
+            .issuer_cert(&root, &root_key)
+            .signature_hash_type(SignatureAlgorithm::RsaPssSha384)
+            .build()
+            .expect("couldn't build rsa-pss leaf");
+        assert_eq!(issued.signature_hash_type(), Some(SignatureAlgorithm::RsaPssSha384));
+
+        let issued = Cert::from_der(&issued.to_der().unwrap()).unwrap();
+        issued
+            .verifier()
+            .chain(std::iter::once(&root))
+            .exact_date(&UTCDate::ymd(2030, 1, 1).unwrap())
+            .verify()
+            .expect("couldn't verify issued rsa-pss leaf");
+    }
+
+    #[test]
+    fn issuer_cert() {
+        let root_key = parse_key(crate::test_files::RSA_2048_PK_1);
+        let leaf_key = parse_key(crate::test_files::RSA_2048_PK_2);
+        let other_key = parse_key(crate::test_files::RSA_2048_PK_3);
+
+        let root = CertificateBuilder::new()
+            .valididy(UTCDate::ymd(2065, 6, 15).unwrap(), UTCDate::ymd(2070, 6, 15).unwrap())
+            .self_signed(DirectoryName::new_common_name("Issuer Root CA"), &root_key)
+            .ca(true)
+            .build()
+            .expect("couldn't build root ca");
+
+        let leaf_builder = || {
+            let builder = CertificateBuilder::new();
+            builder
+                .valididy(UTCDate::ymd(2066, 1, 1).unwrap(), UTCDate::ymd(2069, 1, 1).unwrap())
+                .subject(
+                    DirectoryName::new_common_name("leaf.contoso.local"),
+                    leaf_key.to_public_key(),
+                )
+                .serial_number(vec![7]);
+            builder
+        };
+
+        let from_cert = leaf_builder()
+            .issuer_cert(&root, &root_key)
+            .build()
+            .expect("couldn't build leaf from issuer cert");
+        let by_hand = leaf_builder()
+            .issuer(root.subject_name(), &root_key)
+            .authority_key_identifier(root.subject_key_identifier().unwrap().to_vec())
+            .build()
+            .expect("couldn't build leaf");
+        assert_eq!(from_cert.to_der().unwrap(), by_hand.to_der().unwrap());
+
+        let mismatch_err = leaf_builder().issuer_cert(&root, &other_key).build().unwrap_err();
+        assert_eq!(
+            mismatch_err.to_string(),
+            "issuer private key doesn't match the public key of issuer certificate 'CN=Issuer Root CA'"
+        );
+        assert_eq!(mismatch_err.kind(), ErrorKind::InvalidInput);
+
+        let mut without_ski = Certificate::from(root);
+        (without_ski.tbs_certificate.extensions.0)
+            .0
+            .retain(|extension| *extension.extn_id() != oids::subject_key_identifier());
+        let without_ski = Cert::from(without_ski);
+        let no_ski_err = leaf_builder().issuer_cert(&without_ski, &root_key).build().unwrap_err();
+        assert_eq!(
+            no_ski_err.to_string(),
+            "issuer certificate 'CN=Issuer Root CA' has no subject key identifier"
+        );
+        assert_eq!(no_ski_err.kind(), ErrorKind::InvalidInput);
+        let with_aki = leaf_builder()
+            .issuer_cert(&without_ski, &root_key)
+            .authority_key_identifier(vec![1, 2, 3])
+            .build()
+            .expect("couldn't build leaf with explicit authority key identifier");
+        assert_eq!(
+            with_aki.authority_key_identifier().unwrap().key_identifier(),
+            Some(&[1, 2, 3][..])
+        );
     }
 
     #[test]
@@ -1130,7 +1858,9 @@ mod tests {
                 DirectoryName::new_common_name("V.E.R.Y Legitimate VerySafe Authority"),
                 intermediate_key.to_public_key(),
             )
-            .issuer_cert(&root, &malicious_root_key)
+            // forged by hand, as `issuer_cert` refuses a key not matching the issuer certificate
+            .issuer(root.subject_name(), &malicious_root_key)
+            .authority_key_identifier(root.subject_key_identifier().unwrap().to_vec())
             .signature_hash_type(SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_512))
             .key_id_gen_method(KeyIdGenMethod::SPKValueHashedLeftmost160(HashAlgorithm::SHA2_384))
             .ca(true)
@@ -1166,6 +1896,12 @@ mod tests {
             root_missing_err.to_string(),
             "CA chain error: chain is missing a root certificate"
         );
+        assert_eq!(root_missing_err.kind(), ErrorKind::InvalidChain);
+        let chain_err = root_missing_err
+            .source()
+            .and_then(|source| source.downcast_ref::<CaChainError>())
+            .expect("CA chain error source");
+        assert!(matches!(chain_err, CaChainError::NoRoot));
 
         let invalid_sig_err = signed_leaf
             .verifier()
@@ -1177,6 +1913,13 @@ mod tests {
             invalid_sig_err.to_string(),
             "invalid certificate \'CN=V.E.R.Y Legitimate VerySafe Authority\': signature error: invalid signature"
         );
+        assert_eq!(invalid_sig_err.kind(), ErrorKind::BadSignature);
+        let signature_err = invalid_sig_err
+            .source()
+            .and_then(|source| source.source())
+            .and_then(|source| source.downcast_ref::<SignatureError>())
+            .expect("signature error source");
+        assert!(matches!(signature_err, SignatureError::BadSignature));
     }
 
     #[test]
@@ -0,0 +1,483 @@
+use crate::{
+    error::ErrorKind,
+    hash::HashAlgorithm,
+    key::PrivateKey,
+    pem::Pem,
+    signature::{SignatureAlgorithm, SignatureError},
+    x509::{certificate::Cert, date::UTCDate, name::DirectoryName},
+};
+use picky_asn1::{
+    bit_string::BitString,
+    wrapper::{Asn1SequenceOf, IntegerAsn1},
+};
+use picky_asn1_der::Asn1DerError;
+use picky_asn1_x509::{
+    CertificateList, Extension, Extensions, KeyIdentifier, RevokedCertificate, TBSCertList, Version,
+};
+use std::cell::RefCell;
+use thiserror::Error;
+
+pub use picky_asn1_x509::CrlReason;
+
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum CrlError {
+    /// couldn't generate CRL
+    #[error("couldn't generate CRL: {source}")]
+    CrlGeneration { source: Box<CrlError> },
+
+    /// asn1 serialization error
+    #[error("(asn1) couldn't serialize {element}: {source}")]
+    Asn1Serialization {
+        element: &'static str,
+        source: Asn1DerError,
+    },
+
+    /// asn1 deserialization error
+    #[error("(asn1) couldn't deserialize {element}: {source}")]
+    Asn1Deserialization {
+        element: &'static str,
+        source: Asn1DerError,
+    },
+
+    /// signature error
+    #[error("signature error: {source}")]
+    Signature { source: SignatureError },
+
+    /// issuer name doesn't match
+    #[error("CRL issuer name doesn't match (expected: {expected}, got: {actual})")]
+    IssuerNameMismatch { expected: String, actual: String },
+
+    /// missing required builder argument
+    #[error("missing required builder argument `{arg}`")]
+    MissingBuilderArgument { arg: &'static str },
+
+    /// invalid PEM label error
+    #[error("invalid PEM label: {label}")]
+    InvalidPemLabel { label: String },
+}
+
+impl CrlError {
+    /// Stable classification of this error
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            CrlError::CrlGeneration { source } => source.kind(),
+            CrlError::Asn1Serialization { .. } => ErrorKind::Encoding,
+            CrlError::Asn1Deserialization { .. } => ErrorKind::Decoding,
+            CrlError::Signature { source } => source.kind(),
+            CrlError::IssuerNameMismatch { .. } => ErrorKind::InvalidChain,
+            CrlError::MissingBuilderArgument { .. } | CrlError::InvalidPemLabel { .. } => ErrorKind::InvalidInput,
+        }
+    }
+}
+
+const CRL_PEM_LABEL: &str = "X509 CRL";
+
+/// Certificate Revocation List
+#[derive(Clone, Debug, PartialEq)]
+pub struct Crl(CertificateList);
+
+impl From<CertificateList> for Crl {
+    fn from(certificate_list: CertificateList) -> Self {
+        Self(certificate_list)
+    }
+}
+
+impl From<Crl> for CertificateList {
+    fn from(crl: Crl) -> Self {
+        crl.0
+    }
+}
+
+impl Crl {
+    pub fn from_der<T: ?Sized + AsRef<[u8]>>(der: &T) -> Result<Self, CrlError> {
+        Ok(Self(picky_asn1_der::from_bytes(der.as_ref()).map_err(|e| {
+            CrlError::Asn1Deserialization {
+                source: e,
+                element: "certificate list",
+            }
+        })?))
+    }
+
+    pub fn from_pem(pem: &Pem) -> Result<Self, CrlError> {
+        match pem.label() {
+            CRL_PEM_LABEL => Self::from_der(pem.data()),
+            _ => Err(CrlError::InvalidPemLabel {
+                label: pem.label().to_owned(),
+            }),
+        }
+    }
+
+    pub fn to_der(&self) -> Result<Vec<u8>, CrlError> {
+        picky_asn1_der::to_vec(&self.0).map_err(|e| CrlError::Asn1Serialization {
+            source: e,
+            element: "certificate list",
+        })
+    }
+
+    pub fn to_pem(&self) -> Result<Pem<'static>, CrlError> {
+        Ok(Pem::new(CRL_PEM_LABEL, self.to_der()?))
+    }
+
+    pub fn issuer_name(&self) -> DirectoryName {
+        self.0.tbs_cert_list.issuer.clone().into()
+    }
+
+    pub fn this_update(&self) -> UTCDate {
+        self.0.tbs_cert_list.this_update.clone().into()
+    }
+
+    pub fn next_update(&self) -> Option<UTCDate> {
+        self.0.tbs_cert_list.next_update.clone().map(UTCDate::from)
+    }
+
+    pub fn crl_number(&self) -> Option<&IntegerAsn1> {
+        self.0.crl_number()
+    }
+
+    pub fn revoked_certificates(&self) -> &[RevokedCertificate] {
+        self.0.revoked_certificates()
+    }
+
+    pub fn is_revoked(&self, serial_number: &IntegerAsn1) -> bool {
+        self.revoked_certificates()
+            .iter()
+            .any(|revoked| revoked.user_certificate.as_unsigned_bytes_be() == serial_number.as_unsigned_bytes_be())
+    }
+
+    pub fn extensions(&self) -> &[Extension] {
+        self.0.extensions()
+    }
+
+    /// Checks that this CRL has been issued and signed by the given certificate.
+    pub fn verify(&self, issuer_cert: &Cert) -> Result<(), CrlError> {
+        let issuer_name = self.issuer_name();
+        let issuer_cert_subject_name = issuer_cert.subject_name();
+        if issuer_name != issuer_cert_subject_name {
+            return Err(CrlError::IssuerNameMismatch {
+                expected: issuer_name.to_string(),
+                actual: issuer_cert_subject_name.to_string(),
+            });
+        }
+
+        let hash_type = SignatureAlgorithm::from_algorithm_identifier(&self.0.signature_algorithm)
+            .map_err(|e| CrlError::Signature { source: e })?;
+        let msg = picky_asn1_der::to_vec(&self.0.tbs_cert_list).map_err(|e| CrlError::Asn1Serialization {
+            source: e,
+            element: "tbs cert list",
+        })?;
+        hash_type
+            .verify(issuer_cert.public_key(), &msg, self.0.signature_value.0.payload_view())
+            .map_err(|e| CrlError::Signature { source: e })
+    }
+}
+
+// === builder === //
+
+#[derive(Clone, Debug)]
+struct IssuerInfos<'a> {
+    name: DirectoryName,
+    key: &'a PrivateKey,
+}
+
+#[derive(Clone, Debug)]
+struct RevokedInfos {
+    serial_number: IntegerAsn1,
+    revocation_date: UTCDate,
+    reason: Option<CrlReason>,
+}
+
+// Statically checks the field actually exists and returns a &'static str of the field name
+macro_rules! field_str {
+    ($field:ident) => {{
+        const _: fn() = || {
+            let CrlBuilderInner { $field: _, .. };
+        };
+        stringify!($field)
+    }};
+}
+
+#[derive(Default, Clone, Debug)]
+struct CrlBuilderInner<'a> {
+    this_update: Option<UTCDate>,
+    next_update: Option<UTCDate>,
+    issuer_infos: Option<IssuerInfos<'a>>,
+    authority_key_identifier: Option<Vec<u8>>,
+    crl_number: Option<u64>,
+    revoked: Vec<RevokedInfos>,
+    signature_hash_type: Option<SignatureAlgorithm>,
+}
+
+#[derive(Default, Clone, Debug)]
+pub struct CrlBuilder<'a> {
+    inner: RefCell<CrlBuilderInner<'a>>,
+}
+
+impl<'a> CrlBuilder<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Required
+    #[inline]
+    pub fn this_update(&self, this_update: UTCDate) -> &Self {
+        self.inner.borrow_mut().this_update = Some(this_update);
+        self
+    }
+
+    /// Optional
+    #[inline]
+    pub fn next_update(&self, next_update: UTCDate) -> &Self {
+        self.inner.borrow_mut().next_update = Some(next_update);
+        self
+    }
+
+    /// Required (alternative: `issuer_cert`)
+    #[inline]
+    pub fn issuer(&self, issuer_name: DirectoryName, issuer_key: &'a PrivateKey) -> &Self {
+        self.inner.borrow_mut().issuer_infos = Some(IssuerInfos {
+            name: issuer_name,
+            key: issuer_key,
+        });
+        self
+    }
+
+    /// Required (alternative: `issuer`)
+    #[inline]
+    pub fn issuer_cert(&self, issuer_cert: &Cert, issuer_key: &'a PrivateKey) -> &Self {
+        let builder = self.issuer(issuer_cert.subject_name(), issuer_key);
+
+        if let Ok(issuer_ski) = issuer_cert.subject_key_identifier() {
+            self.authority_key_identifier(issuer_ski.to_vec())
+        } else {
+            builder
+        }
+    }
+
+    /// Optional (alternative: `issuer_cert`)
+    #[inline]
+    pub fn authority_key_identifier(&self, aki: Vec<u8>) -> &Self {
+        self.inner.borrow_mut().authority_key_identifier = Some(aki);
+        self
+    }
+
+    /// Optional
+    ///
+    /// Should be monotonically increasing for a given issuer.
+    #[inline]
+    pub fn crl_number(&self, crl_number: u64) -> &Self {
+        self.inner.borrow_mut().crl_number = Some(crl_number);
+        self
+    }
+
+    /// Optional, may be called several times
+    #[inline]
+    pub fn revoked_certificate(
+        &self,
+        serial_number: IntegerAsn1,
+        revocation_date: UTCDate,
+        reason: Option<CrlReason>,
+    ) -> &Self {
+        self.inner.borrow_mut().revoked.push(RevokedInfos {
+            serial_number,
+            revocation_date,
+            reason,
+        });
+        self
+    }
+
+    /// Optional
+    #[inline]
+    pub fn signature_hash_type(&self, signature_hash_type: SignatureAlgorithm) -> &Self {
+        self.inner.borrow_mut().signature_hash_type = Some(signature_hash_type);
+        self
+    }
+
+    pub fn build(&self) -> Result<Crl, CrlError> {
+        let mut inner = self.inner.borrow_mut();
+
+        let this_update = inner.this_update.take().ok_or(CrlError::MissingBuilderArgument {
+            arg: field_str!(this_update),
+        })?;
+        let next_update = inner.next_update.take();
+
+        let issuer_infos = inner.issuer_infos.take().ok_or(CrlError::MissingBuilderArgument {
+            arg: field_str!(issuer_infos),
+        })?;
+
+        let signature_hash_type = inner
+            .signature_hash_type
+            .take()
+            .unwrap_or(SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_256));
+
+        let aki_opt = inner.authority_key_identifier.take();
+        let crl_number_opt = inner.crl_number.take();
+        let revoked = std::mem::take(&mut inner.revoked);
+
+        drop(inner);
+
+        let revoked_certificates = if revoked.is_empty() {
+            None
+        } else {
+            let revoked_certificates = revoked
+                .into_iter()
+                .map(|infos| RevokedCertificate {
+                    user_certificate: infos.serial_number,
+                    revocation_date: infos.revocation_date.into(),
+                    crl_entry_extensions: infos
+                        .reason
+                        .map(|reason| Extensions(vec![Extension::new_crl_reason(reason)])),
+                })
+                .collect();
+            Some(Asn1SequenceOf(revoked_certificates))
+        };
+
+        let crl_extensions = {
+            let mut extensions = Vec::new();
+
+            // aki
+            if let Some(aki) = aki_opt {
+                extensions.push(Extension::new_authority_key_identifier(
+                    KeyIdentifier::from(aki),
+                    None,
+                    None,
+                ));
+            }
+
+            // crl number
+            if let Some(crl_number) = crl_number_opt {
+                extensions.push(Extension::new_crl_number(crl_number_to_integer(crl_number)));
+            }
+
+            if extensions.is_empty() {
+                None
+            } else {
+                Some(Extensions(extensions).into())
+            }
+        };
+
+        let tbs_cert_list = TBSCertList {
+            version: Some(Version::V2),
+            signature: signature_hash_type.into(),
+            issuer: issuer_infos.name.into(),
+            this_update: this_update.into(),
+            next_update: next_update.map(UTCDate::into),
+            revoked_certificates,
+            crl_extensions,
+        };
+
+        let tbs_der = picky_asn1_der::to_vec(&tbs_cert_list)
+            .map_err(|e| CrlError::Asn1Serialization {
+                source: e,
+                element: "tbs cert list",
+            })
+            .map_err(|e| CrlError::CrlGeneration { source: Box::new(e) })?;
+        let signature_value = BitString::with_bytes(
+            signature_hash_type
+                .sign(&tbs_der, issuer_infos.key)
+                .map_err(|e| CrlError::Signature { source: e })
+                .map_err(|e| CrlError::CrlGeneration { source: Box::new(e) })?,
+        );
+
+        Ok(Crl(CertificateList {
+            tbs_cert_list,
+            signature_algorithm: signature_hash_type.into(),
+            signature_value: signature_value.into(),
+        }))
+    }
+}
+
+fn crl_number_to_integer(crl_number: u64) -> IntegerAsn1 {
+    let bytes = crl_number.to_be_bytes();
+    let first_non_zero = bytes.iter().position(|b| *b != 0).unwrap_or(bytes.len() - 1);
+    IntegerAsn1::from_unsigned_bytes_be(bytes[first_non_zero..].to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::x509::certificate::CertificateBuilder;
+
+    fn parse_key(pem_str: &str) -> PrivateKey {
+        let pem = pem_str.parse::<Pem>().unwrap();
+        PrivateKey::from_pkcs8(pem.data()).unwrap()
+    }
+
+    #[test]
+    fn openssl_crl() {
+        let crl_pem = crate::test_files::TEST_AUTHORITY_CRL.parse::<Pem>().unwrap();
+        let crl = Crl::from_pem(&crl_pem).expect("couldn't parse CRL");
+
+        let issuer_pem = crate::test_files::TEST_AUTHORITY.parse::<Pem>().unwrap();
+        let issuer = Cert::from_pem(&issuer_pem).unwrap();
+
+        crl.verify(&issuer).expect("couldn't verify CRL");
+        assert_eq!(crl.issuer_name().to_string(), "CN=Test Authority");
+        assert_eq!(crl.this_update(), UTCDate::new(2026, 10, 17, 2, 40, 30).unwrap());
+        assert_eq!(crl.next_update(), UTCDate::new(2026, 10, 24, 2, 40, 30));
+        assert_eq!(crl.crl_number().unwrap().as_unsigned_bytes_be(), &[0x10, 0x00]);
+        assert!(crl.is_revoked(&vec![0x01].into()));
+        assert!(crl.is_revoked(&vec![0x02].into()));
+        assert!(!crl.is_revoked(&vec![0x03].into()));
+        assert_eq!(crl.to_der().unwrap(), crl_pem.data());
+    }
+
+    #[test]
+    fn build_and_verify() {
+        let issuer_key = parse_key(crate::test_files::RSA_2048_PK_1);
+        let issuer = CertificateBuilder::new()
+            .valididy(UTCDate::ymd(2020, 1, 1).unwrap(), UTCDate::ymd(2030, 1, 1).unwrap())
+            .self_signed(DirectoryName::new_common_name("CRL Issuer"), &issuer_key)
+            .ca(true)
+            .build()
+            .expect("couldn't build issuer");
+
+        let crl = CrlBuilder::new()
+            .issuer_cert(&issuer, &issuer_key)
+            .this_update(UTCDate::ymd(2020, 6, 1).unwrap())
+            .next_update(UTCDate::ymd(2020, 6, 8).unwrap())
+            .crl_number(128)
+            .revoked_certificate(
+                vec![0x7A, 0x3F].into(),
+                UTCDate::ymd(2020, 5, 30).unwrap(),
+                Some(CrlReason::Superseded),
+            )
+            .revoked_certificate(vec![0x01].into(), UTCDate::ymd(2020, 5, 31).unwrap(), None)
+            .build()
+            .expect("couldn't build CRL");
+
+        let crl = Crl::from_pem(&crl.to_pem().unwrap()).expect("couldn't parse CRL back");
+        crl.verify(&issuer).expect("couldn't verify CRL");
+
+        assert_eq!(crl.crl_number().unwrap(), &IntegerAsn1::from(vec![0x00, 0x80]));
+        assert_eq!(crl.next_update(), UTCDate::ymd(2020, 6, 8));
+        let revoked = crl.revoked_certificates();
+        assert_eq!(revoked.len(), 2);
+        assert_eq!(revoked[0].reason_code(), Some(CrlReason::Superseded));
+        assert_eq!(revoked[1].reason_code(), None);
+        assert!(crl.is_revoked(&vec![0x7A, 0x3F].into()));
+        assert_eq!(
+            crl.extensions()[0].extn_value(),
+            picky_asn1_x509::ExtensionView::AuthorityKeyIdentifier(issuer.authority_key_identifier().unwrap())
+        );
+
+        let other_key = parse_key(crate::test_files::RSA_2048_PK_2);
+        let forged = CrlBuilder::new()
+            .issuer(issuer.subject_name(), &other_key)
+            .this_update(UTCDate::ymd(2020, 6, 1).unwrap())
+            .build()
+            .unwrap();
+        assert!(forged.verify(&issuer).is_err());
+    }
+
+    #[test]
+    fn crl_number_encoding() {
+        assert_eq!(crl_number_to_integer(0).0, vec![0x00]);
+        assert_eq!(crl_number_to_integer(0x7F).0, vec![0x7F]);
+        assert_eq!(crl_number_to_integer(0x1000).0, vec![0x10, 0x00]);
+        assert_eq!(
+            crl_number_to_integer(u64::MAX).0,
+            vec![0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]
+        );
+    }
+}
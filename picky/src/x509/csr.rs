@@ -1,15 +1,23 @@
 use crate::{
+    error::ErrorKind,
     key::{PrivateKey, PublicKey},
-    pem::Pem,
+    pem::{deserialize_pem_or_der, serialize_pem_or_der, Pem},
     signature::{SignatureAlgorithm, SignatureError},
-    x509::name::DirectoryName,
+    x509::name::{DirectoryName, GeneralNames},
 };
 use picky_asn1::bit_string::BitString;
 use picky_asn1_der::Asn1DerError;
-use picky_asn1_x509::{CertificationRequest, CertificationRequestInfo};
+use picky_asn1_x509::{
+    certification_request::Attributes, extension::ExtensionView, AlgorithmIdentifier, CertificationRequest,
+    CertificationRequestInfo, Extension,
+};
+use serde::{ser, Deserialize, Deserializer, Serialize, Serializer};
 use thiserror::Error;
 
+pub use picky_asn1_x509::certification_request::{Attribute, AttributeValue};
+
 #[derive(Debug, Error)]
+#[non_exhaustive]
 pub enum CsrError {
     /// asn1 serialization error
     #[error("(asn1) couldn't serialize {element}: {source}")]
@@ -34,6 +42,18 @@ pub enum CsrError {
     InvalidPemLabel { label: String },
 }
 
+impl CsrError {
+    /// Stable classification of this error
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            CsrError::Asn1Serialization { .. } => ErrorKind::Encoding,
+            CsrError::Asn1Deserialization { .. } => ErrorKind::Decoding,
+            CsrError::Signature { source } => source.kind(),
+            CsrError::InvalidPemLabel { .. } => ErrorKind::InvalidInput,
+        }
+    }
+}
+
 const CSR_PEM_LABEL: &str = "CERTIFICATE REQUEST";
 
 /// Certificate Signing Request
@@ -81,7 +101,21 @@ impl Csr {
         private_key: &PrivateKey,
         signature_hash_type: SignatureAlgorithm,
     ) -> Result<Self, CsrError> {
-        let info = CertificationRequestInfo::new(subject.into(), private_key.to_public_key().into());
+        Self::generate_with_attributes(subject, private_key, signature_hash_type, Vec::new())
+    }
+
+    /// Generates a CSR carrying the given attributes (e.g. `Attribute::new_extension_request`)
+    pub fn generate_with_attributes(
+        subject: DirectoryName,
+        private_key: &PrivateKey,
+        signature_hash_type: SignatureAlgorithm,
+        attributes: Vec<Attribute>,
+    ) -> Result<Self, CsrError> {
+        let info = CertificationRequestInfo::new_with_attributes(
+            subject.into(),
+            private_key.to_public_key().into(),
+            Attributes(attributes),
+        );
         let info_der = picky_asn1_der::to_vec(&info).map_err(|e| CsrError::Asn1Serialization {
             source: e,
             element: "certification request info",
@@ -103,6 +137,35 @@ impl Csr {
         self.0.certification_request_info.subject.clone().into()
     }
 
+    /// Extensions requested using the pkcs-9 extensionRequest attribute
+    pub fn requested_extensions(&self) -> &[Extension] {
+        self.0
+            .certification_request_info
+            .attributes
+            .0
+            .iter()
+            .find_map(|attribute| match &attribute.value {
+                AttributeValue::Extensions(values) => values.0.first(),
+                AttributeValue::Custom(_) => None,
+            })
+            .map(|extensions| extensions.0.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// Subject alternative name requested using the pkcs-9 extensionRequest attribute
+    pub fn subject_alt_name(&self) -> Option<GeneralNames> {
+        self.requested_extensions()
+            .iter()
+            .find_map(|extension| match extension.extn_value() {
+                ExtensionView::SubjectAltName(san) => Some(GeneralNames::from(san)),
+                _ => None,
+            })
+    }
+
+    pub fn signature_algorithm(&self) -> &AlgorithmIdentifier {
+        &self.0.signature_algorithm
+    }
+
     pub fn public_key(&self) -> &PublicKey {
         (&self.0.certification_request_info.subject_public_key_info).into()
     }
@@ -114,12 +177,16 @@ impl Csr {
         )
     }
 
+    /// Verifies the CSR signature, marking it as verified on success
+    pub fn into_verified(self) -> Result<VerifiedCsr, CsrError> {
+        self.verify()?;
+        Ok(VerifiedCsr(self))
+    }
+
     pub fn verify(&self) -> Result<(), CsrError> {
         let hash_type = SignatureAlgorithm::from_algorithm_identifier(&self.0.signature_algorithm)
             .map_err(|e| CsrError::Signature { source: e })?;
 
-        let public_key = &self.0.certification_request_info.subject_public_key_info;
-
         let msg =
             picky_asn1_der::to_vec(&self.0.certification_request_info).map_err(|e| CsrError::Asn1Serialization {
                 source: e,
@@ -127,9 +194,136 @@ impl Csr {
             })?;
 
         hash_type
-            .verify(&public_key.clone().into(), &msg, self.0.signature.0.payload_view())
+            .verify(self.public_key(), &msg, self.0.signature.0.payload_view())
             .map_err(|e| CsrError::Signature { source: e })?;
 
         Ok(())
     }
 }
+
+impl Serialize for Csr {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let der = self.to_der().map_err(ser::Error::custom)?;
+        serialize_pem_or_der(serializer, CSR_PEM_LABEL, &der)
+    }
+}
+
+impl<'de> Deserialize<'de> for Csr {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_pem_or_der(deserializer, Self::from_pem, Self::from_der)
+    }
+}
+
+/// Certificate Signing Request whose signature has been verified
+///
+/// Obtained through `Csr::into_verified`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VerifiedCsr(Csr);
+
+impl VerifiedCsr {
+    pub fn csr(&self) -> &Csr {
+        &self.0
+    }
+
+    pub fn into_csr(self) -> Csr {
+        self.0
+    }
+
+    pub fn into_subject_infos(self) -> (DirectoryName, PublicKey) {
+        self.0.into_subject_infos()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{hash::HashAlgorithm, x509::name::GeneralName};
+
+    #[test]
+    fn generate_with_extension_request() {
+        let pem = crate::test_files::RSA_2048_PK_1
+            .parse::<Pem>()
+            .expect("couldn't parse pem");
+        let private_key = PrivateKey::from_pem(&pem).expect("couldn't extract private key");
+        let san = GeneralNames::from(vec![
+            GeneralName::new_dns_name("test.contoso.local").unwrap(),
+            GeneralName::new_dns_name("www.contoso.local").unwrap(),
+        ]);
+
+        let csr = Csr::generate_with_attributes(
+            DirectoryName::new_common_name("test.contoso.local"),
+            &private_key,
+            SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_256),
+            vec![Attribute::new_extension_request(vec![Extension::new_subject_alt_name(
+                san.clone(),
+            )])],
+        )
+        .unwrap();
+
+        let csr = Csr::from_der(&csr.to_der().unwrap()).unwrap();
+        csr.verify().unwrap();
+
+        let requested = csr.requested_extensions();
+        assert_eq!(requested.len(), 1);
+        match requested[0].extn_value() {
+            ExtensionView::SubjectAltName(requested_san) => assert_eq!(GeneralNames::from(requested_san), san),
+            unexpected => panic!("unexpected extension: {:?}", unexpected),
+        }
+        assert_eq!(csr.subject_alt_name(), Some(san));
+
+        let csr = Csr::generate(
+            DirectoryName::new_common_name("test.contoso.local"),
+            &private_key,
+            SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_256),
+        )
+        .unwrap();
+        assert!(csr.requested_extensions().is_empty());
+        assert_eq!(csr.subject_alt_name(), None);
+    }
+
+    #[test]
+    fn serde_round_trip() {
+        let pem = crate::test_files::RSA_2048_PK_1
+            .parse::<Pem>()
+            .expect("couldn't parse pem");
+        let private_key = PrivateKey::from_pem(&pem).expect("couldn't extract private key");
+        let csr = Csr::generate(
+            DirectoryName::new_common_name("test.contoso.local"),
+            &private_key,
+            SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_256),
+        )
+        .unwrap();
+
+        let json = serde_json::to_string(&csr).unwrap();
+        assert!(json.starts_with("\"-----BEGIN CERTIFICATE REQUEST-----"));
+        assert_eq!(serde_json::from_str::<Csr>(&json).unwrap(), csr);
+
+        let bytes = bincode::serialize(&csr).unwrap();
+        assert_eq!(bincode::deserialize::<Csr>(&bytes).unwrap(), csr);
+    }
+
+    #[test]
+    fn into_verified_rejects_tampered_signature() {
+        let pem = crate::test_files::RSA_2048_PK_1
+            .parse::<Pem>()
+            .expect("couldn't parse pem");
+        let private_key = PrivateKey::from_pem(&pem).expect("couldn't extract private key");
+        let csr = Csr::generate(
+            DirectoryName::new_common_name("test.contoso.local"),
+            &private_key,
+            SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_256),
+        )
+        .unwrap();
+
+        let mut der = csr.to_der().unwrap();
+        let verified = Csr::from_der(&der)
+            .unwrap()
+            .into_verified()
+            .expect("couldn't verify csr");
+        assert_eq!(verified.csr(), &csr);
+
+        *der.last_mut().unwrap() ^= 0xff;
+        let err = Csr::from_der(&der).unwrap().into_verified().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::BadSignature);
+    }
+}
@@ -1,8 +1,38 @@
+use crate::error::ErrorKind;
 #[cfg(feature = "chrono_conversion")]
 use chrono::{DateTime, Utc};
 use picky_asn1::date::{Date, GeneralizedTime, UTCTime, UTCTimeRepr};
 use picky_asn1_x509::validity::Time;
-use std::fmt;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::{fmt, str::FromStr};
+use thiserror::Error;
+
+/// Earliest year accepted when parsing a date (lower bound of the UTCTime encoding)
+const MIN_YEAR: i64 = 1950;
+/// Latest year accepted when parsing a date (upper bound of the GeneralizedTime encoding)
+const MAX_YEAR: i64 = 9999;
+
+#[derive(Debug, Clone, PartialEq, Error)]
+#[non_exhaustive]
+pub enum UTCDateError {
+    /// input isn't a RFC 3339 timestamp
+    #[error("invalid RFC 3339 timestamp `{input}`: {reason}")]
+    InvalidFormat { input: String, reason: &'static str },
+
+    /// date can't be represented
+    #[error("date `{input}` is out of the supported range (years {} to {})", MIN_YEAR, MAX_YEAR)]
+    OutOfRange { input: String },
+}
+
+impl UTCDateError {
+    /// Stable classification of this error
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            UTCDateError::InvalidFormat { .. } => ErrorKind::Decoding,
+            UTCDateError::OutOfRange { .. } => ErrorKind::InvalidInput,
+        }
+    }
+}
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct UTCDate(GeneralizedTime);
@@ -53,6 +83,166 @@ impl UTCDate {
     pub fn second(&self) -> u8 {
         self.0.second()
     }
+
+    /// Formats the date as a RFC 3339 timestamp in UTC, e.g. `2072-01-01T00:00:00Z`
+    pub fn to_rfc3339(&self) -> String {
+        format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+            self.year(),
+            self.month(),
+            self.day(),
+            self.hour(),
+            self.minute(),
+            self.second()
+        )
+    }
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Proleptic Gregorian date of a number of days since 1970-01-01
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if (year % 4 == 0 && year % 100 != 0) || year % 400 == 0 => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+impl FromStr for UTCDate {
+    type Err = UTCDateError;
+
+    /// Parses a RFC 3339 timestamp such as `2020-08-01T12:30:00Z` or `2020-08-01T14:30:00.250+02:00`.
+    ///
+    /// An explicit `Z` or UTC offset is required. Fractional seconds are truncated.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason| UTCDateError::InvalidFormat {
+            input: s.to_owned(),
+            reason,
+        };
+
+        let bytes = s.as_bytes();
+        if bytes.len() < 20 || !s.is_ascii() {
+            return Err(invalid("expected YYYY-MM-DDTHH:MM:SS followed by Z or an offset"));
+        }
+
+        let number = |range: std::ops::Range<usize>| -> Result<i64, UTCDateError> {
+            let digits = &s[range];
+            if digits.bytes().all(|b| b.is_ascii_digit()) {
+                Ok(digits.parse().expect("ascii digits"))
+            } else {
+                Err(invalid("expected digits"))
+            }
+        };
+
+        if bytes[4] != b'-' || bytes[7] != b'-' || bytes[13] != b':' || bytes[16] != b':' {
+            return Err(invalid("expected YYYY-MM-DDTHH:MM:SS"));
+        }
+        if bytes[10] != b'T' && bytes[10] != b't' {
+            return Err(invalid("expected a `T` between date and time"));
+        }
+
+        let year = number(0..4)?;
+        let month = number(5..7)?;
+        let day = number(8..10)?;
+        let hour = number(11..13)?;
+        let minute = number(14..16)?;
+        let second = number(17..19)?;
+
+        if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+            return Err(invalid("invalid date"));
+        }
+        if hour > 23 || minute > 59 || second > 59 {
+            return Err(invalid("invalid time"));
+        }
+
+        let mut rest = &s[19..];
+        if rest.starts_with('.') {
+            let fraction = &rest[1..];
+            let digits = fraction.bytes().take_while(u8::is_ascii_digit).count();
+            if digits == 0 {
+                return Err(invalid("expected digits after the decimal point"));
+            }
+            rest = &fraction[digits..];
+        }
+
+        let offset_secs = match rest.as_bytes() {
+            [b'Z'] | [b'z'] => 0,
+            [sign @ b'+', ..] | [sign @ b'-', ..] if rest.len() == 6 && rest.as_bytes()[3] == b':' => {
+                let offset_start = s.len() - 5;
+                let offset_hours = number(offset_start..offset_start + 2)?;
+                let offset_minutes = number(offset_start + 3..offset_start + 5)?;
+                if offset_hours > 23 || offset_minutes > 59 {
+                    return Err(invalid("invalid UTC offset"));
+                }
+                let offset = offset_hours * 3600 + offset_minutes * 60;
+                if *sign == b'-' {
+                    -offset
+                } else {
+                    offset
+                }
+            }
+            [] => return Err(invalid("missing Z or UTC offset")),
+            _ => return Err(invalid("expected Z or a UTC offset such as +02:00")),
+        };
+
+        let timestamp = days_from_civil(year, month, day) * 86_400 + hour * 3600 + minute * 60 + second - offset_secs;
+        let (year, month, day) = civil_from_days(timestamp.div_euclid(86_400));
+        let seconds_of_day = timestamp.rem_euclid(86_400);
+
+        if !(MIN_YEAR..=MAX_YEAR).contains(&year) {
+            return Err(UTCDateError::OutOfRange { input: s.to_owned() });
+        }
+
+        Ok(UTCDate::new(
+            year as u16,
+            month as u8,
+            day as u8,
+            (seconds_of_day / 3600) as u8,
+            (seconds_of_day % 3600 / 60) as u8,
+            (seconds_of_day % 60) as u8,
+        )
+        .expect("valid date"))
+    }
+}
+
+impl Serialize for UTCDate {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_rfc3339())
+    }
+}
+
+impl<'de> Deserialize<'de> for UTCDate {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
+    }
 }
 
 impl Into<UTCTime> for UTCDate {
@@ -140,3 +330,76 @@ impl fmt::Display for UTCDate {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rfc3339_round_trip() {
+        let date = UTCDate::new(2072, 1, 1, 0, 0, 0).unwrap();
+        assert_eq!(date.to_rfc3339(), "2072-01-01T00:00:00Z");
+        assert_eq!(date.to_rfc3339().parse::<UTCDate>().unwrap(), date);
+
+        for (input, expected) in &[
+            ("2020-08-01T12:30:45Z", "2020-08-01T12:30:45Z"),
+            ("2020-08-01t12:30:45z", "2020-08-01T12:30:45Z"),
+            ("2020-08-01T12:30:45.999Z", "2020-08-01T12:30:45Z"),
+            ("2020-08-01T14:30:45.25+02:00", "2020-08-01T12:30:45Z"),
+            ("2020-08-01T00:30:00+01:00", "2020-07-31T23:30:00Z"),
+            ("2020-02-28T23:00:00-01:30", "2020-02-29T00:30:00Z"),
+            ("2019-12-31T23:59:59-00:00", "2019-12-31T23:59:59Z"),
+            ("1950-01-01T00:00:00Z", "1950-01-01T00:00:00Z"),
+            ("9999-12-31T23:59:59Z", "9999-12-31T23:59:59Z"),
+        ] {
+            let date = input.parse::<UTCDate>().unwrap();
+            assert_eq!(date.to_rfc3339(), *expected, "{}", input);
+            assert_eq!(expected.parse::<UTCDate>().unwrap(), date);
+        }
+    }
+
+    #[test]
+    fn rfc3339_rejections() {
+        for input in &[
+            "",
+            "2020-08-01",
+            "2020-08-01 12:30:45Z",
+            "2020-08-01T12:30:45",
+            "2020-08-01T12:30:45+0200",
+            "2020-08-01T12:30:45.Z",
+            "2020-08-01T12:30:45Z ",
+            "2020-13-01T12:30:45Z",
+            "2019-02-29T12:30:45Z",
+            "2020-08-01T24:00:00Z",
+            "2020-08-01T12:30:60Z",
+            "2020-08-01T12:30:45+24:00",
+            "2020-0a-01T12:30:45Z",
+        ] {
+            let err = input.parse::<UTCDate>().unwrap_err();
+            assert!(matches!(err, UTCDateError::InvalidFormat { .. }), "{}: {}", input, err);
+            assert_eq!(err.kind(), ErrorKind::Decoding);
+        }
+
+        for input in &[
+            "1949-12-31T23:59:59Z",
+            "1950-01-01T00:30:00+01:00",
+            "9999-12-31T23:30:00-01:00",
+        ] {
+            let err = input.parse::<UTCDate>().unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                format!("date `{}` is out of the supported range (years 1950 to 9999)", input)
+            );
+        }
+    }
+
+    #[cfg(feature = "jose")]
+    #[test]
+    fn serde_round_trip() {
+        let date = UTCDate::new(2020, 8, 1, 12, 30, 45).unwrap();
+        let json = serde_json::to_string(&date).unwrap();
+        assert_eq!(json, "\"2020-08-01T12:30:45Z\"");
+        assert_eq!(serde_json::from_str::<UTCDate>(&json).unwrap(), date);
+        assert!(serde_json::from_str::<UTCDate>("\"2020-08-01T12:30:45\"").is_err());
+    }
+}
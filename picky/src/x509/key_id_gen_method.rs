@@ -1,12 +1,16 @@
 use crate::{
+    error::ErrorKind,
     hash::HashAlgorithm,
     key::{KeyError, PublicKey},
 };
 use picky_asn1::wrapper::BitStringAsn1Container;
 use picky_asn1_der::Asn1DerError;
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
 use thiserror::Error;
 
 #[derive(Debug, Error)]
+#[non_exhaustive]
 pub enum KeyIdGenError {
     /// asn1 serialization error
     #[error("(asn1) couldn't serialize {element}: {source}")]
@@ -18,13 +22,30 @@ pub enum KeyIdGenError {
     /// invalid key
     #[error("invalid key: {source}")]
     InvalidKey { source: KeyError },
+
+    /// unsupported key identifier generation method
+    #[error("unsupported key identifier generation method: {method}")]
+    UnsupportedMethod { method: String },
+}
+
+impl KeyIdGenError {
+    /// Stable classification of this error
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            KeyIdGenError::Asn1Serialization { .. } => ErrorKind::Encoding,
+            KeyIdGenError::InvalidKey { source } => source.kind(),
+            KeyIdGenError::UnsupportedMethod { .. } => ErrorKind::UnsupportedAlgorithm,
+        }
+    }
 }
 
 /// Describes which method to use to generate key identifiers.
 ///
 /// See [RFC5280 #4](https://tools.ietf.org/html/rfc5280#section-4.2.1.2) and
 /// [RFC7093 #2](https://tools.ietf.org/html/rfc7093#section-2).
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// The `FromStr` and `Display` implementations use names such as `spk-value-sha1`
+/// (SHA-1 over the subjectPublicKey value, as done by OpenSSL) and `spki-der-sha256` (the default).
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum KeyIdGenMethod {
     /// Hash the leftmost 160-bits of the
     /// SHA-256 hash of the value of the BIT STRING subjectPublicKey
@@ -34,7 +55,79 @@ pub enum KeyIdGenMethod {
     SPKFullDER(HashAlgorithm),
 }
 
+/// Names of the hash algorithms in method names
+const HASH_ALGORITHM_NAMES: &[(HashAlgorithm, &str)] = &[
+    (HashAlgorithm::SHA1, "sha1"),
+    (HashAlgorithm::SHA2_224, "sha224"),
+    (HashAlgorithm::SHA2_256, "sha256"),
+    (HashAlgorithm::SHA2_384, "sha384"),
+    (HashAlgorithm::SHA2_512, "sha512"),
+    (HashAlgorithm::SHA3_384, "sha3-384"),
+    (HashAlgorithm::SHA3_512, "sha3-512"),
+];
+
+const SPK_VALUE_PREFIX: &str = "spk-value-";
+const SPKI_DER_PREFIX: &str = "spki-der-";
+
+impl Default for KeyIdGenMethod {
+    fn default() -> Self {
+        KeyIdGenMethod::SPKFullDER(HashAlgorithm::SHA2_256)
+    }
+}
+
+impl FromStr for KeyIdGenMethod {
+    type Err = KeyIdGenError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lowercase = s.to_lowercase();
+        let hash_algorithm = |name: &str| {
+            HASH_ALGORITHM_NAMES
+                .iter()
+                .find(|(_, hash_name)| *hash_name == name)
+                .map(|(hash_algo, _)| *hash_algo)
+        };
+
+        let method = if let Some(hash_name) = lowercase.strip_prefix(SPK_VALUE_PREFIX) {
+            hash_algorithm(hash_name).map(KeyIdGenMethod::SPKValueHashedLeftmost160)
+        } else if let Some(hash_name) = lowercase.strip_prefix(SPKI_DER_PREFIX) {
+            hash_algorithm(hash_name).map(KeyIdGenMethod::SPKFullDER)
+        } else {
+            None
+        };
+
+        method.ok_or_else(|| KeyIdGenError::UnsupportedMethod { method: s.to_owned() })
+    }
+}
+
+/// Name parsed by the `FromStr` implementation
+impl fmt::Display for KeyIdGenMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (prefix, hash_algo) = match self {
+            KeyIdGenMethod::SPKValueHashedLeftmost160(hash_algo) => (SPK_VALUE_PREFIX, hash_algo),
+            KeyIdGenMethod::SPKFullDER(hash_algo) => (SPKI_DER_PREFIX, hash_algo),
+        };
+        let hash_name = HASH_ALGORITHM_NAMES
+            .iter()
+            .find(|(known, _)| known == hash_algo)
+            .map(|(_, hash_name)| *hash_name)
+            .unwrap_or("unknown");
+        write!(f, "{}{}", prefix, hash_name)
+    }
+}
+
 impl KeyIdGenMethod {
+    /// Every supported method
+    pub fn all() -> impl Iterator<Item = KeyIdGenMethod> {
+        HASH_ALGORITHM_NAMES
+            .iter()
+            .map(|(hash_algo, _)| KeyIdGenMethod::SPKValueHashedLeftmost160(*hash_algo))
+            .chain(
+                HASH_ALGORITHM_NAMES
+                    .iter()
+                    .map(|(hash_algo, _)| KeyIdGenMethod::SPKFullDER(*hash_algo)),
+            )
+    }
+
     pub fn generate_from(self, public_key: &PublicKey) -> Result<Vec<u8>, KeyIdGenError> {
         use picky_asn1_x509::PublicKey as InnerPublicKey;
 
@@ -61,3 +154,40 @@ impl KeyIdGenMethod {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{key::PrivateKey, pem::Pem};
+
+    #[test]
+    fn names_round_trip() {
+        for method in KeyIdGenMethod::all() {
+            assert_eq!(method.to_string().parse::<KeyIdGenMethod>().unwrap(), method);
+        }
+
+        assert_eq!(
+            "SPK-VALUE-SHA1".parse::<KeyIdGenMethod>().unwrap(),
+            KeyIdGenMethod::SPKValueHashedLeftmost160(HashAlgorithm::SHA1)
+        );
+        assert_eq!(KeyIdGenMethod::default().to_string(), "spki-der-sha256");
+
+        let err = "spki-der-md5".parse::<KeyIdGenMethod>().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unsupported key identifier generation method: spki-der-md5"
+        );
+    }
+
+    #[test]
+    fn spk_value_sha1_matches_openssl() {
+        let pem = crate::test_files::RSA_2048_PK_1.parse::<Pem>().unwrap();
+        let public_key = PrivateKey::from_pem(&pem).unwrap().to_public_key();
+
+        // `openssl x509 -noout -ext subjectKeyIdentifier` on a certificate for this key
+        let key_id = KeyIdGenMethod::SPKValueHashedLeftmost160(HashAlgorithm::SHA1)
+            .generate_from(&public_key)
+            .unwrap();
+        assert_eq!(hex::encode(key_id), "4a835a617eb62ca458622c6bcecc6f5e7134ad3c");
+    }
+}
@@ -1,14 +1,20 @@
 //! X.509 certificates implementation based on [RFC5280](https://tools.ietf.org/html/rfc5280)
 
 pub mod certificate;
+pub mod crl;
 pub mod csr;
 pub mod date;
 pub mod key_id_gen_method;
 pub mod name;
+pub mod ocsp;
+pub mod pkcs7;
 
 pub use certificate::Cert;
+pub use crl::Crl;
 pub use csr::Csr;
 pub use key_id_gen_method::KeyIdGenMethod;
+pub use ocsp::{OcspRequest, OcspResponse};
+pub use pkcs7::Pkcs7;
 pub use picky_asn1_x509::{DirectoryString, Extension, Extensions};
 
 pub mod extension {
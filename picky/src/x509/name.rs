@@ -3,10 +3,11 @@ use picky_asn1::{
     restricted_string::{CharSetError, IA5String},
     wrapper::{Asn1SequenceOf, IA5StringAsn1},
 };
+use picky_asn1_der::Asn1RawDer;
 use picky_asn1_x509::{
     DirectoryString, GeneralName as SerdeGeneralName, GeneralNames as SerdeGeneralNames, Name, NamePrettyFormatter,
 };
-use std::fmt;
+use std::{fmt, net::IpAddr};
 
 // === DirectoryName ===
 
@@ -35,6 +36,46 @@ impl DirectoryName {
         self.0.find_common_name()
     }
 
+    /// First value of the given attribute type
+    pub fn find_attr(&self, attr: NameAttr) -> Option<&DirectoryString> {
+        self.0.find_attr(attr)
+    }
+
+    /// First common name (CN)
+    pub fn common_name(&self) -> Option<&DirectoryString> {
+        self.find_attr(NameAttr::CommonName)
+    }
+
+    /// First organization name (O)
+    pub fn organization(&self) -> Option<&DirectoryString> {
+        self.find_attr(NameAttr::OrganizationName)
+    }
+
+    /// First organizational unit name (OU)
+    pub fn organizational_unit(&self) -> Option<&DirectoryString> {
+        self.find_attr(NameAttr::OrganizationalUnitName)
+    }
+
+    /// First country name (C)
+    pub fn country(&self) -> Option<&DirectoryString> {
+        self.find_attr(NameAttr::CountryName)
+    }
+
+    /// First locality name (L)
+    pub fn locality(&self) -> Option<&DirectoryString> {
+        self.find_attr(NameAttr::LocalityName)
+    }
+
+    /// First state or province name (ST)
+    pub fn state_or_province(&self) -> Option<&DirectoryString> {
+        self.find_attr(NameAttr::StateOrProvinceName)
+    }
+
+    /// First emailAddress attribute
+    pub fn email(&self) -> Option<&IA5String> {
+        self.0.find_email().map(|email| &email.0)
+    }
+
     pub fn add_attr<S: Into<DirectoryString>>(&mut self, attr: NameAttr, value: S) {
         self.0.add_attr(attr, value)
     }
@@ -44,6 +85,46 @@ impl DirectoryName {
     pub fn add_email<S: Into<IA5StringAsn1>>(&mut self, value: S) {
         self.0.add_email(value)
     }
+
+    /// Appends a relative distinguished name made of the given attribute
+    ///
+    /// Attributes are encoded (and displayed) in the order they are added, e.g.
+    /// `DirectoryName::new().with_country("CA").with_organization("Acme").with_common_name("x")`
+    /// is displayed as `C=CA,O=Acme,CN=x`.
+    pub fn with_attr<S: Into<DirectoryString>>(mut self, attr: NameAttr, value: S) -> Self {
+        self.add_attr(attr, value);
+        self
+    }
+
+    pub fn with_common_name<S: Into<DirectoryString>>(self, value: S) -> Self {
+        self.with_attr(NameAttr::CommonName, value)
+    }
+
+    pub fn with_organization<S: Into<DirectoryString>>(self, value: S) -> Self {
+        self.with_attr(NameAttr::OrganizationName, value)
+    }
+
+    pub fn with_organizational_unit<S: Into<DirectoryString>>(self, value: S) -> Self {
+        self.with_attr(NameAttr::OrganizationalUnitName, value)
+    }
+
+    pub fn with_country<S: Into<DirectoryString>>(self, value: S) -> Self {
+        self.with_attr(NameAttr::CountryName, value)
+    }
+
+    pub fn with_locality<S: Into<DirectoryString>>(self, value: S) -> Self {
+        self.with_attr(NameAttr::LocalityName, value)
+    }
+
+    pub fn with_state_or_province<S: Into<DirectoryString>>(self, value: S) -> Self {
+        self.with_attr(NameAttr::StateOrProvinceName, value)
+    }
+
+    /// NOTE: this attribute does not conform with the RFC 5280, email should be placed in SAN instead
+    pub fn with_email<S: Into<IA5StringAsn1>>(mut self, value: S) -> Self {
+        self.add_email(value);
+        self
+    }
 }
 
 impl fmt::Display for DirectoryName {
@@ -68,8 +149,15 @@ impl From<DirectoryName> for Name {
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum GeneralName {
+    OtherName {
+        type_id: ObjectIdentifier,
+        /// DER-encoded value (type depends on `type_id`)
+        value: Vec<u8>,
+    },
     RFC822Name(IA5String),
     DNSName(IA5String),
+    /// Raw DER-encoded ORAddress (not supported, but preserved)
+    X400Address(Vec<u8>),
     DirectoryName(DirectoryName),
     EDIPartyName {
         name_assigner: Option<DirectoryString>,
@@ -81,6 +169,13 @@ pub enum GeneralName {
 }
 
 impl GeneralName {
+    pub fn new_other_name<OID: Into<ObjectIdentifier>, V: Into<Vec<u8>>>(type_id: OID, value: V) -> Self {
+        Self::OtherName {
+            type_id: type_id.into(),
+            value: value.into(),
+        }
+    }
+
     pub fn new_rfc822_name<S: Into<String>>(name: S) -> Result<Self, CharSetError> {
         Ok(Self::RFC822Name(IA5String::from_string(name.into())?))
     }
@@ -117,11 +212,25 @@ impl GeneralName {
     }
 }
 
+impl From<IpAddr> for GeneralName {
+    fn from(ip_address: IpAddr) -> Self {
+        match ip_address {
+            IpAddr::V4(ip) => Self::IpAddress(ip.octets().to_vec()),
+            IpAddr::V6(ip) => Self::IpAddress(ip.octets().to_vec()),
+        }
+    }
+}
+
 impl From<SerdeGeneralName> for GeneralName {
     fn from(gn: SerdeGeneralName) -> Self {
         match gn {
+            SerdeGeneralName::OtherName(other_name) => Self::OtherName {
+                type_id: other_name.type_id.0,
+                value: (other_name.value.0).0,
+            },
             SerdeGeneralName::RFC822Name(name) => Self::RFC822Name(name.0),
             SerdeGeneralName::DNSName(name) => Self::DNSName(name.0),
+            SerdeGeneralName::X400Address(raw) => Self::X400Address(raw.0),
             SerdeGeneralName::DirectoryName(name) => Self::DirectoryName(name.into()),
             SerdeGeneralName::EDIPartyName(edi_pn) => Self::EDIPartyName {
                 name_assigner: edi_pn.name_assigner.0.map(|na| na.0),
@@ -137,8 +246,10 @@ impl From<SerdeGeneralName> for GeneralName {
 impl From<GeneralName> for SerdeGeneralName {
     fn from(gn: GeneralName) -> Self {
         match gn {
+            GeneralName::OtherName { type_id, value } => SerdeGeneralName::new_other_name(type_id, value),
             GeneralName::RFC822Name(name) => SerdeGeneralName::RFC822Name(name.into()),
             GeneralName::DNSName(name) => SerdeGeneralName::DNSName(name.into()),
+            GeneralName::X400Address(raw) => SerdeGeneralName::X400Address(Asn1RawDer(raw)),
             GeneralName::DirectoryName(name) => SerdeGeneralName::DirectoryName(name.into()),
             GeneralName::EDIPartyName {
                 name_assigner,
@@ -188,6 +299,66 @@ impl GeneralNames {
         None
     }
 
+    /// All dNSName entries
+    pub fn dns_names(&self) -> Vec<&IA5String> {
+        (self.0)
+            .0
+            .iter()
+            .filter_map(|name| match name {
+                SerdeGeneralName::DNSName(name) => Some(&name.0),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// All rfc822Name (email) entries
+    pub fn emails(&self) -> Vec<&IA5String> {
+        (self.0)
+            .0
+            .iter()
+            .filter_map(|name| match name {
+                SerdeGeneralName::RFC822Name(name) => Some(&name.0),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// All uniformResourceIdentifier entries
+    pub fn uris(&self) -> Vec<&IA5String> {
+        (self.0)
+            .0
+            .iter()
+            .filter_map(|name| match name {
+                SerdeGeneralName::URI(name) => Some(&name.0),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// All iPAddress entries (entries that are neither 4 nor 16 bytes long are skipped)
+    pub fn ips(&self) -> Vec<IpAddr> {
+        (self.0)
+            .0
+            .iter()
+            .filter_map(|name| match name {
+                SerdeGeneralName::IpAddress(addr) => ip_address_from_bytes(&addr.0),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// All directoryName entries
+    pub fn directory_names(&self) -> Vec<DirectoryName> {
+        (self.0)
+            .0
+            .iter()
+            .filter_map(|name| match name {
+                SerdeGeneralName::DirectoryName(name) => Some(name.clone().into()),
+                _ => None,
+            })
+            .collect()
+    }
+
     pub fn add_name<GN: Into<GeneralName>>(&mut self, name: GN) {
         let gn = name.into();
         (self.0).0.push(gn.into());
@@ -202,6 +373,22 @@ impl GeneralNames {
     }
 }
 
+impl<GN: Into<GeneralName>> From<Vec<GN>> for GeneralNames {
+    fn from(names: Vec<GN>) -> Self {
+        Self(Asn1SequenceOf(names.into_iter().map(|gn| gn.into().into()).collect()))
+    }
+}
+
+fn ip_address_from_bytes(bytes: &[u8]) -> Option<IpAddr> {
+    use std::convert::TryFrom;
+
+    match bytes.len() {
+        4 => Some(IpAddr::from(<[u8; 4]>::try_from(bytes).ok()?)),
+        16 => Some(IpAddr::from(<[u8; 16]>::try_from(bytes).ok()?)),
+        _ => None,
+    }
+}
+
 impl From<SerdeGeneralNames> for GeneralNames {
     fn from(gn: SerdeGeneralNames) -> Self {
         Self(gn)
@@ -217,6 +404,7 @@ impl From<GeneralNames> for SerdeGeneralNames {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::convert::TryFrom;
 
     #[test]
     fn build_and_format_directory_name() {
@@ -226,6 +414,67 @@ mod tests {
         assert_eq!(my_name.to_string(), "CN=CommonName,ST=SomeState,C=SomeCountry");
     }
 
+    #[test]
+    fn directory_name_builder_and_accessors() {
+        let name = DirectoryName::new()
+            .with_country("CA")
+            .with_state_or_province("Quebec")
+            .with_locality("Montreal")
+            .with_organization("Acme")
+            .with_organizational_unit("Engineering")
+            .with_common_name("x")
+            .with_email(IA5String::from_string("x@acme.example".to_owned()).unwrap());
+        assert_eq!(
+            name.to_string(),
+            "C=CA,ST=Quebec,L=Montreal,O=Acme,OU=Engineering,CN=x,EMAIL=x@acme.example"
+        );
+
+        let name = DirectoryName::from(
+            picky_asn1_der::from_bytes::<Name>(&picky_asn1_der::to_vec(&Name::from(name)).unwrap()).unwrap(),
+        );
+        assert_eq!(name.common_name().unwrap().to_string(), "x");
+        assert_eq!(name.organization().unwrap().to_string(), "Acme");
+        assert_eq!(name.organizational_unit().unwrap().to_string(), "Engineering");
+        assert_eq!(name.country().unwrap().to_string(), "CA");
+        assert_eq!(name.locality().unwrap().to_string(), "Montreal");
+        assert_eq!(name.state_or_province().unwrap().to_string(), "Quebec");
+        assert_eq!(name.email().unwrap().as_bytes(), b"x@acme.example");
+
+        let cn_only = DirectoryName::new_common_name("x");
+        assert_eq!(cn_only.organization(), None);
+        assert_eq!(cn_only.email(), None);
+    }
+
+    #[test]
+    fn general_names_accessors() {
+        let names = GeneralNames::from(vec![
+            GeneralName::new_dns_name("example.com").unwrap(),
+            GeneralName::from("192.168.1.1".parse::<IpAddr>().unwrap()),
+            GeneralName::from("2001:db8::1".parse::<IpAddr>().unwrap()),
+            GeneralName::new_rfc822_name("admin@example.com").unwrap(),
+            GeneralName::new_uri("https://example.com/").unwrap(),
+            GeneralName::new_directory_name(DirectoryName::new_common_name("picky")),
+            GeneralName::new_other_name(
+                ObjectIdentifier::try_from("1.3.6.1.4.1.311.20.2.3").unwrap(),
+                vec![0x0C, 0x01, b'a'],
+            ),
+        ]);
+
+        let dns_names: Vec<String> = names.dns_names().into_iter().map(|name| name.to_string()).collect();
+        assert_eq!(dns_names, vec!["example.com"]);
+        let ips: Vec<String> = names.ips().into_iter().map(|ip| ip.to_string()).collect();
+        assert_eq!(ips, vec!["192.168.1.1", "2001:db8::1"]);
+        let emails: Vec<String> = names.emails().into_iter().map(|name| name.to_string()).collect();
+        assert_eq!(emails, vec!["admin@example.com"]);
+        let uris: Vec<String> = names.uris().into_iter().map(|name| name.to_string()).collect();
+        assert_eq!(uris, vec!["https://example.com/"]);
+        assert_eq!(names.directory_names()[0].to_string(), "CN=picky");
+
+        let der = picky_asn1_der::to_vec(&SerdeGeneralNames::from(names.clone())).unwrap();
+        let decoded = GeneralNames::from(picky_asn1_der::from_bytes::<SerdeGeneralNames>(&der).unwrap());
+        assert_eq!(decoded, names);
+    }
+
     #[test]
     fn find_common_name() {
         let my_name = DirectoryName::new_common_name("CommonName");
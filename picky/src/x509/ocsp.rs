@@ -0,0 +1,773 @@
+use crate::{
+    error::ErrorKind,
+    hash::HashAlgorithm,
+    key::{PrivateKey, PublicKey},
+    signature::{SignatureAlgorithm, SignatureError},
+    x509::{
+        certificate::{Cert, CertError},
+        date::UTCDate,
+    },
+};
+use picky_asn1::{
+    bit_string::BitString,
+    date::GeneralizedTime,
+    wrapper::{
+        ApplicationTag0, ApplicationTag1, Asn1SequenceOf, BitStringAsn1Container, GeneralizedTimeAsn1, IntegerAsn1,
+    },
+};
+use picky_asn1_der::Asn1DerError;
+use picky_asn1_x509::{
+    oids, AlgorithmIdentifier, BasicOcspResponse, Certificate, Extension, Extensions, Name,
+    OcspRequest as OcspRequestAsn1, OcspResponse as OcspResponseAsn1, ResponderId, ResponseData, RevokedInfo,
+    SHAVariant, SingleRequest, SingleResponse, TBSRequest,
+};
+use std::cell::RefCell;
+use thiserror::Error;
+
+pub use picky_asn1_x509::{CertId, CrlReason, OcspResponseStatus};
+
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum OcspError {
+    /// couldn't generate OCSP response
+    #[error("couldn't generate OCSP response: {source}")]
+    OcspGeneration { source: Box<OcspError> },
+
+    /// asn1 serialization error
+    #[error("(asn1) couldn't serialize {element}: {source}")]
+    Asn1Serialization {
+        element: &'static str,
+        source: Asn1DerError,
+    },
+
+    /// asn1 deserialization error
+    #[error("(asn1) couldn't deserialize {element}: {source}")]
+    Asn1Deserialization {
+        element: &'static str,
+        source: Asn1DerError,
+    },
+
+    /// signature error
+    #[error("signature error: {source}")]
+    Signature { source: SignatureError },
+
+    /// unsupported hash algorithm
+    #[error("unsupported hash algorithm: {algorithm}")]
+    UnsupportedHashAlgorithm { algorithm: String },
+
+    /// response status is not successful
+    #[error("OCSP response status is not successful: {status:?}")]
+    Unsuccessful { status: OcspResponseStatus },
+
+    /// invalid delegated responder certificate
+    #[error("invalid delegated responder certificate: {source}")]
+    InvalidResponder { source: CertError },
+
+    /// delegated responder isn't authorized to sign OCSP responses
+    #[error("delegated responder `{id}` doesn't have the OCSPSigning extended key usage")]
+    UnauthorizedResponder { id: String },
+
+    /// missing required builder argument
+    #[error("missing required builder argument `{arg}`")]
+    MissingBuilderArgument { arg: &'static str },
+}
+
+impl OcspError {
+    /// Stable classification of this error
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            OcspError::OcspGeneration { source } => source.kind(),
+            OcspError::Asn1Serialization { .. } => ErrorKind::Encoding,
+            OcspError::Asn1Deserialization { .. } => ErrorKind::Decoding,
+            OcspError::Signature { source } => source.kind(),
+            OcspError::UnsupportedHashAlgorithm { .. } => ErrorKind::UnsupportedAlgorithm,
+            OcspError::Unsuccessful { .. } | OcspError::MissingBuilderArgument { .. } => ErrorKind::InvalidInput,
+            OcspError::InvalidResponder { source } => source.kind(),
+            OcspError::UnauthorizedResponder { .. } => ErrorKind::InvalidChain,
+        }
+    }
+}
+
+/// Certificate status as reported by an OCSP responder
+#[derive(Clone, Debug, PartialEq)]
+pub enum CertStatus {
+    Good,
+    Revoked {
+        revocation_time: UTCDate,
+        reason: Option<CrlReason>,
+    },
+    Unknown,
+}
+
+impl From<picky_asn1_x509::CertStatus> for CertStatus {
+    fn from(status: picky_asn1_x509::CertStatus) -> Self {
+        match status {
+            picky_asn1_x509::CertStatus::Good => Self::Good,
+            picky_asn1_x509::CertStatus::Revoked(info) => Self::Revoked {
+                revocation_time: info.revocation_time.0.into(),
+                reason: info.revocation_reason.map(|reason| reason.0),
+            },
+            picky_asn1_x509::CertStatus::Unknown => Self::Unknown,
+        }
+    }
+}
+
+impl From<CertStatus> for picky_asn1_x509::CertStatus {
+    fn from(status: CertStatus) -> Self {
+        match status {
+            CertStatus::Good => Self::Good,
+            CertStatus::Revoked {
+                revocation_time,
+                reason,
+            } => Self::Revoked(RevokedInfo {
+                revocation_time: generalized_time(revocation_time),
+                revocation_reason: reason.map(ApplicationTag0),
+            }),
+            CertStatus::Unknown => Self::Unknown,
+        }
+    }
+}
+
+/// Builds the `CertID` identifying the certificate with the given serial number issued by `issuer`.
+pub fn cert_id(issuer: &Cert, serial_number: IntegerAsn1, hash_algorithm: HashAlgorithm) -> Result<CertId, OcspError> {
+    let (issuer_name_hash, issuer_key_hash) = issuer_hashes(issuer, hash_algorithm)?;
+    Ok(CertId {
+        hash_algorithm: hash_algorithm_to_identifier(hash_algorithm),
+        issuer_name_hash: issuer_name_hash.into(),
+        issuer_key_hash: issuer_key_hash.into(),
+        serial_number,
+    })
+}
+
+/// Checks whether the given `CertID` designates a certificate issued by `issuer`.
+pub fn cert_id_matches_issuer(cert_id: &CertId, issuer: &Cert) -> Result<bool, OcspError> {
+    let hash_algorithm = hash_algorithm_from_identifier(&cert_id.hash_algorithm)?;
+    let (issuer_name_hash, issuer_key_hash) = issuer_hashes(issuer, hash_algorithm)?;
+    Ok(cert_id.issuer_name_hash.0 == issuer_name_hash && cert_id.issuer_key_hash.0 == issuer_key_hash)
+}
+
+/// OCSP request
+#[derive(Clone, Debug, PartialEq)]
+pub struct OcspRequest(OcspRequestAsn1);
+
+impl From<OcspRequestAsn1> for OcspRequest {
+    fn from(request: OcspRequestAsn1) -> Self {
+        Self(request)
+    }
+}
+
+impl From<OcspRequest> for OcspRequestAsn1 {
+    fn from(request: OcspRequest) -> Self {
+        request.0
+    }
+}
+
+impl OcspRequest {
+    /// Unsigned request without nonce for the given certificates
+    pub fn new(cert_ids: Vec<CertId>) -> Self {
+        let request_list = cert_ids
+            .into_iter()
+            .map(|req_cert| SingleRequest {
+                req_cert,
+                single_request_extensions: None,
+            })
+            .collect();
+
+        Self(OcspRequestAsn1 {
+            tbs_request: TBSRequest {
+                requestor_name: None,
+                request_list: Asn1SequenceOf(request_list),
+                request_extensions: None,
+            },
+            optional_signature: None,
+        })
+    }
+
+    pub fn from_der<T: ?Sized + AsRef<[u8]>>(der: &T) -> Result<Self, OcspError> {
+        Ok(Self(picky_asn1_der::from_bytes(der.as_ref()).map_err(|e| {
+            OcspError::Asn1Deserialization {
+                source: e,
+                element: "ocsp request",
+            }
+        })?))
+    }
+
+    pub fn to_der(&self) -> Result<Vec<u8>, OcspError> {
+        picky_asn1_der::to_vec(&self.0).map_err(|e| OcspError::Asn1Serialization {
+            source: e,
+            element: "ocsp request",
+        })
+    }
+
+    pub fn cert_ids(&self) -> impl Iterator<Item = &CertId> {
+        self.0.requests().iter().map(|request| &request.req_cert)
+    }
+
+    pub fn nonce(&self) -> Option<&Extension> {
+        self.0.nonce()
+    }
+}
+
+/// OCSP response
+#[derive(Clone, Debug, PartialEq)]
+pub struct OcspResponse(OcspResponseAsn1);
+
+impl From<OcspResponseAsn1> for OcspResponse {
+    fn from(response: OcspResponseAsn1) -> Self {
+        Self(response)
+    }
+}
+
+impl From<OcspResponse> for OcspResponseAsn1 {
+    fn from(response: OcspResponse) -> Self {
+        response.0
+    }
+}
+
+impl OcspResponse {
+    /// Unsuccessful response (`malformedRequest`, `internalError`, …)
+    pub fn new_error(status: OcspResponseStatus) -> Self {
+        Self(OcspResponseAsn1::new_error(status))
+    }
+
+    pub fn from_der<T: ?Sized + AsRef<[u8]>>(der: &T) -> Result<Self, OcspError> {
+        Ok(Self(picky_asn1_der::from_bytes(der.as_ref()).map_err(|e| {
+            OcspError::Asn1Deserialization {
+                source: e,
+                element: "ocsp response",
+            }
+        })?))
+    }
+
+    pub fn to_der(&self) -> Result<Vec<u8>, OcspError> {
+        picky_asn1_der::to_vec(&self.0).map_err(|e| OcspError::Asn1Serialization {
+            source: e,
+            element: "ocsp response",
+        })
+    }
+
+    pub fn response_status(&self) -> OcspResponseStatus {
+        self.0.response_status
+    }
+
+    pub fn produced_at(&self) -> Option<UTCDate> {
+        self.0
+            .basic_response()
+            .map(|basic| basic.tbs_response_data.produced_at.0.clone().into())
+    }
+
+    pub fn responses(&self) -> &[SingleResponse] {
+        match self.0.basic_response() {
+            Some(basic) => &basic.tbs_response_data.responses.0,
+            None => &[],
+        }
+    }
+
+    /// Status of the certificate designated by `cert_id`, if the response contains it
+    pub fn cert_status(&self, cert_id: &CertId) -> Option<CertStatus> {
+        self.responses()
+            .iter()
+            .find(|response| {
+                response.cert_id.hash_algorithm == cert_id.hash_algorithm
+                    && response.cert_id.issuer_name_hash == cert_id.issuer_name_hash
+                    && response.cert_id.issuer_key_hash == cert_id.issuer_key_hash
+                    && response.cert_id.serial_number.as_unsigned_bytes_be()
+                        == cert_id.serial_number.as_unsigned_bytes_be()
+            })
+            .map(|response| response.cert_status.clone().into())
+    }
+
+    pub fn nonce(&self) -> Option<&Extension> {
+        self.0.basic_response().and_then(|basic| {
+            basic
+                .tbs_response_data
+                .extensions()
+                .iter()
+                .find(|ext| ext.extn_id() == &oids::ocsp_nonce())
+        })
+    }
+
+    /// Checks that this response has been signed either by `issuer` itself
+    /// or by a responder certificate `issuer` delegated OCSP signing to.
+    pub fn verify(&self, issuer: &Cert) -> Result<(), OcspError> {
+        let basic = self.0.basic_response().ok_or(OcspError::Unsuccessful {
+            status: self.response_status(),
+        })?;
+
+        let delegated_responder = basic
+            .certs
+            .iter()
+            .flat_map(|certs| (certs.0).0.iter())
+            .map(|cert| Cert::from(cert.clone()))
+            .find(|cert| cert != issuer);
+
+        let responder_public_key = if let Some(responder) = &delegated_responder {
+            verify_delegated_responder(responder, issuer)?;
+            responder.public_key()
+        } else {
+            issuer.public_key()
+        };
+
+        let hash_type = SignatureAlgorithm::from_algorithm_identifier(&basic.signature_algorithm)
+            .map_err(|e| OcspError::Signature { source: e })?;
+        let msg = picky_asn1_der::to_vec(&basic.tbs_response_data).map_err(|e| OcspError::Asn1Serialization {
+            source: e,
+            element: "response data",
+        })?;
+        hash_type
+            .verify(responder_public_key, &msg, basic.signature.0.payload_view())
+            .map_err(|e| OcspError::Signature { source: e })
+    }
+}
+
+fn verify_delegated_responder(responder: &Cert, issuer: &Cert) -> Result<(), OcspError> {
+    issuer
+        .is_parent_of(responder)
+        .map_err(|e| OcspError::InvalidResponder { source: e })?;
+
+    let authorized = responder
+        .extended_key_usage()
+        .map(|eku| eku.ocsp_signing())
+        .unwrap_or(false);
+    if !authorized {
+        return Err(OcspError::UnauthorizedResponder {
+            id: responder.subject_name().to_string(),
+        });
+    }
+
+    let certificate = Certificate::from(responder.clone());
+    let hash_type = SignatureAlgorithm::from_algorithm_identifier(&certificate.signature_algorithm)
+        .map_err(|e| OcspError::Signature { source: e })?;
+    let msg = picky_asn1_der::to_vec(&certificate.tbs_certificate).map_err(|e| OcspError::Asn1Serialization {
+        source: e,
+        element: "tbs certificate",
+    })?;
+    hash_type
+        .verify(issuer.public_key(), &msg, certificate.signature_value.0.payload_view())
+        .map_err(|e| OcspError::Signature { source: e })
+}
+
+// === builder === //
+
+#[derive(Clone, Debug)]
+struct ResponderInfos<'a> {
+    cert: Cert,
+    key: &'a PrivateKey,
+}
+
+#[derive(Clone, Debug)]
+struct SingleResponseInfos {
+    cert_id: CertId,
+    status: CertStatus,
+    this_update: UTCDate,
+    next_update: Option<UTCDate>,
+}
+
+// Statically checks the field actually exists and returns a &'static str of the field name
+macro_rules! field_str {
+    ($field:ident) => {{
+        const _: fn() = || {
+            let OcspResponseBuilderInner { $field: _, .. };
+        };
+        stringify!($field)
+    }};
+}
+
+#[derive(Default, Clone, Debug)]
+struct OcspResponseBuilderInner<'a> {
+    responder_infos: Option<ResponderInfos<'a>>,
+    produced_at: Option<UTCDate>,
+    nonce: Option<Extension>,
+    responses: Vec<SingleResponseInfos>,
+    signature_hash_type: Option<SignatureAlgorithm>,
+}
+
+/// Builds a successful `id-pkix-ocsp-basic` response
+#[derive(Default, Clone, Debug)]
+pub struct OcspResponseBuilder<'a> {
+    inner: RefCell<OcspResponseBuilderInner<'a>>,
+}
+
+impl<'a> OcspResponseBuilder<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Required
+    ///
+    /// Either the issuer of the certificates the response is about
+    /// or a delegated responder certificate issued by it.
+    #[inline]
+    pub fn responder(&self, responder_cert: Cert, responder_key: &'a PrivateKey) -> &Self {
+        self.inner.borrow_mut().responder_infos = Some(ResponderInfos {
+            cert: responder_cert,
+            key: responder_key,
+        });
+        self
+    }
+
+    /// Required
+    #[inline]
+    pub fn produced_at(&self, produced_at: UTCDate) -> &Self {
+        self.inner.borrow_mut().produced_at = Some(produced_at);
+        self
+    }
+
+    /// Optional
+    ///
+    /// Nonce extension from the request, echoed back as-is.
+    #[inline]
+    pub fn nonce(&self, nonce: Extension) -> &Self {
+        self.inner.borrow_mut().nonce = Some(nonce);
+        self
+    }
+
+    /// Required, may be called several times
+    #[inline]
+    pub fn single_response(
+        &self,
+        cert_id: CertId,
+        status: CertStatus,
+        this_update: UTCDate,
+        next_update: Option<UTCDate>,
+    ) -> &Self {
+        self.inner.borrow_mut().responses.push(SingleResponseInfos {
+            cert_id,
+            status,
+            this_update,
+            next_update,
+        });
+        self
+    }
+
+    /// Optional
+    #[inline]
+    pub fn signature_hash_type(&self, signature_hash_type: SignatureAlgorithm) -> &Self {
+        self.inner.borrow_mut().signature_hash_type = Some(signature_hash_type);
+        self
+    }
+
+    pub fn build(&self) -> Result<OcspResponse, OcspError> {
+        let mut inner = self.inner.borrow_mut();
+
+        let responder_infos = inner.responder_infos.take().ok_or(OcspError::MissingBuilderArgument {
+            arg: field_str!(responder_infos),
+        })?;
+
+        let produced_at = inner.produced_at.take().ok_or(OcspError::MissingBuilderArgument {
+            arg: field_str!(produced_at),
+        })?;
+
+        let responses = std::mem::take(&mut inner.responses);
+        if responses.is_empty() {
+            return Err(OcspError::MissingBuilderArgument {
+                arg: field_str!(responses),
+            });
+        }
+
+        let signature_hash_type = inner
+            .signature_hash_type
+            .take()
+            .unwrap_or(SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_256));
+
+        let nonce_opt = inner.nonce.take();
+
+        drop(inner);
+
+        let responses = responses
+            .into_iter()
+            .map(|infos| SingleResponse {
+                cert_id: infos.cert_id,
+                cert_status: infos.status.into(),
+                this_update: generalized_time(infos.this_update),
+                next_update: infos.next_update.map(|date| ApplicationTag0(generalized_time(date))),
+                single_extensions: None,
+            })
+            .collect();
+
+        let tbs_response_data = ResponseData {
+            responder_id: ResponderId::ByName(responder_infos.cert.subject_name().into()),
+            produced_at: generalized_time(produced_at),
+            responses: Asn1SequenceOf(responses),
+            response_extensions: nonce_opt.map(|nonce| ApplicationTag1(Extensions(vec![nonce]))),
+        };
+
+        let tbs_der = picky_asn1_der::to_vec(&tbs_response_data)
+            .map_err(|e| OcspError::Asn1Serialization {
+                source: e,
+                element: "response data",
+            })
+            .map_err(|e| OcspError::OcspGeneration { source: Box::new(e) })?;
+        let signature = BitString::with_bytes(
+            signature_hash_type
+                .sign(&tbs_der, responder_infos.key)
+                .map_err(|e| OcspError::Signature { source: e })
+                .map_err(|e| OcspError::OcspGeneration { source: Box::new(e) })?,
+        );
+
+        Ok(OcspResponse(OcspResponseAsn1::new_basic(BasicOcspResponse {
+            tbs_response_data,
+            signature_algorithm: signature_hash_type.into(),
+            signature: signature.into(),
+            certs: Some(ApplicationTag0(Asn1SequenceOf(vec![responder_infos.cert.into()]))),
+        })))
+    }
+}
+
+fn generalized_time(date: UTCDate) -> GeneralizedTimeAsn1 {
+    let time: GeneralizedTime = date.into();
+    time.into()
+}
+
+fn issuer_hashes(issuer: &Cert, hash_algorithm: HashAlgorithm) -> Result<(Vec<u8>, Vec<u8>), OcspError> {
+    let issuer_name: Name = issuer.subject_name().into();
+    let name_der = picky_asn1_der::to_vec(&issuer_name).map_err(|e| OcspError::Asn1Serialization {
+        source: e,
+        element: "issuer name",
+    })?;
+    let key_bytes = subject_public_key_bytes(issuer.public_key())?;
+    Ok((hash_algorithm.digest(&name_der), hash_algorithm.digest(&key_bytes)))
+}
+
+/// Value of the BIT STRING subjectPublicKey (excluding the tag, length, and number of unused bits)
+fn subject_public_key_bytes(public_key: &PublicKey) -> Result<Vec<u8>, OcspError> {
+    use picky_asn1_x509::PublicKey as InnerPublicKey;
+
+    match &public_key.as_inner().subject_public_key {
+        InnerPublicKey::RSA(BitStringAsn1Container(rsa_pk)) => {
+            picky_asn1_der::to_vec(rsa_pk).map_err(|e| OcspError::Asn1Serialization {
+                source: e,
+                element: "RSA public key",
+            })
+        }
+        InnerPublicKey::EC(bitstring) => Ok(bitstring.0.payload_view().to_vec()),
+    }
+}
+
+fn hash_algorithm_to_identifier(hash_algorithm: HashAlgorithm) -> AlgorithmIdentifier {
+    AlgorithmIdentifier::new_sha(match hash_algorithm {
+        HashAlgorithm::SHA1 => SHAVariant::SHA1,
+        HashAlgorithm::SHA2_224 => SHAVariant::SHA2_224,
+        HashAlgorithm::SHA2_256 => SHAVariant::SHA2_256,
+        HashAlgorithm::SHA2_384 => SHAVariant::SHA2_384,
+        HashAlgorithm::SHA2_512 => SHAVariant::SHA2_512,
+        HashAlgorithm::SHA3_384 => SHAVariant::SHA3_384,
+        HashAlgorithm::SHA3_512 => SHAVariant::SHA3_512,
+    })
+}
+
+fn hash_algorithm_from_identifier(algorithm_identifier: &AlgorithmIdentifier) -> Result<HashAlgorithm, OcspError> {
+    let oid_string: String = algorithm_identifier.oid().into();
+    match oid_string.as_str() {
+        oids::SHA1 => Ok(HashAlgorithm::SHA1),
+        oids::SHA224 => Ok(HashAlgorithm::SHA2_224),
+        oids::SHA256 => Ok(HashAlgorithm::SHA2_256),
+        oids::SHA384 => Ok(HashAlgorithm::SHA2_384),
+        oids::SHA512 => Ok(HashAlgorithm::SHA2_512),
+        oids::SHA3_384 => Ok(HashAlgorithm::SHA3_384),
+        oids::SHA3_512 => Ok(HashAlgorithm::SHA3_512),
+        _ => Err(OcspError::UnsupportedHashAlgorithm { algorithm: oid_string }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        pem::Pem,
+        x509::{certificate::CertificateBuilder, name::DirectoryName},
+    };
+    use picky_asn1_x509::ExtendedKeyUsage;
+
+    fn parse_key(pem_str: &str) -> PrivateKey {
+        let pem = pem_str.parse::<Pem>().unwrap();
+        PrivateKey::from_pkcs8(pem.data()).unwrap()
+    }
+
+    fn test_authority() -> Cert {
+        Cert::from_pem(&crate::test_files::TEST_AUTHORITY.parse::<Pem>().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn openssl_request() {
+        let request = OcspRequest::from_der(crate::test_files::TEST_AUTHORITY_OCSP_REQUEST).unwrap();
+        let issuer = test_authority();
+
+        let cert_ids: Vec<&CertId> = request.cert_ids().collect();
+        assert_eq!(cert_ids.len(), 1);
+        assert!(cert_id_matches_issuer(cert_ids[0], &issuer).unwrap());
+        assert_eq!(
+            cert_ids[0],
+            &cert_id(&issuer, vec![0x01].into(), HashAlgorithm::SHA1).unwrap()
+        );
+        assert!(request.nonce().is_some());
+        assert_eq!(
+            request.to_der().unwrap(),
+            crate::test_files::TEST_AUTHORITY_OCSP_REQUEST
+        );
+
+        let other_key = parse_key(crate::test_files::RSA_2048_PK_1);
+        let other_issuer = CertificateBuilder::new()
+            .valididy(UTCDate::ymd(2020, 1, 1).unwrap(), UTCDate::ymd(2030, 1, 1).unwrap())
+            .self_signed(DirectoryName::new_common_name("Test Authority"), &other_key)
+            .ca(true)
+            .build()
+            .unwrap();
+        assert!(!cert_id_matches_issuer(cert_ids[0], &other_issuer).unwrap());
+    }
+
+    #[test]
+    fn openssl_response() {
+        let response = OcspResponse::from_der(crate::test_files::TEST_AUTHORITY_OCSP_RESPONSE).unwrap();
+        let request = OcspRequest::from_der(crate::test_files::TEST_AUTHORITY_OCSP_REQUEST).unwrap();
+        let issuer = test_authority();
+
+        response.verify(&issuer).expect("couldn't verify OCSP response");
+        assert_eq!(response.response_status(), OcspResponseStatus::Successful);
+        assert_eq!(response.produced_at(), UTCDate::new(2026, 10, 17, 2, 51, 10));
+        assert_eq!(response.nonce(), request.nonce());
+
+        let cert_id = request.cert_ids().next().unwrap();
+        assert_eq!(
+            response.cert_status(cert_id),
+            Some(CertStatus::Revoked {
+                revocation_time: UTCDate::new(2026, 10, 17, 2, 40, 30).unwrap(),
+                reason: Some(CrlReason::KeyCompromise),
+            })
+        );
+        assert_eq!(
+            response.to_der().unwrap(),
+            crate::test_files::TEST_AUTHORITY_OCSP_RESPONSE
+        );
+    }
+
+    #[test]
+    fn build_and_verify() {
+        let issuer_key = parse_key(crate::test_files::RSA_2048_PK_1);
+        let issuer = CertificateBuilder::new()
+            .valididy(UTCDate::ymd(2020, 1, 1).unwrap(), UTCDate::ymd(2030, 1, 1).unwrap())
+            .self_signed(DirectoryName::new_common_name("OCSP Issuer"), &issuer_key)
+            .ca(true)
+            .build()
+            .expect("couldn't build issuer");
+
+        let good_id = cert_id(&issuer, vec![0x01].into(), HashAlgorithm::SHA1).unwrap();
+        let revoked_id = cert_id(&issuer, vec![0x02].into(), HashAlgorithm::SHA2_256).unwrap();
+        let request = OcspRequest::from_der(crate::test_files::TEST_AUTHORITY_OCSP_REQUEST).unwrap();
+
+        let response = OcspResponseBuilder::new()
+            .responder(issuer.clone(), &issuer_key)
+            .produced_at(UTCDate::ymd(2020, 6, 1).unwrap())
+            .nonce(request.nonce().unwrap().clone())
+            .single_response(
+                good_id.clone(),
+                CertStatus::Good,
+                UTCDate::ymd(2020, 6, 1).unwrap(),
+                None,
+            )
+            .single_response(
+                revoked_id.clone(),
+                CertStatus::Revoked {
+                    revocation_time: UTCDate::ymd(2020, 5, 30).unwrap(),
+                    reason: Some(CrlReason::Superseded),
+                },
+                UTCDate::ymd(2020, 6, 1).unwrap(),
+                UTCDate::ymd(2020, 6, 8),
+            )
+            .build()
+            .expect("couldn't build OCSP response");
+
+        let response = OcspResponse::from_der(&response.to_der().unwrap()).expect("couldn't parse response back");
+        response.verify(&issuer).expect("couldn't verify OCSP response");
+        assert_eq!(response.nonce(), request.nonce());
+        assert_eq!(response.cert_status(&good_id), Some(CertStatus::Good));
+        assert_eq!(
+            response.cert_status(&revoked_id),
+            Some(CertStatus::Revoked {
+                revocation_time: UTCDate::ymd(2020, 5, 30).unwrap(),
+                reason: Some(CrlReason::Superseded),
+            })
+        );
+        assert_eq!(
+            response.responses()[1]
+                .next_update
+                .as_ref()
+                .map(|d| UTCDate::from((d.0).0.clone())),
+            UTCDate::ymd(2020, 6, 8)
+        );
+
+        let other_key = parse_key(crate::test_files::RSA_2048_PK_2);
+        let forged = OcspResponseBuilder::new()
+            .responder(issuer.clone(), &other_key)
+            .produced_at(UTCDate::ymd(2020, 6, 1).unwrap())
+            .single_response(good_id, CertStatus::Good, UTCDate::ymd(2020, 6, 1).unwrap(), None)
+            .build()
+            .unwrap();
+        assert!(forged.verify(&issuer).is_err());
+    }
+
+    #[test]
+    fn delegated_responder() {
+        let issuer_key = parse_key(crate::test_files::RSA_2048_PK_1);
+        let issuer = CertificateBuilder::new()
+            .valididy(UTCDate::ymd(2020, 1, 1).unwrap(), UTCDate::ymd(2030, 1, 1).unwrap())
+            .self_signed(DirectoryName::new_common_name("OCSP Issuer"), &issuer_key)
+            .ca(true)
+            .build()
+            .unwrap();
+
+        let responder_key = parse_key(crate::test_files::RSA_2048_PK_2);
+        let build_responder = |eku: ExtendedKeyUsage| {
+            CertificateBuilder::new()
+                .valididy(UTCDate::ymd(2020, 1, 1).unwrap(), UTCDate::ymd(2030, 1, 1).unwrap())
+                .subject(
+                    DirectoryName::new_common_name("OCSP Responder"),
+                    responder_key.to_public_key(),
+                )
+                .issuer_cert(&issuer, &issuer_key)
+                .extended_key_usage(eku)
+                .build()
+                .unwrap()
+        };
+        let cert_id = cert_id(&issuer, vec![0x2A].into(), HashAlgorithm::SHA1).unwrap();
+        let sign_with = |responder: Cert| {
+            OcspResponseBuilder::new()
+                .responder(responder, &responder_key)
+                .produced_at(UTCDate::ymd(2020, 6, 1).unwrap())
+                .single_response(
+                    cert_id.clone(),
+                    CertStatus::Unknown,
+                    UTCDate::ymd(2020, 6, 1).unwrap(),
+                    None,
+                )
+                .build()
+                .unwrap()
+        };
+
+        let response = sign_with(build_responder(ExtendedKeyUsage::new(vec![oids::kp_ocsp_signing()])));
+        response.verify(&issuer).expect("couldn't verify delegated response");
+        assert_eq!(response.cert_status(&cert_id), Some(CertStatus::Unknown));
+
+        let response = sign_with(build_responder(ExtendedKeyUsage::new(vec![oids::kp_server_auth()])));
+        assert!(matches!(
+            response.verify(&issuer),
+            Err(OcspError::UnauthorizedResponder { .. })
+        ));
+    }
+
+    #[test]
+    fn error_response() {
+        let response = OcspResponse::new_error(OcspResponseStatus::MalformedRequest);
+        let der = response.to_der().unwrap();
+        assert_eq!(der, [0x30, 0x03, 0x0A, 0x01, 0x01]);
+        let response = OcspResponse::from_der(&der).unwrap();
+        assert_eq!(response.response_status(), OcspResponseStatus::MalformedRequest);
+        assert!(response.responses().is_empty());
+        assert!(matches!(
+            response.verify(&test_authority()),
+            Err(OcspError::Unsuccessful {
+                status: OcspResponseStatus::MalformedRequest
+            })
+        ));
+    }
+}
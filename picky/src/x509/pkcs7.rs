@@ -0,0 +1,132 @@
+use crate::{error::ErrorKind, pem::Pem, x509::certificate::Cert};
+use picky_asn1_der::Asn1DerError;
+use picky_asn1_x509::{ContentInfo, SignedData};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum Pkcs7Error {
+    /// asn1 serialization error
+    #[error("(asn1) couldn't serialize {element}: {source}")]
+    Asn1Serialization {
+        element: &'static str,
+        source: Asn1DerError,
+    },
+
+    /// asn1 deserialization error
+    #[error("(asn1) couldn't deserialize {element}: {source}")]
+    Asn1Deserialization {
+        element: &'static str,
+        source: Asn1DerError,
+    },
+
+    /// invalid PEM label error
+    #[error("invalid PEM label: {label}")]
+    InvalidPemLabel { label: String },
+}
+
+impl Pkcs7Error {
+    /// Stable classification of this error
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Pkcs7Error::Asn1Serialization { .. } => ErrorKind::Encoding,
+            Pkcs7Error::Asn1Deserialization { .. } => ErrorKind::Decoding,
+            Pkcs7Error::InvalidPemLabel { .. } => ErrorKind::InvalidInput,
+        }
+    }
+}
+
+const PKCS7_PEM_LABEL: &str = "PKCS7";
+
+/// Certificates conveyed in a degenerate "certs-only" PKCS#7 signed data, as found in `.p7b` files
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pkcs7(ContentInfo);
+
+impl From<ContentInfo> for Pkcs7 {
+    fn from(content_info: ContentInfo) -> Self {
+        Self(content_info)
+    }
+}
+
+impl From<Pkcs7> for ContentInfo {
+    fn from(pkcs7: Pkcs7) -> Self {
+        pkcs7.0
+    }
+}
+
+impl Pkcs7 {
+    pub fn from_certs(certs: &[Cert]) -> Self {
+        let certificates = certs.iter().cloned().map(Into::into).collect();
+        Self(ContentInfo::new_signed_data(SignedData::certs_only(certificates)))
+    }
+
+    pub fn from_der<T: ?Sized + AsRef<[u8]>>(der: &T) -> Result<Self, Pkcs7Error> {
+        Ok(Self(picky_asn1_der::from_bytes(der.as_ref()).map_err(|e| {
+            Pkcs7Error::Asn1Deserialization {
+                source: e,
+                element: "content info",
+            }
+        })?))
+    }
+
+    pub fn from_pem(pem: &Pem) -> Result<Self, Pkcs7Error> {
+        match pem.label() {
+            PKCS7_PEM_LABEL => Self::from_der(pem.data()),
+            _ => Err(Pkcs7Error::InvalidPemLabel {
+                label: pem.label().to_owned(),
+            }),
+        }
+    }
+
+    pub fn to_der(&self) -> Result<Vec<u8>, Pkcs7Error> {
+        picky_asn1_der::to_vec(&self.0).map_err(|e| Pkcs7Error::Asn1Serialization {
+            source: e,
+            element: "content info",
+        })
+    }
+
+    pub fn to_pem(&self) -> Result<Pem<'static>, Pkcs7Error> {
+        Ok(Pem::new(PKCS7_PEM_LABEL, self.to_der()?))
+    }
+
+    pub fn certs(&self) -> Vec<Cert> {
+        self.0
+            .signed_data()
+            .certificates
+            .iter()
+            .cloned()
+            .map(Cert::from)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pem::parse_pem;
+
+    #[test]
+    fn certs_only_roundtrip() {
+        let root = Cert::from_pem(&parse_pem(crate::test_files::ROOT_CA).unwrap()).unwrap();
+        let intermediate = Cert::from_pem(&parse_pem(crate::test_files::INTERMEDIATE_CA).unwrap()).unwrap();
+
+        let pkcs7 = Pkcs7::from_certs(&[intermediate.clone(), root.clone()]);
+        let pem = pkcs7.to_pem().unwrap();
+        assert_eq!(pem.label(), "PKCS7");
+
+        let decoded = Pkcs7::from_pem(&pem).unwrap();
+        assert_eq!(decoded, pkcs7);
+        assert_eq!(decoded.certs(), vec![intermediate, root]);
+    }
+
+    #[test]
+    fn openssl_p7b() {
+        // generated using `openssl crl2pkcs7 -nocrl -certfile test_authority.crt -outform DER`
+        let der = crate::test_files::TEST_AUTHORITY_P7B;
+        let pkcs7 = Pkcs7::from_der(der).unwrap();
+        let certs = pkcs7.certs();
+        assert_eq!(certs.len(), 1);
+        assert_eq!(certs[0].subject_name().to_string(), "CN=Test Authority");
+        assert_eq!(pkcs7.to_der().unwrap(), der.to_vec());
+    }
+}
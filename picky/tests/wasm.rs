@@ -0,0 +1,96 @@
+#![cfg(target_arch = "wasm32")]
+#![cfg(feature = "x509")]
+#![cfg(feature = "jose")]
+//! Verification paths exercised on `wasm32-unknown-unknown`.
+//!
+//! Run with `wasm-bindgen-test-runner` configured as cargo runner:
+//! `cargo test -p picky --target wasm32-unknown-unknown --test wasm`
+
+use picky::{
+    hash::HashAlgorithm,
+    jose::jwt::{JwtDate, JwtSig, JwtValidator},
+    key::PrivateKey,
+    pem::Pem,
+    signature::SignatureAlgorithm,
+    x509::{
+        certificate::{Cert, CertificateBuilder},
+        date::UTCDate,
+        name::DirectoryName,
+    },
+};
+use rand::{rngs::StdRng, SeedableRng};
+use serde::Deserialize;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+const RSA_2048_PK_1: &str = include_str!("../../test_assets/private_keys/rsa-2048-pk_1.key");
+const RSA_2048_PK_2: &str = include_str!("../../test_assets/private_keys/rsa-2048-pk_2.key");
+const JOSE_JWT_SIG_WITH_EXP: &str = include_str!("../../test_assets/jose/jwt_sig_with_exp.txt");
+
+fn parse_key(pem_str: &str) -> PrivateKey {
+    let pem = pem_str.parse::<Pem>().unwrap();
+    PrivateKey::from_pkcs8(pem.data()).unwrap()
+}
+
+#[wasm_bindgen_test]
+fn chain_verification() {
+    let root_key = parse_key(RSA_2048_PK_1);
+    let leaf_key = parse_key(RSA_2048_PK_2);
+    let mut rng = StdRng::seed_from_u64(0);
+
+    let root = CertificateBuilder::new()
+        .valididy(UTCDate::ymd(2065, 6, 15).unwrap(), UTCDate::ymd(2070, 6, 15).unwrap())
+        .self_signed(DirectoryName::new_common_name("Wasm Root CA"), &root_key)
+        .ca(true)
+        .build_with_rng(&mut rng)
+        .expect("couldn't build root ca");
+
+    let leaf = CertificateBuilder::new()
+        .valididy(UTCDate::ymd(2066, 1, 1).unwrap(), UTCDate::ymd(2069, 1, 1).unwrap())
+        .subject(DirectoryName::new_common_name("wasm.example"), leaf_key.to_public_key())
+        .issuer_cert(&root, &root_key)
+        .signature_hash_type(SignatureAlgorithm::RsaPkcs1v15(HashAlgorithm::SHA2_384))
+        .build_with_rng(&mut rng)
+        .expect("couldn't build leaf");
+
+    // verification only works on parsed certificates, as received from a server
+    let root = Cert::from_der(&root.to_der().unwrap()).unwrap();
+    let leaf = Cert::from_der(&leaf.to_der().unwrap()).unwrap();
+    let chain = [root];
+
+    leaf.verifier()
+        .chain(chain.iter())
+        .exact_date(&UTCDate::ymd(2067, 1, 1).unwrap())
+        .verify()
+        .expect("couldn't verify chain");
+
+    leaf.verifier()
+        .chain(chain.iter())
+        .exact_date(&UTCDate::ymd(2070, 1, 1).unwrap())
+        .verify()
+        .expect_err("expired leaf should be rejected");
+}
+
+#[derive(Deserialize, Debug)]
+struct MyExpirableClaims {
+    msg: String,
+}
+
+#[wasm_bindgen_test]
+fn jwt_verification() {
+    let public_key = parse_key(RSA_2048_PK_1).to_public_key();
+
+    let jwt = JwtSig::<MyExpirableClaims>::decode(
+        JOSE_JWT_SIG_WITH_EXP,
+        &public_key,
+        &JwtValidator::strict(&JwtDate::new(1545263999)),
+    )
+    .expect("couldn't decode jwt");
+    assert_eq!(jwt.claims.msg, "THIS IS TIME SENSITIVE DATA");
+
+    JwtSig::<MyExpirableClaims>::decode(
+        JOSE_JWT_SIG_WITH_EXP,
+        &public_key,
+        &JwtValidator::strict(&JwtDate::new(1545264001)),
+    )
+    .expect_err("expired token should be rejected");
+}